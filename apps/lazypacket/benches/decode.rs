@@ -0,0 +1,53 @@
+// Decoding throughput over a synthetic session: `cargo bench --bench decode`
+// Definitions are the fuzz target's (fuzz/protocol.yml), which cover containers, arrays, switches and NBT
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use lazypacket::protocol::ProtocolParser;
+use lazypacket::PacketDirection;
+use serde_json::json;
+use std::path::Path;
+
+const SESSION_PACKETS: usize = 10_000;
+
+fn session(parser: &ProtocolParser) -> Vec<Vec<u8>> {
+    let entries: Vec<_> = (0..20)
+        .map(|i| json!({ "key": format!("entry_{}", i), "flags": { "a": i % 2 == 0, "b": true, "c": false } }))
+        .collect();
+    let mut node = json!({ "value": 0, "child": null });
+    for value in 1..10 {
+        node = json!({ "value": value, "child": node });
+    }
+    let packets = [
+        parser.encode_packet("packet_strings", &json!({
+            "name": "Steve", "little": "{\"skin\": true}", "short": "short", "latin": "latin", "data": "0x0102030405",
+        })),
+        parser.encode_packet("packet_arrays", &json!({ "entries": entries, "numbers": ["1", "-2", "3"], "rest": "0x" })),
+        parser.encode_packet("packet_nested", &json!({ "root": node })),
+        parser.encode_packet("packet_switch", &json!({
+            "kind": "position", "payload": { "x": 1.0, "y": 64.0, "z": -3.5 }, "extra": null, "tags": [],
+        })),
+    ];
+    let packets: Vec<Vec<u8>> = packets.into_iter().map(|packet| packet.expect("encode benchmark packet")).collect();
+    (0..SESSION_PACKETS).map(|i| packets[i % packets.len()].clone()).collect()
+}
+
+fn decode_session(c: &mut Criterion) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/protocol.yml");
+    let parser = ProtocolParser::load_from_file(&path, "bench").expect("load benchmark definitions");
+    let packets = session(&parser);
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(packets.len() as u64));
+    group.bench_function("session", |b| {
+        b.iter(|| {
+            for packet in &packets {
+                let decoded = parser.decode_packet(packet, PacketDirection::Clientbound);
+                assert!(decoded.error.is_none(), "{:?}", decoded.error);
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode_session);
+criterion_main!(benches);
@@ -0,0 +1,4 @@
+// Migrations are embedded at compile time (refinery::embed_migrations! in db.rs); rebuild when they change
+fn main() {
+    println!("cargo:rerun-if-changed=../relay/.ddl/migrations");
+}
@@ -0,0 +1,18 @@
+#![no_main]
+
+use lazypacket::protocol::ProtocolParser;
+use lazypacket::PacketDirection;
+use libfuzzer_sys::fuzz_target;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static PARSER: OnceLock<ProtocolParser> = OnceLock::new();
+
+// Any bytes must decode to fields or a decode error, within the default limits, without panicking
+fuzz_target!(|data: &[u8]| {
+    let parser = PARSER.get_or_init(|| {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("protocol.yml");
+        ProtocolParser::load_from_file(&path, "fuzz").expect("fuzz protocol definitions")
+    });
+    let _ = parser.decode_packet(data, PacketDirection::Clientbound);
+});
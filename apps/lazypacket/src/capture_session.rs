@@ -0,0 +1,410 @@
+// Capture session: stamps each packet once (timestamp, packet number) and fans it out to all sinks
+// Sinks are chosen per run with LOG_SINKS (comma-separated: file, db, ws, null; default "file")
+// The `capture` subcommand runs one for a packet stream piped to it
+// CAPTURE_TRUNCATE_PAYLOAD_BYTES keeps only the first N payload bytes of each packet (full size is still recorded)
+
+use crate::log_meta::SessionDetails;
+use crate::log_reader::{LogReader, ReadStats};
+use crate::log_sink::{DbBatchConfig, DbSink, LogSink, NullSink, WebSocketSink};
+use crate::packet_logger::{PacketDirection, PacketEntry, PacketLogger, PacketLoggerConfig};
+use crate::retention::{self, RetentionPolicy};
+use crate::storage::{self, Storage};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+const DEFAULT_LOG_DIR: &str = "logs";
+const FLUSH_CHECK_INTERVAL: Duration = Duration::from_millis(100); // How often the capture loop flushes sinks that are due
+const CAPTURE_QUEUE_LEN: usize = 1024; // Packets read ahead of the capture loop by `capture`
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkKind {
+    File,
+    Db,
+    WebSocket,
+    Null,
+}
+
+impl SinkKind {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "file" => Ok(SinkKind::File),
+            "db" | "database" => Ok(SinkKind::Db),
+            "ws" | "websocket" => Ok(SinkKind::WebSocket),
+            "null" | "none" => Ok(SinkKind::Null),
+            other => Err(anyhow!("Unknown log sink: {} (expected file, db, ws or null)", other)),
+        }
+    }
+
+    /// Parse a comma-separated sink list, e.g. "file,db"
+    pub fn parse_list(s: &str) -> Result<Vec<Self>> {
+        s.split(',')
+            .filter(|part| !part.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+}
+
+pub struct CaptureSession {
+    session_id: Uuid,
+    packet_number: i64,
+    sinks: Vec<Box<dyn LogSink>>,
+    payload_limit: Option<usize>, // Payloads longer than this are truncated before reaching any sink
+    retention: Option<JoinHandle<()>>, // Pruning old sessions (RETENTION_*) while capturing to the database
+}
+
+impl CaptureSession {
+    pub fn new(session_id: Uuid) -> Self {
+        Self {
+            session_id,
+            packet_number: 0,
+            sinks: Vec::new(),
+            payload_limit: None,
+            retention: None,
+        }
+    }
+
+    pub fn with_sink(mut self, sink: Box<dyn LogSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    pub fn with_payload_limit(mut self, limit: usize) -> Self {
+        self.payload_limit = Some(limit);
+        self
+    }
+
+    /// Build a session with the sinks listed in LOG_SINKS
+    /// File sink: PACKET_LOG_DIR (default "logs") and PacketLoggerConfig::from_env
+    /// Db sink: DB_* connection variables and DbBatchConfig::from_env; creates a row in the sessions table, and
+    /// starts the retention task with RETENTION_* limits, as the relay does (never deleting the session captured)
+    /// WebSocket sink: LOG_WS_URL (ws://host:port/path)
+    pub async fn from_env(session_id: Uuid) -> Result<Self> {
+        let kinds = SinkKind::parse_list(&std::env::var("LOG_SINKS").unwrap_or_else(|_| "file".to_string()))
+            .context("Invalid LOG_SINKS")?;
+        let config = PacketLoggerConfig::from_env()?;
+
+        let mut session = Self::new(session_id);
+        if let Ok(limit) = std::env::var("CAPTURE_TRUNCATE_PAYLOAD_BYTES") {
+            session.payload_limit = Some(limit.parse().context("Invalid CAPTURE_TRUNCATE_PAYLOAD_BYTES")?);
+        }
+        for kind in kinds {
+            let sink: Box<dyn LogSink> = match kind {
+                SinkKind::File => {
+                    let log_dir = std::env::var("PACKET_LOG_DIR").unwrap_or_else(|_| DEFAULT_LOG_DIR.to_string());
+                    Box::new(
+                        PacketLogger::with_config(session_id, &log_dir, config.clone())
+                            .with_context(|| format!("Failed to create log file in {}", log_dir))?,
+                    )
+                }
+                SinkKind::Db => {
+                    let db: Arc<dyn Storage> = storage::connect().await?.into();
+                    let db_session_id = db.create_session().await?;
+                    let policy = RetentionPolicy::from_env()?;
+                    if policy.is_enabled() && session.retention.is_none() {
+                        session.retention = Some(retention::spawn(db.clone(), policy, HashSet::from([db_session_id])));
+                    }
+                    Box::new(DbSink::spawn(
+                        db,
+                        db_session_id,
+                        config.protocol_version.clone(),
+                        DbBatchConfig::from_env()?,
+                    ))
+                }
+                SinkKind::WebSocket => {
+                    let url = std::env::var("LOG_WS_URL").context("LOG_WS_URL is required for the ws sink")?;
+                    Box::new(WebSocketSink::connect(&url).await?)
+                }
+                SinkKind::Null => Box::new(NullSink),
+            };
+            session.sinks.push(sink);
+        }
+
+        Ok(session)
+    }
+
+    pub fn session_id(&self) -> Uuid {
+        self.session_id
+    }
+
+    pub fn sink_names(&self) -> Vec<&str> {
+        self.sinks.iter().map(|sink| sink.name()).collect()
+    }
+
+    /// Packets recorded so far
+    pub fn packet_count(&self) -> i64 {
+        self.packet_number
+    }
+
+    /// Record one packet to every sink
+    /// A failing sink is reported but doesn't stop the others; returns the number of sinks that failed
+    pub fn record(&mut self, direction: PacketDirection, data: Vec<u8>) -> usize {
+        self.packet_number += 1;
+        let mut entry = PacketEntry {
+            timestamp: Utc::now().timestamp_millis(),
+            direction,
+            data,
+            protocol_version: None,
+            packet_json: None,
+            packet_number: Some(self.packet_number),
+            original_size: None,
+        };
+        if let Some(limit) = self.payload_limit {
+            entry.truncate_payload(limit);
+        }
+
+        let mut failed = 0;
+        for sink in &mut self.sinks {
+            if let Err(e) = sink.write_packet(&entry) {
+                eprintln!("Session {}: {} sink error: {:#}", self.session_id, sink.name(), e);
+                failed += 1;
+            }
+        }
+        failed
+    }
+
+    /// Pass addresses and tags to every sink (the file sink records them in its `.meta.json` sidecar)
+    pub fn set_details(&mut self, details: &SessionDetails) {
+        for sink in &mut self.sinks {
+            sink.set_session_details(details);
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        for sink in &mut self.sinks {
+            sink.flush()
+                .with_context(|| format!("Failed to flush {} sink", sink.name()))?;
+        }
+        Ok(())
+    }
+
+    /// Flush the sinks holding packets past their flush interval
+    /// Failures are reported like write errors; returns the number of sinks that failed
+    pub fn flush_if_due(&mut self) -> usize {
+        let mut failed = 0;
+        for sink in &mut self.sinks {
+            if let Err(e) = sink.flush_if_due() {
+                eprintln!("Session {}: {} sink error: {:#}", self.session_id, sink.name(), e);
+                failed += 1;
+            }
+        }
+        failed
+    }
+
+    /// End the capture: flush every sink, then close them and wait for the ones writing in the background
+    /// (the database sink finishes its queue and ends the session)
+    pub async fn close(&mut self) -> Result<()> {
+        let flushed = self.flush();
+        for sink in &mut self.sinks {
+            if let Some(writer) = sink.close() {
+                writer.await.with_context(|| format!("{} sink writer failed", sink.name()))?;
+            }
+        }
+        flushed
+    }
+
+    /// Capture loop: record the packets received until the channel closes, then close every sink. Sinks are
+    /// flushed as they become due in between, so the last packets before a quiet stretch reach the log without
+    /// waiting for the next packet or the end of the session
+    pub async fn run(&mut self, mut packets: mpsc::Receiver<(PacketDirection, Vec<u8>)>) -> Result<()> {
+        let mut flush_check = tokio::time::interval(FLUSH_CHECK_INTERVAL);
+        flush_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                packet = packets.recv() => match packet {
+                    Some((direction, data)) => {
+                        self.record(direction, data);
+                    }
+                    None => break,
+                },
+                _ = flush_check.tick() => {
+                    self.flush_if_due();
+                }
+            }
+        }
+        self.close().await
+    }
+}
+
+/// `capture [FILE|-]` - record a packet stream as a new session, through the sinks in LOG_SINKS
+/// The stream is a packet log in either format (stdin by default), e.g. JSON Lines from another process; packets
+/// are stamped as they arrive. Runs until the stream ends or Ctrl-C
+pub async fn capture(args: &[String]) -> Result<()> {
+    let path = match args {
+        [] => "-".to_string(),
+        [path] => path.clone(),
+        _ => return Err(anyhow!("Usage: lazypacket capture [FILE|-]")),
+    };
+    let mut session = CaptureSession::from_env(Uuid::new_v4()).await?;
+    eprintln!("Capturing session {} to {}", session.session_id(), session.sink_names().join(", "));
+
+    // Reads block on the pipe, so the stream is read on a thread of its own (not a blocking task, which the
+    // runtime would wait for on exit after Ctrl-C)
+    let (sender, receiver) = mpsc::channel(CAPTURE_QUEUE_LEN);
+    let reader = std::thread::spawn(move || -> Result<ReadStats> {
+        let mut reader = LogReader::open(&path)?;
+        for entry in reader.by_ref() {
+            let entry = entry?;
+            if sender.blocking_send((entry.direction, entry.data)).is_err() {
+                break;
+            }
+        }
+        Ok(reader.stats().clone())
+    });
+
+    let interrupted = tokio::select! {
+        result = session.run(receiver) => {
+            result?;
+            false
+        }
+        _ = tokio::signal::ctrl_c() => true,
+    };
+    if interrupted {
+        session.close().await?;
+    } else {
+        let stats = reader.join().map_err(|_| anyhow!("Packet stream reader panicked"))??;
+        if stats.skipped > 0 {
+            eprintln!("Skipped {} corrupted entries ({} bytes)", stats.skipped, stats.skipped_bytes);
+        }
+    }
+    eprintln!("Captured {} packets in session {}", session.packet_count(), session.session_id());
+    Ok(())
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        if let Some(retention) = self.retention.take() {
+            retention.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite_db::SqliteDatabase;
+    use std::sync::Mutex;
+
+    // Collects packet numbers; optionally fails every write
+    struct RecordingSink {
+        seen: Arc<Mutex<Vec<i64>>>,
+        fail: bool,
+    }
+
+    // Collects (stored payload length, full size)
+    struct SizeSink(Arc<Mutex<Vec<(usize, usize)>>>);
+
+    impl LogSink for SizeSink {
+        fn name(&self) -> &str {
+            "size"
+        }
+
+        fn write_packet(&mut self, entry: &PacketEntry) -> Result<()> {
+            self.0.lock().unwrap().push((entry.data.len(), entry.size()));
+            Ok(())
+        }
+    }
+
+    impl LogSink for RecordingSink {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn write_packet(&mut self, entry: &PacketEntry) -> Result<()> {
+            if self.fail {
+                return Err(anyhow!("sink unavailable"));
+            }
+            self.seen.lock().unwrap().push(entry.packet_number.unwrap());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fan_out_to_all_sinks() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut session = CaptureSession::new(Uuid::new_v4())
+            .with_sink(Box::new(RecordingSink { seen: seen.clone(), fail: true }))
+            .with_sink(Box::new(NullSink))
+            .with_sink(Box::new(RecordingSink { seen: seen.clone(), fail: false }));
+
+        assert_eq!(session.record(PacketDirection::Clientbound, vec![1]), 1);
+        assert_eq!(session.record(PacketDirection::Serverbound, vec![2]), 1);
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+        assert_eq!(session.sink_names(), vec!["recording", "null", "recording"]);
+    }
+
+    #[test]
+    fn test_payload_limit() {
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let mut session = CaptureSession::new(Uuid::new_v4())
+            .with_payload_limit(4)
+            .with_sink(Box::new(SizeSink(sizes.clone())));
+
+        session.record(PacketDirection::Clientbound, vec![0; 100]);
+        session.record(PacketDirection::Clientbound, vec![0; 3]);
+        assert_eq!(*sizes.lock().unwrap(), vec![(4, 100), (3, 3)]);
+    }
+
+    #[test]
+    fn test_parse_sink_list() {
+        assert_eq!(
+            SinkKind::parse_list("file, db,ws").unwrap(),
+            vec![SinkKind::File, SinkKind::Db, SinkKind::WebSocket]
+        );
+        assert!(SinkKind::parse_list("file,kafka").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_capture_loop_flushes_quiet_session() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+        let config = PacketLoggerConfig {
+            flush_bytes: 1024 * 1024,
+            flush_interval: Duration::from_millis(20),
+            ..PacketLoggerConfig::default()
+        };
+        let logger = PacketLogger::with_config(Uuid::new_v4(), &log_dir, config).unwrap();
+        let log_path = logger.log_path().to_path_buf();
+        let mut session = CaptureSession::new(Uuid::new_v4()).with_sink(Box::new(logger));
+
+        let (sender, receiver) = mpsc::channel(16);
+        let capture = tokio::spawn(async move { session.run(receiver).await });
+        sender.send((PacketDirection::Clientbound, vec![1, 2, 3])).await.unwrap();
+
+        // No packet follows and the channel stays open, yet the packet reaches the log
+        tokio::time::sleep(FLUSH_CHECK_INTERVAL * 3).await;
+        let entries: Vec<_> = LogReader::open(log_path.to_str().unwrap()).unwrap().collect::<Result<_>>().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].data, vec![1, 2, 3]);
+
+        drop(sender);
+        capture.await.unwrap().unwrap();
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_close_waits_for_database_writes() {
+        let path = std::env::temp_dir().join(format!("lazypacket-test-{}.sqlite", Uuid::new_v4()));
+        let db: Arc<dyn Storage> = Arc::new(SqliteDatabase::open(&path).unwrap());
+        let db_session_id = db.create_session().await.unwrap();
+        let sink = DbSink::spawn(db.clone(), db_session_id, "1.21.111".to_string(), DbBatchConfig::default());
+        let mut session = CaptureSession::new(Uuid::new_v4()).with_sink(Box::new(sink));
+
+        for _ in 0..3 {
+            session.record(PacketDirection::Serverbound, vec![0x01, 0x02]);
+        }
+        session.close().await.unwrap();
+        // Written and ended by the time close returns, not whenever the writer task gets to it
+        assert_eq!(db.get_packets(db_session_id, None).await.unwrap().len(), 3);
+        assert!(db.get_session(db_session_id).await.unwrap().unwrap().ended_at.is_some());
+        assert_eq!(session.record(PacketDirection::Serverbound, vec![0x01]), 1);
+
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,10 +1,66 @@
-use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
-use serde_json::Value;
-use tokio_postgres::{Client, NoTls, types::Json};
+use crate::db_tls::{SslMode, TlsSettings};
+use crate::packet_logger::{encode_hex, PacketDirection, PacketEntry};
+use crate::protocol::ProtocolParser;
+use crate::storage::{
+    packet_at_time_query, packet_count_query, packet_filter_clause, packet_gaps_query, packet_page_query, packet_search_count_query, packet_rate_from_rows, packet_rate_query, packet_search_query, packet_stats_from_rows, packet_stats_query, session_search_query, table_rows_query, ConnectionStatus, PacketStream, PageCursor, SqlDialect,
+    SqlParam, Storage, FILTER_HISTORY_LIMIT, HEALTH_TABLES, PACKET_COLUMNS, SESSION_RANGE_QUERY, STREAM_BATCH_SIZE,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_postgres::{binary_copy::BinaryCopyInWriter, NoTls, Row, types::{Json, ToSql, Type}};
 
+// Versioned schema migrations (V<n>__<name>.sql), compiled into the binary
+mod embedded {
+    refinery::embed_migrations!("../relay/.ddl/migrations");
+}
+
+const DEFAULT_POOL_SIZE: usize = 8;
+const DEFAULT_CONNECT_ATTEMPTS: u32 = 5;
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Connections come from a pool; broken ones are discarded when returned and replaced on demand,
+// so a restarted or briefly unreachable server doesn't take the app down with it
 pub struct Database {
-    client: Client,
+    pool: Pool,
+    target: String, // host:port (user, db), for error messages
+    connect_attempts: u32, // Attempts to get a connection before giving up (DB_CONNECT_ATTEMPTS)
+    retry_backoff: Duration, // Delay before the first retry, doubled per attempt (DB_RETRY_BACKOFF_MS)
+    status: Mutex<ConnectionStatus>,
+}
+
+// A pooled connection whose queries go through the connection's prepared statement cache,
+// so repeated queries (packet pages, counts, inserts) are parsed and planned once per connection
+struct CachedClient(Object);
+
+impl CachedClient {
+    async fn query(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, tokio_postgres::Error> {
+        let statement = self.0.prepare_cached(sql).await?;
+        self.0.query(&statement, params).await
+    }
+
+    async fn query_one(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Row, tokio_postgres::Error> {
+        let statement = self.0.prepare_cached(sql).await?;
+        self.0.query_one(&statement, params).await
+    }
+
+    async fn query_opt(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Option<Row>, tokio_postgres::Error> {
+        let statement = self.0.prepare_cached(sql).await?;
+        self.0.query_opt(&statement, params).await
+    }
+
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, tokio_postgres::Error> {
+        let statement = self.0.prepare_cached(sql).await?;
+        self.0.execute(&statement, params).await
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -14,6 +70,32 @@ pub struct Session {
     pub ended_at: Option<DateTime<Utc>>,
 }
 
+// A session with what the session list shows about it, from the session_summaries table
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub session: Session,
+    pub packet_count: usize,
+    pub first_ts: Option<DateTime<Utc>>, // None while the session has no packets
+    pub last_ts: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub archived: bool,
+    pub name: Option<String>, // Shown instead of the session's number when set
+}
+
+impl SessionSummary {
+    /// "name (#12)" for a named session, otherwise "Session #12"
+    pub fn title(&self) -> String {
+        session_title(self.session.id, self.name.as_deref())
+    }
+}
+
+pub fn session_title(session_id: i32, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{} (#{})", name, session_id),
+        None => format!("Session #{}", session_id),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DbPacket {
     pub id: i32,
@@ -26,12 +108,109 @@ pub struct DbPacket {
     pub packet: Value,
 }
 
+// A packet to insert (id and session_id are assigned by Storage::insert_packet)
 #[derive(Debug, Clone)]
+pub struct NewDbPacket {
+    pub ts: DateTime<Utc>,
+    pub session_time_ms: i64,
+    pub packet_number: i64,
+    pub server_version: String,
+    pub direction: String,
+    pub packet: Value,
+}
+
+impl NewDbPacket {
+    /// Row for the packets table from a captured entry
+    /// The table stores decoded JSON: the entry's own, else decoded with `parser`, else the raw bytes as hex.
+    /// session_time_ms counts from `started_at_ms`; entries without a protocol version get `server_version`.
+    pub fn from_entry(entry: &PacketEntry, parser: Option<&ProtocolParser>, started_at_ms: i64, server_version: &str) -> Self {
+        let packet = entry
+            .packet_json
+            .clone()
+            .or_else(|| parser.and_then(|parser| parser.decode_packet(&entry.data, entry.direction).to_packet_json()))
+            .unwrap_or_else(|| json!({ "name": "unknown", "params": { "data": encode_hex(&entry.data) } }));
+        let packet = match (entry.original_size, packet) {
+            // Truncated payloads only decode partially; keep the real size next to what was stored
+            (Some(size), Value::Object(mut fields)) => {
+                fields.insert("original_size".to_string(), json!(size));
+                Value::Object(fields)
+            }
+            (_, packet) => packet,
+        };
+
+        Self {
+            ts: DateTime::<Utc>::from_timestamp_millis(entry.timestamp).unwrap_or_else(Utc::now),
+            session_time_ms: entry.timestamp - started_at_ms,
+            packet_number: entry.packet_number.unwrap_or(0),
+            server_version: entry.protocol_version.clone().unwrap_or_else(|| server_version.to_string()),
+            direction: match entry.direction {
+                PacketDirection::Clientbound => "clientbound",
+                PacketDirection::Serverbound => "serverbound",
+            }
+            .to_string(),
+            packet,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct DbPacketFilter {
     pub direction: Option<String>, // "clientbound", "serverbound", or None for all
     pub packet_name: Option<String>, // Packet name to filter by, or None for all
     pub packet_name_is_wildcard: bool, // If true, use ILIKE with wildcards; if false, use exact match
+    pub packet_name_is_regex: bool, // If true, packet_name is a regular expression the name must match (somewhere)
     pub is_exclusion: bool, // If true, this filter excludes matching packets
+    pub ts_from: Option<DateTime<Utc>>, // Packets captured at or after this time
+    pub ts_to: Option<DateTime<Utc>>, // Packets captured at or before this time
+    pub packet_number_from: Option<i64>, // Packets numbered at least this
+    pub packet_number_to: Option<i64>, // Packets numbered at most this
+    pub fields: Vec<DbFieldCondition>, // Conditions on the packet's JSON, all of which must hold
+    pub entity: Option<i64>, // Packets referencing this entity: one of ENTITY_ID_KEYS holds it, at any depth
+}
+
+// Keys whose values identify an entity, runtime ids first. Servers mostly give an entity the same unique id as
+// its runtime id, so unique ids are matched too (remove_entity only has the unique one)
+pub const ENTITY_ID_KEYS: &[&str] = &[
+    "runtime_entity_id",
+    "runtime_id",
+    "entity_runtime_id",
+    "ridden_runtime_id",
+    "unique_id",
+    "entity_unique_id",
+    "entity_id_self",
+];
+
+// Comparison of a value inside the packet JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbFieldCondition {
+    pub path: Vec<String>, // Keys from the packet root, e.g. ["params", "runtime_id"]; numeric keys index arrays
+    pub op: DbFieldOp,
+    pub value: String, // Compared as text for Eq/Ne/Like (numbers, strings and booleans alike), as a number otherwise
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbFieldOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like, // Case-insensitive, * as wildcard
+}
+
+impl DbFieldOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Like => "~",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -39,8 +218,346 @@ pub struct DbPacketFilterSet {
     pub filters: Vec<DbPacketFilter>, // OR logic: packet matches if it matches any filter
 }
 
+// Search over packet contents (see Storage::search_packets)
+#[derive(Debug, Clone, PartialEq)]
+pub enum PacketSearch {
+    Contains(Value), // Packets whose JSON contains this JSON, e.g. {"name": "text"} (jsonb @>)
+    ChatText(String), // Chat (text) packets whose message has all of these words
+    Text(String), // Packets whose name or JSON text contains all of these words (case-insensitive)
+}
+
+impl PacketSearch {
+    /// JSON objects (starting with '{') are containment searches, `chat:` searches chat messages, and anything
+    /// else searches packet names and contents
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(anyhow!("Empty search"));
+        }
+        if input.starts_with('{') {
+            let value: Value = serde_json::from_str(input).context("Invalid JSON in search")?;
+            Ok(Self::Contains(value))
+        } else if let Some(text) = input.strip_prefix("chat:") {
+            match text.trim() {
+                "" => Err(anyhow!("Empty chat search")),
+                text => Ok(Self::ChatText(text.to_string())),
+            }
+        } else {
+            Ok(Self::Text(input.to_string()))
+        }
+    }
+
+    pub fn to_input(&self) -> String {
+        match self {
+            Self::Contains(value) => value.to_string(),
+            Self::ChatText(text) => format!("chat:{}", text),
+            Self::Text(text) => text.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionSort {
+    #[default]
+    Newest,
+    Oldest,
+    Packets, // Most packets first
+    Duration, // Longest first; sessions that haven't ended last
+    Tag, // By first tag name; untagged sessions last
+}
+
+impl SessionSort {
+    fn parse(text: &str) -> Result<Self> {
+        match text {
+            "newest" => Ok(Self::Newest),
+            "oldest" => Ok(Self::Oldest),
+            "packets" => Ok(Self::Packets),
+            "duration" => Ok(Self::Duration),
+            "tag" => Ok(Self::Tag),
+            _ => Err(anyhow!("Invalid sort: {} (expected newest, oldest, packets, duration or tag)", text)),
+        }
+    }
+
+    /// The order after this one, cycled through in the session list
+    pub fn next(self) -> Self {
+        match self {
+            Self::Newest => Self::Oldest,
+            Self::Oldest => Self::Packets,
+            Self::Packets => Self::Duration,
+            Self::Duration => Self::Tag,
+            Self::Tag => Self::Newest,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Newest => "newest",
+            Self::Oldest => "oldest",
+            Self::Packets => "packets",
+            Self::Duration => "duration",
+            Self::Tag => "tag",
+        }
+    }
+}
+
+// Which sessions the session list shows, and in what order (Storage::search_sessions)
+// Every condition must hold
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionSearch {
+    pub words: Vec<String>, // Each found in the session's id, name, a tag or its note (case-insensitive)
+    pub tags: Vec<String>, // Tagged with each of these
+    pub started_from: Option<NaiveDate>, // Started on or after this day (UTC)
+    pub started_to: Option<NaiveDate>, // Started on or before this day (UTC)
+    pub server_version: Option<String>, // Has packets captured with this protocol version
+    pub packet_types: Vec<String>, // Has at least one packet of each of these names
+    pub archived: ArchivedSessions,
+    pub sort: SessionSort,
+}
+
+// Whether a session search finds archived sessions (archived:only / archived:include)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchivedSessions {
+    #[default]
+    Hidden,
+    Only,
+    Included,
+}
+
+impl ArchivedSessions {
+    pub fn parse(text: &str) -> Result<Self> {
+        match text {
+            "hide" => Ok(ArchivedSessions::Hidden),
+            "only" => Ok(ArchivedSessions::Only),
+            "include" => Ok(ArchivedSessions::Included),
+            _ => Err(anyhow!("Invalid archived: {} (expected only, include or hide)", text)),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ArchivedSessions::Hidden => "hide",
+            ArchivedSessions::Only => "only",
+            ArchivedSessions::Included => "include",
+        }
+    }
+}
+
+impl SessionSearch {
+    /// Space-separated terms: words, tag:NAME, from:YYYY-MM-DD, to:YYYY-MM-DD, version:VERSION, has:PACKET_NAME,
+    /// archived:only|include|hide, sort:newest|oldest|packets|duration|tag (words, tag: and has: may be repeated)
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut search = Self::default();
+        let date = |value: &str| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").with_context(|| format!("Invalid date: {} (expected YYYY-MM-DD)", value))
+        };
+        for term in input.split_whitespace() {
+            match term.split_once(':') {
+                Some((_, "")) => return Err(anyhow!("Missing value in {}", term)),
+                Some(("tag", tag)) => search.tags.push(tag.to_string()),
+                Some(("from", day)) => search.started_from = Some(date(day)?),
+                Some(("to", day)) => search.started_to = Some(date(day)?),
+                Some(("version", version)) => search.server_version = Some(version.to_string()),
+                Some(("has", name)) => search.packet_types.push(name.to_string()),
+                Some(("archived", archived)) => search.archived = ArchivedSessions::parse(archived)?,
+                Some(("sort", sort)) => search.sort = SessionSort::parse(sort)?,
+                None => search.words.push(term.to_string()),
+                _ => return Err(anyhow!("Unknown search term: {} (expected tag:, from:, to:, version:, has:, archived: or sort:)", term)),
+            }
+        }
+        Ok(search)
+    }
+
+    pub fn to_input(&self) -> String {
+        let mut terms = self.words.clone();
+        terms.extend(self.tags.iter().map(|tag| format!("tag:{}", tag)));
+        terms.extend(self.started_from.map(|day| format!("from:{}", day)));
+        terms.extend(self.started_to.map(|day| format!("to:{}", day)));
+        terms.extend(self.server_version.iter().map(|version| format!("version:{}", version)));
+        terms.extend(self.packet_types.iter().map(|name| format!("has:{}", name)));
+        if self.archived != ArchivedSessions::default() {
+            terms.push(format!("archived:{}", self.archived.label()));
+        }
+        if self.sort != SessionSort::default() {
+            terms.push(format!("sort:{}", self.sort.label()));
+        }
+        terms.join(" ")
+    }
+}
+
+// Totals for one packet name and direction in a session (Storage::get_packet_stats)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketTypeStats {
+    pub name: String, // "unknown" if the packet wasn't decoded
+    pub direction: String,
+    pub count: i64,
+    pub total_bytes: i64, // Size of the stored packet JSON
+    pub rate: f64, // Packets per second over the whole session
+}
+
+// Packets in one second of a session (Storage::get_packet_rate)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketRateBucket {
+    pub second: i64, // Since the session's first packet
+    pub clientbound: i64,
+    pub serverbound: i64,
+}
+
+// Time between two consecutive packets passing a filter (Storage::get_packet_gaps)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketGap {
+    pub previous_packet_number: i64,
+    pub previous_name: Option<String>,
+    pub packet_number: i64, // First packet after the gap
+    pub name: Option<String>,
+    pub session_time_ms: i64, // Of the packet after the gap
+    pub gap_ms: i64,
+}
+
+// Compact packet description used by the session preview
+#[derive(Debug, Clone)]
+pub struct PacketSummary {
+    pub packet_number: i64,
+    pub session_time_ms: i64,
+    pub direction: String,
+    pub name: Option<String>, // None if the packet wasn't decoded
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionMilestone {
+    pub milestone: &'static str,
+    pub packet_number: i64, // First occurrence
+    pub session_time_ms: i64,
+    pub count: i64,
+}
+
+// Overview of a session shown in the session list, without loading its packets
+#[derive(Debug, Clone, Default)]
+pub struct SessionPreview {
+    pub first_packets: Vec<PacketSummary>,
+    pub last_packets: Vec<PacketSummary>, // Oldest first
+    pub top_packet_types: Vec<(String, i64)>, // (name, count), most frequent first
+    pub milestones: Vec<SessionMilestone>, // In order of first occurrence
+}
+
+// Named filter saved from the TUI filter panel; filter is the filter text as typed there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterPreset {
+    pub name: String,
+    pub filter: String,
+}
+
+// Stored by name in packet_bookmarks.color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkColor {
+    #[default]
+    Yellow,
+    Red,
+    Green,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl BookmarkColor {
+    const ALL: [Self; 6] = [Self::Yellow, Self::Red, Self::Green, Self::Blue, Self::Magenta, Self::Cyan];
+
+    /// Unknown names read as the default color
+    pub fn from_name(name: &str) -> Self {
+        Self::ALL.into_iter().find(|color| color.name() == name).unwrap_or_default()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Yellow => "yellow",
+            Self::Red => "red",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::Magenta => "magenta",
+            Self::Cyan => "cyan",
+        }
+    }
+
+    /// Next color, for cycling through them in the TUI
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&color| color == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+// A bookmarked packet, identified by its packet_number within the session (Storage::get_packet_bookmarks)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PacketBookmark {
+    pub packet_number: i64,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub color: BookmarkColor,
+}
+
+// Size and age of a session, for retention (Storage::get_session_usage)
+#[derive(Debug, Clone)]
+pub struct SessionUsage {
+    pub session_id: i32,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub bytes: i64, // Stored size of its packets' JSON
+    pub tagged: bool, // Whether the session has tags
+}
+
+// Rows removed by Storage::delete_session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeletedSession {
+    pub packets: u64,
+    pub tag_maps: u64, // Session tags and packet tags
+    pub notes: u64, // Session note and packet notes
+    pub bookmarks: u64,
+}
+
+// Size of one table (Storage::health)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableHealth {
+    pub name: String,
+    pub rows: i64,
+    pub bytes: Option<i64>, // Including indexes; None where the backend can't tell
+}
+
+// Connection state and storage use of the database (Storage::health)
+#[derive(Debug, Clone)]
+pub struct DbHealth {
+    pub status: ConnectionStatus,
+    pub database_bytes: Option<i64>,
+    pub tables: Vec<TableHealth>,
+    pub oldest_session: Option<DateTime<Utc>>, // started_at of the oldest and newest sessions
+    pub newest_session: Option<DateTime<Utc>>,
+}
+
+// Milestones as (name, clientbound packet name) - mirrors MILESTONES in apps/relay/src/lib/tagging/rules.js
+pub const SESSION_MILESTONES: &[(&str, &str)] = &[
+    ("death", "death_info"),
+    ("kick", "disconnect"),
+    ("transfer", "transfer"),
+];
+
 impl Database {
+    /// Connect and apply pending migrations (unless DB_AUTO_MIGRATE=false)
     pub async fn connect() -> Result<Self> {
+        let db = Self::open().await?;
+
+        // DB_AUTO_MIGRATE=false leaves the schema alone (`lazypacket migrate` applies it explicitly)
+        let auto_migrate = match std::env::var("DB_AUTO_MIGRATE") {
+            Ok(value) => value.parse::<bool>().context("Invalid DB_AUTO_MIGRATE")?,
+            Err(_) => true,
+        };
+        if auto_migrate {
+            db.migrate().await?;
+        }
+
+        Ok(db)
+    }
+
+    /// Connect without touching the schema
+    pub async fn open() -> Result<Self> {
         // Get connection string from environment variables
         let host = std::env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_string());
         let port = std::env::var("DB_PORT")
@@ -56,55 +573,260 @@ impl Database {
             host, port, user, password, dbname
         );
 
-        let (client, connection) = tokio_postgres::connect(&connection_string, NoTls)
-            .await
-            .with_context(|| format!(
-                "Failed to connect to database at {}:{} (user: {}, db: {}). \
-                Make sure your .env file is loaded and contains DB_HOST, DB_PORT, DB_USER, DB_PASSWORD, and DB_NAME",
-                host, port, user, dbname
-            ))?;
+        let mut pg_config: tokio_postgres::Config = connection_string.parse().context("Invalid database settings")?;
+        pg_config.connect_timeout(CONNECT_TIMEOUT);
+        let manager_config = ManagerConfig {
+            // Checks that a pooled connection is still open before handing it out
+            recycling_method: RecyclingMethod::Fast,
+        };
+        let tls = TlsSettings::from_env()?;
+        let manager = match tls.connector()? {
+            Some(connector) => {
+                // Never fall back to an unencrypted connection when TLS is configured
+                pg_config.ssl_mode(tokio_postgres::config::SslMode::Require);
+                Manager::from_config(pg_config, connector, manager_config)
+            }
+            None => Manager::from_config(pg_config, NoTls, manager_config),
+        };
+        let pool_size = match std::env::var("DB_POOL_SIZE") {
+            Ok(size) => size.parse().context("Invalid DB_POOL_SIZE")?,
+            Err(_) => DEFAULT_POOL_SIZE,
+        };
+        let pool = Pool::builder(manager)
+            .max_size(pool_size)
+            .build()
+            .context("Failed to create database pool")?;
+
+        let connect_attempts = match std::env::var("DB_CONNECT_ATTEMPTS") {
+            Ok(attempts) => attempts.parse().context("Invalid DB_CONNECT_ATTEMPTS")?,
+            Err(_) => DEFAULT_CONNECT_ATTEMPTS,
+        };
+        let retry_backoff = match std::env::var("DB_RETRY_BACKOFF_MS") {
+            Ok(ms) => Duration::from_millis(ms.parse().context("Invalid DB_RETRY_BACKOFF_MS")?),
+            Err(_) => DEFAULT_RETRY_BACKOFF,
+        };
 
-        // Spawn connection task
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("Database connection error: {}", e);
+        let db = Self {
+            pool,
+            target: match tls.mode {
+                SslMode::Disable => format!("{}:{} (user: {}, db: {})", host, port, user, dbname),
+                mode => format!("{}:{} (user: {}, db: {}, ssl: {})", host, port, user, dbname, mode.label()),
+            },
+            connect_attempts: connect_attempts.max(1),
+            retry_backoff,
+            status: Mutex::new(ConnectionStatus::Connected),
+        };
+        // Fail at startup rather than on the first query if the database can't be reached
+        drop(db.client().await?);
+        Ok(db)
+    }
+
+    /// A pooled connection, retrying with exponential backoff while the database is unreachable
+    async fn client(&self) -> Result<CachedClient> {
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 1;
+        loop {
+            match self.pool.get().await {
+                Ok(client) => {
+                    self.set_status(ConnectionStatus::Connected);
+                    return Ok(CachedClient(client));
+                }
+                Err(e) if attempt < self.connect_attempts => {
+                    self.set_status(ConnectionStatus::Reconnecting { attempt, last_error: e.to_string() });
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    self.set_status(ConnectionStatus::Unavailable { last_error: e.to_string() });
+                    return Err(anyhow!(e)).with_context(|| format!(
+                        "Failed to connect to database at {} after {} attempt(s). \
+                        Make sure your .env file is loaded and contains DB_HOST, DB_PORT, DB_USER, DB_PASSWORD, and DB_NAME",
+                        self.target, attempt
+                    ));
+                }
             }
-        });
+        }
+    }
+
+    fn set_status(&self, status: ConnectionStatus) {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = status;
+    }
+
+    /// Apply pending migrations; returns the names of the ones applied (e.g. "V1__initial_schema")
+    pub async fn migrate(&self) -> Result<Vec<String>> {
+        let mut client = self.client().await?;
+        let client: &mut tokio_postgres::Client = &mut client.0;
+        let report = embedded::migrations::runner()
+            .run_async(client)
+            .await
+            .context("Failed to apply database migrations")?;
+
+        Ok(report.applied_migrations().iter().map(|m| m.to_string()).collect())
+    }
+}
+
+const POSTGRES_DIALECT: SqlDialect = SqlDialect {
+    placeholder: '$',
+    packet_name: "packet->>'name'",
+    packet_size: "octet_length(packet::text)",
+    regex_match: "~",
+    like: "ILIKE",
+    json_path: |path| SqlParam::TextArray(path.to_vec()),
+    json_text: |path| format!("packet #>> {}", path),
+    json_number: |path| format!(
+        "(CASE WHEN jsonb_typeof(packet #> {0}) = 'number' THEN (packet #>> {0})::float8 END)",
+        path
+    ),
+    // Both match the GIN indexes from V2__packet_search_indexes.sql
+    json_contains: Some(|value| format!("packet @> {}", value)),
+    chat_word: |word| format!("to_tsvector('simple', packet->'params'->>'message') @@ plainto_tsquery('simple', {})", word),
+    // The name is part of the JSON text
+    text_contains: |text| format!("strpos(lower(packet::text), lower({})) > 0", text),
+    // 64-bit ids are stored as strings, smaller ones as numbers
+    json_entity: |id| format!(
+        "jsonb_path_exists(packet, '$.** ? (@.type() == \"object\").keyvalue() ? \
+         (@.key like_regex \"^({})$\" && (@.value == $id || @.value == $text))', \
+         jsonb_build_object('id', {1}::bigint, 'text', {1}::bigint::text))",
+        ENTITY_ID_KEYS.join("|"),
+        id
+    ),
+    session_duration: "s.ended_at - s.started_at",
+    session_tags: "ARRAY(SELECT m.tag FROM tag_maps m WHERE m.session_id = s.id ORDER BY m.tag)",
+};
+
+fn postgres_param(param: &SqlParam) -> &(dyn ToSql + Sync) {
+    match param {
+        SqlParam::Text(text) => text,
+        SqlParam::Int(number) => number,
+        SqlParam::Timestamp(ts) => ts,
+        SqlParam::Float(number) => number,
+        SqlParam::TextArray(texts) => texts,
+        SqlParam::Json(value) => value,
+    }
+}
+
+// Session id followed by the query's other parameters
+fn postgres_params<'a>(session_id: &'a i32, params: &'a [SqlParam]) -> Vec<&'a (dyn ToSql + Sync)> {
+    let mut values: Vec<&(dyn ToSql + Sync)> = vec![session_id];
+    values.extend(params.iter().map(postgres_param));
+    values
+}
+
+fn packet_from_row(row: &tokio_postgres::Row) -> DbPacket {
+    // PostgreSQL TIMESTAMP is read as NaiveDateTime, then convert to DateTime<Utc>
+    let ts_naive: chrono::NaiveDateTime = row.get(2);
+    let packet_json: Json<Value> = row.get(7);
 
-        Ok(Self { client })
+    DbPacket {
+        id: row.get(0),
+        session_id: row.get(1),
+        ts: DateTime::from_naive_utc_and_offset(ts_naive, Utc),
+        session_time_ms: row.get(3),
+        packet_number: row.get(4),
+        server_version: row.get(5),
+        direction: row.get(6),
+        packet: packet_json.0,
     }
+}
+
+#[async_trait]
+impl Storage for Database {
+    fn connection_status(&self) -> ConnectionStatus {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    async fn get_sessions(&self) -> Result<Vec<Session>> {
+        self.search_sessions(&SessionSearch { archived: ArchivedSessions::Included, ..Default::default() }).await
+    }
+
+    async fn get_session(&self, session_id: i32) -> Result<Option<Session>> {
+        let row = self
+            .client().await?
+            .query_opt("SELECT id, started_at, ended_at FROM sessions WHERE id = $1", &[&session_id])
+            .await
+            .context("Failed to query session")?;
 
-    pub async fn get_sessions(&self) -> Result<Vec<Session>> {
+        Ok(row.map(|row| {
+            let started_at: chrono::NaiveDateTime = row.get(1);
+            let ended_at: Option<chrono::NaiveDateTime> = row.get(2);
+            Session {
+                id: row.get(0),
+                started_at: DateTime::from_naive_utc_and_offset(started_at, Utc),
+                ended_at: ended_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            }
+        }))
+    }
+
+    async fn search_session_summaries(&self, search: &SessionSearch) -> Result<Vec<SessionSummary>> {
+        let (query, params) = session_search_query(search, &POSTGRES_DIALECT);
+        let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(postgres_param).collect();
         let rows = self
-            .client
-            .query(
-                "SELECT id, started_at, ended_at FROM sessions ORDER BY started_at DESC",
-                &[],
-            )
+            .client().await?
+            .query(&query, &params)
             .await
             .context("Failed to query sessions")?;
 
+        // PostgreSQL TIMESTAMP is read as NaiveDateTime, then convert to DateTime<Utc>
+        let utc = |ts: chrono::NaiveDateTime| DateTime::from_naive_utc_and_offset(ts, Utc);
         let mut sessions = Vec::new();
         for row in rows {
-            // PostgreSQL TIMESTAMP is read as NaiveDateTime, then convert to DateTime<Utc>
-            let started_at_naive: chrono::NaiveDateTime = row.get(1);
-            let ended_at_naive: Option<chrono::NaiveDateTime> = row.get(2);
-            
-            sessions.push(Session {
-                id: row.get(0),
-                started_at: DateTime::from_naive_utc_and_offset(started_at_naive, Utc),
-                ended_at: ended_at_naive.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            let started_at: chrono::NaiveDateTime = row.get(1);
+            let ended_at: Option<chrono::NaiveDateTime> = row.get(2);
+            let packet_count: i64 = row.get(3);
+            let first_ts: Option<chrono::NaiveDateTime> = row.get(4);
+            let last_ts: Option<chrono::NaiveDateTime> = row.get(5);
+
+            sessions.push(SessionSummary {
+                session: Session {
+                    id: row.get(0),
+                    started_at: utc(started_at),
+                    ended_at: ended_at.map(utc),
+                },
+                packet_count: packet_count as usize,
+                first_ts: first_ts.map(utc),
+                last_ts: last_ts.map(utc),
+                tags: row.get(6),
+                archived: row.get(7),
+                name: row.get(8),
             });
         }
 
         Ok(sessions)
     }
 
-    pub async fn get_session_packet_count(&self, session_id: i32) -> Result<usize> {
+    async fn get_session_usage(&self) -> Result<Vec<SessionUsage>> {
+        // pg_column_size is the stored (possibly compressed) size of the jsonb value
+        let rows = self.client().await?
+            .query(
+                "SELECT s.id, s.started_at, s.ended_at, COALESCE(p.bytes, 0)::bigint,
+                        EXISTS (SELECT 1 FROM tag_maps t WHERE t.session_id = s.id)
+                 FROM sessions s
+                 LEFT JOIN (
+                     SELECT session_id, SUM(pg_column_size(packet)) AS bytes FROM packets GROUP BY session_id
+                 ) p ON p.session_id = s.id",
+                &[],
+            )
+            .await
+            .context("Failed to query session usage")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SessionUsage {
+                session_id: row.get(0),
+                started_at: DateTime::from_naive_utc_and_offset(row.get(1), Utc),
+                ended_at: row.get::<_, Option<chrono::NaiveDateTime>>(2).map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+                bytes: row.get(3),
+                tagged: row.get(4),
+            })
+            .collect())
+    }
+
+    async fn get_session_packet_count(&self, session_id: i32) -> Result<usize> {
         let row = self
-            .client
+            .client().await?
             .query_one(
-                "SELECT COUNT(*) FROM packets WHERE session_id = $1",
+                "SELECT COALESCE((SELECT packet_count FROM session_summaries WHERE session_id = $1), 0)",
                 &[&session_id],
             )
             .await
@@ -113,171 +835,268 @@ impl Database {
         Ok(row.get::<_, i64>(0) as usize)
     }
 
-    pub async fn get_packets(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<DbPacket>> {
-        let rows = if let Some(filter_set) = filter_set {
-            if filter_set.filters.is_empty() {
-                // No filters - show all packets
-                self.client
-                    .query(
-                        "SELECT id, session_id, ts, session_time_ms, packet_number, server_version, direction, packet 
-                     FROM packets 
-                     WHERE session_id = $1 
-                     ORDER BY packet_number ASC",
-                        &[&session_id],
-                    )
+    async fn get_packets(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<DbPacket>> {
+        let (where_clause, filter_params) = packet_filter_clause(filter_set, &POSTGRES_DIALECT);
+        let query = format!(
+            "SELECT {} FROM packets WHERE {} ORDER BY packet_number ASC",
+            PACKET_COLUMNS, where_clause
+        );
+
+        let rows = self
+            .client().await?
+            .query(&query, &postgres_params(&session_id, &filter_params))
+            .await
+            .context("Failed to query packets")?;
+
+        Ok(rows.iter().map(packet_from_row).collect())
+    }
+
+    fn stream_packets<'a>(&'a self, session_id: i32, filter_set: Option<&'a DbPacketFilterSet>) -> PacketStream<'a> {
+        Box::pin(async_stream::try_stream! {
+            let (where_clause, filter_params) = packet_filter_clause(filter_set, &POSTGRES_DIALECT);
+            let query = format!(
+                "SELECT {} FROM packets WHERE {} ORDER BY packet_number ASC",
+                PACKET_COLUMNS, where_clause
+            );
+
+            // Portals (server-side cursors) only live inside a transaction; it's rolled back if the stream is dropped early
+            let mut client = self.client().await?;
+            let statement = client.0.prepare_cached(&query).await.context("Failed to prepare packet query")?;
+            let transaction = client.0
+                .build_transaction()
+                .read_only(true)
+                .start()
+                .await
+                .context("Failed to start packet stream transaction")?;
+            let portal = transaction
+                .bind(&statement, &postgres_params(&session_id, &filter_params))
+                .await
+                .context("Failed to open packet cursor")?;
+            loop {
+                let rows = transaction
+                    .query_portal(&portal, STREAM_BATCH_SIZE as i32)
                     .await
-            } else {
-                // Separate inclusion and exclusion filters
-                let inclusion_filters: Vec<_> = filter_set.filters.iter().filter(|f| !f.is_exclusion).collect();
-                let exclusion_filters: Vec<_> = filter_set.filters.iter().filter(|f| f.is_exclusion).collect();
-                
-                let mut param_index = 1;
-                let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync>> = vec![Box::new(session_id)];
-                let mut where_parts = Vec::new();
-                
-                // Build inclusion conditions (OR logic: match any inclusion filter)
-                if !inclusion_filters.is_empty() {
-                    let mut inclusion_conditions = Vec::new();
-                    
-                    for filter in inclusion_filters {
-                        let mut filter_conditions = Vec::new();
-                        
-                        // Direction filter
-                        if let Some(ref direction) = filter.direction {
-                            filter_conditions.push(format!("direction = '{}'", direction));
-                        }
-                        
-                        // Packet name filter
-                        if let Some(ref packet_name) = filter.packet_name {
-                            param_index += 1;
-                            if filter.packet_name_is_wildcard {
-                                // Convert * to % for SQL ILIKE pattern matching
-                                let sql_pattern = packet_name.replace('*', "%");
-                                filter_conditions.push(format!("packet->>'name' ILIKE ${}", param_index));
-                                params.push(Box::new(sql_pattern));
-                            } else {
-                                // Exact match
-                                filter_conditions.push(format!("packet->>'name' = ${}", param_index));
-                                params.push(Box::new(packet_name.clone()));
-                            }
-                        }
-                        
-                        // Combine conditions for this filter with AND
-                        if !filter_conditions.is_empty() {
-                            inclusion_conditions.push(format!("({})", filter_conditions.join(" AND ")));
-                        } else {
-                            // No conditions means match all
-                            inclusion_conditions.push("1=1".to_string());
-                        }
-                    }
-                    
-                    if !inclusion_conditions.is_empty() {
-                        where_parts.push(format!("({})", inclusion_conditions.join(" OR ")));
-                    }
-                } else {
-                    // No inclusion filters means match all packets (before exclusions)
-                    where_parts.push("1=1".to_string());
+                    .context("Failed to fetch from packet cursor")?;
+                if rows.is_empty() {
+                    break;
                 }
-                
-                // Build exclusion conditions (AND NOT logic: exclude all exclusion filters)
-                if !exclusion_filters.is_empty() {
-                    let mut exclusion_conditions = Vec::new();
-                    
-                    for filter in exclusion_filters {
-                        let mut filter_conditions = Vec::new();
-                        
-                        // Direction filter
-                        if let Some(ref direction) = filter.direction {
-                            filter_conditions.push(format!("direction = '{}'", direction));
-                        }
-                        
-                        // Packet name filter
-                        if let Some(ref packet_name) = filter.packet_name {
-                            param_index += 1;
-                            if filter.packet_name_is_wildcard {
-                                // Convert * to % for SQL ILIKE pattern matching
-                                let sql_pattern = packet_name.replace('*', "%");
-                                filter_conditions.push(format!("packet->>'name' ILIKE ${}", param_index));
-                                params.push(Box::new(sql_pattern));
-                            } else {
-                                // Exact match
-                                filter_conditions.push(format!("packet->>'name' = ${}", param_index));
-                                params.push(Box::new(packet_name.clone()));
-                            }
-                        }
-                        
-                        // Combine conditions for this exclusion filter with AND
-                        if !filter_conditions.is_empty() {
-                            exclusion_conditions.push(format!("NOT ({})", filter_conditions.join(" AND ")));
-                        } else {
-                            // No conditions means exclude all - this would exclude everything
-                            // Skip this filter as it's invalid
-                            continue;
-                        }
-                    }
-                    
-                    if !exclusion_conditions.is_empty() {
-                        where_parts.push(format!("({})", exclusion_conditions.join(" AND ")));
-                    }
+                for row in &rows {
+                    yield packet_from_row(row);
                 }
-                
-                // Combine all WHERE parts with AND
-                let where_clause = if where_parts.is_empty() {
-                    "session_id = $1".to_string()
-                } else {
-                    format!("session_id = $1 AND {}", where_parts.join(" AND "))
-                };
-                
-                let query = format!(
-                    "SELECT id, session_id, ts, session_time_ms, packet_number, server_version, direction, packet 
-                     FROM packets 
-                     WHERE {}
-                     ORDER BY packet_number ASC",
-                    where_clause
-                );
-                
-                // Convert Vec<Box<dyn ToSql + Sync>> to &[&dyn ToSql + Sync]
-                let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = params.iter().map(|p| p.as_ref()).collect();
-                self.client.query(&query, &param_refs[..]).await
             }
-        } else {
-            // No filter set - show all packets
-            self.client
-                .query(
-                    "SELECT id, session_id, ts, session_time_ms, packet_number, server_version, direction, packet 
-                 FROM packets 
-                 WHERE session_id = $1 
-                 ORDER BY packet_number ASC",
-                    &[&session_id],
-                )
-                .await
-        }
-        .context("Failed to query packets")?;
+            transaction.commit().await.context("Failed to close packet stream transaction")?;
+        })
+    }
 
-        let mut packets = Vec::new();
-        for row in rows {
-            // PostgreSQL TIMESTAMP is read as NaiveDateTime, then convert to DateTime<Utc>
-            let ts_naive: chrono::NaiveDateTime = row.get(2);
-            let packet_json: Json<Value> = row.get(7);
-            
-            packets.push(DbPacket {
-                id: row.get(0),
-                session_id: row.get(1),
-                ts: DateTime::from_naive_utc_and_offset(ts_naive, Utc),
-                session_time_ms: row.get(3),
-                packet_number: row.get(4),
-                server_version: row.get(5),
-                direction: row.get(6),
-                packet: packet_json.0,
-            });
+    async fn get_packet_page(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        cursor: PageCursor,
+        limit: i64,
+    ) -> Result<Vec<DbPacket>> {
+        let (query, params) = packet_page_query(filter_set, cursor, limit, &POSTGRES_DIALECT);
+        let rows = self
+            .client().await?
+            .query(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to query packet page")?;
+
+        let mut packets: Vec<DbPacket> = rows.iter().map(packet_from_row).collect();
+        if matches!(cursor, PageCursor::Before(_)) {
+            packets.reverse();
         }
+        Ok(packets)
+    }
+
+    async fn count_packets(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, before: Option<i64>) -> Result<usize> {
+        let (query, params) = packet_count_query(filter_set, before, &POSTGRES_DIALECT);
+        let row = self
+            .client().await?
+            .query_one(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to count packets")?;
 
+        Ok(row.get::<_, i64>(0) as usize)
+    }
+
+    async fn packet_at_time(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, ts: DateTime<Utc>) -> Result<Option<i64>> {
+        let (query, params) = packet_at_time_query(filter_set, ts, &POSTGRES_DIALECT);
+        let row = self
+            .client().await?
+            .query_opt(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to find packet by time")?;
+
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    async fn search_packets(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        search: &PacketSearch,
+        cursor: PageCursor,
+        limit: i64,
+    ) -> Result<Vec<DbPacket>> {
+        let (query, params) = packet_search_query(filter_set, search, cursor, limit, &POSTGRES_DIALECT);
+        let rows = self
+            .client().await?
+            .query(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to search packets")?;
+
+        let mut packets: Vec<DbPacket> = rows.iter().map(packet_from_row).collect();
+        if matches!(cursor, PageCursor::Before(_)) {
+            packets.reverse();
+        }
         Ok(packets)
     }
 
-    pub async fn get_session_tags(&self, session_id: i32) -> Result<Vec<String>> {
+    async fn count_search_matches(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        search: &PacketSearch,
+        before: Option<i64>,
+    ) -> Result<usize> {
+        let (query, params) = packet_search_count_query(filter_set, search, before, &POSTGRES_DIALECT);
+        let row = self
+            .client().await?
+            .query_one(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to count search matches")?;
+
+        Ok(row.get::<_, i64>(0) as usize)
+    }
+
+    async fn get_packet_stats(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<PacketTypeStats>> {
+        let (query, params) = packet_stats_query(filter_set, &POSTGRES_DIALECT);
+        let rows = self
+            .client().await?
+            .query(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to query packet statistics")?;
+
+        Ok(packet_stats_from_rows(
+            rows.iter()
+                .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4), row.get(5)))
+                .collect(),
+        ))
+    }
+
+    async fn get_packet_rate(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<PacketRateBucket>> {
+        let (query, params) = packet_rate_query(filter_set, &POSTGRES_DIALECT);
+        let rows = self
+            .client().await?
+            .query(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to query the packet rate")?;
+
+        Ok(packet_rate_from_rows(rows.iter().map(|row| (row.get(0), row.get(1), row.get(2))).collect()))
+    }
+
+    async fn get_packet_gaps(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, limit: i64) -> Result<Vec<PacketGap>> {
+        let (query, params) = packet_gaps_query(filter_set, limit, &POSTGRES_DIALECT);
+        let rows = self
+            .client().await?
+            .query(&query, &postgres_params(&session_id, &params))
+            .await
+            .context("Failed to query packet gaps")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PacketGap {
+                previous_packet_number: row.get(0),
+                previous_name: row.get(1),
+                packet_number: row.get(2),
+                name: row.get(3),
+                session_time_ms: row.get(4),
+                gap_ms: row.get(5),
+            })
+            .collect())
+    }
+
+    async fn get_session_preview(&self, session_id: i32, packet_limit: i64, type_limit: i64) -> Result<SessionPreview> {
+        let summary_query = |order: &str| format!(
+            "SELECT packet_number, session_time_ms, direction, packet->>'name' \
+             FROM packets WHERE session_id = $1 ORDER BY packet_number {} LIMIT $2",
+            order
+        );
+        let to_summary = |row: &tokio_postgres::Row| PacketSummary {
+            packet_number: row.get(0),
+            session_time_ms: row.get(1),
+            direction: row.get(2),
+            name: row.get(3),
+        };
+
+        let first_packets = self
+            .client().await?
+            .query(&summary_query("ASC"), &[&session_id, &packet_limit])
+            .await
+            .context("Failed to query first packets")?
+            .iter()
+            .map(to_summary)
+            .collect();
+        let mut last_packets: Vec<PacketSummary> = self
+            .client().await?
+            .query(&summary_query("DESC"), &[&session_id, &packet_limit])
+            .await
+            .context("Failed to query last packets")?
+            .iter()
+            .map(to_summary)
+            .collect();
+        last_packets.reverse();
+
+        let top_packet_types = self
+            .client().await?
+            .query(
+                "SELECT COALESCE(packet->>'name', 'unknown'), COUNT(*) FROM packets \
+                 WHERE session_id = $1 GROUP BY 1 ORDER BY 2 DESC, 1 LIMIT $2",
+                &[&session_id, &type_limit],
+            )
+            .await
+            .context("Failed to query packet type counts")?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+
+        let milestone_packets: Vec<&str> = SESSION_MILESTONES.iter().map(|(_, packet)| *packet).collect();
+        let rows = self
+            .client().await?
+            .query(
+                "SELECT packet->>'name', MIN(packet_number), MIN(session_time_ms), COUNT(*) FROM packets \
+                 WHERE session_id = $1 AND direction = 'clientbound' AND packet->>'name' = ANY($2) \
+                 GROUP BY 1 ORDER BY 2",
+                &[&session_id, &milestone_packets],
+            )
+            .await
+            .context("Failed to query session milestones")?;
+        let milestones = rows
+            .iter()
+            .filter_map(|row| {
+                let packet_name: String = row.get(0);
+                let (milestone, _) = SESSION_MILESTONES.iter().find(|(_, packet)| *packet == packet_name)?;
+                Some(SessionMilestone {
+                    milestone,
+                    packet_number: row.get(1),
+                    session_time_ms: row.get(2),
+                    count: row.get(3),
+                })
+            })
+            .collect();
+
+        Ok(SessionPreview {
+            first_packets,
+            last_packets,
+            top_packet_types,
+            milestones,
+        })
+    }
+
+    async fn get_session_tags(&self, session_id: i32) -> Result<Vec<String>> {
         let rows = self
-            .client
+            .client().await?
             .query(
                 "SELECT tag FROM tag_maps WHERE session_id = $1 ORDER BY tag",
                 &[&session_id],
@@ -293,9 +1112,9 @@ impl Database {
         Ok(tags)
     }
 
-    pub async fn add_session_tag(&self, session_id: i32, tag: &str) -> Result<()> {
+    async fn add_session_tag(&self, session_id: i32, tag: &str) -> Result<()> {
         // First, ensure the tag exists in the tags table
-        self.client
+        self.client().await?
             .execute(
                 "INSERT INTO tags (tag) VALUES ($1) ON CONFLICT (tag) DO NOTHING",
                 &[&tag],
@@ -305,7 +1124,7 @@ impl Database {
 
         // Check if tag mapping already exists
         let exists = self
-            .client
+            .client().await?
             .query_one(
                 "SELECT COUNT(*) FROM tag_maps WHERE tag = $1 AND session_id = $2",
                 &[&tag, &session_id],
@@ -316,7 +1135,7 @@ impl Database {
         let count: i64 = exists.get(0);
         if count == 0 {
             // Create the tag mapping only if it doesn't exist
-            self.client
+            self.client().await?
                 .execute(
                     "INSERT INTO tag_maps (tag, session_id) VALUES ($1, $2)",
                     &[&tag, &session_id],
@@ -328,8 +1147,8 @@ impl Database {
         Ok(())
     }
 
-    pub async fn remove_session_tag(&self, session_id: i32, tag: &str) -> Result<()> {
-        self.client
+    async fn remove_session_tag(&self, session_id: i32, tag: &str) -> Result<()> {
+        self.client().await?
             .execute(
                 "DELETE FROM tag_maps WHERE session_id = $1 AND tag = $2",
                 &[&session_id, &tag],
@@ -340,9 +1159,9 @@ impl Database {
         Ok(())
     }
 
-    pub async fn get_all_tags(&self) -> Result<Vec<String>> {
+    async fn get_all_tags(&self) -> Result<Vec<String>> {
         let rows = self
-            .client
+            .client().await?
             .query(
                 "SELECT tag FROM tags ORDER BY tag",
                 &[],
@@ -358,16 +1177,443 @@ impl Database {
         Ok(tags)
     }
 
-    pub async fn delete_session(&self, session_id: i32) -> Result<()> {
-        // Delete session - CASCADE will automatically delete associated packets and tag_maps
-        self.client
+    async fn get_session_note(&self, session_id: i32) -> Result<Option<String>> {
+        let row = self.client().await?
+            .query_opt("SELECT note FROM session_notes WHERE session_id = $1", &[&session_id])
+            .await
+            .context("Failed to query session note")?;
+
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    async fn set_session_note(&self, session_id: i32, note: &str) -> Result<()> {
+        let client = self.client().await?;
+        if note.trim().is_empty() {
+            client
+                .execute("DELETE FROM session_notes WHERE session_id = $1", &[&session_id])
+                .await
+                .context("Failed to delete session note")?;
+        } else {
+            client
+                .execute(
+                    "INSERT INTO session_notes (session_id, note) VALUES ($1, $2)
+                     ON CONFLICT (session_id) DO UPDATE SET note = EXCLUDED.note, updated_at = CURRENT_TIMESTAMP",
+                    &[&session_id, &note],
+                )
+                .await
+                .context("Failed to save session note")?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_session_name(&self, session_id: i32) -> Result<Option<String>> {
+        let row = self.client().await?
+            .query_opt("SELECT name FROM session_names WHERE session_id = $1", &[&session_id])
+            .await
+            .context("Failed to query session name")?;
+
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    async fn set_session_name(&self, session_id: i32, name: &str) -> Result<()> {
+        let client = self.client().await?;
+        if name.trim().is_empty() {
+            client
+                .execute("DELETE FROM session_names WHERE session_id = $1", &[&session_id])
+                .await
+                .context("Failed to delete session name")?;
+        } else {
+            client
+                .execute(
+                    "INSERT INTO session_names (session_id, name) VALUES ($1, $2)
+                     ON CONFLICT (session_id) DO UPDATE SET name = EXCLUDED.name, updated_at = CURRENT_TIMESTAMP",
+                    &[&session_id, &name.trim()],
+                )
+                .await
+                .context("Failed to save session name")?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_session_archived(&self, session_id: i32, archived: bool) -> Result<()> {
+        let client = self.client().await?;
+        if archived {
+            client
+                .execute(
+                    "INSERT INTO archived_sessions (session_id) VALUES ($1) ON CONFLICT (session_id) DO NOTHING",
+                    &[&session_id],
+                )
+                .await
+                .context("Failed to archive session")?;
+        } else {
+            client
+                .execute("DELETE FROM archived_sessions WHERE session_id = $1", &[&session_id])
+                .await
+                .context("Failed to restore session")?;
+        }
+        Ok(())
+    }
+
+    async fn get_packet_notes(&self, session_id: i32) -> Result<BTreeMap<i64, String>> {
+        let rows = self.client().await?
+            .query("SELECT packet_number, note FROM packet_notes WHERE session_id = $1", &[&session_id])
+            .await
+            .context("Failed to query packet notes")?;
+
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    async fn set_packet_note(&self, session_id: i32, packet_number: i64, note: &str) -> Result<()> {
+        let client = self.client().await?;
+        if note.trim().is_empty() {
+            client
+                .execute(
+                    "DELETE FROM packet_notes WHERE session_id = $1 AND packet_number = $2",
+                    &[&session_id, &packet_number],
+                )
+                .await
+                .context("Failed to delete packet note")?;
+        } else {
+            client
+                .execute(
+                    "INSERT INTO packet_notes (session_id, packet_number, note) VALUES ($1, $2, $3)
+                     ON CONFLICT (session_id, packet_number) DO UPDATE SET note = EXCLUDED.note, updated_at = CURRENT_TIMESTAMP",
+                    &[&session_id, &packet_number, &note],
+                )
+                .await
+                .context("Failed to save packet note")?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_packet_bookmarks(&self, session_id: i32) -> Result<Vec<PacketBookmark>> {
+        let rows = self.client().await?
+            .query(
+                "SELECT packet_number, label, color FROM packet_bookmarks WHERE session_id = $1 ORDER BY packet_number",
+                &[&session_id],
+            )
+            .await
+            .context("Failed to query packet bookmarks")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PacketBookmark {
+                packet_number: row.get(0),
+                label: row.get(1),
+                color: BookmarkColor::from_name(row.get(2)),
+            })
+            .collect())
+    }
+
+    async fn set_packet_bookmark(&self, session_id: i32, bookmark: &PacketBookmark) -> Result<()> {
+        self.client().await?
             .execute(
-                "DELETE FROM sessions WHERE id = $1",
+                "INSERT INTO packet_bookmarks (session_id, packet_number, label, color) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (session_id, packet_number) DO UPDATE SET label = EXCLUDED.label, color = EXCLUDED.color",
+                &[&session_id, &bookmark.packet_number, &bookmark.label, &bookmark.color.name()],
+            )
+            .await
+            .context("Failed to save packet bookmark")?;
+
+        Ok(())
+    }
+
+    async fn delete_packet_bookmark(&self, session_id: i32, packet_number: i64) -> Result<()> {
+        self.client().await?
+            .execute(
+                "DELETE FROM packet_bookmarks WHERE session_id = $1 AND packet_number = $2",
+                &[&session_id, &packet_number],
+            )
+            .await
+            .context("Failed to delete packet bookmark")?;
+
+        Ok(())
+    }
+
+    async fn get_filter_presets(&self) -> Result<Vec<FilterPreset>> {
+        let rows = self.client().await?
+            .query("SELECT name, filter FROM filters ORDER BY name", &[])
+            .await
+            .context("Failed to query filter presets")?;
+
+        Ok(rows.iter().map(|row| FilterPreset { name: row.get(0), filter: row.get(1) }).collect())
+    }
+
+    async fn save_filter_preset(&self, name: &str, filter: &str) -> Result<()> {
+        self.client().await?
+            .execute(
+                "INSERT INTO filters (name, filter) VALUES ($1, $2)
+                 ON CONFLICT (name) DO UPDATE SET filter = EXCLUDED.filter, updated_at = CURRENT_TIMESTAMP",
+                &[&name, &filter],
+            )
+            .await
+            .context("Failed to save filter preset")?;
+
+        Ok(())
+    }
+
+    async fn delete_filter_preset(&self, name: &str) -> Result<()> {
+        self.client().await?
+            .execute("DELETE FROM filters WHERE name = $1", &[&name])
+            .await
+            .context("Failed to delete filter preset")?;
+
+        Ok(())
+    }
+
+    async fn get_ignored_fields(&self) -> Result<BTreeMap<String, BTreeSet<String>>> {
+        let rows = self.client().await?
+            .query("SELECT packet_name, path FROM compare_ignored_fields", &[])
+            .await
+            .context("Failed to query ignored fields")?;
+
+        let mut fields: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for row in rows {
+            fields.entry(row.get(0)).or_default().insert(row.get(1));
+        }
+        Ok(fields)
+    }
+
+    async fn add_ignored_field(&self, packet_name: &str, path: &str) -> Result<()> {
+        self.client().await?
+            .execute(
+                "INSERT INTO compare_ignored_fields (packet_name, path) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                &[&packet_name, &path],
+            )
+            .await
+            .context("Failed to save ignored field")?;
+
+        Ok(())
+    }
+
+    async fn remove_ignored_field(&self, packet_name: &str, path: &str) -> Result<()> {
+        self.client().await?
+            .execute(
+                "DELETE FROM compare_ignored_fields WHERE packet_name = $1 AND path = $2",
+                &[&packet_name, &path],
+            )
+            .await
+            .context("Failed to delete ignored field")?;
+
+        Ok(())
+    }
+
+    async fn get_filter_history(&self) -> Result<Vec<String>> {
+        let rows = self.client().await?
+            .query("SELECT filter FROM filter_history ORDER BY id", &[])
+            .await
+            .context("Failed to query filter history")?;
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn add_filter_history(&self, filter: &str) -> Result<()> {
+        let mut client = self.client().await?;
+        let transaction = client.0.transaction().await.context("Failed to start filter history transaction")?;
+        // Deleted and reinserted, so a reused filter moves to the end
+        transaction
+            .execute("DELETE FROM filter_history WHERE filter = $1", &[&filter])
+            .await
+            .context("Failed to save filter history")?;
+        transaction
+            .execute("INSERT INTO filter_history (filter) VALUES ($1)", &[&filter])
+            .await
+            .context("Failed to save filter history")?;
+        transaction
+            .execute(
+                "DELETE FROM filter_history WHERE id NOT IN (SELECT id FROM filter_history ORDER BY id DESC LIMIT $1)",
+                &[&FILTER_HISTORY_LIMIT],
+            )
+            .await
+            .context("Failed to trim filter history")?;
+        transaction.commit().await.context("Failed to save filter history")?;
+
+        Ok(())
+    }
+
+    async fn delete_session(&self, session_id: i32) -> Result<DeletedSession> {
+        // Deleted explicitly (rather than by ON DELETE CASCADE) to report what went, all or nothing
+        let mut client = self.client().await?;
+        let transaction = client.0.transaction().await.context("Failed to start delete transaction")?;
+        let tag_maps = transaction
+            .execute(
+                "DELETE FROM tag_maps WHERE session_id = $1 OR packet_id IN (SELECT id FROM packets WHERE session_id = $1)",
                 &[&session_id],
             )
             .await
+            .context("Failed to delete session tags")?;
+        let session_notes = transaction
+            .execute("DELETE FROM session_notes WHERE session_id = $1", &[&session_id])
+            .await
+            .context("Failed to delete session note")?;
+        let packet_notes = transaction
+            .execute("DELETE FROM packet_notes WHERE session_id = $1", &[&session_id])
+            .await
+            .context("Failed to delete packet notes")?;
+        let bookmarks = transaction
+            .execute("DELETE FROM packet_bookmarks WHERE session_id = $1", &[&session_id])
+            .await
+            .context("Failed to delete packet bookmarks")?;
+        let packets = transaction
+            .execute("DELETE FROM packets WHERE session_id = $1", &[&session_id])
+            .await
+            .context("Failed to delete session packets")?;
+        let sessions = transaction
+            .execute("DELETE FROM sessions WHERE id = $1", &[&session_id])
+            .await
             .context("Failed to delete session")?;
+        if sessions == 0 {
+            return Err(anyhow!("Session {} not found", session_id));
+        }
+        transaction.commit().await.context("Failed to commit session delete")?;
+
+        Ok(DeletedSession { packets, tag_maps, notes: session_notes + packet_notes, bookmarks })
+    }
+
+    async fn create_session(&self) -> Result<i32> {
+        let row = self
+            .client().await?
+            .query_one(
+                "INSERT INTO sessions (started_at) VALUES (NOW()) RETURNING id",
+                &[],
+            )
+            .await
+            .context("Failed to create session")?;
+
+        Ok(row.get(0))
+    }
+
+    async fn end_session(&self, session_id: i32) -> Result<()> {
+        self.client().await?
+            .execute("UPDATE sessions SET ended_at = NOW() WHERE id = $1", &[&session_id])
+            .await
+            .context("Failed to end session")?;
 
         Ok(())
     }
+
+    async fn insert_session(&self, started_at: DateTime<Utc>, ended_at: Option<DateTime<Utc>>) -> Result<i32> {
+        let row = self
+            .client().await?
+            .query_one(
+                "INSERT INTO sessions (started_at, ended_at) VALUES ($1, $2) RETURNING id",
+                &[&started_at.naive_utc(), &ended_at.map(|ts| ts.naive_utc())],
+            )
+            .await
+            .context("Failed to create session")?;
+
+        Ok(row.get(0))
+    }
+
+    async fn insert_packet(&self, session_id: i32, packet: &NewDbPacket) -> Result<()> {
+        self.client().await?
+            .execute(
+                "INSERT INTO packets (session_id, ts, session_time_ms, packet_number, server_version, direction, packet) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                &[
+                    &session_id,
+                    &packet.ts.naive_utc(),
+                    &packet.session_time_ms,
+                    &packet.packet_number,
+                    &packet.server_version,
+                    &packet.direction,
+                    &Json(&packet.packet),
+                ],
+            )
+            .await
+            .context("Failed to insert packet")?;
+
+        Ok(())
+    }
+
+    async fn insert_packets(&self, session_id: i32, packets: &[NewDbPacket]) -> Result<()> {
+        if packets.is_empty() {
+            return Ok(());
+        }
+        let client = self.client().await?;
+        let sink = client.0
+            .copy_in("COPY packets (session_id, ts, session_time_ms, packet_number, server_version, direction, packet) FROM STDIN BINARY")
+            .await
+            .context("Failed to start packet COPY")?;
+        let writer = BinaryCopyInWriter::new(
+            sink,
+            &[Type::INT4, Type::TIMESTAMP, Type::INT8, Type::INT8, Type::VARCHAR, Type::VARCHAR, Type::JSONB],
+        );
+        futures::pin_mut!(writer);
+        for packet in packets {
+            writer
+                .as_mut()
+                .write(&[
+                    &session_id,
+                    &packet.ts.naive_utc(),
+                    &packet.session_time_ms,
+                    &packet.packet_number,
+                    &packet.server_version,
+                    &packet.direction,
+                    &Json(&packet.packet),
+                ])
+                .await
+                .with_context(|| format!("Failed to copy packet {}", packet.packet_number))?;
+        }
+        writer.finish().await.context("Failed to finish packet COPY")?;
+
+        Ok(())
+    }
+
+    async fn health(&self) -> Result<DbHealth> {
+        let client = self.client().await?;
+        let mut tables = Vec::new();
+        for &table in HEALTH_TABLES {
+            let rows = client
+                .query_one(&table_rows_query(table), &[])
+                .await
+                .with_context(|| format!("Failed to count {} rows", table))?;
+            // Tables missing before migrations report no size instead of failing
+            let size = client
+                .query_one("SELECT pg_total_relation_size(to_regclass($1))", &[&table])
+                .await
+                .with_context(|| format!("Failed to query the size of {}", table))?;
+            tables.push(TableHealth {
+                name: table.to_string(),
+                rows: rows.get::<_, Option<i64>>(0).unwrap_or(0),
+                bytes: size.get(0),
+            });
+        }
+        let database_bytes: i64 = client
+            .query_one("SELECT pg_database_size(current_database())", &[])
+            .await
+            .context("Failed to query the database size")?
+            .get(0);
+        let range = client.query_one(SESSION_RANGE_QUERY, &[]).await.context("Failed to query session times")?;
+        let utc = |ts: Option<chrono::NaiveDateTime>| ts.map(|ts| DateTime::from_naive_utc_and_offset(ts, Utc));
+
+        Ok(DbHealth {
+            status: self.connection_status(),
+            database_bytes: Some(database_bytes),
+            tables,
+            oldest_session: utc(range.get(0)),
+            newest_session: utc(range.get(1)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_migrations() {
+        let runner = embedded::migrations::runner();
+        // Embedded in directory order; the runner applies them by version
+        let mut migrations = runner.get_migrations().clone();
+        migrations.sort_by_key(|migration| migration.version());
+        assert_eq!(migrations[0].to_string(), "V1__initial_schema");
+        // Versions are consecutive, so a missing or misnamed file is caught at test time
+        for (index, migration) in migrations.iter().enumerate() {
+            assert_eq!(migration.version() as usize, index + 1);
+        }
+    }
 }
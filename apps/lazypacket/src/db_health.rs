@@ -0,0 +1,82 @@
+// `health` subcommand and the TUI database panel: connection status, database and table sizes, row counts
+// and the oldest/newest session, from Storage::health
+
+use crate::db::DbHealth;
+use crate::storage::{self, ConnectionStatus};
+use anyhow::Result;
+use std::io::{self, BufWriter, Write};
+
+/// 1536 -> "1.5 KiB"
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Summary lines, then one aligned line per table
+pub fn format_health(health: &DbHealth) -> Vec<String> {
+    let status = match &health.status {
+        ConnectionStatus::Connected => "connected".to_string(),
+        ConnectionStatus::Reconnecting { attempt, last_error } => {
+            format!("reconnecting (attempt {} failed: {})", attempt, last_error)
+        }
+        ConnectionStatus::Unavailable { last_error } => format!("unavailable ({})", last_error),
+    };
+    let time = |ts: Option<chrono::DateTime<chrono::Utc>>| {
+        ts.map(|ts| ts.format("%Y-%m-%d %H:%M:%S UTC").to_string()).unwrap_or_else(|| "-".to_string())
+    };
+
+    let mut lines = vec![
+        format!("Connection:     {}", status),
+        format!("Database size:  {}", health.database_bytes.map(format_bytes).unwrap_or_else(|| "-".to_string())),
+        format!("Oldest session: {}", time(health.oldest_session)),
+        format!("Newest session: {}", time(health.newest_session)),
+        String::new(),
+    ];
+    let name_width = health.tables.iter().map(|t| t.name.len()).max().unwrap_or(0).max("table".len());
+    lines.push(format!("{:<name_width$}  {:>12}  {:>10}", "table", "rows", "size"));
+    for table in &health.tables {
+        lines.push(format!(
+            "{:<name_width$}  {:>12}  {:>10}",
+            table.name,
+            table.rows,
+            table.bytes.map(format_bytes).unwrap_or_else(|| "-".to_string())
+        ));
+    }
+    lines
+}
+
+/// `health`
+pub async fn health(_args: &[String]) -> Result<()> {
+    let db = storage::connect().await?;
+    let health = db.health().await?;
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for line in format_health(&health) {
+        writeln!(out, "{}", line)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
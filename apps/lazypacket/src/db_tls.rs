@@ -0,0 +1,187 @@
+// TLS for PostgreSQL connections (rustls), configured like libpq's sslmode:
+//   DB_SSL_MODE              - disable (default), require (encrypted, server certificate not checked) or
+//                              verify-full (encrypted, certificate chain and host name checked)
+//   DB_SSL_ROOT_CERT         - PEM file of CA certificates trusted by verify-full (default: Mozilla's root store)
+//   DB_SSL_CERT / DB_SSL_KEY - PEM client certificate and private key, for servers that ask for one
+
+use anyhow::{anyhow, Context, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SslMode {
+    #[default]
+    Disable,
+    Require,
+    VerifyFull,
+}
+
+impl SslMode {
+    pub fn parse(text: &str) -> Result<Self> {
+        match text {
+            "disable" => Ok(Self::Disable),
+            "require" => Ok(Self::Require),
+            "verify-full" => Ok(Self::VerifyFull),
+            _ => Err(anyhow!("Invalid DB_SSL_MODE: {} (expected disable, require or verify-full)", text)),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Disable => "disable",
+            Self::Require => "require",
+            Self::VerifyFull => "verify-full",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsSettings {
+    pub mode: SslMode,
+    pub root_cert: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+}
+
+impl TlsSettings {
+    pub fn from_env() -> Result<Self> {
+        let path = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty()).map(PathBuf::from);
+        let settings = Self {
+            mode: match std::env::var("DB_SSL_MODE") {
+                Ok(mode) if !mode.is_empty() => SslMode::parse(&mode)?,
+                _ => SslMode::Disable,
+            },
+            root_cert: path("DB_SSL_ROOT_CERT"),
+            client_cert: path("DB_SSL_CERT"),
+            client_key: path("DB_SSL_KEY"),
+        };
+        if settings.client_cert.is_some() != settings.client_key.is_some() {
+            return Err(anyhow!("DB_SSL_CERT and DB_SSL_KEY must be set together"));
+        }
+        Ok(settings)
+    }
+
+    /// Connector for tokio-postgres, or None when TLS is disabled
+    pub fn connector(&self) -> Result<Option<MakeRustlsConnect>> {
+        if self.mode == SslMode::Disable {
+            return Ok(None);
+        }
+
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .context("Failed to configure TLS")?;
+        let builder = match self.mode {
+            SslMode::Require => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AcceptAnyServerCert(provider.signature_verification_algorithms))),
+            _ => builder.with_root_certificates(self.root_store()?),
+        };
+        let config = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => builder
+                .with_client_auth_cert(read_certs(cert)?, read_key(key)?)
+                .context("Invalid DB_SSL_CERT / DB_SSL_KEY")?,
+            _ => builder.with_no_client_auth(),
+        };
+        Ok(Some(MakeRustlsConnect::new(config)))
+    }
+
+    fn root_store(&self) -> Result<RootCertStore> {
+        let mut store = RootCertStore::empty();
+        match &self.root_cert {
+            Some(path) => {
+                let (added, _) = store.add_parsable_certificates(read_certs(path)?);
+                if added == 0 {
+                    return Err(anyhow!("No usable certificates in DB_SSL_ROOT_CERT {}", path.display()));
+                }
+            }
+            None => store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+        }
+        Ok(store)
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid PEM certificates in {}", path.display()))
+}
+
+fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .with_context(|| format!("Invalid PEM private key in {}", path.display()))?
+        .ok_or_else(|| anyhow!("No private key in {}", path.display()))
+}
+
+/// sslmode=require: the connection is encrypted, but the server's certificate isn't checked
+/// Handshake signatures are still verified, so the server must hold the certificate's key
+#[derive(Debug)]
+struct AcceptAnyServerCert(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for AcceptAnyServerCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connector() {
+        assert_eq!(SslMode::parse("verify-full").unwrap(), SslMode::VerifyFull);
+        assert!(SslMode::parse("prefer").is_err());
+
+        assert!(TlsSettings::default().connector().unwrap().is_none());
+        for mode in [SslMode::Require, SslMode::VerifyFull] {
+            let settings = TlsSettings { mode, ..Default::default() };
+            assert!(settings.connector().unwrap().is_some());
+        }
+
+        let missing = TlsSettings {
+            mode: SslMode::VerifyFull,
+            root_cert: Some(PathBuf::from("/nonexistent/root.crt")),
+            ..Default::default()
+        };
+        assert!(missing.connector().is_err());
+    }
+}
@@ -0,0 +1,150 @@
+// Decode errors panel (`D` in the packet view): packets of a session that the decoder gave up on, to find the gaps
+// in the protocol definitions. A field decoding broke on holds a "[decode_error: ...]" string in the stored JSON,
+// reported with its path. Log entries stored without a name (`unknown`, with their bytes in params.data) are
+// decoded again with the current definitions for their version, which either name them now or say where they break
+
+use crate::db::{DbPacket, PacketSearch};
+use crate::packet_logger::{decode_hex, PacketDirection};
+use crate::protocol::ProtocolParser;
+use crate::protocol_registry::ProtocolRegistry;
+use crate::storage::{PageCursor, Storage};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const SEARCH_PAGE_SIZE: i64 = 1000;
+const DECODE_ERROR_PREFIX: &str = "[decode_error: ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeProblem {
+    pub packet_number: i64,
+    pub direction: String,
+    pub name: String,
+    pub message: String, // Where and why decoding failed, or what decoding it again gives
+}
+
+/// Packets of a session with decode problems, in packet order
+pub async fn load_decode_problems(db: &dyn Storage, session_id: i32, protocols: &ProtocolRegistry) -> Result<Vec<DecodeProblem>> {
+    // Found by searching the stored JSON, then checked packet by packet
+    let searches = [
+        PacketSearch::Text(DECODE_ERROR_PREFIX.trim().to_string()),
+        PacketSearch::Contains(json!({ "name": "unknown" })),
+    ];
+    let mut packets = BTreeMap::new();
+    for search in &searches {
+        let mut cursor = PageCursor::After(None);
+        loop {
+            let page = db.search_packets(session_id, None, search, cursor, SEARCH_PAGE_SIZE).await?;
+            let Some(last) = page.last().map(|packet| packet.packet_number) else {
+                break;
+            };
+            let full = page.len() as i64 == SEARCH_PAGE_SIZE;
+            packets.extend(page.into_iter().map(|packet| (packet.packet_number, packet)));
+            if !full {
+                break;
+            }
+            cursor = PageCursor::After(Some(last));
+        }
+    }
+
+    Ok(packets
+        .into_values()
+        .filter_map(|packet| {
+            let parser = protocols.get(&packet.server_version);
+            decode_problem(&packet, parser.as_deref())
+        })
+        .collect())
+}
+
+fn decode_problem(packet: &DbPacket, parser: Option<&ProtocolParser>) -> Option<DecodeProblem> {
+    let name = packet.packet["name"].as_str().unwrap_or("unknown");
+    let message = if name == "unknown" {
+        let direction = match packet.direction.as_str() {
+            "clientbound" => PacketDirection::Clientbound,
+            _ => PacketDirection::Serverbound,
+        };
+        decode_again(&packet.packet["params"]["data"], direction, parser)
+    } else {
+        let (path, error) = find_decode_error(&packet.packet["params"], "params")?;
+        format!("{}: {}", path, error)
+    };
+    Some(DecodeProblem {
+        packet_number: packet.packet_number,
+        direction: packet.direction.clone(),
+        name: name.to_string(),
+        message,
+    })
+}
+
+/// What decoding a packet stored without a name gives with the current definitions
+fn decode_again(data: &Value, direction: PacketDirection, parser: Option<&ProtocolParser>) -> String {
+    let Some(data) = data.as_str().and_then(|hex| decode_hex(hex).ok()) else {
+        return "Not decoded, and its bytes weren't stored".to_string();
+    };
+    let Some(parser) = parser else {
+        return "Not decoded, and there are no protocol definitions for its version".to_string();
+    };
+    let decoded = parser.decode_packet(&data, direction);
+    match (decoded.packet_name, decoded.error, decoded.packet_id) {
+        (Some(name), Some(error), _) => format!("Decoded again as {}, which fails at {}", name, error),
+        (Some(name), None, _) => format!("Decodes as {} with the current definitions", name),
+        (None, _, Some(id)) => format!("No {:?} packet with id {} (0x{:02x}) is defined", direction, id, id),
+        (None, _, None) => "No packet id in its bytes".to_string(),
+    }
+}
+
+/// Path and message of the first "[decode_error: ...]" value under a JSON value
+fn find_decode_error(value: &Value, path: &str) -> Option<(String, String)> {
+    match value {
+        Value::String(text) => {
+            let error = text.strip_prefix(DECODE_ERROR_PREFIX)?;
+            Some((path.to_string(), error.strip_suffix(']').unwrap_or(error).to_string()))
+        }
+        Value::Object(fields) => fields.iter().find_map(|(key, value)| find_decode_error(value, &format!("{}.{}", path, key))),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(index, value)| find_decode_error(value, &format!("{}[{}]", path, index))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn db_packet(packet_number: i64, packet: Value) -> DbPacket {
+        DbPacket {
+            id: 0,
+            session_id: 1,
+            ts: Utc::now(),
+            session_time_ms: 0,
+            packet_number,
+            server_version: "1.21.111".to_string(),
+            direction: "clientbound".to_string(),
+            packet,
+        }
+    }
+
+    #[test]
+    fn test_decode_problem() {
+        let broken = db_packet(3, json!({
+            "name": "inventory_content",
+            "params": { "window_id": 0, "input": [{ "network_id": 1 }, "[decode_error: unexpected end of data]"] }
+        }));
+        let problem = decode_problem(&broken, None).unwrap();
+        assert_eq!(problem.name, "inventory_content");
+        assert_eq!(problem.message, "params.input[1]: unexpected end of data");
+
+        let unknown = db_packet(4, json!({ "name": "unknown", "params": { "data": "fe01" } }));
+        assert_eq!(
+            decode_problem(&unknown, None).unwrap().message,
+            "Not decoded, and there are no protocol definitions for its version"
+        );
+
+        // A search match whose text only mentions decode errors isn't one
+        let chat = db_packet(5, json!({ "name": "text", "params": { "message": "what's a [decode_error: x]?" } }));
+        assert_eq!(decode_problem(&chat, None), None);
+    }
+}
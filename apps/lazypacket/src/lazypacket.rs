@@ -1,10 +1,24 @@
-mod packet_logger;
-mod protocol;
-mod db;
+mod packet_dump;
+mod test_export;
+mod parquet_export;
+mod packet_export;
+mod packet_stats;
+mod movement_map;
+mod packet_latency;
+mod decode_errors;
+mod version_switch;
+mod packet_watch;
+mod packet_playback;
+mod sequence_diagram;
+mod db_health;
+mod viewer_config;
+mod theme;
 
+// Everything else is the library's (src/lib.rs), so its types are the same ones here and in the modules above
+use lazypacket::*;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use packet_logger::{PacketDirection, PacketEntry};
@@ -13,23 +27,68 @@ use ratatui::{
     layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    symbols::Marker,
+    widgets::canvas::{Canvas, Line as CanvasLine},
+    widgets::{Block, Cell, List, ListItem, Paragraph, Row, Table, Wrap},
     Frame, Terminal,
 };
 use serde_json;
 use std::io;
-use std::collections::{BTreeMap, BTreeSet, HashSet};
-use db::{Database, Session as DbSession, DbPacketFilterSet, DbPacketFilter};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use db::{ArchivedSessions, BookmarkColor, DbFieldCondition, DbFieldOp, DbPacket, DbPacketFilterSet, DbPacketFilter, FilterPreset, PacketBookmark, PacketGap, PacketRateBucket, PacketSearch, PacketTypeStats, PacketSummary, SessionPreview, SessionSearch, SessionSummary, DbHealth, session_title, ENTITY_ID_KEYS};
+use storage::{ConnectionStatus, PageCursor, Storage};
+use log_index::LogIndex;
+use packet_stats::{format_stats_table, sort_stats, StatsSort};
+use theme::Theme;
+
+// Session preview pane: packets shown at each end of the session, and most frequent packet types
+const PREVIEW_PACKETS: i64 = 5;
+const PREVIEW_PACKET_TYPES: i64 = 8;
+
+// Packets are fetched a page at a time around the current packet (keyset pagination on packet_number),
+// so multi-hour captures never have to be loaded in full
+const PACKET_PAGE_SIZE: usize = 1000;
+const MAX_LOADED_PACKETS: usize = 3 * PACKET_PAGE_SIZE; // Packets farthest from the current one are dropped beyond this
+const LOAD_MARGIN: usize = 100; // Packets kept loaded on each side of the current one (covers the timeline)
+const LIVE_POLL_INTERVAL: Duration = Duration::from_secs(1); // How often a session still being captured is checked for new packets
+const PACKET_LIST_WIDTH: u16 = 60; // Columns taken by the packet list beside the details, when the terminal is wide enough
+const HISTOGRAM_ROWS: u16 = 3; // Height of the packet rate histogram that can replace the timeline strip
+const GAP_LIMIT: i64 = 20; // Gaps listed in the gaps panel (G), largest first
+const SEQUENCE_PACKETS: i64 = 200; // Packets a sequence diagram (T) is drawn for, at most
+const SEQUENCE_COPY_WIDTH: usize = 48; // Characters between the lanes of a copied sequence diagram
+const BAR_EIGHTHS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
 
 struct SessionLog {
     session_id: i32,
-    packets: Vec<PacketEntry>,
+    filter: Option<DbPacketFilterSet>,
+    packets: Vec<PacketEntry>, // Loaded window of the filtered packets
+    window_start: usize, // Index of packets[0] among all filtered packets
+    total: usize, // Number of filtered packets in the session
     start_time: i64,
     protocol_version: Option<String>,
+    notes: BTreeMap<i64, String>, // Packet notes by packet_number
+    bookmarks: BTreeMap<i64, PacketBookmark>, // By packet_number
+    live: bool, // Whether the session is still being captured (it has no ended_at)
+    rate: Option<Vec<PacketRateBucket>>, // Packets per second of the filtered packets, loaded for the histogram
+    packet_types: Option<Vec<PacketTypeStats>>, // Packet names and directions in the whole session, loaded for filter completion
+    log_file: Option<LogFile>, // Log file the packets are read from instead of the database (`view <log>`)
+}
+
+// A packet log opened with `view`, read a page at a time by seeking through its `.idx` sidecar. Its packets are
+// numbered by their position in the log, and decoded as they're read
+struct LogFile {
+    name: String, // File name, for the header
+    index: LogIndex,
+    parser: Option<Arc<protocol::ProtocolParser>>, // For the log's protocol version
+    protocol_version: String, // For entries logged without one
 }
 
 impl PacketFilterSet {
-    fn to_db_filter_set(&self) -> DbPacketFilterSet {
+    /// `session_start` (ms) anchors the filters' relative time ranges
+    fn to_db_filter_set(&self, session_start: i64) -> DbPacketFilterSet {
+        let at = |offset_ms: Option<i64>| offset_ms.and_then(|ms| DateTime::from_timestamp_millis(session_start + ms));
         DbPacketFilterSet {
             filters: self.filters.iter().map(|f| {
                 DbPacketFilter {
@@ -39,7 +98,14 @@ impl PacketFilterSet {
                     }),
                     packet_name: f.packet_name.clone(),
                     packet_name_is_wildcard: f.packet_name_is_wildcard,
+                    packet_name_is_regex: f.packet_name_is_regex,
                     is_exclusion: f.is_exclusion,
+                    ts_from: at(f.time_from_ms),
+                    ts_to: at(f.time_to_ms),
+                    packet_number_from: f.packet_number_from,
+                    packet_number_to: f.packet_number_to,
+                    fields: f.fields.clone(),
+                    entity: f.entity,
                 }
             }).collect(),
         }
@@ -53,77 +119,781 @@ impl PacketFilterSet {
                 Some(FilterPacketDirection::Serverbound) => "s",
                 None => "a",
             };
-            if let Some(ref name) = f.packet_name {
-                format!("{}{}.{}", prefix, dir_str, name)
-            } else {
-                format!("{}{}", prefix, dir_str)
+            let mut parts = vec![format!("{}{}", prefix, dir_str)];
+            match f.packet_name {
+                Some(ref name) if f.packet_name_is_regex => parts.push(format!("/{}/", name)),
+                Some(ref name) => parts.push(name.clone()),
+                None => {}
+            }
+            if f.time_from_ms.is_some() || f.time_to_ms.is_some() {
+                parts.push(format!(
+                    "@{}-{}",
+                    f.time_from_ms.map(format_filter_time).unwrap_or_default(),
+                    f.time_to_ms.map(format_filter_time).unwrap_or_default()
+                ));
+            }
+            match (f.packet_number_from, f.packet_number_to) {
+                (None, None) => {}
+                (Some(from), Some(to)) if from == to => parts.push(format!("#{}", from)),
+                (from, to) => parts.push(format!(
+                    "#{}-{}",
+                    from.map(|n| n.to_string()).unwrap_or_default(),
+                    to.map(|n| n.to_string()).unwrap_or_default()
+                )),
             }
+            if let Some(entity) = f.entity {
+                parts.push(format!("entity:{}", entity));
+            }
+            if !f.fields.is_empty() {
+                let conditions: Vec<String> = f.fields.iter()
+                    .map(|field| format!("{}{}{}", field.path.join("."), field.op.symbol(), field.value))
+                    .collect();
+                parts.push(format!("${}", conditions.join("&")));
+            }
+            parts.join(".")
         }).collect::<Vec<_>>().join(",")
     }
 }
 
-impl SessionLog {
-    async fn load(db: &Database, session_id: i32, filter: Option<PacketFilterSet>) -> Result<Self> {
-        let db_filter_set = filter.as_ref().map(|f| f.to_db_filter_set());
-        let db_packets = db.get_packets(session_id, db_filter_set.as_ref()).await?;
+/// The database for an action that queries it (filters, searches, notes, bookmarks and the panels loaded from it);
+/// there's none when a log file is opened with `view`
+fn database(db: Option<&dyn Storage>) -> Result<&dyn Storage> {
+    db.ok_or_else(|| anyhow::anyhow!("Not available for a log file - import it with import-log first"))
+}
 
-        if db_packets.is_empty() {
-            return Err(anyhow::anyhow!("No packets found for session {}", session_id));
+/// Filter time like 10s, 1.5m, 250ms or 2h (plain numbers are seconds), in milliseconds
+fn parse_filter_time(text: &str) -> Option<i64> {
+    let (number, unit_ms) = if let Some(number) = text.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, 1000.0)
+    } else if let Some(number) = text.strip_suffix('m') {
+        (number, 60_000.0)
+    } else if let Some(number) = text.strip_suffix('h') {
+        (number, 3_600_000.0)
+    } else {
+        (text, 1000.0)
+    };
+    let value: f64 = number.parse().ok()?;
+    (value.is_finite() && value >= 0.0).then(|| (value * unit_ms).round() as i64)
+}
+
+// Where `g` goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GotoTarget {
+    Packet(i64), // Packet number
+    SessionTime(i64), // Milliseconds since the session's first packet
+    Offset(i64), // Milliseconds from the current packet (negative goes back)
+}
+
+impl GotoTarget {
+    /// `1234` or `#1234` (packet number), `@90s` (session time), `+5s` or `-500ms` (from the current packet)
+    fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let invalid = || anyhow::anyhow!("Go to: expected a packet number, @time or +/-time, got '{}'", input);
+        if let Some(time) = input.strip_prefix('@') {
+            return parse_filter_time(time).map(GotoTarget::SessionTime).ok_or_else(invalid);
+        }
+        if let Some(time) = input.strip_prefix('+') {
+            return parse_filter_time(time).map(GotoTarget::Offset).ok_or_else(invalid);
         }
+        if let Some(time) = input.strip_prefix('-') {
+            return parse_filter_time(time).map(|ms| GotoTarget::Offset(-ms)).ok_or_else(invalid);
+        }
+        input.strip_prefix('#').unwrap_or(input).parse().map(GotoTarget::Packet).map_err(|_| invalid())
+    }
+}
 
-        let mut packets = Vec::new();
-        let mut start_time = None;
-        let mut protocol_version = None;
-
-        for db_packet in db_packets {
-            // Convert database packet to PacketEntry
-            let direction = match db_packet.direction.as_str() {
-                "clientbound" => PacketDirection::Clientbound,
-                "serverbound" => PacketDirection::Serverbound,
-                _ => {
-                    return Err(anyhow::anyhow!("Invalid direction: {}", db_packet.direction));
-                }
-            };
+fn format_filter_time(ms: i64) -> String {
+    if ms % 1000 == 0 {
+        format!("{}s", ms / 1000)
+    } else {
+        format!("{}ms", ms)
+    }
+}
+
+/// Index of the slash closing the /regex/ that `text` starts with; a backslash escapes the character after it
+fn closing_slash(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '/' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a filter set at the commas between filters, leaving those inside /regex/ packet names and {conditions}
+fn split_filters(input: &str) -> Vec<&str> {
+    let mut filters = Vec::new();
+    let mut start = 0;
+    let mut skip_to = 0;
+    for (i, c) in input.char_indices() {
+        if i < skip_to {
+            continue;
+        }
+        let end = match c {
+            '/' if input[..i].ends_with('.') => closing_slash(&input[i..]),
+            '{' => input[i..].find('}'),
+            _ => None,
+        };
+        if let Some(end) = end {
+            skip_to = i + end + 1;
+            continue;
+        }
+        if c == ',' {
+            filters.push(&input[start..i]);
+            start = i + 1;
+        }
+    }
+    filters.push(&input[start..]);
+    filters
+}
+
+/// Split a filter at its periods; one followed by a digit is a decimal point (@1.5s-2.5s), not a separator,
+/// field conditions ($...) run to the end of the filter since their paths contain periods, and a /regex/
+/// packet name runs to its closing slash, and {conditions} to their closing brace
+fn split_filter_parts(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut skip_to = 0;
+    for (i, c) in text.char_indices() {
+        if i < skip_to {
+            continue;
+        }
+        if i == start && c == '$' {
+            break;
+        }
+        let end = match c {
+            '/' if i == start => closing_slash(&text[i..]),
+            '{' => text[i..].find('}'),
+            _ => None,
+        };
+        if let Some(end) = end {
+            skip_to = i + end + 1;
+            continue;
+        }
+        if c == '.' && !text.as_bytes().get(i + 1).is_some_and(|b| b.is_ascii_digit()) {
+            parts.push(&text[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Field condition like params.runtime_id=42, params.position.y>=64 or params.message~*hello*
+/// Operators: = != < <= > >= and ~ (case-insensitive match, * as wildcard); quotes around the value are optional
+fn parse_field_condition(text: &str) -> Option<DbFieldCondition> {
+    let op_start = text.find(['=', '!', '<', '>', '~'])?;
+    let (path, rest) = text.split_at(op_start);
+    let (op, value) = [
+        ("!=", DbFieldOp::Ne),
+        ("<=", DbFieldOp::Le),
+        (">=", DbFieldOp::Ge),
+        ("=", DbFieldOp::Eq),
+        ("<", DbFieldOp::Lt),
+        (">", DbFieldOp::Gt),
+        ("~", DbFieldOp::Like),
+    ]
+    .into_iter()
+    .find_map(|(symbol, op)| rest.strip_prefix(symbol).map(|value| (op, value)))?;
+
+    let path: Vec<String> = path.trim().split('.').map(|key| key.to_string()).collect();
+    if path.iter().any(|key| key.is_empty()) {
+        return None;
+    }
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    let numeric = matches!(op, DbFieldOp::Lt | DbFieldOp::Le | DbFieldOp::Gt | DbFieldOp::Ge);
+    if numeric && value.parse::<f64>().is_err() {
+        return None;
+    }
+    Some(DbFieldCondition { path, op, value: value.to_string() })
+}
+
+/// `filter` with its inclusion filters limited to one direction (or to none), leaving exclusions as they are;
+/// with `direction` already the only one shown, the direction is cleared instead
+fn with_direction(filter: Option<&PacketFilterSet>, direction: Option<FilterPacketDirection>) -> Option<PacketFilterSet> {
+    let mut filters = filter.map(|f| f.filters.clone()).unwrap_or_default();
+    let has_inclusions = filters.iter().any(|f| !f.is_exclusion);
+    let direction = if has_inclusions && filters.iter().filter(|f| !f.is_exclusion).all(|f| f.direction == direction) {
+        None
+    } else {
+        direction
+    };
+    if has_inclusions {
+        for f in filters.iter_mut().filter(|f| !f.is_exclusion) {
+            f.direction = direction;
+        }
+    } else if direction.is_some() {
+        // Only exclusions (or nothing): everything else, sent that way
+        filters.insert(0, PacketFilter { direction, ..Default::default() });
+    }
+    simplified_filter(filters)
+}
+
+/// `filter` with its inclusion filters limited to the packets referencing an entity, or no longer limited to
+/// one, leaving exclusions as they are
+fn with_entity(filter: Option<&PacketFilterSet>, entity: Option<i64>) -> Option<PacketFilterSet> {
+    let mut filters = filter.map(|f| f.filters.clone()).unwrap_or_default();
+    if filters.iter().any(|f| !f.is_exclusion) {
+        for f in filters.iter_mut().filter(|f| !f.is_exclusion) {
+            f.entity = entity;
+        }
+    } else if entity.is_some() {
+        filters.insert(0, PacketFilter { entity, ..Default::default() });
+    }
+    simplified_filter(filters)
+}
+
+/// The entity id followed by a filter, if any
+fn followed_entity(filter: Option<&PacketFilterSet>) -> Option<i64> {
+    filter?.filters.iter().find_map(|f| f.entity)
+}
+
+/// A filter set without the filters that became the same as an earlier one, or inclusions that match everything
+/// (no filter at all)
+fn simplified_filter(filters: Vec<PacketFilter>) -> Option<PacketFilterSet> {
+    let mut kept: Vec<PacketFilter> = Vec::new();
+    for f in filters {
+        if !kept.contains(&f) && (f.is_exclusion || f != PacketFilter::default()) {
+            kept.push(f);
+        }
+    }
+    (!kept.is_empty()).then_some(PacketFilterSet { filters: kept })
+}
+
+/// An entity id as stored in packet JSON: a number, or a string for 64-bit ids
+fn entity_id_value(value: &serde_json::Value) -> Option<i64> {
+    value.as_i64().or_else(|| value.as_str()?.parse().ok())
+}
+
+/// Path (keys from the packet root) of the first key, depth first, whose value satisfies `matches`
+fn find_json_key(
+    value: &serde_json::Value,
+    matches: &dyn Fn(&str, &serde_json::Value) -> bool,
+    path: &mut Vec<String>,
+) -> Option<String> {
+    let children: Box<dyn Iterator<Item = (String, &serde_json::Value)>> = match value {
+        serde_json::Value::Object(map) => Box::new(map.iter().map(|(key, child)| (key.clone(), child))),
+        serde_json::Value::Array(items) => Box::new(items.iter().enumerate().map(|(i, child)| (i.to_string(), child))),
+        _ => return None,
+    };
+    for (key, child) in children {
+        path.push(key);
+        if matches(path.last().unwrap(), child) {
+            return Some(path.join("."));
+        }
+        if let Some(found) = find_json_key(child, matches, path) {
+            return Some(found);
+        }
+        path.pop();
+    }
+    None
+}
 
-            // Convert timestamp to milliseconds since epoch
-            let timestamp_ms = db_packet.ts.timestamp_millis();
+/// The entity a packet is about: the value of the first of ENTITY_ID_KEYS it has (runtime ids first)
+fn packet_entity_id(packet: &serde_json::Value) -> Option<i64> {
+    ENTITY_ID_KEYS.iter().find_map(|&wanted| {
+        let path = find_json_key(packet, &|key, value| key == wanted && entity_id_value(value).is_some(), &mut Vec::new())?;
+        entity_id_value(packet.pointer(&format!("/{}", path.replace('.', "/")))?)
+    })
+}
+
+/// Where a packet references an entity, e.g. "params.runtime_entity_id"
+fn entity_reference(packet: &serde_json::Value, entity: i64) -> Option<String> {
+    let matches = |key: &str, value: &serde_json::Value| {
+        ENTITY_ID_KEYS.contains(&key)
+            && match value {
+                serde_json::Value::Array(items) => items.iter().any(|item| entity_id_value(item) == Some(entity)),
+                value => entity_id_value(value) == Some(entity),
+            }
+    };
+    find_json_key(packet, &matches, &mut Vec::new())
+}
+
+/// Just the packets of one type sent one way, or `filter` without them
+fn packet_type_filter(filter: Option<&PacketFilterSet>, direction: FilterPacketDirection, name: &str, exclude: bool) -> PacketFilterSet {
+    let packet_type = PacketFilter {
+        direction: Some(direction),
+        packet_name: Some(name.to_string()),
+        is_exclusion: exclude,
+        ..Default::default()
+    };
+    if !exclude {
+        return PacketFilterSet { filters: vec![packet_type] };
+    }
+    let mut filters = filter.map(|f| f.filters.clone()).unwrap_or_default();
+    if !filters.contains(&packet_type) {
+        filters.push(packet_type);
+    }
+    PacketFilterSet { filters }
+}
+
+/// A packet name part split into the name and the {conditions} after it, if any (`move_player{runtime_id=42}`)
+fn split_name_conditions(part: &str) -> (&str, Option<&str>) {
+    // A regex may have braces of its own
+    let name_end = if part.starts_with('/') { closing_slash(part).map_or(part.len(), |end| end + 1) } else { 0 };
+    match part[name_end..].find('{') {
+        Some(i) => (&part[..name_end + i], Some(&part[name_end + i..])),
+        None => (part, None),
+    }
+}
 
-            // Track start time (first packet's timestamp)
-            if start_time.is_none() {
-                start_time = Some(timestamp_ms);
+/// `{runtime_id=42, position.y>64}`: field conditions on the packet's params, separated by commas or `&`
+/// (quote a value to put either in it)
+fn parse_params_conditions(text: &str) -> Option<Vec<DbFieldCondition>> {
+    let inner = text.strip_prefix('{')?.strip_suffix('}')?;
+    let mut conditions = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in inner.char_indices().chain([(inner.len(), ',')]) {
+        match c {
+            '"' => quoted = !quoted,
+            ',' | '&' if !quoted => {
+                let condition = inner[start..i].trim();
+                if !condition.is_empty() {
+                    conditions.push(parse_field_condition(&format!("params.{}", condition))?);
+                }
+                start = i + 1;
             }
+            _ => {}
+        }
+    }
+    Some(conditions)
+}
 
-            // Extract protocol version from first packet
-            if protocol_version.is_none() {
-                protocol_version = Some(db_packet.server_version.clone());
+/// `from-to` with either end optional (but not both); a lone value is accepted if `single` is set
+fn parse_filter_range(text: &str, single: bool, parse: impl Fn(&str) -> Option<i64>) -> Option<(Option<i64>, Option<i64>)> {
+    let bound = |text: &str| if text.is_empty() { Some(None) } else { parse(text).map(Some) };
+    match text.split_once('-') {
+        Some((from, to)) => {
+            let (from, to) = (bound(from)?, bound(to)?);
+            match (from, to) {
+                (None, None) => None,
+                (Some(from), Some(to)) if from > to => None,
+                range => Some(range),
             }
+        }
+        None if single => parse(text).map(|value| (Some(value), Some(value))),
+        None => None,
+    }
+}
 
-            // Store the JSON packet directly for display
-            // Also serialize to bytes for compatibility with hex view and protocol parsing
-            let data = serde_json::to_vec(&db_packet.packet)
-                .context("Failed to serialize packet to JSON")?;
+/// The packet name being typed at the end of a filter (`!c.pla` -> "pla"): where it starts, the name so far,
+/// and the direction it's filtered to
+fn filter_name_being_typed(input: &str) -> Option<(usize, &str, Option<PacketDirection>)> {
+    let segment = &input[input.rfind(',').map_or(0, |i| i + 1)..];
+    let segment = segment.trim_start();
+    let (direction, name) = segment.strip_prefix('!').unwrap_or(segment).split_once('.')?;
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None; // Wildcards, or past the name (a range or field condition)
+    }
+    let direction = match direction.to_lowercase().as_str() {
+        "c" => Some(PacketDirection::Clientbound),
+        "s" => Some(PacketDirection::Serverbound),
+        "a" | "" => None,
+        _ => return None,
+    };
+    Some((input.len() - name.len(), name, direction))
+}
 
-            packets.push(PacketEntry {
-                timestamp: timestamp_ms,
-                direction,
-                data,
-                protocol_version: Some(db_packet.server_version),
-                packet_json: Some(db_packet.packet),
-                packet_number: Some(db_packet.packet_number),
+// A packet name offered while typing a filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FilterSuggestion {
+    name: String,
+    count: Option<i64>, // Packets of this type (in the filter's direction) in the session, if it has any
+}
+
+/// Packet names completing the one being typed in a filter, best first: those the protocol sends that way and
+/// those the session has (with how many), so names from other protocol versions show up too
+fn filter_name_suggestions(
+    input: &str,
+    parser: Option<&protocol::ProtocolParser>,
+    packet_types: &[PacketTypeStats],
+) -> Vec<FilterSuggestion> {
+    let Some((_, name, direction)) = filter_name_being_typed(input) else {
+        return Vec::new();
+    };
+    let mut counts: BTreeMap<&str, i64> = BTreeMap::new();
+    for stats in packet_types {
+        let sent = match direction {
+            Some(PacketDirection::Clientbound) => stats.direction == "clientbound",
+            Some(PacketDirection::Serverbound) => stats.direction == "serverbound",
+            None => true,
+        };
+        if sent && stats.name != "unknown" {
+            *counts.entry(&stats.name).or_default() += stats.count;
+        }
+    }
+    let mut names: BTreeSet<&str> = counts.keys().copied().collect();
+    if let Some(parser) = parser {
+        names.extend(parser.match_packet_names(name, direction));
+    }
+    protocol::match_names(name, names)
+        .into_iter()
+        .map(|name| FilterSuggestion { name: name.to_string(), count: counts.get(name).copied() })
+        .collect()
+}
+
+/// The filter with the packet name being typed completed to the best suggestion, if that changes it
+fn complete_filter_name(input: &str, suggestions: &[FilterSuggestion]) -> Option<String> {
+    let (start, name, _) = filter_name_being_typed(input)?;
+    let best = &suggestions.first()?.name;
+    (!name.is_empty() && best != name).then(|| format!("{}{}", &input[..start], best))
+}
+
+/// A /regex/ packet name's pattern, or why it can't be used
+fn parse_name_regex(part: &str) -> Option<Result<&str, String>> {
+    let rest = part.strip_prefix('/')?;
+    Some(match rest.strip_suffix('/').filter(|_| closing_slash(part) == Some(part.len() - 1)) {
+        Some(pattern) => match regex::Regex::new(pattern) {
+            Ok(_) => Ok(pattern),
+            // The last line of the message says what's wrong; the ones before point at it
+            Err(e) => Err(format!("Invalid regex /{}/: {}", pattern, e.to_string().lines().last().unwrap_or_default())),
+        },
+        None => Err(format!("Unclosed regex {}", part)),
+    })
+}
+
+/// Problems with the /regex/ packet names in filter input; filters with one are left out when it's applied
+fn filter_regex_errors(input: &str) -> Vec<String> {
+    split_filters(input)
+        .into_iter()
+        .flat_map(|filter| split_filter_parts(filter.trim().trim_start_matches('!')).into_iter().skip(1))
+        .filter_map(|part| parse_name_regex(part)?.err())
+        .collect()
+}
+
+/// Packet names in a filter the protocol doesn't have, or doesn't send in the filtered direction, and regexes
+/// that match none of its packets
+fn filter_name_warnings(filter: &PacketFilterSet, parser: &protocol::ProtocolParser) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for f in &filter.filters {
+        if let (Some(pattern), true) = (f.packet_name.as_deref(), f.packet_name_is_regex) {
+            let matches_any = regex::Regex::new(pattern).is_ok_and(|regex| parser.packets().any(|info| regex.is_match(&info.name)));
+            if !matches_any {
+                warnings.push(format!("No packet matches /{}/", pattern));
+            }
+            continue;
+        }
+        let Some(name) = f.packet_name.as_deref().filter(|_| !f.packet_name_is_wildcard) else {
+            continue;
+        };
+        if parser.get_packet_info_by_name(name).is_none() {
+            warnings.push(match parser.match_packet_names(name, None).first() {
+                Some(suggestion) => format!("Unknown packet '{}' (did you mean {}?)", name, suggestion),
+                None => format!("Unknown packet '{}'", name),
             });
+            continue;
+        }
+        let direction = match f.direction {
+            Some(FilterPacketDirection::Clientbound) => PacketDirection::Clientbound,
+            Some(FilterPacketDirection::Serverbound) => PacketDirection::Serverbound,
+            None => continue,
+        };
+        if parser.packet_id(name, direction).is_none() {
+            let direction = if direction == PacketDirection::Clientbound { "clientbound" } else { "serverbound" };
+            warnings.push(format!("{} is never {}", name, direction));
+        }
+    }
+    warnings
+}
+
+impl SessionLog {
+    async fn load(db: &dyn Storage, session_id: i32, filter: Option<PacketFilterSet>) -> Result<Self> {
+        // Times (shown, and in filter time ranges) are relative to the session's first packet, whatever the filter
+        let session_start = db
+            .get_packet_page(session_id, None, PageCursor::After(None), 1)
+            .await?
+            .first()
+            .map(|p| p.ts.timestamp_millis())
+            .ok_or_else(|| anyhow::anyhow!("No packets found for session {}", session_id))?;
+        let filter = filter.as_ref().map(|f| f.to_db_filter_set(session_start));
+        let live = db.get_session(session_id).await?.is_some_and(|session| session.ended_at.is_none());
+        let total = db.count_packets(session_id, filter.as_ref(), None).await?;
+        let first_page = db
+            .get_packet_page(session_id, filter.as_ref(), PageCursor::After(None), PACKET_PAGE_SIZE as i64)
+            .await?;
+
+        if first_page.is_empty() {
+            return Err(anyhow::anyhow!("No packets found for session {}", session_id));
         }
 
+        let packets = first_page.into_iter().map(Self::to_packet_entry).collect::<Result<Vec<_>>>()?;
+        // The protocol version is taken from the first packet
+        let protocol_version = packets[0].protocol_version.clone();
+        let notes = db.get_packet_notes(session_id).await?;
+        let bookmarks = db
+            .get_packet_bookmarks(session_id)
+            .await?
+            .into_iter()
+            .map(|bookmark| (bookmark.packet_number, bookmark))
+            .collect();
+
         Ok(Self {
             session_id,
+            filter,
+            total: total.max(packets.len()),
             packets,
-            start_time: start_time.unwrap_or(0),
+            window_start: 0,
+            start_time: session_start,
             protocol_version,
+            notes,
+            bookmarks,
+            live,
+            rate: None,
+            packet_types: None,
+            log_file: None,
+        })
+    }
+
+    /// Open a packet log file, building its `.idx` sidecar if it's missing or out of date
+    fn open_file(path: &std::path::Path, protocols: &protocol_registry::ProtocolRegistry) -> Result<Self> {
+        let index = LogIndex::open(path)?;
+        if index.is_empty() {
+            return Err(anyhow::anyhow!("No packets in {}", path.display()));
+        }
+        // The version of the first entry that records one, else the session's from its metadata
+        let first = index.read_packet(0)?;
+        let protocol_version = first
+            .and_then(|entry| entry.protocol_version)
+            .or_else(|| log_meta::SessionMeta::load(path).ok()?.protocol_version)
+            .unwrap_or_else(|| protocol::PROTOCOL_VERSION.to_string());
+        let log_file = LogFile {
+            name: path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+            parser: protocols.get(&protocol_version),
+            index,
+            protocol_version: protocol_version.clone(),
+        };
+        let mut log = Self {
+            session_id: 0, // Not a database session
+            filter: None,
+            packets: Vec::new(),
+            window_start: 0,
+            total: log_file.index.len(),
+            start_time: log_file.index.entries()[0].timestamp,
+            protocol_version: Some(protocol_version),
+            notes: BTreeMap::new(),
+            bookmarks: BTreeMap::new(),
+            live: false,
+            rate: None,
+            packet_types: None,
+            log_file: Some(log_file),
+        };
+        log.load_file_window(0)?;
+        Ok(log)
+    }
+
+    /// Read the page of a log file's packets around `index`, unless the packets within LOAD_MARGIN of it are
+    /// already loaded
+    fn load_file_window(&mut self, index: usize) -> Result<()> {
+        let Some(file) = &self.log_file else {
+            return Ok(());
+        };
+        let wanted_start = index.saturating_sub(LOAD_MARGIN);
+        let wanted_end = (index + LOAD_MARGIN + 1).min(self.total);
+        if !self.packets.is_empty() && self.window_start <= wanted_start && wanted_end <= self.window_end() {
+            return Ok(());
+        }
+        let start = index.saturating_sub(PACKET_PAGE_SIZE / 2).min(self.total.saturating_sub(PACKET_PAGE_SIZE));
+        let Some(reader) = file.index.reader_at(start)? else {
+            return Ok(());
+        };
+        let mut packets = Vec::new();
+        for (position, entry) in (start..).zip(reader.take(PACKET_PAGE_SIZE)) {
+            let mut entry = entry?;
+            entry.packet_number = Some(position as i64 + 1);
+            if entry.protocol_version.is_none() {
+                entry.protocol_version = Some(file.protocol_version.clone());
+            }
+            if entry.packet_json.is_none() {
+                entry.packet_json = file.parser.as_ref().and_then(|parser| parser.decode_packet(&entry.data, entry.direction).to_packet_json());
+            }
+            packets.push(entry);
+        }
+        self.packets = packets;
+        self.window_start = start;
+        Ok(())
+    }
+
+    /// Index of the first packet of a log file from a time (ms), or None past its last packet
+    fn file_position_at(&self, timestamp: i64) -> Option<usize> {
+        let file = self.log_file.as_ref()?;
+        Some(file.index.time_range(timestamp, i64::MAX).start).filter(|&index| index < self.total)
+    }
+
+    fn to_packet_entry(db_packet: DbPacket) -> Result<PacketEntry> {
+        // Convert database packet to PacketEntry
+        let direction = match db_packet.direction.as_str() {
+            "clientbound" => PacketDirection::Clientbound,
+            "serverbound" => PacketDirection::Serverbound,
+            _ => {
+                return Err(anyhow::anyhow!("Invalid direction: {}", db_packet.direction));
+            }
+        };
+
+        // Store the JSON packet directly for display
+        // Also serialize to bytes for compatibility with hex view and protocol parsing
+        let data = serde_json::to_vec(&db_packet.packet)
+            .context("Failed to serialize packet to JSON")?;
+
+        Ok(PacketEntry {
+            timestamp: db_packet.ts.timestamp_millis(),
+            direction,
+            data,
+            protocol_version: Some(db_packet.server_version),
+            packet_json: Some(db_packet.packet),
+            packet_number: Some(db_packet.packet_number),
+            original_size: None,
         })
     }
 
+    async fn fetch_page(&self, db: &dyn Storage, cursor: PageCursor, limit: usize) -> Result<Vec<PacketEntry>> {
+        db.get_packet_page(self.session_id, self.filter.as_ref(), cursor, limit as i64)
+            .await?
+            .into_iter()
+            .map(Self::to_packet_entry)
+            .collect()
+    }
+
+    /// Number of filtered packets in the session
+    fn len(&self) -> usize {
+        self.total
+    }
+
+    /// Packet at an index among all filtered packets, if it's in the loaded window
+    fn get(&self, index: usize) -> Option<&PacketEntry> {
+        self.packets.get(index.checked_sub(self.window_start)?)
+    }
+
+    fn window_end(&self) -> usize {
+        self.window_start + self.packets.len()
+    }
+
+    /// Load the packets within LOAD_MARGIN of `index`, fetching neighbouring pages as needed
+    async fn ensure_loaded(&mut self, db: Option<&dyn Storage>, index: usize) -> Result<()> {
+        if self.log_file.is_some() {
+            return self.load_file_window(index);
+        }
+        let db = database(db)?;
+        let wanted_start = index.saturating_sub(LOAD_MARGIN);
+        let wanted_end = (index + LOAD_MARGIN + 1).min(self.total);
+
+        // Jumps to either end of the session start over from that end instead of paging through everything
+        if index >= self.window_end() + PACKET_PAGE_SIZE && index + PACKET_PAGE_SIZE >= self.total {
+            self.packets = self.fetch_page(db, PageCursor::Before(None), PACKET_PAGE_SIZE).await?;
+            self.window_start = self.total.saturating_sub(self.packets.len());
+        } else if index + PACKET_PAGE_SIZE < self.window_start && index < PACKET_PAGE_SIZE {
+            self.packets = self.fetch_page(db, PageCursor::After(None), PACKET_PAGE_SIZE).await?;
+            self.window_start = 0;
+        }
+
+        while self.window_end() < wanted_end {
+            let after = self.packets.last().and_then(|p| p.packet_number);
+            let page = self.fetch_page(db, PageCursor::After(after), PACKET_PAGE_SIZE).await?;
+            if page.is_empty() {
+                // Packets were deleted since the session was opened
+                self.total = self.window_end();
+                break;
+            }
+            self.packets.extend(page);
+            self.total = self.total.max(self.window_end());
+            // Drop the oldest packets, but never the ones around the target
+            let excess = self.packets.len().saturating_sub(MAX_LOADED_PACKETS)
+                .min(wanted_start.saturating_sub(self.window_start));
+            self.packets.drain(..excess);
+            self.window_start += excess;
+        }
+
+        while self.window_start > wanted_start {
+            let before = self.packets.first().and_then(|p| p.packet_number);
+            let page = self.fetch_page(db, PageCursor::Before(before), PACKET_PAGE_SIZE).await?;
+            if page.is_empty() {
+                self.window_start = 0;
+                break;
+            }
+            self.window_start = self.window_start.saturating_sub(page.len());
+            self.packets.splice(0..0, page);
+            let excess = self.packets.len().saturating_sub(MAX_LOADED_PACKETS)
+                .min(self.window_end().saturating_sub(wanted_end));
+            self.packets.truncate(self.packets.len() - excess);
+        }
+
+        Ok(())
+    }
+
+    /// Load the packets around a packet number; returns the index of the closest filtered packet
+    async fn seek(&mut self, db: Option<&dyn Storage>, packet_number: i64) -> Result<usize> {
+        if self.log_file.is_some() {
+            // Packet numbers of a log file are positions in its index
+            let index = (packet_number - 1).clamp(0, self.total as i64 - 1) as usize;
+            self.load_file_window(index)?;
+            return Ok(index);
+        }
+        let db = database(db)?;
+        let preceding = db.count_packets(self.session_id, self.filter.as_ref(), Some(packet_number)).await?;
+        let mut packets = self.fetch_page(db, PageCursor::Before(Some(packet_number)), PACKET_PAGE_SIZE / 2).await?;
+        let before_len = packets.len();
+        packets.extend(self.fetch_page(db, PageCursor::After(Some(packet_number - 1)), PACKET_PAGE_SIZE / 2).await?);
+        if packets.is_empty() {
+            return Ok(0);
+        }
+
+        self.packets = packets;
+        self.window_start = preceding.saturating_sub(before_len);
+        self.total = self.total.max(self.window_end());
+
+        // The closest packet is the last one before the packet number or the first one from it (earlier wins ties)
+        let closest = [before_len.checked_sub(1), Some(before_len)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| Some((i, self.packets.get(i)?.packet_number?)))
+            .min_by_key(|(_, number)| (number - packet_number).abs())
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        Ok(self.window_start + closest)
+    }
+
+    /// Index of the packet with this packet number, if it passes the filter
+    async fn position_of(&self, db: Option<&dyn Storage>, packet_number: i64) -> Result<Option<usize>> {
+        if self.log_file.is_some() {
+            return Ok((1..=self.total as i64).contains(&packet_number).then(|| packet_number as usize - 1));
+        }
+        let db = database(db)?;
+        let page = self.fetch_page(db, PageCursor::After(Some(packet_number - 1)), 1).await?;
+        if page.first().and_then(|p| p.packet_number) != Some(packet_number) {
+            return Ok(None);
+        }
+        Ok(Some(db.count_packets(self.session_id, self.filter.as_ref(), Some(packet_number)).await?))
+    }
+
     fn relative_time(&self, timestamp: i64) -> i64 {
         timestamp - self.start_time
     }
+
+    /// Count the packets captured since the session was loaded or last polled, and whether it has ended
+    /// since; returns how many new packets pass the filter
+    async fn poll_new_packets(&mut self, db: Option<&dyn Storage>) -> Result<usize> {
+        let db = database(db)?;
+        // Checked before counting, so the packets captured before it ended are all counted
+        self.live = db.get_session(self.session_id).await?.is_some_and(|session| session.ended_at.is_none());
+        let total = db.count_packets(self.session_id, self.filter.as_ref(), None).await?;
+        let added = total.saturating_sub(self.total);
+        self.total += added;
+        if added > 0 {
+            self.rate = None;
+            self.packet_types = None;
+        }
+        Ok(added)
+    }
 }
 
 enum ConfirmationAction {
@@ -132,31 +902,99 @@ enum ConfirmationAction {
     // Add more action types as needed
 }
 
+// Viewing position preserved across filter changes (see ViewerApp::restore_selection)
+struct SelectionAnchor {
+    packet_number: Option<i64>,
+    packet_details_scroll: u16,
+    diff_panel_scroll: u16,
+    baseline_packet_number: Option<i64>, // Only set while compare mode is active
+}
+
+// A session's packets being loaded on a background task, so the UI keeps running and Esc can cancel it
+struct PendingLoad {
+    task: tokio::task::JoinHandle<Result<SessionLog>>,
+    kind: PendingLoadKind,
+}
+
+enum PendingLoadKind {
+    Open, // The session selected in the list
+    Refilter { anchor: SelectionAnchor, previous: Option<PacketFilterSet> }, // The open session, with a new filter
+}
+
 struct ViewerApp {
-    db: Database,
-    sessions: Vec<(DbSession, usize, Vec<String>)>, // session, packet_count, tags
+    db: Option<Arc<dyn Storage>>, // None for a log file opened with `view`, which is read without connecting
+    sessions: Vec<SessionSummary>,
+    sessions_loaded: bool, // Whether the session list has been loaded since startup
     selected_session: usize,
     current_log: Option<SessionLog>,
     packet_index: usize,
     mode: ViewerMode,
     error_message: Option<String>,
+    status_message: Option<String>, // Outcome of the last session list action (e.g. an export), until the next key
     show_hex: bool, // Toggle between JSON (default) and hex view
     packet_details_scroll: u16, // Scroll offset for packet details panel
     diff_panel_scroll: u16, // Scroll offset for differences panel (compare mode)
-    protocol_parser: Option<protocol::ProtocolParser>, // Loaded protocol parser
+    protocols: protocol_registry::ProtocolRegistry, // Parsers for the protocol versions packets were logged with
+    protocol_override: Option<String>, // Version the session's packets are decoded with instead of their own (V)
+    protocol_versions: Option<ProtocolVersionsState>, // Protocol version switcher, while open
     filter_input: String, // Current filter input text
     current_filter: Option<PacketFilterSet>, // Currently applied filter
+    follow: bool, // Whether to move to new packets of a live session as they arrive
+    last_live_poll: Option<Instant>, // When the live session was last checked for new packets
+    playback: Option<packet_playback::Playback>, // Moving through the packets as they were captured, while playing
+    playback_speed: usize, // Index into packet_playback::SPEEDS, kept while paused
+    search_input: String, // Current search input text
+    goto_input: String, // Packet number or time being typed after g
+    watch_input: String, // JSON path being typed after w
+    watched_paths: Vec<String>, // JSON paths pinned to the watch panel, in the order they were added
+    export_input: String, // Path being typed after e / E
+    export_all: bool, // Whether the export is of every packet passing the filter, or only the current one
+    current_search: Option<PacketSearch>, // Search that n and N jump to the next and previous match of
+    search_exhausted: bool, // Whether the last jump found no further match
+    search_total: Option<usize>, // Matches of the current search within the filter
+    search_match: Option<(i64, usize)>, // Packet number and position (from 1) of the match last jumped to
+    session_search_input: String, // Session search being typed
+    current_session_search: Option<SessionSearch>, // Search the session list shows the results of
+    statistics: Option<StatisticsState>, // Statistics panel, while open
+    db_health: Option<DbHealth>, // Database panel, while open
+    note_editor: Option<NoteEditorState>, // Note being edited
+    bookmarks_panel: Option<BookmarksPanelState>, // Bookmarks panel, while open
+    ignored_fields: BTreeMap<String, BTreeSet<String>>, // Fields compare mode leaves out, by packet name
+    ignored_fields_panel: Option<IgnoredFieldsState>, // Ignored fields panel, while open
+    hex_link: Option<HexLinkState>, // Linked hex / JSON view, while open
+    movement_map: Option<MovementMapState>, // Player movement map, while open
+    gaps: Option<GapsState>, // Gaps and round trips panel, while open
+    decode_errors: Option<DecodeErrorsState>, // Decode errors panel, while open
+    exchanges: Option<ExchangesState>, // Request / response exchanges panel, while open
+    sequence_diagram: Option<SequenceDiagramState>, // Sequence diagram, while open
+    filter_presets: Option<FilterPresetsState>, // Preset picker, while open
+    filter_history: FilterHistory, // Filters applied, recalled with Up/Down in filter input
+    persist_filter_history: bool, // Whether the history is kept in the database, rather than for this run (FILTER_HISTORY)
+    session_notes: HashMap<i32, Option<String>>, // Session note per session id, loaded with the preview
     is_loading: bool, // Whether we're currently loading packets
+    pending_load: Option<PendingLoad>, // Packets loading in the background (Esc cancels)
     loading_frame: u8, // Frame counter for loading animation
     compare_mode: bool, // Whether compare mode is active
     baseline_packet_index: Option<usize>, // Index of baseline packet for comparison
-    baseline_packet_json: Option<serde_json::Value>, // JSON of baseline packet
+    baseline_packet: Option<PacketEntry>, // Copy of the baseline packet, which may leave the loaded window
+    baseline_session_id: Option<i32>, // Session the baseline packet is from
+    baseline_pinned: bool, // Whether the baseline is kept when another session is opened (C)
+    diff_previous: bool, // Whether the baseline follows the current packet: the previous packet of its type (p)
+    diff_previous_of: Option<i64>, // Packet number of the packet the previous-packet baseline was found for
     tag_input: String, // Current tag input text
     tag_management: Option<TagManagementState>, // Tag management modal state
     confirmation_dialog: Option<ConfirmationDialogState>, // Confirmation dialog state
     json_expanded_paths: HashSet<String>, // Set of JSON paths that are expanded (e.g., "root.field.subfield")
     packet_details_area: Option<Rect>, // Cached area for packet details panel (for mouse click detection)
+    show_packet_list: bool, // Whether the packet list is shown beside the details
+    show_rate_histogram: bool, // Whether the timeline shows the session's packet rate rather than the packets around the current one
+    packet_list_rows: Option<(Rect, usize)>, // Area of the packet list and the index of its first row (for mouse clicks)
     json_line_to_path: Vec<Option<String>>, // Mapping from line index to JSON path (for mouse click handling)
+    session_previews: HashMap<i32, Result<SessionPreview, String>>, // Preview per session id, loaded when highlighted
+    clipboard: Option<arboard::Clipboard>, // Kept open once used: on X11 and Wayland copied text lasts only as long as this does
+    keymap: viewer_config::Keymap, // Packet view keys, with the config file's remappings
+    key_help: Option<KeyHelpState>, // Keys overlay (?), while open
+    theme: Theme, // Colors and borders, from the config file's theme section
 }
 
 struct TagManagementState {
@@ -166,6 +1004,171 @@ struct TagManagementState {
     add_tag_mode: bool, // Whether we're in add tag input mode
 }
 
+struct KeyHelpState {
+    from_packet_view: bool, // Mode to return to when closed: PacketView, or SessionList
+    scroll: u16,
+}
+
+struct StatisticsState {
+    session_id: i32,
+    stats: Vec<PacketTypeStats>,
+    sort: StatsSort,
+    scroll: u16,
+    from_packet_view: bool, // Mode to return to when closed: PacketView, or SessionList
+    filter: Option<String>, // Filter the totals are limited to (`S` in the packet view), as typed
+}
+
+struct FilterPresetsState {
+    presets: Vec<FilterPreset>,
+    selected: usize,
+    name_input: Option<String>, // Name being typed for saving the current filter
+    message: Option<String>, // Outcome of the last save or delete
+}
+
+// Filters applied in the filter panel, recalled with Up/Down like a shell's history
+#[derive(Default)]
+struct FilterHistory {
+    entries: Vec<String>, // Oldest first, without repeats
+    position: Option<usize>, // Entry in the filter input, while recalling
+    draft: String, // What was typed before recalling
+}
+
+impl FilterHistory {
+    /// Record an applied filter as the most recent, moving it to the end if it was already there
+    fn push(&mut self, filter: &str) {
+        self.entries.retain(|entry| entry != filter);
+        self.entries.push(filter.to_string());
+        self.position = None;
+    }
+
+    /// The entry before the one shown (the most recent one, when `input` is still being typed)
+    fn previous(&mut self, input: &str) -> Option<&str> {
+        let position = match self.position {
+            None => {
+                self.draft = input.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+            Some(position) => position.saturating_sub(1),
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// The entry after the one shown, or what was typed before recalling after the most recent
+    fn next(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            Some(&self.entries[position])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+}
+
+struct IgnoredFieldsState {
+    packet_name: String,
+    fields: Vec<String>, // The packet type's ignored fields and the fields that differ from the baseline, by path
+    selected: usize,
+    path_input: Option<String>, // Path being typed, to ignore a field that isn't listed
+}
+
+// Player movement map (`M`): where the player went over the session, and where they were at the current packet
+struct MovementMapState {
+    path: Vec<movement_map::PathPoint>,
+}
+
+// Gaps and round trips panel (`G`): the largest pauses between packets passing the filter, and how long the
+// session's requests took to be answered
+struct GapsState {
+    gaps: Vec<PacketGap>,
+    round_trips: Vec<packet_latency::RoundTripStats>,
+    selected: usize, // Index into the gaps, then the round trips
+    filter: Option<String>, // Filter the gaps are between packets passing, as typed
+}
+
+// Sequence diagram (`T`): the handshake, or the packets passing the filter from the current one on
+struct SequenceDiagramState {
+    packets: Vec<sequence_diagram::SequencePacket>,
+    handshake: bool,
+    missing: Vec<&'static str>, // Handshake steps that aren't in the session
+    selected: usize,
+}
+
+// Exchanges panel (`R`): every request of a known pair in the session and the packet that answered it
+struct ExchangesState {
+    exchanges: Vec<packet_latency::Exchange>,
+    selected: usize,
+}
+
+// Decode errors panel (`D`): packets of the session the decoder gave up on
+struct DecodeErrorsState {
+    problems: Vec<decode_errors::DecodeProblem>,
+    selected: usize,
+}
+
+// Protocol version switcher (`V`): the current packet decoded with each version there are definitions for
+struct ProtocolVersionsState {
+    versions: Vec<String>,
+    selected: usize,
+    logged: Option<String>, // Version the current packet was logged with
+    comparison: Result<(serde_json::Value, Option<String>), String>, // Packet JSON decoded with the selected version and where that broke, or why it couldn't be
+    scroll: u16,
+}
+
+// A row of the watch panel (`w`)
+struct WatchRow {
+    path: String,
+    value: Option<String>, // None if no packet up to the current one has the path (among those loaded)
+    from: Option<i64>, // Packet number the value is from, when it's an earlier packet than the current one
+    change: Option<String>, // Since the packet before that had the path
+}
+
+// Linked hex / JSON view (`v`): the current packet's decoded fields beside its hex dump, each highlighting the other
+struct HexLinkState {
+    data: Vec<u8>,
+    fields: Vec<(String, std::ops::Range<usize>)>, // Decoded fields by path, in order of where they start
+    values: Vec<Option<String>>, // Each field's value as compact JSON, for the ones that aren't objects or arrays
+    error: Option<String>, // Where decoding stopped, if it failed
+    selected: usize, // Index into fields
+    cursor: usize, // Byte under the hex dump's cursor
+    hex_focus: bool, // Whether the arrow keys move the byte cursor rather than the field selection
+    hex_scroll: usize, // First row of the hex dump shown
+    hex_area: Option<Rect>, // Where the hex dump was drawn (for mouse clicks)
+}
+
+impl HexLinkState {
+    /// Put the byte cursor on a byte, selecting the field it belongs to
+    fn move_cursor(&mut self, offset: usize) {
+        self.cursor = offset.min(self.data.len().saturating_sub(1));
+        if let Some(index) = field_at(&self.fields, self.cursor) {
+            self.selected = index;
+        }
+    }
+
+    /// Select a field, moving the byte cursor to its first byte
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.fields.len().saturating_sub(1));
+        if let Some((_, range)) = self.fields.get(self.selected) {
+            self.cursor = range.start;
+        }
+    }
+}
+
+struct BookmarksPanelState {
+    selected: usize, // Index into the session's bookmarks, in packet order
+    label_input: Option<String>, // Label being typed for the selected bookmark
+}
+
+struct NoteEditorState {
+    session_id: i32,
+    packet_number: Option<i64>, // None for the session's own note
+    text: String,
+    from_packet_view: bool, // Mode to return to when closed: PacketView, or SessionList
+    renaming: bool, // Editing the session's name (a single line) rather than a note
+}
+
 struct ConfirmationDialogState {
     message: String,
     action: ConfirmationAction,
@@ -173,18 +1176,44 @@ struct ConfirmationDialogState {
 
 enum ViewerMode {
     SessionList,
+    SessionSearchInput,
     PacketView,
     FilterInput,
+    SearchInput,
+    GotoInput,
+    WatchInput,
+    ExportInput,
+    Statistics,
+    DatabaseHealth,
+    Bookmarks,
+    IgnoredFields,
+    HexLink,
+    MovementMap,
+    Gaps,
+    Exchanges,
+    SequenceDiagram,
+    DecodeErrors,
+    ProtocolVersions,
+    NoteInput,
+    FilterPresets,
     TagManagement,
     ConfirmationDialog,
+    KeyHelp,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 struct PacketFilter {
     direction: Option<FilterPacketDirection>, // None means "all directions"
     packet_name: Option<String>, // None means "all packet types"
     packet_name_is_wildcard: bool, // If true, packet_name contains wildcards (*)
+    packet_name_is_regex: bool, // If true, packet_name is a regular expression (written /.../)
     is_exclusion: bool, // If true, this filter excludes matching packets
+    time_from_ms: Option<i64>, // Time range relative to the session's first packet, inclusive
+    time_to_ms: Option<i64>,
+    packet_number_from: Option<i64>, // Packet number range, inclusive
+    packet_number_to: Option<i64>,
+    fields: Vec<DbFieldCondition>, // Conditions on the packet's JSON, all of which must hold
+    entity: Option<i64>, // Packets referencing this entity id (see ENTITY_ID_KEYS)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -199,41 +1228,80 @@ struct PacketFilterSet {
 }
 
 impl ViewerApp {
-    async fn new() -> Result<Self> {
-        let db = Database::connect().await?;
-        
-        // Load sessions from database
-        let db_sessions = db.get_sessions().await?;
-        let mut sessions = Vec::new();
-        
-        for session in db_sessions {
-            let packet_count = db.get_session_packet_count(session.id).await?;
-            let tags = db.get_session_tags(session.id).await.unwrap_or_default();
-            sessions.push((session, packet_count, tags));
-        }
+    async fn new(db: Option<Arc<dyn Storage>>) -> Result<Self> {
+        let connected = db.is_some();
 
-        // Try to load protocol parser for default version
-        let protocol_parser = protocol::ProtocolParser::new("1.21.111").ok();
-        
-        Ok(Self {
+        // FILTER_HISTORY=false keeps applied filters for this run only
+        let persist_filter_history = match std::env::var("FILTER_HISTORY") {
+            Ok(value) => value.parse::<bool>().context("Invalid FILTER_HISTORY")?,
+            Err(_) => true,
+        };
+        let config = viewer_config::ViewerConfig::load()?;
+        let keymap = viewer_config::Keymap::new(&config.keymap)
+            .with_context(|| format!("Invalid keymap in {}", viewer_config::ViewerConfig::path().display()))?;
+        let theme = Theme::from_config(&config.theme)
+            .with_context(|| format!("Invalid theme in {}", viewer_config::ViewerConfig::path().display()))?;
+
+        let mut app = Self {
             db,
-            sessions,
+            sessions: Vec::new(), // Loaded once the first frame is drawn (see load_initial_sessions)
+            sessions_loaded: !connected, // Without a database there's no session list to load
             selected_session: 0,
             current_log: None,
             packet_index: 0,
             mode: ViewerMode::SessionList,
             error_message: None,
+            status_message: None,
             show_hex: false, // JSON by default
             packet_details_scroll: 0,
             diff_panel_scroll: 0,
-            protocol_parser,
+            protocols: protocol_registry::ProtocolRegistry::default(),
+            protocol_override: None,
+            watch_input: String::new(),
+            watched_paths: Vec::new(),
+            protocol_versions: None,
             filter_input: String::new(),
             current_filter: None,
-            is_loading: false,
+            follow: false,
+            playback: None,
+            playback_speed: packet_playback::DEFAULT_SPEED,
+            last_live_poll: None,
+            search_input: String::new(),
+            goto_input: String::new(),
+            export_input: String::new(),
+            export_all: false,
+            current_search: None,
+            search_exhausted: false,
+            search_total: None,
+            search_match: None,
+            session_search_input: String::new(),
+            current_session_search: None,
+            statistics: None,
+            db_health: None,
+            bookmarks_panel: None,
+            ignored_fields: BTreeMap::new(),
+            ignored_fields_panel: None,
+            hex_link: None,
+            movement_map: None,
+            gaps: None,
+            decode_errors: None,
+            exchanges: None,
+            sequence_diagram: None,
+            note_editor: None,
+            filter_presets: None,
+            filter_history: FilterHistory::default(),
+            persist_filter_history,
+            session_notes: HashMap::new(),
+            is_loading: connected, // Until the session list is loaded
+            pending_load: None,
             loading_frame: 0,
             compare_mode: false,
             baseline_packet_index: None,
-            baseline_packet_json: None,
+            baseline_packet: None,
+            baseline_session_id: None,
+            baseline_pinned: false,
+            diff_previous: false,
+            diff_previous_of: None,
             tag_input: String::new(),
             tag_management: None,
             confirmation_dialog: None,
@@ -243,49 +1311,393 @@ impl ViewerApp {
                 set
             },
             packet_details_area: None,
+            show_packet_list: true,
+            show_rate_histogram: false,
+            packet_list_rows: None,
             json_line_to_path: Vec::new(),
-        })
+            session_previews: HashMap::new(),
+            clipboard: None,
+            keymap,
+            key_help: None,
+            theme,
+        };
+        if let (true, Some(db)) = (app.persist_filter_history, app.db.as_deref()) {
+            match db.get_filter_history().await {
+                Ok(entries) => app.filter_history.entries = entries,
+                Err(e) => app.error_message = Some(format!("Failed to load filter history: {:#}", e)),
+            }
+        }
+        Ok(app)
+    }
+
+    /// The database, for actions that query it (see database)
+    fn db(&self) -> Result<&dyn Storage> {
+        database(self.db.as_deref())
+    }
+
+    /// Whether there's a database for an action that starts with input it would be saved or run with, saying
+    /// why not if there isn't
+    fn has_database(&mut self) -> bool {
+        match self.db() {
+            Ok(_) => true,
+            Err(e) => {
+                self.error_message = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    fn open_key_help(&mut self, from_packet_view: bool) {
+        self.key_help = Some(KeyHelpState { from_packet_view, scroll: 0 });
+        self.mode = ViewerMode::KeyHelp;
+    }
+
+    /// Add an applied filter to the history, and the database's unless FILTER_HISTORY=false
+    async fn record_filter(&mut self, filter: &str) {
+        self.filter_history.push(filter);
+        if let (true, Some(db)) = (self.persist_filter_history, self.db.as_deref()) {
+            if let Err(e) = db.add_filter_history(filter).await {
+                self.error_message = Some(format!("Failed to save filter history: {:#}", e));
+            }
+        }
+    }
+
+    /// Sessions matching a search, with their packet counts and tags
+    async fn load_sessions(db: &dyn Storage, search: &SessionSearch) -> Result<Vec<SessionSummary>> {
+        // One query for the whole list; counts and tags come with each session
+        db.search_session_summaries(search).await
+    }
+
+    /// Load the session list for the first time; the UI is already up, showing the loading indicator
+    async fn load_initial_sessions(&mut self) {
+        self.sessions_loaded = true;
+        if let Err(e) = self.apply_session_search(None).await {
+            self.error_message = Some(format!("Failed to load sessions: {:#}", e));
+        }
+        self.is_loading = false;
+    }
+
+    /// Show the sessions matching a search (all sessions for None) and select the first
+    async fn apply_session_search(&mut self, search: Option<SessionSearch>) -> Result<()> {
+        self.sessions = Self::load_sessions(self.db()?, search.as_ref().unwrap_or(&SessionSearch::default())).await?;
+        self.current_session_search = search;
+        self.selected_session = 0;
+        Ok(())
+    }
+
+    /// Fetch the preview for the highlighted session unless it's already cached
+    /// Failures are cached too, so an unreachable session isn't re-queried on every idle tick
+    async fn load_selected_preview(&mut self) {
+        let Some(SessionSummary { session, .. }) = self.sessions.get(self.selected_session) else {
+            return;
+        };
+        let session_id = session.id;
+        if self.session_previews.contains_key(&session_id) {
+            return;
+        }
+        let Ok(db) = self.db() else {
+            return;
+        };
+        let preview = db
+            .get_session_preview(session_id, PREVIEW_PACKETS, PREVIEW_PACKET_TYPES)
+            .await
+            .map_err(|e| format!("{:#}", e));
+        if preview.is_ok() {
+            if let Ok(note) = db.get_session_note(session_id).await {
+                self.session_notes.insert(session_id, note);
+            }
+        }
+        self.session_previews.insert(session_id, preview);
+    }
+
+    /// Open the preset picker over the filter panel
+    async fn open_filter_presets(&mut self) {
+        let loaded = async { self.db()?.get_filter_presets().await };
+        let (presets, message) = match loaded.await {
+            Ok(presets) => (presets, None),
+            Err(e) => (Vec::new(), Some(format!("Failed to load presets: {}", e))),
+        };
+        self.filter_presets = Some(FilterPresetsState { presets, selected: 0, name_input: None, message });
+        self.mode = ViewerMode::FilterPresets;
+    }
+
+    /// Save the filter being typed under `name`, replacing any preset of that name
+    async fn save_filter_preset(&mut self, name: &str) -> Result<String> {
+        let filter = Self::parse_filter(&self.filter_input)
+            .ok_or_else(|| anyhow::anyhow!("Type a filter before saving it"))?
+            .to_string();
+        self.db()?.save_filter_preset(name, &filter).await?;
+        let presets = self.db()?.get_filter_presets().await?;
+        if let Some(ref mut picker) = self.filter_presets {
+            picker.selected = presets.iter().position(|p| p.name == name).unwrap_or(0);
+            picker.presets = presets;
+        }
+        Ok(format!("Saved '{}' as {}", name, filter))
+    }
+
+    /// Open the note editor on a session's note, or on one of its packets'
+    async fn open_note_editor(&mut self, session_id: i32, packet_number: Option<i64>) {
+        if !self.has_database() {
+            return;
+        }
+        let text = match packet_number {
+            Some(number) => self.current_log.as_ref().and_then(|log| log.notes.get(&number).cloned()),
+            None => match async { self.db()?.get_session_note(session_id).await }.await {
+                Ok(note) => note,
+                Err(e) => {
+                    self.error_message = Some(format!("Failed to load note: {}", e));
+                    return;
+                }
+            },
+        };
+        self.note_editor = Some(NoteEditorState {
+            session_id,
+            packet_number,
+            text: text.unwrap_or_default(),
+            from_packet_view: matches!(self.mode, ViewerMode::PacketView),
+            renaming: false,
+        });
+        self.mode = ViewerMode::NoteInput;
+    }
+
+    /// Open the editor on the selected session's name
+    fn open_name_editor(&mut self) {
+        let Some(summary) = self.sessions.get(self.selected_session) else {
+            return;
+        };
+        self.note_editor = Some(NoteEditorState {
+            session_id: summary.session.id,
+            packet_number: None,
+            text: summary.name.clone().unwrap_or_default(),
+            from_packet_view: false,
+            renaming: true,
+        });
+        self.mode = ViewerMode::NoteInput;
+    }
+
+    /// Save the note being edited (a blank note removes it) and close the editor
+    async fn save_note(&mut self) -> Result<()> {
+        let Some(editor) = self.note_editor.take() else {
+            return Ok(());
+        };
+        self.mode = if editor.from_packet_view { ViewerMode::PacketView } else { ViewerMode::SessionList };
+        let note = editor.text.trim();
+        if editor.renaming {
+            self.db()?.set_session_name(editor.session_id, note).await?;
+            if let Some(summary) = self.sessions.iter_mut().find(|summary| summary.session.id == editor.session_id) {
+                summary.name = (!note.is_empty()).then(|| note.to_string());
+            }
+            return Ok(());
+        }
+        match editor.packet_number {
+            Some(number) => {
+                self.db()?.set_packet_note(editor.session_id, number, note).await?;
+                if let Some(ref mut log) = self.current_log {
+                    if note.is_empty() {
+                        log.notes.remove(&number);
+                    } else {
+                        log.notes.insert(number, note.to_string());
+                    }
+                }
+            }
+            None => {
+                self.db()?.set_session_note(editor.session_id, note).await?;
+                self.session_notes.insert(editor.session_id, (!note.is_empty()).then(|| note.to_string()));
+            }
+        }
+        Ok(())
     }
 
     async fn refresh_session_tags(&mut self, session_id: i32) -> Result<()> {
-        let tags = self.db.get_session_tags(session_id).await?;
+        let tags = self.db()?.get_session_tags(session_id).await?;
         // Update tags for the session in our sessions list
-        if let Some((_, _, session_tags)) = self.sessions.iter_mut()
-            .find(|(s, _, _)| s.id == session_id) {
+        if let Some(SessionSummary { tags: session_tags, .. }) = self.sessions.iter_mut()
+            .find(|summary| summary.session.id == session_id) {
             *session_tags = tags;
         }
         Ok(())
     }
 
-    async fn load_session(&mut self) -> Result<()> {
-        if let Some((session, _, _)) = self.sessions.get(self.selected_session) {
-            self.is_loading = true;
-            let filter = self.current_filter.clone();
-            let result = SessionLog::load(&self.db, session.id, filter).await;
-            self.is_loading = false;
-            
-            match result {
-                Ok(log) => {
-                    self.current_log = Some(log);
-                    self.packet_index = 0;
-                    self.packet_details_scroll = 0;
-                    self.diff_panel_scroll = 0;
-                    // Reset compare mode when loading new session
-                    self.compare_mode = false;
-                    self.baseline_packet_index = None;
-                    self.baseline_packet_json = None;
-                    // Initialize filter input to show current filter
-                    self.filter_input = self.current_filter.as_ref()
-                        .map(|f| f.to_string())
-                        .unwrap_or_else(|| "a".to_string());
-                    self.mode = ViewerMode::PacketView;
-                    Ok(())
+    /// Reload the session with a new filter, keeping the current packet, its scroll position and the compare
+    /// baseline where the filter allows
+    fn apply_filter(&mut self, filter: Option<PacketFilterSet>) {
+        // Save current position (packet, scroll, compare baseline) to restore after reload
+        let anchor = self.selection_anchor();
+        let previous = std::mem::replace(&mut self.current_filter, filter);
+
+        let Some(session_id) = self.current_log.as_ref().map(|log| log.session_id) else {
+            return;
+        };
+        self.start_load(session_id, PendingLoadKind::Refilter { anchor, previous });
+    }
+
+    /// Load a session's packets with the current filter in the background; poll_pending_load picks them up
+    fn start_load(&mut self, session_id: i32, kind: PendingLoadKind) {
+        if let Some(pending) = self.pending_load.take() {
+            pending.task.abort();
+        }
+        let db = self.db.clone();
+        let filter = self.current_filter.clone();
+        let task = tokio::spawn(async move { SessionLog::load(database(db.as_deref())?, session_id, filter).await });
+        self.pending_load = Some(PendingLoad { task, kind });
+        self.is_loading = true;
+    }
+
+    /// Finish the background load once it's done: show the session, or keep the previous filter if it failed
+    async fn poll_pending_load(&mut self) {
+        if !self.pending_load.as_ref().is_some_and(|pending| pending.task.is_finished()) {
+            return;
+        }
+        let Some(PendingLoad { task, kind }) = self.pending_load.take() else {
+            return;
+        };
+        self.is_loading = false;
+        let result = match task.await {
+            Ok(result) => result,
+            Err(e) => Err(anyhow::anyhow!("Loading stopped: {}", e)),
+        };
+
+        match (kind, result) {
+            (PendingLoadKind::Open, Ok(log)) => {
+                if let Err(e) = self.show_session(log).await {
+                    self.error_message = Some(format!("Failed to load session: {}", e));
+                }
+            }
+            (PendingLoadKind::Open, Err(e)) => {
+                self.error_message = Some(format!("Failed to load session: {}", e));
+            }
+            (PendingLoadKind::Refilter { anchor, .. }, Ok(log)) => {
+                self.current_log = Some(log);
+                if let Err(e) = self.count_search_matches().await {
+                    self.error_message = Some(format!("Search failed: {}", e));
+                }
+                // Keep the same packet, scroll position and compare baseline where the new filter allows
+                if let Err(e) = self.restore_selection(anchor).await {
+                    self.error_message = Some(format!("Failed to restore position: {}", e));
+                }
+            }
+            (PendingLoadKind::Refilter { previous, .. }, Err(e)) => {
+                self.restore_filter(previous);
+                self.error_message = Some(format!("Failed to load filtered packets: {}", e));
+            }
+        }
+    }
+
+    /// Stop the background load (Esc while loading), leaving the session list or the packets shown as they were
+    fn cancel_pending_load(&mut self) {
+        let Some(PendingLoad { task, kind }) = self.pending_load.take() else {
+            return;
+        };
+        task.abort();
+        self.is_loading = false;
+        if let PendingLoadKind::Refilter { previous, .. } = kind {
+            self.restore_filter(previous);
+        }
+        self.status_message = Some("Loading cancelled".to_string());
+    }
+
+    /// Go back to the filter the shown packets were loaded with
+    fn restore_filter(&mut self, filter: Option<PacketFilterSet>) {
+        self.filter_input = filter.as_ref().map(|f| f.to_string()).unwrap_or_else(|| "a".to_string());
+        self.current_filter = filter;
+    }
+
+    /// Show only the packets sent one way (`1` / `2`), or both (`0`), without typing a filter; pressing the key
+    /// of the direction already shown goes back to both
+    fn toggle_direction_filter(&mut self, direction: Option<FilterPacketDirection>) {
+        let filter = with_direction(self.current_filter.as_ref(), direction);
+        self.filter_input = filter.as_ref().map(|f| f.to_string()).unwrap_or_else(|| "a".to_string());
+        self.apply_filter(filter);
+    }
+
+    /// Filter to the current packet's type and direction (`=`), or add an exclusion of them to the filter (`-`)
+    fn filter_current_packet_type(&mut self, exclude: bool) {
+        let Some(direction) = self.current_packet().map(|p| match p.direction {
+            PacketDirection::Clientbound => FilterPacketDirection::Clientbound,
+            PacketDirection::Serverbound => FilterPacketDirection::Serverbound,
+        }) else {
+            return;
+        };
+        let Some(name) = self.current_packet_name().map(str::to_string) else {
+            self.status_message = Some("The packet wasn't decoded, so it has no type to filter on".to_string());
+            return;
+        };
+        let filter = packet_type_filter(self.current_filter.as_ref(), direction, &name, exclude);
+        self.filter_input = filter.to_string();
+        self.apply_filter(Some(filter));
+    }
+
+    /// Follow the current packet's entity through the session (filtering to the packets referencing it), or stop
+    /// following one
+    fn toggle_entity_follow(&mut self) {
+        let entity = if let Some(entity) = followed_entity(self.current_filter.as_ref()) {
+            self.status_message = Some(format!("Stopped following entity {}", entity));
+            None
+        } else {
+            let Some(entity) = self.current_packet().and_then(|p| p.packet_json.as_ref()).and_then(packet_entity_id) else {
+                self.status_message =
+                    Some("No entity id in this packet - filter with entity:<id> to follow one, e.g. a.entity:42".to_string());
+                return;
+            };
+            self.status_message = Some(format!("Following entity {} - m stops", entity));
+            Some(entity)
+        };
+        let filter = with_entity(self.current_filter.as_ref(), entity);
+        self.filter_input = filter.as_ref().map(|f| f.to_string()).unwrap_or_else(|| "a".to_string());
+        self.apply_filter(filter);
+    }
+
+    /// Start loading the selected session; it opens once loaded (see poll_pending_load)
+    fn load_session(&mut self) -> Result<()> {
+        let Some(SessionSummary { session, .. }) = self.sessions.get(self.selected_session) else {
+            return Err(anyhow::anyhow!("No session selected"));
+        };
+        self.start_load(session.id, PendingLoadKind::Open);
+        Ok(())
+    }
+
+    /// Open a packet log file in the packet view (`view <log>`), without a filter since those are queries
+    async fn open_log_file(&mut self, path: &std::path::Path) {
+        match SessionLog::open_file(path, &self.protocols) {
+            Ok(log) => {
+                self.current_filter = None;
+                self.current_search = None;
+                if let Err(e) = self.show_session(log).await {
+                    self.error_message = Some(format!("Failed to open {}: {:#}", path.display(), e));
+                }
+            }
+            Err(e) => self.error_message = Some(format!("Failed to open {}: {:#}", path.display(), e)),
+        }
+    }
+
+    /// Open a loaded session in the packet view
+    async fn show_session(&mut self, log: SessionLog) -> Result<()> {
+        self.current_log = Some(log);
+        self.protocol_override = None;
+        self.packet_index = 0;
+        self.packet_details_scroll = 0;
+        self.diff_panel_scroll = 0;
+        // A pinned baseline carries over to the new session, else compare mode starts over
+        if self.baseline_pinned {
+            self.compare_mode = true;
+            self.baseline_packet_index = None;
+            if !self.baseline_from_other_session() {
+                let number = self.baseline_packet.as_ref().and_then(|p| p.packet_number);
+                if let (Some(log), Some(number)) = (self.current_log.as_ref(), number) {
+                    self.baseline_packet_index = log.position_of(self.db.as_deref(), number).await?;
                 }
-                Err(e) => Err(e)
             }
         } else {
-            Err(anyhow::anyhow!("No session selected"))
+            self.exit_compare_mode();
         }
+        // Initialize filter input to show current filter
+        self.filter_input = self.current_filter.as_ref()
+            .map(|f| f.to_string())
+            .unwrap_or_else(|| "a".to_string());
+        self.mode = ViewerMode::PacketView;
+        self.follow = false;
+        self.playback = None;
+        self.count_search_matches().await
     }
     
     fn parse_filter(input: &str) -> Option<PacketFilterSet> {
@@ -295,7 +1707,7 @@ impl ViewerApp {
         }
         
         // Split by comma to handle multiple filters
-        let filter_strings: Vec<&str> = input.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+        let filter_strings: Vec<&str> = split_filters(input).into_iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
         
         if filter_strings.is_empty() {
             return None;
@@ -304,11 +1716,16 @@ impl ViewerApp {
         let mut filters = Vec::new();
         
         for filter_str in filter_strings {
-            // Parse format: [!][direction][.packet_name]
+            // Parse format: [!][direction][.packet_name][.@from-to][.#from-to][.$conditions]
             // !: exclusion prefix (exclude matching packets)
             // direction: c (clientbound), s (serverbound), a (all), or empty (all)
             // packet_name: optional, delimited by period
-            // packet_name can contain * for wildcard matching
+            // packet_name can contain * for wildcard matching, or be a regex between slashes (/^inventory_/)
+            // @from-to: time range since the session's first packet (e.g. @10s-20s, @1m-, @-500ms)
+            // #from-to: packet number range (e.g. #100-200, #5000-, or #150 for one packet)
+            // entity:id: packets referencing an entity by runtime (or unique) id (e.g. entity:42)
+            // $path<op>value[&...]: conditions on packet fields, last (e.g. $params.runtime_id=42)
+            // {key<op>value[,...]} after the packet name: conditions on its params (e.g. move_player{runtime_id=42})
             
             let filter_str = filter_str.trim();
             if filter_str.is_empty() {
@@ -322,13 +1739,61 @@ impl ViewerApp {
                 (false, filter_str)
             };
             
-            let (direction_char, packet_name) = if let Some(dot_pos) = filter_str_without_prefix.find('.') {
-                let dir = &filter_str_without_prefix[..dot_pos];
-                let name = &filter_str_without_prefix[dot_pos + 1..];
-                (dir, Some(name.to_string()))
-            } else {
-                (filter_str_without_prefix, None)
+            let mut parts = split_filter_parts(filter_str_without_prefix).into_iter();
+            let direction_char = parts.next().unwrap_or("");
+            let mut name_parts = Vec::new();
+            let mut time_range = None;
+            let mut packet_number_range = None;
+            let mut entity = None;
+            let mut fields = Vec::new();
+            let mut valid = true;
+            for part in parts {
+                if let Some(conditions) = part.strip_prefix('$') {
+                    for condition in conditions.split('&') {
+                        match parse_field_condition(condition) {
+                            Some(field) => fields.push(field),
+                            None => valid = false,
+                        }
+                    }
+                } else if let Some(range) = part.strip_prefix('@') {
+                    time_range = parse_filter_range(range, false, parse_filter_time);
+                    valid &= time_range.is_some();
+                } else if let Some(range) = part.strip_prefix('#') {
+                    packet_number_range = parse_filter_range(range, true, |n| n.parse().ok());
+                    valid &= packet_number_range.is_some();
+                } else if let Some(id) = part.strip_prefix("entity:") {
+                    entity = id.trim().parse().ok();
+                    valid &= entity.is_some();
+                } else {
+                    let (name, conditions) = split_name_conditions(part);
+                    if let Some(conditions) = conditions {
+                        match parse_params_conditions(conditions) {
+                            Some(conditions) => fields.extend(conditions),
+                            None => valid = false,
+                        }
+                    }
+                    if !name.is_empty() {
+                        name_parts.push(name);
+                    }
+                }
+            }
+            if !valid {
+                // Invalid range or field condition - skip this filter
+                continue;
+            }
+            // A lone * is any packet (s.*{x>100})
+            let mut packet_name = (!name_parts.is_empty()).then(|| name_parts.join(".")).filter(|name| name != "*");
+            let packet_name_is_regex = match packet_name.as_deref().and_then(parse_name_regex) {
+                Some(Ok(pattern)) => {
+                    packet_name = Some(pattern.to_string());
+                    true
+                }
+                // Invalid or unclosed regex - skip this filter
+                Some(Err(_)) => continue,
+                None => false,
             };
+            let (time_from_ms, time_to_ms) = time_range.unwrap_or_default();
+            let (packet_number_from, packet_number_to) = packet_number_range.unwrap_or_default();
             
             let direction = match direction_char.to_lowercase().as_str() {
                 "c" => Some(FilterPacketDirection::Clientbound),
@@ -341,7 +1806,7 @@ impl ViewerApp {
             };
             
             // Check if packet_name contains wildcards (*)
-            let packet_name_is_wildcard = packet_name.as_ref()
+            let packet_name_is_wildcard = !packet_name_is_regex && packet_name.as_ref()
                 .map(|name| name.contains('*'))
                 .unwrap_or(false);
             
@@ -349,7 +1814,14 @@ impl ViewerApp {
                 direction,
                 packet_name,
                 packet_name_is_wildcard,
+                packet_name_is_regex,
                 is_exclusion,
+                time_from_ms,
+                time_to_ms,
+                packet_number_from,
+                packet_number_to,
+                fields,
+                entity,
             });
         }
         
@@ -361,92 +1833,1040 @@ impl ViewerApp {
     }
 
     fn current_packet(&self) -> Option<&PacketEntry> {
-        self.current_log.as_ref()?.packets.get(self.packet_index)
+        self.current_log.as_ref()?.get(self.packet_index)
+    }
+
+    /// Parser for the protocol version the current packet was logged with, else the session's, else the default
+    fn protocol_parser(&self) -> Option<Arc<protocol::ProtocolParser>> {
+        let version = self.protocol_override.as_deref()
+            .or_else(|| self.current_packet()?.protocol_version.as_deref())
+            .or_else(|| self.current_log.as_ref()?.protocol_version.as_deref())
+            .unwrap_or(protocol::PROTOCOL_VERSION);
+        self.protocols.get(version)
     }
     
-    fn find_closest_packet_index(&self, target_packet_number: i64) -> usize {
-        if let Some(log) = &self.current_log {
-            if log.packets.is_empty() {
-                return 0;
-            }
-            
-            // Find the packet with the closest packet_number
-            let mut closest_index = 0;
-            let mut min_diff = i64::MAX;
-            
-            for (index, packet) in log.packets.iter().enumerate() {
-                if let Some(packet_num) = packet.packet_number {
-                    let diff = (packet_num - target_packet_number).abs();
-                    if diff < min_diff {
-                        min_diff = diff;
-                        closest_index = index;
-                    }
-                }
-            }
-            
-            closest_index
+    /// Make the current packet the compare mode baseline; a pinned one is kept when another session is opened
+    async fn set_baseline(&mut self, pinned: bool) {
+        let baseline = self.current_packet()
+            .filter(|p| p.packet_json.is_some())
+            .cloned();
+        let Some(baseline) = baseline else {
+            return;
+        };
+        if !self.compare_mode {
+            self.load_ignored_fields().await;
+        }
+        self.compare_mode = true;
+        self.baseline_packet_index = Some(self.packet_index);
+        self.baseline_packet = Some(baseline);
+        self.baseline_session_id = self.current_log.as_ref().map(|log| log.session_id);
+        self.baseline_pinned = pinned;
+        self.diff_previous = false;
+        self.packet_details_scroll = 0;
+        self.diff_panel_scroll = 0;
+    }
+
+    /// Compare mode against the previous packet of the current packet's type, wherever the current packet is
+    async fn start_diff_previous(&mut self) {
+        if !self.has_database() {
+            return;
+        }
+        if !self.compare_mode {
+            self.load_ignored_fields().await;
+        }
+        self.exit_compare_mode();
+        self.compare_mode = true;
+        self.diff_previous = true;
+        self.packet_details_scroll = 0;
+        self.diff_panel_scroll = 0;
+    }
+
+    /// In previous-packet mode, make the last packet of the current packet's type before it the baseline
+    /// (whatever the filter); looked up once per packet
+    async fn update_previous_baseline(&mut self) -> Result<()> {
+        let current_number = self.current_packet().and_then(|p| p.packet_number);
+        if !self.diff_previous || current_number == self.diff_previous_of {
+            return Ok(());
+        }
+        let (Some(log), Some(number)) = (self.current_log.as_ref(), current_number) else {
+            return Ok(());
+        };
+        self.diff_previous_of = Some(number);
+        self.baseline_session_id = Some(log.session_id);
+        self.baseline_packet_index = None;
+        self.baseline_packet = None;
+
+        let Some(name) = self.current_packet_name().map(str::to_string) else {
+            return Ok(());
+        };
+        let same_type = DbPacketFilterSet {
+            filters: vec![DbPacketFilter { packet_name: Some(name), ..Default::default() }],
+        };
+        let previous = self.db()?
+            .get_packet_page(log.session_id, Some(&same_type), PageCursor::Before(Some(number)), 1)
+            .await?
+            .pop()
+            .map(SessionLog::to_packet_entry)
+            .transpose()?;
+        if let Some(previous) = previous {
+            // Highlighted on the timeline when it's loaded and passes the filter
+            self.baseline_packet_index = (log.window_start..log.window_end())
+                .find(|&i| log.get(i).and_then(|p| p.packet_number) == previous.packet_number);
+            self.baseline_packet = Some(previous);
+        }
+        Ok(())
+    }
+
+    fn exit_compare_mode(&mut self) {
+        self.compare_mode = false;
+        self.baseline_packet_index = None;
+        self.baseline_packet = None;
+        self.baseline_session_id = None;
+        self.baseline_pinned = false;
+        self.diff_previous = false;
+        self.diff_previous_of = None;
+    }
+
+    /// Leave the packet view for the session list, keeping a pinned baseline for the next session
+    fn close_session(&mut self) {
+        self.mode = ViewerMode::SessionList;
+        self.current_log = None;
+        self.playback = None;
+        if self.baseline_pinned {
+            self.baseline_packet_index = None;
         } else {
-            0
+            self.exit_compare_mode();
         }
     }
 
-    fn prev_packet(&mut self) {
-        if self.packet_index > 0 {
-            self.packet_index -= 1;
-            // Reset scroll when packet changes
+    /// Whether the compare mode baseline was picked in another session than the one open
+    fn baseline_from_other_session(&self) -> bool {
+        self.baseline_session_id.is_some() && self.baseline_session_id != self.current_log.as_ref().map(|log| log.session_id)
+    }
+
+    /// Snapshot of the current viewing position, used to restore it after the log is reloaded
+    fn selection_anchor(&self) -> SelectionAnchor {
+        let packet_number = self.current_packet()
+            .and_then(|p| p.packet_number)
+            .or_else(|| {
+                self.current_log.as_ref()
+                    .and_then(|log| log.packets.first())
+                    .and_then(|p| p.packet_number)
+            });
+        let baseline_packet_number = if self.compare_mode && !self.baseline_from_other_session() && !self.diff_previous {
+            self.baseline_packet.as_ref().and_then(|p| p.packet_number)
+        } else {
+            None
+        };
+
+        SelectionAnchor {
+            packet_number,
+            packet_details_scroll: self.packet_details_scroll,
+            diff_panel_scroll: self.diff_panel_scroll,
+            baseline_packet_number,
+        }
+    }
+
+    /// Restore a viewing position in the (re)loaded log:
+    /// - select the packet with the closest packet_number
+    /// - keep scroll offsets if that exact packet is still present
+    /// - keep the compare baseline if it still matches the filter (or is from another session), otherwise leave
+    ///   compare mode
+    async fn restore_selection(&mut self, anchor: SelectionAnchor) -> Result<()> {
+        let db = self.db.as_deref();
+        let Some(log) = self.current_log.as_mut() else {
+            return Ok(());
+        };
+
+        self.packet_index = match anchor.packet_number {
+            Some(number) => log.seek(db, number).await?,
+            None => 0,
+        };
+        let baseline_index = match anchor.baseline_packet_number {
+            Some(number) => log.position_of(db, number).await?,
+            None => None,
+        };
+
+        let same_packet = anchor.packet_number.is_some()
+            && self.current_packet().and_then(|p| p.packet_number) == anchor.packet_number;
+        if same_packet {
+            self.packet_details_scroll = anchor.packet_details_scroll;
+            self.diff_panel_scroll = anchor.diff_panel_scroll;
+        } else {
             self.packet_details_scroll = 0;
             self.diff_panel_scroll = 0;
         }
+
+        match baseline_index {
+            Some(baseline_index) => {
+                self.compare_mode = true;
+                self.baseline_packet_index = Some(baseline_index);
+                // baseline_packet is unchanged - same packet
+            }
+            // Filters of this session don't apply to a baseline from another one
+            None if self.compare_mode && self.baseline_from_other_session() => {}
+            // Found again for the current packet, whose index may have changed
+            None if self.diff_previous => self.diff_previous_of = None,
+            None => self.exit_compare_mode(),
+        }
+        Ok(())
     }
 
-    fn next_packet(&mut self) {
-        if let Some(log) = &self.current_log {
-            if self.packet_index < log.packets.len().saturating_sub(1) {
-                self.packet_index += 1;
-                // Reset scroll when packet changes
+    /// Jump to the next packet matching the current search (within the filter), after the current packet
+    /// or, with `include_current`, from it; stays put and sets search_exhausted when there's none
+    async fn jump_to_next_match(&mut self, include_current: bool) -> Result<()> {
+        let after = self.current_packet()
+            .and_then(|p| p.packet_number)
+            .map(|n| if include_current { n - 1 } else { n });
+        self.jump_to_match(PageCursor::After(after)).await
+    }
+
+    /// Jump to the last packet matching the current search before the current packet
+    async fn jump_to_previous_match(&mut self) -> Result<()> {
+        let before = self.current_packet().and_then(|p| p.packet_number);
+        self.jump_to_match(PageCursor::Before(before)).await
+    }
+
+    async fn jump_to_match(&mut self, cursor: PageCursor) -> Result<()> {
+        let db = self.db.as_deref();
+        let (Some(search), Some(log)) = (self.current_search.as_ref(), self.current_log.as_mut()) else {
+            return Ok(());
+        };
+
+        let found = database(db)?.search_packets(log.session_id, log.filter.as_ref(), search, cursor, 1).await?;
+        self.search_exhausted = found.is_empty();
+        if let Some(packet) = found.first() {
+            self.packet_index = log.seek(db, packet.packet_number).await?;
+            self.packet_details_scroll = 0;
+            self.diff_panel_scroll = 0;
+            let earlier = database(db)?
+                .count_search_matches(log.session_id, log.filter.as_ref(), search, Some(packet.packet_number))
+                .await?;
+            self.search_match = Some((packet.packet_number, earlier + 1));
+        }
+        Ok(())
+    }
+
+    /// Count the current search's matches in the current session and filter, for the header
+    async fn count_search_matches(&mut self) -> Result<()> {
+        self.search_match = None;
+        self.search_total = match (self.current_search.as_ref(), self.current_log.as_ref()) {
+            (Some(search), Some(log)) => {
+                Some(self.db()?.count_search_matches(log.session_id, log.filter.as_ref(), search, None).await?)
+            }
+            _ => None,
+        };
+        Ok(())
+    }
+
+    /// Bookmark the current packet, or remove its bookmark
+    async fn toggle_bookmark(&mut self) -> Result<()> {
+        let Some(packet_number) = self.current_packet().and_then(|p| p.packet_number) else {
+            return Ok(());
+        };
+        let Some(log) = self.current_log.as_mut() else {
+            return Ok(());
+        };
+        if log.bookmarks.contains_key(&packet_number) {
+            database(self.db.as_deref())?.delete_packet_bookmark(log.session_id, packet_number).await?;
+            log.bookmarks.remove(&packet_number);
+        } else {
+            let bookmark = PacketBookmark { packet_number, label: None, color: BookmarkColor::default() };
+            database(self.db.as_deref())?.set_packet_bookmark(log.session_id, &bookmark).await?;
+            log.bookmarks.insert(packet_number, bookmark);
+        }
+        Ok(())
+    }
+
+    /// Save a changed label or color of a bookmark
+    async fn update_bookmark(&mut self, bookmark: PacketBookmark) -> Result<()> {
+        let Some(log) = self.current_log.as_mut() else {
+            return Ok(());
+        };
+        database(self.db.as_deref())?.set_packet_bookmark(log.session_id, &bookmark).await?;
+        log.bookmarks.insert(bookmark.packet_number, bookmark);
+        Ok(())
+    }
+
+    /// Go to a packet number (the closest packet passing the filter) or to the first packet from a time; times
+    /// past the last packet go to the last packet
+    async fn goto(&mut self, target: GotoTarget) -> Result<()> {
+        let Some(log) = self.current_log.as_ref() else {
+            return Ok(());
+        };
+        let ts = match target {
+            GotoTarget::Packet(packet_number) => return self.jump_to_packet(packet_number).await,
+            GotoTarget::SessionTime(ms) => log.start_time + ms,
+            GotoTarget::Offset(ms) => match self.current_packet() {
+                Some(packet) => packet.timestamp + ms,
+                None => return Ok(()),
+            },
+        };
+        if log.log_file.is_some() {
+            self.packet_index = log.file_position_at(ts).unwrap_or(log.len().saturating_sub(1));
+            self.packet_details_scroll = 0;
+            self.diff_panel_scroll = 0;
+            self.ensure_packets_loaded().await;
+            return Ok(());
+        }
+        let ts = DateTime::from_timestamp_millis(ts.max(log.start_time))
+            .ok_or_else(|| anyhow::anyhow!("Time out of range"))?;
+        match self.db()?.packet_at_time(log.session_id, log.filter.as_ref(), ts).await? {
+            Some(packet_number) => self.jump_to_packet(packet_number).await,
+            None => {
+                self.packet_index = log.len().saturating_sub(1);
                 self.packet_details_scroll = 0;
                 self.diff_panel_scroll = 0;
+                Ok(())
             }
         }
     }
 
-    fn toggle_json_path(&mut self, path: &str) {
-        if self.json_expanded_paths.contains(path) {
-            self.json_expanded_paths.remove(path);
+    /// Go to a bookmarked packet (the closest one if the filter hides it)
+    async fn jump_to_packet(&mut self, packet_number: i64) -> Result<()> {
+        let db = self.db.as_deref();
+        let Some(log) = self.current_log.as_mut() else {
+            return Ok(());
+        };
+        self.packet_index = log.seek(db, packet_number).await?;
+        self.packet_details_scroll = 0;
+        self.diff_panel_scroll = 0;
+        Ok(())
+    }
+
+    /// Bookmark after (or before) the current packet, if any
+    fn adjacent_bookmark(&self, forward: bool) -> Option<i64> {
+        let current = self.current_packet()?.packet_number?;
+        let bookmarks = &self.current_log.as_ref()?.bookmarks;
+        if forward {
+            bookmarks.range(current + 1..).next().map(|(&number, _)| number)
         } else {
-            self.json_expanded_paths.insert(path.to_string());
+            bookmarks.range(..current).next_back().map(|(&number, _)| number)
         }
     }
 
-    fn is_json_path_expanded(&self, path: &str) -> bool {
-        self.json_expanded_paths.contains(path)
+    /// Open the statistics panel for a session
+    async fn open_statistics(&mut self, session_id: i32, from_packet_view: bool) {
+        let loaded = async { self.db()?.get_packet_stats(session_id, None).await };
+        match loaded.await {
+            Ok(stats) => {
+                self.statistics = Some(StatisticsState {
+                    session_id,
+                    stats,
+                    sort: StatsSort::default(),
+                    scroll: 0,
+                    from_packet_view,
+                    filter: None,
+                });
+                self.mode = ViewerMode::Statistics;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to load statistics: {}", e));
+            }
+        }
     }
-}
 
-#[derive(Debug, Clone)]
-enum JsonDiff {
-    Added(serde_json::Value),
-    Removed(serde_json::Value),
-    Modified {
-        old: serde_json::Value,
-        new: serde_json::Value,
-    },
-    Unchanged(serde_json::Value),
-    ObjectDiff(BTreeMap<String, JsonDiff>),
-    ArrayDiff(Vec<JsonDiff>),
-}
+    /// Open the statistics panel for the packets of the current session that pass its filter
+    async fn open_filtered_statistics(&mut self) {
+        let Some(log) = &self.current_log else {
+            return;
+        };
+        let session_id = log.session_id;
+        let loaded = async { self.db()?.get_packet_stats(session_id, log.filter.as_ref()).await };
+        match loaded.await {
+            Ok(stats) => {
+                self.statistics = Some(StatisticsState {
+                    session_id,
+                    stats,
+                    sort: StatsSort::default(),
+                    scroll: 0,
+                    from_packet_view: true,
+                    filter: self.current_filter.as_ref().map(|f| f.to_string()),
+                });
+                self.mode = ViewerMode::Statistics;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to load statistics: {}", e));
+            }
+        }
+    }
 
-fn compare_json(baseline: &serde_json::Value, current: &serde_json::Value) -> JsonDiff {
-    match (baseline, current) {
-        // Both are objects - compare keys
-        (serde_json::Value::Object(baseline_obj), serde_json::Value::Object(current_obj)) => {
-            let mut diff_map = BTreeMap::new();
-            let mut all_keys: BTreeSet<&String> = baseline_obj.keys().collect();
-            all_keys.extend(current_obj.keys());
-            
-            for key in all_keys {
-                match (baseline_obj.get(key), current_obj.get(key)) {
-                    (Some(b_val), Some(c_val)) => {
+    /// Open (or refresh) the database panel
+    async fn open_database_health(&mut self) {
+        let loaded = async { self.db()?.health().await };
+        match loaded.await {
+            Ok(health) => {
+                self.db_health = Some(health);
+                self.mode = ViewerMode::DatabaseHealth;
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to load database health: {:#}", e));
+            }
+        }
+    }
+
+    /// Archive the selected session, or restore it if it's archived; it leaves the list unless the search
+    /// includes both (archived:include)
+    async fn toggle_session_archived(&mut self) {
+        let Some(summary) = self.sessions.get_mut(self.selected_session) else {
+            return;
+        };
+        let (session_id, archived) = (summary.session.id, !summary.archived);
+        let saved = match database(self.db.as_deref()) {
+            Ok(db) => db.set_session_archived(session_id, archived).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = saved {
+            let action = if archived { "archive" } else { "restore" };
+            self.error_message = Some(format!("Failed to {} session: {}", action, e));
+            return;
+        }
+        summary.archived = archived;
+        let included = self.current_session_search.as_ref().is_some_and(|search| search.archived == ArchivedSessions::Included);
+        if !included {
+            self.sessions.retain(|summary| summary.session.id != session_id);
+            self.selected_session = self.selected_session.min(self.sessions.len().saturating_sub(1));
+        }
+        self.status_message = Some(if archived {
+            format!("Archived session #{} - search archived:only to see archived sessions, where A restores them", session_id)
+        } else {
+            format!("Restored session #{}", session_id)
+        });
+    }
+
+    /// Write a session to session-<id>.lpsession in the working directory (`import-session` reads it back)
+    async fn export_session_archive(&mut self, session_id: i32) {
+        let path = session_archive::default_archive_path(session_id);
+        let exported = async { session_archive::export_session(self.db()?, session_id, &path).await };
+        match exported.await {
+            Ok(count) => {
+                self.error_message = None;
+                self.status_message = Some(format!("Exported session #{} ({} packets) to {}", session_id, count, path.display()));
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Failed to export session: {:#}", e));
+            }
+        }
+    }
+
+    /// Write the current packet, or every packet passing the filter, to a file (see packet_export.rs)
+    async fn export_packets(&mut self, path: &std::path::Path) {
+        let Some(log) = &self.current_log else {
+            return;
+        };
+        let result = if self.export_all {
+            async { packet_export::export_packets(self.db()?, log.session_id, log.filter.as_ref(), path)
+                .await
+                .map(|count| format!("Exported {} packets to {}", count, path.display())) }
+                .await
+        } else {
+            match self.current_packet().and_then(|p| p.packet_number) {
+                Some(number) => async { packet_export::export_packet(self.db()?, log.session_id, number, path)
+                    .await
+                    .map(|()| format!("Exported packet {} to {}", number, path.display())) }
+                    .await,
+                None => return,
+            }
+        };
+        match result {
+            Ok(message) => self.status_message = Some(message),
+            Err(e) => self.error_message = Some(format!("Failed to export packets: {:#}", e)),
+        }
+    }
+
+    fn current_packet_name(&self) -> Option<&str> {
+        self.current_packet()?.packet_json.as_ref()?.get("name")?.as_str()
+    }
+
+    /// The baseline's and the current packet's JSON as compare mode diffs them: without the fields ignored for the
+    /// current packet's type
+    fn compared_json(&self) -> (Option<serde_json::Value>, Option<serde_json::Value>) {
+        let mut baseline = self.baseline_packet.as_ref().and_then(|p| p.packet_json.clone());
+        let mut current = self.current_packet().and_then(|p| p.packet_json.clone());
+        let ignored = self.current_packet_name().and_then(|name| self.ignored_fields.get(name));
+        for path in ignored.into_iter().flatten() {
+            for json in baseline.iter_mut().chain(current.iter_mut()) {
+                strip_field(json, path);
+            }
+        }
+        (baseline, current)
+    }
+
+    /// Load the fields compare mode leaves out; without a database none are
+    async fn load_ignored_fields(&mut self) {
+        let Some(db) = self.db.as_deref() else {
+            return;
+        };
+        match db.get_ignored_fields().await {
+            Ok(fields) => self.ignored_fields = fields,
+            Err(e) => self.error_message = Some(format!("Failed to load ignored fields: {:#}", e)),
+        }
+    }
+
+    /// Open the ignored fields panel for the current packet's type
+    async fn open_ignored_fields(&mut self) {
+        let Some(packet_name) = self.current_packet_name().map(str::to_string) else {
+            return;
+        };
+        self.load_ignored_fields().await;
+        let mut fields = self.ignored_fields.get(&packet_name).cloned().unwrap_or_default();
+        // Offer the fields that differ from the baseline, compared in full
+        let baseline = self.baseline_packet.as_ref().and_then(|p| p.packet_json.as_ref());
+        let current = self.current_packet().and_then(|p| p.packet_json.as_ref());
+        if let (true, Some(baseline), Some(current)) = (self.compare_mode, baseline, current) {
+            changed_fields(&compare_json(baseline, current), "", &mut fields);
+        }
+        self.ignored_fields_panel = Some(IgnoredFieldsState {
+            packet_name,
+            fields: fields.into_iter().collect(),
+            selected: 0,
+            path_input: None,
+        });
+        self.mode = ViewerMode::IgnoredFields;
+    }
+
+    /// Open the movement map on the current session's player path
+    async fn open_movement_map(&mut self) {
+        let Some(session_id) = self.current_log.as_ref().map(|log| log.session_id) else {
+            return;
+        };
+        // Loaded each time, since a live session's path keeps growing
+        let loaded = async { movement_map::load_player_path(self.db()?, session_id).await };
+        match loaded.await {
+            Ok(path) => {
+                self.movement_map = Some(MovementMapState { path });
+                self.mode = ViewerMode::MovementMap;
+            }
+            Err(e) => self.error_message = Some(format!("Failed to load the player's movement: {}", e)),
+        }
+    }
+
+    /// Go to the packet of the next (or previous) position on the movement map
+    async fn step_movement_map(&mut self, forward: bool) -> Result<()> {
+        let (Some(map), Some(current)) = (&self.movement_map, self.current_packet().and_then(|p| p.packet_number)) else {
+            return Ok(());
+        };
+        match movement_map::adjacent_point(&map.path, current, forward) {
+            Some(point) => self.jump_to_packet(point.packet_number).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Open the gaps panel: the largest gaps between packets passing the filter, and the session's round trips
+    async fn open_gaps(&mut self) {
+        let Some(log) = &self.current_log else {
+            return;
+        };
+        let session_id = log.session_id;
+        let loaded = async {
+            let gaps = self.db()?.get_packet_gaps(session_id, log.filter.as_ref(), GAP_LIMIT).await?;
+            let round_trips = packet_latency::load_correlation(self.db()?, session_id).await?.stats;
+            anyhow::Ok((gaps, round_trips))
+        };
+        match loaded.await {
+            Ok((gaps, round_trips)) => {
+                self.gaps = Some(GapsState {
+                    gaps,
+                    round_trips,
+                    selected: 0,
+                    filter: self.current_filter.as_ref().map(|f| f.to_string()),
+                });
+                self.mode = ViewerMode::Gaps;
+            }
+            Err(e) => self.error_message = Some(format!("Failed to load gaps: {}", e)),
+        }
+    }
+
+    /// Go to the selected row of the gaps panel: the packet after a gap, or a pair's slowest request
+    async fn jump_to_gap(&mut self) -> Result<()> {
+        let Some(panel) = &self.gaps else {
+            return Ok(());
+        };
+        let target = match panel.gaps.get(panel.selected) {
+            Some(gap) => Some(gap.packet_number),
+            None => panel.round_trips.get(panel.selected - panel.gaps.len()).map(|round_trip| round_trip.slowest),
+        };
+        match target {
+            Some(number) => {
+                self.gaps = None;
+                self.mode = ViewerMode::PacketView;
+                self.jump_to_packet(number).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Open the sequence diagram of the session's handshake, or of the packets from the current one on
+    async fn open_sequence_diagram(&mut self, handshake: bool) {
+        let Some(log) = &self.current_log else {
+            return;
+        };
+        let session_id = log.session_id;
+        let loaded = async {
+            if handshake {
+                self.db()?.get_packet_page(session_id, None, PageCursor::After(None), SEQUENCE_PACKETS).await
+            } else {
+                let after = self.current_packet().and_then(|packet| packet.packet_number).map(|number| number - 1);
+                self.db()?.get_packet_page(session_id, log.filter.as_ref(), PageCursor::After(after), SEQUENCE_PACKETS).await
+            }
+        };
+        match loaded.await {
+            Ok(page) => {
+                let packets = if handshake {
+                    sequence_diagram::handshake(&page)
+                } else {
+                    page.iter().map(sequence_diagram::SequencePacket::from).collect()
+                };
+                let missing = if handshake { sequence_diagram::missing_steps(&packets) } else { Vec::new() };
+                self.sequence_diagram = Some(SequenceDiagramState { packets, handshake, missing, selected: 0 });
+                self.mode = ViewerMode::SequenceDiagram;
+            }
+            Err(e) => self.error_message = Some(format!("Failed to load the sequence diagram: {}", e)),
+        }
+    }
+
+    /// Copy the sequence diagram as text
+    fn copy_sequence_diagram(&mut self) {
+        let Some(diagram) = &self.sequence_diagram else {
+            return;
+        };
+        let text = sequence_diagram::diagram_text(&diagram.packets, SEQUENCE_COPY_WIDTH);
+        let what = if diagram.handshake { "the handshake's sequence diagram" } else { "the sequence diagram" };
+        self.set_clipboard(text, what);
+    }
+
+    /// Open the exchanges panel, at the current packet's exchange if it's a request or a response
+    async fn open_exchanges(&mut self) {
+        let Some(session_id) = self.current_log.as_ref().map(|log| log.session_id) else {
+            return;
+        };
+        let loaded = async { packet_latency::load_correlation(self.db()?, session_id).await };
+        match loaded.await {
+            Ok(correlation) => {
+                let exchanges = correlation.exchanges;
+                let selected = self.current_packet()
+                    .and_then(|packet| packet.packet_number)
+                    .and_then(|number| packet_latency::exchange_of(&exchanges, number))
+                    .unwrap_or(0);
+                self.exchanges = Some(ExchangesState { exchanges, selected });
+                self.mode = ViewerMode::Exchanges;
+            }
+            Err(e) => self.error_message = Some(format!("Failed to load exchanges: {}", e)),
+        }
+    }
+
+    /// Go to the request (or response) of the selected exchange, leaving the panel open to go to the other
+    async fn jump_to_exchange(&mut self, response: bool) -> Result<()> {
+        let number = self.exchanges.as_ref()
+            .and_then(|panel| panel.exchanges.get(panel.selected))
+            .map(|exchange| if response { exchange.response } else { exchange.request });
+        match number {
+            Some(number) => self.jump_to_packet(number).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Open the decode errors panel on the current session's packets that failed to decode
+    async fn open_decode_errors(&mut self) {
+        let Some(session_id) = self.current_log.as_ref().map(|log| log.session_id) else {
+            return;
+        };
+        let loaded = async { decode_errors::load_decode_problems(self.db()?, session_id, &self.protocols).await };
+        match loaded.await {
+            Ok(problems) => {
+                self.decode_errors = Some(DecodeErrorsState { problems, selected: 0 });
+                self.mode = ViewerMode::DecodeErrors;
+            }
+            Err(e) => self.error_message = Some(format!("Failed to load decode errors: {}", e)),
+        }
+    }
+
+    /// The current packet's payload decoded with another protocol version
+    fn decode_with_version(&self, version: &str) -> Result<(serde_json::Value, Option<String>)> {
+        let packet = self.current_packet().ok_or_else(|| anyhow::anyhow!("No packet"))?;
+        let stored = packet.packet_json.as_ref().ok_or_else(|| anyhow::anyhow!("The packet has no stored JSON"))?;
+        let logged = packet.protocol_version.as_deref().and_then(|version| self.protocols.get(version));
+        let data = version_switch::raw_payload(stored, logged.as_deref())?;
+        let parser = self.protocols.get(version).ok_or_else(|| anyhow::anyhow!("No protocol definitions for {}", version))?;
+        Ok(version_switch::decode_with(&data, packet.direction, &parser))
+    }
+
+    /// Open the protocol version switcher on the current packet, at the version it's decoded with
+    fn open_protocol_versions(&mut self) {
+        let versions = self.protocols.versions();
+        if versions.is_empty() {
+            self.error_message = Some("No protocol definitions to decode with".to_string());
+            return;
+        }
+        let logged = self.current_packet().and_then(|packet| packet.protocol_version.clone());
+        let in_use = self.protocol_override.as_ref().or(logged.as_ref());
+        let selected = in_use.and_then(|version| versions.iter().position(|v| v == version)).unwrap_or(0);
+        let comparison = self.decode_with_version(&versions[selected]).map_err(|e| e.to_string());
+        self.protocol_versions = Some(ProtocolVersionsState { versions, selected, logged, comparison, scroll: 0 });
+        self.mode = ViewerMode::ProtocolVersions;
+    }
+
+    /// Compare the current packet with its decoding under another version in the switcher
+    fn select_protocol_version(&mut self, selected: usize) {
+        let Some(version) = self.protocol_versions.as_ref().and_then(|panel| panel.versions.get(selected).cloned()) else {
+            return;
+        };
+        let comparison = self.decode_with_version(&version).map_err(|e| e.to_string());
+        if let Some(panel) = self.protocol_versions.as_mut() {
+            panel.selected = selected;
+            panel.comparison = comparison;
+            panel.scroll = 0;
+        }
+    }
+
+    /// Decode the session's packets with the version selected in the switcher (their own again for the logged one)
+    fn use_protocol_version(&mut self) {
+        let Some(panel) = self.protocol_versions.take() else {
+            return;
+        };
+        let version = panel.versions[panel.selected].clone();
+        self.protocol_override = (Some(&version) != panel.logged.as_ref()).then(|| version.clone());
+        self.status_message = Some(match self.protocol_override {
+            Some(_) => format!("Decoding packets with protocol {}", version),
+            None => "Decoding packets with the versions they were logged with".to_string(),
+        });
+        self.mode = ViewerMode::PacketView;
+    }
+
+    /// Pin a JSON path to the watch panel, or unpin it if it's there; an empty path empties the panel
+    fn toggle_watched_path(&mut self, path: &str) {
+        let path = path.trim();
+        if path.is_empty() {
+            self.watched_paths.clear();
+        } else if let Some(position) = self.watched_paths.iter().position(|watched| watched == path) {
+            self.watched_paths.remove(position);
+        } else {
+            self.watched_paths.push(path.to_string());
+        }
+    }
+
+    /// Watched paths' values at the current packet, or the last loaded packet before it that has them
+    fn watch_rows(&self) -> Vec<WatchRow> {
+        let Some(log) = &self.current_log else {
+            return Vec::new();
+        };
+        let current = self.current_packet().and_then(|packet| packet.packet_number);
+        self.watched_paths
+            .iter()
+            .map(|path| {
+                let mut found = (0..=self.packet_index)
+                    .rev()
+                    .map_while(|index| log.get(index))
+                    .filter_map(|packet| Some((packet.packet_number, packet_watch::watched_value(packet.packet_json.as_ref()?, path)?)));
+                let latest = found.next();
+                let previous = found.next();
+                WatchRow {
+                    path: path.clone(),
+                    value: latest.map(|(_, value)| packet_watch::format_value(value)),
+                    from: latest.and_then(|(number, _)| number).filter(|number| Some(*number) != current),
+                    change: latest.zip(previous).and_then(|((_, value), (_, previous))| packet_watch::change(previous, value)),
+                }
+            })
+            .collect()
+    }
+
+    /// Open the linked hex / JSON view of the current packet, decoding it with its version's definitions
+    fn open_hex_link(&mut self) {
+        let (Some(parser), Some(packet)) = (self.protocol_parser(), self.current_packet()) else {
+            return;
+        };
+        let decoded = parser.decode_packet(&packet.data, packet.direction);
+        if decoded.spans.is_empty() {
+            self.error_message = Some(match decoded.error {
+                Some(error) => format!("No decoded fields to link to the hex dump: {}", error),
+                None => "No decoded fields to link to the hex dump".to_string(),
+            });
+            return;
+        }
+        let fields: Vec<_> = decoded.spans.into_iter().collect();
+        let values = fields
+            .iter()
+            .map(|(path, _)| {
+                field_value(&decoded.fields, path)
+                    .filter(|value| !value.is_object() && !value.is_array())
+                    .map(|value| value.to_string())
+            })
+            .collect();
+        let mut link = HexLinkState {
+            data: packet.data.clone(),
+            fields,
+            values,
+            error: decoded.error.map(|error| format!("Decode error at 0x{:04x}: {}", error.offset, error)),
+            selected: 0,
+            cursor: 0,
+            hex_focus: false,
+            hex_scroll: 0,
+            hex_area: None,
+        };
+        link.select(0);
+        self.hex_link = Some(link);
+        self.mode = ViewerMode::HexLink;
+    }
+
+    /// Ignore a field of a packet type in compare mode, or compare it again if it's ignored
+    async fn toggle_ignored_field(&mut self, packet_name: &str, path: &str) -> Result<()> {
+        let ignored = self.ignored_fields.entry(packet_name.to_string()).or_default();
+        if ignored.contains(path) {
+            database(self.db.as_deref())?.remove_ignored_field(packet_name, path).await?;
+            ignored.remove(path);
+        } else {
+            database(self.db.as_deref())?.add_ignored_field(packet_name, path).await?;
+            ignored.insert(path.to_string());
+        }
+        Ok(())
+    }
+
+    /// Copy the diff from the baseline in compare mode, else the current packet's JSON, to the system clipboard
+    fn copy_to_clipboard(&mut self) {
+        let Some(packet) = self.current_packet() else {
+            return;
+        };
+        let number = packet.packet_number.unwrap_or(self.packet_index as i64 + 1);
+        let baseline = self.baseline_packet.as_ref().filter(|_| self.compare_mode && self.baseline_packet_index != Some(self.packet_index));
+        let (text, what) = match (&packet.packet_json, baseline.and_then(|b| Some((b.packet_number, b.packet_json.as_ref()?)))) {
+            (Some(_), Some((baseline_number, _))) => {
+                let (Some(baseline_json), Some(json)) = self.compared_json() else {
+                    return;
+                };
+                let baseline_session = match self.baseline_session_id {
+                    Some(session_id) if self.baseline_from_other_session() => format!("session #{} ", session_id),
+                    _ => String::new(),
+                };
+                (
+                    json_diff_text(&baseline_json, &json),
+                    format!("the diff of packet {} from {}packet {}", number, baseline_session, baseline_number.unwrap_or_default()),
+                )
+            }
+            (Some(json), None) => (
+                serde_json::to_string_pretty(json).unwrap_or_default(),
+                format!("packet {}", number),
+            ),
+            (None, _) => {
+                self.error_message = Some(format!("Packet {} has no JSON to copy", number));
+                return;
+            }
+        };
+
+        self.set_clipboard(text, &what);
+    }
+
+    /// Put text on the clipboard, saying what it was in the status line
+    fn set_clipboard(&mut self, text: String, what: &str) {
+        let result = match self.clipboard.as_mut() {
+            Some(clipboard) => clipboard.set_text(text),
+            None => arboard::Clipboard::new().and_then(|clipboard| {
+                self.clipboard.insert(clipboard).set_text(text)
+            }),
+        };
+        match result {
+            Ok(()) => self.status_message = Some(format!("Copied {} to the clipboard", what)),
+            Err(e) => self.error_message = Some(format!("Failed to copy to the clipboard: {}", e)),
+        }
+    }
+
+    /// Fetch the pages around the current packet when navigation leaves the loaded window
+    async fn ensure_packets_loaded(&mut self) {
+        let db = self.db.as_deref();
+        if let Some(log) = self.current_log.as_mut() {
+            if let Err(e) = log.ensure_loaded(db, self.packet_index).await {
+                self.error_message = Some(format!("Failed to load packets: {:#}", e));
+            }
+        }
+    }
+
+    /// Load the packet rate of the current session for the histogram, when it's shown and not loaded yet (or
+    /// new packets have arrived since)
+    async fn update_rate_histogram(&mut self) -> Result<()> {
+        if !self.show_rate_histogram {
+            return Ok(());
+        }
+        let Some(log) = self.current_log.as_mut() else {
+            return Ok(());
+        };
+        if log.rate.is_none() {
+            log.rate = Some(database(self.db.as_deref())?.get_packet_rate(log.session_id, log.filter.as_ref()).await?);
+        }
+        Ok(())
+    }
+
+    /// Load the session's packet names for completing them in the filter, if they aren't already
+    async fn load_packet_types(&mut self) -> Result<()> {
+        let Some(log) = self.current_log.as_mut() else {
+            return Ok(());
+        };
+        if log.packet_types.is_none() {
+            log.packet_types = Some(database(self.db.as_deref())?.get_packet_stats(log.session_id, None).await?);
+        }
+        Ok(())
+    }
+
+    /// Packet names completing the one being typed in the filter, from the session and the loaded protocol
+    fn filter_suggestions(&self) -> Vec<FilterSuggestion> {
+        let parser = self.protocol_parser();
+        let packet_types = self.current_log.as_ref().and_then(|log| log.packet_types.as_deref()).unwrap_or_default();
+        filter_name_suggestions(&self.filter_input, parser.as_deref(), packet_types)
+    }
+
+    /// Pick up packets captured since the last poll of a live session, moving to the newest one while following
+    /// and on the newest packet (moving back from it pauses following until `End`)
+    async fn poll_live_session(&mut self) {
+        let Some(log) = self.current_log.as_mut() else {
+            return;
+        };
+        if !log.live || self.last_live_poll.is_some_and(|polled| polled.elapsed() < LIVE_POLL_INTERVAL) {
+            return;
+        }
+        self.last_live_poll = Some(Instant::now());
+
+        let on_newest = self.packet_index + 1 >= log.len();
+        let added = match log.poll_new_packets(self.db.as_deref()).await {
+            Ok(added) => added,
+            Err(e) => {
+                self.error_message = Some(format!("Failed to check for new packets: {:#}", e));
+                return;
+            }
+        };
+        if !log.live {
+            self.follow = false;
+        }
+        if added == 0 {
+            return;
+        }
+        if self.follow && on_newest {
+            self.packet_index = log.len() - 1;
+            self.packet_details_scroll = 0;
+            self.diff_panel_scroll = 0;
+            self.ensure_packets_loaded().await;
+        }
+        if let Err(e) = self.count_search_matches().await {
+            self.error_message = Some(format!("Search failed: {}", e));
+        }
+    }
+
+    /// Start playback from the current packet, or pause it. On the last packet of a finished session it starts over
+    /// from the first
+    async fn toggle_playback(&mut self) {
+        if self.playback.take().is_some() {
+            self.status_message = Some("Playback paused".to_string());
+            return;
+        }
+        let Some(log) = &self.current_log else {
+            return;
+        };
+        if !log.live && self.packet_index + 1 >= log.len() {
+            self.packet_index = 0;
+            self.packet_details_scroll = 0;
+            self.diff_panel_scroll = 0;
+            self.ensure_packets_loaded().await;
+        }
+        if let Some(packet) = self.current_packet() {
+            self.playback = Some(packet_playback::Playback::new(self.packet_index, packet.timestamp, Instant::now()));
+        }
+    }
+
+    fn change_playback_speed(&mut self, faster: bool) {
+        self.playback_speed = if faster {
+            (self.playback_speed + 1).min(packet_playback::SPEEDS.len() - 1)
+        } else {
+            self.playback_speed.saturating_sub(1)
+        };
+        self.status_message = Some(format!("Playback speed: {}", packet_playback::speed_label(self.playback_speed)));
+    }
+
+    /// Move playback on to the packets its clock has reached, stopping at the last packet of a finished session
+    async fn advance_playback(&mut self) {
+        let Some(mut playback) = self.playback.take() else {
+            return;
+        };
+        let (Some(log), Some(current)) = (self.current_log.as_ref(), self.current_packet()) else {
+            return;
+        };
+        let now = Instant::now();
+        // Packets picked while playing carry on from there
+        playback.follow_index(self.packet_index, current.timestamp, now);
+        let mut index = self.packet_index;
+        if let Some(next) = log.get(index + 1) {
+            let played = playback.tick(now, packet_playback::SPEEDS[self.playback_speed], next.timestamp);
+            while log.get(index + 1).is_some_and(|next| next.timestamp <= played) {
+                index += 1;
+            }
+        } else if !log.live && index + 1 >= log.len() {
+            self.status_message = Some("Playback reached the last packet".to_string());
+            return;
+        }
+        // Otherwise the next packet is still loading, or yet to be captured
+        if index != self.packet_index {
+            self.packet_index = index;
+            self.packet_details_scroll = 0;
+            self.diff_panel_scroll = 0;
+            playback.moved_to(index);
+        }
+        self.playback = Some(playback);
+        self.ensure_packets_loaded().await;
+    }
+
+    fn prev_packet(&mut self) {
+        if self.packet_index > 0 {
+            self.packet_index -= 1;
+            // Reset scroll when packet changes
+            self.packet_details_scroll = 0;
+            self.diff_panel_scroll = 0;
+        }
+    }
+
+    fn next_packet(&mut self) {
+        if let Some(log) = &self.current_log {
+            if self.packet_index < log.len().saturating_sub(1) {
+                self.packet_index += 1;
+                // Reset scroll when packet changes
+                self.packet_details_scroll = 0;
+                self.diff_panel_scroll = 0;
+            }
+        }
+    }
+
+    fn toggle_json_path(&mut self, path: &str) {
+        if self.json_expanded_paths.contains(path) {
+            self.json_expanded_paths.remove(path);
+        } else {
+            self.json_expanded_paths.insert(path.to_string());
+        }
+    }
+
+    fn is_json_path_expanded(&self, path: &str) -> bool {
+        self.json_expanded_paths.contains(path)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum JsonDiff {
+    Added(serde_json::Value),
+    Removed(serde_json::Value),
+    Modified {
+        old: serde_json::Value,
+        new: serde_json::Value,
+    },
+    Unchanged(serde_json::Value),
+    ObjectDiff(BTreeMap<String, JsonDiff>),
+    ArrayDiff(Vec<JsonDiff>),
+}
+
+fn compare_json(baseline: &serde_json::Value, current: &serde_json::Value) -> JsonDiff {
+    match (baseline, current) {
+        // Both are objects - compare keys
+        (serde_json::Value::Object(baseline_obj), serde_json::Value::Object(current_obj)) => {
+            let mut diff_map = BTreeMap::new();
+            let mut all_keys: BTreeSet<&String> = baseline_obj.keys().collect();
+            all_keys.extend(current_obj.keys());
+            
+            for key in all_keys {
+                match (baseline_obj.get(key), current_obj.get(key)) {
+                    (Some(b_val), Some(c_val)) => {
                         if b_val == c_val {
                             // Values are identical - skip (will be hidden)
                         } else {
@@ -516,81 +2936,81 @@ fn compare_json(baseline: &serde_json::Value, current: &serde_json::Value) -> Js
     }
 }
 
-fn format_json_diff(diff: &JsonDiff, path: &str, indent: usize) -> Vec<(String, Color)> {
-    let indent_str = "  ".repeat(indent);
-    let mut result = Vec::new();
-    
-    match diff {
-        JsonDiff::Added(value) => {
-            let json_str = serde_json::to_string_pretty(value)
-                .unwrap_or_else(|_| format!("{:?}", value));
-            let lines: Vec<&str> = json_str.lines().collect();
-            for (i, line) in lines.iter().enumerate() {
-                let prefix = if i == 0 {
-                    if path.is_empty() {
-                        format!("{}+ ", indent_str)
-                    } else {
-                        format!("{}+ {}: ", indent_str, path)
-                    }
-                } else {
-                    format!("{}  + ", indent_str)
-                };
-                result.push((format!("{}{}", prefix, line), Color::Green));
+/// Remove the field at a dotted path from JSON; arrays on the way have it removed from each of their elements
+fn strip_field(value: &mut serde_json::Value, path: &str) {
+    let (key, rest) = match path.split_once('.') {
+        Some((key, rest)) => (key, Some(rest)),
+        None => (path, None),
+    };
+    match value {
+        serde_json::Value::Object(map) => match rest {
+            Some(rest) => {
+                if let Some(child) = map.get_mut(key) {
+                    strip_field(child, rest);
+                }
+            }
+            None => {
+                map.remove(key);
+            }
+        },
+        serde_json::Value::Array(items) => {
+            for item in items {
+                strip_field(item, path);
             }
         }
-        JsonDiff::Removed(value) => {
-            let json_str = serde_json::to_string_pretty(value)
-                .unwrap_or_else(|_| format!("{:?}", value));
-            let lines: Vec<&str> = json_str.lines().collect();
-            for (i, line) in lines.iter().enumerate() {
-                let prefix = if i == 0 {
-                    if path.is_empty() {
-                        format!("{}- ", indent_str)
-                    } else {
-                        format!("{}- {}: ", indent_str, path)
-                    }
-                } else {
-                    format!("{}  - ", indent_str)
-                };
-                result.push((format!("{}{}", prefix, line), Color::Red));
+        _ => {}
+    }
+}
+
+/// Paths of the fields that differ, as strip_field takes them (elements of an array share its path)
+fn changed_fields(diff: &JsonDiff, path: &str, fields: &mut BTreeSet<String>) {
+    match diff {
+        JsonDiff::ObjectDiff(map) => {
+            for (key, value_diff) in map {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                changed_fields(value_diff, &path, fields);
             }
         }
-        JsonDiff::Modified { old, new } => {
-            let old_str = serde_json::to_string_pretty(old)
-                .unwrap_or_else(|_| format!("{:?}", old));
-            let new_str = serde_json::to_string_pretty(new)
-                .unwrap_or_else(|_| format!("{:?}", new));
-            
-            // Show old value
-            let old_lines: Vec<&str> = old_str.lines().collect();
-            for (i, line) in old_lines.iter().enumerate() {
-                let prefix = if i == 0 {
-                    if path.is_empty() {
-                        format!("{}- ", indent_str)
-                    } else {
-                        format!("{}- {}: ", indent_str, path)
-                    }
-                } else {
-                    format!("{}  - ", indent_str)
-                };
-                result.push((format!("{}{}", prefix, line), Color::Red));
-            }
-            
-            // Show new value
-            let new_lines: Vec<&str> = new_str.lines().collect();
-            for (i, line) in new_lines.iter().enumerate() {
-                let prefix = if i == 0 {
-                    if path.is_empty() {
-                        format!("{}+ ", indent_str)
-                    } else {
-                        format!("{}+ {}: ", indent_str, path)
-                    }
-                } else {
-                    format!("{}  + ", indent_str)
-                };
-                result.push((format!("{}{}", prefix, line), Color::Green));
+        JsonDiff::ArrayDiff(items) => {
+            for item_diff in items {
+                changed_fields(item_diff, path, fields);
             }
         }
+        JsonDiff::Unchanged(_) => {}
+        _ if path.is_empty() => {}
+        _ => {
+            fields.insert(path.to_string());
+        }
+    }
+}
+
+/// The compare mode diff as plain text, with its +/- markers
+fn json_diff_text(baseline: &serde_json::Value, current: &serde_json::Value) -> String {
+    let lines = format_json_diff(&compare_json(baseline, current), "", 0);
+    if lines.is_empty() {
+        return "No differences".to_string();
+    }
+    lines.into_iter().map(|line| format!("{}{}", line.prefix, line.json)).collect::<Vec<_>>().join("\n")
+}
+
+// One line of a compare mode diff: its +/- marker (and the path, on a value's first line), then a line of the
+// value's JSON
+struct DiffLine {
+    prefix: String,
+    json: String,
+    added: bool, // Whether the value was added, rather than removed
+}
+
+fn format_json_diff(diff: &JsonDiff, path: &str, indent: usize) -> Vec<DiffLine> {
+    let mut result = Vec::new();
+
+    match diff {
+        JsonDiff::Added(value) => push_diff_value(&mut result, value, '+', path, indent),
+        JsonDiff::Removed(value) => push_diff_value(&mut result, value, '-', path, indent),
+        JsonDiff::Modified { old, new } => {
+            push_diff_value(&mut result, old, '-', path, indent);
+            push_diff_value(&mut result, new, '+', path, indent);
+        }
         JsonDiff::ObjectDiff(map) => {
             for (key, value_diff) in map {
                 let new_path = if path.is_empty() {
@@ -617,10 +3037,86 @@ fn format_json_diff(diff: &JsonDiff, path: &str, indent: usize) -> Vec<(String,
             // Skip unchanged values - they're hidden by default
         }
     }
-    
+
     result
 }
 
+/// Lines of a value added (`+`) or removed (`-`) at a path
+fn push_diff_value(result: &mut Vec<DiffLine>, value: &serde_json::Value, marker: char, path: &str, indent: usize) {
+    let indent_str = "  ".repeat(indent);
+    let json_str = serde_json::to_string_pretty(value)
+        .unwrap_or_else(|_| format!("{:?}", value));
+    for (i, line) in json_str.lines().enumerate() {
+        let prefix = match (i, path.is_empty()) {
+            (0, true) => format!("{}{} ", indent_str, marker),
+            (0, false) => format!("{}{} {}: ", indent_str, marker, path),
+            _ => format!("{}  {} ", indent_str, marker),
+        };
+        result.push(DiffLine { prefix, json: line.to_string(), added: marker == '+' });
+    }
+}
+
+/// Spans of a line of pretty-printed JSON with keys, strings, numbers, booleans and null in their own colors.
+/// Everything else on the line (punctuation, and text like the `... 3 keys` of a collapsed object) gets `base`
+fn highlight_json(line: &str, base: Style, theme: &Theme) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = line;
+    // Numbers and literals only where a value can start, so digits in other text stay plain
+    let mut value_position = true;
+
+    while let Some(c) = rest.chars().next() {
+        let token = if c == '"' {
+            let mut escaped = false;
+            let end = rest[1..]
+                .find(|c: char| {
+                    let closes = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    closes
+                })
+                .map_or(rest.len(), |i| i + 2);
+            let is_key = rest[end..].trim_start().starts_with(':');
+            Some((end, if is_key { theme.json_key } else { theme.json_string }))
+        } else if value_position && (c == '-' || c.is_ascii_digit()) {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+                .unwrap_or(rest.len());
+            Some((end, theme.json_number))
+        } else if value_position {
+            ["true", "false", "null"]
+                .into_iter()
+                .find(|literal| {
+                    rest.starts_with(literal) && !rest[literal.len()..].starts_with(|c: char| c.is_alphanumeric())
+                })
+                .map(|literal| (literal.len(), if literal == "null" { theme.json_null } else { theme.json_bool }))
+        } else {
+            None
+        };
+
+        match token {
+            Some((end, color)) => {
+                if !plain.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut plain), base));
+                }
+                spans.push(Span::styled(rest[..end].to_string(), base.fg(color)));
+                rest = &rest[end..];
+                value_position = false;
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+                if !c.is_whitespace() {
+                    value_position = matches!(c, ':' | '[' | ',');
+                }
+            }
+        }
+    }
+    if !plain.is_empty() {
+        spans.push(Span::styled(plain, base));
+    }
+    spans
+}
+
 // Structure to track JSON rendering with expand/collapse
 struct JsonLine {
     line: String,
@@ -833,6 +3329,35 @@ async fn main() -> Result<()> {
     
     dotenvy::dotenv().ok();
 
+    // Headless subcommands operate on log files/stdin and never start the TUI
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut log_file = None; // `view <log>` opens a packet log file instead of the session list
+    match args.first().map(|s| s.as_str()) {
+        Some("dump") => return packet_dump::dump(&args[1..]),
+        Some("grep") => return packet_dump::grep(&args[1..]),
+        Some("sessions") => return packet_dump::sessions(&args[1..]),
+        Some("export-test") => return test_export::export_test(&args[1..]),
+        Some("export-parquet") => return parquet_export::export_parquet(&args[1..]).await,
+        Some("migrate") => return storage::migrate(&args[1..]).await,
+        Some("delete-session") => return storage::delete_sessions(&args[1..]).await,
+        Some("prune") => return retention::prune(&args[1..]).await,
+        Some("stats") => return packet_stats::stats(&args[1..]).await,
+        Some("health") => return db_health::health(&args[1..]).await,
+        Some("import-log") => return log_import::import_logs(&args[1..]).await,
+        Some("capture") => return capture_session::capture(&args[1..]).await,
+        Some("export-session") => return session_archive::export_session_command(&args[1..]).await,
+        Some("import-session") => return session_archive::import_session_command(&args[1..]).await,
+        Some("protocol-diff") => return protocol_diff::protocol_diff(&args[1..]),
+        Some("codegen") => return protocol_codegen::codegen(&args[1..]),
+        Some("proto") => return protocol_validate::proto(&args[1..]),
+        Some("view") => match &args[1..] {
+            [path] => log_file = Some(std::path::PathBuf::from(path)),
+            _ => return Err(anyhow::anyhow!("Usage: lazypacket view <log file>")),
+        },
+        Some(other) => return Err(anyhow::anyhow!("Unknown command: {}", other)),
+        None => {}
+    }
+
     // Quick sanity check:
     dbg!(std::env::current_dir()?);
     dbg!(std::env::var("PROXY_DESTINATION_ADDRESS")?);
@@ -845,238 +3370,1076 @@ async fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?; // Clear the screen before drawing
 
-    let mut app = ViewerApp::new().await?;
+    // A log file opened with `view` is read without connecting to the database
+    let db = match log_file {
+        Some(_) => None,
+        None => Some(storage::connect().await?.into()),
+    };
+    let mut app = ViewerApp::new(db).await?;
+    if let Some(path) = &log_file {
+        app.open_log_file(path).await;
+    }
     let mut should_quit = false;
 
     while !should_quit {
+        app.poll_pending_load().await;
+        if let Err(e) = app.update_previous_baseline().await {
+            app.error_message = Some(format!("Failed to find the previous packet: {:#}", e));
+        }
+        if let Err(e) = app.update_rate_histogram().await {
+            app.show_rate_histogram = false;
+            app.error_message = Some(format!("Failed to load the packet rate: {:#}", e));
+        }
         terminal.draw(|f| ui(f, &mut app))?;
 
+        if !app.sessions_loaded {
+            app.load_initial_sessions().await;
+            continue;
+        }
+
         if event::poll(std::time::Duration::from_millis(50))? {
             match event::read()? {
+                // Only Esc does anything while packets load in the background: it cancels the load
+                Event::Key(key) if app.pending_load.is_some() && key.kind == KeyEventKind::Press && key.code == KeyCode::Esc => {
+                    app.cancel_pending_load();
+                }
+                Event::Key(_) | Event::Mouse(_) if app.pending_load.is_some() => {}
                 Event::Key(key) => {
                     if key.kind == KeyEventKind::Press {
                     match app.mode {
                         ViewerMode::SessionList => {
+                            app.status_message = None;
+                            match key.code {
+                                KeyCode::Char('q') => should_quit = true,
+                                KeyCode::Esc => {
+                                    // Clear an active search first
+                                    if app.current_session_search.is_some() {
+                                        app.error_message = None;
+                                        if let Err(e) = app.apply_session_search(None).await {
+                                            app.error_message = Some(format!("Failed to load sessions: {}", e));
+                                        }
+                                    } else {
+                                        should_quit = true;
+                                    }
+                                }
+                                KeyCode::Char('?') => {
+                                    app.open_key_help(false);
+                                }
+                                KeyCode::Char('/') => {
+                                    app.session_search_input = app.current_session_search.as_ref()
+                                        .map(|s| s.to_input())
+                                        .unwrap_or_default();
+                                    app.mode = ViewerMode::SessionSearchInput;
+                                }
+                                KeyCode::Char('o') => {
+                                    // Next sort order, keeping the rest of the search
+                                    app.error_message = None;
+                                    let mut search = app.current_session_search.clone().unwrap_or_default();
+                                    search.sort = search.sort.next();
+                                    let sort = search.sort;
+                                    match app.apply_session_search((search != SessionSearch::default()).then_some(search)).await {
+                                        Ok(()) => app.status_message = Some(format!("Sorted by {}", sort.label())),
+                                        Err(e) => app.error_message = Some(format!("Failed to load sessions: {}", e)),
+                                    }
+                                }
+                                KeyCode::Up => {
+                                    if app.selected_session > 0 {
+                                        app.selected_session -= 1;
+                                    }
+                                }
+                                KeyCode::Down => {
+                                    if app.selected_session < app.sessions.len().saturating_sub(1) {
+                                        app.selected_session += 1;
+                                    }
+                                }
+                                KeyCode::Enter => {
+                                    app.error_message = None;
+                                    if let Err(e) = app.load_session() {
+                                        app.error_message = Some(format!("Failed to load session: {}", e));
+                                    }
+                                }
+                                KeyCode::Char('t') => {
+                                    // Enter tag management modal
+                                    if let Some(SessionSummary { session, .. }) = app.sessions.get(app.selected_session) {
+                                        let tags = async { app.db()?.get_session_tags(session.id).await }.await.unwrap_or_default();
+                                        app.tag_management = Some(TagManagementState {
+                                            session_id: session.id,
+                                            tags,
+                                            selected_tag_index: 0,
+                                            add_tag_mode: false,
+                                        });
+                                        app.tag_input = String::new();
+                                        app.mode = ViewerMode::TagManagement;
+                                    }
+                                }
+                                KeyCode::Char('s') => {
+                                    if let Some(session_id) = app.sessions.get(app.selected_session).map(|summary| summary.session.id) {
+                                        app.open_statistics(session_id, false).await;
+                                    }
+                                }
+                                KeyCode::Char('a') => {
+                                    if let Some(session_id) = app.sessions.get(app.selected_session).map(|summary| summary.session.id) {
+                                        app.open_note_editor(session_id, None).await;
+                                    }
+                                }
+                                KeyCode::Char('i') => {
+                                    app.error_message = None;
+                                    app.open_database_health().await;
+                                }
+                                KeyCode::Char('e') => {
+                                    if let Some(session_id) = app.sessions.get(app.selected_session).map(|summary| summary.session.id) {
+                                        app.export_session_archive(session_id).await;
+                                    }
+                                }
+                                KeyCode::Char('A') => {
+                                    app.error_message = None;
+                                    app.toggle_session_archived().await;
+                                }
+                                KeyCode::Char('r') => {
+                                    app.error_message = None;
+                                    app.open_name_editor();
+                                }
+                                KeyCode::Char('d') | KeyCode::Char('D') => {
+                                    // Delete selected session
+                                    if let Some(SessionSummary { session, packet_count, .. }) = app.sessions.get(app.selected_session) {
+                                        app.confirmation_dialog = Some(ConfirmationDialogState {
+                                            message: format!(
+                                                "Delete session #{}? This will also delete its {} packets and all tags and notes. This cannot be undone (A archives it instead).",
+                                                session.id, packet_count
+                                            ),
+                                            action: ConfirmationAction::DeleteSession {
+                                                session_id: session.id,
+                                            },
+                                        });
+                                        app.mode = ViewerMode::ConfirmationDialog;
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        ViewerMode::SessionSearchInput => {
+                            match key.code {
+                                KeyCode::Esc => {
+                                    app.mode = ViewerMode::SessionList;
+                                }
+                                KeyCode::Enter => {
+                                    app.mode = ViewerMode::SessionList;
+                                    app.error_message = None;
+                                    // An empty search (or one that only says sort:newest) shows every session again
+                                    let search = SessionSearch::parse(&app.session_search_input)
+                                        .map(|search| (search != SessionSearch::default()).then_some(search));
+                                    match search {
+                                        Ok(search) => {
+                                            if let Err(e) = app.apply_session_search(search).await {
+                                                app.error_message = Some(format!("Session search failed: {}", e));
+                                            }
+                                        }
+                                        Err(e) => {
+                                            app.error_message = Some(format!("{:#}", e));
+                                        }
+                                    }
+                                }
+                                KeyCode::Backspace => {
+                                    app.session_search_input.pop();
+                                }
+                                KeyCode::Char(c) => {
+                                    app.session_search_input.push(c);
+                                }
+                                _ => {}
+                            }
+                        }
+                        ViewerMode::PacketView => {
+                            // Messages are shown until the next key
+                            app.status_message = None;
+                            app.error_message = None;
+                            // Keys from the config file's keymap become the defaults they stand in for
+                            let code = app.keymap.translate(key.code);
+                            match code {
+                                KeyCode::Char('q') => {
+                                    app.close_session();
+                                }
+                                KeyCode::Char('?') => {
+                                    app.open_key_help(true);
+                                }
+                                KeyCode::Esc => {
+                                    // Exit compare mode if active, otherwise go back to session list
+                                    if app.compare_mode {
+                                        app.exit_compare_mode();
+                                        app.packet_details_scroll = 0;
+                                        app.diff_panel_scroll = 0;
+                                    } else {
+                                        app.close_session();
+                                    }
+                                }
+                                KeyCode::Char('c') => {
+                                    // Enter compare mode / Set baseline
+                                    app.set_baseline(false).await;
+                                }
+                                KeyCode::Char('p') => {
+                                    if app.diff_previous {
+                                        app.exit_compare_mode();
+                                    } else {
+                                        app.start_diff_previous().await;
+                                    }
+                                }
+                                KeyCode::Char('C') => {
+                                    // Baseline to compare other sessions' packets against
+                                    app.set_baseline(true).await;
+                                    if let (Some(session_id), Some(number)) = (app.baseline_session_id, app.baseline_packet.as_ref().and_then(|p| p.packet_number)) {
+                                        app.status_message = Some(format!(
+                                            "Pinned packet {} of session #{} as the baseline - open another session to compare its packets against it",
+                                            number, session_id
+                                        ));
+                                    }
+                                }
+                                KeyCode::Left | KeyCode::Char('h') => {
+                                    app.prev_packet();
+                                }
+                                KeyCode::Right | KeyCode::Char('l') => {
+                                    app.next_packet();
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    // Scroll up in packet details
+                                    // Always allow decrementing - it will be clamped during rendering if needed
+                                    if app.packet_details_scroll > 0 {
+                                        app.packet_details_scroll -= 1;
+                                    }
+                                }
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    // Scroll down in packet details
+                                    // We'll clamp this during rendering based on actual content
+                                    app.packet_details_scroll += 1;
+                                }
+                                KeyCode::PageUp => {
+                                    // Jump back 10 packets
+                                    let old_index = app.packet_index;
+                                    for _ in 0..10 {
+                                        app.prev_packet();
+                                    }
+                                    // Reset scroll if packet actually changed
+                                    if app.packet_index != old_index {
+                                        app.packet_details_scroll = 0;
+                                        app.diff_panel_scroll = 0;
+                                    }
+                                }
+                                KeyCode::PageDown => {
+                                    // Jump forward 10 packets
+                                    let old_index = app.packet_index;
+                                    for _ in 0..10 {
+                                        app.next_packet();
+                                    }
+                                    // Reset scroll if packet actually changed
+                                    if app.packet_index != old_index {
+                                        app.packet_details_scroll = 0;
+                                        app.diff_panel_scroll = 0;
+                                    }
+                                }
+                                KeyCode::Home => {
+                                    app.packet_index = 0;
+                                    app.packet_details_scroll = 0;
+                                    app.diff_panel_scroll = 0;
+                                }
+                                KeyCode::End => {
+                                    if let Some(log) = &app.current_log {
+                                        app.packet_index = log.len().saturating_sub(1);
+                                        app.packet_details_scroll = 0;
+                                        app.diff_panel_scroll = 0;
+                                    }
+                                }
+                                KeyCode::Char('t') => {
+                                    app.show_packet_list = !app.show_packet_list;
+                                }
+                                KeyCode::Char('H') => {
+                                    app.show_rate_histogram = !app.show_rate_histogram;
+                                }
+                                KeyCode::Char('1') => {
+                                    app.toggle_direction_filter(Some(FilterPacketDirection::Clientbound));
+                                }
+                                KeyCode::Char('2') => {
+                                    app.toggle_direction_filter(Some(FilterPacketDirection::Serverbound));
+                                }
+                                KeyCode::Char('0') => {
+                                    app.toggle_direction_filter(None);
+                                }
+                                KeyCode::Char('=') => {
+                                    app.filter_current_packet_type(false);
+                                }
+                                KeyCode::Char('-') => {
+                                    app.filter_current_packet_type(true);
+                                }
+                                KeyCode::Char('y') => {
+                                    app.copy_to_clipboard();
+                                }
+                                KeyCode::Char('i') => {
+                                    app.open_ignored_fields().await;
+                                }
+                                KeyCode::Char('m') => {
+                                    app.toggle_entity_follow();
+                                }
+                                KeyCode::Char('M') => {
+                                    app.open_movement_map().await;
+                                }
+                                KeyCode::Char('G') => {
+                                    app.open_gaps().await;
+                                }
+                                KeyCode::Char('D') => {
+                                    app.open_decode_errors().await;
+                                }
+                                KeyCode::Char('R') => {
+                                    app.open_exchanges().await;
+                                }
+                                KeyCode::Char('T') => {
+                                    app.open_sequence_diagram(true).await;
+                                }
+                                KeyCode::Char('V') => {
+                                    app.open_protocol_versions();
+                                }
+                                KeyCode::Char('v') => {
+                                    app.open_hex_link();
+                                }
+                                KeyCode::Char(' ') => {
+                                    app.toggle_playback().await;
+                                }
+                                KeyCode::Char('<') | KeyCode::Char('>') => {
+                                    app.change_playback_speed(code == KeyCode::Char('>'));
+                                }
+                                KeyCode::Char('L') => {
+                                    // Follow a live session from its newest packet
+                                    if let Some(log) = app.current_log.as_ref().filter(|log| log.live) {
+                                        app.follow = !app.follow;
+                                        if app.follow {
+                                            app.packet_index = log.len().saturating_sub(1);
+                                            app.packet_details_scroll = 0;
+                                            app.diff_panel_scroll = 0;
+                                        }
+                                    }
+                                }
+                                KeyCode::Char('x') | KeyCode::Char('X') => {
+                                    // Toggle between JSON and hex view
+                                    app.show_hex = !app.show_hex;
+                                    // Reset scroll when toggling view
+                                    app.packet_details_scroll = 0;
+                                    app.diff_panel_scroll = 0;
+                                }
+                                KeyCode::Char('f') | KeyCode::Char('F') if app.has_database() => {
+                                    // Enter filter input mode
+                                    // Initialize filter input with current filter if one exists
+                                    app.filter_input = app.current_filter.as_ref()
+                                        .map(|f| f.to_string())
+                                        .unwrap_or_else(|| "a".to_string());
+                                    app.mode = ViewerMode::FilterInput;
+                                    if let Err(e) = app.load_packet_types().await {
+                                        app.error_message = Some(format!("Failed to load the session's packet names: {:#}", e));
+                                    }
+                                }
+                                KeyCode::Char('/') if app.has_database() => {
+                                    // Enter search input mode, starting from the current search
+                                    app.search_input = app.current_search.as_ref()
+                                        .map(|s| s.to_input())
+                                        .unwrap_or_default();
+                                    app.mode = ViewerMode::SearchInput;
+                                }
+                                KeyCode::Char('g') => {
+                                    app.goto_input.clear();
+                                    app.mode = ViewerMode::GotoInput;
+                                }
+                                KeyCode::Char('w') => {
+                                    app.watch_input.clear();
+                                    app.mode = ViewerMode::WatchInput;
+                                }
+                                KeyCode::Char('e') | KeyCode::Char('E') if app.has_database() => {
+                                    if let Some(log) = &app.current_log {
+                                        app.export_all = code == KeyCode::Char('E');
+                                        let path = if app.export_all {
+                                            Some(packet_export::default_packets_path(log.session_id))
+                                        } else {
+                                            app.current_packet()
+                                                .and_then(|p| p.packet_number)
+                                                .map(|number| packet_export::default_packet_path(log.session_id, number))
+                                        };
+                                        if let Some(path) = path {
+                                            app.export_input = path.display().to_string();
+                                            app.mode = ViewerMode::ExportInput;
+                                        }
+                                    }
+                                }
+                                KeyCode::Char('n') => {
+                                    if let Err(e) = app.jump_to_next_match(false).await {
+                                        app.error_message = Some(format!("Search failed: {}", e));
+                                    }
+                                }
+                                KeyCode::Char('N') => {
+                                    if let Err(e) = app.jump_to_previous_match().await {
+                                        app.error_message = Some(format!("Search failed: {}", e));
+                                    }
+                                }
+                                KeyCode::Char('s') => {
+                                    if let Some(session_id) = app.current_log.as_ref().map(|log| log.session_id) {
+                                        app.open_statistics(session_id, true).await;
+                                    }
+                                }
+                                KeyCode::Char('S') => {
+                                    app.open_filtered_statistics().await;
+                                }
+                                KeyCode::Char('a') => {
+                                    let session_id = app.current_log.as_ref().map(|log| log.session_id);
+                                    let packet_number = app.current_packet().and_then(|p| p.packet_number);
+                                    if let (Some(session_id), Some(packet_number)) = (session_id, packet_number) {
+                                        app.open_note_editor(session_id, Some(packet_number)).await;
+                                    }
+                                }
+                                KeyCode::Char('b') => {
+                                    if let Err(e) = app.toggle_bookmark().await {
+                                        app.error_message = Some(format!("Failed to save bookmark: {}", e));
+                                    }
+                                }
+                                KeyCode::Char('B') => {
+                                    // Start at the bookmark on or before the current packet
+                                    let current = app.current_packet().and_then(|p| p.packet_number).unwrap_or(0);
+                                    let selected = app.current_log.as_ref()
+                                        .map(|log| log.bookmarks.range(..=current).count().saturating_sub(1))
+                                        .unwrap_or(0);
+                                    app.bookmarks_panel = Some(BookmarksPanelState { selected, label_input: None });
+                                    app.mode = ViewerMode::Bookmarks;
+                                }
+                                KeyCode::Char(']') | KeyCode::Char('[') => {
+                                    if let Some(number) = app.adjacent_bookmark(code == KeyCode::Char(']')) {
+                                        if let Err(e) = app.jump_to_packet(number).await {
+                                            app.error_message = Some(format!("Failed to load packets: {}", e));
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            }
+                            // Navigation may have moved past the loaded packets
+                            app.ensure_packets_loaded().await;
+                        }
+                        ViewerMode::FilterInput => {
+                            match key.code {
+                                KeyCode::Esc => {
+                                    // Cancel filter - revert filter input to current filter
+                                    app.filter_input = app.current_filter.as_ref()
+                                        .map(|f| f.to_string())
+                                        .unwrap_or_else(|| "a".to_string());
+                                    app.filter_history.position = None;
+                                    app.mode = ViewerMode::PacketView;
+                                }
+                                KeyCode::Up => {
+                                    if let Some(filter) = app.filter_history.previous(&app.filter_input) {
+                                        app.filter_input = filter.to_string();
+                                    }
+                                }
+                                KeyCode::Down => {
+                                    if let Some(filter) = app.filter_history.next() {
+                                        app.filter_input = filter.to_string();
+                                    }
+                                }
+                                KeyCode::Enter => {
+                                    // Apply filter
+                                    let filter = ViewerApp::parse_filter(&app.filter_input);
+                                    if filter.is_some() {
+                                        let input = app.filter_input.trim().to_string();
+                                        app.record_filter(&input).await;
+                                    } else {
+                                        app.filter_history.position = None;
+                                    }
+                                    // Applied anyway (logs may name packets the loaded protocol doesn't), but say why nothing matches
+                                    let mut warnings = filter_regex_errors(&app.filter_input);
+                                    if let (Some(filter), Some(parser)) = (&filter, app.protocol_parser()) {
+                                        warnings.extend(filter_name_warnings(filter, &parser));
+                                    }
+                                    if !warnings.is_empty() {
+                                        app.error_message = Some(format!("Filter: {}", warnings.join("; ")));
+                                    }
+                                    
+                                    // Keep filter_input visible so user can see what filter is applied
+                                    app.mode = ViewerMode::PacketView;
+                                    app.apply_filter(filter);
+                                }
+                                KeyCode::Backspace => {
+                                    app.filter_input.pop();
+                                    app.filter_history.position = None;
+                                }
+                                KeyCode::Tab => {
+                                    // Complete the packet name being typed, or else open the presets
+                                    match complete_filter_name(&app.filter_input, &app.filter_suggestions()) {
+                                        Some(completed) => app.filter_input = completed,
+                                        None => app.open_filter_presets().await,
+                                    }
+                                }
+                                KeyCode::Char(c) => {
+                                    // Allow multi-character input for filter strings
+                                    app.filter_input.push(c);
+                                    // Editing a recalled filter makes it the one being typed
+                                    app.filter_history.position = None;
+                                }
+                                _ => {}
+                            }
+                        }
+                        ViewerMode::FilterPresets => match app.filter_presets.as_mut() {
+                            None => app.mode = ViewerMode::FilterInput,
+                            Some(picker) => {
+                                if let Some(ref mut name) = picker.name_input {
+                                    // Naming the current filter
+                                    match key.code {
+                                        KeyCode::Esc => picker.name_input = None,
+                                        KeyCode::Enter => {
+                                            let name = name.trim().to_string();
+                                            picker.name_input = None;
+                                            if !name.is_empty() {
+                                                let message = match app.save_filter_preset(&name).await {
+                                                    Ok(message) => message,
+                                                    Err(e) => format!("Failed to save preset: {}", e),
+                                                };
+                                                if let Some(ref mut picker) = app.filter_presets {
+                                                    picker.message = Some(message);
+                                                }
+                                            }
+                                        }
+                                        KeyCode::Backspace => {
+                                            name.pop();
+                                        }
+                                        KeyCode::Char(c) => name.push(c),
+                                        _ => {}
+                                    }
+                                } else {
+                                    match key.code {
+                                        KeyCode::Esc | KeyCode::Char('q') => {
+                                            app.filter_presets = None;
+                                            app.mode = ViewerMode::FilterInput;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            picker.selected = picker.selected.saturating_sub(1);
+                                        }
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            picker.selected = (picker.selected + 1).min(picker.presets.len().saturating_sub(1));
+                                        }
+                                        KeyCode::Enter => {
+                                            // Load into the filter input; Enter there applies it
+                                            if let Some(preset) = picker.presets.get(picker.selected) {
+                                                app.filter_input = preset.filter.clone();
+                                            }
+                                            app.filter_presets = None;
+                                            app.mode = ViewerMode::FilterInput;
+                                        }
+                                        KeyCode::Char('s') => {
+                                            picker.name_input = Some(String::new());
+                                            picker.message = None;
+                                        }
+                                        KeyCode::Char('d') => {
+                                            if let Some(preset) = picker.presets.get(picker.selected).cloned() {
+                                                let deleted = match database(app.db.as_deref()) {
+                                                    Ok(db) => db.delete_filter_preset(&preset.name).await,
+                                                    Err(e) => Err(e),
+                                                };
+                                                picker.message = Some(match deleted {
+                                                    Ok(()) => {
+                                                        picker.presets.remove(picker.selected);
+                                                        picker.selected = picker.selected.min(picker.presets.len().saturating_sub(1));
+                                                        format!("Deleted '{}'", preset.name)
+                                                    }
+                                                    Err(e) => format!("Failed to delete preset: {}", e),
+                                                });
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
+                        },
+                        ViewerMode::Bookmarks => match app.bookmarks_panel.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(panel) => {
+                                let bookmarks: Vec<PacketBookmark> = app.current_log.as_ref()
+                                    .map(|log| log.bookmarks.values().cloned().collect())
+                                    .unwrap_or_default();
+                                let selected = bookmarks.get(panel.selected).cloned();
+                                if let Some(ref mut label) = panel.label_input {
+                                    // Labelling the selected bookmark
+                                    match key.code {
+                                        KeyCode::Esc => panel.label_input = None,
+                                        KeyCode::Enter => {
+                                            let label = label.trim().to_string();
+                                            panel.label_input = None;
+                                            if let Some(bookmark) = selected {
+                                                let bookmark = PacketBookmark { label: (!label.is_empty()).then_some(label), ..bookmark };
+                                                if let Err(e) = app.update_bookmark(bookmark).await {
+                                                    app.error_message = Some(format!("Failed to save bookmark: {}", e));
+                                                }
+                                            }
+                                        }
+                                        KeyCode::Backspace => {
+                                            label.pop();
+                                        }
+                                        KeyCode::Char(c) => label.push(c),
+                                        _ => {}
+                                    }
+                                } else {
+                                    match key.code {
+                                        KeyCode::Esc | KeyCode::Char('q') => {
+                                            app.bookmarks_panel = None;
+                                            app.mode = ViewerMode::PacketView;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            panel.selected = panel.selected.saturating_sub(1);
+                                        }
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            panel.selected = (panel.selected + 1).min(bookmarks.len().saturating_sub(1));
+                                        }
+                                        KeyCode::Enter => {
+                                            app.bookmarks_panel = None;
+                                            app.mode = ViewerMode::PacketView;
+                                            if let Some(bookmark) = selected {
+                                                if let Err(e) = app.jump_to_packet(bookmark.packet_number).await {
+                                                    app.error_message = Some(format!("Failed to load packets: {}", e));
+                                                }
+                                            }
+                                        }
+                                        KeyCode::Char('l') => {
+                                            if let Some(ref bookmark) = selected {
+                                                panel.label_input = Some(bookmark.label.clone().unwrap_or_default());
+                                            }
+                                        }
+                                        KeyCode::Char('c') => {
+                                            if let Some(bookmark) = selected {
+                                                let bookmark = PacketBookmark { color: bookmark.color.next(), ..bookmark };
+                                                if let Err(e) = app.update_bookmark(bookmark).await {
+                                                    app.error_message = Some(format!("Failed to save bookmark: {}", e));
+                                                }
+                                            }
+                                        }
+                                        KeyCode::Char('d') => {
+                                            if let Some(bookmark) = selected {
+                                                panel.selected = panel.selected.min(bookmarks.len().saturating_sub(2));
+                                                let result = match (app.current_log.as_mut(), database(app.db.as_deref())) {
+                                                    (Some(log), Ok(db)) => db.delete_packet_bookmark(log.session_id, bookmark.packet_number).await
+                                                        .map(|()| { log.bookmarks.remove(&bookmark.packet_number); }),
+                                                    (Some(_), Err(e)) => Err(e),
+                                                    (None, _) => Ok(()),
+                                                };
+                                                if let Err(e) = result {
+                                                    app.error_message = Some(format!("Failed to delete bookmark: {}", e));
+                                                }
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
+                        },
+                        ViewerMode::KeyHelp => match app.key_help.as_mut() {
+                            None => app.mode = ViewerMode::SessionList,
+                            Some(help) => match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => {
+                                    app.mode = if help.from_packet_view { ViewerMode::PacketView } else { ViewerMode::SessionList };
+                                    app.key_help = None;
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => help.scroll = help.scroll.saturating_sub(1),
+                                KeyCode::Down | KeyCode::Char('j') => help.scroll = help.scroll.saturating_add(1),
+                                KeyCode::PageUp => help.scroll = help.scroll.saturating_sub(10),
+                                KeyCode::PageDown => help.scroll = help.scroll.saturating_add(10),
+                                _ => {}
+                            },
+                        },
+                        ViewerMode::IgnoredFields => match app.ignored_fields_panel.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(panel) => {
+                                let packet_name = panel.packet_name.clone();
+                                if let Some(ref mut path) = panel.path_input {
+                                    // Typing a path to ignore
+                                    match key.code {
+                                        KeyCode::Esc => panel.path_input = None,
+                                        KeyCode::Enter => {
+                                            let path = path.trim().trim_matches('.').to_string();
+                                            panel.path_input = None;
+                                            let already_ignored = app.ignored_fields.get(&packet_name).is_some_and(|fields| fields.contains(&path));
+                                            if !path.is_empty() && !already_ignored {
+                                                if !panel.fields.contains(&path) {
+                                                    panel.fields.push(path.clone());
+                                                    panel.fields.sort();
+                                                }
+                                                panel.selected = panel.fields.iter().position(|field| *field == path).unwrap_or(0);
+                                                if let Err(e) = app.toggle_ignored_field(&packet_name, &path).await {
+                                                    app.error_message = Some(format!("Failed to save ignored field: {}", e));
+                                                }
+                                            }
+                                        }
+                                        KeyCode::Backspace => {
+                                            path.pop();
+                                        }
+                                        KeyCode::Char(c) => path.push(c),
+                                        _ => {}
+                                    }
+                                } else {
+                                    match key.code {
+                                        KeyCode::Esc | KeyCode::Char('q') => {
+                                            app.ignored_fields_panel = None;
+                                            app.mode = ViewerMode::PacketView;
+                                        }
+                                        KeyCode::Up | KeyCode::Char('k') => {
+                                            panel.selected = panel.selected.saturating_sub(1);
+                                        }
+                                        KeyCode::Down | KeyCode::Char('j') => {
+                                            panel.selected = (panel.selected + 1).min(panel.fields.len().saturating_sub(1));
+                                        }
+                                        KeyCode::Char('a') => {
+                                            panel.path_input = Some(String::new());
+                                        }
+                                        KeyCode::Enter | KeyCode::Char(' ') => {
+                                            if let Some(path) = panel.fields.get(panel.selected).cloned() {
+                                                if let Err(e) = app.toggle_ignored_field(&packet_name, &path).await {
+                                                    app.error_message = Some(format!("Failed to save ignored field: {}", e));
+                                                }
+                                            }
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
+                        },
+                        ViewerMode::MovementMap => {
+                            let result = match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('M') => {
+                                    app.movement_map = None;
+                                    app.mode = ViewerMode::PacketView;
+                                    Ok(())
+                                }
+                                KeyCode::Left | KeyCode::Char('h') => app.step_movement_map(false).await,
+                                KeyCode::Right | KeyCode::Char('l') => app.step_movement_map(true).await,
+                                KeyCode::Home | KeyCode::End => {
+                                    let end = app.movement_map.as_ref().and_then(|map| match key.code {
+                                        KeyCode::Home => map.path.first(),
+                                        _ => map.path.last(),
+                                    });
+                                    match end.map(|point| point.packet_number) {
+                                        Some(number) => app.jump_to_packet(number).await,
+                                        None => Ok(()),
+                                    }
+                                }
+                                _ => Ok(()),
+                            };
+                            if let Err(e) = result {
+                                app.error_message = Some(format!("Failed to go to the packet: {}", e));
+                            }
+                        }
+                        ViewerMode::Gaps => match app.gaps.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(panel) => match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('G') => {
+                                    app.gaps = None;
+                                    app.mode = ViewerMode::PacketView;
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => panel.selected = panel.selected.saturating_sub(1),
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    let rows = panel.gaps.len() + panel.round_trips.len();
+                                    panel.selected = (panel.selected + 1).min(rows.saturating_sub(1));
+                                }
+                                KeyCode::Enter => {
+                                    if let Err(e) = app.jump_to_gap().await {
+                                        app.error_message = Some(format!("Failed to go to the packet: {}", e));
+                                    }
+                                }
+                                _ => {}
+                            },
+                        },
+                        ViewerMode::SequenceDiagram => match app.sequence_diagram.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(diagram) => {
+                                // Messages (the outcome of copying) are shown until the next key
+                                app.status_message = None;
+                                app.error_message = None;
+                                let last = diagram.packets.len().saturating_sub(1);
+                                match key.code {
+                                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('T') => {
+                                        app.sequence_diagram = None;
+                                        app.mode = ViewerMode::PacketView;
+                                    }
+                                    KeyCode::Up | KeyCode::Char('k') => diagram.selected = diagram.selected.saturating_sub(1),
+                                    KeyCode::Down | KeyCode::Char('j') => diagram.selected = (diagram.selected + 1).min(last),
+                                    KeyCode::PageUp => diagram.selected = diagram.selected.saturating_sub(10),
+                                    KeyCode::PageDown => diagram.selected = (diagram.selected + 10).min(last),
+                                    KeyCode::Tab => {
+                                        let handshake = !diagram.handshake;
+                                        app.open_sequence_diagram(handshake).await;
+                                    }
+                                    KeyCode::Char('y') => app.copy_sequence_diagram(),
+                                    KeyCode::Enter => {
+                                        if let Some(number) = diagram.packets.get(diagram.selected).map(|packet| packet.packet_number) {
+                                            app.sequence_diagram = None;
+                                            app.mode = ViewerMode::PacketView;
+                                            if let Err(e) = app.jump_to_packet(number).await {
+                                                app.error_message = Some(format!("Failed to go to the packet: {}", e));
+                                            }
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        },
+                        ViewerMode::Exchanges => match app.exchanges.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(panel) => {
+                                let result = match key.code {
+                                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('R') => {
+                                        app.exchanges = None;
+                                        app.mode = ViewerMode::PacketView;
+                                        Ok(())
+                                    }
+                                    KeyCode::Up | KeyCode::Char('k') => {
+                                        panel.selected = panel.selected.saturating_sub(1);
+                                        app.jump_to_exchange(false).await
+                                    }
+                                    KeyCode::Down | KeyCode::Char('j') => {
+                                        panel.selected = (panel.selected + 1).min(panel.exchanges.len().saturating_sub(1));
+                                        app.jump_to_exchange(false).await
+                                    }
+                                    KeyCode::Enter | KeyCode::Left | KeyCode::Char('h') => app.jump_to_exchange(false).await,
+                                    KeyCode::Right | KeyCode::Char('l') => app.jump_to_exchange(true).await,
+                                    _ => Ok(()),
+                                };
+                                if let Err(e) = result {
+                                    app.error_message = Some(format!("Failed to go to the packet: {}", e));
+                                }
+                            }
+                        },
+                        ViewerMode::DecodeErrors => match app.decode_errors.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(panel) => match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('D') => {
+                                    app.decode_errors = None;
+                                    app.mode = ViewerMode::PacketView;
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => panel.selected = panel.selected.saturating_sub(1),
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    panel.selected = (panel.selected + 1).min(panel.problems.len().saturating_sub(1));
+                                }
+                                KeyCode::Enter => {
+                                    if let Some(number) = panel.problems.get(panel.selected).map(|problem| problem.packet_number) {
+                                        app.decode_errors = None;
+                                        app.mode = ViewerMode::PacketView;
+                                        if let Err(e) = app.jump_to_packet(number).await {
+                                            app.error_message = Some(format!("Failed to go to the packet: {}", e));
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            },
+                        },
+                        ViewerMode::ProtocolVersions => match app.protocol_versions.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(panel) => match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('V') => {
+                                    app.protocol_versions = None;
+                                    app.mode = ViewerMode::PacketView;
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    let selected = panel.selected.saturating_sub(1);
+                                    app.select_protocol_version(selected);
+                                }
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    let selected = (panel.selected + 1).min(panel.versions.len() - 1);
+                                    app.select_protocol_version(selected);
+                                }
+                                KeyCode::PageUp => panel.scroll = panel.scroll.saturating_sub(10),
+                                KeyCode::PageDown => panel.scroll = panel.scroll.saturating_add(10),
+                                KeyCode::Enter => app.use_protocol_version(),
+                                _ => {}
+                            },
+                        },
+                        ViewerMode::HexLink => match app.hex_link.as_mut() {
+                            None => app.mode = ViewerMode::PacketView,
+                            Some(link) => match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('v') => {
+                                    app.hex_link = None;
+                                    app.mode = ViewerMode::PacketView;
+                                }
+                                KeyCode::Tab => link.hex_focus = !link.hex_focus,
+                                KeyCode::Up | KeyCode::Char('k') if link.hex_focus => link.move_cursor(link.cursor.saturating_sub(16)),
+                                KeyCode::Down | KeyCode::Char('j') if link.hex_focus => link.move_cursor(link.cursor + 16),
+                                KeyCode::Left | KeyCode::Char('h') if link.hex_focus => link.move_cursor(link.cursor.saturating_sub(1)),
+                                KeyCode::Right | KeyCode::Char('l') if link.hex_focus => link.move_cursor(link.cursor + 1),
+                                KeyCode::Up | KeyCode::Char('k') => link.select(link.selected.saturating_sub(1)),
+                                KeyCode::Down | KeyCode::Char('j') => link.select(link.selected + 1),
+                                KeyCode::Home => link.select(0),
+                                KeyCode::End => link.select(link.fields.len()),
+                                _ => {}
+                            },
+                        },
+                        ViewerMode::NoteInput => {
                             match key.code {
-                                KeyCode::Char('q') | KeyCode::Esc => should_quit = true,
-                                KeyCode::Up => {
-                                    if app.selected_session > 0 {
-                                        app.selected_session -= 1;
+                                KeyCode::Esc => {
+                                    let from_packet_view = app.note_editor.take().is_some_and(|e| e.from_packet_view);
+                                    app.mode = if from_packet_view { ViewerMode::PacketView } else { ViewerMode::SessionList };
+                                }
+                                // Alt+Enter or Ctrl+J starts a new line in a note; names are a single line
+                                KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => {
+                                    if let Some(editor) = app.note_editor.as_mut().filter(|e| !e.renaming) {
+                                        editor.text.push('\n');
                                     }
                                 }
-                                KeyCode::Down => {
-                                    if app.selected_session < app.sessions.len().saturating_sub(1) {
-                                        app.selected_session += 1;
+                                KeyCode::Char('j') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                    if let Some(editor) = app.note_editor.as_mut().filter(|e| !e.renaming) {
+                                        editor.text.push('\n');
                                     }
                                 }
                                 KeyCode::Enter => {
-                                    app.error_message = None;
-                                    if let Err(e) = app.load_session().await {
-                                        app.error_message = Some(format!("Failed to load session: {}", e));
+                                    let what = if app.note_editor.as_ref().is_some_and(|e| e.renaming) { "name" } else { "note" };
+                                    if let Err(e) = app.save_note().await {
+                                        app.error_message = Some(format!("Failed to save {}: {}", what, e));
                                     }
                                 }
-                                KeyCode::Char('t') => {
-                                    // Enter tag management modal
-                                    if let Some((session, _, _)) = app.sessions.get(app.selected_session) {
-                                        let tags = app.db.get_session_tags(session.id).await.unwrap_or_default();
-                                        app.tag_management = Some(TagManagementState {
-                                            session_id: session.id,
-                                            tags,
-                                            selected_tag_index: 0,
-                                            add_tag_mode: false,
-                                        });
-                                        app.tag_input = String::new();
-                                        app.mode = ViewerMode::TagManagement;
+                                KeyCode::Backspace => {
+                                    if let Some(ref mut editor) = app.note_editor {
+                                        editor.text.pop();
                                     }
                                 }
-                                KeyCode::Char('d') => {
-                                    // Delete selected session
-                                    if let Some((session, _, _)) = app.sessions.get(app.selected_session) {
-                                        app.confirmation_dialog = Some(ConfirmationDialogState {
-                                            message: format!("Delete session #{}? This will also delete all associated packets and tags.", session.id),
-                                            action: ConfirmationAction::DeleteSession {
-                                                session_id: session.id,
-                                            },
-                                        });
-                                        app.mode = ViewerMode::ConfirmationDialog;
+                                KeyCode::Char(c) => {
+                                    if let Some(ref mut editor) = app.note_editor {
+                                        editor.text.push(c);
                                     }
                                 }
                                 _ => {}
                             }
                         }
-                        ViewerMode::PacketView => {
+                        ViewerMode::Statistics => {
+                            if let Some(ref mut statistics) = app.statistics {
+                                match key.code {
+                                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('s') | KeyCode::Char('S') => {
+                                        app.mode = if statistics.from_packet_view {
+                                            ViewerMode::PacketView
+                                        } else {
+                                            ViewerMode::SessionList
+                                        };
+                                        app.statistics = None;
+                                    }
+                                    KeyCode::Up | KeyCode::Char('k') => {
+                                        statistics.scroll = statistics.scroll.saturating_sub(1);
+                                    }
+                                    KeyCode::Down | KeyCode::Char('j') => {
+                                        // Clamped during rendering
+                                        statistics.scroll += 1;
+                                    }
+                                    KeyCode::Char('o') => {
+                                        statistics.sort = statistics.sort.next();
+                                        sort_stats(&mut statistics.stats, statistics.sort);
+                                        statistics.scroll = 0;
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                        ViewerMode::DatabaseHealth => {
                             match key.code {
-                                KeyCode::Char('q') => {
+                                KeyCode::Char('q') | KeyCode::Esc => {
                                     app.mode = ViewerMode::SessionList;
-                                    app.current_log = None;
-                                    // Reset compare mode when going back to session list
-                                    app.compare_mode = false;
-                                    app.baseline_packet_index = None;
-                                    app.baseline_packet_json = None;
+                                    app.db_health = None;
                                 }
+                                KeyCode::Char('r') => {
+                                    app.open_database_health().await;
+                                }
+                                _ => {}
+                            }
+                        }
+                        ViewerMode::ExportInput => {
+                            match key.code {
                                 KeyCode::Esc => {
-                                    // Exit compare mode if active, otherwise go back to session list
-                                    if app.compare_mode {
-                                        app.compare_mode = false;
-                                        app.baseline_packet_index = None;
-                                        app.baseline_packet_json = None;
-                                        app.packet_details_scroll = 0;
-                                        app.diff_panel_scroll = 0;
-                                    } else {
-                                        app.mode = ViewerMode::SessionList;
-                                        app.current_log = None;
-                                    }
+                                    app.mode = ViewerMode::PacketView;
                                 }
-                                KeyCode::Char('c') => {
-                                    // Enter compare mode / Set baseline
-                                    let packet_json_opt = app.current_packet()
-                                        .and_then(|p| p.packet_json.as_ref())
-                                        .map(|j| j.clone());
-                                    if let Some(packet_json) = packet_json_opt {
-                                        app.compare_mode = true;
-                                        app.baseline_packet_index = Some(app.packet_index);
-                                        app.baseline_packet_json = Some(packet_json);
-                                        app.packet_details_scroll = 0;
-                                        app.diff_panel_scroll = 0;
+                                KeyCode::Enter => {
+                                    app.mode = ViewerMode::PacketView;
+                                    let path = app.export_input.trim().to_string();
+                                    if !path.is_empty() {
+                                        app.export_packets(std::path::Path::new(&path)).await;
                                     }
                                 }
-                                KeyCode::Left | KeyCode::Char('h') => {
-                                    app.prev_packet();
-                                }
-                                KeyCode::Right | KeyCode::Char('l') => {
-                                    app.next_packet();
+                                KeyCode::Backspace => {
+                                    app.export_input.pop();
                                 }
-                                KeyCode::Up | KeyCode::Char('k') => {
-                                    // Scroll up in packet details
-                                    // Always allow decrementing - it will be clamped during rendering if needed
-                                    if app.packet_details_scroll > 0 {
-                                        app.packet_details_scroll -= 1;
-                                    }
+                                KeyCode::Char(c) => {
+                                    app.export_input.push(c);
                                 }
-                                KeyCode::Down | KeyCode::Char('j') => {
-                                    // Scroll down in packet details
-                                    // We'll clamp this during rendering based on actual content
-                                    app.packet_details_scroll += 1;
+                                _ => {}
+                            }
+                        }
+                        ViewerMode::GotoInput => {
+                            match key.code {
+                                KeyCode::Esc => {
+                                    app.mode = ViewerMode::PacketView;
                                 }
-                                KeyCode::PageUp => {
-                                    // Jump back 10 packets
-                                    let old_index = app.packet_index;
-                                    for _ in 0..10 {
-                                        app.prev_packet();
-                                    }
-                                    // Reset scroll if packet actually changed
-                                    if app.packet_index != old_index {
-                                        app.packet_details_scroll = 0;
-                                        app.diff_panel_scroll = 0;
+                                KeyCode::Enter => {
+                                    app.mode = ViewerMode::PacketView;
+                                    let result = match GotoTarget::parse(&app.goto_input) {
+                                        Ok(target) => app.goto(target).await,
+                                        Err(e) => Err(e),
+                                    };
+                                    if let Err(e) = result {
+                                        app.error_message = Some(format!("{:#}", e));
                                     }
                                 }
-                                KeyCode::PageDown => {
-                                    // Jump forward 10 packets
-                                    let old_index = app.packet_index;
-                                    for _ in 0..10 {
-                                        app.next_packet();
-                                    }
-                                    // Reset scroll if packet actually changed
-                                    if app.packet_index != old_index {
-                                        app.packet_details_scroll = 0;
-                                        app.diff_panel_scroll = 0;
-                                    }
+                                KeyCode::Backspace => {
+                                    app.goto_input.pop();
                                 }
-                                KeyCode::Home => {
-                                    app.packet_index = 0;
-                                    app.packet_details_scroll = 0;
-                                    app.diff_panel_scroll = 0;
+                                KeyCode::Char(c) => {
+                                    app.goto_input.push(c);
                                 }
-                                KeyCode::End => {
-                                    if let Some(log) = &app.current_log {
-                                        app.packet_index = log.packets.len().saturating_sub(1);
-                                        app.packet_details_scroll = 0;
-                                        app.diff_panel_scroll = 0;
-                                    }
+                                _ => {}
+                            }
+                        }
+                        ViewerMode::WatchInput => {
+                            match key.code {
+                                KeyCode::Esc => {
+                                    app.mode = ViewerMode::PacketView;
                                 }
-                                KeyCode::Char('x') | KeyCode::Char('X') => {
-                                    // Toggle between JSON and hex view
-                                    app.show_hex = !app.show_hex;
-                                    // Reset scroll when toggling view
-                                    app.packet_details_scroll = 0;
-                                    app.diff_panel_scroll = 0;
+                                KeyCode::Enter => {
+                                    app.mode = ViewerMode::PacketView;
+                                    let path = app.watch_input.clone();
+                                    app.toggle_watched_path(&path);
                                 }
-                                KeyCode::Char('f') | KeyCode::Char('F') => {
-                                    // Enter filter input mode
-                                    // Initialize filter input with current filter if one exists
-                                    app.filter_input = app.current_filter.as_ref()
-                                        .map(|f| f.to_string())
-                                        .unwrap_or_else(|| "a".to_string());
-                                    app.mode = ViewerMode::FilterInput;
+                                KeyCode::Backspace => {
+                                    app.watch_input.pop();
+                                }
+                                KeyCode::Char(c) => {
+                                    app.watch_input.push(c);
                                 }
                                 _ => {}
                             }
                         }
-                        ViewerMode::FilterInput => {
+                        ViewerMode::SearchInput => {
                             match key.code {
                                 KeyCode::Esc => {
-                                    // Cancel filter - revert filter input to current filter
-                                    app.filter_input = app.current_filter.as_ref()
-                                        .map(|f| f.to_string())
-                                        .unwrap_or_else(|| "a".to_string());
                                     app.mode = ViewerMode::PacketView;
                                 }
                                 KeyCode::Enter => {
-                                    // Apply filter
-                                    let filter = ViewerApp::parse_filter(&app.filter_input);
-                                    
-                                    // Save current packet number to preserve position
-                                    let current_packet_number = app.current_packet()
-                                        .and_then(|p| p.packet_number)
-                                        .or_else(|| {
-                                            app.current_log.as_ref()
-                                                .and_then(|log| log.packets.first())
-                                                .and_then(|p| p.packet_number)
-                                        });
-                                    
-                                    app.current_filter = filter;
-                                    // Keep filter_input visible so user can see what filter is applied
                                     app.mode = ViewerMode::PacketView;
-                                    
-                                    // Reload session with new filter
-                                    if let Some((session, _, _)) = app.sessions.get(app.selected_session) {
-                                        app.is_loading = true;
-                                        let filter_to_apply = app.current_filter.clone();
-                                        let result = SessionLog::load(&app.db, session.id, filter_to_apply).await;
-                                        app.is_loading = false;
-                                        
-                                        match result {
-                                            Ok(log) => {
-                                                app.current_log = Some(log);
-                                                
-                                                // Reset compare mode when applying filter
-                                                app.compare_mode = false;
-                                                app.baseline_packet_index = None;
-                                                app.baseline_packet_json = None;
-                                                
-                                                // Try to preserve packet position by finding closest packet_number
-                                                if let Some(target_packet_num) = current_packet_number {
-                                                    app.packet_index = app.find_closest_packet_index(target_packet_num);
-                                                } else {
-                                                    app.packet_index = 0;
+                                    if app.search_input.trim().is_empty() {
+                                        // Empty search clears it
+                                        app.current_search = None;
+                                        app.search_exhausted = false;
+                                        app.search_total = None;
+                                        app.search_match = None;
+                                    } else {
+                                        match PacketSearch::parse(&app.search_input) {
+                                            Ok(search) => {
+                                                app.current_search = Some(search);
+                                                let result = match app.count_search_matches().await {
+                                                    Ok(()) => app.jump_to_next_match(true).await,
+                                                    Err(e) => Err(e),
+                                                };
+                                                if let Err(e) = result {
+                                                    app.error_message = Some(format!("Search failed: {}", e));
                                                 }
-                                                
-                                app.packet_details_scroll = 0;
-                                app.diff_panel_scroll = 0;
-                                // Keep filter_input showing the applied filter
                                             }
                                             Err(e) => {
-                                                app.error_message = Some(format!("Failed to load filtered packets: {}", e));
+                                                app.error_message = Some(format!("{:#}", e));
                                             }
                                         }
                                     }
                                 }
                                 KeyCode::Backspace => {
-                                    app.filter_input.pop();
+                                    app.search_input.pop();
                                 }
                                 KeyCode::Char(c) => {
-                                    // Allow multi-character input for filter strings
-                                    app.filter_input.push(c);
+                                    app.search_input.push(c);
                                 }
                                 _ => {}
                             }
@@ -1094,13 +4457,16 @@ async fn main() -> Result<()> {
                                             let tag = app.tag_input.trim().to_string();
                                             if !tag.is_empty() {
                                                 let session_id = tag_mgmt.session_id;
-                                                let add_result = app.db.add_session_tag(session_id, &tag).await;
+                                                let add_result = match database(app.db.as_deref()) {
+                                                    Ok(db) => db.add_session_tag(session_id, &tag).await,
+                                                    Err(e) => Err(e),
+                                                };
                                                 
                                                 // Drop mutable borrow of tag_mgmt before calling refresh_session_tags
                                                 match add_result {
                                                     Ok(_) => {
                                                         // Refresh tags in tag management
-                                                        if let Ok(updated_tags) = app.db.get_session_tags(session_id).await {
+                                                        if let Ok(updated_tags) = async { app.db()?.get_session_tags(session_id).await }.await {
                                                             if let Some(ref mut tm) = app.tag_management {
                                                                 tm.tags = updated_tags;
                                                                 tm.add_tag_mode = false;
@@ -1189,10 +4555,10 @@ async fn main() -> Result<()> {
                                     if let Some(dialog) = app.confirmation_dialog.take() {
                                         match dialog.action {
                                             ConfirmationAction::DeleteTag { session_id, tag } => {
-                                                match app.db.remove_session_tag(session_id, &tag).await {
+                                                match async { app.db()?.remove_session_tag(session_id, &tag).await }.await {
                                                     Ok(_) => {
                                                         // Refresh tags
-                                                        if let Ok(updated_tags) = app.db.get_session_tags(session_id).await {
+                                                        if let Ok(updated_tags) = async { app.db()?.get_session_tags(session_id).await }.await {
                                                             if let Some(ref mut tag_mgmt) = app.tag_management {
                                                                 tag_mgmt.tags = updated_tags;
                                                                 // Adjust selected index if needed
@@ -1220,10 +4586,12 @@ async fn main() -> Result<()> {
                                                 }
                                             }
                                             ConfirmationAction::DeleteSession { session_id } => {
-                                                match app.db.delete_session(session_id).await {
+                                                match async { app.db()?.delete_session(session_id).await }.await {
                                                     Ok(_) => {
                                                         // Remove session from list
-                                                        app.sessions.retain(|(s, _, _)| s.id != session_id);
+                                                        app.sessions.retain(|summary| summary.session.id != session_id);
+                                                        app.session_previews.remove(&session_id);
+                                                        app.session_notes.remove(&session_id);
                                                         
                                                         // Adjust selected index if needed
                                                         if app.selected_session >= app.sessions.len() && !app.sessions.is_empty() {
@@ -1279,6 +4647,29 @@ async fn main() -> Result<()> {
                             }
                         }
                         MouseEventKind::Down(MouseButton::Left) => {
+                            // A click on a byte of the linked view's hex dump puts the cursor on it
+                            if let (ViewerMode::HexLink, Some(link)) = (&app.mode, app.hex_link.as_mut()) {
+                                if let Some(offset) = link.hex_area.and_then(|area| hex_dump_offset(area, mouse.column, mouse.row, link.hex_scroll)) {
+                                    if offset < link.data.len() {
+                                        link.hex_focus = true;
+                                        link.move_cursor(offset);
+                                    }
+                                }
+                            }
+                            // A click on a row of the packet list selects that packet
+                            if let (ViewerMode::PacketView, Some((area, first_index))) = (&app.mode, app.packet_list_rows) {
+                                // Rows start below the border and the column headings
+                                if mouse.column >= area.x && mouse.column < area.x + area.width &&
+                                   mouse.row >= area.y + 2 && mouse.row < area.y + area.height.saturating_sub(1) {
+                                    let index = first_index + (mouse.row - area.y - 2) as usize;
+                                    let total = app.current_log.as_ref().map(|log| log.len()).unwrap_or(0);
+                                    if index < total && index != app.packet_index {
+                                        app.packet_index = index;
+                                        app.packet_details_scroll = 0;
+                                        app.diff_panel_scroll = 0;
+                                    }
+                                }
+                            }
                             // Handle left click for expand/collapse
                             if matches!(app.mode, ViewerMode::PacketView) && !app.show_hex {
                                 if let Some(area) = app.packet_details_area {
@@ -1308,6 +4699,12 @@ async fn main() -> Result<()> {
                 }
                 _ => {}
             }
+        } else if matches!(app.mode, ViewerMode::SessionList) {
+            // Only fetch previews while idle, so holding an arrow key doesn't query every session passed over
+            app.load_selected_preview().await;
+        } else if matches!(app.mode, ViewerMode::PacketView) {
+            app.poll_live_session().await;
+            app.advance_playback().await;
         }
     }
 
@@ -1323,9 +4720,86 @@ fn ui(f: &mut Frame, app: &mut ViewerApp) {
     }
     
     match app.mode {
-        ViewerMode::SessionList => render_session_list(f, app),
-        ViewerMode::PacketView | ViewerMode::FilterInput => render_packet_view(f, app),
+        ViewerMode::SessionList | ViewerMode::SessionSearchInput => {
+            render_session_list(f, app);
+            render_loading_indicator(f, app);
+        }
+        ViewerMode::PacketView
+        | ViewerMode::FilterInput
+        | ViewerMode::SearchInput
+        | ViewerMode::GotoInput
+        | ViewerMode::WatchInput
+        | ViewerMode::ExportInput => render_packet_view(f, app),
         ViewerMode::TagManagement => render_tag_management(f, app),
+        ViewerMode::FilterPresets => {
+            render_packet_view(f, app);
+            render_filter_presets(f, app);
+        }
+        ViewerMode::NoteInput => {
+            if app.note_editor.as_ref().is_some_and(|e| e.from_packet_view) {
+                render_packet_view(f, app);
+            } else {
+                render_session_list(f, app);
+            }
+            render_note_editor(f, app);
+        }
+        ViewerMode::Statistics => {
+            // Render the view it was opened from, then overlay the panel
+            if app.statistics.as_ref().is_some_and(|s| s.from_packet_view) {
+                render_packet_view(f, app);
+            } else {
+                render_session_list(f, app);
+            }
+            render_statistics(f, app);
+        }
+        ViewerMode::DatabaseHealth => {
+            render_session_list(f, app);
+            render_database_health(f, app);
+        }
+        ViewerMode::Bookmarks => {
+            render_packet_view(f, app);
+            render_bookmarks(f, app);
+        }
+        ViewerMode::IgnoredFields => {
+            render_packet_view(f, app);
+            render_ignored_fields(f, app);
+        }
+        ViewerMode::HexLink => {
+            render_packet_view(f, app);
+            render_hex_link(f, app);
+        }
+        ViewerMode::MovementMap => {
+            render_packet_view(f, app);
+            render_movement_map(f, app);
+        }
+        ViewerMode::Gaps => {
+            render_packet_view(f, app);
+            render_gaps(f, app);
+        }
+        ViewerMode::Exchanges => {
+            render_packet_view(f, app);
+            render_exchanges(f, app);
+        }
+        ViewerMode::SequenceDiagram => {
+            render_packet_view(f, app);
+            render_sequence_diagram(f, app);
+        }
+        ViewerMode::DecodeErrors => {
+            render_packet_view(f, app);
+            render_decode_errors(f, app);
+        }
+        ViewerMode::ProtocolVersions => {
+            render_packet_view(f, app);
+            render_protocol_versions(f, app);
+        }
+        ViewerMode::KeyHelp => {
+            if app.key_help.as_ref().is_some_and(|help| help.from_packet_view) {
+                render_packet_view(f, app);
+            } else {
+                render_session_list(f, app);
+            }
+            render_key_help(f, app);
+        }
         ViewerMode::ConfirmationDialog => {
             // Render the underlying view first, then overlay the confirmation dialog
             match app.tag_management {
@@ -1338,7 +4812,7 @@ fn ui(f: &mut Frame, app: &mut ViewerApp) {
 }
 
 fn render_session_list(f: &mut Frame, app: &mut ViewerApp) {
-    let show_error = app.error_message.is_some();
+    let show_error = app.error_message.is_some() || app.status_message.is_some();
     
     let chunks = if show_error {
         Layout::default()
@@ -1352,20 +4826,50 @@ fn render_session_list(f: &mut Frame, app: &mut ViewerApp) {
     };
     
     let main_area = if show_error {
-        // Show error message if present
-        let error_paragraph = Paragraph::new(app.error_message.as_ref().unwrap().as_str())
-            .block(Block::default().borders(Borders::ALL).title("Error").style(Style::default().fg(Color::Red)))
-            .wrap(Wrap { trim: false });
+        // Show error message if present, else the last action's outcome
+        let error_paragraph = match (&app.error_message, &app.status_message) {
+            (Some(error), _) => Paragraph::new(error.as_str())
+                .block(app.theme.block().title("Error").style(Style::default().fg(app.theme.error))),
+            (None, status) => Paragraph::new(status.as_deref().unwrap_or_default())
+                .block(app.theme.block().title("Status").style(Style::default().fg(app.theme.status))),
+        }
+        .wrap(Wrap { trim: false });
         f.render_widget(error_paragraph, chunks[0]);
         chunks[1]
     } else {
         chunks[0]
     };
 
+    // Search box and its help below the list while a search is typed
+    let main_area = if matches!(app.mode, ViewerMode::SessionSearchInput) {
+        let search_chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3), Constraint::Length(2)])
+            .split(main_area);
+        let input = Paragraph::new(format!("Search: {}", app.session_search_input))
+            .block(app.theme.block().title("Search Sessions"))
+            .style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD));
+        f.render_widget(input, search_chunks[1]);
+        let help = Paragraph::new(
+            "words (in the id, tags or note), tag:NAME, from:YYYY-MM-DD, to:YYYY-MM-DD, version:VERSION, has:PACKET_NAME, \
+             archived:only|include, sort:newest|oldest|packets|duration|tag | e.g. crash tag:repro has:text sort:packets | Enter to search (empty shows all), Esc to cancel",
+        )
+        .style(Style::default().fg(app.theme.muted))
+        .wrap(Wrap { trim: false });
+        f.render_widget(help, search_chunks[2]);
+        f.set_cursor(
+            search_chunks[1].x + 9 + app.session_search_input.len() as u16,
+            search_chunks[1].y + 1,
+        );
+        search_chunks[0]
+    } else {
+        main_area
+    };
+
     let items: Vec<ListItem> = app
         .sessions
         .iter()
-        .map(|(session, packet_count, tags)| {
+        .map(|summary @ SessionSummary { session, packet_count, tags, archived, .. }| {
             let duration = if let Some(ended_at) = session.ended_at {
                 let duration = ended_at - session.started_at;
                 format!("{} packets | {}s", packet_count, duration.num_seconds())
@@ -1378,25 +4882,153 @@ fn render_session_list(f: &mut Frame, app: &mut ViewerApp) {
                 format!(" | Tags: {}", tags.join(", "))
             };
             let text = format!(
-                "Session #{} | Started: {} | {}{}",
-                session.id,
+                "{} | Started: {} | {}{}{}",
+                summary.title(),
                 session.started_at.format("%Y-%m-%d %H:%M:%S"),
                 duration,
-                tags_str
+                tags_str,
+                if *archived { " | Archived" } else { "" }
             );
             ListItem::new(text)
         })
         .collect();
 
-    use ratatui::widgets::ListState;
-    let mut list_state = ListState::default();
-    list_state.select(Some(app.selected_session));
+    use ratatui::widgets::ListState;
+    let mut list_state = ListState::default();
+    list_state.select(Some(app.selected_session));
+
+    // Surface database trouble next to the title; queries still report their own errors
+    let title = match app.db.as_ref().map(|db| db.connection_status()) {
+        None => "Session Logs - no database, as a log file was opened with view (q to quit)".to_string(),
+        Some(ConnectionStatus::Connected) => match &app.current_session_search {
+            Some(search) => format!(
+                "Session Logs [Search: {}] - {} found (/ to change, o to sort, Esc to clear)",
+                search.to_input(),
+                app.sessions.len()
+            ),
+            None => "Session Logs (↑↓ to navigate, Enter to select, / to search, ? for keys, q to quit)".to_string(),
+        },
+        Some(ConnectionStatus::Reconnecting { attempt, last_error }) => {
+            format!("Session Logs - database reconnecting (attempt {} failed: {})", attempt, last_error)
+        }
+        Some(ConnectionStatus::Unavailable { last_error }) => {
+            format!("Session Logs - database unavailable ({}), retrying on next action", last_error)
+        }
+    };
+    let list = List::new(items)
+        .block(app.theme.block().title(title))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD));
+    
+    let list_chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(main_area);
+
+    f.render_stateful_widget(list, list_chunks[0], &mut list_state);
+    render_session_preview(f, list_chunks[1], app);
+}
+
+fn render_session_preview(f: &mut Frame, area: Rect, app: &ViewerApp) {
+    let block = app.theme.block().title("Preview");
+    let Some(summary @ SessionSummary { session, packet_count, tags, first_ts, last_ts, archived, .. }) = app.sessions.get(app.selected_session) else {
+        f.render_widget(Paragraph::new("No sessions").block(block), area);
+        return;
+    };
+
+    let heading = Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(Span::styled(summary.title(), heading)),
+        Line::from(match (first_ts, last_ts) {
+            (Some(first), Some(last)) => format!(
+                "{} packets, {} - {}",
+                packet_count,
+                first.format("%Y-%m-%d %H:%M:%S"),
+                last.format("%H:%M:%S")
+            ),
+            _ => format!("{} packets", packet_count),
+        }),
+        Line::from(if tags.is_empty() {
+            "Tags: none".to_string()
+        } else {
+            format!("Tags: {}", tags.join(", "))
+        }),
+    ];
+    if let Some(Some(note)) = app.session_notes.get(&session.id) {
+        let style = Style::default().fg(app.theme.highlight);
+        let mut note_lines = note.lines();
+        lines.push(Line::from(Span::styled(format!("Note: {}", note_lines.next().unwrap_or_default()), style)));
+        lines.extend(note_lines.map(|line| Line::from(Span::styled(format!("      {}", line), style))));
+    }
+    if *archived {
+        lines.push(Line::from(Span::styled("Archived (A to restore)", Style::default().fg(app.theme.muted))));
+    }
+
+    let preview = match app.session_previews.get(&session.id) {
+        Some(Ok(preview)) => preview,
+        Some(Err(e)) => {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(format!("Failed to load preview: {}", e), Style::default().fg(app.theme.error))));
+            f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+            return;
+        }
+        None => {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Loading preview...", Style::default().fg(app.theme.muted))));
+            f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+            return;
+        }
+    };
+
+    let packet_line = |packet: &PacketSummary| {
+        let (arrow, color) = match packet.direction.as_str() {
+            "clientbound" => ("←", app.theme.clientbound),
+            _ => ("→", app.theme.serverbound),
+        };
+        Line::from(vec![
+            Span::styled(format!("#{:<7} {:>8.3}s ", packet.packet_number, packet.session_time_ms as f64 / 1000.0), Style::default().fg(app.theme.muted)),
+            Span::styled(format!("{} ", arrow), Style::default().fg(color)),
+            Span::raw(packet.name.clone().unwrap_or_else(|| "unknown".to_string())),
+        ])
+    };
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Milestones", heading)));
+    if preview.milestones.is_empty() {
+        lines.push(Line::from(Span::styled("none", Style::default().fg(app.theme.muted))));
+    }
+    for milestone in &preview.milestones {
+        let repeat = if milestone.count > 1 { format!(" (x{})", milestone.count) } else { String::new() };
+        lines.push(Line::from(format!(
+            "{} at #{} ({:.3}s){}",
+            milestone.milestone,
+            milestone.packet_number,
+            milestone.session_time_ms as f64 / 1000.0,
+            repeat
+        )));
+    }
 
-    let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Session Logs (↑↓ to navigate, Enter to select, t to tag, d to delete, q to quit)"))
-        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
-    
-    f.render_stateful_widget(list, main_area, &mut list_state);
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Top packet types", heading)));
+    for (name, count) in &preview.top_packet_types {
+        lines.push(Line::from(format!("{:>7}  {}", count, name)));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("First packets", heading)));
+    lines.extend(preview.first_packets.iter().map(packet_line));
+
+    // Skip the tail when the whole session already fits in the first packets
+    let first_last = preview.first_packets.last().map(|p| p.packet_number);
+    let tail: Vec<&PacketSummary> = preview.last_packets.iter()
+        .filter(|p| first_last.is_none_or(|n| p.packet_number > n))
+        .collect();
+    if !tail.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Last packets", heading)));
+        lines.extend(tail.into_iter().map(packet_line));
+    }
+
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
 }
 
 fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
@@ -1411,19 +5043,31 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
         None => return,
     };
 
+    let current_note = app.current_packet()
+        .and_then(|p| p.packet_number)
+        .and_then(|number| log.notes.get(&number))
+        .cloned();
+
     let chunks = Layout::default()
         .direction(ratatui::layout::Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Header
             Constraint::Length(6), // Filter panel (taller to fit longer help text)
-            Constraint::Length(3), // Timeline
+            Constraint::Length(if app.show_rate_histogram { 2 + HISTOGRAM_ROWS } else { 3 }), // Timeline
+            // Note on the current packet, up to 4 of its lines
+            Constraint::Length(current_note.as_ref().map_or(0, |note| 2 + note.lines().count().clamp(1, 4) as u16)),
+            // Watch panel, up to 8 paths
+            Constraint::Length(match app.watched_paths.len() {
+                0 => 0,
+                watched => 2 + watched.min(8) as u16,
+            }),
             Constraint::Min(0),    // Packet details
         ])
         .split(f.size());
 
     // Header
     let packet_num = app.packet_index + 1;
-    let total_packets = log.packets.len();
+    let total_packets = log.len();
     
     // Get packet data before borrowing app
     let packet_data = app.current_packet().map(|p| (
@@ -1444,47 +5088,148 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
     let filter_str = app.current_filter.as_ref()
         .map(|f| format!(" [Filter: {}]", f.to_string()))
         .unwrap_or_else(|| String::new());
+    // Which match this is while on one, else how many there are
+    let current_number = packet_data.as_ref().and_then(|(_, number, _, _)| *number);
+    let matches_str = match (app.search_match, app.search_total) {
+        (Some((number, position)), Some(total)) if Some(number) == current_number => format!(" - {}/{}", position, total),
+        (_, Some(1)) => " - 1 match".to_string(),
+        (_, Some(total)) => format!(" - {} matches", total),
+        (_, None) => String::new(),
+    };
+    let search_str = app.current_search.as_ref()
+        .map(|s| format!(
+            " [Search: {}{}{}]",
+            s.to_input(),
+            matches_str,
+            if app.search_exhausted { " - no more matches" } else { "" }
+        ))
+        .unwrap_or_default();
+    let bookmark_str = packet_data.as_ref()
+        .and_then(|(_, number, _, _)| log.bookmarks.get(&(*number)?))
+        .map(|bookmark| match bookmark.label {
+            Some(ref label) => format!(" [Bookmark: {}]", label),
+            None => " [Bookmark]".to_string(),
+        })
+        .unwrap_or_default();
+    let ignored_count = app.current_packet_name()
+        .and_then(|name| app.ignored_fields.get(name))
+        .map_or(0, |fields| fields.len());
+    let baseline_str = match (app.baseline_from_other_session(), app.baseline_session_id, app.baseline_packet.as_ref()) {
+        _ if app.diff_previous => match app.baseline_packet.as_ref().and_then(|p| p.packet_number) {
+            Some(number) => format!("Previous of its type (Packet #{})", number),
+            None => "None - first packet of its type".to_string(),
+        },
+        (true, Some(session_id), Some(baseline)) => format!(
+            "Session #{} Packet #{}",
+            session_id,
+            baseline.packet_number.unwrap_or_default()
+        ),
+        _ => format!("Packet {}", app.baseline_packet_index.map(|i| i + 1).unwrap_or(0)),
+    };
     let compare_str = if app.compare_mode {
-        format!(" [Compare Mode | Baseline: Packet {}]", 
-            app.baseline_packet_index.map(|i| i + 1).unwrap_or(0))
+        format!(" [Compare Mode | Baseline: {}{}{}]", 
+            baseline_str,
+            if app.baseline_pinned { " (pinned)" } else { "" },
+            match ignored_count {
+                0 => String::new(),
+                1 => " | Ignoring 1 field".to_string(),
+                count => format!(" | Ignoring {} fields", count),
+            })
     } else {
         String::new()
     };
-    let version_str = log.protocol_version.as_ref()
-        .map(|v| format!("Protocol: {}", v))
-        .unwrap_or_else(|| "Protocol: Unknown".to_string());
+    let version_str = match (log.protocol_version.as_ref(), app.protocol_override.as_ref()) {
+        // Chosen with V
+        (Some(v), Some(o)) => format!("Protocol: {} (decoding as {})", v, o),
+        (None, Some(o)) => format!("Protocol: Unknown (decoding as {})", o),
+        // Raw packets are shown undecoded without definitions for their version
+        (Some(v), None) if app.protocols.get(v).is_none() => format!("Protocol: {} (no definitions)", v),
+        (Some(v), None) => format!("Protocol: {}", v),
+        (None, None) => "Protocol: Unknown".to_string(),
+    };
+    let live_str = match (log.live, app.follow) {
+        (true, true) => " [LIVE - following]",
+        (true, false) => " [LIVE - L: follow]",
+        (false, _) => "",
+    };
+    let playback_str = match app.playback {
+        Some(_) => format!(" [PLAYING {} - Space: pause]", packet_playback::speed_label(app.playback_speed)),
+        None => String::new(),
+    };
+    let session_name = app.sessions.iter()
+        .find(|summary| summary.session.id == log.session_id)
+        .and_then(|summary| summary.name.as_deref());
+    let title = match &log.log_file {
+        Some(file) => format!("Log {}", file.name),
+        None => session_title(log.session_id, session_name),
+    };
     let header_text = format!(
-        "Session: #{} | {} | Packet: {}/{} | Time: {} | View: {}{}{} | [Left/Right/h/l: navigate, Up/Down/k/j/mouse wheel: scroll, Click +/-: expand/collapse JSON, PgUp/PgDn: jump 10, Home/End: first/last, x: view, f: filter, c: compare, Esc: exit compare, q: back]",
-        log.session_id,
+        "{}{}{} | {} | Packet: {}/{} | Time: {} | View: {}{}{}{}{} | [{}]",
+        title,
+        live_str,
+        playback_str,
         version_str,
         packet_num,
         total_packets,
         session_time,
         view_mode,
         filter_str,
-        compare_str
+        search_str,
+        bookmark_str,
+        compare_str,
+        // The rest are listed by ?
+        app.keymap.hints(&["help", "previous_packet", "next_packet", "filter", "search", "compare", "back"])
     );
 
     let header = Paragraph::new(header_text)
-        .block(Block::default().borders(Borders::ALL).title("lazypacket"));
+        .block(app.theme.block().title("lazypacket"));
     f.render_widget(header, chunks[0]);
 
     // Filter panel
     render_filter_panel(f, chunks[1], &app);
 
     // Timeline visualization
-    render_timeline(f, chunks[2], app);
+    if app.show_rate_histogram {
+        render_rate_histogram(f, chunks[2], app);
+    } else {
+        render_timeline(f, chunks[2], app);
+    }
+
+    if let Some(note) = current_note {
+        let note_paragraph = Paragraph::new(note)
+            .block(app.theme.block().title("Note"))
+            .style(Style::default().fg(app.theme.highlight));
+        f.render_widget(note_paragraph, chunks[3]);
+    }
+
+    if !app.watched_paths.is_empty() {
+        render_watch_panel(f, chunks[4], app);
+    }
+
+    // Packet list on the left, when there's room for it beside the details
+    let details_chunk = if app.show_packet_list && chunks[5].width >= 2 * PACKET_LIST_WIDTH {
+        let list_chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Horizontal)
+            .constraints([Constraint::Length(PACKET_LIST_WIDTH), Constraint::Min(0)])
+            .split(chunks[5]);
+        let first_index = render_packet_list(f, list_chunks[0], app, log);
+        app.packet_list_rows = Some((list_chunks[0], first_index));
+        list_chunks[1]
+    } else {
+        app.packet_list_rows = None;
+        chunks[5]
+    };
 
     // Split packet details area horizontally if in compare mode
     let detail_chunks: Vec<Rect> = if app.compare_mode && !app.show_hex {
         Layout::default()
             .direction(ratatui::layout::Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(chunks[3])
+            .split(details_chunk)
             .to_vec()
     } else {
         // Single panel - use full width
-        vec![chunks[3]]
+        vec![details_chunk]
     };
 
     // Store packet details area for mouse click detection (before using packet_data)
@@ -1492,7 +5237,7 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
     app.packet_details_area = Some(details_area);
 
     // Extract packet data for rendering
-    let packet_json_for_diff = packet_data.as_ref().and_then(|(_, _, json, _)| json.clone());
+    let (baseline_json_for_diff, packet_json_for_diff) = app.compared_json();
     let packet_name_for_title = packet_data.as_ref()
         .and_then(|(_, _, json, _)| json.as_ref())
         .and_then(|json| json.get("name"))
@@ -1500,18 +5245,15 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
         .map(|s| s.to_string())
         .unwrap_or_else(|| "unknown".to_string());
     let is_baseline_for_diff = app.baseline_packet_index == Some(app.packet_index);
-    let baseline_json_for_diff = app.baseline_packet_json.clone();
     let diff_panel_scroll_value = app.diff_panel_scroll;
     
     // Extract metadata for delta calculation (use as_ref to avoid move)
     let current_packet_timestamp = packet_data.as_ref().map(|(ts, _, _, _)| *ts);
     let current_packet_number = packet_data.as_ref().and_then(|(_, num, _, _)| *num);
-    let baseline_packet_timestamp = app.baseline_packet_index
-        .and_then(|idx| log.packets.get(idx))
-        .map(|p| p.timestamp);
-    let baseline_packet_number = app.baseline_packet_index
-        .and_then(|idx| log.packets.get(idx))
-        .and_then(|p| p.packet_number);
+    // Times and packet numbers of another session's baseline aren't comparable to this one's
+    let baseline_for_deltas = app.baseline_packet.as_ref().filter(|_| !app.baseline_from_other_session());
+    let baseline_packet_timestamp = baseline_for_deltas.map(|p| p.timestamp);
+    let baseline_packet_number = baseline_for_deltas.and_then(|p| p.packet_number);
 
     // Packet details (left panel, or full width if not in compare mode)
     if let Some((timestamp, packet_number_opt, packet_json, direction)) = packet_data {
@@ -1521,8 +5263,8 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
         };
         
         let direction_color = match direction {
-            PacketDirection::Clientbound => Color::Green,
-            PacketDirection::Serverbound => Color::Blue,
+            PacketDirection::Clientbound => app.theme.clientbound,
+            PacketDirection::Serverbound => app.theme.serverbound,
         };
 
         let timestamp_dt = DateTime::<Utc>::from_timestamp_millis(timestamp)
@@ -1539,12 +5281,26 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
         let (lines_vec, total_lines) = if app.show_hex {
             // Hex view
             let data_len = packet_data_for_hex.as_ref().map(|d| d.len()).unwrap_or(0);
+            let decoded = match (app.protocol_parser(), app.current_packet()) {
+                (Some(parser), Some(packet)) => Some(parser.decode_packet(&packet.data, packet.direction)),
+                _ => None,
+            };
+            // Where protocol decoding broke, to find in the dump
+            let decode_error_str = decoded.as_ref()
+                .and_then(|decoded| decoded.error.as_ref())
+                .map(|error| format!("Decode error at 0x{:04x}: {}\n", error.offset, error))
+                .unwrap_or_default();
+            let field_spans_str = decoded.as_ref()
+                .map(|decoded| field_spans_text(decoded, data_len))
+                .unwrap_or_default();
             let hex_content = format!(
-                "Direction: {}\nTimestamp: {}\n{}Size: {} bytes\n\nHex Dump:\n{}",
+                "Direction: {}\nTimestamp: {}\n{}Size: {} bytes\n{}{}\nHex Dump:\n{}",
                 direction_str,
                 time_str,
                 packet_number_str,
                 data_len,
+                decode_error_str,
+                field_spans_str,
                 packet_data_for_hex.as_ref().map(|d| hex_dump(d, 16)).unwrap_or_else(|| String::new())
             );
             let lines: Vec<Line> = hex_content.lines().map(|l| Line::from(l.to_string())).collect();
@@ -1572,7 +5328,7 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
                 }
                 
                 // Try to decode packet using protocol parser
-                if let (Some(ref parser), Some((ref data, dir))) = (app.protocol_parser.as_ref(), packet_data_for_json.as_ref()) {
+                if let (Some(parser), Some((ref data, dir))) = (app.protocol_parser(), packet_data_for_json.as_ref()) {
                     let decoded = parser.decode_packet(data, *dir);
                     
                     if let Some(packet_name) = decoded.packet_name {
@@ -1581,6 +5337,12 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
                     if let Some(packet_id) = decoded.packet_id {
                         json_value["packet_id"] = serde_json::json!(format!("0x{:02x}", packet_id));
                     }
+                    if let Some(note) = decoded.note {
+                        json_value["decode_note"] = serde_json::json!(note);
+                    }
+                    if let Some(error) = decoded.error {
+                        json_value["decode_error"] = serde_json::to_value(error).unwrap_or_default();
+                    }
                     
                     if !decoded.fields.is_empty() {
                         json_value["decoded_fields"] = serde_json::Value::Object(
@@ -1624,8 +5386,9 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
                     "  "
                 };
                 
-                let line_text = format!("{}{}", indicator, json_line.line);
-                all_lines.push(Line::from(line_text));
+                let mut spans = vec![Span::raw(indicator)];
+                spans.extend(highlight_json(&json_line.line, Style::default(), &app.theme));
+                all_lines.push(Line::from(spans));
                 line_to_path.push(json_line.path);
             }
 
@@ -1678,20 +5441,25 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
             String::new()
         };
         
+        // Where the packet references the entity being followed
+        let entity_str = followed_entity(app.current_filter.as_ref())
+            .and_then(|entity| Some((entity, entity_reference(packet_json.as_ref()?, entity)?)))
+            .map(|(entity, path)| format!(" | Entity {} in {}", entity, path))
+            .unwrap_or_default();
         let title_text = format!(
-            "Packet Details ({}) | {}{} | {:.3}s | {} | {}",
+            "Packet Details ({}) | {}{} | {:.3}s | {}{} | {}",
             view_type,
             packet_num_str,
             direction_str,
             relative_time_sec,
             packet_name_for_title,
+            entity_str,
             scroll_info
         );
         
         let details_paragraph = Paragraph::new(visible_lines)
             .block(
-                Block::default()
-                    .borders(Borders::ALL)
+                app.theme.block()
                     .title(Span::styled(
                         title_text,
                         Style::default().fg(direction_color),
@@ -1714,22 +5482,26 @@ fn render_packet_view(f: &mut Frame, app: &mut ViewerApp) {
                 baseline_packet_timestamp,
                 baseline_packet_number,
                 diff_panel_scroll_value, 
-                &mut app.diff_panel_scroll
+                &mut app.diff_panel_scroll,
+                &app.theme,
             );
         }
     } else {
         let empty = Paragraph::new("No packet selected")
-            .block(Block::default().borders(Borders::ALL).title("Packet Details"));
+            .block(app.theme.block().title("Packet Details"));
         f.render_widget(empty, detail_chunks[0]);
         
         // Render empty diff panel if in compare mode
         if app.compare_mode && !app.show_hex && detail_chunks.len() > 1 {
             let empty_diff = Paragraph::new("No packet selected")
-                .block(Block::default().borders(Borders::ALL).title("Differences"));
+                .block(app.theme.block().title("Differences"));
             f.render_widget(empty_diff, detail_chunks[1]);
         }
     }
     
+    // Over the panels below the filter input
+    render_filter_suggestions(f, chunks[1], app);
+
     // Show loading indicator overlay if loading
     render_loading_indicator(f, app);
     
@@ -1750,7 +5522,8 @@ fn render_diff_panel(
     baseline_timestamp: Option<i64>,
     baseline_packet_number: Option<i64>,
     scroll: u16, 
-    scroll_ref: &mut u16
+    scroll_ref: &mut u16,
+    theme: &Theme,
 ) {
     // Build colored lines for differences
     let (diff_lines_vec, total_diff_lines) = if let Some(ref packet_json) = packet_json {
@@ -1770,7 +5543,7 @@ fn render_diff_panel(
                     };
                     all_lines.push(Line::from(Span::styled(
                         time_delta_str,
-                        Style::default().fg(Color::Cyan)
+                        Style::default().fg(theme.accent)
                     )));
                 }
                 
@@ -1784,7 +5557,7 @@ fn render_diff_panel(
                     };
                     all_lines.push(Line::from(Span::styled(
                         packet_delta_str,
-                        Style::default().fg(Color::Cyan)
+                        Style::default().fg(theme.accent)
                     )));
                 }
                 
@@ -1797,7 +5570,7 @@ fn render_diff_panel(
             if is_baseline {
                 all_lines.push(Line::from(Span::styled(
                     "This is the baseline packet for comparison.",
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD)
                 )));
                 all_lines.push(Line::from(""));
                 all_lines.push(Line::from("Navigate to other packets to see differences."));
@@ -1811,9 +5584,12 @@ fn render_diff_panel(
                     all_lines.push(Line::from("Differences from baseline:"));
                     all_lines.push(Line::from(""));
                     
-                    // Add colored diff lines
-                    for (line, color) in diff_lines {
-                        all_lines.push(Line::from(Span::styled(line, Style::default().fg(color))));
+                    // Markers in the diff colors, values highlighted over them
+                    for line in diff_lines {
+                        let style = Style::default().fg(theme.diff(line.added));
+                        let mut spans = vec![Span::styled(line.prefix, style.add_modifier(Modifier::BOLD))];
+                        spans.extend(highlight_json(&line.json, style, theme));
+                        all_lines.push(Line::from(spans));
                     }
                 }
             }
@@ -1821,7 +5597,7 @@ fn render_diff_panel(
             let total_lines = all_lines.len();
             (all_lines, total_lines)
         } else {
-            (vec![Line::from("Error: Baseline packet JSON not available")], 1)
+            (vec![Line::from("No baseline packet to compare against")], 1)
         }
     } else {
         (vec![Line::from("Error: Current packet JSON not available for comparison")], 1)
@@ -1854,8 +5630,7 @@ fn render_diff_panel(
     
     let diff_paragraph = Paragraph::new(visible_lines)
         .block(
-            Block::default()
-                .borders(Borders::ALL)
+            theme.block()
                 .title(Span::styled(
                     format!(
                         "Differences {}",
@@ -1865,7 +5640,7 @@ fn render_diff_panel(
                             String::new()
                         }
                     ),
-                    Style::default().fg(Color::Cyan),
+                    Style::default().fg(theme.accent),
                 )),
         )
         .wrap(Wrap { trim: false });
@@ -1873,13 +5648,72 @@ fn render_diff_panel(
     f.render_widget(diff_paragraph, area);
 }
 
+/// Indexes of the packets shown in a list of `rows` rows: the current packet is kept in the middle, except
+/// near either end of the session
+fn packet_list_window(current: usize, total: usize, rows: usize) -> std::ops::Range<usize> {
+    let start = current.saturating_sub(rows / 2).min(total.saturating_sub(rows));
+    start..(start + rows).min(total)
+}
+
+/// Table of the packets around the current one; returns the index of the first row
+fn render_packet_list(f: &mut Frame, area: Rect, app: &ViewerApp, log: &SessionLog) -> usize {
+    // Borders and the column headings take three rows
+    let window = packet_list_window(app.packet_index, log.len(), area.height.saturating_sub(3) as usize);
+    let first_index = window.start;
+
+    let rows: Vec<Row> = window
+        .filter_map(|i| Some((i, log.get(i)?)))
+        .map(|(i, packet)| {
+            let name = packet.packet_json.as_ref()
+                .and_then(|json| json.get("name"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown");
+            let (direction, color) = match packet.direction {
+                PacketDirection::Clientbound => ("C", app.theme.clientbound),
+                PacketDirection::Serverbound => ("S", app.theme.serverbound),
+            };
+            let bookmark = packet.packet_number.and_then(|number| log.bookmarks.get(&number));
+            let style = if i == app.packet_index {
+                Style::default().fg(color).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else if app.compare_mode && app.baseline_packet_index == Some(i) {
+                Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)
+            } else if let Some(bookmark) = bookmark {
+                Style::default().fg(bookmark_color(bookmark.color))
+            } else {
+                Style::default().fg(color)
+            };
+            Row::new(vec![
+                Cell::from(packet.packet_number.map(|n| n.to_string()).unwrap_or_else(|| (i + 1).to_string())),
+                Cell::from(format!("{:.3}s", log.relative_time(packet.timestamp) as f64 / 1000.0)),
+                Cell::from(direction),
+                Cell::from(name.to_string()),
+                Cell::from(packet.size().to_string()),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Length(7),
+        Constraint::Length(10),
+        Constraint::Length(3),
+        Constraint::Min(10),
+        Constraint::Length(6),
+    ];
+    let table = Table::new(rows, widths)
+        .header(Row::new(vec!["#", "Time", "Dir", "Name", "Size"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(app.theme.block().title("Packets (t: hide)"));
+    f.render_widget(table, area);
+    first_index
+}
+
 fn render_timeline(f: &mut Frame, area: Rect, app: &ViewerApp) {
     let _log = match &app.current_log {
         Some(log) => log,
         None => return,
     };
 
-    if app.current_log.as_ref().map(|l| l.packets.is_empty()).unwrap_or(true) {
+    if app.current_log.as_ref().map(|l| l.len() == 0).unwrap_or(true) {
         return;
     }
     
@@ -1888,7 +5722,7 @@ fn render_timeline(f: &mut Frame, area: Rect, app: &ViewerApp) {
     // Show a timeline around the current packet
     let window_size = (area.width as usize).saturating_sub(4).min(100);
     let current_idx = app.packet_index;
-    let total = log.packets.len();
+    let total = log.len();
 
     // Calculate window start/end
     let half_window = window_size / 2;
@@ -1899,24 +5733,32 @@ fn render_timeline(f: &mut Frame, area: Rect, app: &ViewerApp) {
     let mut timeline_styles = Vec::new();
 
     for i in start..end {
-        let direction = log.packets[i].direction;
+        // Packets outside the loaded window (only while a page is being fetched) are skipped
+        let Some(packet) = log.get(i) else {
+            continue;
+        };
+        let direction = packet.direction;
         let (symbol, color) = match direction {
-            PacketDirection::Clientbound => ('?', Color::Green),
-            PacketDirection::Serverbound => ('?', Color::Blue),
+            PacketDirection::Clientbound => ('?', app.theme.clientbound),
+            PacketDirection::Serverbound => ('?', app.theme.serverbound),
         };
 
         let is_baseline = app.compare_mode && app.baseline_packet_index == Some(i);
         let is_current = i == current_idx;
+        let bookmark = packet.packet_number.and_then(|number| log.bookmarks.get(&number));
 
         let style = if is_current && is_baseline {
             // Current packet is also baseline - use yellow with bold and reversed
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD | Modifier::REVERSED)
         } else if is_current {
             // Current packet (not baseline)
             Style::default().fg(color).add_modifier(Modifier::BOLD | Modifier::REVERSED)
         } else if is_baseline {
             // Baseline packet (not current) - use yellow background
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)
+        } else if let Some(bookmark) = bookmark {
+            // Bookmarked packet - in its bookmark's color
+            Style::default().fg(bookmark_color(bookmark.color)).add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
         } else {
             // Regular packet
             Style::default().fg(color)
@@ -1942,12 +5784,694 @@ fn render_timeline(f: &mut Frame, area: Rect, app: &ViewerApp) {
     
     let timeline = Paragraph::new(Line::from(spans))
         .block(
-            Block::default()
-                .borders(Borders::ALL)
+            app.theme.block()
                 .title(timeline_title),
         );
 
-    f.render_widget(timeline, area);
+    f.render_widget(timeline, area);
+}
+
+/// Packets per second (clientbound, serverbound) in each of at most `columns` columns spanning the whole
+/// session, and the seconds each column covers
+fn rate_histogram(buckets: &[PacketRateBucket], columns: usize) -> (i64, Vec<(f64, f64)>) {
+    let Some(last) = buckets.last() else {
+        return (1, Vec::new());
+    };
+    let span = last.second + 1;
+    let columns = columns.max(1) as i64;
+    let seconds_per_column = (span + columns - 1) / columns;
+    let mut rates = vec![(0.0, 0.0); ((span + seconds_per_column - 1) / seconds_per_column) as usize];
+    for bucket in buckets {
+        let column = &mut rates[(bucket.second / seconds_per_column) as usize];
+        column.0 += bucket.clientbound as f64;
+        column.1 += bucket.serverbound as f64;
+    }
+    for column in &mut rates {
+        column.0 /= seconds_per_column as f64;
+        column.1 /= seconds_per_column as f64;
+    }
+    (seconds_per_column, rates)
+}
+
+/// Row `row` (from the bottom) of a histogram column `rows` high, in eighths of a cell: clientbound packets
+/// stacked under serverbound ones. A cell where they meet has the clientbound part over a serverbound background
+fn histogram_cell(clientbound: f64, serverbound: f64, peak: f64, rows: usize, row: usize, theme: &Theme) -> (char, Style) {
+    // Any packets at all get at least an eighth, so short bursts don't vanish next to the peak
+    let eighths = |rate: f64| match (rate / peak * (rows * 8) as f64).round() as usize {
+        0 if rate > 0.0 => 1,
+        eighths => eighths,
+    };
+    let client_top = eighths(clientbound);
+    let total_top = eighths(clientbound + serverbound).max(client_top);
+    let bottom = row * 8;
+    if total_top <= bottom {
+        (' ', Style::default())
+    } else if client_top >= bottom + 8 {
+        ('█', Style::default().fg(theme.clientbound))
+    } else if client_top > bottom {
+        let style = Style::default().fg(theme.clientbound);
+        let style = if total_top >= bottom + 8 { style.bg(theme.serverbound) } else { style };
+        (BAR_EIGHTHS[client_top - bottom], style)
+    } else {
+        (BAR_EIGHTHS[(total_top - bottom).min(8)], Style::default().fg(theme.serverbound))
+    }
+}
+
+/// The timeline as packets per second over the whole session (`H`), with the current packet's column highlighted
+fn render_rate_histogram(f: &mut Frame, area: Rect, app: &ViewerApp) {
+    let Some(log) = &app.current_log else {
+        return;
+    };
+    let block = app.theme.block();
+    let Some(buckets) = &log.rate else {
+        f.render_widget(Paragraph::new("Loading...").block(block.title("Packet rate")), area);
+        return;
+    };
+
+    let rows = area.height.saturating_sub(2) as usize;
+    let (seconds_per_column, rates) = rate_histogram(buckets, area.width.saturating_sub(2) as usize);
+    let peak = rates.iter().map(|(client, server)| client + server).fold(0.0, f64::max);
+    let current_column = app
+        .current_packet()
+        .map(|p| (log.relative_time(p.timestamp) / 1000 / seconds_per_column) as usize);
+
+    let lines: Vec<Line> = (0..rows)
+        .rev()
+        .map(|row| {
+            let spans: Vec<Span> = rates
+                .iter()
+                .enumerate()
+                .map(|(column, &(client, server))| {
+                    let (symbol, style) = histogram_cell(client, server, peak, rows, row, &app.theme);
+                    if Some(column) == current_column {
+                        let symbol = if symbol == ' ' { '│' } else { symbol };
+                        Span::styled(symbol.to_string(), Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+                    } else {
+                        Span::styled(symbol.to_string(), style)
+                    }
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect();
+
+    let title = format!(
+        "Packet rate (peak {:.1}/s, {}s per column; green: clientbound, blue: serverbound) | H: timeline",
+        peak, seconds_per_column
+    );
+    f.render_widget(Paragraph::new(lines).block(block.title(title)), area);
+}
+
+/// Byte range of each top-level decoded field, to find in the hex dump, and any bytes after the last one
+fn field_spans_text(decoded: &protocol::DecodedPacket, data_len: usize) -> String {
+    let top_level: Vec<_> = decoded.spans
+        .iter()
+        .filter(|(path, _)| !path.contains(['.', '[']))
+        .collect();
+    if top_level.is_empty() {
+        return String::new();
+    }
+
+    let mut text = String::from("Fields:\n");
+    for (path, range) in &top_level {
+        text.push_str(&format!("  0x{:04x}-0x{:04x} {:>6} bytes  {}\n", range.start, range.end, range.len(), path));
+    }
+    let end = top_level.iter().map(|(_, range)| range.end).max().unwrap_or(0);
+    if decoded.error.is_none() && end < data_len {
+        text.push_str(&format!("  0x{:04x}-0x{:04x} {:>6} bytes  (not decoded)\n", end, data_len, data_len - end));
+    }
+    text
+}
+
+/// Index of the innermost decoded field containing a byte (the shortest span; the later one, nested deeper, on
+/// a tie), if any
+fn field_at(fields: &[(String, std::ops::Range<usize>)], offset: usize) -> Option<usize> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, range))| range.contains(&offset))
+        .min_by_key(|(index, (_, range))| (range.len(), std::cmp::Reverse(*index)))
+        .map(|(index, _)| index)
+}
+
+/// Decoded value at a span path ("entries[2].key")
+fn field_value<'a>(fields: &'a indexmap::IndexMap<String, serde_json::Value>, path: &str) -> Option<&'a serde_json::Value> {
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let (key, indexes) = first.split_once('[').map_or((first, ""), |(key, rest)| (key, rest));
+    let mut value = fields.get(key)?;
+    let mut rest = indexes;
+    loop {
+        for index in rest.split('[').filter(|index| !index.is_empty()) {
+            value = value.get(index.trim_end_matches(']').parse::<usize>().ok()?)?;
+        }
+        let Some(segment) = segments.next() else {
+            return Some(value);
+        };
+        let (key, indexes) = segment.split_once('[').map_or((segment, ""), |(key, rest)| (key, rest));
+        value = value.get(key)?;
+        rest = indexes;
+    }
+}
+
+/// A row of the linked view's hex dump, laid out like hex_dump's, with the selected field's bytes highlighted
+fn hex_dump_row(data: &[u8], row: usize, selected: &std::ops::Range<usize>, cursor: Option<usize>, theme: &Theme) -> Line<'static> {
+    let start = row * 16;
+    let chunk = &data[start..(start + 16).min(data.len())];
+    let style = |offset: usize| {
+        let style = if selected.contains(&offset) {
+            Style::default().fg(theme.highlight_text).bg(theme.highlight)
+        } else {
+            Style::default()
+        };
+        if cursor == Some(offset) {
+            style.add_modifier(Modifier::BOLD | Modifier::REVERSED | Modifier::UNDERLINED)
+        } else {
+            style
+        }
+    };
+
+    let mut spans = vec![Span::styled(format!("{:04x}  ", start), Style::default().fg(theme.muted))];
+    for (i, byte) in chunk.iter().enumerate() {
+        spans.push(Span::styled(format!("{:02x}", byte), style(start + i)));
+        spans.push(Span::raw(" "));
+    }
+    spans.push(Span::raw(" ".repeat(3 * (16 - chunk.len()) + 1)));
+    for (i, byte) in chunk.iter().enumerate() {
+        let ch = if (32..127).contains(byte) { *byte as char } else { '.' };
+        spans.push(Span::styled(ch.to_string(), style(start + i)));
+    }
+    Line::from(spans)
+}
+
+/// Byte offset under a click on the linked view's hex dump (hex or ASCII column), if it's on one
+fn hex_dump_offset(area: Rect, column: u16, row: u16, scroll: usize) -> Option<usize> {
+    if column <= area.x || row <= area.y || column >= area.x + area.width - 1 || row >= area.y + area.height - 1 {
+        return None;
+    }
+    let x = (column - area.x - 1) as usize;
+    let line = (row - area.y - 1) as usize + scroll;
+    // "0000  " then 16 "xx " then a space and 16 ASCII characters
+    let byte = match x {
+        6..=53 if (x - 6) % 3 < 2 => (x - 6) / 3,
+        55..=70 => x - 55,
+        _ => return None,
+    };
+    Some(line * 16 + byte)
+}
+
+fn render_movement_map(f: &mut Frame, app: &ViewerApp) {
+    let Some(ref map) = app.movement_map else {
+        return;
+    };
+    let modal_area = centered_rect(90, 85, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Map
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let current = app.current_packet()
+        .and_then(|p| p.packet_number)
+        .and_then(|number| movement_map::point_at(&map.path, number));
+    let title = match current.map(|i| map.path[i]) {
+        _ if map.path.is_empty() => "Movement - no player positions (move_player, player_auth_input) in this session".to_string(),
+        Some(point) => format!(
+            "Movement - {} positions | x {:.1} y {:.1} z {:.1} at packet #{}",
+            map.path.len(),
+            point.x,
+            point.y,
+            point.z,
+            point.packet_number
+        ),
+        None => format!("Movement - {} positions | the current packet is before the first", map.path.len()),
+    };
+    let block = app.theme.block().title(title);
+    let (x_bounds, y_bounds) = movement_map::map_bounds(&map.path, block.inner(chunks[0]).width, block.inner(chunks[0]).height);
+    // The path up to the current packet over the rest of it, from S(tart) to @ (the current position)
+    let travelled = current.map_or(0, |i| i + 1);
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds(x_bounds)
+        .y_bounds(y_bounds)
+        .paint(|ctx| {
+            let segments = [(&map.path[travelled.saturating_sub(1)..], app.theme.muted), (&map.path[..travelled], app.theme.accent)];
+            for (points, color) in segments {
+                for pair in points.windows(2) {
+                    ctx.draw(&CanvasLine::new(pair[0].x, -pair[0].z, pair[1].x, -pair[1].z, color));
+                }
+                ctx.layer();
+            }
+            if let Some(start) = map.path.first() {
+                ctx.print(start.x, -start.z, Span::styled("S", Style::default().fg(app.theme.secondary)));
+            }
+            if let Some(point) = current.map(|i| map.path[i]) {
+                ctx.print(point.x, -point.z, Span::styled("@", Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)));
+            }
+        });
+    f.render_widget(canvas, chunks[0]);
+
+    let help_paragraph = Paragraph::new("←/→/h/l: previous/next position | Home/End: first/last | Esc/q/M: close | North is up")
+        .style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[1]);
+}
+
+fn render_gaps(f: &mut Frame, app: &ViewerApp) {
+    let (Some(ref panel), Some(ref log)) = (&app.gaps, &app.current_log) else {
+        return;
+    };
+    let modal_area = centered_rect(80, 80, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Gaps
+            Constraint::Length(panel.round_trips.len().max(1) as u16 + 3), // Round trips, under a header
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let name = |name: &Option<String>| name.clone().unwrap_or_else(|| "?".to_string());
+    let gap_items: Vec<ListItem> = if panel.gaps.is_empty() {
+        vec![ListItem::new("(Fewer than two packets pass the filter)")]
+    } else {
+        panel
+            .gaps
+            .iter()
+            .map(|gap| ListItem::new(Line::from(vec![
+                Span::styled(format!("{:>8}ms ", gap.gap_ms), Style::default().fg(app.theme.accent)),
+                Span::styled(format!("at {:>8.3}s  ", gap.session_time_ms as f64 / 1000.0), Style::default().fg(app.theme.muted)),
+                Span::raw(format!(
+                    "#{} {} → #{} {}",
+                    gap.previous_packet_number,
+                    name(&gap.previous_name),
+                    gap.packet_number,
+                    name(&gap.name)
+                )),
+            ])))
+            .collect()
+    };
+    let mut gap_state = ratatui::widgets::ListState::default();
+    if panel.selected < panel.gaps.len() {
+        gap_state.select(Some(panel.selected));
+    }
+    let filter = match &panel.filter {
+        Some(filter) => format!(" (filter: {})", filter),
+        None => String::new(),
+    };
+    let gaps = List::new(gap_items)
+        .block(app.theme.block().title(format!("Largest gaps between packets{} - Session #{}", filter, log.session_id)))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(gaps, chunks[0], &mut gap_state);
+
+    let mut round_trip_items = vec![ListItem::new(Span::styled(
+        format!("  {:<46} {:>6} {:>8} {:>8} {:>8} {:>10}", "Request → response", "count", "min", "median", "max", "unanswered"),
+        Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD),
+    ))];
+    if panel.round_trips.is_empty() {
+        round_trip_items.push(ListItem::new("  (No answered requests of the known pairs in this session)"));
+    }
+    round_trip_items.extend(panel.round_trips.iter().map(|round_trip| {
+        let pair = format!("{} → {}", round_trip.pair.request, round_trip.pair.response);
+        ListItem::new(format!(
+            "{:<46} {:>6} {:>6}ms {:>6}ms {:>6}ms {:>10}",
+            pair, round_trip.count, round_trip.min_ms, round_trip.median_ms, round_trip.max_ms, round_trip.unanswered
+        ))
+    }));
+    let mut round_trip_state = ratatui::widgets::ListState::default();
+    if panel.selected >= panel.gaps.len() {
+        // After the header
+        round_trip_state.select(Some(panel.selected - panel.gaps.len() + 1));
+    }
+    let round_trips = List::new(round_trip_items)
+        .block(app.theme.block().title("Round trips - whole session"))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(round_trips, chunks[1], &mut round_trip_state);
+
+    let help_paragraph = Paragraph::new("↑↓/j/k: select | Enter: go to the packet after the gap, or the slowest request | Esc/q/G: close")
+        .style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[2]);
+}
+
+fn render_sequence_diagram(f: &mut Frame, app: &ViewerApp) {
+    let (Some(ref diagram), Some(ref log)) = (&app.sequence_diagram, &app.current_log) else {
+        return;
+    };
+    let modal_area = centered_rect(90, 85, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Diagram
+            Constraint::Length(if diagram.handshake { 1 } else { 0 }), // Missing handshake steps
+            Constraint::Length(1), // Help text, or the outcome of copying
+        ])
+        .split(modal_area);
+
+    let title = if diagram.handshake {
+        format!("Handshake - Session #{} ({} packets)", log.session_id, diagram.packets.len())
+    } else {
+        format!("Sequence from the current packet - Session #{} ({} packets)", log.session_id, diagram.packets.len())
+    };
+    let block = app.theme.block().title(title);
+    // The time and packet columns, the lanes' bars and the highlight symbol take 26 columns
+    let width = (block.inner(chunks[0]).width as usize).saturating_sub(26).clamp(12, 80);
+    let start = diagram.packets.first().map_or(0, |packet| packet.session_time_ms);
+    let items: Vec<ListItem> = diagram
+        .packets
+        .iter()
+        .map(|packet| {
+            let color = if packet.clientbound { app.theme.clientbound } else { app.theme.serverbound };
+            ListItem::new(sequence_diagram::diagram_line(packet, start, width)).style(Style::default().fg(color))
+        })
+        .collect();
+    let diagram_area = block.inner(chunks[0]);
+    f.render_widget(block, chunks[0]);
+    let rows = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(diagram_area);
+    let heading = Paragraph::new(format!("  {}", sequence_diagram::heading(width)))
+        .style(Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD));
+    f.render_widget(heading, rows[0]);
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !diagram.packets.is_empty() {
+        list_state.select(Some(diagram.selected));
+    }
+    let list = List::new(items)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, rows[1], &mut list_state);
+
+    if diagram.handshake {
+        let (text, color) = match diagram.missing.as_slice() {
+            [] => ("Every login step is there".to_string(), app.theme.status),
+            missing => (format!("Missing login steps: {}", missing.join(", ")), app.theme.error),
+        };
+        f.render_widget(Paragraph::new(text).style(Style::default().fg(color)), chunks[1]);
+    }
+
+    let (help_text, help_color) = match (&app.error_message, &app.status_message) {
+        (Some(error), _) => (error.as_str(), app.theme.error),
+        (None, Some(status)) => (status.as_str(), app.theme.status),
+        _ => (
+            "↑↓/j/k/PgUp/PgDn: select | Enter: go to the packet | Tab: handshake / from the current packet | y: copy as text | Esc/q/T: close",
+            app.theme.muted,
+        ),
+    };
+    f.render_widget(Paragraph::new(help_text).style(Style::default().fg(help_color)), chunks[2]);
+}
+
+fn render_exchanges(f: &mut Frame, app: &ViewerApp) {
+    let (Some(ref panel), Some(ref log)) = (&app.exchanges, &app.current_log) else {
+        return;
+    };
+    // Along the bottom, so the packet view above shows the packet gone to
+    let area = f.size();
+    let height = (area.height / 2).max(6).min(area.height);
+    let modal_area = Rect { y: area.y + area.height - height, height, ..area };
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Exchange list
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let current = app.current_packet().and_then(|packet| packet.packet_number);
+    let items: Vec<ListItem> = if panel.exchanges.is_empty() {
+        vec![ListItem::new("(No answered requests of the known pairs in this session)")]
+    } else {
+        panel
+            .exchanges
+            .iter()
+            .map(|exchange| {
+                // The end of the exchange the packet view is on
+                let style = |number: i64| {
+                    if current == Some(number) {
+                        Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    }
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{:>7}ms  ", exchange.round_trip_ms), Style::default().fg(app.theme.accent)),
+                    Span::styled(format!("#{} {}", exchange.request, exchange.pair.request), style(exchange.request)),
+                    Span::raw(" → "),
+                    Span::styled(format!("#{} {}", exchange.response, exchange.pair.response), style(exchange.response)),
+                ]))
+            })
+            .collect()
+    };
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !panel.exchanges.is_empty() {
+        list_state.select(Some(panel.selected));
+    }
+    let list = List::new(items)
+        .block(app.theme.block().title(format!("Requests and responses - Session #{} ({} exchanges)", log.session_id, panel.exchanges.len())))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    let help_paragraph = Paragraph::new("↑↓/j/k: select (goes to the request) | ←/h/Enter: request | →/l: response | Esc/q/R: close")
+        .style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[1]);
+}
+
+fn render_decode_errors(f: &mut Frame, app: &ViewerApp) {
+    let (Some(ref panel), Some(ref log)) = (&app.decode_errors, &app.current_log) else {
+        return;
+    };
+    let modal_area = centered_rect(80, 70, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Packet list
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let items: Vec<ListItem> = if panel.problems.is_empty() {
+        vec![ListItem::new("(Every packet in this session decoded)")]
+    } else {
+        panel
+            .problems
+            .iter()
+            .map(|problem| {
+                let direction_color = if problem.direction == "clientbound" { app.theme.clientbound } else { app.theme.serverbound };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("#{:<8}", problem.packet_number), Style::default().fg(app.theme.muted)),
+                    Span::styled(format!("{:<28} ", problem.name), Style::default().fg(direction_color)),
+                    Span::styled(problem.message.clone(), Style::default().fg(app.theme.error)),
+                ]))
+            })
+            .collect()
+    };
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !panel.problems.is_empty() {
+        list_state.select(Some(panel.selected));
+    }
+    let list = List::new(items)
+        .block(app.theme.block().title(format!("Decode errors - Session #{} ({} packets)", log.session_id, panel.problems.len())))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    let help_paragraph = Paragraph::new("↑↓/j/k: select | Enter: go to the packet | Esc/q/D: close")
+        .style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[1]);
+}
+
+fn render_protocol_versions(f: &mut Frame, app: &ViewerApp) {
+    let (Some(ref panel), Some(packet)) = (&app.protocol_versions, app.current_packet()) else {
+        return;
+    };
+    let modal_area = centered_rect(85, 80, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Versions and comparison
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+    let panes = Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints([Constraint::Length(28), Constraint::Min(0)])
+        .split(chunks[0]);
+
+    let items: Vec<ListItem> = panel
+        .versions
+        .iter()
+        .map(|version| {
+            let mark = if app.protocol_override.as_ref() == Some(version) {
+                " (in use)"
+            } else if panel.logged.as_ref() == Some(version) {
+                " (logged)"
+            } else {
+                ""
+            };
+            ListItem::new(format!("{}{}", version, mark))
+        })
+        .collect();
+    let mut list_state = ratatui::widgets::ListState::default();
+    list_state.select(Some(panel.selected));
+    let list = List::new(items)
+        .block(app.theme.block().title("Protocol versions"))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, panes[0], &mut list_state);
+
+    // The stored packet against the selected version's decoding of its payload
+    let version = &panel.versions[panel.selected];
+    let stored = packet.packet_json.clone().unwrap_or_default();
+    let describe = |json: &serde_json::Value| {
+        format!("{} with {} fields", json["name"].as_str().unwrap_or("unknown"), version_switch::count_fields(&json["params"]))
+    };
+    let mut lines = vec![Line::from(format!(
+        "Stored ({}): {}",
+        panel.logged.as_deref().unwrap_or("unknown version"),
+        describe(&stored)
+    ))];
+    match &panel.comparison {
+        Err(e) => lines.push(Line::from(Span::styled(format!("Can't decode it with {}: {}", version, e), Style::default().fg(app.theme.error)))),
+        Ok((decoded, error)) => {
+            lines.push(Line::from(format!("Decoded with {}: {}", version, describe(decoded))));
+            if let Some(error) = error {
+                lines.push(Line::from(Span::styled(format!("Decoding broke at {}", error), Style::default().fg(app.theme.error))));
+            }
+            lines.push(Line::from(""));
+            let diff_lines = format_json_diff(&compare_json(&stored, decoded), "", 0);
+            if diff_lines.is_empty() {
+                lines.push(Line::from("Decodes the same as stored."));
+            }
+            for line in diff_lines {
+                let style = Style::default().fg(app.theme.diff(line.added));
+                let mut spans = vec![Span::styled(line.prefix, style.add_modifier(Modifier::BOLD))];
+                spans.extend(highlight_json(&line.json, style, &app.theme));
+                lines.push(Line::from(spans));
+            }
+        }
+    }
+    let title = match packet.packet_number {
+        Some(number) => format!("Packet #{} - stored (-) and decoded with {} (+)", number, version),
+        None => format!("Stored (-) and decoded with {} (+)", version),
+    };
+    let comparison = Paragraph::new(lines)
+        .block(app.theme.block().title(title))
+        .scroll((panel.scroll, 0));
+    f.render_widget(comparison, panes[1]);
+
+    let help_paragraph = Paragraph::new("↑↓/j/k: select a version | PgUp/PgDn: scroll | Enter: decode the session with it | Esc/q/V: close")
+        .style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[1]);
+}
+
+fn render_hex_link(f: &mut Frame, app: &mut ViewerApp) {
+    let Some(ref mut link) = app.hex_link else {
+        return;
+    };
+    let modal_area = centered_rect(90, 85, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Fields and hex dump
+            Constraint::Length(1), // Byte under the cursor, or the decode error
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+    let panes = Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(16 * 4 + 9)])
+        .split(chunks[0]);
+
+    let (focused, unfocused) = (Style::default().fg(app.theme.highlight), Style::default());
+    let items: Vec<ListItem> = link.fields
+        .iter()
+        .zip(&link.values)
+        .map(|((path, range), value)| {
+            let depth = path.matches(['.', '[']).count();
+            let name = path.rsplit('.').next().unwrap_or(path);
+            let mut spans = vec![
+                Span::styled(format!("{:04x} ", range.start), Style::default().fg(app.theme.muted)),
+                Span::raw(format!("{}{}", "  ".repeat(depth), name)),
+            ];
+            if let Some(value) = value {
+                spans.push(Span::raw(": "));
+                spans.extend(highlight_json(value, Style::default(), &app.theme));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let mut list_state = ratatui::widgets::ListState::default();
+    list_state.select(Some(link.selected));
+    let list = List::new(items)
+        .block(
+            app.theme.block()
+                .border_style(if link.hex_focus { unfocused } else { focused })
+                .title("Decoded Fields"),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, panes[0], &mut list_state);
+
+    // Keep the cursor's row (or the selected field's first row) in view
+    let rows = link.data.len().div_ceil(16);
+    let visible = panes[1].height.saturating_sub(2) as usize;
+    let focus_row = link.cursor / 16;
+    if focus_row < link.hex_scroll {
+        link.hex_scroll = focus_row;
+    } else if focus_row >= link.hex_scroll + visible {
+        link.hex_scroll = focus_row + 1 - visible;
+    }
+    let selected = link.fields.get(link.selected).map(|(_, range)| range.clone()).unwrap_or_default();
+    let cursor = link.hex_focus.then_some(link.cursor);
+    let lines: Vec<Line> = (link.hex_scroll..rows.min(link.hex_scroll + visible))
+        .map(|row| hex_dump_row(&link.data, row, &selected, cursor, &app.theme))
+        .collect();
+    let hex = Paragraph::new(lines).block(
+        app.theme.block()
+            .border_style(if link.hex_focus { focused } else { unfocused })
+            .title(format!("Hex Dump ({} bytes)", link.data.len())),
+    );
+    f.render_widget(hex, panes[1]);
+    link.hex_area = Some(panes[1]);
+
+    let status = match (&link.error, field_at(&link.fields, link.cursor)) {
+        (Some(error), _) if !link.hex_focus => Span::styled(error.clone(), Style::default().fg(app.theme.error)),
+        (_, Some(index)) => {
+            let (path, range) = &link.fields[index];
+            Span::raw(format!(
+                "0x{:04x}: {} (0x{:04x}-0x{:04x}, {} bytes)",
+                link.cursor, path, range.start, range.end, range.len()
+            ))
+        }
+        (_, None) => Span::raw(format!("0x{:04x}: not part of a decoded field", link.cursor)),
+    };
+    f.render_widget(Paragraph::new(Line::from(status)), chunks[1]);
+
+    let help_text = if link.hex_focus {
+        "←→↑↓/h/l/k/j: move the byte cursor | click: pick a byte | Tab: fields | Esc/q/v: close"
+    } else {
+        "↑↓/j/k: select a field | Tab/click: pick bytes in the hex dump | Esc/q/v: close"
+    };
+    f.render_widget(Paragraph::new(help_text).style(Style::default().fg(app.theme.muted)), chunks[2]);
 }
 
 fn hex_dump(data: &[u8], bytes_per_line: usize) -> String {
@@ -1982,9 +6506,68 @@ fn hex_dump(data: &[u8], bytes_per_line: usize) -> String {
     output
 }
 
+/// Watched paths (w) and their values at the current packet
+fn render_watch_panel(f: &mut Frame, area: Rect, app: &ViewerApp) {
+    let rows = app.watch_rows();
+    let width = rows.iter().map(|row| row.path.chars().count()).max().unwrap_or(0);
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .map(|row| {
+            let mut spans = vec![Span::styled(format!("{:<width$}  ", row.path, width = width), Style::default().fg(app.theme.json_key))];
+            match row.value {
+                Some(value) => spans.push(Span::raw(value)),
+                None => spans.push(Span::styled("-", Style::default().fg(app.theme.muted))),
+            }
+            if let Some(change) = row.change {
+                spans.push(Span::styled(format!("  {}", change), Style::default().fg(app.theme.accent)));
+            }
+            if let Some(number) = row.from {
+                spans.push(Span::styled(format!("  (packet #{})", number), Style::default().fg(app.theme.muted)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let more = match app.watched_paths.len() {
+        watched if watched > 8 => format!(" - {} more below", watched - 8),
+        _ => String::new(),
+    };
+    let watch_paragraph = Paragraph::new(lines).block(app.theme.block().title(format!("Watch{}", more)));
+    f.render_widget(watch_paragraph, area);
+}
+
 fn render_filter_panel(f: &mut Frame, area: Rect, app: &ViewerApp) {
-    let filter_text = format!("Filter: {}", app.filter_input);
-    let help_text = "Format: [!][c|s|a][.packet_name][,filter2,...] | Examples: s.player_auth_input, c.start_game, !s.player_auth_movement, s.*action* | Enter to apply, Esc to cancel";
+    // The search and go to boxes take the filter panel's place while they're being typed
+    let (prefix, input, title) = match app.mode {
+        ViewerMode::SearchInput => ("Search: ", &app.search_input, "Search Packets"),
+        ViewerMode::GotoInput => ("Go to: ", &app.goto_input, "Go To Packet"),
+        ViewerMode::WatchInput => ("Watch: ", &app.watch_input, "Watch a Field"),
+        ViewerMode::ExportInput if app.export_all => ("Export to: ", &app.export_input, "Export Filtered Packets"),
+        ViewerMode::ExportInput => ("Export to: ", &app.export_input, "Export Packet"),
+        _ => ("Filter: ", &app.filter_input, "Filter Packets"),
+    };
+    let filter_text = format!("{}{}", prefix, input);
+    let help_text = if matches!(app.mode, ViewerMode::ExportInput) {
+        "A file path: .ndjson or .jsonl for one packet per line, anything else for pretty JSON | Enter to export (overwrites the file), Esc to cancel"
+    } else if matches!(app.mode, ViewerMode::WatchInput) {
+        "A JSON path in the packet, with [i] for array elements: params.position.x, params.tick, params.items[0].count (params. can be left out) | Enter to watch it, or stop watching it if it's watched (empty stops watching them all), Esc to cancel"
+    } else if matches!(app.mode, ViewerMode::GotoInput) {
+        "A packet number (1234 or #1234), a time since the session's first packet (@90s, @1.5m) or a time from the current packet (+5s, -500ms; units ms, s, m, h) | Enter to go, Esc to cancel"
+    } else if matches!(app.mode, ViewerMode::SearchInput) {
+        "Words search packet names and contents; chat: searches chat (text) messages; a JSON object finds packets containing it, e.g. {\"name\":\"move_player\",\"params\":{\"runtime_id\":42}} | Searches within the filter | Enter to search (empty clears), n/N for the next/previous match, Esc to cancel"
+    } else {
+        "Format: [!][c|s|a][.packet_name][.@from-to][.#from-to][.entity:id][.$field=value][,filter2,...] | Examples: s.player_auth_input, c.start_game, !s.player_auth_movement, s.*action*, a.@10s-20s, c.text.#100-, a.entity:42, a.$params.runtime_id=42 | Enter to apply, ↑/↓ for earlier filters, Tab to complete a packet name or for saved presets, Esc to cancel"
+    };
+    // While typing: problems with the packet names already typed (the ones being typed are suggested below)
+    let help_text = match (app.protocol_parser(), matches!(app.mode, ViewerMode::FilterInput)) {
+        (Some(parser), true) if filter_name_being_typed(&app.filter_input).is_none() => {
+            let mut lines = ViewerApp::parse_filter(&app.filter_input)
+                .map(|filter| filter_name_warnings(&filter, &parser))
+                .unwrap_or_default();
+            lines.push(help_text.to_string());
+            lines.join(" | ")
+        }
+        _ => help_text.to_string(),
+    };
     
     let chunks = Layout::default()
         .direction(ratatui::layout::Direction::Vertical)
@@ -1994,32 +6577,90 @@ fn render_filter_panel(f: &mut Frame, area: Rect, app: &ViewerApp) {
         ])
         .split(area);
     
-    let input_style = if matches!(app.mode, ViewerMode::FilterInput) {
-        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+    let input_style = if matches!(app.mode, ViewerMode::FilterInput | ViewerMode::SearchInput | ViewerMode::GotoInput | ViewerMode::WatchInput | ViewerMode::ExportInput) {
+        Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD)
     } else {
-        Style::default().fg(Color::White)
+        Style::default().fg(app.theme.text)
     };
     
     let input_paragraph = Paragraph::new(filter_text.as_str())
-        .block(Block::default().borders(Borders::ALL).title("Filter Packets"))
+        .block(app.theme.block().title(title))
         .style(input_style);
     f.render_widget(input_paragraph, chunks[0]);
     
-    let help_paragraph = Paragraph::new(help_text)
+    // The outcome of the last action (an export, a go to...) takes the help text's place until the next key
+    let (help_text, help_color) = match (&app.mode, &app.error_message, &app.status_message) {
+        (ViewerMode::PacketView, Some(error), _) => (error.clone(), app.theme.error),
+        (ViewerMode::PacketView, None, Some(status)) => (status.clone(), app.theme.status),
+        _ => (help_text, app.theme.muted),
+    };
+    let help_paragraph = Paragraph::new(help_text.as_str())
         .block(Block::default())
-        .style(Style::default().fg(Color::DarkGray))
+        .style(Style::default().fg(help_color))
         .wrap(Wrap { trim: false });
     f.render_widget(help_paragraph, chunks[1]);
     
-    // Show cursor only when in FilterInput mode
-    if matches!(app.mode, ViewerMode::FilterInput) {
+    // Show cursor only while typing
+    if matches!(app.mode, ViewerMode::FilterInput | ViewerMode::SearchInput | ViewerMode::GotoInput | ViewerMode::WatchInput | ViewerMode::ExportInput) {
         f.set_cursor(
-            chunks[0].x + 8 + app.filter_input.len() as u16,
+            chunks[0].x + (prefix.len() + input.len()) as u16,
             chunks[0].y + 1,
         );
     }
 }
 
+/// Packet names completing the one being typed, in a popup under it; the first is what Tab completes to
+fn render_filter_suggestions(f: &mut Frame, panel: Rect, app: &ViewerApp) {
+    const MAX_ROWS: usize = 8;
+    if !matches!(app.mode, ViewerMode::FilterInput) {
+        return;
+    }
+    let Some((start, _, _)) = filter_name_being_typed(&app.filter_input) else {
+        return;
+    };
+    let suggestions = app.filter_suggestions();
+    if suggestions.is_empty() {
+        return;
+    }
+
+    let rows: Vec<(String, String)> = suggestions
+        .iter()
+        .take(MAX_ROWS)
+        .map(|s| (s.name.clone(), s.count.map(|count| count.to_string()).unwrap_or_default()))
+        .collect();
+    let name_width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let count_width = rows.iter().map(|(_, count)| count.len()).max().unwrap_or(0);
+    let title = match suggestions.len().saturating_sub(MAX_ROWS) {
+        0 => "Tab".to_string(),
+        more => format!("Tab | +{} more", more),
+    };
+    let width = ((name_width + count_width + 3).max(title.len() + 2) + 2) as u16;
+    let screen = f.size();
+    // Under the name being typed, kept on screen
+    let x = (panel.x + 1 + "Filter: ".len() as u16 + start as u16).min(screen.width.saturating_sub(width));
+    let y = panel.y + 2;
+    let area = Rect::new(x, y, width.min(screen.width), (rows.len() as u16 + 2).min(screen.height.saturating_sub(y)));
+
+    let items: Vec<ListItem> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, count))| {
+            // Names the session has are the likelier ones; the rest only come from the protocol
+            let style = match (i, count.is_empty()) {
+                (0, _) => Style::default().fg(app.theme.highlight_text).bg(app.theme.highlight),
+                (_, false) => Style::default().fg(app.theme.text),
+                (_, true) => Style::default().fg(app.theme.secondary),
+            };
+            ListItem::new(format!("{:<name_width$} {:>count_width$}", name, count)).style(style)
+        })
+        .collect();
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(
+        List::new(items).block(app.theme.block().title(title).border_style(Style::default().fg(app.theme.highlight))),
+        area,
+    );
+}
+
 fn render_loading_indicator(f: &mut Frame, app: &ViewerApp) {
     if !app.is_loading {
         return;
@@ -2032,15 +6673,391 @@ fn render_loading_indicator(f: &mut Frame, app: &ViewerApp) {
     let spinner_chars = ['?', '?', '?', '?', '?', '?', '?', '?', '?', '?'];
     let spinner = spinner_chars[(app.loading_frame as usize / 3) % spinner_chars.len()];
     
-    let loading_text = format!("{} Loading packets...", spinner);
+    let (what, title) = match &app.pending_load {
+        Some(PendingLoad { kind: PendingLoadKind::Open, .. }) => ("session", "Loading (Esc to cancel)"),
+        Some(PendingLoad { kind: PendingLoadKind::Refilter { .. }, .. }) => ("packets", "Loading (Esc to cancel)"),
+        None => ("sessions", "Loading"),
+    };
+    let loading_text = format!("{} Loading {}...", spinner, what);
     let loading_paragraph = Paragraph::new(loading_text)
-        .block(Block::default().borders(Borders::ALL).title("Loading"))
-        .style(Style::default().fg(Color::Cyan))
+        .block(app.theme.block().title(title))
+        .style(Style::default().fg(app.theme.accent))
         .alignment(ratatui::layout::Alignment::Center);
     
     f.render_widget(loading_paragraph, popup_area);
 }
 
+fn render_filter_presets(f: &mut Frame, app: &ViewerApp) {
+    let Some(ref picker) = app.filter_presets else {
+        return;
+    };
+    let modal_area = centered_rect(60, 50, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Preset list
+            Constraint::Length(if picker.name_input.is_some() { 3 } else { 0 }), // Name input
+            Constraint::Length(1), // Last save or delete
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let name_width = picker.presets.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    let items: Vec<ListItem> = if picker.presets.is_empty() {
+        vec![ListItem::new("(No saved filters)")]
+    } else {
+        picker.presets
+            .iter()
+            .map(|preset| ListItem::new(Line::from(vec![
+                Span::raw(format!("{:<width$}  ", preset.name, width = name_width)),
+                Span::styled(preset.filter.clone(), Style::default().fg(app.theme.muted)),
+            ])))
+            .collect()
+    };
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !picker.presets.is_empty() {
+        list_state.select(Some(picker.selected));
+    }
+    let list = List::new(items)
+        .block(app.theme.block().title("Filter Presets"))
+        .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    if let Some(ref name) = picker.name_input {
+        let input_paragraph = Paragraph::new(format!("Name: {}", name))
+            .block(app.theme.block().title(format!("Save '{}'", app.filter_input.trim())))
+            .style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD));
+        f.render_widget(input_paragraph, chunks[1]);
+        f.set_cursor(chunks[1].x + 7 + name.len() as u16, chunks[1].y + 1);
+    }
+
+    if let Some(ref message) = picker.message {
+        f.render_widget(Paragraph::new(message.as_str()).style(Style::default().fg(app.theme.accent)), chunks[2]);
+    }
+
+    let help_text = if picker.name_input.is_some() {
+        "Enter: save (replaces a preset of the same name) | Esc: cancel"
+    } else {
+        "↑↓/j/k: select | Enter: load into filter | s: save current filter | d: delete | Esc/q: back"
+    };
+    f.render_widget(Paragraph::new(help_text).style(Style::default().fg(app.theme.muted)), chunks[3]);
+}
+
+fn bookmark_color(color: BookmarkColor) -> Color {
+    match color {
+        BookmarkColor::Yellow => Color::Yellow,
+        BookmarkColor::Red => Color::Red,
+        BookmarkColor::Green => Color::Green,
+        BookmarkColor::Blue => Color::Blue,
+        BookmarkColor::Magenta => Color::Magenta,
+        BookmarkColor::Cyan => Color::Cyan,
+    }
+}
+
+fn render_bookmarks(f: &mut Frame, app: &ViewerApp) {
+    let (Some(ref panel), Some(ref log)) = (&app.bookmarks_panel, &app.current_log) else {
+        return;
+    };
+    let modal_area = centered_rect(60, 50, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Bookmark list
+            Constraint::Length(if panel.label_input.is_some() { 3 } else { 0 }), // Label input
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let items: Vec<ListItem> = if log.bookmarks.is_empty() {
+        vec![ListItem::new("(No bookmarks - b in the packet view bookmarks the current packet)")]
+    } else {
+        log.bookmarks
+            .values()
+            .map(|bookmark| ListItem::new(Line::from(vec![
+                Span::styled("■ ", Style::default().fg(bookmark_color(bookmark.color))),
+                Span::raw(format!("Packet #{:<8}", bookmark.packet_number)),
+                Span::styled(bookmark.label.clone().unwrap_or_default(), Style::default().fg(app.theme.secondary)),
+            ])))
+            .collect()
+    };
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !log.bookmarks.is_empty() {
+        list_state.select(Some(panel.selected));
+    }
+    let list = List::new(items)
+        .block(app.theme.block().title(format!("Bookmarks - Session #{}", log.session_id)))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    if let Some(ref label) = panel.label_input {
+        let input_paragraph = Paragraph::new(format!("Label: {}", label))
+            .block(app.theme.block().title("Label"))
+            .style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD));
+        f.render_widget(input_paragraph, chunks[1]);
+        f.set_cursor(chunks[1].x + 8 + label.chars().count() as u16, chunks[1].y + 1);
+    }
+
+    let help_text = if panel.label_input.is_some() {
+        "Enter: save (an empty label removes it) | Esc: cancel"
+    } else {
+        "↑↓/j/k: select | Enter: go to packet | l: label | c: color | d: delete | Esc/q: close"
+    };
+    f.render_widget(Paragraph::new(help_text).style(Style::default().fg(app.theme.muted)), chunks[2]);
+}
+
+/// Keys of every view (?), the one it was opened from first; the packet view's come from the keymap
+fn render_key_help(f: &mut Frame, app: &mut ViewerApp) {
+    let Some(ref mut help) = app.key_help else {
+        return;
+    };
+    let modal_area = centered_rect(70, 80, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(modal_area);
+
+    let packet_view: Vec<(String, &str)> = app
+        .keymap
+        .cheat_sheet()
+        .into_iter()
+        .chain(viewer_config::PACKET_VIEW_FIXED_KEYS.iter().map(|(keys, description)| (keys.to_string(), *description)))
+        .collect();
+    let mut sections: Vec<(&str, Vec<(String, &str)>)> = viewer_config::VIEW_KEYS
+        .iter()
+        .map(|(view, keys)| (*view, keys.iter().map(|(keys, description)| (keys.to_string(), *description)).collect()))
+        .collect();
+    // After the session list unless it was opened from the packet view
+    sections.insert(if help.from_packet_view { 0 } else { 1 }, ("Packet view", packet_view));
+
+    let width = sections
+        .iter()
+        .flat_map(|(_, keys)| keys.iter().map(|(keys, _)| keys.chars().count()))
+        .max()
+        .unwrap_or(0);
+    let mut lines = Vec::new();
+    for (view, keys) in sections {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(view, Style::default().fg(app.theme.accent).add_modifier(Modifier::BOLD))));
+        for (keys, description) in keys {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<width$}  ", keys, width = width), Style::default().fg(app.theme.highlight)),
+                Span::raw(description),
+            ]));
+        }
+    }
+
+    let visible = chunks[0].height.saturating_sub(2);
+    help.scroll = help.scroll.min((lines.len() as u16).saturating_sub(visible));
+    let total = lines.len();
+    let keys = Paragraph::new(lines)
+        .block(app.theme.block().title(format!("Keys [{}-{}/{}]", help.scroll + 1, (help.scroll + visible).min(total as u16), total)))
+        .scroll((help.scroll, 0));
+    f.render_widget(keys, chunks[0]);
+
+    let help_text = format!(
+        "↑↓/j/k/PgUp/PgDn: scroll | Esc/q/?: close | packet view keys can be remapped in the keymap section of {}",
+        viewer_config::ViewerConfig::path().display()
+    );
+    f.render_widget(Paragraph::new(help_text).style(Style::default().fg(app.theme.muted)), chunks[1]);
+}
+
+fn render_ignored_fields(f: &mut Frame, app: &ViewerApp) {
+    let Some(ref panel) = app.ignored_fields_panel else {
+        return;
+    };
+    let modal_area = centered_rect(60, 50, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Field list
+            Constraint::Length(if panel.path_input.is_some() { 3 } else { 0 }), // Path input
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let ignored = app.ignored_fields.get(&panel.packet_name);
+    let items: Vec<ListItem> = if panel.fields.is_empty() {
+        vec![ListItem::new("(No ignored fields - fields that differ from the baseline in compare mode are listed here)")]
+    } else {
+        panel.fields
+            .iter()
+            .map(|path| {
+                let is_ignored = ignored.is_some_and(|fields| fields.contains(path));
+                ListItem::new(Line::from(vec![
+                    Span::styled(if is_ignored { "[x] " } else { "[ ] " }, Style::default().fg(if is_ignored { app.theme.highlight } else { app.theme.secondary })),
+                    Span::raw(path.clone()),
+                ]))
+            })
+            .collect()
+    };
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !panel.fields.is_empty() {
+        list_state.select(Some(panel.selected));
+    }
+    let list = List::new(items)
+        .block(app.theme.block().title(format!("Ignored Fields - {}", panel.packet_name)))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    if let Some(ref path) = panel.path_input {
+        let input_paragraph = Paragraph::new(format!("Path: {}", path))
+            .block(app.theme.block().title("Ignore Field"))
+            .style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD));
+        f.render_widget(input_paragraph, chunks[1]);
+        f.set_cursor(chunks[1].x + 7 + path.chars().count() as u16, chunks[1].y + 1);
+    }
+
+    let help_text = if panel.path_input.is_some() {
+        "Enter: ignore (keys from the packet root, e.g. params.tick) | Esc: cancel"
+    } else {
+        "↑↓/j/k: select | Enter/Space: ignore or compare again | a: add a path | Esc/q: close"
+    };
+    f.render_widget(Paragraph::new(help_text).style(Style::default().fg(app.theme.muted)), chunks[2]);
+}
+
+fn render_note_editor(f: &mut Frame, app: &ViewerApp) {
+    let Some(ref editor) = app.note_editor else {
+        return;
+    };
+    let modal_area = centered_rect(60, 30, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Note text
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let title = match editor.packet_number {
+        _ if editor.renaming => format!("Name - Session #{}", editor.session_id),
+        Some(number) => format!("Note - Session #{}, Packet #{}", editor.session_id, number),
+        None => format!("Note - Session #{}", editor.session_id),
+    };
+    let input_paragraph = Paragraph::new(editor.text.as_str())
+        .block(app.theme.block().title(title))
+        .style(Style::default().fg(app.theme.highlight))
+        .wrap(Wrap { trim: false });
+    f.render_widget(input_paragraph, chunks[0]);
+
+    // Cursor after the last character, following the wrapped lines
+    let width = chunks[0].width.saturating_sub(2).max(1) as usize;
+    let (row, column) = editor.text.split('\n').fold((0, 0), |(row, _), line| {
+        let chars = line.chars().count();
+        (row + chars / width + 1, chars % width)
+    });
+    f.set_cursor(chunks[0].x + 1 + column as u16, chunks[0].y + row as u16);
+
+    let help_text = if editor.renaming {
+        "Enter: save (an empty name removes it) | Esc: cancel"
+    } else {
+        "Enter: save (an empty note removes it) | Alt+Enter/Ctrl+J: new line | Esc: cancel"
+    };
+    let help_paragraph = Paragraph::new(help_text)
+        .style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[1]);
+}
+
+fn render_statistics(f: &mut Frame, app: &mut ViewerApp) {
+    let Some(ref mut statistics) = app.statistics else {
+        return;
+    };
+    let modal_area = centered_rect(80, 80, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Table
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let lines = format_stats_table(&statistics.stats);
+    // Header and totals stay put; the packet types between them scroll
+    let visible_rows = chunks[0].height.saturating_sub(4) as usize;
+    let max_scroll = statistics.stats.len().saturating_sub(visible_rows) as u16;
+    statistics.scroll = statistics.scroll.min(max_scroll);
+    let start = 1 + statistics.scroll as usize;
+    let end = (start + visible_rows).min(lines.len() - 1);
+
+    let mut table = vec![Line::from(Span::styled(lines[0].clone(), Style::default().add_modifier(Modifier::BOLD)))];
+    table.extend(lines[start..end].iter().map(|line| Line::from(line.clone())));
+    table.push(Line::from(Span::styled(
+        lines[lines.len() - 1].clone(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )));
+
+    let filter = match &statistics.filter {
+        Some(filter) => format!(", filter: {}", filter),
+        None => String::new(),
+    };
+    let title = format!(
+        "Packet Statistics - Session #{} ({} packet types{}, by {})",
+        statistics.session_id,
+        statistics.stats.len(),
+        filter,
+        statistics.sort.label()
+    );
+    let table_paragraph = Paragraph::new(table)
+        .block(app.theme.block().title(title));
+    f.render_widget(table_paragraph, chunks[0]);
+
+    let help_paragraph = Paragraph::new("↑↓/j/k: scroll | o: sort by count/bytes/avg size/rate/name/direction | Esc/q/s/S: close")
+        .style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[1]);
+}
+
+fn render_database_health(f: &mut Frame, app: &ViewerApp) {
+    let Some(ref health) = app.db_health else {
+        return;
+    };
+    let modal_area = centered_rect(60, 60, f.size());
+    f.render_widget(ratatui::widgets::Clear, modal_area);
+
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Summary and tables
+            Constraint::Length(1), // Help text
+        ])
+        .split(modal_area);
+
+    let lines = db_health::format_health(health);
+    // The table header follows the blank line after the summary
+    let header = lines.iter().position(|line| line.is_empty()).map(|blank| blank + 1);
+    let text: Vec<Line> = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            if Some(index) == header {
+                Line::from(Span::styled(line.clone(), Style::default().add_modifier(Modifier::BOLD)))
+            } else {
+                Line::from(line.clone())
+            }
+        })
+        .collect();
+    let paragraph = Paragraph::new(text).block(app.theme.block().title("Database"));
+    f.render_widget(paragraph, chunks[0]);
+
+    let help_paragraph = Paragraph::new("r: refresh | Esc/q: close").style(Style::default().fg(app.theme.muted));
+    f.render_widget(help_paragraph, chunks[1]);
+}
+
 fn render_tag_management(f: &mut Frame, app: &mut ViewerApp) {
     if let Some(ref mut tag_mgmt) = app.tag_management {
         // Create modal area (centered, 60% width, 70% height)
@@ -2057,17 +7074,17 @@ fn render_tag_management(f: &mut Frame, app: &mut ViewerApp) {
         
         // Title
         let title = Paragraph::new(format!("Tags for Session #{}", tag_mgmt.session_id))
-            .block(Block::default().borders(Borders::ALL).title("Tag Management"))
+            .block(app.theme.block().title("Tag Management"))
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(title, chunks[0]);
         
         if tag_mgmt.add_tag_mode {
             // Add tag input mode
             let tag_text = format!("Tag: {}", app.tag_input);
-            let input_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+            let input_style = Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD);
             
             let input_paragraph = Paragraph::new(tag_text.as_str())
-                .block(Block::default().borders(Borders::ALL).title("Add Tag"))
+                .block(app.theme.block().title("Add Tag"))
                 .style(input_style);
             f.render_widget(input_paragraph, chunks[1]);
             
@@ -2080,7 +7097,7 @@ fn render_tag_management(f: &mut Frame, app: &mut ViewerApp) {
             let help_text = "Enter tag name and press Enter to add, Esc to cancel";
             let help_paragraph = Paragraph::new(help_text)
                 .block(Block::default())
-                .style(Style::default().fg(Color::DarkGray))
+                .style(Style::default().fg(app.theme.muted))
                 .wrap(Wrap { trim: false });
             f.render_widget(help_paragraph, chunks[2]);
         } else {
@@ -2109,15 +7126,15 @@ fn render_tag_management(f: &mut Frame, app: &mut ViewerApp) {
             }
             
             let list = List::new(items)
-                .block(Block::default().borders(Borders::ALL).title("Tags"))
-                .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+                .block(app.theme.block().title("Tags"))
+                .highlight_style(Style::default().fg(app.theme.highlight).add_modifier(Modifier::BOLD));
             
             f.render_stateful_widget(list, chunks[1], &mut list_state);
             
             let help_text = "↑↓: navigate | a: add tag | d: delete tag | Esc/q: close";
             let help_paragraph = Paragraph::new(help_text)
                 .block(Block::default())
-                .style(Style::default().fg(Color::DarkGray))
+                .style(Style::default().fg(app.theme.muted))
                 .wrap(Wrap { trim: false });
             f.render_widget(help_paragraph, chunks[2]);
         }
@@ -2144,7 +7161,7 @@ fn render_confirmation_dialog(f: &mut Frame, app: &mut ViewerApp) {
             .collect();
         
         let message_paragraph = Paragraph::new(message_lines)
-            .block(Block::default().borders(Borders::ALL).title("Confirm"))
+            .block(app.theme.block().title("Confirm"))
             .wrap(Wrap { trim: false })
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(message_paragraph, chunks[0]);
@@ -2153,7 +7170,7 @@ fn render_confirmation_dialog(f: &mut Frame, app: &mut ViewerApp) {
         let button_text = "[Y]es / [N]o (or Enter/Esc)";
         let button_paragraph = Paragraph::new(button_text)
             .block(Block::default())
-            .style(Style::default().fg(Color::Cyan))
+            .style(Style::default().fg(app.theme.accent))
             .alignment(ratatui::layout::Alignment::Center);
         f.render_widget(button_paragraph, chunks[1]);
     }
@@ -2178,3 +7195,434 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         ])
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range_filters() {
+        let filter = ViewerApp::parse_filter("a.@10s-1.5m,!c.text.#100-,s.#150").unwrap();
+        assert_eq!(filter.filters[0].time_from_ms, Some(10_000));
+        assert_eq!(filter.filters[0].time_to_ms, Some(90_000));
+        assert_eq!(filter.filters[0].packet_name, None);
+        assert_eq!(filter.filters[1].packet_name.as_deref(), Some("text"));
+        assert_eq!((filter.filters[1].packet_number_from, filter.filters[1].packet_number_to), (Some(100), None));
+        assert_eq!((filter.filters[2].packet_number_from, filter.filters[2].packet_number_to), (Some(150), Some(150)));
+        assert_eq!(filter.to_string(), "a.@10s-90s,!c.text.#100-,s.#150");
+
+        // Invalid ranges drop the filter
+        assert!(ViewerApp::parse_filter("a.@20s-10s").is_none());
+        assert!(ViewerApp::parse_filter("a.@10s").is_none());
+        assert!(ViewerApp::parse_filter("a.#-").is_none());
+    }
+
+    #[test]
+    fn test_parse_field_filters() {
+        let filter = ViewerApp::parse_filter("c.move_player.@5s-.$params.runtime_id=42&params.position.y>=64.5").unwrap();
+        let filter = &filter.filters[0];
+        assert_eq!(filter.packet_name.as_deref(), Some("move_player"));
+        assert_eq!(filter.time_from_ms, Some(5_000));
+        assert_eq!(
+            filter.fields,
+            vec![
+                DbFieldCondition { path: vec!["params".into(), "runtime_id".into()], op: DbFieldOp::Eq, value: "42".into() },
+                DbFieldCondition {
+                    path: vec!["params".into(), "position".into(), "y".into()],
+                    op: DbFieldOp::Ge,
+                    value: "64.5".into(),
+                },
+            ]
+        );
+
+        let filter = ViewerApp::parse_filter("a.$params.message~\"*hi*\"").unwrap();
+        assert_eq!(filter.filters[0].fields[0].value, "*hi*");
+        assert_eq!(filter.to_string(), "a.$params.message~*hi*");
+
+        // Ordering needs a number, and a condition needs a path
+        assert!(ViewerApp::parse_filter("a.$params.y>high").is_none());
+        assert!(ViewerApp::parse_filter("a.$=42").is_none());
+    }
+
+    #[test]
+    fn test_with_direction() {
+        let toggle = |filter: &str, direction| {
+            with_direction(ViewerApp::parse_filter(filter).as_ref(), direction).map(|f| f.to_string())
+        };
+        let clientbound = Some(FilterPacketDirection::Clientbound);
+        let serverbound = Some(FilterPacketDirection::Serverbound);
+        assert_eq!(toggle("a", clientbound).as_deref(), Some("c"));
+        assert_eq!(toggle("c", clientbound), None);
+        assert_eq!(toggle("c", serverbound).as_deref(), Some("s"));
+        assert_eq!(toggle("c.text,s.move_player,!a.set_time", serverbound).as_deref(), Some("s.text,s.move_player,!a.set_time"));
+        assert_eq!(toggle("c.text,c.move_player", clientbound).as_deref(), Some("a.text,a.move_player"));
+        assert_eq!(toggle("c.text,s.text", None).as_deref(), Some("a.text"));
+        // Exclusions alone keep excluding, from the packets sent that way
+        assert_eq!(toggle("!s.player_auth_input", clientbound).as_deref(), Some("c,!s.player_auth_input"));
+        assert_eq!(toggle("c,!s.player_auth_input", None).as_deref(), Some("!s.player_auth_input"));
+    }
+
+    #[test]
+    fn test_entity_follow() {
+        let filter = ViewerApp::parse_filter("c.move_entity.#10-.entity:42").unwrap();
+        assert_eq!(filter.filters[0].entity, Some(42));
+        assert_eq!(filter.to_string(), "c.move_entity.#10-.entity:42");
+        assert!(ViewerApp::parse_filter("a.entity:zombie").is_none());
+
+        let follow = |filter: &str, entity| with_entity(ViewerApp::parse_filter(filter).as_ref(), entity).map(|f| f.to_string());
+        assert_eq!(follow("a", Some(7)).as_deref(), Some("a.entity:7"));
+        assert_eq!(follow("c.text,!s.set_time", Some(7)).as_deref(), Some("c.text.entity:7,!s.set_time"));
+        assert_eq!(follow("!s.player_auth_input", Some(7)).as_deref(), Some("a.entity:7,!s.player_auth_input"));
+        assert_eq!(follow("a.entity:7,!s.player_auth_input", None).as_deref(), Some("!s.player_auth_input"));
+
+        let add = serde_json::json!({ "name": "add_entity", "params": { "unique_id": "-12", "runtime_id": "12" } });
+        assert_eq!(packet_entity_id(&add), Some(12));
+        assert_eq!(entity_reference(&add, -12).as_deref(), Some("params.unique_id"));
+        let link = serde_json::json!({ "name": "set_entity_link", "params": { "link": { "ridden_runtime_id": 5 } } });
+        assert_eq!(packet_entity_id(&link), Some(5));
+        assert_eq!(entity_reference(&link, 5).as_deref(), Some("params.link.ridden_runtime_id"));
+        assert_eq!(entity_reference(&link, 6), None);
+        assert_eq!(packet_entity_id(&serde_json::json!({ "name": "text", "params": { "message": "hi" } })), None);
+    }
+
+    #[test]
+    fn test_packet_type_filter() {
+        let quick = |filter: &str, direction, exclude| {
+            packet_type_filter(ViewerApp::parse_filter(filter).as_ref(), direction, "move_player", exclude).to_string()
+        };
+        assert_eq!(quick("a.text,!s.set_time", FilterPacketDirection::Clientbound, false), "c.move_player");
+        assert_eq!(quick("a", FilterPacketDirection::Serverbound, true), "a,!s.move_player");
+        assert_eq!(quick("c.@10s-,!s.set_time", FilterPacketDirection::Clientbound, true), "c.@10s-,!s.set_time,!c.move_player");
+        // Already excluded
+        assert_eq!(quick("!c.move_player", FilterPacketDirection::Clientbound, true), "!c.move_player");
+    }
+
+    #[test]
+    fn test_filter_history() {
+        let mut history = FilterHistory::default();
+        assert_eq!(history.previous("c.te"), None);
+        history.push("c.text");
+        history.push("s.move_player");
+        history.push("c.text");
+        assert_eq!(history.entries, ["s.move_player", "c.text"]);
+
+        assert_eq!(history.previous("a.mo"), Some("c.text"));
+        assert_eq!(history.previous("c.text"), Some("s.move_player"));
+        assert_eq!(history.previous("s.move_player"), Some("s.move_player"));
+        assert_eq!(history.next(), Some("c.text"));
+        assert_eq!(history.next(), Some("a.mo"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn test_parse_params_filters() {
+        let filter = ViewerApp::parse_filter("c.move_player{runtime_id=1234}").unwrap();
+        assert_eq!(filter.filters[0].packet_name.as_deref(), Some("move_player"));
+        assert_eq!(
+            filter.filters[0].fields,
+            [DbFieldCondition { path: vec!["params".into(), "runtime_id".into()], op: DbFieldOp::Eq, value: "1234".into() }]
+        );
+        assert_eq!(filter.to_string(), "c.move_player.$params.runtime_id=1234");
+
+        // Commas inside the braces (or quotes) don't separate filters
+        let filter = ViewerApp::parse_filter("s.*{x>100, position.y<=5},a.text{message~\"*a,b*\"}.#10-,c./^inv/{slot=1}").unwrap();
+        assert_eq!(filter.filters.len(), 3);
+        assert_eq!(filter.filters[0].packet_name, None);
+        assert_eq!(filter.filters[0].fields.len(), 2);
+        assert_eq!(filter.filters[0].fields[1].path, ["params", "position", "y"]);
+        assert_eq!(filter.filters[1].fields[0].value, "*a,b*");
+        assert_eq!(filter.filters[1].packet_number_from, Some(10));
+        assert_eq!(filter.filters[2].packet_name.as_deref(), Some("^inv"));
+        assert_eq!(filter.filters[2].fields[0].path, ["params", "slot"]);
+
+        assert!(ViewerApp::parse_filter("c.move_player{runtime_id}").is_none());
+        assert!(ViewerApp::parse_filter("c.move_player{x>1").is_none());
+    }
+
+    #[test]
+    fn test_parse_regex_filters() {
+        // Periods and commas inside the slashes are part of the regex
+        let filter = ViewerApp::parse_filter("c./^inventory_.*/,!a./^(text|set_title){1,2}$/.#10-,s.move*").unwrap();
+        assert_eq!(filter.filters.len(), 3);
+        assert_eq!(filter.filters[0].packet_name.as_deref(), Some("^inventory_.*"));
+        assert!(filter.filters[0].packet_name_is_regex && !filter.filters[0].packet_name_is_wildcard);
+        assert_eq!(filter.filters[1].packet_name.as_deref(), Some("^(text|set_title){1,2}$"));
+        assert_eq!(filter.filters[1].packet_number_from, Some(10));
+        assert!(filter.filters[2].packet_name_is_wildcard && !filter.filters[2].packet_name_is_regex);
+        assert_eq!(filter.to_string(), "c./^inventory_.*/,!a./^(text|set_title){1,2}$/.#10-,s.move*");
+
+        // Invalid regexes drop their filter, and say why
+        assert!(ViewerApp::parse_filter("a./(/").is_none());
+        assert!(ViewerApp::parse_filter("a./text").is_none());
+        let errors = filter_regex_errors("a./(/,c./ok/,s./open");
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("Invalid regex /(/: "), "{}", errors[0]);
+        assert_eq!(errors[1], "Unclosed regex /open");
+    }
+
+    #[test]
+    fn test_ignored_fields() {
+        let baseline = serde_json::json!({ "name": "move_player", "params": { "tick": 1, "position": { "x": 1, "y": 64 }, "entries": [{ "id": 1, "tick": 5 }] } });
+        let current = serde_json::json!({ "name": "move_player", "params": { "tick": 2, "position": { "x": 2, "y": 64 }, "entries": [{ "id": 1, "tick": 6 }] } });
+        let mut fields = BTreeSet::new();
+        changed_fields(&compare_json(&baseline, &current), "", &mut fields);
+        assert_eq!(fields.iter().collect::<Vec<_>>(), ["params.entries.tick", "params.position.x", "params.tick"]);
+
+        let (mut baseline, mut current) = (baseline, current);
+        for path in &fields {
+            strip_field(&mut baseline, path);
+            strip_field(&mut current, path);
+        }
+        assert_eq!(current, serde_json::json!({ "name": "move_player", "params": { "position": { "y": 64 }, "entries": [{ "id": 1 }] } }));
+        assert_eq!(json_diff_text(&baseline, &current), "No differences");
+    }
+
+    #[test]
+    fn test_json_diff_text() {
+        let baseline = serde_json::json!({ "name": "move_player", "params": { "x": 1, "y": 2 } });
+        let current = serde_json::json!({ "name": "move_player", "params": { "x": 3, "y": 2, "on_ground": true } });
+        let text = json_diff_text(&baseline, &current);
+        assert!(text.contains("- params.x: 1"), "{}", text);
+        assert!(text.contains("+ params.x: 3"), "{}", text);
+        assert!(text.contains("+ params.on_ground: true"), "{}", text);
+        assert!(!text.contains("params.y"), "{}", text);
+        assert_eq!(json_diff_text(&baseline, &baseline), "No differences");
+    }
+
+    #[test]
+    fn test_highlight_json() {
+        let spans = |line: &str| {
+            highlight_json(line, Style::default(), &Theme::default())
+                .into_iter()
+                .map(|span| (span.content.into_owned(), span.style.fg))
+                .collect::<Vec<_>>()
+        };
+        let span = |text: &str, color: Option<Color>| (text.to_string(), color);
+        assert_eq!(
+            spans(r#"  "message": "say \"hi\": 1","#),
+            [span("  ", None), span(r#""message""#, Some(Color::Cyan)), span(": ", None), span(r#""say \"hi\": 1""#, Some(Color::Green)), span(",", None)]
+        );
+        assert_eq!(
+            spans(r#"[-1.5e3, true, null]"#),
+            [
+                span("[", None),
+                span("-1.5e3", Some(Color::Magenta)),
+                span(", ", None),
+                span("true", Some(Color::Yellow)),
+                span(", ", None),
+                span("null", Some(Color::DarkGray)),
+                span("]", None),
+            ]
+        );
+        // Not values
+        assert_eq!(spans("  ... 3 keys"), [span("  ... 3 keys", None)]);
+    }
+
+    #[test]
+    fn test_hex_link_fields() {
+        let fields = vec![
+            ("count".to_string(), 1..2),
+            ("entries".to_string(), 2..8),
+            ("entries[0].key".to_string(), 2..5),
+            ("entries[0].value".to_string(), 5..8),
+        ];
+        assert_eq!(field_at(&fields, 1), Some(0));
+        assert_eq!(field_at(&fields, 6), Some(3));
+        assert_eq!(field_at(&fields, 0), None);
+
+        let mut decoded = indexmap::IndexMap::new();
+        decoded.insert("count".to_string(), serde_json::json!(1));
+        decoded.insert("entries".to_string(), serde_json::json!([{ "key": "a", "value": [1, 2] }]));
+        assert_eq!(field_value(&decoded, "entries[0].key"), Some(&serde_json::json!("a")));
+        assert_eq!(field_value(&decoded, "entries[0].value[1]"), Some(&serde_json::json!(2)));
+        assert_eq!(field_value(&decoded, "entries[1].key"), None);
+
+        // The hex dump's border, then "0000  " and three columns a byte
+        let area = Rect::new(10, 5, 75, 10);
+        assert_eq!(hex_dump_offset(area, 17, 6, 0), Some(0));
+        assert_eq!(hex_dump_offset(area, 21, 7, 2), Some(49));
+        assert_eq!(hex_dump_offset(area, 19, 6, 0), None);
+        assert_eq!(hex_dump_offset(area, 66 + 15, 6, 0), Some(15));
+    }
+
+    #[test]
+    fn test_packet_list_window() {
+        assert_eq!(packet_list_window(50, 100, 10), 45..55);
+        assert_eq!(packet_list_window(2, 100, 10), 0..10);
+        assert_eq!(packet_list_window(98, 100, 10), 90..100);
+        assert_eq!(packet_list_window(3, 5, 10), 0..5);
+        assert_eq!(packet_list_window(0, 0, 10), 0..0);
+    }
+
+    #[test]
+    fn test_parse_goto_target() {
+        assert_eq!(GotoTarget::parse("1234").unwrap(), GotoTarget::Packet(1234));
+        assert_eq!(GotoTarget::parse(" #56 ").unwrap(), GotoTarget::Packet(56));
+        assert_eq!(GotoTarget::parse("@1.5m").unwrap(), GotoTarget::SessionTime(90_000));
+        assert_eq!(GotoTarget::parse("+5s").unwrap(), GotoTarget::Offset(5_000));
+        assert_eq!(GotoTarget::parse("-500ms").unwrap(), GotoTarget::Offset(-500));
+        assert_eq!(GotoTarget::parse("+2").unwrap(), GotoTarget::Offset(2_000));
+        assert!(GotoTarget::parse("soon").is_err());
+        assert!(GotoTarget::parse("@-5s").is_err());
+    }
+
+    #[test]
+    fn test_open_log_file() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", uuid::Uuid::new_v4()));
+        let log_path = {
+            let mut logger = packet_logger::PacketLogger::new(uuid::Uuid::new_v4(), &log_dir).unwrap();
+            for i in 0..3000 {
+                let entry = PacketEntry {
+                    timestamp: 1000 + i * 10,
+                    direction: PacketDirection::Clientbound,
+                    data: vec![(i % 256) as u8; 4],
+                    protocol_version: None,
+                    packet_json: None,
+                    packet_number: None,
+                    original_size: None,
+                };
+                logger.log_entry(&entry).unwrap();
+            }
+            logger.log_path().to_path_buf()
+        };
+
+        // No definitions in the directory, so nothing is decoded
+        let protocols = protocol_registry::ProtocolRegistry::new(&log_dir);
+        let mut log = SessionLog::open_file(&log_path, &protocols).unwrap();
+        assert_eq!(log.len(), 3000);
+        assert_eq!(log.start_time, 1000);
+        assert_eq!(log.get(0).and_then(|p| p.packet_number), Some(1));
+        assert!(log.get(2500).is_none());
+
+        // Seeking reads the page around the packet from the index
+        log.load_file_window(2500).unwrap();
+        let packet = log.get(2500).unwrap();
+        assert_eq!(packet.packet_number, Some(2501));
+        assert_eq!(packet.data, vec![(2500 % 256) as u8; 4]);
+        assert!(log.get(2500 - LOAD_MARGIN).is_some() && log.get(2500 + LOAD_MARGIN).is_some());
+
+        assert_eq!(log.file_position_at(1000 + 1234 * 10), Some(1234));
+        assert_eq!(log.file_position_at(1000 + 1234 * 10 - 5), Some(1234));
+        assert_eq!(log.file_position_at(0), Some(0));
+        assert_eq!(log.file_position_at(1000 + 3000 * 10), None);
+
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_view_without_database() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", uuid::Uuid::new_v4()));
+        let log_path = {
+            let mut logger = packet_logger::PacketLogger::new(uuid::Uuid::new_v4(), &log_dir).unwrap();
+            for i in 0..10 {
+                let entry = PacketEntry {
+                    timestamp: 1000 + i * 10,
+                    direction: PacketDirection::Serverbound,
+                    data: vec![i as u8; 4],
+                    protocol_version: None,
+                    packet_json: None,
+                    packet_number: None,
+                    original_size: None,
+                };
+                logger.log_entry(&entry).unwrap();
+            }
+            logger.log_path().to_path_buf()
+        };
+
+        let mut app = ViewerApp::new(None).await.unwrap();
+        assert!(app.sessions_loaded && !app.is_loading);
+        app.open_log_file(&log_path).await;
+        assert!(matches!(app.mode, ViewerMode::PacketView));
+        assert_eq!(app.error_message, None);
+
+        // Reading the file needs no database
+        app.goto(GotoTarget::Packet(5)).await.unwrap();
+        assert_eq!(app.current_packet().and_then(|p| p.packet_number), Some(5));
+
+        // Actions that query it say so instead
+        app.open_statistics(0, true).await;
+        assert!(app.error_message.as_ref().is_some_and(|e| e.contains("import-log")));
+        assert!(app.statistics.is_none());
+        assert!(app.toggle_bookmark().await.is_err());
+        app.error_message = None;
+        assert!(!app.has_database());
+        assert!(app.error_message.is_some());
+
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_filter_name_completion() {
+        let path = std::env::temp_dir().join(format!("lazypacket-filter-{}.yml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            concat!(
+                "packet_text:\n  \"!id\": 0x09\n  \"!bound\": both\n  message: string\n",
+                "packet_set_title:\n  \"!id\": 0x58\n  \"!bound\": client\n  text: string\n",
+                "packet_player_auth_input:\n  \"!id\": 0x90\n  \"!bound\": server\n  tick: varint64\n",
+            ),
+        )
+        .unwrap();
+        let parser = protocol::ProtocolParser::load_from_file(&path, "test").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let names = |input: &str, packet_types: &[PacketTypeStats]| -> Vec<String> {
+            filter_name_suggestions(input, Some(&parser), packet_types).into_iter().map(|s| s.name).collect()
+        };
+        let complete = |input: &str| complete_filter_name(input, &filter_name_suggestions(input, Some(&parser), &[]));
+        assert_eq!(names("a.text,c.se", &[]), ["set_title"]);
+        assert_eq!(names("s.", &[]), ["text", "player_auth_input"]);
+        assert_eq!(complete("a.text, !c.set").as_deref(), Some("a.text, !c.set_title"));
+        // Already complete, or past the name
+        assert_eq!(complete("a.text"), None);
+        assert_eq!(complete("a.text.#1"), None);
+        assert_eq!(complete("a.te*"), None);
+
+        // The session's own packets, sent the filter's way, join the protocol's
+        let stats = |name: &str, direction: &str, count: i64| PacketTypeStats {
+            name: name.to_string(),
+            direction: direction.to_string(),
+            count,
+            total_bytes: 0,
+            rate: 0.0,
+        };
+        let packet_types = [stats("set_time", "clientbound", 4), stats("set_title", "clientbound", 2), stats("set_health", "serverbound", 1)];
+        assert_eq!(
+            filter_name_suggestions("c.set", Some(&parser), &packet_types),
+            [
+                FilterSuggestion { name: "set_time".to_string(), count: Some(4) },
+                FilterSuggestion { name: "set_title".to_string(), count: Some(2) },
+            ]
+        );
+        assert_eq!(names("a.set_h", &packet_types), ["set_health"]);
+        assert_eq!(
+            filter_name_suggestions("s.tex", None, &[stats("text", "serverbound", 3), stats("text", "clientbound", 5)]),
+            [FilterSuggestion { name: "text".to_string(), count: Some(3) }]
+        );
+
+        let filter = ViewerApp::parse_filter("a.txt,s.set_title,c.set_*,c.text").unwrap();
+        assert_eq!(
+            filter_name_warnings(&filter, &parser),
+            ["Unknown packet 'txt' (did you mean text?)", "set_title is never serverbound"]
+        );
+    }
+
+    #[test]
+    fn test_rate_histogram() {
+        let bucket = |second, clientbound, serverbound| PacketRateBucket { second, clientbound, serverbound };
+        let buckets = [bucket(0, 4, 0), bucket(1, 2, 2), bucket(4, 0, 1)];
+        assert_eq!(rate_histogram(&buckets, 10), (1, vec![(4.0, 0.0), (2.0, 2.0), (0.0, 0.0), (0.0, 0.0), (0.0, 1.0)]));
+        // Two seconds a column to fit five seconds into three columns
+        assert_eq!(rate_histogram(&buckets, 3), (2, vec![(3.0, 1.0), (0.0, 0.0), (0.0, 0.5)]));
+        assert_eq!(rate_histogram(&[], 3), (1, vec![]));
+
+        // 2 rows of 8 eighths: 2 clientbound + 2 serverbound of a peak of 4 fills both rows, half of them green
+        let theme = Theme::default();
+        assert_eq!(histogram_cell(2.0, 2.0, 4.0, 2, 0, &theme), ('█', Style::default().fg(Color::Green)));
+        assert_eq!(histogram_cell(2.0, 2.0, 4.0, 2, 1, &theme), ('█', Style::default().fg(Color::Blue)));
+        assert_eq!(histogram_cell(1.0, 1.0, 4.0, 2, 0, &theme), ('▄', Style::default().fg(Color::Green).bg(Color::Blue)));
+        assert_eq!(histogram_cell(1.0, 0.0, 4.0, 2, 1, &theme).0, ' ');
+        // Any packets show up, however far below the peak
+        assert_eq!(histogram_cell(0.0, 0.01, 4.0, 2, 0, &theme), ('▁', Style::default().fg(Color::Blue)));
+    }
+}
@@ -1,5 +1,24 @@
 // Library module declarations
 pub mod packet_logger;
+pub mod log_reader;
+pub mod log_index;
+pub mod log_meta;
+pub mod log_import;
+pub mod log_decode;
+pub mod session_archive;
+pub mod packet_expect;
+pub mod log_sink;
+pub mod capture_session;
+pub mod db;
+pub mod db_tls;
+pub mod sqlite_db;
+pub mod storage;
+pub mod retention;
 pub mod protocol;
+pub mod protocol_diff;
+pub mod protocol_codegen;
+pub mod protocol_validate;
+pub mod protocol_registry;
+pub mod nbt;
 
 pub use packet_logger::{PacketEntry, PacketDirection};
@@ -0,0 +1,169 @@
+// Packet logs decoded lazily: each entry is read and decoded as the iterator (or stream) is advanced, so
+// captures larger than memory can be processed in one pass
+//   for entry in DecodeIter::open("session.bin")? { let entry = entry?; ... }
+
+use crate::log_reader::{LogReader, ReadStats};
+use crate::packet_logger::PacketEntry;
+use crate::protocol::{DecodedPacket, PROTOCOL_VERSION};
+use crate::protocol_registry::ProtocolRegistry;
+use anyhow::Result;
+use futures::stream::BoxStream;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+// Entries the stream reads ahead of its consumer
+const STREAM_BUFFER: usize = 256;
+
+pub struct DecodedEntry {
+    pub entry: PacketEntry,
+    // None for entries logged with their JSON (already decoded), and for versions without definitions
+    pub decoded: Option<DecodedPacket>,
+}
+
+pub type DecodedStream = BoxStream<'static, Result<DecodedEntry>>;
+
+pub struct DecodeIter<R: BufRead> {
+    reader: LogReader<R>,
+    protocols: Arc<ProtocolRegistry>,
+    default_version: String, // For entries logged without a protocol version
+}
+
+impl DecodeIter<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::from_reader(LogReader::open_at(path, 0)?))
+    }
+}
+
+impl<R: BufRead> DecodeIter<R> {
+    /// Decode a log from any byte source (e.g. stdin), in any format LogReader reads
+    pub fn new(reader: R) -> Result<Self> {
+        Ok(Self::from_reader(LogReader::new(reader)?))
+    }
+
+    pub fn from_reader(reader: LogReader<R>) -> Self {
+        Self {
+            reader,
+            protocols: Arc::new(ProtocolRegistry::default()),
+            default_version: PROTOCOL_VERSION.to_string(),
+        }
+    }
+
+    /// Share parsers with other readers (e.g. when decoding several logs), or use definitions from elsewhere
+    pub fn with_protocols(mut self, protocols: Arc<ProtocolRegistry>) -> Self {
+        self.protocols = protocols;
+        self
+    }
+
+    /// Version to decode entries with when they don't record one (e.g. the session's, from its metadata)
+    pub fn with_default_version(mut self, version: &str) -> Self {
+        self.default_version = version.to_string();
+        self
+    }
+
+    /// Entries read and skipped so far
+    pub fn stats(&self) -> &ReadStats {
+        self.reader.stats()
+    }
+
+    fn decode(&self, entry: PacketEntry) -> DecodedEntry {
+        let decoded = match entry.packet_json {
+            Some(_) => None,
+            None => {
+                let version = entry.protocol_version.as_deref().unwrap_or(&self.default_version);
+                self.protocols.get(version).map(|parser| parser.decode_packet(&entry.data, entry.direction))
+            }
+        };
+        DecodedEntry { entry, decoded }
+    }
+}
+
+impl<R: BufRead + Send + 'static> DecodeIter<R> {
+    /// The entries as an async stream. Reading and decoding run on a blocking thread, up to STREAM_BUFFER
+    /// entries ahead of the consumer; dropping the stream stops them
+    pub fn into_stream(self) -> DecodedStream {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            for entry in self {
+                if tx.blocking_send(entry).is_err() {
+                    break;
+                }
+            }
+        });
+        Box::pin(async_stream::stream! {
+            while let Some(entry) = rx.recv().await {
+                yield entry;
+            }
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for DecodeIter<R> {
+    type Item = Result<DecodedEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.reader.next()?;
+        Some(entry.map(|entry| self.decode(entry)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_logger::{PacketDirection, PacketLogger};
+    use futures::TryStreamExt;
+
+    fn entry(data: Vec<u8>, protocol_version: Option<&str>) -> PacketEntry {
+        PacketEntry {
+            timestamp: 1000,
+            direction: PacketDirection::Clientbound,
+            data,
+            protocol_version: protocol_version.map(str::to_string),
+            packet_json: None,
+            packet_number: None,
+            original_size: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode_log_lazily() {
+        let dir = std::env::temp_dir().join(format!("lazypacket-decode-{}", uuid::Uuid::new_v4()));
+        let protocol_dir = dir.join("protocol");
+        std::fs::create_dir_all(&protocol_dir).unwrap();
+        let text = |id: u8| format!("packet_text:\n  \"!id\": {}\n  \"!bound\": both\n  message: string\n", id);
+        std::fs::write(protocol_dir.join("proto-1.0.yml"), text(0x09)).unwrap();
+        std::fs::write(protocol_dir.join("proto-2.0.yml"), text(0x0a)).unwrap();
+        let protocols = Arc::new(ProtocolRegistry::new(&protocol_dir));
+
+        // Entries without a version are logged with the logger's
+        let mut logger = PacketLogger::with_protocol_version(uuid::Uuid::new_v4(), &dir, "1.0".to_string()).unwrap();
+        logger.log_entry(&entry(vec![0x09, 2, b'h', b'i'], Some("1.0"))).unwrap();
+        logger.log_entry(&entry(vec![0x0a, 2, b'y', b'o'], Some("2.0"))).unwrap();
+        logger.log_entry(&entry(vec![0x09, 1, b'!'], None)).unwrap();
+        logger.log_entry(&entry(vec![0x09, 1, b'?'], Some("3.0"))).unwrap();
+        logger.flush().unwrap();
+
+        let messages = |entries: Vec<DecodedEntry>| -> Vec<Option<serde_json::Value>> {
+            entries.iter().map(|e| e.decoded.as_ref().map(|decoded| decoded.fields["message"].clone())).collect()
+        };
+        let expected = [Some("hi".into()), Some("yo".into()), Some("!".into()), None];
+
+        let mut iter = DecodeIter::open(logger.log_path()).unwrap().with_protocols(protocols.clone());
+        let entries: Vec<_> = iter.by_ref().collect::<Result<_>>().unwrap();
+        assert_eq!(messages(entries), expected);
+        assert_eq!(iter.stats().entries, 4);
+
+        let stream = DecodeIter::open(logger.log_path()).unwrap().with_protocols(protocols.clone()).into_stream();
+        let entries: Vec<_> = stream.try_collect().await.unwrap();
+        assert_eq!(messages(entries), expected);
+
+        // Legacy logs don't record versions
+        let serialized = bincode::serialize(&entry(vec![0x0a, 2, b'o', b'k'], None)).unwrap();
+        let legacy = [&(serialized.len() as u32).to_le_bytes()[..], &serialized].concat();
+        let iter = DecodeIter::new(&legacy[..]).unwrap().with_protocols(protocols).with_default_version("2.0");
+        assert_eq!(messages(iter.collect::<Result<_>>().unwrap()), [Some("ok".into())]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
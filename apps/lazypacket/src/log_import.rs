@@ -0,0 +1,199 @@
+// `import-log`: backfill sessions and packets rows from packet log files (.bin / .jsonl), so captures made
+// with the file sink (or before the database existed) can be browsed in the TUI
+// Packets are decoded with their entry's protocol version, falling back to --protocol-version, the log's
+// metadata, then BEDROCK_VERSION. Imported sessions are tagged `imported` and `log:<file name>`; a log
+// whose tag is already present is skipped unless --force.
+
+use crate::db::{ArchivedSessions, NewDbPacket, SessionSearch};
+use crate::log_meta::{list_sessions, SessionMeta};
+use crate::log_reader::LogReader;
+use crate::packet_logger::PacketLoggerConfig;
+use crate::protocol_registry::ProtocolRegistry;
+use crate::storage::{self, Storage};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+const IMPORT_BATCH_SIZE: usize = 1000;
+pub const IMPORTED_TAG: &str = "imported";
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    pub protocol_version: Option<String>, // For entries that don't record one (legacy logs)
+    pub force: bool, // Import logs that were imported before again
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportOutcome {
+    Imported { session_id: i32, packets: usize, skipped: usize }, // skipped: corrupted entries
+    AlreadyImported { session_id: i32 },
+}
+
+/// Tag marking the session imported from a log, e.g. `log:3f2c....bin`
+pub fn source_tag(log_path: &Path) -> String {
+    let name = log_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    format!("log:{}", name)
+}
+
+/// Import one log as a new session; on failure nothing of the session is left behind
+pub async fn import_log(db: &dyn Storage, log_path: &Path, meta: &SessionMeta, options: &ImportOptions) -> Result<ImportOutcome> {
+    let tag = source_tag(log_path);
+    if !options.force {
+        // Archived sessions count as imported too
+        let search = SessionSearch { tags: vec![tag.clone()], archived: ArchivedSessions::Included, ..Default::default() };
+        if let Some(session) = db.search_sessions(&search).await?.first() {
+            return Ok(ImportOutcome::AlreadyImported { session_id: session.id });
+        }
+    }
+
+    let started_at = DateTime::<Utc>::from_timestamp_millis(meta.started_at)
+        .ok_or_else(|| anyhow!("Invalid start time in metadata of {}", log_path.display()))?;
+    let ended_at = meta.ended_at.or(meta.last_packet_at).and_then(DateTime::<Utc>::from_timestamp_millis);
+    let session_id = db.insert_session(started_at, ended_at).await?;
+
+    match import_packets(db, session_id, log_path, meta, options).await {
+        Ok((packets, skipped)) => {
+            for session_tag in [IMPORTED_TAG.to_string(), tag].iter().chain(&meta.tags) {
+                db.add_session_tag(session_id, session_tag).await?;
+            }
+            Ok(ImportOutcome::Imported { session_id, packets, skipped })
+        }
+        Err(e) => {
+            if let Err(cleanup) = db.delete_session(session_id).await {
+                eprintln!("Failed to remove partially imported session #{}: {:#}", session_id, cleanup);
+            }
+            Err(e)
+        }
+    }
+}
+
+async fn import_packets(
+    db: &dyn Storage,
+    session_id: i32,
+    log_path: &Path,
+    meta: &SessionMeta,
+    options: &ImportOptions,
+) -> Result<(usize, usize)> {
+    let default_version = match options.protocol_version.clone().or_else(|| meta.protocol_version.clone()) {
+        Some(version) => version,
+        None => PacketLoggerConfig::from_env()?.protocol_version,
+    };
+    let protocols = ProtocolRegistry::default();
+
+    let mut reader = LogReader::open_at(log_path, 0)?;
+    let mut batch: Vec<NewDbPacket> = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut packets = 0;
+    for entry in reader.by_ref() {
+        let mut entry = entry.with_context(|| format!("Failed to read {}", log_path.display()))?;
+        packets += 1;
+        // Binary logs don't record packet numbers; number them in capture order like the relay does
+        entry.packet_number.get_or_insert(packets as i64);
+        let version = entry.protocol_version.get_or_insert_with(|| default_version.clone()).clone();
+        let parser = protocols.get(&version);
+
+        batch.push(NewDbPacket::from_entry(&entry, parser.as_deref(), meta.started_at, &default_version));
+        if batch.len() == IMPORT_BATCH_SIZE {
+            db.insert_packets(session_id, &batch).await?;
+            batch.clear();
+        }
+    }
+    db.insert_packets(session_id, &batch).await?;
+
+    Ok((packets, reader.stats().skipped))
+}
+
+/// Logs to import for each argument: a log file (with its sidecar metadata, or scanned), or every log in a directory
+fn collect_logs(paths: &[PathBuf]) -> Result<Vec<(PathBuf, SessionMeta)>> {
+    let mut logs = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            // Oldest first, so session ids follow capture order
+            let mut sessions = list_sessions(path)?;
+            sessions.reverse();
+            logs.extend(sessions.into_iter().map(|session| (session.log_path, session.meta)));
+        } else {
+            let meta = SessionMeta::load(path).or_else(|_| SessionMeta::scan(path))?;
+            logs.push((path.clone(), meta));
+        }
+    }
+    Ok(logs)
+}
+
+/// `import-log [--protocol-version VERSION] [--force] PATH...`
+pub async fn import_logs(args: &[String]) -> Result<()> {
+    let mut options = ImportOptions::default();
+    let mut paths = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--protocol-version" => {
+                options.protocol_version =
+                    Some(iter.next().ok_or_else(|| anyhow!("--protocol-version requires a value"))?.clone());
+            }
+            "--force" => options.force = true,
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(anyhow!("Usage: lazypacket import-log [--protocol-version VERSION] [--force] LOG_FILE_OR_DIR..."));
+    }
+
+    let logs = collect_logs(&paths)?;
+    let db = storage::connect().await?;
+    let mut imported = 0;
+    for (log_path, meta) in &logs {
+        match import_log(db.as_ref(), log_path, meta, &options)
+            .await
+            .with_context(|| format!("Failed to import {}", log_path.display()))?
+        {
+            ImportOutcome::Imported { session_id, packets, skipped } => {
+                imported += 1;
+                let skipped_note = if skipped > 0 { format!(", {} corrupted entries skipped", skipped) } else { String::new() };
+                eprintln!("Imported {} as session #{} ({} packets{})", log_path.display(), session_id, packets, skipped_note);
+            }
+            ImportOutcome::AlreadyImported { session_id } => {
+                eprintln!("Skipped {}: already imported as session #{} (--force imports it again)", log_path.display(), session_id);
+            }
+        }
+    }
+    eprintln!("Imported {} of {} logs", imported, logs.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_logger::{PacketDirection, PacketLogger};
+    use crate::sqlite_db::SqliteDatabase;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_import_log() {
+        let dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+        let log_path = {
+            let mut logger = PacketLogger::new(Uuid::new_v4(), &dir).unwrap();
+            logger.log_packet(PacketDirection::Serverbound, vec![0xc1, 0x01]).unwrap();
+            logger.log_packet(PacketDirection::Clientbound, vec![0x02]).unwrap();
+            logger.log_path().to_path_buf()
+        };
+        let meta = SessionMeta::load(&log_path).unwrap();
+        let db = SqliteDatabase::open(dir.join("import.sqlite")).unwrap();
+        let options = ImportOptions::default();
+
+        let ImportOutcome::Imported { session_id, packets, skipped } = import_log(&db, &log_path, &meta, &options).await.unwrap() else {
+            panic!("log should be imported");
+        };
+        assert_eq!((packets, skipped), (2, 0));
+        let rows = db.get_packets(session_id, None).await.unwrap();
+        assert_eq!(rows.iter().map(|p| p.packet_number).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(rows[1].direction, "clientbound");
+        let tags = db.get_session_tags(session_id).await.unwrap();
+        assert!(tags.contains(&IMPORTED_TAG.to_string()) && tags.contains(&source_tag(&log_path)));
+
+        assert_eq!(
+            import_log(&db, &log_path, &meta, &options).await.unwrap(),
+            ImportOutcome::AlreadyImported { session_id }
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+// Length-index sidecar for packet logs
+// A `<session>.idx` file next to each log holds one fixed-size record per packet:
+//   [u64 offset (LE)][i64 timestamp ms (LE)][u8 direction]
+// so readers can seek to packet N or a time range without deserializing the whole log
+
+use crate::log_reader::LogReader;
+use crate::packet_logger::{PacketDirection, PacketEntry};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const INDEX_RECORD_SIZE: usize = 17;
+
+#[derive(Debug, Clone, Copy)]
+pub struct IndexEntry {
+    pub offset: u64,
+    pub timestamp: i64,
+    pub direction: PacketDirection,
+}
+
+impl IndexEntry {
+    pub fn to_bytes(self) -> [u8; INDEX_RECORD_SIZE] {
+        let mut buf = [0u8; INDEX_RECORD_SIZE];
+        buf[0..8].copy_from_slice(&self.offset.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.timestamp.to_le_bytes());
+        buf[16] = match self.direction {
+            PacketDirection::Clientbound => 0,
+            PacketDirection::Serverbound => 1,
+        };
+        buf
+    }
+
+    fn from_bytes(buf: &[u8]) -> Self {
+        let mut offset = [0u8; 8];
+        let mut timestamp = [0u8; 8];
+        offset.copy_from_slice(&buf[0..8]);
+        timestamp.copy_from_slice(&buf[8..16]);
+        Self {
+            offset: u64::from_le_bytes(offset),
+            timestamp: i64::from_le_bytes(timestamp),
+            direction: if buf[16] == 0 {
+                PacketDirection::Clientbound
+            } else {
+                PacketDirection::Serverbound
+            },
+        }
+    }
+}
+
+/// Sidecar path for a log file: logs/<session>.bin -> logs/<session>.idx
+pub fn index_path(log_path: &Path) -> PathBuf {
+    log_path.with_extension("idx")
+}
+
+pub struct LogIndex {
+    log_path: PathBuf,
+    entries: Vec<IndexEntry>,
+}
+
+impl LogIndex {
+    /// Load the sidecar index for a log, rebuilding it by scanning the log if it is missing or stale
+    pub fn open(log_path: impl AsRef<Path>) -> Result<Self> {
+        let log_path = log_path.as_ref();
+        match Self::load(log_path) {
+            Ok(index) if !index.is_stale()? => Ok(index),
+            _ => {
+                let index = Self::build(log_path)?;
+                // Best effort - a read-only log directory shouldn't prevent reading the log
+                let _ = index.save();
+                Ok(index)
+            }
+        }
+    }
+
+    /// Load an existing `.idx` sidecar
+    pub fn load(log_path: impl AsRef<Path>) -> Result<Self> {
+        let log_path = log_path.as_ref();
+        let idx_path = index_path(log_path);
+        let mut bytes = Vec::new();
+        File::open(&idx_path)
+            .with_context(|| format!("Failed to open index file: {}", idx_path.display()))?
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("Failed to read index file: {}", idx_path.display()))?;
+
+        // Ignore a trailing partial record (e.g. writer killed mid-write)
+        let entries = bytes
+            .chunks_exact(INDEX_RECORD_SIZE)
+            .map(IndexEntry::from_bytes)
+            .collect();
+
+        Ok(Self {
+            log_path: log_path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Build an index by scanning the log file
+    pub fn build(log_path: impl AsRef<Path>) -> Result<Self> {
+        let log_path = log_path.as_ref();
+        let mut reader = LogReader::open_at(log_path, 0)?;
+        let mut entries = Vec::new();
+
+        while let Some(entry) = reader.next() {
+            let entry = entry?;
+            entries.push(IndexEntry {
+                offset: reader.entry_offset(),
+                timestamp: entry.timestamp,
+                direction: entry.direction,
+            });
+        }
+
+        Ok(Self {
+            log_path: log_path.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// Write the index to its `.idx` sidecar
+    pub fn save(&self) -> Result<()> {
+        let idx_path = index_path(&self.log_path);
+        let file = File::create(&idx_path)
+            .with_context(|| format!("Failed to create index file: {}", idx_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        for entry in &self.entries {
+            writer.write_all(&entry.to_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    // An index is stale if the log has entries after the last indexed one, or was truncated below it
+    fn is_stale(&self) -> Result<bool> {
+        let log_len = std::fs::metadata(&self.log_path)
+            .with_context(|| format!("Failed to stat log file: {}", self.log_path.display()))?
+            .len();
+        match self.entries.last() {
+            Some(last) => {
+                if last.offset >= log_len {
+                    return Ok(true);
+                }
+                let mut reader = LogReader::open_at(&self.log_path, last.offset)?;
+                reader.next();
+                if reader.next().is_some() {
+                    return Ok(true);
+                }
+                Ok(false)
+            }
+            // An empty index is only stale if the log holds entries (it may be just a header)
+            None => Ok(log_len > 0 && LogReader::open_at(&self.log_path, 0)?.next().is_some()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Read packet N (0-based) directly from the log
+    pub fn read_packet(&self, n: usize) -> Result<Option<PacketEntry>> {
+        let entry = match self.entries.get(n) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let mut reader = LogReader::open_at(&self.log_path, entry.offset)?;
+        reader.next().transpose()
+    }
+
+    /// Reader positioned at packet N (0-based), for streaming from there to the end
+    pub fn reader_at(&self, n: usize) -> Result<Option<LogReader<std::io::BufReader<File>>>> {
+        match self.entries.get(n) {
+            Some(entry) => LogReader::open_at(&self.log_path, entry.offset).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Index range [start, end) of packets with timestamps in [from_ms, to_ms]
+    /// Timestamps are assumed to be non-decreasing, as written by PacketLogger
+    pub fn time_range(&self, from_ms: i64, to_ms: i64) -> std::ops::Range<usize> {
+        let start = self.entries.partition_point(|e| e.timestamp < from_ms);
+        let end = self.entries.partition_point(|e| e.timestamp <= to_ms);
+        start..end.max(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_logger::PacketLogger;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_logger_writes_index_sidecar() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+
+        let log_path = {
+            let mut logger = PacketLogger::new(Uuid::new_v4(), &log_dir).unwrap();
+            for i in 0..5u8 {
+                logger.log_packet(PacketDirection::Clientbound, vec![i; i as usize + 1]).unwrap();
+            }
+            logger.log_path().to_path_buf()
+        };
+        assert!(index_path(&log_path).exists());
+
+        let index = LogIndex::open(&log_path).unwrap();
+        assert_eq!(index.len(), 5);
+        let packet = index.read_packet(3).unwrap().unwrap();
+        assert_eq!(packet.data, vec![3, 3, 3, 3]);
+
+        // A rebuilt index matches the one written by the logger
+        let rebuilt = LogIndex::build(&log_path).unwrap();
+        let offsets: Vec<_> = rebuilt.entries().iter().map(|e| e.offset).collect();
+        let written: Vec<_> = index.entries().iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, written);
+
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+}
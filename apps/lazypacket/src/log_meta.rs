@@ -0,0 +1,209 @@
+// Session metadata sidecar for packet logs
+// A `<session>.meta.json` file next to each log summarizes the session (addresses, start/end time,
+// protocol version, packet counts, tags), so session lists don't have to scan every log
+// PacketLogger rewrites it on every flush and when the session ends
+
+use crate::log_reader::LogReader;
+use crate::packet_logger::PacketDirection;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionMeta {
+    pub session_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_address: Option<String>,
+    pub started_at: i64,         // Milliseconds since epoch
+    pub ended_at: Option<i64>,   // None while the session is being captured
+    pub protocol_version: Option<String>,
+    pub packet_count: u64,
+    pub clientbound_count: u64,
+    pub serverbound_count: u64,
+    pub first_packet_at: Option<i64>,
+    pub last_packet_at: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Session details known to the capturing side rather than found in packets
+#[derive(Debug, Clone, Default)]
+pub struct SessionDetails {
+    pub client_address: Option<String>,
+    pub server_address: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Sidecar path for a log file: logs/<session>.bin -> logs/<session>.meta.json
+pub fn meta_path(log_path: &Path) -> PathBuf {
+    log_path.with_extension("meta.json")
+}
+
+impl SessionMeta {
+    pub fn new(session_id: Uuid, started_at: i64, protocol_version: Option<String>) -> Self {
+        Self {
+            session_id,
+            started_at,
+            protocol_version,
+            ..Self::default()
+        }
+    }
+
+    pub fn record_packet(&mut self, timestamp: i64, direction: PacketDirection) {
+        self.packet_count += 1;
+        match direction {
+            PacketDirection::Clientbound => self.clientbound_count += 1,
+            PacketDirection::Serverbound => self.serverbound_count += 1,
+        }
+        self.first_packet_at.get_or_insert(timestamp);
+        self.last_packet_at = Some(timestamp);
+    }
+
+    pub fn apply_details(&mut self, details: &SessionDetails) {
+        if details.client_address.is_some() {
+            self.client_address = details.client_address.clone();
+        }
+        if details.server_address.is_some() {
+            self.server_address = details.server_address.clone();
+        }
+        for tag in &details.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+    }
+
+    /// Load the `.meta.json` sidecar of a log
+    pub fn load(log_path: impl AsRef<Path>) -> Result<Self> {
+        let path = meta_path(log_path.as_ref());
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read session metadata: {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid session metadata: {}", path.display()))
+    }
+
+    /// Build metadata by scanning a log (for logs written without a sidecar)
+    /// Addresses and tags aren't stored in logs, so they stay empty
+    pub fn scan(log_path: impl AsRef<Path>) -> Result<Self> {
+        let log_path = log_path.as_ref();
+        let session_id = log_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| Uuid::parse_str(stem).ok())
+            .unwrap_or_default();
+
+        let mut meta = Self::new(session_id, 0, None);
+        for entry in LogReader::open_at(log_path, 0)? {
+            let entry = entry?;
+            if meta.protocol_version.is_none() {
+                meta.protocol_version = entry.protocol_version.clone();
+            }
+            meta.record_packet(entry.timestamp, entry.direction);
+        }
+        meta.started_at = meta.first_packet_at.unwrap_or(0);
+        meta.ended_at = meta.last_packet_at;
+        Ok(meta)
+    }
+
+    /// Write the sidecar, replacing the previous one atomically so readers never see a partial file
+    pub fn save(&self, log_path: impl AsRef<Path>) -> Result<()> {
+        let path = meta_path(log_path.as_ref());
+        let tmp_path = path.with_extension("json.tmp");
+        let json = serde_json::to_vec_pretty(self)?;
+        std::fs::write(&tmp_path, json)
+            .with_context(|| format!("Failed to write session metadata: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write session metadata: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// A log found in a directory, with its metadata
+pub struct SessionLogFile {
+    pub log_path: PathBuf,
+    pub meta: SessionMeta,
+    pub scanned: bool, // No usable sidecar; metadata was rebuilt from the log
+}
+
+/// Metadata for every `.bin` / `.jsonl` log in a directory, newest first
+/// Sidecars are used when present; other logs are scanned
+pub fn list_sessions(log_dir: impl AsRef<Path>) -> Result<Vec<SessionLogFile>> {
+    let log_dir = log_dir.as_ref();
+    let mut sessions = Vec::new();
+    let dir = std::fs::read_dir(log_dir)
+        .with_context(|| format!("Failed to read log directory: {}", log_dir.display()))?;
+
+    for dir_entry in dir {
+        let log_path = dir_entry?.path();
+        let is_log = matches!(log_path.extension().and_then(|e| e.to_str()), Some("bin") | Some("jsonl"));
+        if !is_log {
+            continue;
+        }
+
+        let (meta, scanned) = match SessionMeta::load(&log_path) {
+            Ok(meta) => (meta, false),
+            Err(_) => match SessionMeta::scan(&log_path) {
+                Ok(meta) => (meta, true),
+                Err(e) => {
+                    eprintln!("lazypacket: skipping {}: {:#}", log_path.display(), e);
+                    continue;
+                }
+            },
+        };
+        sessions.push(SessionLogFile { log_path, meta, scanned });
+    }
+
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.meta.started_at));
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_logger::PacketLogger;
+
+    #[test]
+    fn test_meta_sidecar_written_by_logger() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+        let session_id = Uuid::new_v4();
+
+        let log_path = {
+            let mut logger = PacketLogger::new(session_id, &log_dir).unwrap();
+            logger.set_session_details(&SessionDetails {
+                client_address: Some("10.0.0.2:50000".to_string()),
+                server_address: Some("10.0.0.1:19132".to_string()),
+                tags: vec!["repro".to_string()],
+            });
+            logger.log_packet(PacketDirection::Clientbound, vec![1]).unwrap();
+            logger.log_packet(PacketDirection::Serverbound, vec![2]).unwrap();
+            logger.log_packet(PacketDirection::Clientbound, vec![3]).unwrap();
+
+            // Live sessions have a sidecar without an end time
+            logger.flush().unwrap();
+            let live = SessionMeta::load(logger.log_path()).unwrap();
+            assert_eq!(live.packet_count, 3);
+            assert_eq!(live.ended_at, None);
+            logger.log_path().to_path_buf()
+        };
+
+        let meta = SessionMeta::load(&log_path).unwrap();
+        assert_eq!(meta.session_id, session_id);
+        assert_eq!((meta.clientbound_count, meta.serverbound_count), (2, 1));
+        assert_eq!(meta.client_address.as_deref(), Some("10.0.0.2:50000"));
+        assert_eq!(meta.tags, vec!["repro"]);
+        assert!(meta.ended_at.is_some());
+
+        // Without the sidecar, the session is rebuilt from the log
+        std::fs::remove_file(meta_path(&log_path)).unwrap();
+        let sessions = list_sessions(&log_dir).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions[0].scanned);
+        assert_eq!(sessions[0].meta.session_id, session_id);
+        assert_eq!(sessions[0].meta.packet_count, 3);
+
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+}
@@ -0,0 +1,523 @@
+// Log reader module for packet capture streams
+// Reads length-prefixed bincode entries or JSON Lines from any byte source (files, stdin pipes)
+// Binary logs are dispatched on their header version; headerless files are read as legacy logs
+// Version 2+ entries carry a CRC-32, so corrupted entries are skipped and reading resumes at the next valid one
+
+use crate::packet_logger::{
+    crc32, JsonLineEntry, LogFormat, PacketDirection, PacketEntry, LOG_FORMAT_VERSION, LOG_HEADER_SIZE, LOG_MAGIC,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// Upper bound for a single length-prefixed entry (sanity check against garbage input)
+pub const MAX_ENTRY_SIZE: u32 = 16 * 1024 * 1024;
+
+// Checked (version 2+) entry header: [u32 length][u32 CRC-32]
+const CHECKED_HEADER_SIZE: usize = 8;
+// A bincode PacketEntry starts with [i64 timestamp][u32 direction variant]
+const ENTRY_PREFIX_SIZE: usize = 12;
+
+// Entries written before protocol_version was added to PacketEntry
+#[derive(Deserialize)]
+struct LegacyPacketEntry {
+    timestamp: i64,
+    direction: PacketDirection,
+    data: Vec<u8>,
+}
+
+// Entries in headerless logs and format versions 1-2, before original_size was added
+#[derive(Deserialize)]
+struct PacketEntryV2 {
+    timestamp: i64,
+    direction: PacketDirection,
+    data: Vec<u8>,
+    protocol_version: Option<String>,
+}
+
+impl From<PacketEntryV2> for PacketEntry {
+    fn from(entry: PacketEntryV2) -> Self {
+        PacketEntry {
+            timestamp: entry.timestamp,
+            direction: entry.direction,
+            data: entry.data,
+            protocol_version: entry.protocol_version,
+            packet_json: None,
+            packet_number: None,
+            original_size: None,
+        }
+    }
+}
+
+/// Entry counts for a read, including entries skipped because they were corrupted
+#[derive(Debug, Clone, Default)]
+pub struct ReadStats {
+    pub entries: usize,     // Entries read successfully
+    pub skipped: usize,     // Corrupted entries (or runs of garbage) skipped over
+    pub skipped_bytes: u64, // Bytes discarded while resynchronizing
+}
+
+pub struct LogReader<R: BufRead> {
+    reader: R,
+    format: LogFormat,
+    version: Option<u16>, // Binary format version from the header; None for legacy headerless logs
+    line: String,
+    pending: VecDeque<u8>, // Bytes read ahead for checked entries, starting at `offset`
+    in_corruption: bool,   // Currently skipping a corrupted region (counted once in stats)
+    stats: ReadStats,
+    finished: bool,
+    offset: u64,       // Byte position of the next unread entry
+    entry_offset: u64, // Byte position of the most recently returned entry
+}
+
+impl LogReader<BufReader<Box<dyn Read>>> {
+    /// Open a log file, or stdin when the path is "-"
+    pub fn open(path: &str) -> Result<Self> {
+        let source: Box<dyn Read> = if path == "-" {
+            Box::new(io::stdin())
+        } else {
+            let file = File::open(Path::new(path))
+                .with_context(|| format!("Failed to open log file: {}", path))?;
+            Box::new(file)
+        };
+
+        Self::new(BufReader::new(source))
+    }
+}
+
+impl LogReader<BufReader<File>> {
+    /// Open a log file positioned at a byte offset (e.g. from a `.idx` sidecar)
+    pub fn open_at(path: impl AsRef<Path>, offset: u64) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open log file: {}", path.display()))?;
+
+        // Read the header at the start of the file, then jump to the requested entry
+        // (offsets inside the header, e.g. 0, mean the first entry)
+        let mut reader = Self::new(BufReader::new(file))?;
+        let offset = offset.max(reader.offset);
+        reader.reader
+            .seek(SeekFrom::Start(offset))
+            .with_context(|| format!("Failed to seek to offset {} in {}", offset, path.display()))?;
+        reader.offset = offset;
+        reader.entry_offset = offset;
+        Ok(reader)
+    }
+}
+
+impl<R: BufRead> LogReader<R> {
+    /// Create a reader, detecting the stream format from the magic header (or the first byte for legacy logs)
+    pub fn new(mut reader: R) -> Result<Self> {
+        let (format, has_header) = {
+            let buf = reader.fill_buf().context("Failed to read log stream")?;
+            if buf.starts_with(&LOG_MAGIC) {
+                (LogFormat::Binary, true)
+            } else {
+                // Headerless: a binary stream starts with a little-endian length; '{' as the low byte would
+                // imply an entry far larger than MAX_ENTRY_SIZE once the following JSON bytes are included
+                match buf.iter().find(|b| !b.is_ascii_whitespace()) {
+                    Some(b'{') => (LogFormat::JsonLines, false),
+                    _ => (LogFormat::Binary, false),
+                }
+            }
+        };
+
+        let mut offset = 0;
+        let version = if has_header {
+            let mut header = [0u8; LOG_HEADER_SIZE as usize];
+            reader.read_exact(&mut header).context("Truncated log header")?;
+            let version = u16::from_le_bytes([header[4], header[5]]);
+            if version == 0 || version > LOG_FORMAT_VERSION {
+                return Err(anyhow!(
+                    "Unsupported log format version {} (this build reads up to {})",
+                    version,
+                    LOG_FORMAT_VERSION
+                ));
+            }
+            offset = LOG_HEADER_SIZE;
+            Some(version)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            reader,
+            format,
+            version,
+            line: String::new(),
+            pending: VecDeque::new(),
+            in_corruption: false,
+            stats: ReadStats::default(),
+            finished: false,
+            offset,
+            entry_offset: offset,
+        })
+    }
+
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    /// Binary format version from the log header, or None for JSON Lines and legacy headerless logs
+    pub fn format_version(&self) -> Option<u16> {
+        self.version
+    }
+
+    /// Entries read and skipped so far
+    pub fn stats(&self) -> &ReadStats {
+        &self.stats
+    }
+
+    /// Byte offset of the entry most recently returned by `next()`
+    pub fn entry_offset(&self) -> u64 {
+        self.entry_offset
+    }
+
+    /// Read a CRC-checked entry, skipping forward byte by byte past corrupted data until the next
+    /// position with a sane length, a plausible entry prefix and a matching checksum
+    fn read_checked_entry(&mut self) -> Result<Option<PacketEntry>> {
+        loop {
+            if !self.fill(CHECKED_HEADER_SIZE)? {
+                // Partial entry at the end (e.g. writer killed mid-write)
+                if !self.pending.is_empty() {
+                    self.skip(self.pending.len());
+                }
+                return Ok(None);
+            }
+
+            if let Some(entry) = self.try_checked_entry()? {
+                self.in_corruption = false;
+                return Ok(Some(entry));
+            }
+            self.skip(1);
+        }
+    }
+
+    fn try_checked_entry(&mut self) -> Result<Option<PacketEntry>> {
+        let header: Vec<u8> = self.pending.range(..CHECKED_HEADER_SIZE).copied().collect();
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if len < ENTRY_PREFIX_SIZE || len > MAX_ENTRY_SIZE as usize {
+            return Ok(None);
+        }
+
+        // Check the direction variant before reading (and checksumming) a possibly huge bogus entry
+        if !self.fill(CHECKED_HEADER_SIZE + ENTRY_PREFIX_SIZE)? {
+            return Ok(None);
+        }
+        if self.pending[CHECKED_HEADER_SIZE + 8] > 1
+            || self.pending.range(CHECKED_HEADER_SIZE + 9..CHECKED_HEADER_SIZE + 12).any(|&b| b != 0)
+        {
+            return Ok(None);
+        }
+
+        let total = CHECKED_HEADER_SIZE + len;
+        if !self.fill(total)? {
+            return Ok(None);
+        }
+        let bytes = &self.pending.make_contiguous()[CHECKED_HEADER_SIZE..total];
+        if crc32(bytes) != crc {
+            return Ok(None);
+        }
+        let entry = match decode_versioned_entry(bytes, self.version.unwrap_or(LOG_FORMAT_VERSION)) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(None),
+        };
+
+        self.pending.drain(..total);
+        self.entry_offset = self.offset;
+        self.offset += total as u64;
+        Ok(Some(entry))
+    }
+
+    /// Ensure at least `n` bytes are buffered in `pending`; false at end of stream
+    fn fill(&mut self, n: usize) -> Result<bool> {
+        while self.pending.len() < n {
+            let buf = self.reader.fill_buf().context("Failed to read log stream")?;
+            if buf.is_empty() {
+                return Ok(false);
+            }
+            let take = buf.len().min(n - self.pending.len());
+            self.pending.extend(&buf[..take]);
+            self.reader.consume(take);
+        }
+        Ok(true)
+    }
+
+    /// Discard `n` corrupted bytes from the front of `pending`
+    fn skip(&mut self, n: usize) {
+        if !self.in_corruption {
+            self.in_corruption = true;
+            self.stats.skipped += 1;
+        }
+        self.pending.drain(..n);
+        self.offset += n as u64;
+        self.stats.skipped_bytes += n as u64;
+    }
+
+    fn read_binary_entry(&mut self) -> Result<Option<PacketEntry>> {
+        let mut len_buf = [0u8; 4];
+        match self.reader.read_exact(&mut len_buf) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e).context("Failed to read entry length"),
+        }
+
+        let len = u32::from_le_bytes(len_buf);
+        if len == 0 || len > MAX_ENTRY_SIZE {
+            return Err(anyhow!("Invalid entry length: {} bytes", len));
+        }
+
+        let mut entry_buf = vec![0u8; len as usize];
+        self.reader
+            .read_exact(&mut entry_buf)
+            .with_context(|| format!("Truncated entry: expected {} bytes", len))?;
+        self.entry_offset = self.offset;
+        self.offset += 4 + len as u64;
+
+        let entry = match self.version {
+            Some(version) => decode_versioned_entry(&entry_buf, version)?,
+            None => decode_binary_entry(&entry_buf)?,
+        };
+        Ok(Some(entry))
+    }
+
+    fn read_json_line_entry(&mut self) -> Result<Option<PacketEntry>> {
+        loop {
+            self.line.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.line)
+                .context("Failed to read JSON line")?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.entry_offset = self.offset;
+            self.offset += read as u64;
+
+            let trimmed = self.line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            // Lines are self-delimiting, so a corrupted one is skipped rather than ending the read
+            match serde_json::from_str::<JsonLineEntry>(trimmed)
+                .map_err(anyhow::Error::from)
+                .and_then(JsonLineEntry::into_packet_entry)
+            {
+                Ok(entry) => return Ok(Some(entry)),
+                Err(_) => {
+                    self.stats.skipped += 1;
+                    self.stats.skipped_bytes += read as u64;
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = Result<PacketEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let result = match self.format {
+            LogFormat::Binary if self.version.is_some_and(|v| v >= 2) => self.read_checked_entry(),
+            LogFormat::Binary => self.read_binary_entry(),
+            LogFormat::JsonLines => self.read_json_line_entry(),
+        };
+
+        match result {
+            Ok(Some(entry)) => {
+                self.stats.entries += 1;
+                Some(Ok(entry))
+            }
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                // A framing error leaves the stream position unknown, so stop after reporting it
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Deserialize one bincode entry from a log with the given header format version
+fn decode_versioned_entry(bytes: &[u8], version: u16) -> Result<PacketEntry> {
+    let entry = if version >= 3 {
+        bincode::deserialize::<PacketEntry>(bytes)
+    } else {
+        bincode::deserialize::<PacketEntryV2>(bytes).map(PacketEntry::from)
+    };
+    entry.context("Failed to deserialize packet entry")
+}
+
+/// Deserialize one bincode entry from a headerless log, falling back to the legacy layout without protocol_version
+pub fn decode_binary_entry(bytes: &[u8]) -> Result<PacketEntry> {
+    match bincode::deserialize::<PacketEntryV2>(bytes) {
+        Ok(entry) => Ok(entry.into()),
+        Err(_) => {
+            let legacy: LegacyPacketEntry = bincode::deserialize(bytes)
+                .context("Failed to deserialize packet entry")?;
+            Ok(PacketEntry {
+                timestamp: legacy.timestamp,
+                direction: legacy.direction,
+                data: legacy.data,
+                protocol_version: None,
+                packet_json: None,
+                packet_number: None,
+                original_size: None,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary_stream(entries: &[PacketEntry]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for entry in entries {
+            let serialized = bincode::serialize(entry).unwrap();
+            out.extend_from_slice(&(serialized.len() as u32).to_le_bytes());
+            out.extend_from_slice(&serialized);
+        }
+        out
+    }
+
+    #[test]
+    fn test_read_binary_stream() {
+        let entry = PacketEntry {
+            timestamp: 1000,
+            direction: PacketDirection::Serverbound,
+            data: vec![0x01, 0x02, 0x03],
+            protocol_version: Some("1.21.111".to_string()),
+            packet_json: None,
+            packet_number: None,
+            original_size: None,
+        };
+        let stream = binary_stream(&[entry.clone(), entry]);
+
+        let reader = LogReader::new(&stream[..]).unwrap();
+        assert_eq!(reader.format(), LogFormat::Binary);
+        let entries: Vec<_> = reader.collect::<Result<_>>().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].data, vec![0x01, 0x02, 0x03]);
+        assert_eq!(entries[1].protocol_version.as_deref(), Some("1.21.111"));
+    }
+
+    #[test]
+    fn test_read_versioned_binary_stream() {
+        let entry = PacketEntry {
+            timestamp: 1000,
+            direction: PacketDirection::Clientbound,
+            data: vec![0xff],
+            protocol_version: None,
+            packet_json: None,
+            packet_number: None,
+            original_size: None,
+        };
+        // Version 1 entries have no checksum
+        let mut stream = LOG_MAGIC.to_vec();
+        stream.extend_from_slice(&1u16.to_le_bytes());
+        stream.extend(binary_stream(&[entry]));
+
+        let mut reader = LogReader::new(&stream[..]).unwrap();
+        assert_eq!(reader.format(), LogFormat::Binary);
+        assert_eq!(reader.format_version(), Some(1));
+        assert_eq!(reader.next().unwrap().unwrap().data, vec![0xff]);
+        assert_eq!(reader.entry_offset(), LOG_HEADER_SIZE);
+        assert!(reader.next().is_none());
+
+        // Logs from a newer writer are rejected rather than misread
+        let mut future = LOG_MAGIC.to_vec();
+        future.extend_from_slice(&(LOG_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(LogReader::new(&future[..]).is_err());
+    }
+
+    // Current-version stream of `count` entries, with the byte position where each entry starts
+    fn checked_stream(count: u8) -> (Vec<u8>, Vec<usize>) {
+        let mut stream = LOG_MAGIC.to_vec();
+        stream.extend_from_slice(&LOG_FORMAT_VERSION.to_le_bytes());
+        let mut starts = Vec::new();
+        for i in 0..count {
+            let entry = PacketEntry {
+                timestamp: i as i64,
+                direction: PacketDirection::Clientbound,
+                data: vec![i; 8],
+                protocol_version: None,
+                packet_json: None,
+                packet_number: None,
+                original_size: None,
+            };
+            let serialized = bincode::serialize(&entry).unwrap();
+            starts.push(stream.len());
+            stream.extend_from_slice(&(serialized.len() as u32).to_le_bytes());
+            stream.extend_from_slice(&crc32(&serialized).to_le_bytes());
+            stream.extend_from_slice(&serialized);
+        }
+        (stream, starts)
+    }
+
+    #[test]
+    fn test_skip_corrupted_checked_entries() {
+        // A flipped payload byte in entry 1 and a garbled length in entry 3
+        let (mut stream, starts) = checked_stream(5);
+        stream[starts[1] + 30] ^= 0xff;
+        stream[starts[3]] = 0xff;
+
+        let mut reader = LogReader::new(&stream[..]).unwrap();
+        let entries: Vec<_> = reader.by_ref().collect::<Result<_>>().unwrap();
+        let timestamps: Vec<_> = entries.iter().map(|e| e.timestamp).collect();
+        assert_eq!(timestamps, vec![0, 2, 4]);
+        assert_eq!(reader.stats().entries, 3);
+        assert_eq!(reader.stats().skipped, 2);
+        assert_eq!(
+            reader.stats().skipped_bytes,
+            ((starts[2] - starts[1]) + (starts[4] - starts[3])) as u64
+        );
+
+        // A partial entry at the end is skipped too
+        let (mut stream, starts) = checked_stream(2);
+        stream.truncate(stream.len() - 3);
+        let mut reader = LogReader::new(&stream[..]).unwrap();
+        assert_eq!(reader.by_ref().count(), 1);
+        assert_eq!(reader.stats().skipped_bytes, (stream.len() - starts[1]) as u64);
+    }
+
+    #[test]
+    fn test_read_json_lines_stream() {
+        let stream = "{\"timestamp\":5,\"direction\":\"clientbound\",\"data\":\"0a0b\"}\n\n\
+                      {\"timestamp\":6,\"direction\":\"Serverbound\",\"packet\":{\"name\":\"text\"}}\n";
+
+        let reader = LogReader::new(stream.as_bytes()).unwrap();
+        assert_eq!(reader.format(), LogFormat::JsonLines);
+        let entries: Vec<_> = reader.collect::<Result<_>>().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].data, vec![0x0a, 0x0b]);
+        assert!(matches!(entries[1].direction, PacketDirection::Serverbound));
+        assert_eq!(entries[1].packet_json.as_ref().unwrap()["name"], "text");
+    }
+
+    #[test]
+    fn test_skip_invalid_json_lines_data() {
+        // Piped lines with bytes that aren't hex are skipped as corrupted, even when they aren't ASCII
+        let stream = "{\"timestamp\":5,\"direction\":\"clientbound\",\"data\":\"aéb0\"}\n\
+                      {\"timestamp\":6,\"direction\":\"clientbound\",\"data\":\"0a0b\"}\n";
+
+        let mut reader = LogReader::new(stream.as_bytes()).unwrap();
+        let entries: Vec<_> = reader.by_ref().collect::<Result<_>>().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].timestamp, 6);
+        assert_eq!(reader.stats().skipped, 1);
+    }
+}
@@ -0,0 +1,361 @@
+// Destinations for captured packets
+// A CaptureSession (capture_session.rs) fans each packet out to every configured LogSink:
+//   FileSink      - binary / JSON Lines log file (PacketLogger), plus `.idx` and `.meta.json` sidecars
+//   DbSink        - packets table in PostgreSQL or SQLite (DB_BACKEND), written from a background task
+//   WebSocketSink - JSON Lines entries as text frames to a ws:// endpoint
+//   NullSink      - discards packets (benchmarks, dry runs)
+
+use crate::db::NewDbPacket;
+use crate::log_meta::SessionDetails;
+use crate::packet_logger::{encode_hex, JsonLineEntry, PacketEntry, PacketLogger};
+use crate::protocol::ProtocolParser;
+use crate::storage::Storage;
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+const WS_QUEUE_LEN: usize = 4096; // Frames waiting for the WebSocket connection before packets are dropped
+const WS_CLOSE_TIMEOUT: Duration = Duration::from_secs(1); // How long closing waits to send the close frame
+
+pub trait LogSink: Send {
+    /// Short name for error messages (e.g. "file", "db")
+    fn name(&self) -> &str;
+
+    fn write_packet(&mut self, entry: &PacketEntry) -> Result<()>;
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Flush packets that have been buffered longer than the sink's flush interval; called periodically by the
+    /// capture loop. Sinks that don't buffer, or flush on their own, have nothing to do
+    fn flush_if_due(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Session details from the capturing side (addresses, tags); sinks without session metadata ignore them
+    fn set_session_details(&mut self, _details: &SessionDetails) {}
+
+    /// Stop taking packets at the end of a capture. Sinks that write from a background task return it, so the
+    /// caller can wait for the packets still queued before the process exits
+    fn close(&mut self) -> Option<JoinHandle<()>> {
+        None
+    }
+}
+
+pub type FileSink = PacketLogger;
+
+impl LogSink for PacketLogger {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn write_packet(&mut self, entry: &PacketEntry) -> Result<()> {
+        self.log_entry(entry)
+            .with_context(|| format!("Failed to write to {}", self.log_path().display()))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        PacketLogger::flush(self)
+            .with_context(|| format!("Failed to flush {}", self.log_path().display()))
+    }
+
+    fn flush_if_due(&mut self) -> Result<()> {
+        PacketLogger::flush_if_due(self)
+            .map(|_| ())
+            .with_context(|| format!("Failed to flush {}", self.log_path().display()))
+    }
+
+    fn set_session_details(&mut self, details: &SessionDetails) {
+        PacketLogger::set_session_details(self, details)
+    }
+}
+
+pub struct NullSink;
+
+impl LogSink for NullSink {
+    fn name(&self) -> &str {
+        "null"
+    }
+
+    fn write_packet(&mut self, _entry: &PacketEntry) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// How DbSink groups packets into writes (shared with the relay's packet writer)
+/// DB_BATCH_SIZE - packets per write; 1 writes every packet on its own (default 500)
+/// DB_FLUSH_INTERVAL_MS - longest a packet waits for its batch to fill (default 50)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbBatchConfig {
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+}
+
+impl Default for DbBatchConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            flush_interval: Duration::from_millis(50),
+        }
+    }
+}
+
+impl DbBatchConfig {
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+
+        if let Ok(size) = std::env::var("DB_BATCH_SIZE") {
+            config.batch_size = size.parse().context("Invalid DB_BATCH_SIZE")?;
+            if config.batch_size == 0 {
+                return Err(anyhow!("Invalid DB_BATCH_SIZE: must be at least 1"));
+            }
+        }
+        if let Ok(ms) = std::env::var("DB_FLUSH_INTERVAL_MS") {
+            config.flush_interval = Duration::from_millis(ms.parse().context("Invalid DB_FLUSH_INTERVAL_MS")?);
+        }
+
+        Ok(config)
+    }
+}
+
+/// Writes packets to the database without blocking the capture path
+/// Rows are queued to a background task that writes them in batches (Storage::insert_packets); closing or
+/// dropping the sink lets the task drain the queue, end the session and exit
+pub struct DbSink {
+    sender: Option<mpsc::UnboundedSender<NewDbPacket>>, // None once closed
+    writer: Option<JoinHandle<()>>,
+    started_at_ms: i64,
+    server_version: String,
+    protocol_parser: Option<ProtocolParser>,
+}
+
+impl DbSink {
+    /// Must be called within a tokio runtime
+    pub fn spawn(db: Arc<dyn Storage>, session_id: i32, server_version: String, batching: DbBatchConfig) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<NewDbPacket>();
+        let writer = tokio::spawn(async move {
+            let mut batch = Vec::with_capacity(batching.batch_size);
+            // A batch starts with the next packet and is written once full, after the flush interval,
+            // or when the sink is dropped
+            while let Some(packet) = receiver.recv().await {
+                batch.push(packet);
+                let deadline = tokio::time::sleep(batching.flush_interval);
+                tokio::pin!(deadline);
+                while batch.len() < batching.batch_size {
+                    tokio::select! {
+                        packet = receiver.recv() => match packet {
+                            Some(packet) => batch.push(packet),
+                            None => break,
+                        },
+                        _ = &mut deadline => break,
+                    }
+                }
+
+                if let Err(e) = db.insert_packets(session_id, &batch).await {
+                    eprintln!(
+                        "Error writing packets {}-{} to database: {:#}",
+                        batch[0].packet_number,
+                        batch[batch.len() - 1].packet_number,
+                        e
+                    );
+                }
+                batch.clear();
+            }
+            if let Err(e) = db.end_session(session_id).await {
+                eprintln!("Error ending session {}: {:#}", session_id, e);
+            }
+        });
+
+        let protocol_parser = ProtocolParser::new(&server_version).ok();
+        Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            started_at_ms: Utc::now().timestamp_millis(),
+            server_version,
+            protocol_parser,
+        }
+    }
+}
+
+impl LogSink for DbSink {
+    fn name(&self) -> &str {
+        "db"
+    }
+
+    fn write_packet(&mut self, entry: &PacketEntry) -> Result<()> {
+        let row = NewDbPacket::from_entry(entry, self.protocol_parser.as_ref(), self.started_at_ms, &self.server_version);
+        self.sender
+            .as_ref()
+            .ok_or_else(|| anyhow!("Database sink is closed"))?
+            .send(row)
+            .map_err(|_| anyhow!("Database writer task has stopped"))
+    }
+
+    fn close(&mut self) -> Option<JoinHandle<()>> {
+        self.sender = None;
+        self.writer.take()
+    }
+}
+
+/// Sends each packet as a JSON Lines entry in a text frame to a ws:// endpoint
+/// Frames are queued to a background task that owns the connection: it answers pings, and stops when the endpoint
+/// closes the connection, after which writes fail. A full queue (an endpoint not keeping up) drops the packet
+/// with an error rather than blocking the capture
+pub struct WebSocketSink {
+    sender: Option<mpsc::Sender<String>>, // None once closed
+    writer: Option<JoinHandle<()>>,
+}
+
+impl WebSocketSink {
+    /// Must be called within a tokio runtime
+    pub async fn connect(url: &str) -> Result<Self> {
+        if !url.starts_with("ws://") {
+            return Err(anyhow!("Unsupported WebSocket URL (only ws:// is supported): {}", url));
+        }
+        let (stream, _) = tokio_tungstenite::connect_async(url)
+            .await
+            .with_context(|| format!("Failed to connect to WebSocket endpoint {}", url))?;
+        let (mut write, mut read) = stream.split();
+
+        let (sender, mut receiver) = mpsc::channel::<String>(WS_QUEUE_LEN);
+        let url = url.to_string();
+        let writer = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    line = receiver.recv() => match line {
+                        Some(line) => {
+                            if let Err(e) = write.send(Message::Text(line)).await {
+                                eprintln!("Error sending to WebSocket endpoint {}: {}", url, e);
+                                break;
+                            }
+                        }
+                        None => {
+                            // Sink closed or dropped: say goodbye, without waiting long for the endpoint
+                            let _ = tokio::time::timeout(WS_CLOSE_TIMEOUT, write.send(Message::Close(None))).await;
+                            break;
+                        }
+                    },
+                    frame = read.next() => match frame {
+                        // Reading a ping or close queues the reply; flushing sends it
+                        Some(Ok(Message::Close(_))) | None => {
+                            let _ = write.flush().await;
+                            eprintln!("WebSocket endpoint {} closed the connection", url);
+                            break;
+                        }
+                        Some(Ok(Message::Ping(_))) => {
+                            if let Err(e) = write.flush().await {
+                                eprintln!("Error answering WebSocket ping from {}: {}", url, e);
+                                break;
+                            }
+                        }
+                        Some(Ok(_)) => {} // Nothing is expected back from the endpoint
+                        Some(Err(e)) => {
+                            eprintln!("WebSocket connection to {} failed: {}", url, e);
+                            break;
+                        }
+                    },
+                }
+            }
+        });
+
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+}
+
+impl LogSink for WebSocketSink {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    fn write_packet(&mut self, entry: &PacketEntry) -> Result<()> {
+        let line = JsonLineEntry {
+            timestamp: entry.timestamp,
+            direction: entry.direction,
+            packet_number: entry.packet_number,
+            protocol_version: entry.protocol_version.clone(),
+            size: Some(entry.size()),
+            data: Some(encode_hex(&entry.data)),
+            packet: entry.packet_json.clone(),
+        };
+        let sender = self.sender.as_ref().ok_or_else(|| anyhow!("WebSocket sink is closed"))?;
+        match sender.try_send(serde_json::to_string(&line)?) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => Err(anyhow!("WebSocket endpoint isn't keeping up; packet dropped")),
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(anyhow!("WebSocket connection has closed")),
+        }
+    }
+
+    fn close(&mut self) -> Option<JoinHandle<()>> {
+        self.sender = None;
+        self.writer.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_logger::PacketDirection;
+    use tokio::net::TcpListener;
+
+    fn entry(packet_number: i64) -> PacketEntry {
+        PacketEntry {
+            timestamp: 42,
+            direction: PacketDirection::Serverbound,
+            data: vec![0xab],
+            protocol_version: None,
+            packet_json: None,
+            packet_number: Some(packet_number),
+            original_size: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websocket_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            socket.send(Message::Ping(b"alive?".to_vec())).await.unwrap();
+
+            // The pong and the packet, in whichever order they come
+            let (mut pong, mut text) = (None, None);
+            while pong.is_none() || text.is_none() {
+                match socket.next().await.unwrap().unwrap() {
+                    Message::Pong(payload) => pong = Some(payload),
+                    Message::Text(line) => text = Some(line),
+                    other => panic!("unexpected frame {:?}", other),
+                }
+            }
+            socket.close(None).await.unwrap();
+            (pong.unwrap(), text.unwrap())
+        });
+
+        let mut sink = WebSocketSink::connect(&format!("ws://127.0.0.1:{}/packets", port)).await.unwrap();
+        sink.write_packet(&entry(7)).unwrap();
+        let (pong, text) = server.await.unwrap();
+        assert_eq!(pong, b"alive?");
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["packet_number"], 7);
+        assert_eq!(json["data"], "ab");
+
+        // The endpoint closed the connection: the writer stops, and later packets are errors rather than lost silently
+        let stopped = tokio::time::timeout(Duration::from_secs(5), async {
+            while sink.write_packet(&entry(8)).is_ok() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        assert!(stopped.await.is_ok());
+        sink.close().unwrap().await.unwrap();
+        assert!(WebSocketSink::connect("wss://127.0.0.1:1/").await.is_err());
+    }
+}
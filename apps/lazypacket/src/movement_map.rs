@@ -0,0 +1,142 @@
+// The player's path through a session, for the movement map (`M` in the packet view): positions from the
+// client's own move_player and player_auth_input packets, and the server's move_player packets for the player's
+// runtime id (teleports and corrections, with the id taken from start_game). The map is drawn top-down, x across
+// and z down (north up), with one braille dot as the unit on both axes
+
+use crate::db::{DbFieldCondition, DbFieldOp, DbPacketFilter, DbPacketFilterSet};
+use crate::storage::{PageCursor, Storage};
+use anyhow::Result;
+use futures::TryStreamExt;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathPoint {
+    pub packet_number: i64, // First packet that put the player here
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Every position the player moved to, in packet order (standing still adds nothing)
+pub async fn load_player_path(db: &dyn Storage, session_id: i32) -> Result<Vec<PathPoint>> {
+    let packets = |direction: &str, name: &str| DbPacketFilter {
+        direction: Some(direction.to_string()),
+        packet_name: Some(name.to_string()),
+        ..Default::default()
+    };
+    let start_game = DbPacketFilterSet { filters: vec![packets("clientbound", "start_game")] };
+    let runtime_id = db
+        .get_packet_page(session_id, Some(&start_game), PageCursor::After(None), 1)
+        .await?
+        .first()
+        .and_then(|packet| match &packet.packet["params"]["runtime_entity_id"] {
+            Value::String(id) => Some(id.clone()),
+            Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        });
+
+    let mut filters = vec![packets("serverbound", "move_player"), packets("serverbound", "player_auth_input")];
+    if let Some(runtime_id) = runtime_id {
+        filters.push(DbPacketFilter {
+            fields: vec![DbFieldCondition {
+                path: vec!["params".to_string(), "runtime_id".to_string()],
+                op: DbFieldOp::Eq,
+                value: runtime_id,
+            }],
+            ..packets("clientbound", "move_player")
+        });
+    }
+    let filter_set = DbPacketFilterSet { filters };
+
+    let mut path = Vec::new();
+    let mut stream = db.stream_packets(session_id, Some(&filter_set));
+    while let Some(packet) = stream.try_next().await? {
+        if let Some((x, y, z)) = position(&packet.packet) {
+            push_point(&mut path, PathPoint { packet_number: packet.packet_number, x, y, z });
+        }
+    }
+    Ok(path)
+}
+
+/// params.position of a decoded packet
+fn position(packet: &Value) -> Option<(f64, f64, f64)> {
+    let position = &packet["params"]["position"];
+    Some((position["x"].as_f64()?, position["y"].as_f64()?, position["z"].as_f64()?))
+}
+
+fn push_point(path: &mut Vec<PathPoint>, point: PathPoint) {
+    let standing_still = path.last().is_some_and(|last| (last.x, last.y, last.z) == (point.x, point.y, point.z));
+    if !standing_still {
+        path.push(point);
+    }
+}
+
+/// Index of where the player was at a packet: the last point at or before it
+pub fn point_at(path: &[PathPoint], packet_number: i64) -> Option<usize> {
+    path.partition_point(|point| point.packet_number <= packet_number).checked_sub(1)
+}
+
+/// Canvas bounds ([x], [y]) showing the whole path on a `width` by `height` character area, with equal scales on
+/// both axes (braille dots are 2 across and 4 down a character, which is about twice as tall as it's wide).
+/// The y bounds are -z, since the canvas's y grows upwards
+pub fn map_bounds(path: &[PathPoint], width: u16, height: u16) -> ([f64; 2], [f64; 2]) {
+    let (mut min_x, mut max_x, mut min_z, mut max_z) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for point in path {
+        min_x = min_x.min(point.x);
+        max_x = max_x.max(point.x);
+        min_z = min_z.min(point.z);
+        max_z = max_z.max(point.z);
+    }
+    if path.is_empty() {
+        (min_x, max_x, min_z, max_z) = (0.0, 0.0, 0.0, 0.0);
+    }
+    let (dots_x, dots_y) = (f64::from(width.max(1)) * 2.0, f64::from(height.max(1)) * 4.0);
+    // Blocks per dot, with a block of margin around the path
+    let scale = ((max_x - min_x + 2.0) / dots_x).max((max_z - min_z + 2.0) / dots_y);
+    let (center_x, center_z) = ((min_x + max_x) / 2.0, (min_z + max_z) / 2.0);
+    let (half_x, half_y) = (scale * dots_x / 2.0, scale * dots_y / 2.0);
+    ([center_x - half_x, center_x + half_x], [-center_z - half_y, -center_z + half_y])
+}
+
+/// The next position after a packet, or the last one before it
+pub fn adjacent_point(path: &[PathPoint], packet_number: i64, forward: bool) -> Option<&PathPoint> {
+    if forward {
+        path.get(path.partition_point(|point| point.packet_number <= packet_number))
+    } else {
+        path[..path.partition_point(|point| point.packet_number < packet_number)].last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_player_path() {
+        let mut path = Vec::new();
+        for (number, packet) in [
+            (1, json!({ "name": "player_auth_input", "params": { "position": { "x": 0.5, "y": 64.0, "z": 0.5 } } })),
+            (2, json!({ "name": "player_auth_input", "params": { "position": { "x": 0.5, "y": 64.0, "z": 0.5 } } })),
+            (5, json!({ "name": "move_player", "params": { "position": { "x": 10.5, "y": 65.0, "z": -3.0 } } })),
+            (6, json!({ "name": "text", "params": { "message": "hi" } })),
+        ] {
+            if let Some((x, y, z)) = position(&packet) {
+                push_point(&mut path, PathPoint { packet_number: number, x, y, z });
+            }
+        }
+        assert_eq!(path.iter().map(|p| p.packet_number).collect::<Vec<_>>(), [1, 5]);
+        assert_eq!(point_at(&path, 0), None);
+        assert_eq!(point_at(&path, 4), Some(0));
+        assert_eq!(point_at(&path, 9), Some(1));
+        let adjacent = |number, forward| adjacent_point(&path, number, forward).map(|p| p.packet_number);
+        assert_eq!((adjacent(1, true), adjacent(1, false)), (Some(5), None));
+        assert_eq!((adjacent(3, true), adjacent(3, false)), (Some(5), Some(1)));
+        assert_eq!((adjacent(5, true), adjacent(5, false)), (None, Some(1)));
+
+        // 10 blocks across and 3.5 down (plus margins) on 24x40 dots: the width sets the scale, half a block a dot
+        let (x, y) = map_bounds(&path, 12, 10);
+        assert_eq!(x, [-0.5, 11.5]);
+        assert_eq!(y, [-8.75, 11.25]);
+    }
+}
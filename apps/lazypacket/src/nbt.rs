@@ -0,0 +1,409 @@
+// Bedrock NBT decoding and encoding for the protocol parser (nbt, lnbt and nbtLoop fields)
+// Values come out in prismarine-nbt's typed JSON shape, the same one the relay stores for these fields:
+//   { "type": "compound", "name": "", "value": { "Health": { "type": "short", "value": 20 }, ... } }
+// Longs are decimal strings, like the parser's other 64-bit values.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value as JsonValue};
+use std::io::{Cursor, Read};
+
+// Compounds and lists nested deeper than this are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NbtFormat {
+    Network,      // nbt: varint string and array lengths, zigzag varint ints and longs
+    LittleEndian, // lnbt: fixed-width little-endian numbers and lengths (level.dat, disk format)
+}
+
+const TAG_END: u8 = 0;
+
+fn tag_id(name: &str) -> Result<u8> {
+    (0..=12).find(|&tag| tag_name(tag).ok() == Some(name)).ok_or_else(|| anyhow!("Invalid NBT tag type {}", name))
+}
+
+fn tag_name(tag: u8) -> Result<&'static str> {
+    Ok(match tag {
+        0 => "end",
+        1 => "byte",
+        2 => "short",
+        3 => "int",
+        4 => "long",
+        5 => "float",
+        6 => "double",
+        7 => "byteArray",
+        8 => "string",
+        9 => "list",
+        10 => "compound",
+        11 => "intArray",
+        12 => "longArray",
+        _ => return Err(anyhow!("Invalid NBT tag type {}", tag)),
+    })
+}
+
+struct NbtReader<'a, 'b> {
+    cursor: &'a mut Cursor<&'b [u8]>,
+    format: NbtFormat,
+}
+
+impl NbtReader<'_, '_> {
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.cursor.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn remaining(&self) -> usize {
+        self.cursor.get_ref().len().saturating_sub(self.cursor.position() as usize)
+    }
+
+    fn read_varint(&mut self, max_bytes: usize) -> Result<u64> {
+        let mut result = 0u64;
+        for i in 0..max_bytes {
+            let [byte] = self.read_bytes::<1>()?;
+            result |= ((byte & 0x7f) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(anyhow!("NBT varint overflow"))
+    }
+
+    fn read_int(&mut self) -> Result<i32> {
+        match self.format {
+            NbtFormat::Network => {
+                let value = self.read_varint(5)? as u32;
+                Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+            }
+            NbtFormat::LittleEndian => Ok(i32::from_le_bytes(self.read_bytes()?)),
+        }
+    }
+
+    fn read_long(&mut self) -> Result<i64> {
+        match self.format {
+            NbtFormat::Network => {
+                let value = self.read_varint(10)?;
+                Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+            }
+            NbtFormat::LittleEndian => Ok(i64::from_le_bytes(self.read_bytes()?)),
+        }
+    }
+
+    /// Array and list lengths; `min_size` is the smallest encoding of one element, to reject lengths the data can't hold
+    fn read_length(&mut self, min_size: usize) -> Result<usize> {
+        let length = self.read_int()?;
+        if length < 0 {
+            return Err(anyhow!("Negative NBT length {}", length));
+        }
+        let length = length as usize;
+        if length.saturating_mul(min_size) > self.remaining() {
+            return Err(anyhow!("NBT length {} exceeds the remaining {} bytes", length, self.remaining()));
+        }
+        Ok(length)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let length = match self.format {
+            NbtFormat::Network => self.read_varint(5)? as usize,
+            NbtFormat::LittleEndian => u16::from_le_bytes(self.read_bytes()?) as usize,
+        };
+        if length > self.remaining() {
+            return Err(anyhow!("NBT string length {} exceeds the remaining {} bytes", length, self.remaining()));
+        }
+        let mut buf = vec![0u8; length];
+        self.cursor.read_exact(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    fn read_tag_type(&mut self) -> Result<u8> {
+        let [tag] = self.read_bytes::<1>()?;
+        tag_name(tag)?;
+        Ok(tag)
+    }
+
+    fn float(value: f64) -> JsonValue {
+        serde_json::Number::from_f64(value).map(JsonValue::Number).unwrap_or(JsonValue::Null)
+    }
+
+    /// The payload of a tag, without its type and name
+    fn read_payload(&mut self, tag: u8, depth: usize) -> Result<JsonValue> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("NBT nested deeper than {} levels", MAX_DEPTH));
+        }
+        Ok(match tag {
+            1 => json!(i8::from_le_bytes(self.read_bytes()?)),
+            2 => json!(i16::from_le_bytes(self.read_bytes()?)),
+            3 => json!(self.read_int()?),
+            4 => json!(self.read_long()?.to_string()),
+            5 => Self::float(f32::from_le_bytes(self.read_bytes()?) as f64),
+            6 => Self::float(f64::from_le_bytes(self.read_bytes()?)),
+            7 => {
+                let length = self.read_length(1)?;
+                let mut buf = vec![0u8; length];
+                self.cursor.read_exact(&mut buf)?;
+                JsonValue::Array(buf.into_iter().map(|b| json!(b as i8)).collect())
+            }
+            8 => JsonValue::String(self.read_string()?),
+            9 => {
+                let element = self.read_tag_type()?;
+                let length = self.read_length(if element == TAG_END { 0 } else { 1 })?;
+                let values = (0..length)
+                    .map(|_| self.read_payload(element, depth + 1))
+                    .collect::<Result<Vec<_>>>()?;
+                json!({ "type": tag_name(element)?, "value": values })
+            }
+            10 => {
+                let mut entries = Map::new();
+                loop {
+                    let child = self.read_tag_type()?;
+                    if child == TAG_END {
+                        break;
+                    }
+                    let name = self.read_string()?;
+                    let value = self.read_payload(child, depth + 1)?;
+                    entries.insert(name, json!({ "type": tag_name(child)?, "value": value }));
+                }
+                JsonValue::Object(entries)
+            }
+            11 => {
+                let length = self.read_length(1)?;
+                JsonValue::Array((0..length).map(|_| Ok(json!(self.read_int()?))).collect::<Result<_>>()?)
+            }
+            12 => {
+                let length = self.read_length(1)?;
+                JsonValue::Array((0..length).map(|_| Ok(json!(self.read_long()?.to_string()))).collect::<Result<_>>()?)
+            }
+            _ => return Err(anyhow!("Unexpected NBT tag type {}", tag)),
+        })
+    }
+
+    /// A named root tag; a lone end tag (empty NBT) reads as {"type": "end", "name": "", "value": null}
+    fn read_root(&mut self) -> Result<JsonValue> {
+        let tag = self.read_tag_type()?;
+        if tag == TAG_END {
+            return Ok(json!({ "type": "end", "name": "", "value": null }));
+        }
+        let name = self.read_string()?;
+        let value = self.read_payload(tag, 0)?;
+        Ok(json!({ "type": tag_name(tag)?, "name": name, "value": value }))
+    }
+}
+
+struct NbtWriter<'a> {
+    out: &'a mut Vec<u8>,
+    format: NbtFormat,
+}
+
+impl NbtWriter<'_> {
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn write_int(&mut self, value: i32) {
+        match self.format {
+            NbtFormat::Network => self.write_varint(((value << 1) ^ (value >> 31)) as u32 as u64),
+            NbtFormat::LittleEndian => self.out.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    fn write_long(&mut self, value: i64) {
+        match self.format {
+            NbtFormat::Network => self.write_varint(((value << 1) ^ (value >> 63)) as u64),
+            NbtFormat::LittleEndian => self.out.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    fn write_length(&mut self, length: usize) -> Result<()> {
+        self.write_int(i32::try_from(length).map_err(|_| anyhow!("NBT length {} too large", length))?);
+        Ok(())
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<()> {
+        match self.format {
+            NbtFormat::Network => self.write_varint(value.len() as u64),
+            NbtFormat::LittleEndian => {
+                let length = u16::try_from(value.len()).map_err(|_| anyhow!("NBT string of {} bytes too long", value.len()))?;
+                self.out.extend_from_slice(&length.to_le_bytes());
+            }
+        }
+        self.out.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn integer<T: TryFrom<i64>>(value: &JsonValue, tag: u8) -> Result<T> {
+        // Longs are decimal strings
+        let number = match value {
+            JsonValue::String(s) => s.parse().ok(),
+            _ => value.as_i64(),
+        };
+        number
+            .and_then(|n| T::try_from(n).ok())
+            .ok_or_else(|| anyhow!("Invalid NBT {} value {}", tag_name(tag).unwrap_or("?"), value))
+    }
+
+    fn float(value: &JsonValue) -> Result<f64> {
+        value.as_f64().ok_or_else(|| anyhow!("Invalid NBT float value {}", value))
+    }
+
+    fn array(value: &JsonValue) -> Result<&Vec<JsonValue>> {
+        value.as_array().ok_or_else(|| anyhow!("Expected an NBT array, got {}", value))
+    }
+
+    /// The payload of a tag, in the shape read_payload returns it
+    fn write_payload(&mut self, tag: u8, value: &JsonValue) -> Result<()> {
+        match tag {
+            1 => self.out.push(Self::integer::<i8>(value, tag)? as u8),
+            2 => self.out.extend_from_slice(&Self::integer::<i16>(value, tag)?.to_le_bytes()),
+            3 => self.write_int(Self::integer(value, tag)?),
+            4 => self.write_long(Self::integer(value, tag)?),
+            5 => self.out.extend_from_slice(&(Self::float(value)? as f32).to_le_bytes()),
+            6 => self.out.extend_from_slice(&Self::float(value)?.to_le_bytes()),
+            7 => {
+                let values = Self::array(value)?;
+                self.write_length(values.len())?;
+                for value in values {
+                    self.out.push(Self::integer::<i8>(value, 1)? as u8);
+                }
+            }
+            8 => self.write_string(value.as_str().ok_or_else(|| anyhow!("Invalid NBT string value {}", value))?)?,
+            9 => {
+                let element = tag_id(value["type"].as_str().unwrap_or("end"))?;
+                let values = Self::array(&value["value"])?;
+                self.out.push(element);
+                self.write_length(values.len())?;
+                for value in values {
+                    self.write_payload(element, value)?;
+                }
+            }
+            10 => {
+                let entries = value.as_object().ok_or_else(|| anyhow!("Invalid NBT compound value {}", value))?;
+                for (name, entry) in entries {
+                    let child = tag_id(entry["type"].as_str().ok_or_else(|| anyhow!("NBT tag {} has no type", name))?)?;
+                    self.out.push(child);
+                    self.write_string(name)?;
+                    self.write_payload(child, &entry["value"])?;
+                }
+                self.out.push(TAG_END);
+            }
+            11 => {
+                let values = Self::array(value)?;
+                self.write_length(values.len())?;
+                for value in values {
+                    self.write_int(Self::integer(value, 3)?);
+                }
+            }
+            12 => {
+                let values = Self::array(value)?;
+                self.write_length(values.len())?;
+                for value in values {
+                    self.write_long(Self::integer(value, 4)?);
+                }
+            }
+            _ => return Err(anyhow!("Unexpected NBT tag type {}", tag)),
+        }
+        Ok(())
+    }
+
+    fn write_root(&mut self, value: &JsonValue) -> Result<()> {
+        let tag = tag_id(value["type"].as_str().ok_or_else(|| anyhow!("NBT root has no type"))?)?;
+        self.out.push(tag);
+        if tag != TAG_END {
+            self.write_string(value["name"].as_str().unwrap_or(""))?;
+            self.write_payload(tag, &value["value"])?;
+        }
+        Ok(())
+    }
+}
+
+/// Write one root tag, as read_nbt returns it
+pub fn write_nbt(out: &mut Vec<u8>, value: &JsonValue, format: NbtFormat) -> Result<()> {
+    NbtWriter { out, format }.write_root(value)
+}
+
+/// Write network NBT roots and the terminating end tag, as read_nbt_loop returns them
+pub fn write_nbt_loop(out: &mut Vec<u8>, value: &JsonValue) -> Result<()> {
+    let mut writer = NbtWriter { out, format: NbtFormat::Network };
+    for root in NbtWriter::array(value)? {
+        writer.write_root(root)?;
+    }
+    writer.out.push(TAG_END);
+    Ok(())
+}
+
+/// Read one root tag
+pub fn read_nbt(cursor: &mut Cursor<&[u8]>, format: NbtFormat) -> Result<JsonValue> {
+    NbtReader { cursor, format }.read_root()
+}
+
+/// Read network NBT root tags until a terminating end tag (or the end of the data), as bedrock-protocol's nbtLoop
+pub fn read_nbt_loop(cursor: &mut Cursor<&[u8]>) -> Result<JsonValue> {
+    let mut reader = NbtReader { cursor, format: NbtFormat::Network };
+    let mut roots = Vec::new();
+    while reader.remaining() > 0 {
+        let position = reader.cursor.position();
+        if reader.read_bytes::<1>()? == [TAG_END] {
+            break;
+        }
+        reader.cursor.set_position(position);
+        roots.push(reader.read_root()?);
+    }
+    Ok(JsonValue::Array(roots))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_network_and_little_endian_nbt() {
+        // { "": { Health: short 20, Pos: list<int> [1, -2], Name: "Steve", Age: long -3 } } in network NBT
+        let network = [
+            10, 0, // compound, empty name
+            2, 6, b'H', b'e', b'a', b'l', b't', b'h', 20, 0, // short Health = 20
+            9, 3, b'P', b'o', b's', 3, 4, 2, 3, // list Pos of 2 ints (zigzag): 1, -2
+            8, 4, b'N', b'a', b'm', b'e', 5, b'S', b't', b'e', b'v', b'e', // string Name
+            4, 3, b'A', b'g', b'e', 5, // long Age = -3 (zigzag)
+            0, // end
+        ];
+        let value = read_nbt(&mut Cursor::new(&network[..]), NbtFormat::Network).unwrap();
+        assert_eq!(
+            value,
+            json!({ "type": "compound", "name": "", "value": {
+                "Health": { "type": "short", "value": 20 },
+                "Pos": { "type": "list", "value": { "type": "int", "value": [1, -2] } },
+                "Name": { "type": "string", "value": "Steve" },
+                "Age": { "type": "long", "value": "-3" },
+            }})
+        );
+
+        // { "": { Count: int 7 } } in little-endian NBT
+        let little_endian = [10, 0, 0, 3, 5, 0, b'C', b'o', b'u', b'n', b't', 7, 0, 0, 0, 0];
+        let value = read_nbt(&mut Cursor::new(&little_endian[..]), NbtFormat::LittleEndian).unwrap();
+        assert_eq!(value["value"]["Count"], json!({ "type": "int", "value": 7 }));
+
+        // Truncated data and impossible lengths are errors rather than huge allocations
+        assert!(read_nbt(&mut Cursor::new(&network[..network.len() - 1]), NbtFormat::Network).is_err());
+        assert!(read_nbt(&mut Cursor::new(&[7u8, 0, 0xfe, 0xff, 0xff, 0xff, 0x0f][..]), NbtFormat::Network).is_err());
+
+        let looped = [network.as_slice(), network.as_slice(), &[0]].concat();
+        let value = read_nbt_loop(&mut Cursor::new(&looped[..])).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 2);
+
+        // Writing what was read gives the same bytes (compound entries come back in name order)
+        let value = read_nbt(&mut Cursor::new(&little_endian[..]), NbtFormat::LittleEndian).unwrap();
+        let mut out = Vec::new();
+        write_nbt(&mut out, &value, NbtFormat::LittleEndian).unwrap();
+        assert_eq!(out, little_endian);
+        let value = read_nbt(&mut Cursor::new(&network[..]), NbtFormat::Network).unwrap();
+        let mut out = Vec::new();
+        write_nbt(&mut out, &value, NbtFormat::Network).unwrap();
+        assert_eq!(read_nbt(&mut Cursor::new(&out[..]), NbtFormat::Network).unwrap(), value);
+        let mut out = Vec::new();
+        write_nbt_loop(&mut out, &read_nbt_loop(&mut Cursor::new(&looped[..])).unwrap()).unwrap();
+        assert_eq!(read_nbt_loop(&mut Cursor::new(&out[..])).unwrap().as_array().unwrap().len(), 2);
+    }
+}
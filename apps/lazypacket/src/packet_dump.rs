@@ -0,0 +1,280 @@
+// Headless CLI commands for packet logs (dump, grep, sessions)
+// dump and grep read a log file or stdin, so they compose in pipelines:
+//   cat session.bin | lazypacket grep 'text'
+
+use crate::log_index::LogIndex;
+use crate::log_meta::list_sessions;
+use crate::log_reader::{LogReader, ReadStats};
+use crate::packet_logger::{PacketDirection, PacketEntry};
+use crate::protocol;
+use crate::protocol_registry::ProtocolRegistry;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::io::{self, BufRead, BufWriter, ErrorKind, Write};
+
+pub(crate) struct DumpContext {
+    protocols: ProtocolRegistry,
+}
+
+impl DumpContext {
+    pub(crate) fn new() -> Self {
+        // Protocol parsers are optional - without one raw entries just show as "unknown"
+        Self {
+            protocols: ProtocolRegistry::default(),
+        }
+    }
+
+    /// Packet JSON for an entry: stored JSON if present, otherwise decoded from raw bytes
+    pub(crate) fn packet_json(&self, entry: &PacketEntry) -> Option<serde_json::Value> {
+        if let Some(ref json) = entry.packet_json {
+            return Some(json.clone());
+        }
+
+        // Decoded with the version the entry was logged with (entries from before versions were logged get the default)
+        let version = entry.protocol_version.as_deref().unwrap_or(protocol::PROTOCOL_VERSION);
+        let parser = self.protocols.get(version)?;
+        parser.decode_packet(&entry.data, entry.direction).to_packet_json()
+    }
+
+    fn summary_line(&self, index: usize, entry: &PacketEntry, json: Option<&serde_json::Value>) -> String {
+        let time_str = DateTime::<Utc>::from_timestamp_millis(entry.timestamp)
+            .unwrap_or_default()
+            .format("%H:%M:%S%.3f")
+            .to_string();
+        let direction_str = match entry.direction {
+            PacketDirection::Clientbound => "clientbound",
+            PacketDirection::Serverbound => "serverbound",
+        };
+        let name = json
+            .and_then(|j| j.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or("unknown");
+        let number = entry.packet_number.unwrap_or(index as i64 + 1);
+
+        let truncated = if entry.is_truncated() {
+            format!(" ({} stored)", entry.data.len())
+        } else {
+            String::new()
+        };
+
+        format!(
+            "{:>8}  {}  {}  {:<40} {} bytes{}",
+            number,
+            time_str,
+            direction_str,
+            name,
+            entry.size(),
+            truncated
+        )
+    }
+}
+
+// Packet selection for `dump`: --from N, --count M, --time START-END (seconds from first packet)
+#[derive(Default)]
+struct DumpOptions {
+    path: Option<String>,
+    from: Option<usize>,
+    count: Option<usize>,
+    time: Option<(f64, f64)>,
+}
+
+impl DumpOptions {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut options = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |flag: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{} requires a value", flag))
+            };
+            match arg.as_str() {
+                "--from" => options.from = Some(value("--from")?.parse().context("Invalid --from")?),
+                "--count" => options.count = Some(value("--count")?.parse().context("Invalid --count")?),
+                "--time" => {
+                    let range = value("--time")?;
+                    let (start, end) = range
+                        .split_once('-')
+                        .ok_or_else(|| anyhow!("Invalid --time range: {} (expected START-END)", range))?;
+                    let start = if start.is_empty() { 0.0 } else { start.parse().context("Invalid --time start")? };
+                    let end = if end.is_empty() { f64::MAX } else { end.parse().context("Invalid --time end")? };
+                    options.time = Some((start, end));
+                }
+                _ if options.path.is_none() => options.path = Some(arg.clone()),
+                _ => return Err(anyhow!("Unexpected argument: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    fn is_seek(&self) -> bool {
+        self.from.is_some() || self.time.is_some()
+    }
+}
+
+/// `dump [--from N] [--count M] [--time START-END] [FILE|-]` - print one summary line per packet
+/// Seeking options use the log's `.idx` sidecar (rebuilt if missing), so they need a file path
+pub fn dump(args: &[String]) -> Result<()> {
+    let options = DumpOptions::parse(args)?;
+    let path = options.path.as_deref().unwrap_or("-");
+    let context = DumpContext::new();
+
+    if !options.is_seek() {
+        let limit = options.count.unwrap_or(usize::MAX);
+        return dump_entries(&context, LogReader::open(path)?, 0, limit);
+    }
+
+    if path == "-" {
+        return Err(anyhow!("--from and --time need a log file path, not stdin"));
+    }
+    let index = LogIndex::open(path)?;
+    let (mut start, mut end) = (0, index.len());
+    if let Some((from_s, to_s)) = options.time {
+        let first = index.entries().first().map(|e| e.timestamp).unwrap_or(0);
+        let range = index.time_range(
+            first + (from_s * 1000.0) as i64,
+            first.saturating_add((to_s * 1000.0).min(i64::MAX as f64) as i64),
+        );
+        start = range.start;
+        end = range.end;
+    }
+    if let Some(from) = options.from {
+        start = start.max(from);
+    }
+    let limit = end
+        .saturating_sub(start)
+        .min(options.count.unwrap_or(usize::MAX));
+
+    match index.reader_at(start)? {
+        Some(reader) => dump_entries(&context, reader, start, limit),
+        None => Ok(()),
+    }
+}
+
+fn dump_entries<R: BufRead>(context: &DumpContext, mut reader: LogReader<R>, start: usize, limit: usize) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    for (index, entry) in reader.by_ref().take(limit).enumerate() {
+        let entry = entry?;
+        let json = context.packet_json(&entry);
+        let line = context.summary_line(start + index, &entry, json.as_ref());
+        if !write_line(&mut out, &line)? {
+            return Ok(());
+        }
+    }
+
+    report_skipped(reader.stats());
+    finish(&mut out)
+}
+
+/// `grep PATTERN [FILE|-]` - print packets whose name or JSON contains PATTERN (case-insensitive)
+pub fn grep(args: &[String]) -> Result<()> {
+    let pattern = args
+        .first()
+        .ok_or_else(|| anyhow!("Usage: lazypacket grep PATTERN [FILE|-]"))?
+        .to_lowercase();
+    let path = args.get(1).map(|s| s.as_str()).unwrap_or("-");
+    let context = DumpContext::new();
+    let mut reader = LogReader::open(path)?;
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    for (index, entry) in reader.by_ref().enumerate() {
+        let entry = entry?;
+        let json = context.packet_json(&entry);
+        let haystack = json
+            .as_ref()
+            .map(|j| j.to_string().to_lowercase())
+            .unwrap_or_default();
+        if !haystack.contains(&pattern) {
+            continue;
+        }
+
+        let line = context.summary_line(index, &entry, json.as_ref());
+        if !write_line(&mut out, &line)? {
+            return Ok(());
+        }
+    }
+
+    report_skipped(reader.stats());
+    finish(&mut out)
+}
+
+/// `sessions [DIR]` - one line per log in DIR (default PACKET_LOG_DIR, then "logs"), newest first
+/// Reads `.meta.json` sidecars; logs without one are scanned and marked "(scanned)"
+pub fn sessions(args: &[String]) -> Result<()> {
+    let log_dir = match args.first() {
+        Some(dir) => dir.clone(),
+        None => std::env::var("PACKET_LOG_DIR").unwrap_or_else(|_| "logs".to_string()),
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    for session in list_sessions(&log_dir)? {
+        let meta = &session.meta;
+        let started = DateTime::<Utc>::from_timestamp_millis(meta.started_at)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M:%S");
+        let duration = match meta.ended_at {
+            Some(ended_at) => format!("{}s", (ended_at - meta.started_at) / 1000),
+            None => "active".to_string(),
+        };
+        let mut line = format!(
+            "{}  {}  {:>8}  {:>8} packets ({} c / {} s)  {}",
+            meta.session_id,
+            started,
+            duration,
+            meta.packet_count,
+            meta.clientbound_count,
+            meta.serverbound_count,
+            meta.protocol_version.as_deref().unwrap_or("unknown"),
+        );
+        if meta.client_address.is_some() || meta.server_address.is_some() {
+            line.push_str(&format!(
+                "  {} -> {}",
+                meta.client_address.as_deref().unwrap_or("?"),
+                meta.server_address.as_deref().unwrap_or("?")
+            ));
+        }
+        if !meta.tags.is_empty() {
+            line.push_str(&format!("  [{}]", meta.tags.join(", ")));
+        }
+        if session.scanned {
+            line.push_str("  (scanned)");
+        }
+        if !write_line(&mut out, &line)? {
+            return Ok(());
+        }
+    }
+
+    finish(&mut out)
+}
+
+/// Note corrupted entries on stderr, so stdout stays clean for pipelines
+fn report_skipped(stats: &ReadStats) {
+    if stats.skipped > 0 {
+        eprintln!(
+            "lazypacket: recovered {} entries, skipped {} corrupted entries ({} bytes)",
+            stats.entries, stats.skipped, stats.skipped_bytes
+        );
+    }
+}
+
+/// Write a line, returning false if the downstream end of the pipe was closed (e.g. `| head`)
+fn write_line(out: &mut impl Write, line: &str) -> Result<bool> {
+    match writeln!(out, "{}", line) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn finish(out: &mut impl Write) -> Result<()> {
+    match out.flush() {
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,160 @@
+// Packet sequence expectations for regression tests
+// Tests generated by `lazypacket export-test` list the packets (and selected fields) a capture must
+// contain, in order; `assert_log_contains` checks a capture of a replay against a server build
+
+use crate::log_reader::LogReader;
+use crate::packet_logger::{PacketDirection, PacketEntry};
+use crate::protocol::{self, ProtocolParser};
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value as JsonValue;
+
+#[derive(Debug, Clone)]
+pub struct ExpectedPacket {
+    pub direction: PacketDirection,
+    pub name: String,
+    pub fields: Vec<(String, JsonValue)>, // (dotted path into the packet JSON, expected value)
+}
+
+impl ExpectedPacket {
+    pub fn new(direction: PacketDirection, name: &str) -> Self {
+        Self {
+            direction,
+            name: name.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Expect the value at `path` (e.g. "params.message") to equal `json`
+    /// Panics on invalid JSON, since it is only written by the test generator
+    pub fn field(mut self, path: &str, json: &str) -> Self {
+        let value = serde_json::from_str(json)
+            .unwrap_or_else(|e| panic!("Invalid expected JSON for {}: {}", path, e));
+        self.fields.push((path.to_string(), value));
+        self
+    }
+
+    fn matches(&self, direction: PacketDirection, packet: &JsonValue) -> bool {
+        direction == self.direction
+            && packet.get("name").and_then(|n| n.as_str()) == Some(self.name.as_str())
+            && self
+                .fields
+                .iter()
+                .all(|(path, expected)| json_path(packet, path) == Some(expected))
+    }
+
+    fn describe(&self) -> String {
+        let direction = match self.direction {
+            PacketDirection::Clientbound => "clientbound",
+            PacketDirection::Serverbound => "serverbound",
+        };
+        format!("{} {}", direction, self.name)
+    }
+}
+
+/// Look up a dotted path ("params.items.0.name") in a JSON value; numeric segments index arrays
+pub fn json_path<'a>(value: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.').try_fold(value, |current, segment| match current {
+        JsonValue::Object(map) => map.get(segment),
+        JsonValue::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+/// Check that `entries` contain the expected packets in order (other packets may appear in between)
+pub fn check_sequence(
+    entries: impl Iterator<Item = Result<PacketEntry>>,
+    parser: Option<&ProtocolParser>,
+    expected: &[ExpectedPacket],
+) -> Result<()> {
+    let mut next = 0;
+    // Closest miss for the pending expectation: same direction and name, different fields
+    let mut near_miss: Option<(usize, JsonValue)> = None;
+
+    for (index, entry) in entries.enumerate() {
+        let Some(want) = expected.get(next) else {
+            break;
+        };
+        let entry = entry?;
+        let packet = match entry.packet_json {
+            Some(json) => json,
+            None => match parser.and_then(|p| p.decode_packet(&entry.data, entry.direction).to_packet_json()) {
+                Some(json) => json,
+                None => continue,
+            },
+        };
+
+        if want.matches(entry.direction, &packet) {
+            next += 1;
+            near_miss = None;
+        } else if near_miss.is_none()
+            && entry.direction == want.direction
+            && packet.get("name").and_then(|n| n.as_str()) == Some(want.name.as_str())
+        {
+            near_miss = Some((index + 1, packet));
+        }
+    }
+
+    match expected.get(next) {
+        None => Ok(()),
+        Some(want) => {
+            let mut message = format!(
+                "Expected packet {} of {} ({}) not found",
+                next + 1,
+                expected.len(),
+                want.describe()
+            );
+            if let Some((number, packet)) = near_miss {
+                for (path, value) in &want.fields {
+                    let actual = json_path(&packet, path).cloned().unwrap_or(JsonValue::Null);
+                    if &actual != value {
+                        message.push_str(&format!(
+                            "\n  packet {} has {} = {} (expected {})",
+                            number, path, actual, value
+                        ));
+                    }
+                }
+            }
+            Err(anyhow!(message))
+        }
+    }
+}
+
+/// Check that the capture at `log_path` contains the expected packets in order
+pub fn assert_log_contains(log_path: &str, expected: &[ExpectedPacket]) -> Result<()> {
+    let reader = LogReader::open(log_path)
+        .with_context(|| format!("Failed to open replay capture: {}", log_path))?;
+    // Raw binary entries need the protocol parser; JSON Lines entries may already be decoded
+    let parser = ProtocolParser::new(protocol::PROTOCOL_VERSION).ok();
+    check_sequence(reader, parser.as_ref(), expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_sequence_in_order() {
+        let log = "\
+{\"timestamp\":1,\"direction\":\"clientbound\",\"packet\":{\"name\":\"start_game\",\"params\":{\"gamemode\":\"survival\"}}}
+{\"timestamp\":2,\"direction\":\"serverbound\",\"packet\":{\"name\":\"text\",\"params\":{\"message\":\"hi\"}}}
+{\"timestamp\":3,\"direction\":\"clientbound\",\"packet\":{\"name\":\"text\",\"params\":{\"message\":\"hi\"}}}
+";
+        let entries = || LogReader::new(log.as_bytes()).unwrap();
+
+        let expected = [
+            ExpectedPacket::new(PacketDirection::Clientbound, "start_game")
+                .field("params.gamemode", "\"survival\""),
+            ExpectedPacket::new(PacketDirection::Clientbound, "text"),
+        ];
+        check_sequence(entries(), None, &expected).unwrap();
+
+        // Out of order
+        let reversed = [expected[1].clone(), expected[0].clone()];
+        assert!(check_sequence(entries(), None, &reversed).is_err());
+
+        // Field mismatch is reported against the closest packet
+        let wrong = [ExpectedPacket::new(PacketDirection::Serverbound, "text").field("params.message", "\"bye\"")];
+        let err = check_sequence(entries(), None, &wrong).unwrap_err().to_string();
+        assert!(err.contains("params.message = \"hi\""), "{}", err);
+    }
+}
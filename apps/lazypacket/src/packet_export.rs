@@ -0,0 +1,176 @@
+// Packet exports from the packet view (`e` / `E`): the current packet, or every packet passing the filter, as
+// JSON files to share or diff outside the TUI. Paths ending in .ndjson or .jsonl get one compact packet per line;
+// anything else gets pretty JSON (an array when there are several packets). Each packet is written as
+//   {"packet_number": 12, "ts": "...", "session_time_ms": 340, "direction": "clientbound", "packet": {...}}
+
+use crate::db::{DbPacket, DbPacketFilterSet};
+use crate::storage::{PageCursor, Storage};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::ser::{SerializeSeq, Serializer as _};
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Pretty,
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// By the path's extension
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("ndjson") || ext.eq_ignore_ascii_case("jsonl") => ExportFormat::Ndjson,
+            _ => ExportFormat::Pretty,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExportedPacket<'a> {
+    packet_number: i64,
+    ts: DateTime<Utc>,
+    session_time_ms: i64,
+    direction: &'a str,
+    packet: &'a Value,
+}
+
+impl<'a> From<&'a DbPacket> for ExportedPacket<'a> {
+    fn from(packet: &'a DbPacket) -> Self {
+        ExportedPacket {
+            packet_number: packet.packet_number,
+            ts: packet.ts,
+            session_time_ms: packet.session_time_ms,
+            direction: &packet.direction,
+            packet: &packet.packet,
+        }
+    }
+}
+
+pub fn default_packet_path(session_id: i32, packet_number: i64) -> PathBuf {
+    PathBuf::from(format!("session-{}-packet-{}.json", session_id, packet_number))
+}
+
+pub fn default_packets_path(session_id: i32) -> PathBuf {
+    PathBuf::from(format!("session-{}-packets.ndjson", session_id))
+}
+
+/// Write one packet of a session
+pub async fn export_packet(db: &dyn Storage, session_id: i32, packet_number: i64, path: &Path) -> Result<()> {
+    let packet = db
+        .get_packet_page(session_id, None, PageCursor::After(Some(packet_number - 1)), 1)
+        .await?
+        .into_iter()
+        .next()
+        .filter(|packet| packet.packet_number == packet_number)
+        .ok_or_else(|| anyhow!("Session #{} has no packet {}", session_id, packet_number))?;
+
+    let mut writer = create(path)?;
+    match ExportFormat::from_path(path) {
+        ExportFormat::Pretty => serde_json::to_writer_pretty(&mut writer, &ExportedPacket::from(&packet))?,
+        ExportFormat::Ndjson => serde_json::to_writer(&mut writer, &ExportedPacket::from(&packet))?,
+    }
+    writeln!(writer)?;
+    writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Write the packets of a session that pass a filter, in packet order; returns how many were written
+pub async fn export_packets(
+    db: &dyn Storage,
+    session_id: i32,
+    filter_set: Option<&DbPacketFilterSet>,
+    path: &Path,
+) -> Result<usize> {
+    let mut writer = create(path)?;
+    let mut packets = db.stream_packets(session_id, filter_set);
+    let mut count = 0;
+    match ExportFormat::from_path(path) {
+        ExportFormat::Pretty => {
+            // Streamed as one array, so long sessions never have to fit in memory
+            let mut serializer = serde_json::Serializer::with_formatter(&mut writer, PrettyFormatter::new());
+            let mut array = serializer.serialize_seq(None)?;
+            while let Some(packet) = packets.try_next().await? {
+                array.serialize_element(&ExportedPacket::from(&packet))?;
+                count += 1;
+            }
+            array.end()?;
+            writeln!(writer)?;
+        }
+        ExportFormat::Ndjson => {
+            while let Some(packet) = packets.try_next().await? {
+                serde_json::to_writer(&mut writer, &ExportedPacket::from(&packet))?;
+                writeln!(writer)?;
+                count += 1;
+            }
+        }
+    }
+    writer.flush().with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(count)
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DbPacketFilter, NewDbPacket};
+    use crate::sqlite_db::SqliteDatabase;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_export_packets() {
+        let dir = std::env::temp_dir().join(format!("lazypacket-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = SqliteDatabase::open(dir.join("packets.sqlite")).unwrap();
+        let started_at = DateTime::<Utc>::from_timestamp_millis(1_700_000_000_000).unwrap();
+        let session_id = db.insert_session(started_at, None).await.unwrap();
+        let packets: Vec<NewDbPacket> = (1..=3)
+            .map(|n| NewDbPacket {
+                ts: started_at + chrono::Duration::milliseconds(n * 10),
+                session_time_ms: n * 10,
+                packet_number: n,
+                server_version: "1.21.111".to_string(),
+                direction: if n == 2 { "serverbound" } else { "clientbound" }.to_string(),
+                packet: json!({ "name": "text", "params": { "message": format!("hi {}", n) } }),
+            })
+            .collect();
+        db.insert_packets(session_id, &packets).await.unwrap();
+
+        let path = dir.join("packet.json");
+        export_packet(&db, session_id, 2, &path).await.unwrap();
+        let exported: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(exported["packet_number"], 2);
+        assert_eq!(exported["direction"], "serverbound");
+        assert_eq!(exported["packet"]["params"]["message"], "hi 2");
+        assert!(export_packet(&db, session_id, 7, &path).await.is_err());
+
+        let clientbound = DbPacketFilterSet {
+            filters: vec![DbPacketFilter { direction: Some("clientbound".to_string()), ..Default::default() }],
+        };
+        let path = dir.join("packets.ndjson");
+        assert_eq!(export_packets(&db, session_id, Some(&clientbound), &path).await.unwrap(), 2);
+        let lines: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.iter().map(|p| p["packet_number"].as_i64().unwrap()).collect::<Vec<_>>(), [1, 3]);
+
+        let path = dir.join("packets.json");
+        assert_eq!(export_packets(&db, session_id, None, &path).await.unwrap(), 3);
+        let array: Vec<Value> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(array.len(), 3);
+        assert_eq!(array[2]["session_time_ms"], 30);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+// Request / response correlation for the gaps panel (`G` in the packet view) and the exchanges panel (`R`): which
+// packet answered which request, for known pairs, and how long the other side took. Pairs whose packets carry an
+// id (item stack request ids, command origin uuids, resource pack chunks) are matched on it; for the rest each
+// response answers the oldest unanswered request of its pair, which holds since neither side reorders them.
+// Gaps between consecutive packets come from Storage::get_packet_gaps
+
+use crate::db::{DbPacket, DbPacketFilter, DbPacketFilterSet};
+use crate::storage::Storage;
+use anyhow::Result;
+use futures::TryStreamExt;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+#[derive(Debug)]
+pub struct RoundTripPair {
+    pub request_direction: &'static str, // The response goes the other way
+    pub request: &'static str,
+    pub response: &'static str,
+    pub ids: Option<fn(&Value) -> Vec<String>>, // Ids matching a response to its request, from either one's params
+}
+
+pub const ROUND_TRIP_PAIRS: &[RoundTripPair] = &[
+    RoundTripPair { request_direction: "serverbound", request: "request_network_settings", response: "network_settings", ids: None },
+    RoundTripPair { request_direction: "clientbound", request: "resource_packs_info", response: "resource_pack_client_response", ids: None },
+    RoundTripPair { request_direction: "clientbound", request: "resource_pack_data_info", response: "resource_pack_chunk_request", ids: Some(pack_ids) },
+    RoundTripPair {
+        request_direction: "serverbound",
+        request: "resource_pack_chunk_request",
+        response: "resource_pack_chunk_data",
+        ids: Some(pack_chunk_ids),
+    },
+    RoundTripPair { request_direction: "clientbound", request: "resource_pack_stack", response: "resource_pack_client_response", ids: None },
+    RoundTripPair { request_direction: "serverbound", request: "request_chunk_radius", response: "chunk_radius_update", ids: None },
+    RoundTripPair { request_direction: "clientbound", request: "network_stack_latency", response: "network_stack_latency", ids: None },
+    RoundTripPair { request_direction: "serverbound", request: "tick_sync", response: "tick_sync", ids: None },
+    RoundTripPair { request_direction: "serverbound", request: "command_request", response: "command_output", ids: Some(command_ids) },
+    RoundTripPair { request_direction: "serverbound", request: "item_stack_request", response: "item_stack_response", ids: Some(item_stack_ids) },
+];
+
+impl RoundTripPair {
+    fn response_direction(&self) -> &'static str {
+        if self.request_direction == "clientbound" { "serverbound" } else { "clientbound" }
+    }
+}
+
+fn id_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+/// The pack a resource_pack_data_info describes, and the one a resource_pack_chunk_request is for
+fn pack_ids(params: &Value) -> Vec<String> {
+    id_text(&params["pack_id"]).into_iter().collect()
+}
+
+/// The chunk of a pack that's requested, or sent
+fn pack_chunk_ids(params: &Value) -> Vec<String> {
+    match (id_text(&params["pack_id"]), id_text(&params["chunk_index"])) {
+        (Some(pack), Some(chunk)) => vec![format!("{}#{}", pack, chunk)],
+        _ => Vec::new(),
+    }
+}
+
+/// The command's origin, which the output repeats
+fn command_ids(params: &Value) -> Vec<String> {
+    id_text(&params["origin"]["uuid"]).into_iter().collect()
+}
+
+/// Request ids of an item_stack_request's requests, or of an item_stack_response's responses (both are batched)
+fn item_stack_ids(params: &Value) -> Vec<String> {
+    let entries = params.get("requests").or_else(|| params.get("responses"));
+    entries
+        .and_then(Value::as_array)
+        .map(|entries| entries.iter().filter_map(|entry| id_text(&entry["request_id"])).collect())
+        .unwrap_or_default()
+}
+
+// A request and the packet that answered it
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub pair: &'static RoundTripPair,
+    pub request: i64, // Packet numbers
+    pub response: i64,
+    pub round_trip_ms: i64,
+}
+
+// Round trip times of one pair over a session
+#[derive(Debug, Clone)]
+pub struct RoundTripStats {
+    pub pair: &'static RoundTripPair,
+    pub count: usize, // Answered requests
+    pub min_ms: i64,
+    pub median_ms: i64,
+    pub max_ms: i64,
+    pub slowest: i64, // Packet number of the request answered slowest
+    pub unanswered: usize,
+}
+
+// Every exchange of a session in order of request, and each pair's round trips
+pub struct Correlation {
+    pub exchanges: Vec<Exchange>,
+    pub stats: Vec<RoundTripStats>, // In ROUND_TRIP_PAIRS order, for the pairs with answered requests
+}
+
+/// Exchanges of every known pair in a session
+pub async fn load_correlation(db: &dyn Storage, session_id: i32) -> Result<Correlation> {
+    let packets = |direction: &str, name: &str| DbPacketFilter {
+        direction: Some(direction.to_string()),
+        packet_name: Some(name.to_string()),
+        ..Default::default()
+    };
+    let filters = ROUND_TRIP_PAIRS
+        .iter()
+        .flat_map(|pair| [packets(pair.request_direction, pair.request), packets(pair.response_direction(), pair.response)])
+        .collect();
+    let filter_set = DbPacketFilterSet { filters };
+
+    let mut matcher = RoundTripMatcher::default();
+    let mut stream = db.stream_packets(session_id, Some(&filter_set));
+    while let Some(packet) = stream.try_next().await? {
+        matcher.push(&packet);
+    }
+    Ok(matcher.finish())
+}
+
+// Request waiting for its answer
+struct Pending {
+    id: Option<String>, // One entry per id of a batched request
+    packet_number: i64,
+    session_time_ms: i64,
+}
+
+// Matches responses to requests, packet by packet in session order
+#[derive(Default)]
+struct RoundTripMatcher {
+    pending: Vec<VecDeque<Pending>>, // By index into ROUND_TRIP_PAIRS, oldest first
+    exchanges: Vec<Exchange>,
+}
+
+impl RoundTripMatcher {
+    fn push(&mut self, packet: &DbPacket) {
+        if self.pending.is_empty() {
+            self.pending.resize_with(ROUND_TRIP_PAIRS.len(), VecDeque::new);
+        }
+        let name = packet.packet["name"].as_str().unwrap_or_default();
+        let params = &packet.packet["params"];
+        for (pair, pending) in ROUND_TRIP_PAIRS.iter().zip(&mut self.pending) {
+            let ids = || pair.ids.map(|ids| ids(params)).unwrap_or_default();
+            if packet.direction == pair.request_direction && name == pair.request {
+                let ids = ids();
+                let ids = if ids.is_empty() { vec![None] } else { ids.into_iter().map(Some).collect() };
+                pending.extend(ids.into_iter().map(|id| Pending {
+                    id,
+                    packet_number: packet.packet_number,
+                    session_time_ms: packet.session_time_ms,
+                }));
+            } else if packet.direction == pair.response_direction() && name == pair.response {
+                // The requests with the response's ids, or the oldest one when it has none
+                let ids = ids();
+                let answered: Vec<Pending> = if ids.is_empty() {
+                    pending.pop_front().into_iter().collect()
+                } else {
+                    let mut indexes: Vec<usize> =
+                        ids.iter().filter_map(|id| pending.iter().position(|p| p.id.as_ref() == Some(id))).collect();
+                    indexes.sort_unstable();
+                    indexes.dedup();
+                    indexes.into_iter().rev().filter_map(|index| pending.remove(index)).collect()
+                };
+                let mut requests: Vec<(i64, i64)> = answered.iter().map(|p| (p.packet_number, p.session_time_ms)).collect();
+                requests.sort_unstable();
+                requests.dedup();
+                self.exchanges.extend(requests.into_iter().map(|(request, request_time)| Exchange {
+                    pair,
+                    request,
+                    response: packet.packet_number,
+                    round_trip_ms: packet.session_time_ms - request_time,
+                }));
+            }
+        }
+    }
+
+    fn finish(mut self) -> Correlation {
+        self.exchanges.sort_by_key(|exchange| (exchange.request, exchange.response));
+        let stats = ROUND_TRIP_PAIRS
+            .iter()
+            .zip(&self.pending)
+            .filter_map(|(pair, pending)| {
+                let mut times: Vec<(i64, i64)> = self
+                    .exchanges
+                    .iter()
+                    .filter(|exchange| std::ptr::eq(exchange.pair, pair))
+                    .map(|exchange| (exchange.round_trip_ms, exchange.request))
+                    .collect();
+                times.sort_unstable();
+                let &(max_ms, slowest) = times.last()?;
+                let mut unanswered: Vec<i64> = pending.iter().map(|p| p.packet_number).collect();
+                unanswered.dedup();
+                Some(RoundTripStats {
+                    pair,
+                    count: times.len(),
+                    min_ms: times[0].0,
+                    median_ms: times[times.len() / 2].0,
+                    max_ms,
+                    slowest,
+                    unanswered: unanswered.len(),
+                })
+            })
+            .collect();
+        Correlation { exchanges: self.exchanges, stats }
+    }
+}
+
+/// Index of the exchange a packet is the request or the response of (the first, for a batch)
+pub fn exchange_of(exchanges: &[Exchange], packet_number: i64) -> Option<usize> {
+    exchanges
+        .iter()
+        .position(|exchange| exchange.request == packet_number || exchange.response == packet_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn db_packet(packet_number: i64, session_time_ms: i64, direction: &str, packet: Value) -> DbPacket {
+        DbPacket {
+            id: 0,
+            session_id: 1,
+            ts: Utc::now(),
+            session_time_ms,
+            packet_number,
+            server_version: "1.21.111".to_string(),
+            direction: direction.to_string(),
+            packet,
+        }
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let mut matcher = RoundTripMatcher::default();
+        for (number, time, direction, name) in [
+            (1, 0, "serverbound", "request_network_settings"),
+            (2, 40, "clientbound", "network_settings"),
+            (3, 100, "serverbound", "tick_sync"),
+            (4, 110, "serverbound", "tick_sync"),
+            (5, 130, "clientbound", "tick_sync"), // Answers 3
+            (6, 200, "clientbound", "tick_sync"), // Answers 4
+            (7, 300, "serverbound", "tick_sync"),
+            (8, 320, "clientbound", "text"),
+            (9, 400, "serverbound", "command_request"),
+        ] {
+            matcher.push(&db_packet(number, time, direction, json!({ "name": name, "params": {} })));
+        }
+        let correlation = matcher.finish();
+        let summary: Vec<_> = correlation
+            .stats
+            .iter()
+            .map(|s| (s.pair.request, s.count, s.min_ms, s.median_ms, s.max_ms, s.slowest, s.unanswered))
+            .collect();
+        // command_request was never answered, so has no round trips to show
+        assert_eq!(summary, [("request_network_settings", 1, 40, 40, 40, 1, 0), ("tick_sync", 2, 30, 90, 90, 4, 1)]);
+        assert_eq!(exchange_of(&correlation.exchanges, 6), Some(2));
+    }
+
+    #[test]
+    fn test_exchanges_by_id() {
+        let mut matcher = RoundTripMatcher::default();
+        let request = |ids: &[i64]| json!({
+            "name": "item_stack_request",
+            "params": { "requests": ids.iter().map(|id| json!({ "request_id": id, "actions": [] })).collect::<Vec<_>>() }
+        });
+        let response = |ids: &[i64]| json!({
+            "name": "item_stack_response",
+            "params": { "responses": ids.iter().map(|id| json!({ "status": "ok", "request_id": id })).collect::<Vec<_>>() }
+        });
+        matcher.push(&db_packet(1, 0, "serverbound", request(&[-1, -3])));
+        matcher.push(&db_packet(2, 10, "serverbound", request(&[-5])));
+        matcher.push(&db_packet(3, 50, "clientbound", response(&[-5]))); // Out of order, matched by id
+        matcher.push(&db_packet(4, 80, "clientbound", response(&[-1, -3])));
+        matcher.push(&db_packet(5, 90, "serverbound", json!({
+            "name": "command_request",
+            "params": { "command": "/time set day", "origin": { "type": "player", "uuid": "a-b" } }
+        })));
+        matcher.push(&db_packet(6, 95, "clientbound", json!({
+            "name": "command_output",
+            "params": { "origin": { "type": "player", "uuid": "c-d" }, "output": [] }
+        })));
+        let correlation = matcher.finish();
+        let exchanges: Vec<_> = correlation
+            .exchanges
+            .iter()
+            .map(|e| (e.pair.request, e.request, e.response, e.round_trip_ms))
+            .collect();
+        assert_eq!(exchanges, [("item_stack_request", 1, 4, 80), ("item_stack_request", 2, 3, 40)]);
+        // The command's output was for another origin
+        assert_eq!(correlation.stats.iter().map(|s| (s.pair.request, s.unanswered)).collect::<Vec<_>>(), [("item_stack_request", 0)]);
+    }
+}
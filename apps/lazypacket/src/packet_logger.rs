@@ -1,14 +1,34 @@
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Context};
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 use uuid::Uuid;
 use chrono::Utc;
+use crate::log_index::{index_path, IndexEntry};
+use crate::log_meta::{SessionDetails, SessionMeta};
+use crate::log_reader::MAX_ENTRY_SIZE;
+use crate::protocol::ProtocolParser;
 
 // Default protocol version - matches protocol.rs
 const DEFAULT_PROTOCOL_VERSION: &str = "1.21.111";
 
+// Buffered entries are flushed once this much data or time has accumulated
+const DEFAULT_FLUSH_BYTES: usize = 64 * 1024;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(1000);
+
+// Binary logs start with a header: [b"LZPK"][u16 format version (little-endian)]
+// Files without it are legacy logs (see log_reader::decode_binary_entry)
+// The magic read as a u32 entry length is far above MAX_ENTRY_SIZE, so it can't be mistaken for one
+pub const LOG_MAGIC: [u8; 4] = *b"LZPK";
+// 1: [u32 length][bincode PacketEntry]
+// 2: [u32 length][u32 CRC-32 of the entry bytes][bincode PacketEntry]
+// 3: as 2, with PacketEntry::original_size
+pub const LOG_FORMAT_VERSION: u16 = 3;
+pub const LOG_HEADER_SIZE: u64 = 6;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PacketEntry {
     pub timestamp: i64,
@@ -20,73 +40,364 @@ pub struct PacketEntry {
     pub packet_json: Option<Value>,
     #[serde(skip)]
     pub packet_number: Option<i64>,
+    // Payload size before truncation; None when `data` is the full payload
+    #[serde(default)]
+    pub original_size: Option<usize>,
+}
+
+impl PacketEntry {
+    /// Size of the captured payload, including any bytes dropped by truncation
+    pub fn size(&self) -> usize {
+        self.original_size.unwrap_or(self.data.len())
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.original_size.is_some()
+    }
+
+    /// Keep at most `limit` payload bytes, recording the full size if anything was dropped
+    pub fn truncate_payload(&mut self, limit: usize) {
+        if self.data.len() > limit {
+            self.original_size = Some(self.size());
+            self.data.truncate(limit);
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PacketDirection {
+    #[serde(alias = "clientbound")]
     Clientbound,
+    #[serde(alias = "serverbound")]
     Serverbound,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Binary,    // Header, then [u32 length (little-endian)][u32 CRC-32][bincode-serialized PacketEntry] per packet
+    JsonLines, // One JSON object per line
+}
+
+impl LogFormat {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "bin" | "binary" => Ok(LogFormat::Binary),
+            "jsonl" | "ndjson" | "json" => Ok(LogFormat::JsonLines),
+            _ => Err(anyhow!("Unknown log format: {} (expected binary or jsonl)", s)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LogFormat::Binary => "bin",
+            LogFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+// One packet in a JSON Lines log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonLineEntry {
+    pub timestamp: i64,
+    pub direction: PacketDirection,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_number: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>, // Full payload size; larger than `data` when the payload was truncated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>, // Raw payload as a hex string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet: Option<Value>, // Decoded packet JSON ({ name, params })
+}
+
+impl JsonLineEntry {
+    pub fn into_packet_entry(self) -> anyhow::Result<PacketEntry> {
+        let data = match self.data {
+            Some(ref hex) => decode_hex(hex)?,
+            None => Vec::new(),
+        };
+
+        let original_size = self.size.filter(|&size| size > data.len());
+        Ok(PacketEntry {
+            timestamp: self.timestamp,
+            direction: self.direction,
+            data,
+            protocol_version: self.protocol_version,
+            packet_json: self.packet,
+            packet_number: self.packet_number,
+            original_size,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PacketLoggerConfig {
+    pub protocol_version: String,
+    pub format: LogFormat,
+    pub flush_bytes: usize,        // Flush when this many bytes are buffered (0 = every entry)
+    pub flush_interval: Duration,  // Flush when the oldest buffered entry is this old (checked on write and by the capture loop)
+    pub max_entry_size: usize,     // Entries larger than this (encoded) are rejected; capped at MAX_ENTRY_SIZE for binary logs
+}
+
+impl Default for PacketLoggerConfig {
+    fn default() -> Self {
+        Self {
+            protocol_version: DEFAULT_PROTOCOL_VERSION.to_string(),
+            format: LogFormat::Binary,
+            flush_bytes: DEFAULT_FLUSH_BYTES,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            max_entry_size: MAX_ENTRY_SIZE as usize,
+        }
+    }
+}
+
+impl PacketLoggerConfig {
+    /// Build config from environment variables
+    /// (PACKET_LOG_FORMAT, BEDROCK_VERSION, PACKET_LOG_FLUSH_BYTES, PACKET_LOG_FLUSH_MS, PACKET_LOG_MAX_ENTRY_BYTES)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+
+        if let Ok(version) = std::env::var("BEDROCK_VERSION") {
+            config.protocol_version = version;
+        }
+        if let Ok(format) = std::env::var("PACKET_LOG_FORMAT") {
+            config.format = LogFormat::parse(&format).context("Invalid PACKET_LOG_FORMAT")?;
+        }
+        if let Ok(bytes) = std::env::var("PACKET_LOG_FLUSH_BYTES") {
+            config.flush_bytes = bytes.parse().context("Invalid PACKET_LOG_FLUSH_BYTES")?;
+        }
+        if let Ok(ms) = std::env::var("PACKET_LOG_FLUSH_MS") {
+            config.flush_interval = Duration::from_millis(ms.parse().context("Invalid PACKET_LOG_FLUSH_MS")?);
+        }
+        if let Ok(bytes) = std::env::var("PACKET_LOG_MAX_ENTRY_BYTES") {
+            config.max_entry_size = bytes.parse().context("Invalid PACKET_LOG_MAX_ENTRY_BYTES")?;
+        }
+
+        Ok(config)
+    }
+}
+
 pub struct PacketLogger {
     session_id: Uuid,
     log_path: PathBuf,
     writer: Option<BufWriter<File>>,
-    protocol_version: String,
+    index_writer: Option<BufWriter<File>>, // `.idx` sidecar with one record per packet
+    bytes_written: u64,
+    unflushed_bytes: usize,
+    first_unflushed_at: Option<Instant>,
+    config: PacketLoggerConfig,
+    meta: SessionMeta, // Written to the `.meta.json` sidecar on flush and drop
+    // Only loaded for JSON Lines output, where packets are written decoded when possible
+    protocol_parser: Option<ProtocolParser>,
 }
 
 impl PacketLogger {
     pub fn new(session_id: Uuid, log_dir: impl AsRef<Path>) -> Result<Self, std::io::Error> {
-        Self::with_protocol_version(session_id, log_dir, DEFAULT_PROTOCOL_VERSION.to_string())
+        Self::with_config(session_id, log_dir, PacketLoggerConfig::default())
     }
 
     pub fn with_protocol_version(
-        session_id: Uuid, 
+        session_id: Uuid,
         log_dir: impl AsRef<Path>,
         protocol_version: String,
+    ) -> Result<Self, std::io::Error> {
+        let config = PacketLoggerConfig {
+            protocol_version,
+            ..PacketLoggerConfig::default()
+        };
+        Self::with_config(session_id, log_dir, config)
+    }
+
+    pub fn with_config(
+        session_id: Uuid,
+        log_dir: impl AsRef<Path>,
+        config: PacketLoggerConfig,
     ) -> Result<Self, std::io::Error> {
         let log_dir = log_dir.as_ref();
-        
+
         // Create log directory if it doesn't exist
         std::fs::create_dir_all(log_dir)?;
 
-        // Create log file path: logs/session_id.bin
-        let log_path = log_dir.join(format!("{}.bin", session_id));
+        // Create log file path: logs/session_id.bin (or .jsonl)
+        let log_path = log_dir.join(format!("{}.{}", session_id, config.format.extension()));
 
         let file = File::create(&log_path)?;
-        let writer = BufWriter::new(file);
+        let mut writer = BufWriter::new(file);
+        let mut bytes_written = 0;
+        if config.format == LogFormat::Binary {
+            writer.write_all(&LOG_MAGIC)?;
+            writer.write_all(&LOG_FORMAT_VERSION.to_le_bytes())?;
+            writer.flush()?;
+            bytes_written = LOG_HEADER_SIZE;
+        }
+        let index_writer = BufWriter::new(File::create(index_path(&log_path))?);
+
+        let protocol_parser = match config.format {
+            LogFormat::JsonLines => ProtocolParser::new(&config.protocol_version).ok(),
+            LogFormat::Binary => None,
+        };
+
+        let meta = SessionMeta::new(session_id, Utc::now().timestamp_millis(), Some(config.protocol_version.clone()));
+        meta.save(&log_path).map_err(std::io::Error::other)?;
 
         Ok(Self {
             session_id,
             log_path,
             writer: Some(writer),
-            protocol_version,
+            index_writer: Some(index_writer),
+            bytes_written,
+            unflushed_bytes: 0,
+            first_unflushed_at: None,
+            config,
+            meta,
+            protocol_parser,
         })
     }
 
     pub fn log_packet(&mut self, direction: PacketDirection, data: Vec<u8>) -> Result<(), std::io::Error> {
+        let entry = PacketEntry {
+            timestamp: Utc::now().timestamp_millis(),
+            direction,
+            data,
+            protocol_version: None,
+            packet_json: None,
+            packet_number: None,
+            original_size: None,
+        };
+        self.log_entry(&entry)
+    }
+
+    /// Write an entry stamped by the caller (timestamp, packet number)
+    /// protocol_version falls back to the logger's configured version
+    pub fn log_entry(&mut self, entry: &PacketEntry) -> Result<(), std::io::Error> {
+        let timestamp = entry.timestamp;
+        let direction = entry.direction;
+        let protocol_version = entry
+            .protocol_version
+            .clone()
+            .unwrap_or_else(|| self.config.protocol_version.clone());
+
         if let Some(ref mut writer) = self.writer {
-            let entry = PacketEntry {
-                timestamp: Utc::now().timestamp_millis(),
+            let index_entry = IndexEntry {
+                offset: self.bytes_written,
+                timestamp,
                 direction,
-                data,
-                protocol_version: Some(self.protocol_version.clone()),
-                packet_json: None,
-                packet_number: None, // Binary logs don't have packet_number
             };
 
-            // Serialize the packet entry using bincode
-            // We write the length first so we can read entries back correctly
-            let serialized = bincode::serialize(&entry)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-            
-            // Write length as u32 (little-endian) followed by data
-            let len = serialized.len() as u32;
-            writer.write_all(&len.to_le_bytes())?;
-            writer.write_all(&serialized)?;
+            let written = match self.config.format {
+                LogFormat::Binary => {
+                    let entry = PacketEntry {
+                        timestamp,
+                        direction,
+                        data: entry.data.clone(),
+                        protocol_version: Some(protocol_version),
+                        packet_json: None,
+                        packet_number: None, // Binary logs don't have packet_number
+                        original_size: entry.original_size,
+                    };
+
+                    // Serialize the packet entry using bincode
+                    // We write the length first so we can read entries back correctly
+                    let serialized = bincode::serialize(&entry)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                    // Readers treat longer entries as corruption, so never write one
+                    check_entry_size(serialized.len(), self.config.max_entry_size.min(MAX_ENTRY_SIZE as usize))?;
+
+                    // Write length and checksum as u32 (little-endian) followed by data
+                    let len = serialized.len() as u32;
+                    writer.write_all(&len.to_le_bytes())?;
+                    writer.write_all(&crc32(&serialized).to_le_bytes())?;
+                    writer.write_all(&serialized)?;
+                    8 + serialized.len()
+                }
+                LogFormat::JsonLines => {
+                    // Decoded packet JSON when the protocol parser identifies it, raw hex always
+                    let packet = entry.packet_json.clone().or_else(|| {
+                        self.protocol_parser.as_ref()
+                            .and_then(|parser| parser.decode_packet(&entry.data, direction).to_packet_json())
+                    });
+                    let entry = JsonLineEntry {
+                        timestamp,
+                        direction,
+                        packet_number: entry.packet_number,
+                        protocol_version: Some(protocol_version),
+                        size: Some(entry.size()),
+                        data: Some(encode_hex(&entry.data)),
+                        packet,
+                    };
+
+                    let mut line = serde_json::to_vec(&entry)?;
+                    line.push(b'\n');
+                    check_entry_size(line.len(), self.config.max_entry_size)?;
+                    writer.write_all(&line)?;
+                    line.len()
+                }
+            };
+            self.bytes_written += written as u64;
+
+            if let Some(ref mut index_writer) = self.index_writer {
+                index_writer.write_all(&index_entry.to_bytes())?;
+            }
+            self.meta.record_packet(timestamp, direction);
+
+            self.unflushed_bytes += written;
+            self.first_unflushed_at.get_or_insert_with(Instant::now);
+            if self.unflushed_bytes >= self.config.flush_bytes {
+                self.flush()?;
+            } else {
+                self.flush_if_due()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flush if the oldest buffered entry is flush_interval old; returns whether it did. Called on every write,
+    /// and periodically by the capture loop so the last packets of a session that goes quiet are written too
+    pub fn flush_if_due(&mut self) -> Result<bool, std::io::Error> {
+        match self.first_unflushed_at {
+            Some(first_unflushed_at) if first_unflushed_at.elapsed() >= self.config.flush_interval => {
+                self.flush()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Write buffered entries to the OS and refresh the `.meta.json` sidecar
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        // Log before index and metadata, so a crash never leaves either describing packets past the log's end
+        if let Some(ref mut writer) = self.writer {
             writer.flush()?;
         }
-        
+        if let Some(ref mut index_writer) = self.index_writer {
+            index_writer.flush()?;
+        }
+        self.unflushed_bytes = 0;
+        self.first_unflushed_at = None;
+        self.meta.save(&self.log_path).map_err(std::io::Error::other)
+    }
+
+    /// Record addresses and tags in the session metadata (written on the next flush)
+    pub fn set_session_details(&mut self, details: &SessionDetails) {
+        self.meta.apply_details(details);
+    }
+
+    pub fn meta(&self) -> &SessionMeta {
+        &self.meta
+    }
+
+    /// Flush buffered entries and wait until they reach the disk
+    pub fn sync(&mut self) -> Result<(), std::io::Error> {
+        self.flush()?;
+        if let Some(ref writer) = self.writer {
+            writer.get_ref().sync_data()?;
+        }
+        if let Some(ref index_writer) = self.index_writer {
+            index_writer.get_ref().sync_data()?;
+        }
         Ok(())
     }
 
@@ -97,6 +408,10 @@ impl PacketLogger {
     pub fn log_path(&self) -> &Path {
         &self.log_path
     }
+
+    pub fn format(&self) -> LogFormat {
+        self.config.format
+    }
 }
 
 impl Drop for PacketLogger {
@@ -106,5 +421,207 @@ impl Drop for PacketLogger {
             let _ = writer.into_inner()
                 .map_err(|e| eprintln!("Error flushing log file: {}", e));
         }
+        if let Some(mut index_writer) = self.index_writer.take() {
+            let _ = index_writer.flush();
+        }
+        self.meta.ended_at = Some(Utc::now().timestamp_millis());
+        if let Err(e) = self.meta.save(&self.log_path) {
+            eprintln!("Error writing session metadata: {:#}", e);
+        }
+    }
+}
+
+fn check_entry_size(len: usize, max: usize) -> Result<(), std::io::Error> {
+    if len > max {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Entry of {} bytes exceeds the maximum entry size of {} bytes", len, max),
+        ));
+    }
+    Ok(())
+}
+
+/// CRC-32 (IEEE 802.3, as used by zlib and PNG)
+pub fn crc32(bytes: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    !bytes.iter().fold(!0u32, |crc, &b| TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+pub fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn decode_hex(hex: &str) -> anyhow::Result<Vec<u8>> {
+    let hex = hex.trim_start_matches("0x");
+    // Byte offsets below are only char boundaries in ASCII
+    if !hex.is_ascii() {
+        return Err(anyhow!("Hex string has non-ASCII characters"));
+    }
+    if !hex.len().is_multiple_of(2) {
+        return Err(anyhow!("Hex string has odd length: {}", hex.len()));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .with_context(|| format!("Invalid hex byte at offset {}", i))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_reader::LogReader;
+
+    #[test]
+    fn test_json_lines_round_trip() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+        let config = PacketLoggerConfig {
+            format: LogFormat::JsonLines,
+            ..PacketLoggerConfig::default()
+        };
+
+        let log_path = {
+            let mut logger = PacketLogger::with_config(Uuid::new_v4(), &log_dir, config).unwrap();
+            logger.log_packet(PacketDirection::Clientbound, vec![0xde, 0xad]).unwrap();
+            logger.log_packet(PacketDirection::Serverbound, vec![0xbe, 0xef]).unwrap();
+            logger.log_path().to_path_buf()
+        };
+        assert_eq!(log_path.extension().unwrap(), "jsonl");
+
+        let reader = LogReader::open(log_path.to_str().unwrap()).unwrap();
+        let entries: Vec<_> = reader.collect::<anyhow::Result<_>>().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].data, vec![0xde, 0xad]);
+        assert!(matches!(entries[1].direction, PacketDirection::Serverbound));
+
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_batched_flush() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+        let config = PacketLoggerConfig {
+            flush_bytes: 1024,
+            flush_interval: Duration::from_secs(3600),
+            ..PacketLoggerConfig::default()
+        };
+
+        let mut logger = PacketLogger::with_config(Uuid::new_v4(), &log_dir, config).unwrap();
+        let file_len = |logger: &PacketLogger| std::fs::metadata(logger.log_path()).unwrap().len();
+
+        // Small entries stay buffered until the byte threshold
+        logger.log_packet(PacketDirection::Clientbound, vec![0; 10]).unwrap();
+        assert_eq!(file_len(&logger), LOG_HEADER_SIZE);
+        logger.log_packet(PacketDirection::Clientbound, vec![0; 2000]).unwrap();
+        let flushed = file_len(&logger);
+        assert!(flushed > LOG_HEADER_SIZE);
+
+        logger.log_packet(PacketDirection::Serverbound, vec![0; 10]).unwrap();
+        assert_eq!(file_len(&logger), flushed);
+        logger.sync().unwrap();
+        assert!(file_len(&logger) > flushed);
+
+        drop(logger);
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_flush_if_due() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+        let config = PacketLoggerConfig {
+            flush_bytes: 1024 * 1024,
+            flush_interval: Duration::from_millis(20),
+            ..PacketLoggerConfig::default()
+        };
+
+        let mut logger = PacketLogger::with_config(Uuid::new_v4(), &log_dir, config).unwrap();
+        let file_len = |logger: &PacketLogger| std::fs::metadata(logger.log_path()).unwrap().len();
+
+        // With no packet after it, the entry is only written once it's due
+        logger.log_packet(PacketDirection::Clientbound, vec![0; 10]).unwrap();
+        assert!(!logger.flush_if_due().unwrap());
+        assert_eq!(file_len(&logger), LOG_HEADER_SIZE);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(logger.flush_if_due().unwrap());
+        assert!(file_len(&logger) > LOG_HEADER_SIZE);
+        // Nothing is left to flush
+        assert!(!logger.flush_if_due().unwrap());
+
+        drop(logger);
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_payload_and_max_entry_size() {
+        let log_dir = std::env::temp_dir().join(format!("lazypacket-test-{}", Uuid::new_v4()));
+        let config = PacketLoggerConfig {
+            max_entry_size: 1024,
+            ..PacketLoggerConfig::default()
+        };
+
+        let log_path = {
+            let mut logger = PacketLogger::with_config(Uuid::new_v4(), &log_dir, config).unwrap();
+            let mut entry = PacketEntry {
+                timestamp: 1,
+                direction: PacketDirection::Clientbound,
+                data: vec![0x3a; 5000],
+                protocol_version: None,
+                packet_json: None,
+                packet_number: None,
+                original_size: None,
+            };
+
+            // Too large for the guard, and nothing is written for it
+            let err = logger.log_entry(&entry).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+            entry.truncate_payload(16);
+            logger.log_entry(&entry).unwrap();
+            logger.log_path().to_path_buf()
+        };
+
+        let entries: Vec<_> = LogReader::open(log_path.to_str().unwrap())
+            .unwrap()
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].data.len(), 16);
+        assert_eq!(entries[0].size(), 5000);
+        assert!(entries[0].is_truncated());
+
+        std::fs::remove_dir_all(&log_dir).unwrap();
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("0xfe01").unwrap(), [0xfe, 0x01]);
+        assert!(decode_hex("fe0").is_err());
+        assert!(decode_hex("zz").is_err());
+        // Multi-byte characters are an error, not a panic on a char boundary
+        assert!(decode_hex("aéb0").is_err());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
     }
 }
@@ -0,0 +1,77 @@
+// Playback (Space in the packet view): moves through the packets on its own, as far apart as they were captured
+// and scaled by the speed (< and >), so a session can be watched like a recording while the details and watch
+// panels follow along. A played clock runs at the chosen speed and every packet it has reached is moved to, so
+// bursts are passed over at the same pace as quiet stretches; long pauses are cut short to keep it moving
+
+use std::time::{Duration, Instant};
+
+pub const SPEEDS: &[f64] = &[0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0];
+pub const DEFAULT_SPEED: usize = 2; // Real time
+const MAX_WAIT: Duration = Duration::from_secs(2); // Longest wait for the next packet, whatever the gap before it
+const MAX_TICK: Duration = Duration::from_millis(250); // Most a tick plays, so time spent in another view isn't skipped
+
+#[derive(Debug, Clone)]
+pub struct Playback {
+    played_ms: f64, // Session time (packet timestamp) the played clock is at
+    last_tick: Instant,
+    packet_index: usize, // Packet playback last moved to, to notice when another one is picked
+}
+
+impl Playback {
+    pub fn new(packet_index: usize, timestamp: i64, now: Instant) -> Self {
+        Self { played_ms: timestamp as f64, last_tick: now, packet_index }
+    }
+
+    /// Start the played clock again from a packet moved to by other means than playback
+    pub fn follow_index(&mut self, packet_index: usize, timestamp: i64, now: Instant) {
+        if packet_index != self.packet_index {
+            *self = Self::new(packet_index, timestamp, now);
+        }
+    }
+
+    /// Run the played clock up to `now` at `speed`, not letting it wait more than MAX_WAIT for the next packet
+    /// (at `next_timestamp`), and give the time it's at
+    pub fn tick(&mut self, now: Instant, speed: f64, next_timestamp: i64) -> i64 {
+        let elapsed = now.saturating_duration_since(self.last_tick).min(MAX_TICK).as_secs_f64() * 1000.0;
+        self.last_tick = now;
+        self.played_ms += elapsed * speed;
+        let latest_start = next_timestamp as f64 - MAX_WAIT.as_secs_f64() * 1000.0 * speed;
+        self.played_ms = self.played_ms.max(latest_start);
+        self.played_ms as i64
+    }
+
+    /// Record the packet playback moved to
+    pub fn moved_to(&mut self, packet_index: usize) {
+        self.packet_index = packet_index;
+    }
+}
+
+/// A speed as shown in the header, e.g. 0.5x
+pub fn speed_label(speed: usize) -> String {
+    format!("{}x", SPEEDS[speed])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_clock() {
+        let start = Instant::now();
+        let mut playback = Playback::new(0, 10_000, start);
+        // Twice as fast: 100ms later the clock has played 200ms of the session
+        assert_eq!(playback.tick(start + Duration::from_millis(100), 2.0, 10_500), 10_200);
+        assert_eq!(playback.tick(start + Duration::from_millis(150), 0.5, 10_500), 10_225);
+        // A minute's gap is waited out in MAX_WAIT
+        assert_eq!(playback.tick(start + Duration::from_millis(200), 1.0, 70_225), 68_225);
+        assert_eq!(playback.tick(start + Duration::from_millis(400), 1.0, 70_225), 68_425);
+        // Ticks far apart (with the packet view hidden) play no more than MAX_TICK
+        assert_eq!(playback.tick(start + Duration::from_millis(5000), 1.0, 70_225), 68_675);
+
+        // Picking another packet restarts the clock from it
+        playback.follow_index(5, 90_000, start);
+        assert_eq!(playback.tick(start + Duration::from_millis(10), 1.0, 90_500), 90_010);
+        assert_eq!(speed_label(0), "0.25x");
+        assert_eq!(speed_label(DEFAULT_SPEED), "1x");
+    }
+}
@@ -0,0 +1,175 @@
+// `stats` subcommand and the TUI statistics panel: per packet name and direction totals for a database session
+// (count, bytes of stored JSON, average size, rate over the session), from Storage::get_packet_stats. The panel
+// opened with `S` covers only the packets passing the packet view's filter
+
+use crate::db::PacketTypeStats;
+use crate::storage;
+use anyhow::{anyhow, Context, Result};
+use std::cmp::Reverse;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsSort {
+    #[default]
+    Count,
+    Bytes,
+    Average,
+    Rate,
+    Name,
+    Direction,
+}
+
+impl StatsSort {
+    fn parse(text: &str) -> Result<Self> {
+        match text {
+            "count" => Ok(Self::Count),
+            "bytes" => Ok(Self::Bytes),
+            "avg" => Ok(Self::Average),
+            "rate" => Ok(Self::Rate),
+            "name" => Ok(Self::Name),
+            "direction" => Ok(Self::Direction),
+            _ => Err(anyhow!("Invalid --sort: {} (expected count, bytes, avg, rate, name or direction)", text)),
+        }
+    }
+
+    /// Next order, for cycling through them in the TUI
+    pub fn next(self) -> Self {
+        match self {
+            Self::Count => Self::Bytes,
+            Self::Bytes => Self::Average,
+            Self::Average => Self::Rate,
+            Self::Rate => Self::Name,
+            Self::Name => Self::Direction,
+            Self::Direction => Self::Count,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Bytes => "bytes",
+            Self::Average => "avg size",
+            Self::Rate => "rate",
+            Self::Name => "name",
+            Self::Direction => "direction",
+        }
+    }
+}
+
+fn average_size(stats: &PacketTypeStats) -> i64 {
+    stats.total_bytes / stats.count.max(1)
+}
+
+/// Largest first for the numeric columns, alphabetical for name and direction; ties keep their current order
+pub fn sort_stats(stats: &mut [PacketTypeStats], sort: StatsSort) {
+    match sort {
+        StatsSort::Count => stats.sort_by_key(|s| Reverse(s.count)),
+        StatsSort::Bytes => stats.sort_by_key(|s| Reverse(s.total_bytes)),
+        StatsSort::Average => stats.sort_by_key(|s| Reverse(average_size(s))),
+        StatsSort::Rate => stats.sort_by(|a, b| b.rate.total_cmp(&a.rate)),
+        StatsSort::Name => stats.sort_by(|a, b| a.name.cmp(&b.name)),
+        StatsSort::Direction => stats.sort_by(|a, b| a.direction.cmp(&b.direction)),
+    }
+}
+
+/// Header, one line per packet type and a totals line, aligned in columns
+pub fn format_stats_table(stats: &[PacketTypeStats]) -> Vec<String> {
+    let name_width = stats.iter().map(|s| s.name.len()).max().unwrap_or(0).max("packet".len());
+    let row = |name: &str, direction: &str, count: &str, bytes: &str, average: &str, rate: &str| {
+        format!(
+            "{:<name_width$}  {:<11}  {:>9}  {:>12}  {:>9}  {:>9}",
+            name, direction, count, bytes, average, rate
+        )
+    };
+
+    let mut lines = vec![row("packet", "direction", "count", "bytes", "avg size", "per sec")];
+    for s in stats {
+        lines.push(row(
+            &s.name,
+            &s.direction,
+            &s.count.to_string(),
+            &s.total_bytes.to_string(),
+            &average_size(s).to_string(),
+            &format!("{:.2}", s.rate),
+        ));
+    }
+    let count: i64 = stats.iter().map(|s| s.count).sum();
+    let bytes: i64 = stats.iter().map(|s| s.total_bytes).sum();
+    let rate: f64 = stats.iter().map(|s| s.rate).sum();
+    lines.push(row(
+        "total",
+        "",
+        &count.to_string(),
+        &bytes.to_string(),
+        &(bytes / count.max(1)).to_string(),
+        &format!("{:.2}", rate),
+    ));
+    lines
+}
+
+/// `stats [--sort count|bytes|avg|rate|name|direction] SESSION_ID`
+pub async fn stats(args: &[String]) -> Result<()> {
+    let mut sort = StatsSort::default();
+    let mut session_id = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--sort" => {
+                sort = StatsSort::parse(iter.next().ok_or_else(|| anyhow!("--sort requires a value"))?)?;
+            }
+            _ if session_id.is_none() => session_id = Some(arg.parse::<i32>().context("Invalid session id")?),
+            _ => return Err(anyhow!("Unexpected argument: {}", arg)),
+        }
+    }
+    let session_id = session_id.ok_or_else(|| anyhow!("Usage: lazypacket stats [--sort count|bytes|avg|rate|name|direction] SESSION_ID"))?;
+
+    let db = storage::connect().await?;
+    let mut stats = db.get_packet_stats(session_id, None).await?;
+    if stats.is_empty() {
+        return Err(anyhow!("No packets found for session {}", session_id));
+    }
+    sort_stats(&mut stats, sort);
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for line in format_stats_table(&stats) {
+        writeln!(out, "{}", line)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet_type(name: &str, count: i64, total_bytes: i64, rate: f64) -> PacketTypeStats {
+        PacketTypeStats {
+            name: name.to_string(),
+            direction: "clientbound".to_string(),
+            count,
+            total_bytes,
+            rate,
+        }
+    }
+
+    #[test]
+    fn test_sort_and_format() {
+        let mut stats = vec![packet_type("text", 2, 400, 0.5), packet_type("move_player", 10, 300, 2.5)];
+        sort_stats(&mut stats, StatsSort::Bytes);
+        assert_eq!(stats[0].name, "text");
+        sort_stats(&mut stats, StatsSort::Average);
+        assert_eq!(stats[0].name, "text");
+        sort_stats(&mut stats, StatsSort::Name);
+        assert_eq!(stats[0].name, "move_player");
+        sort_stats(&mut stats, StatsSort::Count);
+        assert_eq!(stats[0].name, "move_player");
+        assert_eq!(StatsSort::parse("avg").unwrap(), StatsSort::Average);
+
+        let lines = format_stats_table(&stats);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("move_player  clientbound"));
+        assert!(lines[3].starts_with("total"));
+        assert!(lines[3].ends_with("12           700         58       3.00"));
+    }
+}
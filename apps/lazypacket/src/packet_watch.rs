@@ -0,0 +1,78 @@
+// Watch panel (`w` in the packet view): JSON paths pinned to show their values as packets are navigated, e.g.
+// params.position.x or params.tick. Paths are dotted keys with [i] for array elements; one that doesn't start with
+// a top-level key of the packet is looked for under params, so position.x works as well
+
+use serde_json::Value;
+
+const MAX_VALUE_WIDTH: usize = 60; // Characters of a watched value shown, for objects and long strings
+
+/// The value at a watched path in packet JSON ({name, params}), if the packet has it
+pub fn watched_value<'a>(packet: &'a Value, path: &str) -> Option<&'a Value> {
+    path_value(packet, path).or_else(|| path_value(&packet["params"], path))
+}
+
+fn path_value<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut value = value;
+    for segment in path.split('.') {
+        let (key, indexes) = segment.split_once('[').map_or((segment, ""), |(key, rest)| (key, rest));
+        if !key.is_empty() {
+            value = value.get(key)?;
+        }
+        for index in indexes.split('[').filter(|index| !index.is_empty()) {
+            value = value.get(index.trim_end_matches(']').parse::<usize>().ok()?)?;
+        }
+    }
+    Some(value)
+}
+
+/// A watched value as compact JSON, shortened to fit the panel
+pub fn format_value(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(MAX_VALUE_WIDTH) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+/// How a value changed since the last packet that had it: the difference for numbers (which may be stored as
+/// strings, for 64-bit ones), or None when it's the same
+pub fn change(previous: &Value, current: &Value) -> Option<String> {
+    if previous == current {
+        return None;
+    }
+    let number = |value: &Value| value.as_f64().or_else(|| value.as_str()?.parse().ok());
+    Some(match (number(previous), number(current)) {
+        (Some(previous), Some(current)) => {
+            let delta = current - previous;
+            let delta = if delta.fract() == 0.0 { format!("{}", delta) } else { format!("{:.3}", delta) };
+            if delta.starts_with('-') { delta } else { format!("+{}", delta) }
+        }
+        _ => "changed".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_watched_value() {
+        let packet = json!({
+            "name": "move_player",
+            "params": { "position": { "x": 1.5, "y": 64.0 }, "tick": "1200", "items": [{ "count": 3 }] }
+        });
+        assert_eq!(watched_value(&packet, "params.position.x"), Some(&json!(1.5)));
+        assert_eq!(watched_value(&packet, "position.y"), Some(&json!(64.0)));
+        assert_eq!(watched_value(&packet, "items[0].count"), Some(&json!(3)));
+        assert_eq!(watched_value(&packet, "name"), Some(&json!("move_player")));
+        assert_eq!(watched_value(&packet, "params.position.z"), None);
+        assert_eq!(watched_value(&packet, "items[1]"), None);
+
+        assert_eq!(change(&json!(1.5), &json!(1.5)), None);
+        assert_eq!(change(&json!(1.5), &json!(1.25)).as_deref(), Some("-0.250"));
+        assert_eq!(change(&json!("1200"), &json!("1203")).as_deref(), Some("+3"));
+        assert_eq!(change(&json!("a"), &json!("b")).as_deref(), Some("changed"));
+        assert_eq!(format_value(&json!("x".repeat(70))).chars().count(), MAX_VALUE_WIDTH + 1);
+    }
+}
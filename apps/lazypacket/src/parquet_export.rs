@@ -0,0 +1,272 @@
+// `export-parquet` subcommand: convert a session (log file or database) into a Parquet file
+// for analysis in DuckDB / pandas, one row per packet:
+//   packet_number, timestamp (ms, UTC), direction, packet_name, size, packet_json
+
+use crate::log_reader::LogReader;
+use crate::packet_dump::DumpContext;
+use crate::packet_logger::PacketDirection;
+use crate::storage;
+use anyhow::{anyhow, Context, Result};
+use arrow_array::builder::{Int64Builder, StringBuilder, TimestampMillisecondBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use futures::TryStreamExt;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Rows buffered per record batch (and so per row group at most)
+const BATCH_ROWS: usize = 8192;
+
+pub struct PacketRow {
+    pub packet_number: i64,
+    pub timestamp: i64,
+    pub direction: PacketDirection,
+    pub packet_name: Option<String>,
+    pub size: i64,
+    pub packet_json: Option<serde_json::Value>,
+}
+
+pub fn packet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("packet_number", DataType::Int64, false),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+        Field::new("direction", DataType::Utf8, false),
+        Field::new("packet_name", DataType::Utf8, true),
+        Field::new("size", DataType::Int64, false),
+        Field::new("packet_json", DataType::Utf8, true),
+    ]))
+}
+
+/// Writes packet rows to a Parquet file in record batches
+pub struct ParquetPacketWriter {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    packet_number: Int64Builder,
+    timestamp: TimestampMillisecondBuilder,
+    direction: StringBuilder,
+    packet_name: StringBuilder,
+    size: Int64Builder,
+    packet_json: StringBuilder,
+    buffered: usize,
+    rows: usize,
+}
+
+impl ParquetPacketWriter {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create Parquet file: {}", path.display()))?;
+        let schema = packet_schema();
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+
+        Ok(Self {
+            writer,
+            schema,
+            packet_number: Int64Builder::new(),
+            timestamp: TimestampMillisecondBuilder::new().with_timezone("UTC"),
+            direction: StringBuilder::new(),
+            packet_name: StringBuilder::new(),
+            size: Int64Builder::new(),
+            packet_json: StringBuilder::new(),
+            buffered: 0,
+            rows: 0,
+        })
+    }
+
+    pub fn push(&mut self, row: PacketRow) -> Result<()> {
+        self.packet_number.append_value(row.packet_number);
+        self.timestamp.append_value(row.timestamp);
+        self.direction.append_value(match row.direction {
+            PacketDirection::Clientbound => "clientbound",
+            PacketDirection::Serverbound => "serverbound",
+        });
+        self.packet_name.append_option(row.packet_name);
+        self.size.append_value(row.size);
+        self.packet_json.append_option(row.packet_json.map(|json| json.to_string()));
+
+        self.buffered += 1;
+        self.rows += 1;
+        if self.buffered >= BATCH_ROWS {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<()> {
+        if self.buffered == 0 {
+            return Ok(());
+        }
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.packet_number.finish()),
+            Arc::new(self.timestamp.finish()),
+            Arc::new(self.direction.finish()),
+            Arc::new(self.packet_name.finish()),
+            Arc::new(self.size.finish()),
+            Arc::new(self.packet_json.finish()),
+        ];
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+        self.buffered = 0;
+        Ok(())
+    }
+
+    /// Write remaining rows and the file footer; returns the number of rows written
+    pub fn finish(mut self) -> Result<usize> {
+        self.write_batch()?;
+        self.writer.close()?;
+        Ok(self.rows)
+    }
+}
+
+#[derive(Default)]
+struct ParquetOptions {
+    path: Option<String>,
+    session: Option<i32>,
+    output: Option<PathBuf>,
+}
+
+impl ParquetOptions {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut options = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |flag: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{} requires a value", flag))
+            };
+            match arg.as_str() {
+                "--session" => options.session = Some(value("--session")?.parse().context("Invalid --session")?),
+                "-o" | "--output" => options.output = Some(PathBuf::from(value("--output")?)),
+                _ if options.path.is_none() => options.path = Some(arg.clone()),
+                _ => return Err(anyhow!("Unexpected argument: {}", arg)),
+            }
+        }
+        if options.session.is_some() && options.path.is_some() {
+            return Err(anyhow!("Use either --session ID or a log file, not both"));
+        }
+        Ok(options)
+    }
+
+    // Default output: logs/<session>.bin -> logs/<session>.parquet, database session 12 -> session-12.parquet
+    fn output_path(&self) -> Result<PathBuf> {
+        if let Some(ref output) = self.output {
+            return Ok(output.clone());
+        }
+        match (self.session, self.path.as_deref()) {
+            (Some(session), _) => Ok(PathBuf::from(format!("session-{}.parquet", session))),
+            (None, Some(path)) if path != "-" => Ok(Path::new(path).with_extension("parquet")),
+            _ => Err(anyhow!("--output is required when reading from stdin")),
+        }
+    }
+}
+
+/// `export-parquet [--output FILE] (--session ID | FILE|-)`
+/// Log files are decoded with the protocol parser where possible; database sessions use the stored packet JSON
+/// (their size column is the size of that JSON, as raw payloads aren't stored)
+pub async fn export_parquet(args: &[String]) -> Result<()> {
+    let options = ParquetOptions::parse(args)?;
+    let output = options.output_path()?;
+    let mut writer = ParquetPacketWriter::create(&output)?;
+
+    match options.session {
+        Some(session_id) => {
+            let db = storage::connect().await?;
+            // Streamed, so sessions of any length export in constant memory
+            let mut packets = db.stream_packets(session_id, None);
+            while let Some(packet) = packets.try_next().await? {
+                let direction = match packet.direction.as_str() {
+                    "clientbound" => PacketDirection::Clientbound,
+                    "serverbound" => PacketDirection::Serverbound,
+                    other => return Err(anyhow!("Invalid direction: {}", other)),
+                };
+                writer.push(PacketRow {
+                    packet_number: packet.packet_number,
+                    timestamp: packet.ts.timestamp_millis(),
+                    direction,
+                    packet_name: packet_name(&packet.packet),
+                    size: packet.packet.to_string().len() as i64,
+                    packet_json: Some(packet.packet),
+                })?;
+            }
+        }
+        None => {
+            let context = DumpContext::new();
+            let mut reader = LogReader::open(options.path.as_deref().unwrap_or("-"))?;
+            for (index, entry) in reader.by_ref().enumerate() {
+                let entry = entry?;
+                let json = context.packet_json(&entry);
+                writer.push(PacketRow {
+                    packet_number: entry.packet_number.unwrap_or(index as i64 + 1),
+                    timestamp: entry.timestamp,
+                    direction: entry.direction,
+                    packet_name: json.as_ref().and_then(packet_name),
+                    size: entry.size() as i64,
+                    packet_json: json,
+                })?;
+            }
+            if reader.stats().skipped > 0 {
+                eprintln!("lazypacket: skipped {} corrupted entries", reader.stats().skipped);
+            }
+        }
+    }
+
+    let rows = writer.finish()?;
+    eprintln!("Wrote {} packets to {}", rows, output.display());
+    Ok(())
+}
+
+fn packet_name(json: &serde_json::Value) -> Option<String> {
+    json.get("name").and_then(|n| n.as_str()).map(|n| n.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, Int64Array, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let path = std::env::temp_dir().join(format!("lazypacket-test-{}.parquet", uuid::Uuid::new_v4()));
+        let mut writer = ParquetPacketWriter::create(&path).unwrap();
+        for i in 0..(BATCH_ROWS as i64 + 10) {
+            writer.push(PacketRow {
+                packet_number: i + 1,
+                timestamp: 1_700_000_000_000 + i,
+                direction: if i % 2 == 0 { PacketDirection::Clientbound } else { PacketDirection::Serverbound },
+                packet_name: (i != 1).then(|| "text".to_string()),
+                size: 42,
+                packet_json: (i != 1).then(|| json!({ "name": "text", "params": { "message": "hi" } })),
+            }).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), BATCH_ROWS + 10);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<std::result::Result<_, _>>().unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), BATCH_ROWS + 10);
+
+        let first = &batches[0];
+        assert_eq!(first.schema().fields().len(), 6);
+        let numbers = first.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        let directions = first.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+        let names = first.column(3).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(numbers.value(1), 2);
+        assert_eq!(directions.value(1), "serverbound");
+        assert!(names.is_null(1));
+        assert_eq!(names.value(2), "text");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
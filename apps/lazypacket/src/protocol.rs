@@ -3,21 +3,28 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use indexmap::IndexMap;
 use std::path::Path;
 use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::sync::Arc;
 use anyhow::{Result, Context, anyhow};
 use serde_yaml::Value as YamlValue;
 use serde_json::Value as JsonValue;
+use crate::packet_logger::PacketDirection;
+use crate::nbt::{read_nbt, read_nbt_loop, write_nbt, write_nbt_loop, NbtFormat};
 
 // Target protocol version - we'll use the closest available to 1.21.113
 pub const PROTOCOL_VERSION: &str = "1.21.111";
+// Where protocol definitions are looked up by version
+pub const PROTOCOL_DIR: &str = "data/protocol";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PacketInfo {
     pub id: u32,
     pub name: String,
     pub bound: PacketBound, // "client", "server", or "both"
-    pub fields: HashMap<String, YamlValue>, // Field definitions
+    pub fields: IndexMap<String, YamlValue>, // Field definitions, in declaration order (the order they are encoded)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -42,11 +49,51 @@ impl PacketBound {
 pub struct DecodedPacket {
     pub packet_id: Option<u32>,
     pub packet_name: Option<String>,
-    pub fields: HashMap<String, serde_json::Value>,
+    pub fields: IndexMap<String, serde_json::Value>,
+    // Set when the ID wasn't found in the packet's own direction and was resolved in the other one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    // Where decoding stopped, if it failed; `fields` has what was decoded before that
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<DecodeError>,
+    // Bytes each decoded field came from, by path ("entries[2].key"), in order of where they start
+    // Offsets are into the whole packet, including its ID; nested fields and array elements have their own
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub spans: IndexMap<String, Range<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeError {
+    pub field: String,    // Path of the field that failed, e.g. "entries[2].key"
+    pub offset: usize,    // Byte offset in the packet (including its ID) where the failing field starts
+    pub expected: String, // The field's type definition
+    pub remaining: usize, // Bytes left from the offset
+    pub message: String,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}) at byte {} with {} bytes left: {}",
+            self.field, self.expected, self.offset, self.remaining, self.message
+        )
+    }
+}
+
+impl DecodedPacket {
+    /// Packet JSON in the relay's `{ name, params }` shape, if the packet was identified
+    pub fn to_packet_json(&self) -> Option<JsonValue> {
+        let name = self.packet_name.as_ref()?;
+        Some(serde_json::json!({
+            "name": name,
+            "params": self.fields.clone().into_iter().collect::<serde_json::Map<_, _>>(),
+        }))
+    }
 }
 
 #[derive(Debug, Clone)]
-enum ProtoType {
+pub(crate) enum ProtoType {
     // Primitives
     I8,
     U8,
@@ -86,15 +133,50 @@ enum ProtoType {
     // Nested
     Encapsulated(Box<ProtoType>),
     Container(String), // Reference to a container type name
+    Fields(Vec<CompiledField>), // Inline container (e.g. a switch case)
+    // Conditional
+    Option(Box<ProtoType>), // bool prefix, then the value if true (null if false)
+    Switch(Switch),
+    Void, // Nothing (a switch case without data); the field is left out
+    // Symbolic values
+    Mapper(Box<ProtoType>, Vec<(i128, String)>),   // Enum: numeric value -> name (unknown values stay numbers)
+    Bitflags(Box<ProtoType>, Vec<(String, u64)>), // Flag name -> mask, decoded as { name: bool, ..., _value }
+    // NBT
+    Nbt(NbtFormat),     // nbt (network) and lnbt (little-endian)
+    NbtLoop,            // Network NBT roots until an end tag
     // Special
-    Native(String),     // Native type (nbt, etc.) - just read as bytes
+    Native(String),     // Other native types - read as bytes
+    Custom(String),     // A type with a decoder registered by the user (see ProtocolParser::register_decoder)
     RestBuffer,         // Read remaining bytes
+    AvailableCommands,  // The whole available_commands packet (see decode_available_commands)
+    // Items
+    Item { stack_id: bool }, // Item (with a stack network ID) or ItemLegacy
+    StackRequestSlotInfo,
+}
+
+// A container or packet field with its type resolved when the definitions are loaded, so decoding doesn't
+// walk the YAML again for every packet
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledField {
+    pub(crate) name: String, // As declared: `_` for an anonymous field, `name?` for an optional one
+    pub(crate) key: Option<String>, // Name the value goes under; None for anonymous fields, whose fields merge into the container
+    pub(crate) proto_type: std::result::Result<ProtoType, String>, // Or why the definition didn't parse, reported when a packet reaches it
+    pub(crate) expected: String, // The definition as text, for decode errors
+}
+
+// Type chosen by the value of an already decoded field
+#[derive(Debug, Clone)]
+pub(crate) struct Switch {
+    pub(crate) compare_to: String, // Sibling field name; each leading "../" goes up one enclosing container
+    pub(crate) cases: Vec<(Vec<String>, ProtoType)>, // Values matching each case (as strings), in declaration order
+    pub(crate) default: Option<Box<ProtoType>>, // Void if absent
 }
 
 #[derive(Debug, Clone, Copy)]
-enum CountType {
+pub(crate) enum CountType {
     VarInt,
     ZigZag32,
+    U8,
     LI16,
     LI32,
     LI64,
@@ -103,42 +185,101 @@ enum CountType {
     Fixed(usize),
 }
 
+/// Bounds on what one packet may make the decoder do, so malformed or adversarial input fails with a
+/// decode error instead of allocating huge buffers from a length prefix or recursing until the stack runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    pub max_array_length: usize, // Elements in one array
+    pub max_string_length: usize, // Bytes in one string or buffer
+    pub max_depth: usize, // Nested values (containers, arrays, options, switches, ...)
+    pub max_output_bytes: usize, // Rough size of the decoded fields of one packet
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_array_length: 1 << 20,
+            max_string_length: 16 * 1024 * 1024,
+            max_depth: 128,
+            max_output_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Decodes one value of a custom type: reads its bytes from the cursor (positioned at the value, over the rest
+/// of the packet) and returns what to show for it
+pub type CustomDecoder = Arc<dyn Fn(&mut Cursor<&[u8]>) -> Result<JsonValue> + Send + Sync>;
+
 struct BinaryDecoder<'a> {
     cursor: Cursor<&'a [u8]>,
     type_aliases: &'a HashMap<String, YamlValue>,
-    containers: &'a HashMap<String, HashMap<String, YamlValue>>,
+    containers: &'a HashMap<String, IndexMap<String, YamlValue>>,
+    container_types: &'a HashMap<String, Vec<CompiledField>>, // Compiled fields of each container
+    custom_decoders: &'a HashMap<String, CustomDecoder>,
+    scopes: Vec<IndexMap<String, JsonValue>>, // Fields decoded so far in each enclosing container, innermost last
+    path: Vec<String>, // Field names and array indexes down to the value being decoded
+    error: Option<DecodeError>, // The first (innermost) failure
+    spans: IndexMap<String, Range<usize>>, // Bytes of each decoded value, by path
+    limits: DecodeLimits,
+    depth: usize,
+    output_bytes: usize, // Rough size of what's been decoded so far
 }
 
 pub struct ProtocolParser {
     protocol_version: String,
-    packet_id_to_info: HashMap<u32, PacketInfo>,
-    // Separate maps for clientbound and serverbound packets
-    clientbound_ids: Vec<u32>,
-    serverbound_ids: Vec<u32>,
+    packets: HashMap<String, PacketInfo>, // By name
+    // Separate ID -> packet name maps for clientbound and serverbound packets, as IDs can overlap between them
+    clientbound_ids: HashMap<u32, String>,
+    serverbound_ids: HashMap<u32, String>,
     // Type aliases and container definitions
     type_aliases: HashMap<String, YamlValue>,
-    containers: HashMap<String, HashMap<String, YamlValue>>,
+    containers: HashMap<String, IndexMap<String, YamlValue>>,
+    // The same definitions compiled: fields of each packet (by name) and of each container
+    packet_types: HashMap<String, Vec<CompiledField>>,
+    container_types: HashMap<String, Vec<CompiledField>>,
+    custom_decoders: HashMap<String, CustomDecoder>, // By type name
+    limits: DecodeLimits,
 }
 
 impl ProtocolParser {
+    /// Load data/protocol/proto-<version>.yml, or data/protocol/protocol-<version>.json (minecraft-data's
+    /// protocol.json) if there's no YAML for the version
     pub fn new(version: &str) -> Result<Self> {
-        let protocol_file = format!("data/protocol/proto-{}.yml", version);
-        let proto_path = Path::new(&protocol_file);
+        Self::load_from_dir(Path::new(PROTOCOL_DIR), version)
+    }
+
+    /// Load proto-<version>.yml, or protocol-<version>.json if there's no YAML for the version, from `dir`
+    pub fn load_from_dir(dir: &Path, version: &str) -> Result<Self> {
+        let proto_path = dir.join(format!("proto-{}.yml", version));
+        let json_path = dir.join(format!("protocol-{}.json", version));
+        if !proto_path.exists() && json_path.exists() {
+            return Self::load_from_file(&json_path, version);
+        }
         
-        Self::load_from_file(proto_path, version)
+        Self::load_from_file(&proto_path, version)
     }
 
+    /// Load a proto.yml, or a minecraft-data protocol.json if the file name ends in .json
     pub fn load_from_file(path: &Path, version: &str) -> Result<Self> {
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Failed to read protocol file: {}", path.display()))?;
-        
+
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let json: JsonValue = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse JSON from {}", path.display()))?;
+            return Self::from_protocol_json(&json, version)
+                .with_context(|| format!("Failed to load protocol definitions from {}", path.display()));
+        }
+        Self::from_yaml_str(&content, version)
+            .with_context(|| format!("Failed to load protocol definitions from {}", path.display()))
+    }
+
+    /// Load the packets and types of a proto.yml's contents
+    pub fn from_yaml_str(content: &str, version: &str) -> Result<Self> {
         // Parse YAML - use from_slice to handle single document
-        let yaml: YamlValue = serde_yaml::from_str(&content)
-            .with_context(|| format!("Failed to parse YAML from {}", path.display()))?;
+        let yaml: YamlValue = serde_yaml::from_str(content).context("Failed to parse YAML")?;
 
-        let mut packet_id_to_info = HashMap::new();
-        let mut clientbound_ids = Vec::new();
-        let mut serverbound_ids = Vec::new();
+        let mut packets = Vec::new();
         let mut type_aliases = HashMap::new();
         let mut containers = HashMap::new();
 
@@ -151,7 +292,7 @@ impl ProtocolParser {
                         if let YamlValue::Mapping(packet_def) = value {
                             let mut packet_id = None;
                             let mut bound = PacketBound::Both;
-                            let mut fields = HashMap::new();
+                            let mut fields = IndexMap::new();
 
                             for (k, v) in packet_def {
                                 if let YamlValue::String(key_str) = k {
@@ -183,22 +324,12 @@ impl ProtocolParser {
                             }
 
                             if let Some(id) = packet_id {
-                                let packet_info = PacketInfo {
+                                packets.push(PacketInfo {
                                     id,
                                     name: name.clone(),
                                     bound,
                                     fields: fields.clone(),
-                                };
-                                
-                                packet_id_to_info.insert(id, packet_info);
-
-                                // Track which direction this packet can be used for
-                                if bound == PacketBound::Client || bound == PacketBound::Both {
-                                    clientbound_ids.push(id);
-                                }
-                                if bound == PacketBound::Server || bound == PacketBound::Both {
-                                    serverbound_ids.push(id);
-                                }
+                                });
                             }
                         }
                     } else if !name.starts_with("!") {
@@ -206,13 +337,15 @@ impl ProtocolParser {
                         // Type aliases are simple mappings like "string: [...]"
                         // Containers are mappings with field definitions
                         match &value {
+                            // Natives are built into the decoder
+                            YamlValue::String(native) if native == "native" => {}
                             YamlValue::Sequence(_) | YamlValue::String(_) => {
                                 // Likely a type alias
                                 type_aliases.insert(name.clone(), value.clone());
                             }
                             YamlValue::Mapping(fields) => {
                                 // Likely a container definition (has fields, not !id or !bound)
-                                let mut container_fields = HashMap::new();
+                                let mut container_fields = IndexMap::new();
                                 for (k, v) in fields {
                                     if let YamlValue::String(field_name) = k {
                                         if !field_name.starts_with("!") {
@@ -231,14 +364,187 @@ impl ProtocolParser {
             }
         }
 
-        Ok(Self {
+        Ok(Self::from_definitions(version, packets, type_aliases, containers))
+    }
+
+    fn from_definitions(
+        version: &str,
+        packets: Vec<PacketInfo>,
+        type_aliases: HashMap<String, YamlValue>,
+        containers: HashMap<String, IndexMap<String, YamlValue>>,
+    ) -> Self {
+        let mut parser = Self {
             protocol_version: version.to_string(),
-            packet_id_to_info,
-            clientbound_ids,
-            serverbound_ids,
+            packets: HashMap::new(),
+            clientbound_ids: HashMap::new(),
+            serverbound_ids: HashMap::new(),
             type_aliases,
             containers,
-        })
+            packet_types: HashMap::new(),
+            container_types: HashMap::new(),
+            custom_decoders: HashMap::new(),
+            limits: DecodeLimits::default(),
+        };
+        for info in packets {
+            // Track which direction this packet can be used for
+            if info.bound == PacketBound::Client || info.bound == PacketBound::Both {
+                parser.clientbound_ids.insert(info.id, info.name.clone());
+            }
+            if info.bound == PacketBound::Server || info.bound == PacketBound::Both {
+                parser.serverbound_ids.insert(info.id, info.name.clone());
+            }
+            parser.packets.insert(info.name.clone(), info);
+        }
+        parser.compile_types();
+        parser
+    }
+
+    /// Types are resolved once here, aliases and all; containers stay references by name, as they can nest themselves
+    fn compile_types(&mut self) {
+        let no_types = HashMap::new();
+        let compiler = BinaryDecoder::new(&[], &self.type_aliases, &self.containers, &no_types, &self.custom_decoders);
+        let container_types = self.containers
+            .iter()
+            .map(|(name, fields)| (name.clone(), compiler.compile_fields(fields)))
+            .collect();
+        let packet_types = self.packets
+            .values()
+            .map(|info| {
+                let fields = match dedicated_packet_type(&info.name) {
+                    Some(fields) => fields,
+                    None => compiler.compile_fields(&info.fields),
+                };
+                (info.name.clone(), fields)
+            })
+            .collect();
+        self.container_types = container_types;
+        self.packet_types = packet_types;
+    }
+
+    /// Build a parser from minecraft-data's JSON protocol schema (`{ "types": { ... } }`, as in
+    /// data/bedrock/<version>/protocol.json), the format bedrock-protocol compiles proto.yml into
+    /// Packet IDs come from mcpe_packet's name mapper; the schema doesn't say which way packets go, so
+    /// every packet is treated as bound both ways
+    fn from_protocol_json(json: &JsonValue, version: &str) -> Result<Self> {
+        let types = json.get("types").unwrap_or(json).as_object()
+            .ok_or_else(|| anyhow!("Protocol JSON must be an object of types"))?;
+
+        let mut type_aliases = HashMap::new();
+        let mut containers = HashMap::new();
+        let mut packet_fields = HashMap::new();
+        for (name, def) in types {
+            if def == "native" {
+                continue; // Built into the decoder
+            }
+            match Self::json_container_fields(def)? {
+                Some(fields) if name.starts_with("packet_") => {
+                    packet_fields.insert(name.clone(), fields);
+                }
+                Some(fields) => {
+                    containers.insert(name.clone(), fields);
+                }
+                None => {
+                    type_aliases.insert(name.clone(), Self::json_type(def)?);
+                }
+            }
+        }
+
+        // mcpe_packet: [container, [{ name: name, type: [mapper, { type: varint, mappings: { "0x01": login, ... } }] }, ...]]
+        let mappings = types.get("mcpe_packet")
+            .and_then(|packet| packet.pointer("/1/0/type/1/mappings"))
+            .and_then(JsonValue::as_object)
+            .ok_or_else(|| anyhow!("Protocol JSON has no mcpe_packet name mapping to take packet IDs from"))?;
+
+        let mut packets = Vec::new();
+        for (id, name) in mappings {
+            let id = yaml_integer(&YamlValue::String(id.clone()))
+                .and_then(|id| u32::try_from(id).ok())
+                .ok_or_else(|| anyhow!("Invalid packet ID {}", id))?;
+            let name = format!("packet_{}", name.as_str().ok_or_else(|| anyhow!("Invalid packet name for ID {}", id))?);
+            let Some(fields) = packet_fields.remove(&name) else {
+                continue;
+            };
+            packets.push(PacketInfo { id, name, bound: PacketBound::Both, fields });
+        }
+
+        Ok(Self::from_definitions(version, packets, type_aliases, containers))
+    }
+
+    /// Fields of a JSON `["container", [{ name, type } | { anon: true, type }, ...]]`, in the proto.yml shape;
+    /// anonymous fields are named `_`, `_1`, `_2`, ... (their fields belong to the container)
+    fn json_container_fields(def: &JsonValue) -> Result<Option<IndexMap<String, YamlValue>>> {
+        let Some(fields) = def.as_array()
+            .filter(|def| def.first().is_some_and(|kind| kind == "container"))
+            .map(|def| def.get(1).and_then(JsonValue::as_array).map(Vec::as_slice).unwrap_or_default())
+        else {
+            return Ok(None);
+        };
+
+        let mut container = IndexMap::new();
+        let mut anonymous = 0;
+        for field in fields {
+            let field_type = Self::json_type(field.get("type").ok_or_else(|| anyhow!("Container field without a type: {}", field))?)?;
+            let name = match field.get("name").and_then(JsonValue::as_str) {
+                Some(name) => name.to_string(),
+                None if anonymous == 0 => "_".to_string(),
+                None => format!("_{}", anonymous),
+            };
+            if field.get("name").is_none() {
+                anonymous += 1;
+            }
+            container.insert(name, field_type);
+        }
+        Ok(Some(container))
+    }
+
+    /// A JSON type in the shape parse_type reads: the same as proto.yml's, except that containers are lists of fields
+    fn json_type(def: &JsonValue) -> Result<YamlValue> {
+        if let Some(fields) = Self::json_container_fields(def)? {
+            return Ok(YamlValue::Mapping(fields.into_iter().map(|(name, def)| (YamlValue::String(name), def)).collect()));
+        }
+        match def {
+            JsonValue::Array(parts) => match (parts.first().and_then(JsonValue::as_str), parts.get(1)) {
+                // Nested types: [option, T], [encapsulated, { lengthType, type }], [array, { type, ... }],
+                // [switch, { fields: { value: T }, default: T }], [mapper | bitflags, { type, ... }]
+                (Some("option"), Some(inner)) => Ok(serde_yaml::to_value(["option".into(), Self::json_type(inner)?])?),
+                (Some("encapsulated"), Some(options)) => {
+                    let inner = options.get("type").unwrap_or(options);
+                    Ok(serde_yaml::to_value(["encapsulated".into(), Self::json_type(inner)?])?)
+                }
+                (Some(kind), Some(JsonValue::Object(options))) => {
+                    let mut converted = serde_yaml::Mapping::new();
+                    for (key, value) in options {
+                        let value = match key.as_str() {
+                            "type" | "default" => Self::json_type(value)?,
+                            "fields" if kind == "switch" => {
+                                let cases = value.as_object().ok_or_else(|| anyhow!("switch fields must be an object"))?;
+                                YamlValue::Mapping(
+                                    cases.iter()
+                                        .map(|(case, def)| Ok((YamlValue::String(case.clone()), Self::json_type(def)?)))
+                                        .collect::<Result<_>>()?,
+                                )
+                            }
+                            _ => serde_yaml::to_value(value)?,
+                        };
+                        converted.insert(YamlValue::String(key.clone()), value);
+                    }
+                    Ok(serde_yaml::to_value([YamlValue::String(kind.to_string()), YamlValue::Mapping(converted)])?)
+                }
+                _ => Ok(serde_yaml::to_value(def)?),
+            },
+            _ => Ok(serde_yaml::to_value(def)?),
+        }
+    }
+
+    /// A protocol file (.yml or minecraft-data .json), or a version under data/protocol
+    pub fn load(spec: &str) -> Result<Self> {
+        let path = Path::new(spec);
+        if path.is_file() {
+            let version = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or(spec);
+            Self::load_from_file(path, version)
+        } else {
+            Self::new(spec)
+        }
     }
 
     pub fn version(&self) -> &str {
@@ -246,12 +552,107 @@ impl ProtocolParser {
     }
 
     pub fn packet_count(&self) -> usize {
-        self.packet_id_to_info.len()
+        self.packets.len()
     }
 
-    /// Get packet info by ID
+    /// All packet definitions, in no particular order
+    pub fn packets(&self) -> impl Iterator<Item = &PacketInfo> {
+        self.packets.values()
+    }
+
+    /// Named types that aren't containers (`string: ["pstring", ...]`, mappers, ...)
+    pub fn type_aliases(&self) -> &HashMap<String, YamlValue> {
+        &self.type_aliases
+    }
+
+    /// Named containers: type name -> field definitions
+    pub fn containers(&self) -> &HashMap<String, IndexMap<String, YamlValue>> {
+        &self.containers
+    }
+
+    /// Compiled fields of a packet, by its full name (`packet_text`)
+    pub(crate) fn packet_types(&self, name: &str) -> Option<&[CompiledField]> {
+        self.packet_types.get(name).map(Vec::as_slice)
+    }
+
+    /// Compiled fields of a named container
+    pub(crate) fn container_types(&self, name: &str) -> Option<&[CompiledField]> {
+        self.container_types.get(name).map(Vec::as_slice)
+    }
+
+    /// Get packet info by ID, clientbound first when both directions use the ID
+    /// (get_packet_info_for resolves it in a known direction)
     pub fn get_packet_info(&self, id: u32) -> Option<&PacketInfo> {
-        self.packet_id_to_info.get(&id)
+        self.clientbound_ids.get(&id)
+            .or_else(|| self.serverbound_ids.get(&id))
+            .and_then(|name| self.packets.get(name))
+    }
+
+    /// Get packet info by ID among the packets sent in a direction
+    pub fn get_packet_info_for(&self, id: u32, direction: PacketDirection) -> Option<&PacketInfo> {
+        let ids = match direction {
+            PacketDirection::Clientbound => &self.clientbound_ids,
+            PacketDirection::Serverbound => &self.serverbound_ids,
+        };
+        ids.get(&id).and_then(|name| self.packets.get(name))
+    }
+
+    /// Packet info for an ID in its direction, or else in the other direction with a note saying so
+    fn resolve_packet(&self, id: u32, direction: PacketDirection) -> Option<(&PacketInfo, Option<String>)> {
+        if let Some(info) = self.get_packet_info_for(id, direction) {
+            return Some((info, None));
+        }
+        let other = match direction {
+            PacketDirection::Clientbound => PacketDirection::Serverbound,
+            PacketDirection::Serverbound => PacketDirection::Clientbound,
+        };
+        let info = self.get_packet_info_for(id, other)?;
+        let note = format!(
+            "0x{:02x} is not a {} packet ID; decoded as {} {}",
+            id,
+            direction_name(direction),
+            direction_name(other),
+            info.name
+        );
+        Some((info, Some(note)))
+    }
+
+    /// Get packet info by name, with or without the `packet_` prefix
+    pub fn get_packet_info_by_name(&self, name: &str) -> Option<&PacketInfo> {
+        let name = name.strip_prefix("packet_").unwrap_or(name);
+        self.packets.get(&format!("packet_{}", name)).or_else(|| self.packets.get(name))
+    }
+
+    /// Names of all packets, sorted, without the `packet_` prefix (as logs and filters name them)
+    pub fn packet_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.packets.keys().map(|name| short_packet_name(name)).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// ID of a packet (named with or without `packet_`) sent in a direction; None if it isn't sent that way
+    pub fn packet_id(&self, name: &str, direction: PacketDirection) -> Option<u32> {
+        let info = self.get_packet_info_by_name(name)?;
+        let sent = match direction {
+            PacketDirection::Clientbound => info.bound != PacketBound::Server,
+            PacketDirection::Serverbound => info.bound != PacketBound::Client,
+        };
+        sent.then_some(info.id)
+    }
+
+    /// Name (without `packet_`) of the packet with an ID in a direction
+    pub fn packet_name(&self, id: u32, direction: PacketDirection) -> Option<&str> {
+        self.get_packet_info_for(id, direction).map(|info| short_packet_name(&info.name))
+    }
+
+    /// Packet names (without `packet_`) matching `query`, best first (see match_names); `direction` keeps only
+    /// packets sent that way
+    pub fn match_packet_names(&self, query: &str, direction: Option<PacketDirection>) -> Vec<&str> {
+        let names = self
+            .packet_names()
+            .into_iter()
+            .filter(|name| direction.is_none_or(|direction| self.packet_id(name, direction).is_some()));
+        match_names(query, names)
     }
 
     /// Extract packet ID from raw bytes (after RakNet header)
@@ -287,18 +688,48 @@ impl ProtocolParser {
         }
     }
 
+    /// Use other limits than the defaults when decoding packets
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Decode fields of type `type_name` (as written in the definitions, with or without `native:`) with
+    /// `decoder`, e.g. for a native type the crate has no decoder for. It takes precedence over the built-in
+    /// decoders and the definitions, so it can also replace how a type the crate knows is shown. Values of the
+    /// type can't be encoded
+    pub fn register_decoder<F>(&mut self, type_name: &str, decoder: F)
+    where
+        F: Fn(&mut Cursor<&[u8]>) -> Result<JsonValue> + Send + Sync + 'static,
+    {
+        self.custom_decoders.insert(type_name.trim_start_matches("native:").to_string(), Arc::new(decoder));
+        // Fields were resolved without it
+        self.compile_types();
+    }
+
     /// Decode a packet using protocol definitions
     pub fn decode_packet(
         &self,
         data: &[u8],
-        _direction: crate::packet_logger::PacketDirection,
+        direction: PacketDirection,
     ) -> DecodedPacket {
+        // Raw RakNet datagrams (from captures below the game layer) are decoded as such
+        if let Some(decoded) = decode_raknet(data) {
+            return decoded;
+        }
+
         let packet_id = self.extract_packet_id(data);
         
-        let packet_info = packet_id.and_then(|id| self.get_packet_info(id));
+        // Clientbound and serverbound packets can share an ID, so it's resolved in the packet's direction
+        let (packet_info, note) = match packet_id.and_then(|id| self.resolve_packet(id, direction)) {
+            Some((info, note)) => (Some(info), note),
+            None => (None, None),
+        };
         let packet_name = packet_info.map(|info| info.name.clone());
         
-        let mut fields = HashMap::new();
+        let mut fields = IndexMap::new();
+        let mut error = None;
+        let mut spans = IndexMap::new();
         
         // If we have packet info, try to decode fields
         if let Some(info) = packet_info {
@@ -323,78 +754,363 @@ impl ProtocolParser {
                 packet_data,
                 &self.type_aliases,
                 &self.containers,
+                &self.container_types,
+                &self.custom_decoders,
             );
+            decoder.limits = self.limits;
             
-            // Decode fields from packet definition
-            match decoder.decode_fields(&info.fields) {
-                Ok(decoded) => fields = decoded,
-                Err(_e) => {
-                    // On decode error, still return packet ID and name
-                    // (could be due to missing data, wrong format, etc.)
-                }
+            // Decode fields from packet definition, keeping the ones before a failure
+            // (could be due to missing data, wrong format, etc.)
+            decoder.scopes.push(IndexMap::new());
+            let decoded = decoder.decode_fields_in_scope(&self.packet_types[&info.name]);
+            fields = decoder.scopes.pop().unwrap_or_default();
+            if let Err(e) = decoded {
+                let mut decode_error = decoder.error.take().unwrap_or_else(|| DecodeError {
+                    field: String::new(),
+                    offset: decoder.cursor.position() as usize,
+                    expected: String::new(),
+                    remaining: 0,
+                    message: e.to_string(),
+                });
+                decode_error.offset += id_size;
+                error = Some(decode_error);
             }
+            // Spans are recorded as values finish, so a container comes after its fields until sorted
+            spans = decoder.spans
+                .into_iter()
+                .map(|(path, range)| (path, range.start + id_size..range.end + id_size))
+                .collect();
+            spans.sort_by(|_, a: &Range<usize>, _, b: &Range<usize>| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
         }
         
         DecodedPacket {
             packet_id,
             packet_name,
             fields,
+            note,
+            error,
+            spans,
         }
     }
+
+    /// Encode a packet from its fields (in the shape decode_packet returns them): varint packet ID, then the fields
+    pub fn encode_packet(&self, name: &str, fields: &JsonValue) -> Result<Vec<u8>> {
+        let info = self.get_packet_info_by_name(name)
+            .ok_or_else(|| anyhow!("Unknown packet '{}'", name))?;
+        let fields = fields.as_object()
+            .ok_or_else(|| anyhow!("Fields of packet '{}' must be an object", name))?;
+
+        let mut encoder = BinaryEncoder::new(&self.type_aliases, &self.containers, &self.container_types, &self.custom_decoders);
+        encoder.write_varint(info.id as u64);
+        encoder.encode_fields(&self.packet_types[&info.name], fields)
+            .with_context(|| format!("Failed to encode packet '{}'", name))?;
+        Ok(encoder.out)
+    }
+}
+
+/// `names` matching `query`, best first: the exact name, then names starting with it, containing it, and
+/// containing its characters in order (`pai` finds `player_auth_input`). Shorter names come first within each,
+/// and a `packet_` prefix on the query is ignored
+pub fn match_names<'a>(query: &str, names: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let query = query.strip_prefix("packet_").unwrap_or(query).to_lowercase();
+    let mut matches: Vec<(u8, &str)> = names
+        .into_iter()
+        .filter_map(|name| {
+            let lower = name.to_lowercase();
+            let rank = if lower == query {
+                0
+            } else if lower.starts_with(&query) {
+                1
+            } else if lower.contains(&query) {
+                2
+            } else {
+                let mut chars = lower.chars();
+                if !query.chars().all(|c| chars.any(|n| n == c)) {
+                    return None;
+                }
+                3
+            };
+            Some((rank, name))
+        })
+        .collect();
+    matches.sort_by_key(|&(rank, name)| (rank, name.len(), name));
+    matches.into_iter().map(|(_, name)| name).collect()
 }
 
 impl<'a> BinaryDecoder<'a> {
     fn new(
         data: &'a [u8],
         type_aliases: &'a HashMap<String, YamlValue>,
-        containers: &'a HashMap<String, HashMap<String, YamlValue>>,
+        containers: &'a HashMap<String, IndexMap<String, YamlValue>>,
+        container_types: &'a HashMap<String, Vec<CompiledField>>,
+        custom_decoders: &'a HashMap<String, CustomDecoder>,
     ) -> Self {
         Self {
             cursor: Cursor::new(data),
             type_aliases,
             containers,
+            container_types,
+            custom_decoders,
+            scopes: Vec::new(),
+            path: Vec::new(),
+            error: None,
+            spans: IndexMap::new(),
+            limits: DecodeLimits::default(),
+            depth: 0,
+            output_bytes: 0,
         }
     }
     
-    fn decode_fields(
-        &mut self,
-        field_defs: &HashMap<String, YamlValue>,
-    ) -> Result<HashMap<String, JsonValue>> {
-        let mut result = HashMap::new();
-        
-        // Sort fields by key for consistent processing
-        let mut fields: Vec<_> = field_defs.iter().collect();
-        fields.sort_by_key(|(k, _)| *k);
-        
-        for (field_name, field_def) in fields {
-            // Skip conditional fields and metadata fields for now
-            if field_name == "_" || field_name.starts_with("!") {
-                continue;
-            }
-            
-            // Parse the field type
-            let proto_type = self.parse_type(field_def)?;
-            
-            // Decode the value
-            match self.decode_value(&proto_type) {
-                Ok(value) => {
-                    result.insert(field_name.clone(), value);
-                }
-                Err(e) => {
-                    // Continue with other fields on decode error
-                    // Insert error placeholder
-                    result.insert(
-                        field_name.clone(),
+    /// Resolve the types of a container's fields
+    fn compile_fields(&self, field_defs: &IndexMap<String, YamlValue>) -> Vec<CompiledField> {
+        field_defs
+            .iter()
+            .filter(|(name, _)| !name.starts_with('!')) // Metadata
+            .map(|(name, def)| {
+                // `_` is an anonymous switch (or container): the fields it decodes belong to this container
+                // `name?` is an optional field
+                let key = (!is_anonymous(name)).then(|| name.strip_suffix('?').unwrap_or(name).to_string());
+                let proto_type = if key.is_none() {
+                    self.parse_anonymous_field(def)
+                } else if name.ends_with('?') {
+                    self.parse_type(def).map(|inner| ProtoType::Option(Box::new(inner)))
+                } else {
+                    self.parse_type(def)
+                };
+                CompiledField {
+                    name: name.clone(),
+                    key,
+                    proto_type: proto_type.map_err(|e| e.to_string()),
+                    expected: type_text(def),
+                }
+            })
+            .collect()
+    }
+
+    fn decode_fields(&mut self, fields: &[CompiledField]) -> Result<IndexMap<String, JsonValue>> {
+        // Decoded fields go into their own scope, where switches on later fields can see them
+        self.scopes.push(IndexMap::new());
+        let decoded = self.decode_fields_in_scope(fields);
+        let result = self.scopes.pop().unwrap_or_default();
+        decoded.map(|()| result)
+    }
+
+    fn decode_fields_in_scope(&mut self, fields: &[CompiledField]) -> Result<()> {
+        // Fields are encoded one after another, so they must be decoded in declaration order
+        for field in fields {
+            let name = field.key.as_deref();
+            self.path.push(name.unwrap_or(&field.name).to_string());
+            let start = self.cursor.position() as usize;
+            let decoded = match &field.proto_type {
+                Ok(proto_type) => self.decode_value(proto_type).map(|value| (proto_type, value)),
+                Err(message) => Err(anyhow!("{}", message)),
+            };
+
+            match (decoded, name) {
+                (Ok((_, JsonValue::Object(fields))), None) => self.current_scope().extend(fields),
+                (Ok(_), None) => {}
+                (Ok((proto_type, value)), Some(name)) => {
+                    if !matches!(proto_type, ProtoType::Void) && !Self::is_void_switch(proto_type, &value) {
+                        self.current_scope().insert(name.to_string(), value);
+                        self.record_span(start);
+                    }
+                }
+                (Err(e), name) => {
+                    // The innermost field records where decoding broke; enclosing ones keep that
+                    self.record_error(&field.expected, start, &e);
+                    self.current_scope().insert(
+                        name.unwrap_or("_").to_string(),
                         JsonValue::String(format!("[decode_error: {}]", e)),
                     );
-                    break; // Stop decoding on error to avoid cascading failures
+                    // Stop decoding on error to avoid cascading failures
+                    return Err(e);
                 }
             }
+            self.path.pop();
         }
         
-        Ok(result)
+        Ok(())
+    }
+
+    /// The value at the current path was decoded from `start` up to the cursor
+    fn record_span(&mut self, start: usize) {
+        let end = self.cursor.position() as usize;
+        self.spans.insert(self.path_text(), start..end);
+    }
+
+    /// `offset` is where the field starts (a failed read can leave the cursor anywhere after it)
+    fn record_error(&mut self, expected: &str, offset: usize, error: &anyhow::Error) {
+        if self.error.is_some() {
+            return;
+        }
+        self.error = Some(DecodeError {
+            field: self.path_text(),
+            offset,
+            expected: expected.to_string(),
+            remaining: self.cursor.get_ref().len().saturating_sub(offset),
+            message: error.to_string(),
+        });
+    }
+
+    /// The path as text, e.g. `entries[2].key`; anonymous fields merge into their container, so they're left out
+    fn path_text(&self) -> String {
+        let mut text = String::new();
+        for segment in self.path.iter().filter(|segment| !is_anonymous(segment)) {
+            if !text.is_empty() && !segment.starts_with('[') {
+                text.push('.');
+            }
+            text.push_str(segment);
+        }
+        text
+    }
+
+    fn current_scope(&mut self) -> &mut IndexMap<String, JsonValue> {
+        if self.scopes.is_empty() {
+            self.scopes.push(IndexMap::new());
+        }
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// A switch that chose a void case decodes to null and leaves its field out
+    fn is_void_switch(proto_type: &ProtoType, value: &JsonValue) -> bool {
+        matches!(proto_type, ProtoType::Switch(_)) && value.is_null()
+    }
+
+    /// Value of an already decoded field as a switch compares it: strings as is, numbers and bools as text
+    fn compare_value(&self, compare_to: &str) -> Option<String> {
+        let mut name = compare_to;
+        let mut depth = 0;
+        while let Some(rest) = name.strip_prefix("../") {
+            name = rest;
+            depth += 1;
+        }
+        let scope = self.scopes.len().checked_sub(depth + 1).and_then(|index| self.scopes.get(index))?;
+        match scope.get(name)? {
+            JsonValue::String(s) => Some(s.clone()),
+            JsonValue::Number(n) => Some(n.to_string()),
+            JsonValue::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    /// An anonymous field is a proto.yml switch (below), or any other type when loaded from JSON
+    fn parse_anonymous_field(&self, yaml_value: &YamlValue) -> Result<ProtoType> {
+        match yaml_value {
+            YamlValue::Mapping(map)
+                if map.len() == 1 && map.keys().all(|key| key.as_str().is_some_and(|key| key.ends_with('?'))) =>
+            {
+                self.parse_anonymous_switch(yaml_value)
+            }
+            _ => self.parse_type(yaml_value),
+        }
+    }
+
+    /// `_: { field?: { "if a or b": fields, ..., default: fields } }`
+    fn parse_anonymous_switch(&self, yaml_value: &YamlValue) -> Result<ProtoType> {
+        let YamlValue::Mapping(map) = yaml_value else {
+            return Err(anyhow!("Anonymous switch must be a mapping: {:?}", yaml_value));
+        };
+        let (compare_to, cases) = map
+            .iter()
+            .find_map(|(key, cases)| Some((key.as_str()?.strip_suffix('?')?, cases)))
+            .ok_or_else(|| anyhow!("Anonymous switch needs a `field?` key"))?;
+        let YamlValue::Mapping(cases) = cases else {
+            return Err(anyhow!("Cases of switch on {} must be a mapping", compare_to));
+        };
+
+        let mut switch = Switch { compare_to: compare_to.to_string(), cases: Vec::new(), default: None };
+        for (condition, case) in cases {
+            let condition = condition.as_str().ok_or_else(|| anyhow!("Invalid switch case: {:?}", condition))?;
+            let case_type = self.parse_type(case)?;
+            if condition == "default" {
+                switch.default = Some(Box::new(case_type));
+            } else {
+                let values = condition.strip_prefix("if ").unwrap_or(condition);
+                switch.cases.push((values.split(" or ").map(|v| v.trim().to_string()).collect(), case_type));
+            }
+        }
+        Ok(ProtoType::Switch(switch))
+    }
+
+    /// `["switch", { compareTo: field, fields: { value: type, ... }, default: type }]`
+    fn parse_switch(&self, options: Option<&YamlValue>) -> Result<ProtoType> {
+        let options = options.ok_or_else(|| anyhow!("switch requires options"))?;
+        let compare_to = options
+            .get("compareTo")
+            .and_then(YamlValue::as_str)
+            .ok_or_else(|| anyhow!("switch requires compareTo"))?;
+
+        let mut switch = Switch { compare_to: compare_to.to_string(), cases: Vec::new(), default: None };
+        if let Some(YamlValue::Mapping(fields)) = options.get("fields") {
+            for (value, case) in fields {
+                let value = match value {
+                    YamlValue::String(s) => s.clone(),
+                    YamlValue::Number(n) => n.to_string(),
+                    YamlValue::Bool(b) => b.to_string(),
+                    _ => return Err(anyhow!("Invalid switch case: {:?}", value)),
+                };
+                switch.cases.push((vec![value], self.parse_type(case)?));
+            }
+        }
+        if let Some(default) = options.get("default") {
+            switch.default = Some(Box::new(self.parse_type(default)?));
+        }
+        Ok(ProtoType::Switch(switch))
     }
     
+    /// `["mapper", { type: varint, mappings: { 0: survival, "0x10": spectator, ... } }]`
+    fn parse_mapper(&self, options: Option<&YamlValue>) -> Result<ProtoType> {
+        let options = options.ok_or_else(|| anyhow!("mapper requires options"))?;
+        let inner_type = self.parse_type(options.get("type").ok_or_else(|| anyhow!("mapper requires type"))?)?;
+        let YamlValue::Mapping(mappings) = options.get("mappings").ok_or_else(|| anyhow!("mapper requires mappings"))? else {
+            return Err(anyhow!("mapper mappings must be a mapping"));
+        };
+
+        let mut values = Vec::new();
+        for (value, name) in mappings {
+            let value = yaml_integer(value).ok_or_else(|| anyhow!("Invalid mapper value: {:?}", value))?;
+            let name = name.as_str().ok_or_else(|| anyhow!("Invalid mapper name: {:?}", name))?;
+            values.push((value, name.to_string()));
+        }
+        Ok(ProtoType::Mapper(Box::new(inner_type), values))
+    }
+
+    /// `["bitflags", { type: varint, flags: [a, b, ...] }]` (bit i for the i-th name) or `flags: { a: 1, b: 4 }` (masks);
+    /// with `shift: true` the mapping values are bit positions instead of masks
+    fn parse_bitflags(&self, options: Option<&YamlValue>) -> Result<ProtoType> {
+        let options = options.ok_or_else(|| anyhow!("bitflags requires options"))?;
+        let inner_type = self.parse_type(options.get("type").ok_or_else(|| anyhow!("bitflags requires type"))?)?;
+        let shift = options.get("shift").and_then(YamlValue::as_bool).unwrap_or(false);
+
+        let mut flags = Vec::new();
+        match options.get("flags") {
+            Some(YamlValue::Sequence(names)) => {
+                for (bit, name) in names.iter().enumerate() {
+                    let name = name.as_str().ok_or_else(|| anyhow!("Invalid bitflags name: {:?}", name))?;
+                    if bit >= 64 {
+                        return Err(anyhow!("bitflags has more than 64 flags"));
+                    }
+                    flags.push((name.to_string(), 1u64 << bit));
+                }
+            }
+            Some(YamlValue::Mapping(masks)) => {
+                for (name, mask) in masks {
+                    let name = name.as_str().ok_or_else(|| anyhow!("Invalid bitflags name: {:?}", name))?;
+                    let mask = yaml_integer(mask).ok_or_else(|| anyhow!("Invalid bitflags value for {}", name))?;
+                    let mask = if shift {
+                        u32::try_from(mask).ok().and_then(|bit| 1u64.checked_shl(bit))
+                    } else {
+                        u64::try_from(mask).ok()
+                    }
+                    .ok_or_else(|| anyhow!("bitflags value for {} out of range", name))?;
+                    flags.push((name.to_string(), mask));
+                }
+            }
+            _ => return Err(anyhow!("bitflags requires flags")),
+        }
+        Ok(ProtoType::Bitflags(Box::new(inner_type), flags))
+    }
+
     fn parse_type(&self, yaml_value: &YamlValue) -> Result<ProtoType> {
         match yaml_value {
             YamlValue::String(type_str) => {
@@ -430,6 +1146,29 @@ impl<'a> BinaryDecoder<'a> {
                                 };
                                 Ok(ProtoType::Encapsulated(Box::new(inner_type)))
                             }
+                            "option" => {
+                                let inner_type = if seq.len() >= 2 {
+                                    self.parse_type(&seq[1])?
+                                } else {
+                                    return Err(anyhow!("option requires inner type"));
+                                };
+                                Ok(ProtoType::Option(Box::new(inner_type)))
+                            }
+                            "switch" => self.parse_switch(seq.get(1)),
+                            "array" => {
+                                // ["array", { countType: varint, type: T }] or a fixed { count: n }
+                                let options = seq.get(1).ok_or_else(|| anyhow!("array requires options"))?;
+                                let element_type = self.parse_type(options.get("type").ok_or_else(|| anyhow!("array requires type"))?)?;
+                                let count_type = match options.get("count") {
+                                    Some(count) => CountType::Fixed(
+                                        count.as_u64().ok_or_else(|| anyhow!("Unsupported array count {:?}", count))? as usize,
+                                    ),
+                                    None => self.parse_count_type(options)?,
+                                };
+                                Ok(ProtoType::Array(Box::new(element_type), count_type))
+                            }
+                            "mapper" => self.parse_mapper(seq.get(1)),
+                            "bitflags" => self.parse_bitflags(seq.get(1)),
                             _ => Err(anyhow!("Unknown array type: {}", first)),
                         }
                     } else {
@@ -439,12 +1178,32 @@ impl<'a> BinaryDecoder<'a> {
                     Err(anyhow!("Array type must have at least one element"))
                 }
             }
+            YamlValue::Mapping(fields) => {
+                // Inline container: field name -> type
+                let fields = fields
+                    .iter()
+                    .filter_map(|(name, def)| Some((name.as_str()?.to_string(), def.clone())))
+                    .collect();
+                Ok(ProtoType::Fields(self.compile_fields(&fields)))
+            }
             _ => Err(anyhow!("Invalid type definition: {:?}", yaml_value)),
         }
     }
     
     fn parse_type_string(&self, type_str: &str) -> Result<ProtoType> {
-        // Check type aliases first
+        // Registered decoders replace anything else of the name
+        let name = type_str.trim_start_matches("native:");
+        if self.custom_decoders.contains_key(name) {
+            return Ok(ProtoType::Custom(name.to_string()));
+        }
+
+        // NBT and item types are declared as native aliases (or as containers the generic decoder can't follow),
+        // so they're matched before aliases and containers are resolved
+        if let Some(native_type) = Self::native_type(type_str.trim_start_matches("native:")) {
+            return Ok(native_type);
+        }
+
+        // Check type aliases
         if let Some(alias_def) = self.type_aliases.get(type_str) {
             return self.parse_type(alias_def);
         }
@@ -455,16 +1214,7 @@ impl<'a> BinaryDecoder<'a> {
             let count_type_str = &type_str[bracket_pos + 2..];
             
             let element_type = self.parse_type_string(element_type_str)?;
-            let count_type = match count_type_str {
-                "varint" => CountType::VarInt,
-                "zigzag32" => CountType::ZigZag32,
-                "li16" => CountType::LI16,
-                "li32" => CountType::LI32,
-                "li64" => CountType::LI64,
-                "lu16" => CountType::LU16,
-                "lu32" => CountType::LU32,
-                _ => CountType::VarInt, // Default
-            };
+            let count_type = Self::count_type(count_type_str)?;
             
             return Ok(ProtoType::Array(Box::new(element_type), count_type));
         }
@@ -505,6 +1255,7 @@ impl<'a> BinaryDecoder<'a> {
             "vec2f" => Ok(ProtoType::Vec2F),
             "vec3f" => Ok(ProtoType::Vec3F),
             "restBuffer" => Ok(ProtoType::RestBuffer),
+            "void" => Ok(ProtoType::Void),
             s if s.starts_with("native:") => {
                 Ok(ProtoType::Native(s.trim_start_matches("native:").to_string()))
             }
@@ -519,19 +1270,37 @@ impl<'a> BinaryDecoder<'a> {
         }
     }
     
+    fn native_type(name: &str) -> Option<ProtoType> {
+        match name {
+            "nbt" => Some(ProtoType::Nbt(NbtFormat::Network)),
+            "lnbt" => Some(ProtoType::Nbt(NbtFormat::LittleEndian)),
+            "nbtLoop" => Some(ProtoType::NbtLoop),
+            "Item" | "ItemInstance" => Some(ProtoType::Item { stack_id: true }),
+            "ItemLegacy" => Some(ProtoType::Item { stack_id: false }),
+            "StackRequestSlotInfo" => Some(ProtoType::StackRequestSlotInfo),
+            _ => None,
+        }
+    }
+
+    /// Length prefix of a string, buffer or array (`countType`, or after the `[]` in `Entry[]varint`)
+    fn count_type(name: &str) -> Result<CountType> {
+        match name {
+            "varint" => Ok(CountType::VarInt),
+            "zigzag32" => Ok(CountType::ZigZag32),
+            "u8" => Ok(CountType::U8),
+            "li16" => Ok(CountType::LI16),
+            "li32" => Ok(CountType::LI32),
+            "li64" => Ok(CountType::LI64),
+            "lu16" => Ok(CountType::LU16),
+            "lu32" => Ok(CountType::LU32),
+            _ => Err(anyhow!("Unknown count type: {}", name)),
+        }
+    }
+
     fn parse_count_type(&self, yaml_value: &YamlValue) -> Result<CountType> {
         if let YamlValue::Mapping(map) = yaml_value {
             if let Some(YamlValue::String(count_type)) = map.get(&YamlValue::String("countType".to_string())) {
-                match count_type.as_str() {
-                    "varint" => Ok(CountType::VarInt),
-                    "zigzag32" => Ok(CountType::ZigZag32),
-                    "li16" => Ok(CountType::LI16),
-                    "li32" => Ok(CountType::LI32),
-                    "li64" => Ok(CountType::LI64),
-                    "lu16" => Ok(CountType::LU16),
-                    "lu32" => Ok(CountType::LU32),
-                    _ => Err(anyhow!("Unknown count type: {}", count_type)),
-                }
+                Self::count_type(count_type)
             } else {
                 Ok(CountType::VarInt) // Default
             }
@@ -541,6 +1310,26 @@ impl<'a> BinaryDecoder<'a> {
     }
     
     fn decode_value(&mut self, proto_type: &ProtoType) -> Result<JsonValue> {
+        if self.depth >= self.limits.max_depth {
+            return Err(anyhow!("Nesting deeper than the limit of {}", self.limits.max_depth));
+        }
+        self.depth += 1;
+        let value = self.decode_type(proto_type);
+        self.depth -= 1;
+        let value = value?;
+
+        // Nested values are counted as they're decoded, so only a value's own size is added here
+        self.output_bytes += match &value {
+            JsonValue::String(s) => s.len() + 2,
+            _ => 8,
+        };
+        if self.output_bytes > self.limits.max_output_bytes {
+            return Err(anyhow!("Decoded output exceeds the limit of {} bytes", self.limits.max_output_bytes));
+        }
+        Ok(value)
+    }
+
+    fn decode_type(&mut self, proto_type: &ProtoType) -> Result<JsonValue> {
         match proto_type {
             ProtoType::I8 => {
                 let mut buf = [0u8; 1];
@@ -673,29 +1462,29 @@ impl<'a> BinaryDecoder<'a> {
                 Ok(JsonValue::String(value.to_string()))
             }
             ProtoType::String(count_type) => {
-                let len = self.read_count(count_type)?;
-                let mut buf = vec![0u8; len as usize];
+                let len = self.read_length(count_type)?;
+                let mut buf = vec![0u8; len];
                 self.cursor.read_exact(&mut buf)?;
                 let string = String::from_utf8_lossy(&buf).to_string();
                 Ok(JsonValue::String(string))
             }
             ProtoType::LittleString => {
-                let len = self.read_count(&CountType::LI32)?;
-                let mut buf = vec![0u8; len as usize];
+                let len = self.read_length(&CountType::LI32)?;
+                let mut buf = vec![0u8; len];
                 self.cursor.read_exact(&mut buf)?;
                 let string = String::from_utf8_lossy(&buf).to_string();
                 Ok(JsonValue::String(string))
             }
             ProtoType::ShortString => {
-                let len = self.read_count(&CountType::LI16)?;
-                let mut buf = vec![0u8; len as usize];
+                let len = self.read_length(&CountType::LI16)?;
+                let mut buf = vec![0u8; len];
                 self.cursor.read_exact(&mut buf)?;
                 let string = String::from_utf8_lossy(&buf).to_string();
                 Ok(JsonValue::String(string))
             }
             ProtoType::LatinString => {
-                let len = self.read_count(&CountType::VarInt)?;
-                let mut buf = vec![0u8; len as usize];
+                let len = self.read_length(&CountType::VarInt)?;
+                let mut buf = vec![0u8; len];
                 self.cursor.read_exact(&mut buf)?;
                 // Latin1 encoding: each byte is a character
                 let string: String = buf.iter().map(|&b| b as char).collect();
@@ -752,18 +1541,23 @@ impl<'a> BinaryDecoder<'a> {
                 }))
             }
             ProtoType::Buffer(count_type) => {
-                let len = self.read_count(count_type)?;
-                let mut buf = vec![0u8; len as usize];
+                let len = self.read_length(count_type)?;
+                let mut buf = vec![0u8; len];
                 self.cursor.read_exact(&mut buf)?;
                 // Return as hex string for readability
                 let hex = buf.iter().map(|b| format!("{:02x}", b)).collect::<String>();
                 Ok(JsonValue::String(format!("0x{}", hex)))
             }
             ProtoType::Array(element_type, count_type) => {
-                let count = self.read_count(count_type)?;
+                let count = self.read_array_count(count_type)?;
                 let mut array = Vec::new();
-                for _ in 0..count {
+                for index in 0..count {
+                    // Left on the path if the element fails, for the error
+                    self.path.push(format!("[{}]", index));
+                    let start = self.cursor.position() as usize;
                     array.push(self.decode_value(element_type)?);
+                    self.record_span(start);
+                    self.path.pop();
                 }
                 Ok(JsonValue::Array(array))
             }
@@ -782,22 +1576,76 @@ impl<'a> BinaryDecoder<'a> {
                 Ok(value)
             }
             ProtoType::Container(name) => {
-                if let Some(container_fields) = self.containers.get(name) {
+                if let Some(container_fields) = self.container_types.get(name) {
                     let fields_map = self.decode_fields(container_fields)?;
                     Ok(JsonValue::Object(fields_map.into_iter().collect()))
                 } else {
                     Err(anyhow!("Container '{}' not found", name))
                 }
             }
+            ProtoType::Fields(fields) => {
+                let fields_map = self.decode_fields(fields)?;
+                Ok(JsonValue::Object(fields_map.into_iter().collect()))
+            }
+            ProtoType::Option(inner_type) => {
+                let mut buf = [0u8; 1];
+                self.cursor.read_exact(&mut buf)?;
+                if buf[0] == 0 {
+                    Ok(JsonValue::Null)
+                } else {
+                    self.decode_value(inner_type)
+                }
+            }
+            ProtoType::Switch(switch) => {
+                let value = self.compare_value(&switch.compare_to)
+                    .ok_or_else(|| anyhow!("switch: field '{}' has not been decoded", switch.compare_to))?;
+                let case_type = switch.cases
+                    .iter()
+                    .find(|(values, _)| values.contains(&value))
+                    .map(|(_, case_type)| case_type)
+                    .or(switch.default.as_deref());
+                match case_type {
+                    Some(case_type) => self.decode_value(case_type),
+                    None => Ok(JsonValue::Null),
+                }
+            }
+            ProtoType::Void => Ok(JsonValue::Null),
+            ProtoType::Mapper(inner_type, mappings) => {
+                let value = self.decode_value(inner_type)?;
+                let name = json_integer(&value)
+                    .and_then(|number| mappings.iter().find(|(mapped, _)| *mapped == number))
+                    .map(|(_, name)| JsonValue::String(name.clone()));
+                Ok(name.unwrap_or(value))
+            }
+            ProtoType::Bitflags(inner_type, flags) => {
+                let value = self.decode_value(inner_type)?;
+                let bits = json_integer(&value)
+                    .ok_or_else(|| anyhow!("bitflags: {} is not an integer", value))? as u64;
+                let mut result: serde_json::Map<String, JsonValue> = flags
+                    .iter()
+                    .map(|(name, mask)| (name.clone(), JsonValue::Bool(bits & mask == *mask)))
+                    .collect();
+                result.insert("_value".to_string(), value);
+                Ok(JsonValue::Object(result))
+            }
+            ProtoType::Nbt(format) => read_nbt(&mut self.cursor, *format),
+            ProtoType::NbtLoop => read_nbt_loop(&mut self.cursor),
             ProtoType::Native(_) => {
-                // For native types, just read as hex string
-                // In a full implementation, we'd parse NBT, etc.
+                // Other native types are read as a hex string
                 let remaining = self.cursor.get_ref().len() - self.cursor.position() as usize;
                 let mut buf = vec![0u8; remaining.min(1024)]; // Limit to 1KB
                 self.cursor.read_exact(&mut buf)?;
                 let hex = buf.iter().map(|b| format!("{:02x}", b)).collect::<String>();
                 Ok(JsonValue::String(format!("[native: 0x{}]", hex)))
             }
+            ProtoType::Custom(name) => {
+                let decoder = self.custom_decoders.get(name)
+                    .ok_or_else(|| anyhow!("No decoder registered for {}", name))?;
+                decoder(&mut self.cursor).with_context(|| format!("Custom decoder for {} failed", name))
+            }
+            ProtoType::AvailableCommands => self.decode_available_commands(),
+            ProtoType::Item { stack_id } => self.decode_item(*stack_id),
+            ProtoType::StackRequestSlotInfo => self.decode_stack_request_slot_info(),
             ProtoType::RestBuffer => {
                 let remaining = self.cursor.get_ref().len() - self.cursor.position() as usize;
                 let mut buf = vec![0u8; remaining];
@@ -808,36 +1656,260 @@ impl<'a> BinaryDecoder<'a> {
         }
     }
     
-    fn read_varint32(&mut self) -> Result<u32> {
-        let mut result: u32 = 0;
-        let mut shift = 0;
-        
-        for _ in 0..5 {
-            let mut buf = [0u8; 1];
-            self.cursor.read_exact(&mut buf)?;
-            let byte = buf[0];
-            
-            result |= ((byte & 0x7F) as u32) << shift;
-            shift += 7;
-            
-            if (byte & 0x80) == 0 {
-                return Ok(result);
+    /// Item (with a stack network ID) or ItemLegacy; an empty slot (network ID 0) is just `{network_id: 0}`
+    fn decode_item(&mut self, with_stack_id: bool) -> Result<JsonValue> {
+        let mut item = serde_json::Map::new();
+        let network_id = self.decode_value(&ProtoType::ZigZag32)?;
+        let empty = json_integer(&network_id) == Some(0);
+        item.insert("network_id".to_string(), network_id);
+        if empty {
+            return Ok(JsonValue::Object(item));
+        }
+        item.insert("count".to_string(), self.decode_value(&ProtoType::LU16)?);
+        item.insert("metadata".to_string(), self.decode_value(&ProtoType::VarInt32)?);
+        if with_stack_id {
+            let has_stack_id = self.decode_value(&ProtoType::U8)?;
+            let has = json_integer(&has_stack_id) != Some(0);
+            item.insert("has_stack_id".to_string(), has_stack_id);
+            if has {
+                item.insert("stack_id".to_string(), self.decode_value(&ProtoType::ZigZag32)?);
             }
         }
-        
-        Err(anyhow!("Varint32 overflow"))
+        item.insert("block_runtime_id".to_string(), self.decode_value(&ProtoType::ZigZag32)?);
+        item.insert("extra".to_string(), self.decode_item_extra()?);
+        Ok(JsonValue::Object(item))
     }
-    
-    fn read_varint64(&mut self) -> Result<u64> {
-        let mut result: u64 = 0;
-        let mut shift = 0;
-        
-        for _ in 0..10 {
-            let mut buf = [0u8; 1];
-            self.cursor.read_exact(&mut buf)?;
-            let byte = buf[0];
-            
-            result |= ((byte & 0x7F) as u64) << shift;
+
+    /// An item's user data: a length-prefixed block with its NBT and the blocks it can be placed on and destroy.
+    /// Shields also have a blocking tick; which ID is the shield is only known from start_game, so it's told
+    /// apart by the 8 bytes it leaves at the end
+    fn decode_item_extra(&mut self) -> Result<JsonValue> {
+        let len = self.read_length(&CountType::VarInt)?;
+        let start = self.cursor.position() as usize;
+        let mut extra = serde_json::Map::new();
+        // 0xffff and a version byte before the NBT, or 0 for none
+        let has_nbt = json_integer(&self.decode_value(&ProtoType::LU16)?) == Some(0xffff);
+        extra.insert("has_nbt".to_string(), JsonValue::Bool(has_nbt));
+        if has_nbt {
+            let version = self.decode_value(&ProtoType::U8)?;
+            let nbt = self.decode_value(&ProtoType::Nbt(NbtFormat::LittleEndian))?;
+            extra.insert("nbt".to_string(), serde_json::json!({ "version": version, "nbt": nbt }));
+        }
+        let blocks = ProtoType::Array(Box::new(ProtoType::ShortString), CountType::LI32);
+        extra.insert("can_place_on".to_string(), self.decode_value(&blocks)?);
+        extra.insert("can_destroy".to_string(), self.decode_value(&blocks)?);
+        let read = self.cursor.position() as usize - start;
+        if len.saturating_sub(read) == 8 {
+            extra.insert("blocking_tick".to_string(), self.decode_value(&ProtoType::LI64)?);
+        }
+
+        let read = self.cursor.position() as usize - start;
+        if read != len {
+            return Err(anyhow!("Item extra data is {} bytes, but {} were read", len, read));
+        }
+        Ok(JsonValue::Object(extra))
+    }
+
+    /// Slot of an item stack request: `{slot_type: {container_id, dynamic_container_id}, slot, stack_id}`
+    fn decode_stack_request_slot_info(&mut self) -> Result<JsonValue> {
+        let container_id = self.decode_value(&ProtoType::U8)?;
+        let dynamic_container_id = self.decode_value(&ProtoType::Option(Box::new(ProtoType::LU32)))?;
+        let slot = self.decode_value(&ProtoType::U8)?;
+        let stack_id = self.decode_value(&ProtoType::ZigZag32)?;
+        Ok(serde_json::json!({
+            "slot_type": { "container_id": container_id, "dynamic_container_id": dynamic_container_id },
+            "slot": slot,
+            "stack_id": stack_id,
+        }))
+    }
+
+    /// AvailableCommands, whose parts refer to each other by index: enum values are indexes into one palette
+    /// of strings (as wide as its length needs), and command parameters point into the enums, soft enums or
+    /// suffixes depending on their flags. Numbers are kept as sent, with what they refer to alongside them
+    fn decode_available_commands(&mut self) -> Result<JsonValue> {
+        let enum_values = self.read_list("enum_values", Self::read_string)?;
+        let chained_subcommand_values = self.read_list("chained_subcommand_values", Self::read_string)?;
+        let suffixes = self.read_list("suffixes", Self::read_string)?;
+        let index_type = match enum_values.len() {
+            0..=0xff => ProtoType::U8,
+            0x100..=0xffff => ProtoType::LU16,
+            _ => ProtoType::LU32,
+        };
+        let enums = self.read_list("enums", |decoder| {
+            let name = decoder.read_string()?;
+            let values = decoder.read_list("values", |decoder| {
+                let index = decoder.decode_value(&index_type)?;
+                Ok(palette_entry(&enum_values, &index))
+            })?;
+            Ok(serde_json::json!({ "name": name, "values": values }))
+        })?;
+        let chained_subcommands = self.read_list("chained_subcommands", |decoder| {
+            let name = decoder.read_string()?;
+            let values = decoder.read_list("values", |decoder| {
+                let index = decoder.decode_value(&ProtoType::LU16)?;
+                let value = decoder.decode_value(&ProtoType::LU16)?;
+                Ok(serde_json::json!({
+                    "index": index,
+                    "value": value,
+                    "name": palette_entry(&chained_subcommand_values, &index),
+                }))
+            })?;
+            Ok(serde_json::json!({ "name": name, "values": values }))
+        })?;
+        let mut command_data = self.read_list("command_data", |decoder| {
+            let mut command = serde_json::Map::new();
+            command.insert("name".to_string(), JsonValue::String(decoder.read_string()?));
+            command.insert("description".to_string(), JsonValue::String(decoder.read_string()?));
+            command.insert("flags".to_string(), decoder.decode_value(&ProtoType::LU16)?);
+            command.insert("permission_level".to_string(), decoder.decode_value(&ProtoType::U8)?);
+            command.insert("alias".to_string(), decoder.decode_value(&ProtoType::LI32)?);
+            let offsets = decoder.read_list("chained_subcommand_offsets", |decoder| decoder.decode_value(&ProtoType::LU16))?;
+            command.insert("chained_subcommand_offsets".to_string(), JsonValue::Array(offsets));
+            let overloads = decoder.read_list("overloads", |decoder| {
+                let chaining = decoder.decode_value(&ProtoType::Bool)?;
+                let parameters = decoder.read_list("parameters", |decoder| {
+                    let parameter_name = decoder.read_string()?;
+                    let value_type = decoder.decode_value(&ProtoType::LU16)?;
+                    let enum_type = decoder.decode_value(&ProtoType::LU16)?;
+                    let optional = decoder.decode_value(&ProtoType::Bool)?;
+                    let options = decoder.decode_value(&ProtoType::U8)?;
+                    let options_bits = json_integer(&options).unwrap_or(0);
+                    Ok(serde_json::json!({
+                        "parameter_name": parameter_name,
+                        "value_type": value_type,
+                        "enum_type": enum_type,
+                        "optional": optional,
+                        "options": {
+                            "collapse_enum": options_bits & 1 != 0,
+                            "has_semantic_constraint": options_bits & 2 != 0,
+                            "as_chained_command": options_bits & 4 != 0,
+                            "_value": options,
+                        },
+                    }))
+                })?;
+                Ok(serde_json::json!({ "chaining": chaining, "parameters": parameters }))
+            })?;
+            command.insert("overloads".to_string(), JsonValue::Array(overloads));
+            Ok(JsonValue::Object(command))
+        })?;
+        let dynamic_enums = self.read_list("dynamic_enums", |decoder| {
+            let name = decoder.read_string()?;
+            let values = decoder.read_list("values", Self::read_string)?;
+            Ok(serde_json::json!({ "name": name, "values": values }))
+        })?;
+        let enum_constraints = self.read_list("enum_constraints", |decoder| {
+            let value_index = decoder.decode_value(&ProtoType::LI32)?;
+            let enum_index = decoder.decode_value(&ProtoType::LI32)?;
+            let constraints = decoder.read_list("constraints", |decoder| {
+                let constraint = decoder.decode_value(&ProtoType::U8)?;
+                Ok(match json_integer(&constraint) {
+                    Some(0) => JsonValue::from("cheats_enabled"),
+                    Some(1) => JsonValue::from("operator_permissions"),
+                    Some(2) => JsonValue::from("host_permissions"),
+                    _ => constraint,
+                })
+            })?;
+            Ok(serde_json::json!({
+                "value_index": value_index,
+                "enum_index": enum_index,
+                "constraints": constraints,
+                "value": palette_entry(&enum_values, &value_index),
+                "enum": palette_entry(&enums, &enum_index)["name"],
+            }))
+        })?;
+
+        // Soft enums come after the commands, so references are resolved once everything is read
+        for command in command_data.iter_mut() {
+            let alias = palette_entry(&enums, &command["alias"])["name"].clone();
+            let chained: Vec<JsonValue> = command["chained_subcommand_offsets"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|offset| palette_entry(&chained_subcommands, offset)["name"].clone())
+                .collect();
+            command["alias_enum"] = alias;
+            command["chained_subcommand_names"] = JsonValue::Array(chained);
+            let Some(overloads) = command["overloads"].as_array_mut() else { continue };
+            for parameter in overloads.iter_mut().filter_map(|overload| overload["parameters"].as_array_mut()).flatten() {
+                let value_type = &parameter["value_type"];
+                let flags = json_integer(&parameter["enum_type"]).unwrap_or(0);
+                let (key, target) = if flags & 0x100 != 0 {
+                    ("suffix", palette_entry(&suffixes, value_type))
+                } else if flags & 0x400 != 0 {
+                    ("soft_enum", palette_entry(&dynamic_enums, value_type)["name"].clone())
+                } else if flags & 0x20 != 0 {
+                    ("enum", palette_entry(&enums, value_type)["name"].clone())
+                } else {
+                    ("type", json_integer(value_type).and_then(command_arg_type).map(JsonValue::from).unwrap_or_default())
+                };
+                parameter[key] = target;
+            }
+        }
+
+        Ok(serde_json::json!({
+            "values_len": enum_values.len(),
+            "enum_values": enum_values,
+            "chained_subcommand_values": chained_subcommand_values,
+            "suffixes": suffixes,
+            "enums": enums,
+            "chained_subcommands": chained_subcommands,
+            "command_data": command_data,
+            "dynamic_enums": dynamic_enums,
+            "enum_constraints": enum_constraints,
+        }))
+    }
+
+    /// A varint-counted list, with `name` and each index on the path for errors
+    fn read_list<T>(&mut self, name: &str, mut read: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        self.path.push(name.to_string());
+        let count = self.read_array_count(&CountType::VarInt)?;
+        let mut items = Vec::new();
+        for index in 0..count {
+            self.path.push(format!("[{}]", index));
+            items.push(read(self)?);
+            self.path.pop();
+        }
+        self.path.pop();
+        Ok(items)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        match self.decode_value(&ProtoType::String(CountType::VarInt))? {
+            JsonValue::String(string) => Ok(string),
+            value => Ok(value.to_string()),
+        }
+    }
+
+    fn read_varint32(&mut self) -> Result<u32> {
+        let mut result: u32 = 0;
+        let mut shift = 0;
+        
+        for _ in 0..5 {
+            let mut buf = [0u8; 1];
+            self.cursor.read_exact(&mut buf)?;
+            let byte = buf[0];
+            
+            result |= ((byte & 0x7F) as u32) << shift;
+            shift += 7;
+            
+            if (byte & 0x80) == 0 {
+                return Ok(result);
+            }
+        }
+        
+        Err(anyhow!("Varint32 overflow"))
+    }
+    
+    fn read_varint64(&mut self) -> Result<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        
+        for _ in 0..10 {
+            let mut buf = [0u8; 1];
+            self.cursor.read_exact(&mut buf)?;
+            let byte = buf[0];
+            
+            result |= ((byte & 0x7F) as u64) << shift;
             shift += 7;
             
             if (byte & 0x80) == 0 {
@@ -848,6 +1920,27 @@ impl<'a> BinaryDecoder<'a> {
         Err(anyhow!("Varint64 overflow"))
     }
     
+    fn read_array_count(&mut self, count_type: &CountType) -> Result<usize> {
+        let count = self.read_count(count_type)? as usize;
+        if count > self.limits.max_array_length {
+            return Err(anyhow!("Array length {} exceeds the limit of {}", count, self.limits.max_array_length));
+        }
+        Ok(count)
+    }
+
+    /// Length prefix of a string or buffer, checked before anything is allocated for it
+    fn read_length(&mut self, count_type: &CountType) -> Result<usize> {
+        let len = self.read_count(count_type)? as usize;
+        if len > self.limits.max_string_length {
+            return Err(anyhow!("Length {} exceeds the limit of {} bytes", len, self.limits.max_string_length));
+        }
+        let remaining = self.cursor.get_ref().len().saturating_sub(self.cursor.position() as usize);
+        if len > remaining {
+            return Err(anyhow!("Length {} exceeds the {} bytes left", len, remaining));
+        }
+        Ok(len)
+    }
+
     fn read_count(&mut self, count_type: &CountType) -> Result<u32> {
         match count_type {
             CountType::VarInt => self.read_varint32(),
@@ -855,6 +1948,11 @@ impl<'a> BinaryDecoder<'a> {
                 let value = self.read_varint32()?;
                 Ok(((value >> 1) as i32 ^ (-((value & 1) as i32))) as u32)
             }
+            CountType::U8 => {
+                let mut buf = [0u8; 1];
+                self.cursor.read_exact(&mut buf)?;
+                Ok(buf[0] as u32)
+            }
             CountType::LI16 => {
                 let mut buf = [0u8; 2];
                 self.cursor.read_exact(&mut buf)?;
@@ -885,10 +1983,800 @@ impl<'a> BinaryDecoder<'a> {
     }
 }
 
+// The inverse of BinaryDecoder: writes values in the shape decode_value returns them
+struct BinaryEncoder<'a> {
+    out: Vec<u8>,
+    types: BinaryDecoder<'a>, // Parses type definitions and holds the containers being encoded, for switches
+}
+
+impl<'a> BinaryEncoder<'a> {
+    fn new(
+        type_aliases: &'a HashMap<String, YamlValue>,
+        containers: &'a HashMap<String, IndexMap<String, YamlValue>>,
+        container_types: &'a HashMap<String, Vec<CompiledField>>,
+        custom_decoders: &'a HashMap<String, CustomDecoder>,
+    ) -> Self {
+        Self {
+            out: Vec::new(),
+            types: BinaryDecoder::new(&[], type_aliases, containers, container_types, custom_decoders),
+        }
+    }
+
+    fn encode_fields(&mut self, fields: &[CompiledField], values: &serde_json::Map<String, JsonValue>) -> Result<()> {
+        // Switches look up sibling values in the container being encoded
+        self.types.scopes.push(values.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+        let encoded = self.encode_fields_in_scope(fields, values);
+        self.types.scopes.pop();
+        encoded
+    }
+
+    fn encode_fields_in_scope(&mut self, fields: &[CompiledField], values: &serde_json::Map<String, JsonValue>) -> Result<()> {
+        for field in fields {
+            let proto_type = field.proto_type.as_ref().map_err(|message| anyhow!("{}", message))?;
+            match &field.key {
+                // The fields of an anonymous switch's case are in this container
+                None => self.encode_value(proto_type, &JsonValue::Object(values.clone()))
+                    .with_context(|| format!("Failed to encode anonymous field '{}'", field.name))?,
+                Some(name) => {
+                    let value = values.get(name).unwrap_or(&JsonValue::Null);
+                    self.encode_value(proto_type, value)
+                        .with_context(|| format!("Failed to encode field '{}'", name))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn integer<T: TryFrom<i128>>(value: &JsonValue, type_name: &str) -> Result<T> {
+        json_integer(value)
+            .and_then(|n| T::try_from(n).ok())
+            .ok_or_else(|| anyhow!("Expected {} value, got {}", type_name, value))
+    }
+
+    fn float(value: &JsonValue) -> Result<f64> {
+        value.as_f64().ok_or_else(|| anyhow!("Expected a number, got {}", value))
+    }
+
+    fn string(value: &JsonValue) -> Result<&str> {
+        value.as_str().ok_or_else(|| anyhow!("Expected a string, got {}", value))
+    }
+
+    /// Buffers are "0x"-prefixed hex strings (or arrays of bytes)
+    fn bytes(value: &JsonValue) -> Result<Vec<u8>> {
+        match value {
+            JsonValue::String(hex) => {
+                let hex = hex.strip_prefix("0x").unwrap_or(hex);
+                if hex.len() % 2 != 0 {
+                    return Err(anyhow!("Odd-length hex string"));
+                }
+                (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| anyhow!("Invalid hex string")))
+                    .collect()
+            }
+            JsonValue::Array(bytes) => bytes.iter().map(|b| Self::integer(b, "byte")).collect(),
+            _ => Err(anyhow!("Expected a hex string, got {}", value)),
+        }
+    }
+
+    fn encode_item(&mut self, value: &JsonValue, with_stack_id: bool) -> Result<()> {
+        self.encode_value(&ProtoType::ZigZag32, &value["network_id"]).context("Failed to encode network_id")?;
+        if json_integer(&value["network_id"]) == Some(0) {
+            return Ok(());
+        }
+        self.encode_value(&ProtoType::LU16, &value["count"]).context("Failed to encode count")?;
+        self.encode_value(&ProtoType::VarInt32, &value["metadata"]).context("Failed to encode metadata")?;
+        if with_stack_id {
+            let stack_id = &value["stack_id"];
+            self.out.push(!stack_id.is_null() as u8);
+            if !stack_id.is_null() {
+                self.encode_value(&ProtoType::ZigZag32, stack_id).context("Failed to encode stack_id")?;
+            }
+        }
+        self.encode_value(&ProtoType::ZigZag32, &value["block_runtime_id"]).context("Failed to encode block_runtime_id")?;
+
+        // Length-prefixed, like an encapsulated value
+        let extra = &value["extra"];
+        let start = self.out.len();
+        match &extra["nbt"] {
+            JsonValue::Null => self.out.extend_from_slice(&0u16.to_le_bytes()),
+            nbt => {
+                self.out.extend_from_slice(&0xffffu16.to_le_bytes());
+                self.encode_value(&ProtoType::U8, &nbt["version"]).context("Failed to encode nbt version")?;
+                self.encode_value(&ProtoType::Nbt(NbtFormat::LittleEndian), &nbt["nbt"]).context("Failed to encode nbt")?;
+            }
+        }
+        let blocks = ProtoType::Array(Box::new(ProtoType::ShortString), CountType::LI32);
+        for field in ["can_place_on", "can_destroy"] {
+            let list = match &extra[field] {
+                JsonValue::Null => JsonValue::Array(Vec::new()),
+                list => list.clone(),
+            };
+            self.encode_value(&blocks, &list).with_context(|| format!("Failed to encode {}", field))?;
+        }
+        if !extra["blocking_tick"].is_null() {
+            self.encode_value(&ProtoType::LI64, &extra["blocking_tick"]).context("Failed to encode blocking_tick")?;
+        }
+        let inner = self.out.split_off(start);
+        self.write_varint(inner.len() as u64);
+        self.out.extend_from_slice(&inner);
+        Ok(())
+    }
+
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push((value as u8 & 0x7F) | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn write_count(&mut self, count_type: &CountType, count: usize) -> Result<()> {
+        match count_type {
+            CountType::VarInt => self.write_varint(count as u64),
+            CountType::ZigZag32 => self.write_varint(count as u64 * 2),
+            CountType::U8 => self.out.push(u8::try_from(count)?),
+            CountType::LI16 => self.out.extend_from_slice(&i16::try_from(count)?.to_le_bytes()),
+            CountType::LI32 => self.out.extend_from_slice(&i32::try_from(count)?.to_le_bytes()),
+            CountType::LI64 => self.out.extend_from_slice(&(count as i64).to_le_bytes()),
+            CountType::LU16 => self.out.extend_from_slice(&u16::try_from(count)?.to_le_bytes()),
+            CountType::LU32 => self.out.extend_from_slice(&u32::try_from(count)?.to_le_bytes()),
+            CountType::Fixed(n) => {
+                if count != *n {
+                    return Err(anyhow!("Expected {} elements, got {}", n, count));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_value(&mut self, proto_type: &ProtoType, value: &JsonValue) -> Result<()> {
+        match proto_type {
+            ProtoType::I8 => self.out.push(Self::integer::<i8>(value, "i8")? as u8),
+            ProtoType::U8 => self.out.push(Self::integer::<u8>(value, "u8")?),
+            ProtoType::I16 | ProtoType::LI16 => self.out.extend_from_slice(&Self::integer::<i16>(value, "i16")?.to_le_bytes()),
+            ProtoType::U16 | ProtoType::LU16 => self.out.extend_from_slice(&Self::integer::<u16>(value, "u16")?.to_le_bytes()),
+            ProtoType::I32 | ProtoType::LI32 => self.out.extend_from_slice(&Self::integer::<i32>(value, "i32")?.to_le_bytes()),
+            ProtoType::U32 | ProtoType::LU32 => self.out.extend_from_slice(&Self::integer::<u32>(value, "u32")?.to_le_bytes()),
+            ProtoType::I64 | ProtoType::LI64 => self.out.extend_from_slice(&Self::integer::<i64>(value, "i64")?.to_le_bytes()),
+            ProtoType::U64 | ProtoType::LU64 => self.out.extend_from_slice(&Self::integer::<u64>(value, "u64")?.to_le_bytes()),
+            ProtoType::F32 => self.out.extend_from_slice(&(Self::float(value)? as f32).to_le_bytes()),
+            ProtoType::F64 => self.out.extend_from_slice(&Self::float(value)?.to_le_bytes()),
+            ProtoType::Bool => {
+                let value = value.as_bool().ok_or_else(|| anyhow!("Expected a bool, got {}", value))?;
+                self.out.push(value as u8);
+            }
+            ProtoType::VarInt32 => {
+                let value = Self::integer::<u32>(value, "varint").or_else(|_| Self::integer::<i32>(value, "varint").map(|v| v as u32))?;
+                self.write_varint(value as u64);
+            }
+            ProtoType::VarInt64 => {
+                let value = Self::integer::<u64>(value, "varint64").or_else(|_| Self::integer::<i64>(value, "varint64").map(|v| v as u64))?;
+                self.write_varint(value);
+            }
+            ProtoType::ZigZag32 => {
+                let value = Self::integer::<i32>(value, "zigzag32")?;
+                self.write_varint(((value << 1) ^ (value >> 31)) as u32 as u64);
+            }
+            ProtoType::ZigZag64 => {
+                let value = Self::integer::<i64>(value, "zigzag64")?;
+                self.write_varint(((value << 1) ^ (value >> 63)) as u64);
+            }
+            ProtoType::String(count_type) => {
+                let string = Self::string(value)?;
+                self.write_count(count_type, string.len())?;
+                self.out.extend_from_slice(string.as_bytes());
+            }
+            ProtoType::LittleString => self.encode_value(&ProtoType::String(CountType::LI32), value)?,
+            ProtoType::ShortString => self.encode_value(&ProtoType::String(CountType::LI16), value)?,
+            ProtoType::LatinString => {
+                let bytes = Self::string(value)?
+                    .chars()
+                    .map(|c| u8::try_from(c).map_err(|_| anyhow!("'{}' is not a latin1 character", c)))
+                    .collect::<Result<Vec<_>>>()?;
+                self.write_varint(bytes.len() as u64);
+                self.out.extend_from_slice(&bytes);
+            }
+            ProtoType::UUID => {
+                let bytes = Self::bytes(&JsonValue::String(Self::string(value)?.replace('-', "")))?;
+                if bytes.len() != 16 {
+                    return Err(anyhow!("Invalid UUID {}", value));
+                }
+                self.out.extend_from_slice(&bytes);
+            }
+            ProtoType::Vec2F | ProtoType::Vec3F => {
+                let axes: &[&str] = if matches!(proto_type, ProtoType::Vec2F) { &["x", "y"] } else { &["x", "y", "z"] };
+                for axis in axes {
+                    self.encode_value(&ProtoType::F32, &value[*axis])
+                        .with_context(|| format!("Failed to encode {}", axis))?;
+                }
+            }
+            ProtoType::Buffer(count_type) => {
+                let bytes = Self::bytes(value)?;
+                self.write_count(count_type, bytes.len())?;
+                self.out.extend_from_slice(&bytes);
+            }
+            ProtoType::Array(element_type, count_type) => {
+                let elements = value.as_array().ok_or_else(|| anyhow!("Expected an array, got {}", value))?;
+                self.write_count(count_type, elements.len())?;
+                for (index, element) in elements.iter().enumerate() {
+                    self.encode_value(element_type, element)
+                        .with_context(|| format!("Failed to encode element {}", index))?;
+                }
+            }
+            ProtoType::Encapsulated(inner_type) => {
+                // Length-prefixed, so the inner value is encoded on its own first
+                let start = self.out.len();
+                self.encode_value(inner_type, value)?;
+                let inner = self.out.split_off(start);
+                self.write_varint(inner.len() as u64);
+                self.out.extend_from_slice(&inner);
+            }
+            ProtoType::Container(name) => {
+                let container_fields = self.types.container_types.get(name)
+                    .ok_or_else(|| anyhow!("Container '{}' not found", name))?;
+                let values = value.as_object().ok_or_else(|| anyhow!("Expected an object, got {}", value))?;
+                self.encode_fields(container_fields, values)?;
+            }
+            ProtoType::Fields(fields) => {
+                let values = value.as_object().ok_or_else(|| anyhow!("Expected an object, got {}", value))?;
+                self.encode_fields(fields, values)?;
+            }
+            ProtoType::Option(inner_type) => {
+                if value.is_null() {
+                    self.out.push(0);
+                } else {
+                    self.out.push(1);
+                    self.encode_value(inner_type, value)?;
+                }
+            }
+            ProtoType::Switch(switch) => {
+                let compare = self.types.compare_value(&switch.compare_to)
+                    .ok_or_else(|| anyhow!("switch: field '{}' is missing", switch.compare_to))?;
+                let case_type = switch.cases
+                    .iter()
+                    .find(|(values, _)| values.contains(&compare))
+                    .map(|(_, case_type)| case_type)
+                    .or(switch.default.as_deref());
+                if let Some(case_type) = case_type {
+                    self.encode_value(case_type, value)?;
+                }
+            }
+            ProtoType::Void => {}
+            ProtoType::Mapper(inner_type, mappings) => {
+                // Names back to their values; numbers are written as they are
+                let number = match value {
+                    JsonValue::String(name) => mappings.iter().find(|(_, mapped)| mapped == name).map(|(n, _)| *n),
+                    _ => None,
+                };
+                match number {
+                    Some(number) => self.encode_value(inner_type, &JsonValue::String(number.to_string()))?,
+                    None => self.encode_value(inner_type, value)?,
+                }
+            }
+            ProtoType::Bitflags(inner_type, flags) => {
+                // Named flags are set from their booleans; other bits are kept from _value
+                let bits = match value {
+                    JsonValue::Object(map) => {
+                        let mut bits = map.get("_value").and_then(json_integer).unwrap_or(0) as u64;
+                        for (name, mask) in flags {
+                            match map.get(name).and_then(JsonValue::as_bool) {
+                                Some(true) => bits |= mask,
+                                Some(false) => bits &= !mask,
+                                None => {}
+                            }
+                        }
+                        bits
+                    }
+                    _ => Self::integer::<i128>(value, "bitflags")? as u64,
+                };
+                let bits = match inner_type.as_ref() {
+                    // Signed types take the bits as their two's complement value
+                    ProtoType::ZigZag32 | ProtoType::I32 | ProtoType::LI32 => bits as u32 as i32 as i128,
+                    ProtoType::ZigZag64 | ProtoType::I64 | ProtoType::LI64 => bits as i64 as i128,
+                    _ => bits as i128,
+                };
+                self.encode_value(inner_type, &JsonValue::String(bits.to_string()))?;
+            }
+            ProtoType::Nbt(format) => write_nbt(&mut self.out, value, *format)?,
+            ProtoType::NbtLoop => write_nbt_loop(&mut self.out, value)?,
+            ProtoType::Native(name) => {
+                // Decoded as "[native: 0x...]"
+                let hex = Self::string(value)?
+                    .strip_prefix("[native: ")
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .ok_or_else(|| anyhow!("Cannot encode native type {} from {}", name, value))?;
+                self.out.extend_from_slice(&Self::bytes(&JsonValue::String(hex.to_string()))?);
+            }
+            ProtoType::RestBuffer => self.out.extend_from_slice(&Self::bytes(value)?),
+            ProtoType::Custom(name) => return Err(anyhow!("Cannot encode {}: it has a custom decoder but no encoder", name)),
+            ProtoType::AvailableCommands => return Err(anyhow!("Encoding available_commands is not supported")),
+            ProtoType::Item { stack_id } => self.encode_item(value, *stack_id)?,
+            ProtoType::StackRequestSlotInfo => {
+                self.encode_value(&ProtoType::U8, &value["slot_type"]["container_id"])?;
+                self.encode_value(&ProtoType::Option(Box::new(ProtoType::LU32)), &value["slot_type"]["dynamic_container_id"])?;
+                self.encode_value(&ProtoType::U8, &value["slot"])?;
+                self.encode_value(&ProtoType::ZigZag32, &value["stack_id"])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// RakNet, the transport under the game packets. Its offline handshake messages carry a fixed magic;
+// connected control packets (pings, connection requests, ...) travel inside frame sets, so they're
+// decoded from the frames' bodies. Unlike game packets, RakNet is big-endian apart from its 24-bit indexes
+const RAKNET_MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+const RAKNET_GAME_PACKET: u8 = 0xfe;
+
+/// Decode a raw RakNet datagram: an offline handshake message (recognised by its magic), an ACK/NACK,
+/// or a frame set with its frames' headers and the control packets they carry. Returns None for anything
+/// else (including game packets), and for ACKs and frame sets that don't parse exactly, as their IDs
+/// overlap with game packet IDs
+pub fn decode_raknet(data: &[u8]) -> Option<DecodedPacket> {
+    let id = *data.first()?;
+    let (name, magic_at) = match id {
+        0x01 => ("raknet_unconnected_ping", 9),
+        0x02 => ("raknet_unconnected_ping_open_connections", 9),
+        0x05 => ("raknet_open_connection_request_1", 1),
+        0x06 => ("raknet_open_connection_reply_1", 1),
+        0x07 => ("raknet_open_connection_request_2", 1),
+        0x08 => ("raknet_open_connection_reply_2", 1),
+        0x19 => ("raknet_incompatible_protocol_version", 2),
+        0x1c => ("raknet_unconnected_pong", 17),
+        0xc0 => ("raknet_ack", 0),
+        0xa0 => ("raknet_nack", 0),
+        0x80..=0x8f => ("raknet_frame_set", 0),
+        _ => return None,
+    };
+
+    let mut reader = RakNetReader { data, pos: 1, path: String::new(), spans: IndexMap::new() };
+    let mut fields = IndexMap::new();
+    if magic_at > 0 {
+        if data.get(magic_at..magic_at + RAKNET_MAGIC.len())? != RAKNET_MAGIC {
+            return None;
+        }
+        // Offline messages are unambiguous, so one that breaks off is still shown, with the error
+        let error = reader.offline_message(id, &mut fields).err().map(|e| reader.error(e));
+        return Some(DecodedPacket {
+            packet_id: Some(id as u32),
+            packet_name: Some(name.to_string()),
+            fields,
+            note: None,
+            error,
+            spans: reader.spans,
+        });
+    }
+
+    let decoded = match id {
+        0xc0 | 0xa0 => reader.acknowledgement(&mut fields),
+        _ => reader.frame_set(&mut fields),
+    };
+    (decoded.is_ok() && reader.pos == data.len()).then(|| DecodedPacket {
+        packet_id: Some(id as u32),
+        packet_name: Some(name.to_string()),
+        fields,
+        note: None,
+        error: None,
+        spans: reader.spans,
+    })
+}
+
+struct RakNetReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    path: String, // Field being read, for errors
+    spans: IndexMap<String, Range<usize>>,
+}
+
+impl<'a> RakNetReader<'a> {
+    fn offline_message(&mut self, id: u8, fields: &mut IndexMap<String, JsonValue>) -> Result<()> {
+        match id {
+            0x01 | 0x02 => {
+                self.field(fields, "time", Self::i64)?;
+                self.field(fields, "magic", Self::magic)?;
+                self.field(fields, "client_guid", Self::guid)?;
+            }
+            0x05 => {
+                self.field(fields, "magic", Self::magic)?;
+                self.field(fields, "protocol", |r| r.u8().map(JsonValue::from))?;
+                // The request is padded to the MTU the client is trying; 28 bytes of IP and UDP headers precede it
+                let padding = self.data.len() - self.pos;
+                self.spans.insert("padding".to_string(), self.pos..self.data.len());
+                self.pos = self.data.len();
+                fields.insert("mtu_size".to_string(), JsonValue::from(self.data.len() + 28));
+                fields.insert("padding".to_string(), JsonValue::from(padding));
+            }
+            0x06 => {
+                self.field(fields, "magic", Self::magic)?;
+                self.field(fields, "server_guid", Self::guid)?;
+                let security = self.field(fields, "use_security", |r| r.u8().map(|b| JsonValue::Bool(b != 0)))?;
+                if security == JsonValue::Bool(true) {
+                    self.field(fields, "cookie", |r| r.u32().map(JsonValue::from))?;
+                }
+                self.field(fields, "mtu_size", |r| r.u16().map(JsonValue::from))?;
+            }
+            0x07 => {
+                self.field(fields, "magic", Self::magic)?;
+                self.field(fields, "server_address", Self::address)?;
+                self.field(fields, "mtu_size", |r| r.u16().map(JsonValue::from))?;
+                self.field(fields, "client_guid", Self::guid)?;
+            }
+            0x08 => {
+                self.field(fields, "magic", Self::magic)?;
+                self.field(fields, "server_guid", Self::guid)?;
+                self.field(fields, "client_address", Self::address)?;
+                self.field(fields, "mtu_size", |r| r.u16().map(JsonValue::from))?;
+                self.field(fields, "encryption_enabled", |r| r.u8().map(|b| JsonValue::Bool(b != 0)))?;
+            }
+            0x19 => {
+                self.field(fields, "protocol", |r| r.u8().map(JsonValue::from))?;
+                self.field(fields, "magic", Self::magic)?;
+                self.field(fields, "server_guid", Self::guid)?;
+            }
+            _ => {
+                // 0x1c: the server ID is the MOTD line of the server list, `MCPE;name;protocol;version;...`
+                self.field(fields, "time", Self::i64)?;
+                self.field(fields, "server_guid", Self::guid)?;
+                self.field(fields, "magic", Self::magic)?;
+                self.field(fields, "server_id", |r| {
+                    let len = r.u16()? as usize;
+                    Ok(JsonValue::String(String::from_utf8_lossy(r.bytes(len)?).to_string()))
+                })?;
+            }
+        }
+        if self.pos < self.data.len() {
+            fields.insert("trailing".to_string(), hex_value(&self.data[self.pos..]));
+            self.spans.insert("trailing".to_string(), self.pos..self.data.len());
+            self.pos = self.data.len();
+        }
+        Ok(())
+    }
+
+    /// ACK/NACK: a count of records, each a single datagram sequence number or a range of them
+    fn acknowledgement(&mut self, fields: &mut IndexMap<String, JsonValue>) -> Result<()> {
+        let count = self.field(fields, "record_count", |r| r.u16().map(JsonValue::from))?;
+        let mut records = Vec::new();
+        for index in 0..count.as_u64().unwrap_or(0) {
+            self.path = format!("records[{}]", index);
+            let record_start = self.pos;
+            let single = self.u8()? != 0;
+            let start = self.u24()?;
+            let end = if single { start } else { self.u24()? };
+            records.push(serde_json::json!({ "start": start, "end": end }));
+            self.spans.insert(self.path.clone(), record_start..self.pos);
+        }
+        fields.insert("records".to_string(), JsonValue::Array(records));
+        Ok(())
+    }
+
+    fn frame_set(&mut self, fields: &mut IndexMap<String, JsonValue>) -> Result<()> {
+        self.field(fields, "sequence_number", |r| r.u24().map(JsonValue::from))?;
+        let mut frames = Vec::new();
+        while self.pos < self.data.len() {
+            self.path = format!("frames[{}]", frames.len());
+            let start = self.pos;
+            frames.push(self.frame()?);
+            self.spans.insert(self.path.clone(), start..self.pos);
+        }
+        if frames.is_empty() {
+            return Err(anyhow!("Frame set has no frames"));
+        }
+        fields.insert("frames".to_string(), JsonValue::Array(frames));
+        Ok(())
+    }
+
+    fn frame(&mut self) -> Result<JsonValue> {
+        let flags = self.u8()?;
+        let reliability = flags >> 5;
+        let length = (self.u16()? as usize).div_ceil(8); // In bits
+        let mut frame = serde_json::Map::new();
+        frame.insert("reliability".to_string(), JsonValue::from(RELIABILITIES[reliability as usize]));
+        if matches!(reliability, 2 | 3 | 4 | 6 | 7) {
+            frame.insert("reliable_index".to_string(), JsonValue::from(self.u24()?));
+        }
+        if matches!(reliability, 1 | 4) {
+            frame.insert("sequenced_index".to_string(), JsonValue::from(self.u24()?));
+        }
+        if matches!(reliability, 1 | 3 | 4 | 7) {
+            frame.insert("ordered_index".to_string(), JsonValue::from(self.u24()?));
+            frame.insert("order_channel".to_string(), JsonValue::from(self.u8()?));
+        }
+        let split = flags & 0x10 != 0;
+        if split {
+            frame.insert("split_count".to_string(), JsonValue::from(self.u32()?));
+            frame.insert("split_id".to_string(), JsonValue::from(self.u16()?));
+            frame.insert("split_index".to_string(), JsonValue::from(self.u32()?));
+        }
+        let body = self.bytes(length)?;
+        frame.insert("length".to_string(), JsonValue::from(length));
+
+        // Only a whole (unsplit) body starts with a packet ID
+        match body.first() {
+            Some(&RAKNET_GAME_PACKET) if !split => {
+                frame.insert("packet".to_string(), JsonValue::from("game_packet"));
+                frame.insert("body".to_string(), hex_value(&body[1..]));
+            }
+            Some(&id) if !split => {
+                let mut reader = RakNetReader { data: body, pos: 1, path: String::new(), spans: IndexMap::new() };
+                let mut packet = IndexMap::new();
+                match reader.connected_message(id, &mut packet) {
+                    Some(Ok(name)) if reader.pos == body.len() => {
+                        frame.insert("packet".to_string(), JsonValue::from(name));
+                        frame.insert("fields".to_string(), JsonValue::Object(packet.into_iter().collect()));
+                    }
+                    _ => {
+                        frame.insert("body".to_string(), hex_value(body));
+                    }
+                }
+            }
+            _ => {
+                frame.insert("body".to_string(), hex_value(body));
+            }
+        }
+        Ok(JsonValue::Object(frame))
+    }
+
+    /// Control packets sent over an established connection; None for IDs that aren't one
+    fn connected_message(&mut self, id: u8, fields: &mut IndexMap<String, JsonValue>) -> Option<Result<&'static str>> {
+        let name = match id {
+            0x00 => "raknet_connected_ping",
+            0x03 => "raknet_connected_pong",
+            0x09 => "raknet_connection_request",
+            0x10 => "raknet_connection_request_accepted",
+            0x13 => "raknet_new_incoming_connection",
+            0x15 => "raknet_disconnection_notification",
+            _ => return None,
+        };
+        Some(self.connected_fields(id, fields).map(|()| name))
+    }
+
+    fn connected_fields(&mut self, id: u8, fields: &mut IndexMap<String, JsonValue>) -> Result<()> {
+        match id {
+            0x00 => {
+                self.field(fields, "time", Self::i64)?;
+            }
+            0x03 => {
+                self.field(fields, "ping_time", Self::i64)?;
+                self.field(fields, "pong_time", Self::i64)?;
+            }
+            0x09 => {
+                self.field(fields, "client_guid", Self::guid)?;
+                self.field(fields, "time", Self::i64)?;
+                self.field(fields, "use_security", |r| r.u8().map(|b| JsonValue::Bool(b != 0)))?;
+            }
+            0x10 => {
+                self.field(fields, "client_address", Self::address)?;
+                self.field(fields, "system_index", |r| r.u16().map(JsonValue::from))?;
+                self.field(fields, "internal_addresses", Self::internal_addresses)?;
+                self.field(fields, "request_time", Self::i64)?;
+                self.field(fields, "time", Self::i64)?;
+            }
+            0x13 => {
+                self.field(fields, "server_address", Self::address)?;
+                self.field(fields, "internal_addresses", Self::internal_addresses)?;
+                self.field(fields, "request_time", Self::i64)?;
+                self.field(fields, "time", Self::i64)?;
+            }
+            _ => {} // 0x15 has no fields
+        }
+        Ok(())
+    }
+
+    /// Read one field into `fields`, also returning it
+    fn field(
+        &mut self,
+        fields: &mut IndexMap<String, JsonValue>,
+        name: &str,
+        read: impl FnOnce(&mut Self) -> Result<JsonValue>,
+    ) -> Result<JsonValue> {
+        self.path = name.to_string();
+        let start = self.pos;
+        let value = read(self)?;
+        fields.insert(name.to_string(), value.clone());
+        self.spans.insert(name.to_string(), start..self.pos);
+        Ok(value)
+    }
+
+    fn error(&self, e: anyhow::Error) -> DecodeError {
+        DecodeError {
+            field: self.path.clone(),
+            offset: self.pos,
+            expected: String::new(),
+            remaining: self.data.len().saturating_sub(self.pos),
+            message: e.to_string(),
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len)
+            .ok_or_else(|| anyhow!("Needs {} bytes, {} left", len, self.data.len() - self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u24(&mut self) -> Result<u32> {
+        let bytes = self.bytes(3)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn i64(&mut self) -> Result<JsonValue> {
+        Ok(JsonValue::from(i64::from_be_bytes(self.bytes(8)?.try_into()?)))
+    }
+
+    /// GUIDs are written as decimal strings, like other 64-bit values the decoder can't keep exact as JSON numbers
+    fn guid(&mut self) -> Result<JsonValue> {
+        Ok(JsonValue::String(u64::from_be_bytes(self.bytes(8)?.try_into()?).to_string()))
+    }
+
+    fn magic(&mut self) -> Result<JsonValue> {
+        Ok(hex_value(self.bytes(RAKNET_MAGIC.len())?))
+    }
+
+    /// `a.b.c.d:port` (IPv4 bytes are stored inverted) or `[v6]:port`
+    fn address(&mut self) -> Result<JsonValue> {
+        match self.u8()? {
+            4 => {
+                let ip = self.bytes(4)?.iter().map(|b| (!b).to_string()).collect::<Vec<_>>().join(".");
+                let port = self.u16()?;
+                Ok(JsonValue::String(format!("{}:{}", ip, port)))
+            }
+            6 => {
+                self.bytes(2)?; // Address family
+                let port = self.u16()?;
+                self.bytes(4)?; // Flow info
+                let ip: [u8; 16] = self.bytes(16)?.try_into()?;
+                self.bytes(4)?; // Scope ID
+                Ok(JsonValue::String(format!("[{}]:{}", std::net::Ipv6Addr::from(ip), port)))
+            }
+            version => Err(anyhow!("Unknown IP version {}", version)),
+        }
+    }
+
+    /// Addresses up to the two 8-byte times that end the packet (10 or 20 of them, depending on the implementation)
+    fn internal_addresses(&mut self) -> Result<JsonValue> {
+        let mut addresses = Vec::new();
+        while self.data.len().saturating_sub(self.pos) > 16 {
+            addresses.push(self.address()?);
+        }
+        Ok(JsonValue::Array(addresses))
+    }
+}
+
+const RELIABILITIES: [&str; 8] = [
+    "unreliable",
+    "unreliable_sequenced",
+    "reliable",
+    "reliable_ordered",
+    "reliable_sequenced",
+    "unreliable_with_ack_receipt",
+    "reliable_with_ack_receipt",
+    "reliable_ordered_with_ack_receipt",
+];
+
+/// Bytes as a 0x-prefixed hex string, as the decoder shows buffers
+fn hex_value(bytes: &[u8]) -> JsonValue {
+    JsonValue::String(format!("0x{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>()))
+}
+
+/// Packets the generic decoder can't follow, decoded whole by dedicated code; their fields merge into the packet
+fn dedicated_packet_type(packet_name: &str) -> Option<Vec<CompiledField>> {
+    let (proto_type, expected) = match packet_name {
+        "packet_available_commands" => (ProtoType::AvailableCommands, "available_commands"),
+        _ => return None,
+    };
+    Some(vec![CompiledField {
+        name: "_".to_string(),
+        key: None,
+        proto_type: Ok(proto_type),
+        expected: expected.to_string(),
+    }])
+}
+
+/// Entry `index` of a palette, or the index itself if it's out of range (-1 is often "none")
+fn palette_entry<T: Clone + Into<JsonValue>>(palette: &[T], index: &JsonValue) -> JsonValue {
+    json_integer(index)
+        .and_then(|index| usize::try_from(index).ok())
+        .and_then(|index| palette.get(index))
+        .map(|entry| entry.clone().into())
+        .unwrap_or_else(|| index.clone())
+}
+
+/// Basic command parameter types, by the value_type of a parameter that isn't an enum or suffixed
+fn command_arg_type(value_type: i128) -> Option<&'static str> {
+    Some(match value_type {
+        1 => "int",
+        3 => "float",
+        4 => "value",
+        5 => "wildcard_int",
+        6 => "operator",
+        7 => "command_operator",
+        8 => "target",
+        10 => "wildcard_target",
+        17 => "file_path",
+        23 => "integer_range",
+        43 => "equipment_slots",
+        44 => "string",
+        52 => "block_position",
+        53 => "position",
+        55 => "message",
+        58 => "raw_text",
+        62 => "json",
+        71 => "block_states",
+        74 => "command",
+        _ => return None,
+    })
+}
+
+/// A type definition on one line: type names as they are, anything else as compact JSON
+pub fn type_text(def: &YamlValue) -> String {
+    match def {
+        YamlValue::String(name) => name.clone(),
+        _ => serde_json::to_string(def).unwrap_or_else(|_| format!("{:?}", def)),
+    }
+}
+
+fn short_packet_name(name: &str) -> &str {
+    name.strip_prefix("packet_").unwrap_or(name)
+}
+
+fn direction_name(direction: PacketDirection) -> &'static str {
+    match direction {
+        PacketDirection::Clientbound => "clientbound",
+        PacketDirection::Serverbound => "serverbound",
+    }
+}
+
+/// `_`, or `_1`, `_2`, ... for the further anonymous fields of a container loaded from JSON
+fn is_anonymous(field_name: &str) -> bool {
+    field_name
+        .strip_prefix('_')
+        .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Integer in a protocol definition: a number, or a decimal or 0x-prefixed hex string
+fn yaml_integer(value: &YamlValue) -> Option<i128> {
+    match value {
+        YamlValue::Number(n) => n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from)),
+        YamlValue::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => i128::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+/// Integer decoded value; 64-bit values may be decimal strings
+fn json_integer(value: &JsonValue) -> Option<i128> {
+    match value {
+        JsonValue::Number(n) => n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from)),
+        JsonValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parser_from_yaml(yaml: &str) -> ProtocolParser {
+        ProtocolParser::from_yaml_str(yaml, "test").unwrap()
+    }
+
     #[test]
     fn test_protocol_loading() {
         let parser = ProtocolParser::new("1.21.111");
@@ -910,4 +2798,575 @@ mod tests {
         let data = vec![0x81, 0x01];
         assert_eq!(parser.extract_packet_id(&data), Some(129));
     }
+
+    #[test]
+    fn test_decode_nbt_field() {
+        // bedrock-protocol declares nbt as a native alias
+        let parser = parser_from_yaml("nbt: native\npacket_block_entity_data:\n  \"!id\": 0x38\n  \"!bound\": client\n  nbt: nbt\n");
+
+        // { "": { id: "Chest" } }
+        let data = [0x38, 10, 0, 8, 2, b'i', b'd', 5, b'C', b'h', b'e', b's', b't', 0];
+        let decoded = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.fields["nbt"]["type"], "compound");
+        assert_eq!(decoded.fields["nbt"]["value"]["id"]["value"], "Chest");
+    }
+
+    #[test]
+    fn test_resolve_packet_id_by_direction() {
+        let parser = parser_from_yaml(concat!(
+            "packet_to_client:\n  \"!id\": 0x50\n  \"!bound\": client\n  a: u8\n",
+            "packet_to_server:\n  \"!id\": 0x50\n  \"!bound\": server\n  b: lu16\n",
+            "packet_server_only:\n  \"!id\": 0x51\n  \"!bound\": server\n  c: u8\n",
+        ));
+        let decode = |data: &[u8], direction| parser.decode_packet(data, direction);
+
+        let decoded = decode(&[0x50, 1, 0], PacketDirection::Clientbound);
+        assert_eq!(decoded.packet_name.as_deref(), Some("packet_to_client"));
+        assert!(decoded.note.is_none());
+        let decoded = decode(&[0x50, 1, 0], PacketDirection::Serverbound);
+        assert_eq!(decoded.packet_name.as_deref(), Some("packet_to_server"));
+        assert_eq!(decoded.fields["b"], 1);
+
+        // An ID only the other direction uses is still decoded, with a note
+        let decoded = decode(&[0x51, 7], PacketDirection::Clientbound);
+        assert_eq!(decoded.packet_name.as_deref(), Some("packet_server_only"));
+        assert_eq!(decoded.note.as_deref(), Some("0x51 is not a clientbound packet ID; decoded as serverbound packet_server_only"));
+        assert!(decode(&[0x52], PacketDirection::Clientbound).packet_name.is_none());
+    }
+
+    #[test]
+    fn test_decode_error_location() {
+        let parser = parser_from_yaml(concat!(
+            "Entry:\n  key: string\n  value: u8\n",
+            "packet_entries:\n  \"!id\": 0x60\n  \"!bound\": client\n  count: u8\n  entries: Entry[]varint\n  after: u8\n",
+        ));
+
+        // The second entry's key (at byte 6) says 3 bytes but only 1 is left
+        let data = [0x60, 5, 2, 1, b'a', 1, 3, b'b'];
+        let decoded = parser.decode_packet(&data, PacketDirection::Clientbound);
+        assert_eq!(decoded.fields["count"], 5);
+        assert!(decoded.fields["entries"].as_str().unwrap().starts_with("[decode_error:"));
+        assert!(!decoded.fields.contains_key("after"));
+        let error = decoded.error.unwrap();
+        assert_eq!(error.field, "entries[1].key");
+        assert_eq!(error.expected, "string");
+        assert_eq!((error.offset, error.remaining), (6, 2));
+
+        assert!(parser.decode_packet(&[0x60, 5, 0, 1], PacketDirection::Clientbound).error.is_none());
+    }
+
+    #[test]
+    fn test_decode_field_spans() {
+        let parser = parser_from_yaml(concat!(
+            "Entry:\n  key: string\n  value: u8\n",
+            "packet_entries:\n  \"!id\": 0x60\n  \"!bound\": client\n  count: u8\n  entries: Entry[]varint\n  after: lu16\n",
+        ));
+
+        let data = [0x60, 5, 2, 1, b'a', 1, 0, 2, 9, 0, 0xaa];
+        let decoded = parser.decode_packet(&data, PacketDirection::Clientbound);
+        let spans: Vec<(&str, Range<usize>)> = decoded.spans.iter().map(|(path, range)| (path.as_str(), range.clone())).collect();
+        assert_eq!(spans, [
+            ("count", 1..2),
+            ("entries", 2..8),
+            ("entries[0]", 3..6),
+            ("entries[0].key", 3..5),
+            ("entries[0].value", 5..6),
+            ("entries[1]", 6..8),
+            ("entries[1].key", 6..7),
+            ("entries[1].value", 7..8),
+            ("after", 8..10),
+        ]);
+    }
+
+    #[test]
+    fn test_decode_available_commands() {
+        // The dedicated decoder replaces whatever the definitions say
+        let parser = parser_from_yaml("packet_available_commands:\n  \"!id\": 0x4c\n  \"!bound\": client\n  values_len: varint\n");
+
+        let mut data = vec![0x4c];
+        let strings = |data: &mut Vec<u8>, strings: &[&str]| {
+            data.push(strings.len() as u8);
+            for s in strings {
+                data.push(s.len() as u8);
+                data.extend(s.as_bytes());
+            }
+        };
+        strings(&mut data, &["false", "true", "levels"]); // enum_values
+        strings(&mut data, &[]); // chained_subcommand_values
+        strings(&mut data, &["L"]); // suffixes
+        data.extend([1, 4]); // enums: Bool with values 0 and 1 (u8 indexes, as there are 3 values)
+        data.extend(b"Bool");
+        data.extend([2, 0, 1]);
+        data.push(0); // chained_subcommands
+        data.extend([1, 2]); // command_data: xp
+        data.extend(b"xp");
+        data.push(7);
+        data.extend(b"Give XP");
+        data.extend([0, 0, 1]); // flags, permission_level
+        data.extend(0i32.to_le_bytes()); // alias: the Bool enum
+        data.push(0); // chained_subcommand_offsets
+        data.extend([1, 0, 4]); // one overload, not chaining, four parameters
+        for (name, value_type, enum_type, options) in [("amount", 1u16, 0x10u16, 0u8), ("flag", 0, 0x30, 1), ("unit", 0, 0x100, 0), ("who", 0, 0x410, 0)] {
+            data.push(name.len() as u8);
+            data.extend(name.as_bytes());
+            data.extend(value_type.to_le_bytes());
+            data.extend(enum_type.to_le_bytes());
+            data.extend([0, options]);
+        }
+        data.extend([1, 7]); // dynamic_enums: Players
+        data.extend(b"Players");
+        strings(&mut data, &["Steve"]);
+        data.push(1); // enum_constraints
+        data.extend(1i32.to_le_bytes());
+        data.extend(0i32.to_le_bytes());
+        data.extend([1, 1]);
+
+        let decoded = parser.decode_packet(&data, PacketDirection::Clientbound);
+        assert!(decoded.error.is_none(), "{:?}", decoded.error);
+        let fields = &decoded.fields;
+        assert_eq!(fields["values_len"], 3);
+        assert_eq!(fields["enums"][0], serde_json::json!({ "name": "Bool", "values": ["false", "true"] }));
+        let command = &fields["command_data"][0];
+        assert_eq!(command["name"], "xp");
+        assert_eq!(command["alias"], 0);
+        assert_eq!(command["alias_enum"], "Bool");
+        let parameters = &command["overloads"][0]["parameters"];
+        assert_eq!(parameters[0]["type"], "int");
+        assert_eq!(parameters[1]["enum"], "Bool");
+        assert_eq!(parameters[1]["options"]["collapse_enum"], true);
+        assert_eq!(parameters[2]["suffix"], "L");
+        assert_eq!(parameters[3]["soft_enum"], "Players");
+        assert_eq!(fields["dynamic_enums"][0]["values"], serde_json::json!(["Steve"]));
+        let constraint = &fields["enum_constraints"][0];
+        assert_eq!((&constraint["value"], &constraint["enum"]), (&serde_json::json!("true"), &serde_json::json!("Bool")));
+        assert_eq!(constraint["constraints"], serde_json::json!(["operator_permissions"]));
+
+        // Cut short inside the second parameter
+        let decoded = parser.decode_packet(&data[..80], PacketDirection::Clientbound);
+        assert!(decoded.error.unwrap().field.starts_with("command_data[0].overloads[0].parameters[1]"));
+    }
+
+    #[test]
+    fn test_custom_decoders() {
+        let mut parser = parser_from_yaml(concat!(
+            "Color: native\n",
+            "packet_set_color:\n",
+            "  \"!id\": 0x20\n",
+            "  \"!bound\": client\n",
+            "  color: Color\n",
+            "  tint: native:Color\n",
+            "  position: vec3f\n",
+        ));
+        let data = [&[0x20, 0xff, 0x80, 0x00, 0x01, 0, 0, 0, 0][..], &[0; 12]].concat();
+        // Without a decoder, Color is an unknown type
+        let decoded = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.error.unwrap().field, "color");
+
+        parser.register_decoder("Color", |cursor| {
+            let mut rgba = [0u8; 4];
+            cursor.read_exact(&mut rgba)?;
+            Ok(JsonValue::String(format!("#{}", rgba.iter().map(|b| format!("{:02x}", b)).collect::<String>())))
+        });
+        // Built-in types can be replaced too
+        parser.register_decoder("vec3f", |cursor| {
+            cursor.set_position(cursor.position() + 12);
+            Ok(JsonValue::String("somewhere".to_string()))
+        });
+        let decoded = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound);
+        assert!(decoded.error.is_none(), "{:?}", decoded.error);
+        let fields = serde_json::to_value(&decoded.fields).unwrap();
+        assert_eq!(fields, serde_json::json!({ "color": "#ff800001", "tint": "#00000000", "position": "somewhere" }));
+        assert_eq!(decoded.spans["tint"], 5..9);
+
+        // A decoder's error is the field's
+        parser.register_decoder("Color", |_| Err(anyhow!("bad color")));
+        let error = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound).error.unwrap();
+        assert_eq!(error.field, "color");
+        assert!(parser.encode_packet("set_color", &fields).is_err());
+    }
+
+    #[test]
+    fn test_decode_item_types() {
+        let parser = parser_from_yaml(concat!(
+            "packet_inventory_slot:\n",
+            "  \"!id\": 0x32\n",
+            "  \"!bound\": client\n",
+            "  item: Item\n",
+            "  legacy: ItemLegacy\n",
+            "  shield: ItemLegacy\n",
+            "  empty: Item\n",
+            "  source: StackRequestSlotInfo\n",
+        ));
+
+        let mut data = vec![0x32];
+        data.extend([0x80, 0x01, 3, 0, 0, 1, 0x0a, 0x02]); // item: network ID 64, count 3, has a stack ID (5), block 1
+        let extra = [&[0xff, 0xff, 1, 0x0a, 0, 0, 0][..], &[1, 0, 0, 0, 5, 0], b"stone", &[0, 0, 0, 0]].concat();
+        data.push(extra.len() as u8);
+        data.extend(&extra);
+        data.extend([0x02, 1, 0, 4, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // legacy: no NBT, nothing to place on or destroy
+        data.extend([0x04, 1, 0, 0, 0, 18, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 0]); // shield
+        data.push(0); // empty
+        data.extend([7, 1, 9, 0, 0, 0, 2, 0x0b]); // source: container 7, dynamic container 9, slot 2, stack -6
+
+        let decoded = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound);
+        assert!(decoded.error.is_none(), "{:?}", decoded.error);
+        let fields = serde_json::to_value(&decoded.fields).unwrap();
+        assert_eq!(
+            fields["item"],
+            serde_json::json!({
+                "network_id": 64, "count": 3, "metadata": 0, "has_stack_id": 1, "stack_id": 5, "block_runtime_id": 1,
+                "extra": {
+                    "has_nbt": true,
+                    "nbt": { "version": 1, "nbt": { "type": "compound", "name": "", "value": {} } },
+                    "can_place_on": ["stone"],
+                    "can_destroy": [],
+                },
+            })
+        );
+        assert_eq!(fields["legacy"]["metadata"], 4);
+        assert!(fields["legacy"].get("stack_id").is_none());
+        assert_eq!(fields["legacy"]["extra"]["has_nbt"], false);
+        assert_eq!(fields["shield"]["extra"]["blocking_tick"], "40");
+        assert_eq!(fields["empty"], serde_json::json!({ "network_id": 0 }));
+        assert_eq!(
+            fields["source"],
+            serde_json::json!({ "slot_type": { "container_id": 7, "dynamic_container_id": 9 }, "slot": 2, "stack_id": -6 })
+        );
+
+        // Encoding the decoded items gives the same bytes
+        assert_eq!(parser.encode_packet("inventory_slot", &fields).unwrap(), data);
+
+        // Extra data that doesn't add up is an error on the item
+        let mut bad = data.clone();
+        bad[9] += 1;
+        let decoded = parser.decode_packet(&bad, crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.error.unwrap().field, "item");
+    }
+
+    #[test]
+    fn test_array_count_types() {
+        let parser = parser_from_yaml("packet_counts:\n  \"!id\": 0x01\n  \"!bound\": client\n  small: u8[]u8\n  big: u8[]lu32\n");
+        let mut data = vec![0x01, 0x81];
+        data.extend(vec![7; 0x81]);
+        data.extend([1, 0, 0, 0, 9]);
+        let decoded = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound);
+        assert!(decoded.error.is_none(), "{:?}", decoded.error);
+        assert_eq!(decoded.fields["small"].as_array().unwrap().len(), 0x81);
+        assert_eq!(decoded.fields["big"], serde_json::json!([9]));
+        let fields = serde_json::to_value(&decoded.fields).unwrap();
+        assert_eq!(parser.encode_packet("counts", &fields).unwrap(), data);
+
+        // Unknown count types don't compile, rather than being read as varints
+        let parser = parser_from_yaml("packet_counts:\n  \"!id\": 0x01\n  \"!bound\": client\n  small: u8[]nibble\n");
+        let decoded = parser.decode_packet(&[0x01, 0x00], crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.error.unwrap().message, "Unknown count type: nibble");
+    }
+
+    #[test]
+    fn test_packet_name_lookup() {
+        let parser = parser_from_yaml(concat!(
+            "packet_text:\n  \"!id\": 0x09\n  \"!bound\": both\n  message: string\n",
+            "packet_player_auth_input:\n  \"!id\": 0x90\n  \"!bound\": server\n  tick: varint64\n",
+            "packet_start_game:\n  \"!id\": 0x0b\n  \"!bound\": client\n  seed: lu64\n",
+            "packet_set_title:\n  \"!id\": 0x58\n  \"!bound\": client\n  text: string\n",
+        ));
+        assert_eq!(parser.packet_names(), ["player_auth_input", "set_title", "start_game", "text"]);
+
+        assert_eq!(parser.packet_id("text", PacketDirection::Serverbound), Some(0x09));
+        assert_eq!(parser.packet_id("packet_start_game", PacketDirection::Clientbound), Some(0x0b));
+        assert_eq!(parser.packet_id("start_game", PacketDirection::Serverbound), None);
+        assert_eq!(parser.packet_name(0x90, PacketDirection::Serverbound), Some("player_auth_input"));
+        assert_eq!(parser.packet_name(0x90, PacketDirection::Clientbound), None);
+
+        assert_eq!(parser.match_packet_names("text", None), ["text"]);
+        assert_eq!(parser.match_packet_names("title", None), ["set_title"]);
+        assert_eq!(parser.match_packet_names("st", None), ["start_game", "set_title"]);
+        assert_eq!(parser.match_packet_names("PAI", None), ["player_auth_input"]);
+        assert_eq!(parser.match_packet_names("t", Some(PacketDirection::Serverbound)), ["text", "player_auth_input"]);
+        assert!(parser.match_packet_names("xyz", None).is_empty());
+    }
+
+    #[test]
+    fn test_decode_raknet() {
+        let mut ping = vec![0x01];
+        ping.extend(1000i64.to_be_bytes());
+        ping.extend(RAKNET_MAGIC);
+        ping.extend(42u64.to_be_bytes());
+        let decoded = decode_raknet(&ping).unwrap();
+        assert_eq!(decoded.packet_name.as_deref(), Some("raknet_unconnected_ping"));
+        assert_eq!(decoded.fields["time"], 1000);
+        assert_eq!(decoded.fields["client_guid"], "42");
+        assert!(decoded.error.is_none());
+
+        // Open connection request 1 is padded out to the MTU being tried
+        let mut request = vec![0x05];
+        request.extend(RAKNET_MAGIC);
+        request.push(11);
+        request.extend([0; 100]);
+        let decoded = decode_raknet(&request).unwrap();
+        assert_eq!(decoded.fields["protocol"], 11);
+        assert_eq!(decoded.fields["mtu_size"], 146);
+
+        let mut reply = vec![0x08];
+        reply.extend(RAKNET_MAGIC);
+        reply.extend(7u64.to_be_bytes());
+        reply.extend([4, !127, !0, !0, !1, 0x4a, 0xbc, 0x05, 0xd4, 0]);
+        let decoded = decode_raknet(&reply).unwrap();
+        assert_eq!(decoded.fields["client_address"], "127.0.0.1:19132");
+        assert_eq!(decoded.spans["client_address"], 25..32);
+        assert_eq!(decoded.fields["mtu_size"], 1492);
+        assert_eq!(decoded.fields["encryption_enabled"], false);
+
+        // A single sequence number and a range
+        let decoded = decode_raknet(&[0xc0, 0, 2, 1, 5, 0, 0, 0, 7, 0, 0, 9, 0, 0]).unwrap();
+        assert_eq!(decoded.packet_name.as_deref(), Some("raknet_ack"));
+        assert_eq!(decoded.fields["records"], serde_json::json!([{"start": 5, "end": 5}, {"start": 7, "end": 9}]));
+
+        // A reliable ordered connected ping and an unreliable game packet
+        let mut frames = vec![0x84, 1, 0, 0, 0x60, 0, 72, 0, 0, 0, 0, 0, 0, 0, 0x00];
+        frames.extend(5i64.to_be_bytes());
+        frames.extend([0x00, 0, 32, 0xfe, 1, 2, 3]);
+        let decoded = decode_raknet(&frames).unwrap();
+        assert_eq!(decoded.fields["sequence_number"], 1);
+        let frames = decoded.fields["frames"].as_array().unwrap();
+        assert_eq!(frames[0]["reliability"], "reliable_ordered");
+        assert_eq!(frames[0]["packet"], "raknet_connected_ping");
+        assert_eq!(frames[0]["fields"]["time"], 5);
+        assert_eq!(frames[1]["packet"], "game_packet");
+        assert_eq!(frames[1]["body"], "0x010203");
+
+        // Game packets, and lookalikes that don't parse exactly, are left to the game layer
+        assert!(decode_raknet(&[0x09, 1, 2]).is_none());
+        assert!(decode_raknet(&[0x84, 1]).is_none());
+        assert!(decode_raknet(&[0xc0, 0, 1, 1, 5, 0, 0, 0xff]).is_none());
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        // The fuzz target's definitions
+        let parser = parser_from_yaml(include_str!("../fuzz/protocol.yml"));
+        let decode = |parser: &ProtocolParser, data: &[u8]| parser.decode_packet(data, PacketDirection::Clientbound);
+
+        // A string claiming ~4GB fails on the length, before anything is allocated
+        let error = decode(&parser, &[0x01, 0xff, 0xff, 0xff, 0xff, 0x0f]).error.unwrap();
+        assert_eq!(error.field, "name");
+        assert!(error.message.contains("exceeds the limit"), "{}", error.message);
+        let error = decode(&parser, &[0x01, 5, b'a']).error.unwrap();
+        assert!(error.message.contains("exceeds the 1 bytes left"), "{}", error.message);
+
+        // Nodes nested 1000 deep stop at the depth limit
+        let mut nested = vec![0x03];
+        for _ in 0..1000 {
+            nested.extend([7, 1]);
+        }
+        let error = decode(&parser, &nested).error.unwrap();
+        assert!(error.message.contains("Nesting deeper than the limit of 128"), "{}", error.message);
+        assert!(error.field.starts_with("root.child.child."));
+
+        let small = parser_from_yaml(include_str!("../fuzz/protocol.yml")).with_limits(DecodeLimits {
+            max_array_length: 2,
+            max_output_bytes: 64,
+            ..DecodeLimits::default()
+        });
+        let entries = [0x02, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(decode(&parser, &entries).error.is_none());
+        let error = decode(&small, &entries).error.unwrap();
+        assert_eq!(error.field, "entries");
+        assert!(error.message.contains("Array length 3 exceeds the limit of 2"), "{}", error.message);
+        let mut long_name = vec![0x01, 70];
+        long_name.extend([b'x'; 70]);
+        let error = decode(&small, &long_name).error.unwrap();
+        assert!(error.message.contains("Decoded output exceeds the limit of 64 bytes"), "{}", error.message);
+
+        // A quick, deterministic run of what the fuzz target does: random bodies for every packet never panic
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..5000 {
+            let len = (next() % 48) as usize;
+            let mut data = vec![(next() % 4) as u8 + 1];
+            data.extend((0..len).map(|_| next() as u8));
+            decode(&parser, &data);
+        }
+    }
+
+    #[test]
+    fn test_decode_fields_in_declaration_order() {
+        let parser = parser_from_yaml("packet_ordered:\n  \"!id\": 0x41\n  \"!bound\": client\n  zeta: u8\n  alpha: lu16\n  mid: u8\n");
+
+        let decoded = parser.decode_packet(&[0x41, 1, 2, 0, 3], crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.fields.keys().collect::<Vec<_>>(), ["zeta", "alpha", "mid"]);
+        assert_eq!(decoded.fields["zeta"], 1);
+        assert_eq!(decoded.fields["alpha"], 2);
+        assert_eq!(decoded.fields["mid"], 3);
+    }
+
+    #[test]
+    fn test_decode_mapper_and_bitflags() {
+        let parser = parser_from_yaml(concat!(
+            "GameMode: [\"mapper\", { type: zigzag32, mappings: { 0: survival, 1: creative, \"0x05\": fallback } }]\n",
+            "InputFlag: [\"bitflags\", { type: varint64, flags: [ascend, descend, jump] }]\n",
+            "packet_modes:\n",
+            "  \"!id\": 0x42\n",
+            "  \"!bound\": client\n",
+            "  gamemode: GameMode\n",
+            "  other: GameMode\n",
+            "  flags: InputFlag\n",
+            "  extra: [\"switch\", { compareTo: gamemode, fields: { creative: u8 } }]\n",
+        ));
+
+        // gamemode 1 (zigzag 2), other 7 (zigzag 14, unmapped), flags ascend | jump, then the creative case
+        let decoded = parser.decode_packet(&[0x42, 2, 14, 5, 9], crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.fields["gamemode"], "creative");
+        assert_eq!(decoded.fields["other"], 7);
+        assert_eq!(
+            decoded.fields["flags"],
+            serde_json::json!({ "ascend": true, "descend": false, "jump": true, "_value": 5 })
+        );
+        assert_eq!(decoded.fields["extra"], 9);
+    }
+
+    #[test]
+    fn test_decode_switch_and_option_fields() {
+        let parser = parser_from_yaml(concat!(
+            "packet_conditional:\n",
+            "  \"!id\": 0x40\n",
+            "  \"!bound\": client\n",
+            "  kind: u8\n",
+            "  maybe?: u8\n",
+            "  tail:\n",
+            "    _:\n",
+            "      \"../kind?\":\n",
+            "        \"if 1 or 3\":\n",
+            "          more: u8\n",
+            "        default: void\n",
+            "  value: [\"switch\", { compareTo: kind, fields: { 1: u8, 2: void }, default: i16 }]\n",
+        ));
+        let decode = |data: &[u8]| parser.decode_packet(data, crate::packet_logger::PacketDirection::Clientbound).fields;
+
+        // kind 1: option present, the anonymous switch adds `more`, value is a u8
+        let fields = decode(&[0x40, 1, 1, 9, 7, 5]);
+        assert_eq!(fields["maybe"], 9);
+        assert_eq!(fields["tail"]["more"], 7);
+        assert_eq!(fields["value"], 5);
+
+        // kind 2: option absent, no `more`, value is void and left out
+        let fields = decode(&[0x40, 2, 0]);
+        assert!(fields["maybe"].is_null());
+        assert_eq!(fields["tail"], serde_json::json!({}));
+        assert!(!fields.contains_key("value"));
+
+        // Any other kind falls back to the default case
+        let fields = decode(&[0x40, 4, 0, 0xff, 0xff]);
+        assert_eq!(fields["value"], -1);
+    }
+
+    #[test]
+    fn test_load_minecraft_data_json() {
+        let protocol = serde_json::json!({ "types": {
+            "varint": "native",
+            "string": ["pstring", { "countType": "varint" }],
+            "TextType": ["mapper", { "type": "u8", "mappings": { "0": "raw", "1": "chat" } }],
+            "Position": ["container", [{ "name": "x", "type": "zigzag32" }, { "name": "y", "type": "zigzag32" }]],
+            "packet_text": ["container", [
+                { "name": "type", "type": "TextType" },
+                { "name": "positions", "type": ["array", { "countType": "varint", "type": "Position" }] },
+                { "anon": true, "type": ["switch", { "compareTo": "type", "fields": {
+                    "chat": ["container", [{ "name": "source_name", "type": "string" }, { "name": "message", "type": "string" }]],
+                    "raw": ["container", [{ "name": "message", "type": "string" }]],
+                } }] },
+                { "name": "xuid", "type": ["option", "string"] },
+            ]],
+            "mcpe_packet": ["container", [
+                { "name": "name", "type": ["mapper", { "type": "varint", "mappings": { "0x09": "text" } }] },
+                { "name": "params", "type": ["switch", { "compareTo": "name", "fields": { "text": "packet_text" } }] },
+            ]],
+        }});
+        let path = std::env::temp_dir().join(format!("lazypacket-protocol-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, protocol.to_string()).unwrap();
+        let parser = ProtocolParser::load_from_file(&path, "test").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(parser.packet_count(), 1);
+
+        // text (0x09), type chat, one position (1, -1), then the chat case's fields and no xuid
+        let data = [0x09, 1, 1, 2, 1, 1, b'a', 2, b'h', b'i', 0];
+        let decoded = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.packet_name.as_deref(), Some("packet_text"));
+        assert_eq!(
+            serde_json::to_value(&decoded.fields).unwrap(),
+            serde_json::json!({
+                "type": "chat",
+                "positions": [{ "x": 1, "y": -1 }],
+                "source_name": "a",
+                "message": "hi",
+                "xuid": null,
+            })
+        );
+        assert_eq!(parser.encode_packet("text", &serde_json::to_value(&decoded.fields).unwrap()).unwrap(), data);
+    }
+
+    #[test]
+    fn test_encode_packet_round_trip() {
+        let parser = parser_from_yaml(concat!(
+            "GameMode: [\"mapper\", { type: zigzag32, mappings: { 0: survival, 1: creative } }]\n",
+            "InputFlag: [\"bitflags\", { type: varint64, flags: [ascend, descend, jump] }]\n",
+            "Entry:\n",
+            "  key: string\n",
+            "  value: zigzag64\n",
+            "packet_rewrite:\n",
+            "  \"!id\": 0x44\n",
+            "  \"!bound\": client\n",
+            "  name: string\n",
+            "  mode: GameMode\n",
+            "  flags: InputFlag\n",
+            "  position: vec3f\n",
+            "  player: uuid\n",
+            "  entries: Entry[]varint\n",
+            "  payload: [\"encapsulated\", \"string\"]\n",
+            "  tag?: lu64\n",
+            "  kind: u8\n",
+            "  _:\n",
+            "    \"kind?\":\n",
+            "      \"if 1\":\n",
+            "        extra: li32\n",
+            "      default: void\n",
+            "  nbt: nbt\n",
+            "  rest: restBuffer\n",
+        ));
+        let fields = serde_json::json!({
+            "name": "Steve",
+            "mode": "creative",
+            "flags": { "ascend": true, "descend": false, "jump": true, "_value": 5 },
+            "position": { "x": 1.5, "y": -2.0, "z": 0.25 },
+            "player": "00112233-4455-6677-8899-aabbccddeeff",
+            "entries": [{ "key": "a", "value": "-5" }, { "key": "b", "value": "7" }],
+            "payload": "inner",
+            "tag": null,
+            "kind": 1,
+            "extra": -3,
+            "nbt": { "type": "compound", "name": "", "value": { "id": { "type": "string", "value": "Chest" } } },
+            "rest": "0xbeef",
+        });
+
+        let data = parser.encode_packet("rewrite", &fields).unwrap();
+        assert_eq!(&data[..7], [0x44, 5, b'S', b't', b'e', b'v', b'e']);
+        assert_eq!(&data[data.len() - 2..], [0xbe, 0xef]);
+
+        // Decoding gives the fields back, and encoding those gives the same bytes
+        let decoded = parser.decode_packet(&data, crate::packet_logger::PacketDirection::Clientbound);
+        assert_eq!(decoded.packet_name.as_deref(), Some("packet_rewrite"));
+        assert_eq!(serde_json::to_value(&decoded.fields).unwrap(), fields);
+        assert_eq!(parser.encode_packet("packet_rewrite", &serde_json::to_value(&decoded.fields).unwrap()).unwrap(), data);
+
+        // Unknown packets and values that don't fit their type are errors naming the field
+        assert!(parser.encode_packet("missing", &fields).is_err());
+        let mut bad = fields.clone();
+        bad["kind"] = serde_json::json!(300);
+        let error = format!("{:#}", parser.encode_packet("rewrite", &bad).unwrap_err());
+        assert!(error.contains("'kind'"), "{}", error);
+    }
 }
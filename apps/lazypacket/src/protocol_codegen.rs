@@ -0,0 +1,648 @@
+// `codegen` subcommand: generates Rust structs (with serde derives) for chosen packets from the protocol
+// definitions, so bots built on this crate get compile-time checked packet types instead of walking
+// serde_json::Value. The structs have the shape decode_packet gives and encode_packet takes, so a
+// DecodedPacket converts with TypedPacket::from_decoded and goes back to bytes with TypedPacket::encode
+
+use crate::protocol::{CompiledField, DecodedPacket, ProtoType, ProtocolParser};
+use anyhow::{anyhow, Context, Result};
+use indexmap::IndexMap;
+use serde::de::{self, DeserializeOwned, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A generated packet struct
+pub trait TypedPacket: Serialize + DeserializeOwned {
+    /// Name in the definitions, e.g. `packet_text`
+    const NAME: &'static str;
+    const ID: u32;
+
+    /// The fields of a decoded packet as this type; fails for another packet, a packet that didn't fully
+    /// decode, or fields that don't fit the type
+    fn from_decoded(decoded: &DecodedPacket) -> Result<Self> {
+        let name = decoded.packet_name.as_deref();
+        if name != Some(Self::NAME) {
+            return Err(anyhow!("Expected {}, got {}", Self::NAME, name.unwrap_or("an unknown packet")));
+        }
+        if let Some(error) = &decoded.error {
+            return Err(anyhow!("{} did not decode: {}", Self::NAME, error));
+        }
+        let fields = serde_json::to_value(&decoded.fields)?;
+        serde_json::from_value(fields).with_context(|| format!("Fields don't match {}", Self::NAME))
+    }
+
+    /// Encode the packet (ID included) with the definitions the type was generated from
+    fn encode(&self, parser: &ProtocolParser) -> Result<Vec<u8>> {
+        parser.encode_packet(Self::NAME, &serde_json::to_value(self)?)
+    }
+}
+
+/// A 64-bit integer. The decoder writes these as decimal strings (or numbers, when they fit), so both are
+/// read; they are written as strings, which encode_packet takes for any integer type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Long(pub i128);
+
+impl Serialize for Long {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Long {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct LongVisitor;
+
+        impl Visitor<'_> for LongVisitor {
+            type Value = Long;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an integer or a decimal string")
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Long, E> {
+                Ok(Long(value.into()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Long, E> {
+                Ok(Long(value.into()))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Long, E> {
+                value.parse().map(Long).map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(LongVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vec2f {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vec3f {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// `Item`, or `ItemLegacy` (which has no stack ID); an empty slot only has `network_id` 0
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub network_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_stack_id: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_runtime_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<ItemExtra>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ItemExtra {
+    pub has_nbt: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbt: Option<ItemNbt>,
+    pub can_place_on: Vec<String>,
+    pub can_destroy: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocking_tick: Option<Long>, // Shields only
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ItemNbt {
+    pub version: u8,
+    pub nbt: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackRequestSlotInfo {
+    pub slot_type: FullContainerName,
+    pub slot: u8,
+    pub stack_id: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FullContainerName {
+    pub container_id: u8,
+    pub dynamic_container_id: Option<u32>,
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else", "enum",
+    "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// Type names the generated code uses unqualified, which a generated type mustn't shadow
+const RESERVED_TYPE_NAMES: &[&str] = &["Box", "None", "Option", "Result", "Self", "Some", "String", "Vec"];
+
+struct Field {
+    rust_type: String,
+    optional: bool, // Left out of the JSON when absent (a switch that decoded nothing)
+    flatten: bool,  // An object whose keys merge into the struct's
+}
+
+struct Generator<'a> {
+    parser: &'a ProtocolParser,
+    crate_path: &'a str,
+    items: IndexMap<String, String>, // Rust type name -> its source, in the order the names were taken
+    container_names: HashMap<String, String>, // Container -> Rust type name
+    enum_names: HashMap<String, String>, // Mapper or bitflags definition -> Rust type name, so a shared alias is generated once
+    generating: HashSet<String>, // Structs being generated, which a field refers back to through a Box
+}
+
+/// Rust source for the given packets (named with or without `packet_`) and the types they use.
+/// `crate_path` is how the generated code refers to this crate: `lazypacket`, or `crate` inside it
+pub fn generate_packet_structs(parser: &ProtocolParser, packets: &[String], crate_path: &str) -> Result<String> {
+    let mut generator = Generator {
+        parser,
+        crate_path,
+        items: IndexMap::new(),
+        container_names: HashMap::new(),
+        enum_names: HashMap::new(),
+        generating: HashSet::new(),
+    };
+    for name in packets {
+        let info = parser.get_packet_info_by_name(name).ok_or_else(|| anyhow!("Unknown packet: {}", name))?;
+        generator.packet(&info.name, info.id).with_context(|| format!("Failed to generate {}", info.name))?;
+    }
+
+    let mut source = format!(
+        "// Generated by `lazypacket codegen` from protocol {}; regenerate instead of editing\n",
+        parser.version()
+    );
+    for item in generator.items.values() {
+        source.push('\n');
+        source.push_str(item);
+    }
+    Ok(source)
+}
+
+impl Generator<'_> {
+    fn packet(&mut self, name: &str, id: u32) -> Result<()> {
+        let rust_name = pascal_case(name);
+        if self.items.contains_key(&rust_name) {
+            return Ok(()); // Listed twice
+        }
+        let fields = self.parser.packet_types(name).ok_or_else(|| anyhow!("No fields compiled for {}", name))?;
+        self.generate_struct(&rust_name, fields, &format!("{} (0x{:02x})", name, id))?;
+        self.items[&rust_name].push_str(&format!(
+            "\nimpl {}::protocol_codegen::TypedPacket for {} {{\n    const NAME: &'static str = \"{}\";\n    const ID: u32 = 0x{:02x};\n}}\n",
+            self.crate_path, rust_name, name, id
+        ));
+        Ok(())
+    }
+
+    fn generate_struct(&mut self, rust_name: &str, fields: &[CompiledField], doc: &str) -> Result<()> {
+        self.items.insert(rust_name.to_string(), String::new());
+        self.generating.insert(rust_name.to_string());
+        let mut struct_fields = IndexMap::new();
+        let result = self.struct_fields(fields, rust_name, &mut struct_fields);
+        self.generating.remove(rust_name);
+        result?;
+
+        let mut source = String::new();
+        if !doc.is_empty() {
+            source.push_str(&format!("/// {}\n", doc));
+        }
+        source.push_str(&format!("#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\npub struct {} {{\n", rust_name));
+        let mut idents = HashSet::new();
+        for (key, field) in &struct_fields {
+            let (ident, renamed) = field_ident(key, &mut idents);
+            let mut attributes = Vec::new();
+            if renamed && !field.flatten {
+                attributes.push(format!("rename = \"{}\"", key.escape_default()));
+            }
+            if field.flatten {
+                attributes.push("flatten".to_string());
+            }
+            if field.optional {
+                attributes.push("default, skip_serializing_if = \"Option::is_none\"".to_string());
+            }
+            if !attributes.is_empty() {
+                source.push_str(&format!("    #[serde({})]\n", attributes.join(", ")));
+            }
+            let rust_type = if field.optional { format!("Option<{}>", field.rust_type) } else { field.rust_type.clone() };
+            source.push_str(&format!("    pub {}: {},\n", ident, rust_type));
+        }
+        source.push_str("}\n");
+        self.items[rust_name] = source;
+        Ok(())
+    }
+
+    fn struct_fields(&mut self, fields: &[CompiledField], struct_name: &str, out: &mut IndexMap<String, Field>) -> Result<()> {
+        for field in fields {
+            let proto_type = field.proto_type.as_ref().map_err(|message| anyhow!("{}: {}", field.name, message))?;
+            let Some(key) = &field.key else {
+                self.merged_fields(proto_type, struct_name, false, out)
+                    .with_context(|| format!("Failed to generate {}", field.name))?;
+                continue;
+            };
+            // Types declared by name (`mode: GameMode`) keep it; others are named after the field
+            let hint = if self.parser.type_aliases().contains_key(&field.expected) {
+                pascal_case(&field.expected)
+            } else {
+                format!("{}{}", struct_name, pascal_case(key))
+            };
+            let field = match proto_type {
+                ProtoType::Void => continue,
+                ProtoType::Switch(_) => Field { rust_type: self.switch_type(proto_type, &hint)?, optional: true, flatten: false },
+                _ => Field { rust_type: self.rust_type(proto_type, &hint)?, optional: false, flatten: false },
+            };
+            add_field(out, key, field);
+        }
+        Ok(())
+    }
+
+    /// Fields an anonymous field merges into its container; `optional` for ones only some switch cases have
+    fn merged_fields(&mut self, proto_type: &ProtoType, struct_name: &str, optional: bool, out: &mut IndexMap<String, Field>) -> Result<()> {
+        let fields = match proto_type {
+            ProtoType::Fields(fields) => fields.as_slice(),
+            ProtoType::Container(name) => self.parser.container_types(name).ok_or_else(|| anyhow!("Unknown container {}", name))?,
+            ProtoType::Switch(switch) => {
+                let cases = switch.cases.iter().map(|(_, case)| case).chain(switch.default.as_deref());
+                for case in cases {
+                    self.merged_fields(case, struct_name, true, out)?;
+                }
+                return Ok(());
+            }
+            ProtoType::AvailableCommands => {
+                let rust_type = "serde_json::Map<String, serde_json::Value>".to_string();
+                add_field(out, "available_commands", Field { rust_type, optional: false, flatten: true });
+                return Ok(());
+            }
+            _ => return Ok(()), // Only objects merge; the decoder drops anything else
+        };
+
+        let mut merged = IndexMap::new();
+        self.struct_fields(fields, struct_name, &mut merged)?;
+        for (key, mut field) in merged {
+            field.optional |= optional;
+            add_field(out, &key, field);
+        }
+        Ok(())
+    }
+
+    /// What a switch decodes to: the type its cases share, or any JSON value if they differ
+    fn switch_type(&mut self, proto_type: &ProtoType, hint: &str) -> Result<String> {
+        let ProtoType::Switch(switch) = proto_type else {
+            return self.rust_type(proto_type, hint);
+        };
+        let mut cases = switch.cases.iter().map(|(_, case)| case).chain(switch.default.as_deref())
+            .filter(|case| !matches!(case, ProtoType::Void));
+        let Some(first) = cases.next() else {
+            return Ok("serde_json::Value".to_string());
+        };
+        let shape = format!("{:?}", first);
+        if cases.all(|case| format!("{:?}", case) == shape) {
+            self.rust_type(first, hint)
+        } else {
+            Ok("serde_json::Value".to_string())
+        }
+    }
+
+    /// The Rust type for a value; `hint` names a struct or enum generated for it
+    fn rust_type(&mut self, proto_type: &ProtoType, hint: &str) -> Result<String> {
+        let crate_path = self.crate_path;
+        let rust_type = match proto_type {
+            ProtoType::I8 => "i8".to_string(),
+            ProtoType::U8 => "u8".to_string(),
+            ProtoType::I16 | ProtoType::LI16 => "i16".to_string(),
+            ProtoType::U16 | ProtoType::LU16 => "u16".to_string(),
+            ProtoType::I32 | ProtoType::LI32 | ProtoType::ZigZag32 => "i32".to_string(),
+            ProtoType::U32 | ProtoType::LU32 | ProtoType::VarInt32 => "u32".to_string(),
+            ProtoType::I64 => "i64".to_string(),
+            ProtoType::U64 | ProtoType::VarInt64 | ProtoType::ZigZag64 | ProtoType::LI64 | ProtoType::LU64 => {
+                format!("{}::protocol_codegen::Long", crate_path)
+            }
+            ProtoType::F32 => "f32".to_string(),
+            ProtoType::F64 => "f64".to_string(),
+            ProtoType::Bool => "bool".to_string(),
+            // Buffers and natives are hex strings
+            ProtoType::String(_) | ProtoType::LittleString | ProtoType::ShortString | ProtoType::LatinString
+            | ProtoType::UUID | ProtoType::Buffer(_) | ProtoType::RestBuffer | ProtoType::Native(_) => "String".to_string(),
+            ProtoType::Vec2F => format!("{}::protocol_codegen::Vec2f", crate_path),
+            ProtoType::Vec3F => format!("{}::protocol_codegen::Vec3f", crate_path),
+            ProtoType::Item { .. } => format!("{}::protocol_codegen::Item", crate_path),
+            ProtoType::StackRequestSlotInfo => format!("{}::protocol_codegen::StackRequestSlotInfo", crate_path),
+            ProtoType::Array(element, _) => {
+                // A Vec already breaks a type's recursion
+                let element = self.rust_type(element, &format!("{}Entry", hint))?;
+                let element = element.strip_prefix("Box<").and_then(|inner| inner.strip_suffix('>')).unwrap_or(&element);
+                format!("Vec<{}>", element)
+            }
+            ProtoType::Encapsulated(inner) => self.rust_type(inner, hint)?,
+            ProtoType::Option(inner) => format!("Option<{}>", self.rust_type(inner, hint)?),
+            ProtoType::Switch(_) => format!("Option<{}>", self.switch_type(proto_type, hint)?),
+            ProtoType::Container(name) => self.container(name)?,
+            ProtoType::Fields(fields) => {
+                let rust_name = self.unique_name(hint);
+                self.generate_struct(&rust_name, fields, "")?;
+                rust_name
+            }
+            ProtoType::Mapper(..) | ProtoType::Bitflags(..) => {
+                // Aliases are resolved when the definitions compile, so the same alias is told apart by its definition
+                let definition = format!("{:?}", proto_type);
+                if let Some(rust_name) = self.enum_names.get(&definition) {
+                    return Ok(rust_name.clone());
+                }
+                let rust_name = match proto_type {
+                    ProtoType::Mapper(_, mappings) => self.mapper(hint, mappings),
+                    ProtoType::Bitflags(_, flags) => self.bitflags(hint, flags),
+                    _ => unreachable!(),
+                };
+                self.enum_names.insert(definition, rust_name.clone());
+                rust_name
+            }
+            ProtoType::Nbt(_) | ProtoType::NbtLoop | ProtoType::AvailableCommands | ProtoType::Custom(_) | ProtoType::Void => {
+                "serde_json::Value".to_string()
+            }
+        };
+        Ok(rust_type)
+    }
+
+    fn container(&mut self, name: &str) -> Result<String> {
+        let rust_name = match self.container_names.get(name) {
+            Some(rust_name) => rust_name.clone(),
+            None => {
+                let rust_name = self.unique_name(&pascal_case(name));
+                self.container_names.insert(name.to_string(), rust_name.clone());
+                let fields = self.parser.container_types(name).ok_or_else(|| anyhow!("Unknown container {}", name))?;
+                self.generate_struct(&rust_name, fields, name).with_context(|| format!("Failed to generate {}", name))?;
+                rust_name
+            }
+        };
+        if self.generating.contains(&rust_name) {
+            Ok(format!("Box<{}>", rust_name))
+        } else {
+            Ok(rust_name)
+        }
+    }
+
+    /// An enum of the mapped names; values without a name decode as numbers, which `Unknown` keeps
+    fn mapper(&mut self, hint: &str, mappings: &[(i128, String)]) -> String {
+        let rust_name = self.unique_name(hint);
+        let mut variants = HashSet::from(["Unknown".to_string()]);
+        let mut source = format!(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]\npub enum {} {{\n",
+            rust_name
+        );
+        for (value, name) in mappings {
+            let variant = unique(pascal_case(name), &mut variants);
+            source.push_str(&format!("    #[serde(rename = \"{}\")]\n    {}, // {}\n", name.escape_default(), variant, value));
+        }
+        source.push_str(&format!("    #[serde(untagged)]\n    Unknown({}::protocol_codegen::Long),\n}}\n", self.crate_path));
+        self.items[&rust_name] = source;
+        rust_name
+    }
+
+    /// A bool per flag, and every bit as `_value`
+    fn bitflags(&mut self, hint: &str, flags: &[(String, u64)]) -> String {
+        let rust_name = self.unique_name(hint);
+        let mut idents = HashSet::new();
+        let mut source = format!(
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]\npub struct {} {{\n",
+            rust_name
+        );
+        for (name, mask) in flags {
+            let (ident, renamed) = field_ident(name, &mut idents);
+            if renamed {
+                source.push_str(&format!("    #[serde(rename = \"{}\")]\n", name.escape_default()));
+            }
+            source.push_str(&format!("    pub {}: bool, // 0x{:x}\n", ident, mask));
+        }
+        let (ident, _) = field_ident("value", &mut idents);
+        source.push_str(&format!("    #[serde(rename = \"_value\")]\n    pub {}: {}::protocol_codegen::Long,\n}}\n", ident, self.crate_path));
+        self.items[&rust_name] = source;
+        rust_name
+    }
+
+    /// `name`, or `name2`, `name3`, ... if it's taken; the name is taken until the type's source replaces the placeholder
+    fn unique_name(&mut self, name: &str) -> String {
+        let mut rust_name = name.to_string();
+        let mut suffix = 1;
+        while self.items.contains_key(&rust_name) || RESERVED_TYPE_NAMES.contains(&rust_name.as_str()) {
+            suffix += 1;
+            rust_name = format!("{}{}", name, suffix);
+        }
+        self.items.insert(rust_name.clone(), String::new());
+        rust_name
+    }
+}
+
+/// Add a field, or merge it with one of the same name from another switch case (any JSON value if the types differ)
+fn add_field(fields: &mut IndexMap<String, Field>, key: &str, field: Field) {
+    match fields.get_mut(key) {
+        Some(existing) => {
+            existing.optional |= field.optional;
+            if existing.rust_type != field.rust_type {
+                existing.rust_type = "serde_json::Value".to_string();
+                existing.optional = true;
+            }
+        }
+        None => {
+            fields.insert(key.to_string(), field);
+        }
+    }
+}
+
+/// `packet_text` -> `PacketText`
+fn pascal_case(name: &str) -> String {
+    let mut result: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+        })
+        .collect();
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result.insert(0, 'T');
+    }
+    result
+}
+
+/// The snake_case Rust identifier for a JSON key, unique among `taken`, and whether it differs from the key
+/// (so it needs a serde rename)
+fn field_ident(key: &str, taken: &mut HashSet<String>) -> (String, bool) {
+    let mut ident = String::new();
+    let mut previous = '_';
+    for c in key.chars() {
+        if c.is_ascii_uppercase() && (previous.is_ascii_lowercase() || previous.is_ascii_digit()) {
+            ident.push('_');
+        }
+        ident.push(if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' });
+        previous = c;
+    }
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') || ident == "_" || ident == "self" || ident == "crate" || ident == "super" {
+        ident.insert(0, '_');
+    }
+    let ident = unique(ident, taken);
+    let renamed = ident != key;
+    if KEYWORDS.contains(&ident.as_str()) {
+        // serde strips the r#
+        return (format!("r#{}", ident), renamed);
+    }
+    (ident, renamed)
+}
+
+fn unique(name: String, taken: &mut HashSet<String>) -> String {
+    let mut unique_name = name.clone();
+    let mut suffix = 1;
+    while taken.contains(&unique_name) {
+        suffix += 1;
+        unique_name = format!("{}{}", name, suffix);
+    }
+    taken.insert(unique_name.clone());
+    unique_name
+}
+
+/// `codegen PROTOCOL PACKET... [--output FILE] [--crate PATH]`
+pub fn codegen(args: &[String]) -> Result<()> {
+    let usage = || {
+        anyhow!("Usage: lazypacket codegen PROTOCOL PACKET... [--output FILE] [--crate PATH] (PROTOCOL is a version or a .yml/.json file)")
+    };
+    let mut output = None;
+    let mut crate_path = "lazypacket".to_string();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => output = Some(args.next().ok_or_else(usage)?.clone()),
+            "--crate" => crate_path = args.next().ok_or_else(usage)?.clone(),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let Some((protocol, packets)) = positional.split_first() else {
+        return Err(usage());
+    };
+    if packets.is_empty() {
+        return Err(usage());
+    }
+
+    let parser = ProtocolParser::load(protocol)?;
+    let source = generate_packet_structs(&parser, packets, &crate_path)?;
+    match output {
+        Some(path) => std::fs::write(&path, source).with_context(|| format!("Failed to write {}", path))?,
+        None => print!("{}", source),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_logger::PacketDirection;
+
+    // What test_generate_packet_structs generates from PROTOCOL, compiled here to check the types work
+    #[allow(dead_code)]
+    mod generated {
+        include!("../tests/fixtures/generated_packets.rs");
+    }
+
+    const PROTOCOL: &str = concat!(
+        "GameMode: [\"mapper\", { type: zigzag32, mappings: { 0: survival, 1: creative } }]\n",
+        "InputFlag: [\"bitflags\", { type: varint64, flags: [ascend, descend, jump] }]\n",
+        "Entry:\n",
+        "  key: string\n",
+        "  value: zigzag64\n",
+        "Node:\n",
+        "  name: string\n",
+        "  children: Node[]varint\n",
+        "  parent?: Node\n",
+        "packet_rewrite:\n",
+        "  \"!id\": 0x44\n",
+        "  \"!bound\": client\n",
+        "  name: string\n",
+        "  mode: GameMode\n",
+        "  flags: InputFlag\n",
+        "  position: vec3f\n",
+        "  entries: Entry[]varint\n",
+        "  tag?: lu64\n",
+        "  type: u8\n",
+        "  runtimeEntityId: varint64\n",
+        "  _:\n",
+        "    \"type?\":\n",
+        "      \"if 1\":\n",
+        "        extra: li32\n",
+        "      \"if 2\":\n",
+        "        extra: li32\n",
+        "        message: string\n",
+        "      default: void\n",
+        "  tree: Node\n",
+        "packet_input:\n",
+        "  \"!id\": 0x90\n",
+        "  \"!bound\": server\n",
+        "  mode: GameMode\n",
+        "  held: Item\n",
+    );
+
+    #[test]
+    fn test_generate_packet_structs() {
+        let parser = ProtocolParser::from_yaml_str(PROTOCOL, "test").unwrap();
+        let packets = ["rewrite".to_string(), "packet_input".to_string()];
+        let source = generate_packet_structs(&parser, &packets, "crate").unwrap();
+        assert_eq!(source, include_str!("../tests/fixtures/generated_packets.rs"));
+
+        assert!(generate_packet_structs(&parser, &["missing".to_string()], "crate").is_err());
+    }
+
+    #[test]
+    fn test_typed_packet_round_trip() {
+        use generated::*;
+
+        let parser = ProtocolParser::from_yaml_str(PROTOCOL, "test").unwrap();
+        let fields = serde_json::json!({
+            "name": "root",
+            "mode": "creative",
+            "flags": { "ascend": true, "descend": false, "jump": true, "_value": 5 },
+            "position": { "x": 1.5, "y": -2.0, "z": 0.25 },
+            "entries": [{ "key": "a", "value": "-5" }],
+            "tag": "18446744073709551615",
+            "type": 2,
+            "runtimeEntityId": 7,
+            "extra": -3,
+            "message": "hi",
+            "tree": { "name": "a", "children": [{ "name": "b", "children": [], "parent": null }], "parent": null },
+        });
+        let data = parser.encode_packet("rewrite", &fields).unwrap();
+        let decoded = parser.decode_packet(&data, PacketDirection::Clientbound);
+
+        let packet = PacketRewrite::from_decoded(&decoded).unwrap();
+        assert_eq!(packet.mode, GameMode::Creative);
+        assert!(packet.flags.jump && !packet.flags.descend);
+        assert_eq!(packet.entries[0].value, Long(-5));
+        assert_eq!(packet.tag, Some(Long(u64::MAX as i128)));
+        assert_eq!(packet.runtime_entity_id, Long(7));
+        assert_eq!((packet.extra, packet.message.as_deref()), (Some(-3), Some("hi")));
+        assert_eq!(packet.tree.children[0].name, "b");
+        assert_eq!(packet.encode(&parser).unwrap(), data);
+
+        // Unmapped values and empty switches
+        let mut fields = fields;
+        fields["mode"] = serde_json::json!(9);
+        fields["type"] = serde_json::json!(0);
+        let data = parser.encode_packet("rewrite", &fields).unwrap();
+        let packet = PacketRewrite::from_decoded(&parser.decode_packet(&data, PacketDirection::Clientbound)).unwrap();
+        assert_eq!(packet.mode, GameMode::Unknown(Long(9)));
+        assert_eq!((packet.extra, packet.message.as_deref()), (None, None));
+        assert_eq!(packet.encode(&parser).unwrap(), data);
+
+        // Another packet isn't converted
+        let input = parser.encode_packet("input", &serde_json::json!({ "mode": "survival", "held": { "network_id": 0 } })).unwrap();
+        let decoded = parser.decode_packet(&input, PacketDirection::Serverbound);
+        assert!(PacketRewrite::from_decoded(&decoded).is_err());
+        assert_eq!(PacketInput::from_decoded(&decoded).unwrap().held.network_id, 0);
+    }
+}
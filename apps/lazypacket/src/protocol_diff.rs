@@ -0,0 +1,313 @@
+// `protocol-diff` subcommand: compares two protocol versions (packets added, removed or renumbered, and
+// field-level changes to packets and named types), to see what a game update changed before captures break
+
+use crate::protocol::{type_text, PacketBound, ProtocolParser};
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+use serde_yaml::Value as YamlValue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufWriter, Write};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldChange {
+    Added { name: String, def: String },
+    Removed { name: String, def: String },
+    Changed { name: String, old: String, new: String },
+    Moved { name: String, from: usize, to: usize }, // Position among the fields both versions have
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketRef {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdChange {
+    pub name: String,
+    pub old_id: u32,
+    pub new_id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketChange {
+    pub name: String,
+    pub bound: Option<(PacketBound, PacketBound)>,
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeChange {
+    Added { name: String },
+    Removed { name: String },
+    Fields { name: String, fields: Vec<FieldChange> },  // Container
+    Definition { name: String, old: String, new: String }, // Alias, or a container that became an alias (or back)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtocolDiff {
+    pub old_version: String,
+    pub new_version: String,
+    pub added_packets: Vec<PacketRef>,
+    pub removed_packets: Vec<PacketRef>,
+    pub changed_ids: Vec<IdChange>,
+    pub changed_packets: Vec<PacketChange>,
+    pub changed_types: Vec<TypeChange>,
+}
+
+impl ProtocolDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_packets.is_empty()
+            && self.removed_packets.is_empty()
+            && self.changed_ids.is_empty()
+            && self.changed_packets.is_empty()
+            && self.changed_types.is_empty()
+    }
+}
+
+/// Field-level differences between two versions of a container, in the new version's order
+pub fn diff_fields(old: &IndexMap<String, YamlValue>, new: &IndexMap<String, YamlValue>) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    for (name, def) in old {
+        if !new.contains_key(name) {
+            changes.push(FieldChange::Removed { name: name.clone(), def: type_text(def) });
+        }
+    }
+    for (name, def) in new {
+        match old.get(name) {
+            None => changes.push(FieldChange::Added { name: name.clone(), def: type_text(def) }),
+            Some(old_def) if old_def != def => changes.push(FieldChange::Changed {
+                name: name.clone(),
+                old: type_text(old_def),
+                new: type_text(def),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    // Fields are decoded in declaration order, so a reordering changes the wire format
+    let old_order: Vec<&String> = old.keys().filter(|name| new.contains_key(*name)).collect();
+    let new_order: Vec<&String> = new.keys().filter(|name| old.contains_key(*name)).collect();
+    for (to, name) in new_order.iter().enumerate() {
+        let from = old_order.iter().position(|old_name| old_name == name).unwrap_or(to);
+        if from != to {
+            changes.push(FieldChange::Moved { name: (*name).clone(), from, to });
+        }
+    }
+    changes
+}
+
+/// Differences from `old` to `new`, matching packets and types by name
+pub fn diff_protocols(old: &ProtocolParser, new: &ProtocolParser) -> ProtocolDiff {
+    let mut diff = ProtocolDiff {
+        old_version: old.version().to_string(),
+        new_version: new.version().to_string(),
+        ..Default::default()
+    };
+
+    let old_packets: BTreeMap<&str, _> = old.packets().map(|info| (info.name.as_str(), info)).collect();
+    let new_packets: BTreeMap<&str, _> = new.packets().map(|info| (info.name.as_str(), info)).collect();
+    for (name, info) in &old_packets {
+        if !new_packets.contains_key(name) {
+            diff.removed_packets.push(PacketRef { id: info.id, name: name.to_string() });
+        }
+    }
+    for (name, info) in &new_packets {
+        let Some(old_info) = old_packets.get(name) else {
+            diff.added_packets.push(PacketRef { id: info.id, name: name.to_string() });
+            continue;
+        };
+        if old_info.id != info.id {
+            diff.changed_ids.push(IdChange { name: name.to_string(), old_id: old_info.id, new_id: info.id });
+        }
+        let bound = (old_info.bound != info.bound).then_some((old_info.bound, info.bound));
+        let fields = diff_fields(&old_info.fields, &info.fields);
+        if bound.is_some() || !fields.is_empty() {
+            diff.changed_packets.push(PacketChange { name: name.to_string(), bound, fields });
+        }
+    }
+
+    // Named types: containers compare field by field, aliases as a whole
+    let names: BTreeSet<&String> = [old, new]
+        .iter()
+        .flat_map(|parser| parser.type_aliases().keys().chain(parser.containers().keys()))
+        .collect();
+    for name in names {
+        let change = match (old.containers().get(name), new.containers().get(name)) {
+            (Some(old_fields), Some(new_fields)) => {
+                let fields = diff_fields(old_fields, new_fields);
+                (!fields.is_empty()).then(|| TypeChange::Fields { name: name.clone(), fields })
+            }
+            (old_fields, new_fields) => {
+                let as_text = |fields: Option<&IndexMap<String, YamlValue>>, parser: &ProtocolParser| {
+                    match fields {
+                        Some(fields) => Some(format!("container of {} fields", fields.len())),
+                        None => parser.type_aliases().get(name).map(type_text),
+                    }
+                };
+                match (as_text(old_fields, old), as_text(new_fields, new)) {
+                    (None, Some(_)) => Some(TypeChange::Added { name: name.clone() }),
+                    (Some(_), None) => Some(TypeChange::Removed { name: name.clone() }),
+                    (Some(old_def), Some(new_def)) if old_def != new_def || old_fields.is_some() != new_fields.is_some() => {
+                        Some(TypeChange::Definition { name: name.clone(), old: old_def, new: new_def })
+                    }
+                    _ => None,
+                }
+            }
+        };
+        diff.changed_types.extend(change);
+    }
+
+    diff.added_packets.sort_by_key(|packet| packet.id);
+    diff.removed_packets.sort_by_key(|packet| packet.id);
+    diff
+}
+
+fn bound_name(bound: PacketBound) -> &'static str {
+    match bound {
+        PacketBound::Client => "client",
+        PacketBound::Server => "server",
+        PacketBound::Both => "both",
+    }
+}
+
+fn format_field_change(change: &FieldChange) -> String {
+    match change {
+        FieldChange::Added { name, def } => format!("+ {}: {}", name, def),
+        FieldChange::Removed { name, def } => format!("- {}: {}", name, def),
+        FieldChange::Changed { name, old, new } => format!("~ {}: {} -> {}", name, old, new),
+        FieldChange::Moved { name, from, to } => format!("~ {} moved from position {} to {}", name, from + 1, to + 1),
+    }
+}
+
+/// Sections of `+` added, `-` removed and `~` changed entries
+pub fn format_diff(diff: &ProtocolDiff) -> Vec<String> {
+    let mut lines = vec![format!("Protocol {} -> {}", diff.old_version, diff.new_version)];
+    if diff.is_empty() {
+        lines.push("No differences".to_string());
+        return lines;
+    }
+
+    let mut section = |title: &str, entries: Vec<String>| {
+        if !entries.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("{} ({}):", title, entries.iter().filter(|e| !e.starts_with("    ")).count()));
+            lines.extend(entries.into_iter().map(|entry| format!("  {}", entry)));
+        }
+    };
+    section(
+        "Added packets",
+        diff.added_packets.iter().map(|p| format!("+ 0x{:02x} {}", p.id, p.name)).collect(),
+    );
+    section(
+        "Removed packets",
+        diff.removed_packets.iter().map(|p| format!("- 0x{:02x} {}", p.id, p.name)).collect(),
+    );
+    section(
+        "Changed packet IDs",
+        diff.changed_ids.iter().map(|c| format!("~ {}: 0x{:02x} -> 0x{:02x}", c.name, c.old_id, c.new_id)).collect(),
+    );
+    section(
+        "Changed packets",
+        diff.changed_packets
+            .iter()
+            .flat_map(|change| {
+                let bound = change
+                    .bound
+                    .map(|(old, new)| format!("    ~ bound: {} -> {}", bound_name(old), bound_name(new)));
+                std::iter::once(format!("~ {}", change.name))
+                    .chain(bound)
+                    .chain(change.fields.iter().map(|field| format!("    {}", format_field_change(field))))
+            })
+            .collect(),
+    );
+    section(
+        "Changed types",
+        diff.changed_types
+            .iter()
+            .flat_map(|change| match change {
+                TypeChange::Added { name } => vec![format!("+ {}", name)],
+                TypeChange::Removed { name } => vec![format!("- {}", name)],
+                TypeChange::Definition { name, old, new } => vec![format!("~ {}: {} -> {}", name, old, new)],
+                TypeChange::Fields { name, fields } => std::iter::once(format!("~ {}", name))
+                    .chain(fields.iter().map(|field| format!("    {}", format_field_change(field))))
+                    .collect(),
+            })
+            .collect(),
+    );
+    lines
+}
+
+/// `protocol-diff OLD NEW`
+pub fn protocol_diff(args: &[String]) -> Result<()> {
+    let [old, new] = args else {
+        return Err(anyhow!("Usage: lazypacket protocol-diff OLD NEW (protocol versions or .yml/.json files)"));
+    };
+    let diff = diff_protocols(&ProtocolParser::load(old)?, &ProtocolParser::load(new)?);
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for line in format_diff(&diff) {
+        writeln!(out, "{}", line)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_protocols() {
+        let old = ProtocolParser::from_yaml_str(
+            concat!(
+                "Item:\n  id: varint\n  count: u8\n",
+                "packet_text:\n  \"!id\": 0x09\n  \"!bound\": both\n  type: u8\n  message: string\n  xuid: string\n",
+                "packet_old:\n  \"!id\": 0x10\n  \"!bound\": client\n  value: u8\n",
+                "packet_moved:\n  \"!id\": 0x11\n  \"!bound\": client\n  value: u8\n",
+            ),
+            "1.0",
+        )
+        .unwrap();
+        let new = ProtocolParser::from_yaml_str(
+            concat!(
+                "Item:\n  id: varint\n  count: varint\n",
+                "packet_text:\n  \"!id\": 0x09\n  \"!bound\": both\n  message: string\n  type: varint\n  platform_id: string\n",
+                "packet_moved:\n  \"!id\": 0x12\n  \"!bound\": client\n  value: u8\n",
+                "packet_new:\n  \"!id\": 0x13\n  \"!bound\": server\n  value: u8\n",
+            ),
+            "1.1",
+        )
+        .unwrap();
+
+        let diff = diff_protocols(&old, &new);
+        assert_eq!(diff.added_packets, [PacketRef { id: 0x13, name: "packet_new".to_string() }]);
+        assert_eq!(diff.removed_packets, [PacketRef { id: 0x10, name: "packet_old".to_string() }]);
+        assert_eq!(diff.changed_ids, [IdChange { name: "packet_moved".to_string(), old_id: 0x11, new_id: 0x12 }]);
+        assert_eq!(diff.changed_packets.len(), 1);
+        assert_eq!(
+            diff.changed_packets[0].fields,
+            [
+                FieldChange::Removed { name: "xuid".to_string(), def: "string".to_string() },
+                FieldChange::Changed { name: "type".to_string(), old: "u8".to_string(), new: "varint".to_string() },
+                FieldChange::Added { name: "platform_id".to_string(), def: "string".to_string() },
+                FieldChange::Moved { name: "message".to_string(), from: 1, to: 0 },
+                FieldChange::Moved { name: "type".to_string(), from: 0, to: 1 },
+            ]
+        );
+        assert_eq!(
+            diff.changed_types,
+            [TypeChange::Fields {
+                name: "Item".to_string(),
+                fields: vec![FieldChange::Changed { name: "count".to_string(), old: "u8".to_string(), new: "varint".to_string() }],
+            }]
+        );
+
+        let lines = format_diff(&diff);
+        assert_eq!(lines[0], "Protocol 1.0 -> 1.1");
+        assert!(lines.contains(&"Changed packets (1):".to_string()));
+        assert!(lines.contains(&"      ~ type: u8 -> varint".to_string()));
+        assert_eq!(format_diff(&diff_protocols(&old, &old))[1], "No differences");
+    }
+}
@@ -0,0 +1,81 @@
+// Protocol parsers by version, loaded the first time a version is asked for: logs record the version each
+// packet was captured with, and decoding it with another version's definitions gives wrong fields
+
+use crate::protocol::{ProtocolParser, PROTOCOL_DIR};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub struct ProtocolRegistry {
+    dir: PathBuf,
+    parsers: Mutex<HashMap<String, Option<Arc<ProtocolParser>>>>, // None for versions that failed to load
+}
+
+impl Default for ProtocolRegistry {
+    fn default() -> Self {
+        Self::new(Path::new(PROTOCOL_DIR))
+    }
+}
+
+impl ProtocolRegistry {
+    /// Definitions in `dir`, as proto-<version>.yml or protocol-<version>.json
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf(), parsers: Mutex::new(HashMap::new()) }
+    }
+
+    /// The parser for `version`, or None if there are no definitions for it (or they don't load). Loading is
+    /// slow, so it's done once per version, failures included
+    pub fn get(&self, version: &str) -> Option<Arc<ProtocolParser>> {
+        let mut parsers = self.parsers.lock().unwrap_or_else(|e| e.into_inner());
+        parsers
+            .entry(version.to_string())
+            .or_insert_with(|| ProtocolParser::load_from_dir(&self.dir, version).ok().map(Arc::new))
+            .clone()
+    }
+
+    /// Versions there are definitions for, sorted
+    pub fn versions(&self) -> Vec<String> {
+        let mut versions: Vec<String> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let version = name
+                    .strip_prefix("proto-")
+                    .and_then(|rest| rest.strip_suffix(".yml"))
+                    .or_else(|| name.strip_prefix("protocol-").and_then(|rest| rest.strip_suffix(".json")))?;
+                Some(version.to_string())
+            })
+            .collect();
+        versions.sort_unstable();
+        versions.dedup();
+        versions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_loads_each_version_once() {
+        let dir = std::env::temp_dir().join(format!("lazypacket-protocols-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let packet = |id: u8| format!("packet_text:\n  \"!id\": {}\n  \"!bound\": both\n  message: string\n", id);
+        std::fs::write(dir.join("proto-1.0.yml"), packet(0x09)).unwrap();
+        std::fs::write(dir.join("proto-2.0.yml"), packet(0x0a)).unwrap();
+        std::fs::write(dir.join("proto-broken.yml"), "packet_text: [").unwrap();
+        std::fs::write(dir.join("README.md"), "").unwrap();
+
+        let registry = ProtocolRegistry::new(&dir);
+        assert_eq!(registry.versions(), ["1.0", "2.0", "broken"]);
+        let v1 = registry.get("1.0").unwrap();
+        assert_eq!(v1.get_packet_info_by_name("text").unwrap().id, 0x09);
+        assert_eq!(registry.get("2.0").unwrap().get_packet_info_by_name("text").unwrap().id, 0x0a);
+        assert!(Arc::ptr_eq(&v1, &registry.get("1.0").unwrap()));
+        assert!(registry.get("broken").is_none());
+        assert!(registry.get("3.0").is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+// `proto validate` subcommand: loads a protocol definition and reports what would break decoding (unknown
+// types, duplicate packet IDs, unreadable count types, fields the decoder can't handle), so mistakes in a
+// definition file show up before a capture session rather than as decode errors during one
+
+use crate::protocol::{CompiledField, PacketBound, ProtoType, ProtocolParser};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueKind {
+    UnknownType,
+    DuplicateId,
+    CountType,         // A length or count prefix the decoder can't read
+    InvalidDefinition, // Any other definition that doesn't compile, or a switch on a field that isn't decoded first
+    Unsupported,       // Compiles, but doesn't decode into fields (a warning)
+}
+
+impl IssueKind {
+    pub fn is_error(self) -> bool {
+        self != IssueKind::Unsupported
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            IssueKind::UnknownType => "unknown type",
+            IssueKind::DuplicateId => "duplicate ID",
+            IssueKind::CountType => "count type",
+            IssueKind::InvalidDefinition => "invalid definition",
+            IssueKind::Unsupported => "unsupported",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub location: String, // Packet or type, and the path to the field: "packet_text.entries[].key"
+    pub message: String,
+}
+
+/// Problems in the loaded definitions, packets first (by name), then named types
+pub fn validate(parser: &ProtocolParser) -> Vec<Issue> {
+    let mut issues = duplicate_ids(parser);
+
+    let mut packets: Vec<_> = parser.packets().map(|info| info.name.as_str()).collect();
+    packets.sort_unstable();
+    for name in packets {
+        if let Some(fields) = parser.packet_types(name) {
+            check_fields(fields, name, &mut Vec::new(), &mut issues);
+        }
+    }
+    let mut containers: Vec<_> = parser.containers().keys().map(String::as_str).collect();
+    containers.sort_unstable();
+    for name in containers {
+        if let Some(fields) = parser.container_types(name) {
+            check_fields(fields, name, &mut Vec::new(), &mut issues);
+        }
+    }
+    issues
+}
+
+/// Packets sent the same way with the same ID: only one of them can ever be decoded
+fn duplicate_ids(parser: &ProtocolParser) -> Vec<Issue> {
+    let mut by_id: BTreeMap<(u32, &str), Vec<&str>> = BTreeMap::new();
+    for info in parser.packets() {
+        if info.bound != PacketBound::Server {
+            by_id.entry((info.id, "clientbound")).or_default().push(&info.name);
+        }
+        if info.bound != PacketBound::Client {
+            by_id.entry((info.id, "serverbound")).or_default().push(&info.name);
+        }
+    }
+    by_id
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|((id, direction), mut names)| {
+            names.sort_unstable();
+            Issue {
+                kind: IssueKind::DuplicateId,
+                location: names[0].to_string(),
+                message: format!("0x{:02x} is the {} ID of {}", id, direction, names.join(", ")),
+            }
+        })
+        .collect()
+}
+
+/// `scopes` has the keys decoded so far in each enclosing container, innermost last, for checking switches
+fn check_fields(fields: &[CompiledField], location: &str, scopes: &mut Vec<Vec<String>>, issues: &mut Vec<Issue>) {
+    scopes.push(Vec::new());
+    let mut rest_buffer: Option<&str> = None;
+    for field in fields {
+        let path = format!("{}.{}", location, field.key.as_deref().unwrap_or(&field.name));
+        if let Some(rest_buffer) = rest_buffer {
+            issues.push(Issue {
+                kind: IssueKind::InvalidDefinition,
+                location: path,
+                message: format!("Never decoded: {} before it reads the rest of the packet", rest_buffer),
+            });
+            continue;
+        }
+        match &field.proto_type {
+            Ok(proto_type) => {
+                check_type(proto_type, &path, scopes, issues);
+                if matches!(proto_type, ProtoType::RestBuffer) {
+                    rest_buffer = Some(&field.name);
+                }
+                let scope = scopes.last_mut().expect("pushed above");
+                match &field.key {
+                    Some(key) => scope.push(key.clone()),
+                    None => merged_keys(proto_type, scope),
+                }
+            }
+            Err(message) => issues.push(Issue { kind: compile_error_kind(message), location: path, message: message.clone() }),
+        }
+    }
+    scopes.pop();
+}
+
+fn check_type(proto_type: &ProtoType, path: &str, scopes: &mut Vec<Vec<String>>, issues: &mut Vec<Issue>) {
+    match proto_type {
+        ProtoType::Array(element, _) => check_type(element, &format!("{}[]", path), scopes, issues),
+        ProtoType::Encapsulated(inner)
+        | ProtoType::Option(inner)
+        | ProtoType::Mapper(inner, _)
+        | ProtoType::Bitflags(inner, _) => check_type(inner, path, scopes, issues),
+        ProtoType::Fields(fields) => check_fields(fields, path, scopes, issues),
+        ProtoType::Switch(switch) => {
+            if !switch_field_decoded(&switch.compare_to, scopes) {
+                issues.push(Issue {
+                    kind: IssueKind::InvalidDefinition,
+                    location: path.to_string(),
+                    message: format!("Switches on {}, which isn't decoded before it", switch.compare_to),
+                });
+            }
+            for case in switch.cases.iter().map(|(_, case)| case).chain(switch.default.as_deref()) {
+                check_type(case, path, scopes, issues);
+            }
+        }
+        ProtoType::Native(name) => issues.push(Issue {
+            kind: IssueKind::Unsupported,
+            location: path.to_string(),
+            message: format!("No decoder for native type {}: it's shown as the raw bytes left in the packet (up to 1 KB)", name),
+        }),
+        // Named containers are checked on their own
+        _ => {}
+    }
+}
+
+/// Whether a switch's `compareTo` names a field decoded before it. Paths that go up out of a named container
+/// depend on where it's used, so they aren't checked
+fn switch_field_decoded(compare_to: &str, scopes: &[Vec<String>]) -> bool {
+    let mut name = compare_to;
+    let mut depth = 0;
+    while let Some(rest) = name.strip_prefix("../") {
+        name = rest;
+        depth += 1;
+    }
+    match scopes.len().checked_sub(depth + 1) {
+        Some(index) => scopes[index].iter().any(|key| key == name),
+        None => true,
+    }
+}
+
+/// Keys an anonymous field adds to its container
+fn merged_keys(proto_type: &ProtoType, scope: &mut Vec<String>) {
+    match proto_type {
+        ProtoType::Fields(fields) => {
+            for field in fields {
+                match (&field.key, &field.proto_type) {
+                    (Some(key), _) => scope.push(key.clone()),
+                    (None, Ok(proto_type)) => merged_keys(proto_type, scope),
+                    (None, Err(_)) => {}
+                }
+            }
+        }
+        ProtoType::Switch(switch) => {
+            for case in switch.cases.iter().map(|(_, case)| case).chain(switch.default.as_deref()) {
+                merged_keys(case, scope);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Errors from compiling a field definition, by their message
+fn compile_error_kind(message: &str) -> IssueKind {
+    if message.starts_with("Unknown type") || message.starts_with("Container '") {
+        IssueKind::UnknownType
+    } else if message.starts_with("Unknown count type") || message.starts_with("Unsupported array count") {
+        IssueKind::CountType
+    } else {
+        IssueKind::InvalidDefinition
+    }
+}
+
+pub fn format_issues(issues: &[Issue]) -> Vec<String> {
+    issues
+        .iter()
+        .map(|issue| {
+            let severity = if issue.kind.is_error() { "error" } else { "warning" };
+            format!("{} [{}] {}: {}", severity, issue.kind.label(), issue.location, issue.message)
+        })
+        .collect()
+}
+
+/// `proto validate PROTOCOL`; fails if there are errors, so it can gate a capture or CI job
+pub fn proto(args: &[String]) -> Result<()> {
+    let usage = "Usage: lazypacket proto validate PROTOCOL (a protocol version or a .yml/.json file)";
+    let [command, spec] = args else {
+        return Err(anyhow!(usage));
+    };
+    if command != "validate" {
+        return Err(anyhow!(usage));
+    }
+
+    let parser = ProtocolParser::load(spec)?;
+    let issues = validate(&parser);
+    let errors = issues.iter().filter(|issue| issue.kind.is_error()).count();
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for line in format_issues(&issues) {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(
+        out,
+        "{} packets, {} types: {} errors, {} warnings",
+        parser.packet_count(),
+        parser.containers().len() + parser.type_aliases().len(),
+        errors,
+        issues.len() - errors
+    )?;
+    out.flush()?;
+
+    if errors > 0 {
+        return Err(anyhow!("{} has {} errors", spec, errors));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_protocol() {
+        let parser = ProtocolParser::from_yaml_str(
+            concat!(
+                "Entry:\n",
+                "  key: string\n",
+                "  value: Missing\n",
+                "packet_a:\n",
+                "  \"!id\": 0x09\n",
+                "  \"!bound\": both\n",
+                "  count: u8\n",
+                "  entries: Entry[]nibble\n",
+                "  _:\n",
+                "    \"count?\":\n",
+                "      \"if 1\":\n",
+                "        data: [\"switch\", { compareTo: later, fields: { \"1\": u8 } }]\n",
+                "        up: [\"switch\", { compareTo: ../count, fields: { \"1\": u8 } }]\n",
+                "  blob: native:MapInfo\n",
+                "  rest: restBuffer\n",
+                "  after: u8\n",
+                "  later: u8\n",
+                "packet_b:\n",
+                "  \"!id\": 0x09\n",
+                "  \"!bound\": client\n",
+                "  value: u8\n",
+                "packet_c:\n",
+                "  \"!id\": 0x09\n",
+                "  \"!bound\": client\n",
+                "  kind: u8\n",
+                "  value: [\"switch\", { compareTo: kind, fields: { \"1\": lu16 } }]\n",
+            ),
+            "test",
+        )
+        .unwrap();
+
+        let issues: Vec<_> = validate(&parser).into_iter().map(|issue| (issue.kind, issue.location)).collect();
+        assert_eq!(
+            issues,
+            [
+                (IssueKind::DuplicateId, "packet_a".to_string()),
+                (IssueKind::CountType, "packet_a.entries".to_string()),
+                (IssueKind::InvalidDefinition, "packet_a._.data".to_string()),
+                (IssueKind::Unsupported, "packet_a.blob".to_string()),
+                (IssueKind::InvalidDefinition, "packet_a.after".to_string()),
+                (IssueKind::InvalidDefinition, "packet_a.later".to_string()),
+                (IssueKind::UnknownType, "Entry.value".to_string()),
+            ]
+        );
+        let lines = format_issues(&validate(&parser));
+        assert_eq!(lines[0], "error [duplicate ID] packet_a: 0x09 is the clientbound ID of packet_a, packet_b, packet_c");
+        assert!(lines[3].starts_with("warning [unsupported] packet_a.blob: No decoder for native type MapInfo"), "{}", lines[3]);
+    }
+}
@@ -0,0 +1,254 @@
+// Retention: delete sessions older than a maximum age, then the oldest ones beyond a total size
+// Same rules as the relay's background task (apps/relay/src/lib/retention), and the same settings:
+//   RETENTION_MAX_AGE_DAYS     - sessions that started more than this many days ago
+//   RETENTION_MAX_TOTAL_GB     - oldest finished sessions while packets take more space than this
+//   RETENTION_UNTAGGED_ONLY    - true to never delete sessions that have tags
+//   RETENTION_INTERVAL_MINUTES - how often a background task prunes (default 60)
+
+use crate::db::{DeletedSession, SessionUsage};
+use crate::storage::{self, Storage};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<f64>,
+    pub max_total_bytes: Option<i64>,
+    pub untagged_only: bool,
+    pub interval: Duration, // Between runs of a background task
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: None,
+            max_total_bytes: None,
+            untagged_only: false,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn from_env() -> Result<Self> {
+        let positive = |name: &str| -> Result<Option<f64>> {
+            match std::env::var(name) {
+                Ok(value) if !value.is_empty() => parse_positive(&value).with_context(|| format!("Invalid {}", name)).map(Some),
+                _ => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            max_age_days: positive("RETENTION_MAX_AGE_DAYS")?,
+            max_total_bytes: positive("RETENTION_MAX_TOTAL_GB")?.map(|gb| (gb * GB) as i64),
+            untagged_only: match std::env::var("RETENTION_UNTAGGED_ONLY") {
+                Ok(value) => value.parse().context("Invalid RETENTION_UNTAGGED_ONLY")?,
+                Err(_) => false,
+            },
+            interval: positive("RETENTION_INTERVAL_MINUTES")?
+                .map(|minutes| Duration::from_secs_f64(minutes * 60.0))
+                .unwrap_or(DEFAULT_INTERVAL),
+        })
+    }
+
+    /// Whether any limit is set (without one nothing is ever deleted)
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days.is_some() || self.max_total_bytes.is_some()
+    }
+}
+
+fn parse_positive(value: &str) -> Result<f64> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Ok(number),
+        _ => Err(anyhow!("expected a positive number, got {}", value)),
+    }
+}
+
+/// Sessions the policy deletes, oldest first
+/// Sessions older than max_age_days go first; then, while the rest exceed max_total_bytes, the oldest finished
+/// ones. Protected sessions (and tagged ones with untagged_only) are never picked, and sessions that haven't
+/// ended are only removed by age.
+pub fn select_sessions(
+    usage: &[SessionUsage],
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    protected: &HashSet<i32>,
+) -> Vec<i32> {
+    let mut oldest_first: Vec<&SessionUsage> = usage.iter().collect();
+    oldest_first.sort_by_key(|session| session.started_at);
+    let eligible = |session: &SessionUsage| {
+        !protected.contains(&session.session_id) && (!policy.untagged_only || !session.tagged)
+    };
+    let mut selected = HashSet::new();
+
+    if let Some(days) = policy.max_age_days {
+        let cutoff = now - chrono::Duration::milliseconds((days * 24.0 * 60.0 * 60.0 * 1000.0) as i64);
+        for session in oldest_first.iter().filter(|s| eligible(s) && s.started_at < cutoff) {
+            selected.insert(session.session_id);
+        }
+    }
+
+    if let Some(max_bytes) = policy.max_total_bytes {
+        let mut total: i64 = oldest_first
+            .iter()
+            .filter(|s| !selected.contains(&s.session_id))
+            .map(|s| s.bytes)
+            .sum();
+        for session in oldest_first.iter().filter(|s| eligible(s)) {
+            if total <= max_bytes {
+                break;
+            }
+            if session.ended_at.is_some() && selected.insert(session.session_id) {
+                total -= session.bytes;
+            }
+        }
+    }
+
+    oldest_first
+        .iter()
+        .filter(|s| selected.contains(&s.session_id))
+        .map(|s| s.session_id)
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub deleted: Vec<(i32, DeletedSession)>,
+    pub bytes: i64, // Packet bytes of the deleted sessions
+}
+
+/// Delete the sessions the policy selects (see select_sessions)
+pub async fn prune_sessions(db: &dyn Storage, policy: &RetentionPolicy, protected: &HashSet<i32>) -> Result<PruneReport> {
+    let usage = db.get_session_usage().await?;
+    let mut report = PruneReport::default();
+    for session_id in select_sessions(&usage, policy, Utc::now(), protected) {
+        let deleted = db.delete_session(session_id).await?;
+        report.bytes += usage.iter().find(|u| u.session_id == session_id).map_or(0, |u| u.bytes);
+        report.deleted.push((session_id, deleted));
+    }
+    Ok(report)
+}
+
+/// Prune now and then every policy.interval until the task is aborted; failures are logged and retried next time
+/// Protected sessions (e.g. the ones being captured) are never deleted
+pub fn spawn(db: Arc<dyn Storage>, policy: RetentionPolicy, protected: HashSet<i32>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(policy.interval);
+        loop {
+            interval.tick().await;
+            match prune_sessions(db.as_ref(), &policy, &protected).await {
+                Ok(report) if !report.deleted.is_empty() => {
+                    eprintln!(
+                        "Retention: deleted {} sessions ({} bytes of packets)",
+                        report.deleted.len(),
+                        report.bytes
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Retention: failed to prune sessions: {:#}", e),
+            }
+        }
+    })
+}
+
+/// `prune [--max-age-days N] [--max-total-gb N] [--untagged-only] [--dry-run | --watch]`
+/// Flags override the RETENTION_* settings; --watch keeps pruning every RETENTION_INTERVAL_MINUTES until interrupted
+pub async fn prune(args: &[String]) -> Result<()> {
+    let mut policy = RetentionPolicy::from_env()?;
+    let mut dry_run = false;
+    let mut watch = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |flag: &str| -> Result<f64> {
+            let value = iter.next().ok_or_else(|| anyhow!("{} requires a value", flag))?;
+            parse_positive(value).with_context(|| format!("Invalid {}", flag))
+        };
+        match arg.as_str() {
+            "--max-age-days" => policy.max_age_days = Some(value("--max-age-days")?),
+            "--max-total-gb" => policy.max_total_bytes = Some((value("--max-total-gb")? * GB) as i64),
+            "--untagged-only" => policy.untagged_only = true,
+            "--dry-run" => dry_run = true,
+            "--watch" => watch = true,
+            _ => return Err(anyhow!("Unexpected argument: {}", arg)),
+        }
+    }
+    if dry_run && watch {
+        return Err(anyhow!("--dry-run and --watch can't be used together"));
+    }
+    if !policy.is_enabled() {
+        return Err(anyhow!(
+            "No retention limit set: use --max-age-days / --max-total-gb or RETENTION_MAX_AGE_DAYS / RETENTION_MAX_TOTAL_GB"
+        ));
+    }
+
+    let db = storage::connect().await?;
+    if watch {
+        // The background task the relay and captures run, in the foreground; it only ends with the process
+        eprintln!("Pruning every {} minutes", policy.interval.as_secs_f64() / 60.0);
+        return spawn(Arc::from(db), policy, HashSet::new()).await.context("Retention task failed");
+    }
+    if dry_run {
+        let usage = db.get_session_usage().await?;
+        let selected = select_sessions(&usage, &policy, Utc::now(), &HashSet::new());
+        for session in usage.iter().filter(|u| selected.contains(&u.session_id)) {
+            eprintln!(
+                "Would delete session #{} (started {}, {} bytes of packets)",
+                session.session_id,
+                session.started_at.format("%Y-%m-%d %H:%M"),
+                session.bytes
+            );
+        }
+        eprintln!("{} of {} sessions would be deleted", selected.len(), usage.len());
+        return Ok(());
+    }
+
+    let report = prune_sessions(db.as_ref(), &policy, &HashSet::new()).await?;
+    for (session_id, deleted) in &report.deleted {
+        eprintln!("Deleted session #{} ({} packets)", session_id, deleted.packets);
+    }
+    eprintln!("Deleted {} sessions ({} bytes of packets)", report.deleted.len(), report.bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(session_id: i32, age_days: i64, bytes: i64, ended: bool, tagged: bool) -> SessionUsage {
+        let started_at = now() - chrono::Duration::days(age_days);
+        SessionUsage { session_id, started_at, ended_at: ended.then_some(started_at), bytes, tagged }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_719_748_800, 0).unwrap()
+    }
+
+    #[test]
+    fn test_select_sessions() {
+        let usage = vec![
+            session(1, 40, 50, true, true),
+            session(2, 31, 50, true, false),
+            session(3, 3, 50, false, false),
+            session(4, 2, 50, true, false),
+            session(5, 1, 50, true, false),
+        ];
+        let none = HashSet::new();
+
+        let by_age = RetentionPolicy { max_age_days: Some(30.0), ..Default::default() };
+        assert_eq!(select_sessions(&usage, &by_age, now(), &none), vec![1, 2]);
+        let untagged = RetentionPolicy { untagged_only: true, ..by_age.clone() };
+        assert_eq!(select_sessions(&usage, &untagged, now(), &none), vec![2]);
+        assert!(select_sessions(&usage, &untagged, now(), &HashSet::from([2])).is_empty());
+
+        // Session 3 hasn't ended, so session 4 goes after the ones over the age limit
+        let by_size = RetentionPolicy { max_total_bytes: Some(100), ..by_age };
+        assert_eq!(select_sessions(&usage, &by_size, now(), &none), vec![1, 2, 4]);
+    }
+}
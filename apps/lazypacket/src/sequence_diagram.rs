@@ -0,0 +1,148 @@
+// Sequence diagram (`T` in the packet view): packets between the client and the server drawn as arrows with their
+// names and times, for the login handshake or the packets from the current one on. The handshake runs from the
+// first packet to the client's set_local_player_as_initialized, and is checked against the steps a login goes
+// through so a missing one stands out. The diagram is plain text, so it can be copied into documentation
+
+use crate::db::DbPacket;
+
+// Login steps in the order they happen, and whether a login can do without them (the encryption handshake is only
+// done by servers in online mode)
+pub const HANDSHAKE_STEPS: &[(&str, &str, bool)] = &[
+    ("serverbound", "request_network_settings", false),
+    ("clientbound", "network_settings", false),
+    ("serverbound", "login", false),
+    ("clientbound", "server_to_client_handshake", true),
+    ("serverbound", "client_to_server_handshake", true),
+    ("clientbound", "play_status", false),
+    ("clientbound", "resource_packs_info", false),
+    ("serverbound", "resource_pack_client_response", false),
+    ("clientbound", "resource_pack_stack", false),
+    ("clientbound", "start_game", false),
+    ("serverbound", "set_local_player_as_initialized", false),
+];
+pub const HANDSHAKE_END: &str = "set_local_player_as_initialized";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequencePacket {
+    pub packet_number: i64,
+    pub session_time_ms: i64,
+    pub clientbound: bool,
+    pub name: String,
+}
+
+impl From<&DbPacket> for SequencePacket {
+    fn from(packet: &DbPacket) -> Self {
+        Self {
+            packet_number: packet.packet_number,
+            session_time_ms: packet.session_time_ms,
+            clientbound: packet.direction == "clientbound",
+            name: packet.packet["name"].as_str().unwrap_or("unknown").to_string(),
+        }
+    }
+}
+
+/// The handshake: the packets up to and including the client's set_local_player_as_initialized
+pub fn handshake(packets: &[DbPacket]) -> Vec<SequencePacket> {
+    let end = packets
+        .iter()
+        .position(|packet| packet.direction == "serverbound" && packet.packet["name"] == HANDSHAKE_END)
+        .map_or(packets.len(), |index| index + 1);
+    packets[..end].iter().map(SequencePacket::from).collect()
+}
+
+/// Required handshake steps that aren't among the packets
+pub fn missing_steps(packets: &[SequencePacket]) -> Vec<&'static str> {
+    HANDSHAKE_STEPS
+        .iter()
+        .filter(|(direction, name, optional)| {
+            !optional && !packets.iter().any(|p| p.name == *name && p.clientbound == (*direction == "clientbound"))
+        })
+        .map(|(_, name, _)| *name)
+        .collect()
+}
+
+/// Column headings of the diagram, over the lanes of `width` characters diagram_line draws
+pub fn heading(width: usize) -> String {
+    let lane = width.max(12);
+    // Server ends over the server's lane
+    format!("{:>10}  {:<8}  Client{}Server", "time", "packet", " ".repeat(lane - 10))
+}
+
+/// One packet as an arrow between the client's and the server's lanes, `width` characters apart, with its time
+/// since the first packet of the diagram
+pub fn diagram_line(packet: &SequencePacket, start_ms: i64, width: usize) -> String {
+    let lane = width.max(12);
+    let label_width = lane - 6;
+    let name: String = if packet.name.chars().count() > label_width {
+        let mut name: String = packet.name.chars().take(label_width - 1).collect();
+        name.push('…');
+        name
+    } else {
+        packet.name.clone()
+    };
+    let label = format!(" {} ", name);
+    // The label centered on the arrow's shaft, between the head and the lanes
+    let shaft = lane - 1 - label.chars().count();
+    let (left, right) = (shaft / 2, shaft - shaft / 2);
+    let arrow = if packet.clientbound {
+        format!("<{}{}{}", "─".repeat(left), label, "─".repeat(right))
+    } else {
+        format!("{}{}{}>", "─".repeat(left), label, "─".repeat(right))
+    };
+    let time = (packet.session_time_ms - start_ms) as f64 / 1000.0;
+    format!("{:>+9.3}s  #{:<7}  |{}|", time, packet.packet_number, arrow)
+}
+
+/// The whole diagram as text, headings first
+pub fn diagram_text(packets: &[SequencePacket], width: usize) -> String {
+    let start = packets.first().map_or(0, |packet| packet.session_time_ms);
+    std::iter::once(heading(width))
+        .chain(packets.iter().map(|packet| diagram_line(packet, start, width)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn db_packet(packet_number: i64, direction: &str, name: &str) -> DbPacket {
+        DbPacket {
+            id: 0,
+            session_id: 1,
+            ts: Utc::now(),
+            session_time_ms: 1000 + packet_number * 12,
+            packet_number,
+            server_version: "1.21.111".to_string(),
+            direction: direction.to_string(),
+            packet: json!({ "name": name, "params": {} }),
+        }
+    }
+
+    #[test]
+    fn test_sequence_diagram() {
+        let packets = [
+            db_packet(1, "serverbound", "request_network_settings"),
+            db_packet(2, "clientbound", "network_settings"),
+            db_packet(3, "serverbound", "login"),
+            db_packet(4, "clientbound", "play_status"),
+            db_packet(5, "clientbound", "start_game"),
+            db_packet(6, "serverbound", "set_local_player_as_initialized"),
+            db_packet(7, "clientbound", "text"),
+        ];
+        let handshake = handshake(&packets);
+        assert_eq!(handshake.len(), 6);
+        // The encryption handshake is optional
+        assert_eq!(missing_steps(&handshake), ["resource_packs_info", "resource_pack_client_response", "resource_pack_stack"]);
+
+        let text = diagram_text(&handshake[..2], 30);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "      time  packet    Client                    Server");
+        assert_eq!(lines[1], "   +0.000s  #1        |─ request_network_settings ──>|");
+        assert_eq!(lines[2], "   +0.012s  #2        |<───── network_settings ──────|");
+        // Names too long for the lanes are cut short
+        assert_eq!(diagram_line(&handshake[0], 1012, 14), "   +0.000s  #1        |─ request… ──>|");
+    }
+}
@@ -0,0 +1,304 @@
+// Session archives (.lpsession): a database session in one self-contained file, to move captures between
+// machines or attach them to bug reports
+// The archive is gzip-compressed JSON Lines: a header line with the session's times, tags, notes and bookmarks,
+// then one line per packet (ts, session_time_ms, packet_number, server_version, direction, packet).
+// gzip's checksum makes a truncated or damaged archive fail to import instead of importing partially.
+//   export-session [--output FILE] SESSION_ID
+//   import-session FILE...
+
+use crate::db::{NewDbPacket, PacketBookmark};
+use crate::storage::{self, Storage};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const ARCHIVE_FORMAT: &str = "lazypacket-session";
+pub const ARCHIVE_VERSION: u32 = 1;
+pub const ARCHIVE_EXTENSION: &str = "lpsession";
+
+// Packets inserted per write while importing
+const IMPORT_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveHeader {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub session_id: i32, // Id in the exporting database (imports get a new one)
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub packet_notes: BTreeMap<i64, String>, // By packet_number
+    #[serde(default)]
+    pub bookmarks: Vec<PacketBookmark>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivePacket {
+    ts: DateTime<Utc>,
+    session_time_ms: i64,
+    packet_number: i64,
+    server_version: String,
+    direction: String,
+    packet: serde_json::Value,
+}
+
+/// Default archive name for a session: session-12.lpsession
+pub fn default_archive_path(session_id: i32) -> PathBuf {
+    PathBuf::from(format!("session-{}.{}", session_id, ARCHIVE_EXTENSION))
+}
+
+/// Write a session, its tags, notes and bookmarks to an archive; returns the number of packets written
+/// Packets are streamed, so sessions of any length export in constant memory
+pub async fn export_session(db: &dyn Storage, session_id: i32, path: &Path) -> Result<usize> {
+    let session = db
+        .get_session(session_id)
+        .await?
+        .ok_or_else(|| anyhow!("Session #{} does not exist", session_id))?;
+    let header = ArchiveHeader {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: Utc::now(),
+        session_id,
+        started_at: session.started_at,
+        ended_at: session.ended_at,
+        name: db.get_session_name(session_id).await?,
+        tags: db.get_session_tags(session_id).await?,
+        note: db.get_session_note(session_id).await?,
+        packet_notes: db.get_packet_notes(session_id).await?,
+        bookmarks: db.get_packet_bookmarks(session_id).await?,
+    };
+
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = GzEncoder::new(BufWriter::new(file), Compression::default());
+    write_line(&mut writer, &header)?;
+
+    let mut packets = db.stream_packets(session_id, None);
+    let mut count = 0;
+    while let Some(packet) = packets.try_next().await? {
+        write_line(
+            &mut writer,
+            &ArchivePacket {
+                ts: packet.ts,
+                session_time_ms: packet.session_time_ms,
+                packet_number: packet.packet_number,
+                server_version: packet.server_version,
+                direction: packet.direction,
+                packet: packet.packet,
+            },
+        )?;
+        count += 1;
+    }
+
+    writer
+        .finish()
+        .and_then(|mut file| file.flush())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(count)
+}
+
+fn write_line(writer: &mut impl Write, value: &impl Serialize) -> Result<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n").context("Failed to write archive")
+}
+
+fn archive_lines(path: &Path) -> Result<std::io::Lines<BufReader<GzDecoder<File>>>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(BufReader::new(GzDecoder::new(file)).lines())
+}
+
+fn parse_header(path: &Path, lines: &mut impl Iterator<Item = std::io::Result<String>>) -> Result<ArchiveHeader> {
+    let line = lines
+        .next()
+        .ok_or_else(|| anyhow!("{} is empty", path.display()))?
+        .with_context(|| format!("{} is not a session archive", path.display()))?;
+    let header: ArchiveHeader =
+        serde_json::from_str(&line).with_context(|| format!("{} is not a session archive", path.display()))?;
+    if header.format != ARCHIVE_FORMAT {
+        return Err(anyhow!("{} is not a session archive (format {:?})", path.display(), header.format));
+    }
+    if header.version > ARCHIVE_VERSION {
+        return Err(anyhow!(
+            "{} is archive version {}; this lazypacket reads up to version {}",
+            path.display(),
+            header.version,
+            ARCHIVE_VERSION
+        ));
+    }
+    Ok(header)
+}
+
+/// Import an archive as a new session with its tags, notes and bookmarks; returns the new session id and packet count
+/// On failure (including a truncated archive) nothing of the session is left behind
+pub async fn import_session(db: &dyn Storage, path: &Path) -> Result<(i32, usize)> {
+    let mut lines = archive_lines(path)?;
+    let header = parse_header(path, &mut lines)?;
+    let session_id = db.insert_session(header.started_at, header.ended_at).await?;
+
+    match import_contents(db, session_id, &header, lines).await {
+        Ok(count) => Ok((session_id, count)),
+        Err(e) => {
+            if let Err(cleanup) = db.delete_session(session_id).await {
+                eprintln!("Failed to remove partially imported session #{}: {:#}", session_id, cleanup);
+            }
+            Err(e.context(format!("Failed to import {}", path.display())))
+        }
+    }
+}
+
+async fn import_contents(
+    db: &dyn Storage,
+    session_id: i32,
+    header: &ArchiveHeader,
+    lines: impl Iterator<Item = std::io::Result<String>>,
+) -> Result<usize> {
+    let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut count = 0;
+    for (index, line) in lines.enumerate() {
+        let line = line.context("Archive is damaged or truncated")?;
+        let packet: ArchivePacket =
+            serde_json::from_str(&line).with_context(|| format!("Invalid packet on line {}", index + 2))?;
+        batch.push(NewDbPacket {
+            ts: packet.ts,
+            session_time_ms: packet.session_time_ms,
+            packet_number: packet.packet_number,
+            server_version: packet.server_version,
+            direction: packet.direction,
+            packet: packet.packet,
+        });
+        count += 1;
+        if batch.len() == IMPORT_BATCH_SIZE {
+            db.insert_packets(session_id, &batch).await?;
+            batch.clear();
+        }
+    }
+    db.insert_packets(session_id, &batch).await?;
+
+    if let Some(name) = &header.name {
+        db.set_session_name(session_id, name).await?;
+    }
+    for tag in &header.tags {
+        db.add_session_tag(session_id, tag).await?;
+    }
+    if let Some(note) = &header.note {
+        db.set_session_note(session_id, note).await?;
+    }
+    for (packet_number, note) in &header.packet_notes {
+        db.set_packet_note(session_id, *packet_number, note).await?;
+    }
+    for bookmark in &header.bookmarks {
+        db.set_packet_bookmark(session_id, bookmark).await?;
+    }
+    Ok(count)
+}
+
+/// `export-session [--output FILE] SESSION_ID`
+pub async fn export_session_command(args: &[String]) -> Result<()> {
+    let mut output = None;
+    let mut session_id = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(iter.next().ok_or_else(|| anyhow!("--output requires a value"))?));
+            }
+            _ if session_id.is_none() => {
+                session_id = Some(arg.parse::<i32>().with_context(|| format!("Invalid session id: {}", arg))?);
+            }
+            _ => return Err(anyhow!("Unexpected argument: {}", arg)),
+        }
+    }
+    let session_id = session_id.ok_or_else(|| anyhow!("Usage: lazypacket export-session [--output FILE] SESSION_ID"))?;
+    let output = output.unwrap_or_else(|| default_archive_path(session_id));
+
+    let db = storage::connect().await?;
+    let count = export_session(db.as_ref(), session_id, &output).await?;
+    eprintln!("Exported session #{} ({} packets) to {}", session_id, count, output.display());
+    Ok(())
+}
+
+/// `import-session FILE...`
+pub async fn import_session_command(args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        return Err(anyhow!("Usage: lazypacket import-session FILE..."));
+    }
+
+    let db = storage::connect().await?;
+    for path in args.iter().map(Path::new) {
+        let (session_id, count) = import_session(db.as_ref(), path).await?;
+        eprintln!("Imported {} as session #{} ({} packets)", path.display(), session_id, count);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite_db::SqliteDatabase;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_archive_round_trip() {
+        let dir = std::env::temp_dir().join(format!("lazypacket-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = SqliteDatabase::open(dir.join("source.sqlite")).unwrap();
+        let started_at = DateTime::<Utc>::from_timestamp_millis(1_700_000_000_000).unwrap();
+        let session_id = source.insert_session(started_at, None).await.unwrap();
+        let packets: Vec<NewDbPacket> = (1..=3)
+            .map(|n| NewDbPacket {
+                ts: started_at + chrono::Duration::milliseconds(n * 10),
+                session_time_ms: n * 10,
+                packet_number: n,
+                server_version: "1.21.111".to_string(),
+                direction: "clientbound".to_string(),
+                packet: json!({ "name": "text", "params": { "message": format!("hi {}", n) } }),
+            })
+            .collect();
+        source.insert_packets(session_id, &packets).await.unwrap();
+        source.add_session_tag(session_id, "repro").await.unwrap();
+        source.set_session_name(session_id, "nether portal").await.unwrap();
+        source.set_session_note(session_id, "crashes on join").await.unwrap();
+        source.set_packet_note(session_id, 2, "this one").await.unwrap();
+        let bookmark = PacketBookmark { packet_number: 3, label: Some("last".to_string()), color: Default::default() };
+        source.set_packet_bookmark(session_id, &bookmark).await.unwrap();
+
+        let path = dir.join("session.lpsession");
+        assert_eq!(export_session(&source, session_id, &path).await.unwrap(), 3);
+
+        let target = SqliteDatabase::open(dir.join("target.sqlite")).unwrap();
+        let (imported_id, count) = import_session(&target, &path).await.unwrap();
+        assert_eq!(count, 3);
+        let session = target.get_session(imported_id).await.unwrap().unwrap();
+        assert_eq!((session.started_at, session.ended_at), (started_at, None));
+        let imported = target.get_packets(imported_id, None).await.unwrap();
+        assert_eq!(imported[2].packet, packets[2].packet);
+        assert_eq!(target.get_session_tags(imported_id).await.unwrap(), vec!["repro".to_string()]);
+        assert_eq!(target.get_session_name(imported_id).await.unwrap().as_deref(), Some("nether portal"));
+        assert_eq!(target.get_session_note(imported_id).await.unwrap().as_deref(), Some("crashes on join"));
+        assert_eq!(target.get_packet_notes(imported_id).await.unwrap().get(&2).map(String::as_str), Some("this one"));
+        assert_eq!(target.get_packet_bookmarks(imported_id).await.unwrap(), vec![bookmark]);
+
+        // A truncated archive is rejected and leaves no session behind
+        let bytes = std::fs::read(&path).unwrap();
+        let truncated = dir.join("truncated.lpsession");
+        std::fs::write(&truncated, &bytes[..bytes.len() - 12]).unwrap();
+        assert!(import_session(&target, &truncated).await.is_err());
+        assert_eq!(target.get_sessions().await.unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
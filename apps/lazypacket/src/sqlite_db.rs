@@ -0,0 +1,1363 @@
+// SQLite storage backend (DB_BACKEND=sqlite): the same tables as the PostgreSQL schema in a single file,
+// so a capture can be browsed locally without running a database server
+// The relay writes the same file (apps/relay/src/lib/db/sqlite.js); both apply the shared schema on open
+
+use crate::db::{
+    ArchivedSessions, BookmarkColor, DbHealth, DbPacket, DbPacketFilterSet, DeletedSession, FilterPreset, NewDbPacket, PacketBookmark, PacketGap, PacketRateBucket, PacketSearch, PacketSummary, PacketTypeStats, Session, SessionMilestone, SessionPreview,
+    SessionSearch, SessionSummary, SessionUsage, TableHealth, ENTITY_ID_KEYS, SESSION_MILESTONES,
+};
+use crate::storage::{
+    packet_at_time_query, packet_count_query, packet_filter_clause, packet_gaps_query, packet_page_query, packet_search_count_query, packet_rate_from_rows, packet_rate_query, packet_search_query, packet_stats_from_rows, packet_stats_query, session_search_query, table_rows_query, ConnectionStatus,
+    PageCursor, SqlDialect, SqlParam, Storage, FILTER_HISTORY_LIMIT, HEALTH_TABLES, PACKET_COLUMNS, SESSION_RANGE_QUERY,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, params_from_iter, types::Value, Connection, OptionalExtension};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+const SCHEMA: &str = include_str!("../../relay/.ddl/sqlite_schema.sql");
+
+// Timestamps are stored as UTC text, matching strftime('%Y-%m-%d %H:%M:%f') defaults in the schema
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+const SQLITE_DIALECT: SqlDialect = SqlDialect {
+    placeholder: '?',
+    packet_name: "json_extract(packet, '$.name')",
+    packet_size: "length(CAST(packet AS BLOB))",
+    regex_match: "REGEXP", // The regexp() function registered in SqliteDatabase::open
+    like: "LIKE", // Case-insensitive for ASCII, like ILIKE
+    json_path: |path| SqlParam::Text(sqlite_json_path(path)),
+    // json_extract returns booleans as 1/0; spell them the way PostgreSQL's #>> does
+    json_text: |path| format!(
+        "(CASE json_type(packet, {0}) WHEN 'true' THEN 'true' WHEN 'false' THEN 'false' \
+         ELSE CAST(json_extract(packet, {0}) AS TEXT) END)",
+        path
+    ),
+    json_number: |path| format!(
+        "(CASE WHEN json_type(packet, {0}) IN ('integer', 'real') THEN json_extract(packet, {0}) END)",
+        path
+    ),
+    json_contains: None,
+    // Case-insensitive substring rather than PostgreSQL's whole-word match
+    chat_word: |word| format!("instr(lower(json_extract(packet, '$.params.message')), lower({})) > 0", word),
+    text_contains: |text| format!("instr(lower(packet), lower({})) > 0", text),
+    // 64-bit ids are stored as strings, smaller ones as numbers
+    json_entity: |id| format!(
+        "EXISTS (SELECT 1 FROM json_tree(packet) t WHERE t.key IN ('{}') AND CAST(t.atom AS TEXT) = CAST({} AS TEXT))",
+        ENTITY_ID_KEYS.join("', '"),
+        id
+    ),
+    session_duration: "julianday(s.ended_at) - julianday(s.started_at)",
+    session_tags: "(SELECT json_group_array(tag) FROM (SELECT m.tag FROM tag_maps m WHERE m.session_id = s.id ORDER BY m.tag))",
+};
+
+// JSON path like $."params"."entries"[0] for json_extract
+fn sqlite_json_path(path: &[String]) -> String {
+    let mut json_path = String::from("$");
+    for key in path {
+        if key.parse::<usize>().is_ok() {
+            json_path.push_str(&format!("[{}]", key));
+        } else {
+            json_path.push_str(&format!(".\"{}\"", key.replace('"', "")));
+        }
+    }
+    json_path
+}
+
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+// Queries are short, so a blocking connection behind a mutex is enough for both the TUI and DbSink
+pub struct SqliteDatabase {
+    connection: Mutex<Connection>,
+}
+
+/// `text REGEXP pattern` (regexp(pattern, text)), which SQLite leaves to the application. The pattern is compiled
+/// once per statement; NULL text (an undecoded packet's name) never matches
+fn add_regexp_function(connection: &Connection) -> Result<()> {
+    connection
+        .create_scalar_function(
+            "regexp",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let regex: std::sync::Arc<regex::Regex> = ctx.get_or_create_aux(0, |pattern| -> std::result::Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                    Ok(regex::Regex::new(pattern.as_str()?)?)
+                })?;
+                Ok(match ctx.get_raw(1) {
+                    ValueRef::Text(text) => regex.is_match(&String::from_utf8_lossy(text)),
+                    _ => false,
+                })
+            },
+        )
+        .context("Failed to add the SQLite regexp function")
+}
+
+impl SqliteDatabase {
+    /// Open (or create) the database file and apply the schema
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory for {}", path.display()))?;
+        }
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open SQLite database at {}", path.display()))?;
+        // WAL lets lazypacket read while the relay is writing; the busy timeout covers writer contention
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection
+            .execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL; PRAGMA foreign_keys = ON;")
+            .context("Failed to configure SQLite database")?;
+        connection
+            .execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to apply schema to {}", path.display()))?;
+        // Statements are prepared through the cache; filter queries vary with the filter's shape
+        connection.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+        add_regexp_function(&connection)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        // A panic while holding the lock can't leave a half-applied statement behind
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn query_packets(&self, query: &str, session_id: i32, params: &[SqlParam]) -> Result<Vec<DbPacket>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(query)?;
+        let rows = statement.query_map(params_from_iter(sqlite_params(session_id, params)), |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(7)?,
+            ))
+        })?;
+
+        let mut packets = Vec::new();
+        for row in rows {
+            let (id, session_id, ts, session_time_ms, packet_number, server_version, direction, packet) = row?;
+            packets.push(DbPacket {
+                id,
+                session_id,
+                ts: parse_timestamp(&ts)?,
+                session_time_ms,
+                packet_number,
+                server_version,
+                direction,
+                packet: serde_json::from_str(&packet)
+                    .with_context(|| format!("Invalid packet JSON for packet {}", id))?,
+            });
+        }
+
+        Ok(packets)
+    }
+}
+
+fn sqlite_param(param: &SqlParam) -> Value {
+    match param {
+        SqlParam::Text(text) => Value::Text(text.clone()),
+        SqlParam::Int(number) => Value::Integer(*number),
+        // Stored as text in TIMESTAMP_FORMAT, which sorts chronologically
+        SqlParam::Timestamp(ts) => Value::Text(ts.format(TIMESTAMP_FORMAT).to_string()),
+        SqlParam::Float(number) => Value::Real(*number),
+        // Paths are bound as text (sqlite_json_path); arrays aren't used with SQLite
+        SqlParam::TextArray(texts) => Value::Text(texts.join(",")),
+        SqlParam::Json(value) => Value::Text(value.to_string()),
+    }
+}
+
+// Session id followed by the query's other parameters
+fn sqlite_params(session_id: i32, params: &[SqlParam]) -> Vec<Value> {
+    let mut values = vec![Value::Integer(session_id.into())];
+    values.extend(params.iter().map(sqlite_param));
+    values
+}
+
+fn parse_timestamp(text: &str) -> Result<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(text, TIMESTAMP_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S"))
+        .with_context(|| format!("Invalid timestamp: {}", text))?;
+    Ok(DateTime::from_naive_utc_and_offset(naive, Utc))
+}
+
+fn format_timestamp(ts: &DateTime<Utc>) -> String {
+    ts.naive_utc().format(TIMESTAMP_FORMAT).to_string()
+}
+
+#[async_trait]
+impl Storage for SqliteDatabase {
+    async fn get_sessions(&self) -> Result<Vec<Session>> {
+        self.search_sessions(&SessionSearch { archived: ArchivedSessions::Included, ..Default::default() }).await
+    }
+
+    async fn get_session(&self, session_id: i32) -> Result<Option<Session>> {
+        let row = self
+            .connection()
+            .prepare_cached("SELECT id, started_at, ended_at FROM sessions WHERE id = ?1")
+            .and_then(|mut statement| {
+                statement.query_row([session_id], |row| {
+                    Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+                })
+            })
+            .optional()
+            .context("Failed to query session")?;
+
+        row.map(|(id, started_at, ended_at)| {
+            Ok(Session {
+                id,
+                started_at: parse_timestamp(&started_at)?,
+                ended_at: ended_at.as_deref().map(parse_timestamp).transpose()?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn search_session_summaries(&self, search: &SessionSearch) -> Result<Vec<SessionSummary>> {
+        let (query, params) = session_search_query(search, &SQLITE_DIALECT);
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(&query).context("Failed to query sessions")?;
+        let rows = statement
+            .query_map(params_from_iter(params.iter().map(sqlite_param)), |row| {
+                Ok((
+                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, bool>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                ))
+            })
+            .context("Failed to query sessions")?;
+
+        let mut sessions = Vec::new();
+        for row in rows {
+            let (id, started_at, ended_at, packet_count, first_ts, last_ts, tags, archived, name) = row?;
+            sessions.push(SessionSummary {
+                session: Session {
+                    id,
+                    started_at: parse_timestamp(&started_at)?,
+                    ended_at: ended_at.as_deref().map(parse_timestamp).transpose()?,
+                },
+                packet_count: packet_count as usize,
+                first_ts: first_ts.as_deref().map(parse_timestamp).transpose()?,
+                last_ts: last_ts.as_deref().map(parse_timestamp).transpose()?,
+                tags: serde_json::from_str(&tags).context("Invalid session tags")?,
+                archived,
+                name,
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    async fn get_session_usage(&self) -> Result<Vec<SessionUsage>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached(
+                "SELECT s.id, s.started_at, s.ended_at, COALESCE(p.bytes, 0),
+                        EXISTS (SELECT 1 FROM tag_maps t WHERE t.session_id = s.id)
+                 FROM sessions s
+                 LEFT JOIN (
+                     SELECT session_id, SUM(length(CAST(packet AS BLOB))) AS bytes FROM packets GROUP BY session_id
+                 ) p ON p.session_id = s.id",
+            )
+            .context("Failed to query session usage")?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, bool>(4)?,
+                ))
+            })
+            .context("Failed to query session usage")?;
+
+        let mut usage = Vec::new();
+        for row in rows {
+            let (session_id, started_at, ended_at, bytes, tagged) = row?;
+            usage.push(SessionUsage {
+                session_id,
+                started_at: parse_timestamp(&started_at)?,
+                ended_at: ended_at.as_deref().map(parse_timestamp).transpose()?,
+                bytes,
+                tagged,
+            });
+        }
+
+        Ok(usage)
+    }
+
+    async fn get_session_packet_count(&self, session_id: i32) -> Result<usize> {
+        let count: i64 = self
+            .connection()
+            .prepare_cached("SELECT COALESCE((SELECT packet_count FROM session_summaries WHERE session_id = ?1), 0)")
+            .and_then(|mut statement| statement.query_row([session_id], |row| row.get(0)))
+            .context("Failed to count packets")?;
+
+        Ok(count as usize)
+    }
+
+    async fn get_packets(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<DbPacket>> {
+        let (where_clause, params) = packet_filter_clause(filter_set, &SQLITE_DIALECT);
+        let query = format!(
+            "SELECT {} FROM packets WHERE {} ORDER BY packet_number ASC",
+            PACKET_COLUMNS, where_clause
+        );
+        self.query_packets(&query, session_id, &params).context("Failed to query packets")
+    }
+
+    async fn get_packet_page(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        cursor: PageCursor,
+        limit: i64,
+    ) -> Result<Vec<DbPacket>> {
+        let (query, params) = packet_page_query(filter_set, cursor, limit, &SQLITE_DIALECT);
+        let mut packets = self.query_packets(&query, session_id, &params).context("Failed to query packet page")?;
+        if matches!(cursor, PageCursor::Before(_)) {
+            packets.reverse();
+        }
+        Ok(packets)
+    }
+
+    async fn count_packets(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, before: Option<i64>) -> Result<usize> {
+        let (query, params) = packet_count_query(filter_set, before, &SQLITE_DIALECT);
+        let count: i64 = self
+            .connection()
+            .prepare_cached(&query)
+            .and_then(|mut statement| {
+                statement.query_row(params_from_iter(sqlite_params(session_id, &params)), |row| row.get(0))
+            })
+            .context("Failed to count packets")?;
+
+        Ok(count as usize)
+    }
+
+    async fn packet_at_time(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, ts: DateTime<Utc>) -> Result<Option<i64>> {
+        let (query, params) = packet_at_time_query(filter_set, ts, &SQLITE_DIALECT);
+        self.connection()
+            .prepare_cached(&query)
+            .and_then(|mut statement| {
+                statement
+                    .query_row(params_from_iter(sqlite_params(session_id, &params)), |row| row.get(0))
+                    .optional()
+            })
+            .context("Failed to find packet by time")
+    }
+
+    async fn search_packets(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        search: &PacketSearch,
+        cursor: PageCursor,
+        limit: i64,
+    ) -> Result<Vec<DbPacket>> {
+        let (query, params) = packet_search_query(filter_set, search, cursor, limit, &SQLITE_DIALECT);
+        let mut packets = self.query_packets(&query, session_id, &params).context("Failed to search packets")?;
+        if matches!(cursor, PageCursor::Before(_)) {
+            packets.reverse();
+        }
+        Ok(packets)
+    }
+
+    async fn count_search_matches(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        search: &PacketSearch,
+        before: Option<i64>,
+    ) -> Result<usize> {
+        let (query, params) = packet_search_count_query(filter_set, search, before, &SQLITE_DIALECT);
+        let count: i64 = self
+            .connection()
+            .prepare_cached(&query)
+            .and_then(|mut statement| {
+                statement.query_row(params_from_iter(sqlite_params(session_id, &params)), |row| row.get(0))
+            })
+            .context("Failed to count search matches")?;
+
+        Ok(count as usize)
+    }
+
+    async fn get_packet_stats(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<PacketTypeStats>> {
+        let (query, params) = packet_stats_query(filter_set, &SQLITE_DIALECT);
+        let connection = self.connection();
+        let rows = connection
+            .prepare_cached(&query)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params_from_iter(sqlite_params(session_id, &params)), |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .context("Failed to query packet statistics")?;
+
+        Ok(packet_stats_from_rows(rows))
+    }
+
+    async fn get_packet_rate(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<PacketRateBucket>> {
+        let (query, params) = packet_rate_query(filter_set, &SQLITE_DIALECT);
+        let connection = self.connection();
+        let rows = connection
+            .prepare_cached(&query)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params_from_iter(sqlite_params(session_id, &params)), |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .context("Failed to query the packet rate")?;
+
+        Ok(packet_rate_from_rows(rows))
+    }
+
+    async fn get_packet_gaps(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, limit: i64) -> Result<Vec<PacketGap>> {
+        let (query, params) = packet_gaps_query(filter_set, limit, &SQLITE_DIALECT);
+        let connection = self.connection();
+        connection
+            .prepare_cached(&query)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params_from_iter(sqlite_params(session_id, &params)), |row| {
+                        Ok(PacketGap {
+                            previous_packet_number: row.get(0)?,
+                            previous_name: row.get(1)?,
+                            packet_number: row.get(2)?,
+                            name: row.get(3)?,
+                            session_time_ms: row.get(4)?,
+                            gap_ms: row.get(5)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .context("Failed to query packet gaps")
+    }
+
+    async fn get_session_preview(&self, session_id: i32, packet_limit: i64, type_limit: i64) -> Result<SessionPreview> {
+        let connection = self.connection();
+        let summaries = |order: &str| -> Result<Vec<PacketSummary>> {
+            let query = format!(
+                "SELECT packet_number, session_time_ms, direction, json_extract(packet, '$.name') \
+                 FROM packets WHERE session_id = ?1 ORDER BY packet_number {} LIMIT ?2",
+                order
+            );
+            let mut statement = connection.prepare_cached(&query)?;
+            let rows = statement.query_map(params![session_id, packet_limit], |row| {
+                Ok(PacketSummary {
+                    packet_number: row.get(0)?,
+                    session_time_ms: row.get(1)?,
+                    direction: row.get(2)?,
+                    name: row.get(3)?,
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        };
+
+        let first_packets = summaries("ASC").context("Failed to query first packets")?;
+        let mut last_packets = summaries("DESC").context("Failed to query last packets")?;
+        last_packets.reverse();
+
+        let top_packet_types = connection
+            .prepare_cached(
+                "SELECT COALESCE(json_extract(packet, '$.name'), 'unknown'), COUNT(*) FROM packets \
+                 WHERE session_id = ?1 GROUP BY 1 ORDER BY 2 DESC, 1 LIMIT ?2",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params![session_id, type_limit], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<Vec<(String, i64)>>>()
+            })
+            .context("Failed to query packet type counts")?;
+
+        let milestone_placeholders: Vec<String> = (0..SESSION_MILESTONES.len()).map(|i| format!("?{}", i + 2)).collect();
+        let query = format!(
+            "SELECT json_extract(packet, '$.name'), MIN(packet_number), MIN(session_time_ms), COUNT(*) FROM packets \
+             WHERE session_id = ?1 AND direction = 'clientbound' AND json_extract(packet, '$.name') IN ({}) \
+             GROUP BY 1 ORDER BY 2",
+            milestone_placeholders.join(", ")
+        );
+        let mut milestone_params: Vec<&dyn rusqlite::ToSql> = vec![&session_id];
+        milestone_params.extend(SESSION_MILESTONES.iter().map(|(_, packet)| packet as &dyn rusqlite::ToSql));
+        let rows = connection
+            .prepare_cached(&query)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params_from_iter(milestone_params), |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .context("Failed to query session milestones")?;
+        let milestones = rows
+            .into_iter()
+            .filter_map(|(packet_name, packet_number, session_time_ms, count)| {
+                let (milestone, _) = SESSION_MILESTONES.iter().find(|(_, packet)| *packet == packet_name)?;
+                Some(SessionMilestone {
+                    milestone,
+                    packet_number,
+                    session_time_ms,
+                    count,
+                })
+            })
+            .collect();
+
+        Ok(SessionPreview {
+            first_packets,
+            last_packets,
+            top_packet_types,
+            milestones,
+        })
+    }
+
+    async fn get_session_tags(&self, session_id: i32) -> Result<Vec<String>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached("SELECT tag FROM tag_maps WHERE session_id = ?1 ORDER BY tag")
+            .context("Failed to query session tags")?;
+        let tags = statement
+            .query_map([session_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to query session tags")?;
+
+        Ok(tags)
+    }
+
+    async fn add_session_tag(&self, session_id: i32, tag: &str) -> Result<()> {
+        let connection = self.connection();
+        connection
+            .prepare_cached("INSERT INTO tags (tag) VALUES (?1) ON CONFLICT (tag) DO NOTHING")
+            .and_then(|mut statement| statement.execute([tag]))
+            .context("Failed to insert tag")?;
+
+        // Create the tag mapping only if it doesn't exist
+        let exists = connection
+            .prepare_cached("SELECT 1 FROM tag_maps WHERE tag = ?1 AND session_id = ?2")
+            .and_then(|mut statement| statement.query_row(params![tag, session_id], |_| Ok(())))
+            .optional()
+            .context("Failed to check tag mapping")?
+            .is_some();
+        if !exists {
+            connection
+                .prepare_cached("INSERT INTO tag_maps (tag, session_id) VALUES (?1, ?2)")
+                .and_then(|mut statement| statement.execute(params![tag, session_id]))
+                .context("Failed to create tag mapping")?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_session_tag(&self, session_id: i32, tag: &str) -> Result<()> {
+        self.connection()
+            .prepare_cached("DELETE FROM tag_maps WHERE session_id = ?1 AND tag = ?2")
+            .and_then(|mut statement| statement.execute(params![session_id, tag]))
+            .context("Failed to remove tag mapping")?;
+
+        Ok(())
+    }
+
+    async fn get_all_tags(&self) -> Result<Vec<String>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached("SELECT tag FROM tags ORDER BY tag")
+            .context("Failed to query all tags")?;
+        let tags = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to query all tags")?;
+
+        Ok(tags)
+    }
+
+    async fn get_session_note(&self, session_id: i32) -> Result<Option<String>> {
+        self.connection()
+            .prepare_cached("SELECT note FROM session_notes WHERE session_id = ?1")
+            .and_then(|mut statement| statement.query_row([session_id], |row| row.get(0)))
+            .optional()
+            .context("Failed to query session note")
+    }
+
+    async fn set_session_note(&self, session_id: i32, note: &str) -> Result<()> {
+        let connection = self.connection();
+        if note.trim().is_empty() {
+            connection
+                .prepare_cached("DELETE FROM session_notes WHERE session_id = ?1")
+                .and_then(|mut statement| statement.execute([session_id]))
+                .context("Failed to delete session note")?;
+        } else {
+            connection
+                .prepare_cached(
+                    "INSERT INTO session_notes (session_id, note) VALUES (?1, ?2)
+                     ON CONFLICT (session_id) DO UPDATE SET note = excluded.note,
+                         updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now')",
+                )
+                .and_then(|mut statement| statement.execute(params![session_id, note]))
+                .context("Failed to save session note")?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_session_name(&self, session_id: i32) -> Result<Option<String>> {
+        self.connection()
+            .prepare_cached("SELECT name FROM session_names WHERE session_id = ?1")
+            .and_then(|mut statement| statement.query_row([session_id], |row| row.get(0)).optional())
+            .context("Failed to query session name")
+    }
+
+    async fn set_session_name(&self, session_id: i32, name: &str) -> Result<()> {
+        let connection = self.connection();
+        if name.trim().is_empty() {
+            connection
+                .prepare_cached("DELETE FROM session_names WHERE session_id = ?1")
+                .and_then(|mut statement| statement.execute([session_id]))
+                .context("Failed to delete session name")?;
+        } else {
+            connection
+                .prepare_cached(
+                    "INSERT INTO session_names (session_id, name) VALUES (?1, ?2)
+                     ON CONFLICT (session_id) DO UPDATE SET name = excluded.name,
+                         updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now')",
+                )
+                .and_then(|mut statement| statement.execute(params![session_id, name.trim()]))
+                .context("Failed to save session name")?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_session_archived(&self, session_id: i32, archived: bool) -> Result<()> {
+        let sql = if archived {
+            "INSERT INTO archived_sessions (session_id) VALUES (?1) ON CONFLICT (session_id) DO NOTHING"
+        } else {
+            "DELETE FROM archived_sessions WHERE session_id = ?1"
+        };
+        self.connection()
+            .prepare_cached(sql)
+            .and_then(|mut statement| statement.execute([session_id]))
+            .with_context(|| if archived { "Failed to archive session" } else { "Failed to restore session" })?;
+        Ok(())
+    }
+
+    async fn get_packet_notes(&self, session_id: i32) -> Result<BTreeMap<i64, String>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached("SELECT packet_number, note FROM packet_notes WHERE session_id = ?1")
+            .context("Failed to query packet notes")?;
+        let notes = statement
+            .query_map([session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to query packet notes")?;
+
+        Ok(notes)
+    }
+
+    async fn set_packet_note(&self, session_id: i32, packet_number: i64, note: &str) -> Result<()> {
+        let connection = self.connection();
+        if note.trim().is_empty() {
+            connection
+                .prepare_cached("DELETE FROM packet_notes WHERE session_id = ?1 AND packet_number = ?2")
+                .and_then(|mut statement| statement.execute(params![session_id, packet_number]))
+                .context("Failed to delete packet note")?;
+        } else {
+            connection
+                .prepare_cached(
+                    "INSERT INTO packet_notes (session_id, packet_number, note) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (session_id, packet_number) DO UPDATE SET note = excluded.note,
+                         updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now')",
+                )
+                .and_then(|mut statement| statement.execute(params![session_id, packet_number, note]))
+                .context("Failed to save packet note")?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_packet_bookmarks(&self, session_id: i32) -> Result<Vec<PacketBookmark>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached(
+                "SELECT packet_number, label, color FROM packet_bookmarks WHERE session_id = ?1 ORDER BY packet_number",
+            )
+            .context("Failed to query packet bookmarks")?;
+        let bookmarks = statement
+            .query_map([session_id], |row| {
+                Ok(PacketBookmark {
+                    packet_number: row.get(0)?,
+                    label: row.get(1)?,
+                    color: BookmarkColor::from_name(&row.get::<_, String>(2)?),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to query packet bookmarks")?;
+
+        Ok(bookmarks)
+    }
+
+    async fn set_packet_bookmark(&self, session_id: i32, bookmark: &PacketBookmark) -> Result<()> {
+        self.connection()
+            .prepare_cached(
+                "INSERT INTO packet_bookmarks (session_id, packet_number, label, color) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (session_id, packet_number) DO UPDATE SET label = excluded.label, color = excluded.color",
+            )
+            .and_then(|mut statement| {
+                statement.execute(params![session_id, bookmark.packet_number, bookmark.label, bookmark.color.name()])
+            })
+            .context("Failed to save packet bookmark")?;
+
+        Ok(())
+    }
+
+    async fn delete_packet_bookmark(&self, session_id: i32, packet_number: i64) -> Result<()> {
+        self.connection()
+            .prepare_cached("DELETE FROM packet_bookmarks WHERE session_id = ?1 AND packet_number = ?2")
+            .and_then(|mut statement| statement.execute(params![session_id, packet_number]))
+            .context("Failed to delete packet bookmark")?;
+
+        Ok(())
+    }
+
+    async fn get_filter_presets(&self) -> Result<Vec<FilterPreset>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached("SELECT name, filter FROM filters ORDER BY name")
+            .context("Failed to query filter presets")?;
+        let presets = statement
+            .query_map([], |row| Ok(FilterPreset { name: row.get(0)?, filter: row.get(1)? }))?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to query filter presets")?;
+
+        Ok(presets)
+    }
+
+    async fn save_filter_preset(&self, name: &str, filter: &str) -> Result<()> {
+        self.connection()
+            .prepare_cached(
+                "INSERT INTO filters (name, filter) VALUES (?1, ?2)
+                 ON CONFLICT (name) DO UPDATE SET filter = excluded.filter,
+                     updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now')",
+            )
+            .and_then(|mut statement| statement.execute(params![name, filter]))
+            .context("Failed to save filter preset")?;
+
+        Ok(())
+    }
+
+    async fn delete_filter_preset(&self, name: &str) -> Result<()> {
+        self.connection()
+            .prepare_cached("DELETE FROM filters WHERE name = ?1")
+            .and_then(|mut statement| statement.execute([name]))
+            .context("Failed to delete filter preset")?;
+
+        Ok(())
+    }
+
+    async fn get_ignored_fields(&self) -> Result<BTreeMap<String, BTreeSet<String>>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached("SELECT packet_name, path FROM compare_ignored_fields")
+            .context("Failed to query ignored fields")?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to query ignored fields")?;
+
+        let mut fields: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (packet_name, path) in rows {
+            fields.entry(packet_name).or_default().insert(path);
+        }
+        Ok(fields)
+    }
+
+    async fn add_ignored_field(&self, packet_name: &str, path: &str) -> Result<()> {
+        self.connection()
+            .prepare_cached("INSERT INTO compare_ignored_fields (packet_name, path) VALUES (?1, ?2) ON CONFLICT DO NOTHING")
+            .and_then(|mut statement| statement.execute(params![packet_name, path]))
+            .context("Failed to save ignored field")?;
+
+        Ok(())
+    }
+
+    async fn remove_ignored_field(&self, packet_name: &str, path: &str) -> Result<()> {
+        self.connection()
+            .prepare_cached("DELETE FROM compare_ignored_fields WHERE packet_name = ?1 AND path = ?2")
+            .and_then(|mut statement| statement.execute(params![packet_name, path]))
+            .context("Failed to delete ignored field")?;
+
+        Ok(())
+    }
+
+    async fn get_filter_history(&self) -> Result<Vec<String>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached("SELECT filter FROM filter_history ORDER BY id")
+            .context("Failed to query filter history")?;
+        let filters = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to query filter history")?;
+
+        Ok(filters)
+    }
+
+    async fn add_filter_history(&self, filter: &str) -> Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().context("Failed to start filter history transaction")?;
+        // Deleted and reinserted, so a reused filter moves to the end
+        transaction
+            .execute("DELETE FROM filter_history WHERE filter = ?1", [filter])
+            .and_then(|_| transaction.execute("INSERT INTO filter_history (filter) VALUES (?1)", [filter]))
+            .and_then(|_| {
+                transaction.execute(
+                    "DELETE FROM filter_history WHERE id NOT IN (SELECT id FROM filter_history ORDER BY id DESC LIMIT ?1)",
+                    [FILTER_HISTORY_LIMIT],
+                )
+            })
+            .context("Failed to save filter history")?;
+        transaction.commit().context("Failed to save filter history")?;
+
+        Ok(())
+    }
+
+    async fn delete_session(&self, session_id: i32) -> Result<DeletedSession> {
+        // Deleted explicitly (rather than by ON DELETE CASCADE) to report what went, all or nothing
+        let mut connection = self.connection();
+        let transaction = connection.transaction().context("Failed to start delete transaction")?;
+        let delete = |sql: &str| {
+            transaction
+                .prepare_cached(sql)
+                .and_then(|mut statement| statement.execute([session_id]))
+        };
+        let tag_maps = delete(
+            "DELETE FROM tag_maps WHERE session_id = ?1 OR packet_id IN (SELECT id FROM packets WHERE session_id = ?1)",
+        )
+        .context("Failed to delete session tags")?;
+        let notes = delete("DELETE FROM session_notes WHERE session_id = ?1").context("Failed to delete session note")?
+            + delete("DELETE FROM packet_notes WHERE session_id = ?1").context("Failed to delete packet notes")?;
+        let bookmarks = delete("DELETE FROM packet_bookmarks WHERE session_id = ?1").context("Failed to delete packet bookmarks")?;
+        let packets = delete("DELETE FROM packets WHERE session_id = ?1").context("Failed to delete session packets")?;
+        let sessions = delete("DELETE FROM sessions WHERE id = ?1").context("Failed to delete session")?;
+        if sessions == 0 {
+            return Err(anyhow!("Session {} not found", session_id));
+        }
+        transaction.commit().context("Failed to commit session delete")?;
+
+        Ok(DeletedSession {
+            packets: packets as u64,
+            tag_maps: tag_maps as u64,
+            notes: notes as u64,
+            bookmarks: bookmarks as u64,
+        })
+    }
+
+    async fn create_session(&self) -> Result<i32> {
+        let connection = self.connection();
+        connection
+            .prepare_cached("INSERT INTO sessions (started_at) VALUES (?1)")
+            .and_then(|mut statement| statement.execute([format_timestamp(&Utc::now())]))
+            .context("Failed to create session")?;
+
+        Ok(connection.last_insert_rowid() as i32)
+    }
+
+    async fn end_session(&self, session_id: i32) -> Result<()> {
+        self.connection()
+            .prepare_cached("UPDATE sessions SET ended_at = ?1 WHERE id = ?2")
+            .and_then(|mut statement| statement.execute(params![format_timestamp(&Utc::now()), session_id]))
+            .context("Failed to end session")?;
+
+        Ok(())
+    }
+
+    async fn insert_session(&self, started_at: DateTime<Utc>, ended_at: Option<DateTime<Utc>>) -> Result<i32> {
+        let connection = self.connection();
+        connection
+            .prepare_cached("INSERT INTO sessions (started_at, ended_at) VALUES (?1, ?2)")
+            .and_then(|mut statement| {
+                statement.execute(params![format_timestamp(&started_at), ended_at.as_ref().map(format_timestamp)])
+            })
+            .context("Failed to create session")?;
+
+        Ok(connection.last_insert_rowid() as i32)
+    }
+
+    async fn insert_packet(&self, session_id: i32, packet: &NewDbPacket) -> Result<()> {
+        self.connection()
+            .prepare_cached(
+                "INSERT INTO packets (session_id, ts, session_time_ms, packet_number, server_version, direction, packet) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .and_then(|mut statement| {
+                statement.execute(params![
+                    session_id,
+                    format_timestamp(&packet.ts),
+                    packet.session_time_ms,
+                    packet.packet_number,
+                    packet.server_version,
+                    packet.direction,
+                    packet.packet.to_string(),
+                ])
+            })
+            .context("Failed to insert packet")?;
+
+        Ok(())
+    }
+
+    async fn insert_packets(&self, session_id: i32, packets: &[NewDbPacket]) -> Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().context("Failed to start packet insert transaction")?;
+        {
+            let mut statement = transaction
+                .prepare_cached(
+                    "INSERT INTO packets (session_id, ts, session_time_ms, packet_number, server_version, direction, packet) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .context("Failed to insert packets")?;
+            for packet in packets {
+                statement
+                    .execute(params![
+                        session_id,
+                        format_timestamp(&packet.ts),
+                        packet.session_time_ms,
+                        packet.packet_number,
+                        packet.server_version,
+                        packet.direction,
+                        packet.packet.to_string(),
+                    ])
+                    .with_context(|| format!("Failed to insert packet {}", packet.packet_number))?;
+            }
+        }
+        transaction.commit().context("Failed to commit packet inserts")?;
+
+        Ok(())
+    }
+
+    async fn health(&self) -> Result<DbHealth> {
+        let connection = self.connection();
+        // Per-table sizes need the dbstat virtual table, which not every SQLite build has
+        let table_bytes: Option<BTreeMap<String, i64>> = connection
+            .prepare("SELECT name, SUM(pgsize) FROM dbstat GROUP BY name")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+                    .collect()
+            })
+            .ok();
+        let mut tables = Vec::new();
+        for &table in HEALTH_TABLES {
+            let rows: i64 = connection
+                .query_row(&table_rows_query(table), [], |row| row.get(0))
+                .with_context(|| format!("Failed to count {} rows", table))?;
+            // dbstat lists indexes under their own names; a table's size includes those on it
+            let bytes = table_bytes.as_ref().map(|sizes| {
+                let mut bytes = sizes.get(table).copied().unwrap_or(0);
+                let mut indexes = connection.prepare_cached("SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = ?1")?;
+                for index in indexes.query_map([table], |row| row.get::<_, String>(0))? {
+                    bytes += sizes.get(&index?).copied().unwrap_or(0);
+                }
+                Ok::<_, rusqlite::Error>(bytes)
+            });
+            tables.push(TableHealth {
+                name: table.to_string(),
+                rows,
+                bytes: bytes.transpose().context("Failed to query table sizes")?,
+            });
+        }
+        let database_bytes: i64 = connection
+            .query_row("SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()", [], |row| row.get(0))
+            .context("Failed to query the database size")?;
+        let (oldest, newest): (Option<String>, Option<String>) = connection
+            .query_row(SESSION_RANGE_QUERY, [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context("Failed to query session times")?;
+
+        Ok(DbHealth {
+            status: ConnectionStatus::Connected,
+            database_bytes: Some(database_bytes),
+            tables,
+            oldest_session: oldest.as_deref().map(parse_timestamp).transpose()?,
+            newest_session: newest.as_deref().map(parse_timestamp).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DbFieldCondition, DbFieldOp, DbPacketFilter, PacketSearch};
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    const TEST_START_MS: i64 = 1_700_000_000_000;
+
+    fn packet(packet_number: i64, direction: &str, name: &str) -> NewDbPacket {
+        NewDbPacket {
+            ts: DateTime::from_timestamp_millis(TEST_START_MS + packet_number * 10).unwrap(),
+            session_time_ms: packet_number * 10,
+            packet_number,
+            server_version: "1.21.111".to_string(),
+            direction: direction.to_string(),
+            packet: json!({ "name": name, "params": {} }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summaries_backfilled_on_open() {
+        let path = std::env::temp_dir().join(format!("lazypacket-test-{}.sqlite", uuid::Uuid::new_v4()));
+        let db = SqliteDatabase::open(&path).unwrap();
+        let session_id = db.create_session().await.unwrap();
+        let empty_session = db.create_session().await.unwrap();
+        // A database written before session_summaries existed: packets, but no summary rows
+        db.connection()
+            .execute_batch("DROP TRIGGER packets_summary_insert; DROP TRIGGER packets_summary_delete; DROP TABLE session_summaries;")
+            .unwrap();
+        let packets: Vec<NewDbPacket> = (1..=3).map(|number| packet(number, "clientbound", "text")).collect();
+        db.insert_packets(session_id, &packets).await.unwrap();
+        drop(db);
+
+        let db = SqliteDatabase::open(&path).unwrap();
+        assert_eq!(db.get_session_packet_count(session_id).await.unwrap(), 3);
+        let summaries = db.search_session_summaries(&SessionSearch::parse("sort:packets").unwrap()).await.unwrap();
+        assert_eq!(summaries.iter().map(|s| (s.session.id, s.packet_count)).collect::<Vec<_>>(), [(session_id, 3), (empty_session, 0)]);
+        assert_eq!(summaries[0].first_ts, DateTime::from_timestamp_millis(TEST_START_MS + 10));
+        assert_eq!(summaries[0].last_ts, DateTime::from_timestamp_millis(TEST_START_MS + 30));
+        assert_eq!(db.get_session_usage().await.unwrap().len(), 2);
+
+        // Reopening doesn't count them again, and the triggers are back
+        drop(db);
+        let db = SqliteDatabase::open(&path).unwrap();
+        db.insert_packets(session_id, &[packet(4, "serverbound", "text")]).await.unwrap();
+        assert_eq!(db.get_session_packet_count(session_id).await.unwrap(), 4);
+
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_storage() {
+        let path = std::env::temp_dir().join(format!("lazypacket-test-{}.sqlite", uuid::Uuid::new_v4()));
+        let db = SqliteDatabase::open(&path).unwrap();
+
+        let session_id = db.create_session().await.unwrap();
+        let packets = [
+            (1, "serverbound", "login"),
+            (2, "clientbound", "start_game"),
+            (3, "clientbound", "text"),
+            (4, "serverbound", "text"),
+            (5, "clientbound", "disconnect"),
+        ];
+        let packets: Vec<NewDbPacket> = packets.iter().map(|&(number, direction, name)| packet(number, direction, name)).collect();
+        db.insert_packets(session_id, &packets).await.unwrap();
+
+        assert_eq!(db.get_sessions().await.unwrap()[0].id, session_id);
+        let search = |input: &str| SessionSearch::parse(input).unwrap();
+        let found = db.search_sessions(&search("has:start_game version:1.21.111 sort:packets")).await.unwrap();
+        assert_eq!(found[0].id, session_id);
+        assert!(db.search_sessions(&search("has:start_game tag:none")).await.unwrap().is_empty());
+        db.add_session_tag(session_id, "repro").await.unwrap();
+        db.set_session_note(session_id, "Kicked after the second respawn").await.unwrap();
+        assert_eq!(db.search_sessions(&search("EPR")).await.unwrap().len(), 1);
+        assert_eq!(db.search_sessions(&search(&format!("#{} RESPAWN sort:tag", session_id))).await.unwrap().len(), 1);
+        assert!(db.search_sessions(&search("respawn tag:none")).await.unwrap().is_empty());
+        db.remove_session_tag(session_id, "repro").await.unwrap();
+        db.set_session_note(session_id, "").await.unwrap();
+        db.set_session_archived(session_id, true).await.unwrap();
+        assert!(db.search_sessions(&search("sort:packets")).await.unwrap().is_empty());
+        let archived = db.search_session_summaries(&search("archived:only")).await.unwrap();
+        assert!(archived.len() == 1 && archived[0].archived);
+        assert_eq!(db.get_sessions().await.unwrap().len(), 1);
+        db.set_session_archived(session_id, false).await.unwrap();
+        assert!(db.search_sessions(&search("archived:only")).await.unwrap().is_empty());
+        db.set_session_name(session_id, "  Nether portal ").await.unwrap();
+        let named = db.search_session_summaries(&search("PORTAL")).await.unwrap();
+        assert_eq!(named[0].title(), format!("Nether portal (#{})", session_id));
+        db.set_session_name(session_id, "").await.unwrap();
+        assert_eq!(db.get_session_name(session_id).await.unwrap(), None);
+        assert_eq!(db.get_session_packet_count(session_id).await.unwrap(), 5);
+        let health = db.health().await.unwrap();
+        let rows = |name: &str| health.tables.iter().find(|t| t.name == name).unwrap().rows;
+        assert_eq!((rows("sessions"), rows("packets"), rows("filters")), (1, 5, 0));
+        assert_eq!(health.oldest_session, health.newest_session);
+        assert!(health.database_bytes.unwrap() > 0 && health.tables[1].bytes.unwrap() > 0);
+        let all = db.get_packets(session_id, None).await.unwrap();
+        assert_eq!(all[1].packet["name"], "start_game");
+
+        // Clientbound START* (wildcards are case-insensitive) or any text packet, minus serverbound packets
+        let filter = DbPacketFilterSet {
+            filters: vec![
+                DbPacketFilter {
+                    direction: Some("clientbound".to_string()),
+                    packet_name: Some("START*".to_string()),
+                    packet_name_is_wildcard: true,
+                    is_exclusion: false,
+                    ..Default::default()
+                },
+                DbPacketFilter {
+                    direction: None,
+                    packet_name: Some("text".to_string()),
+                    packet_name_is_wildcard: false,
+                    is_exclusion: false,
+                    ..Default::default()
+                },
+                DbPacketFilter {
+                    direction: Some("serverbound".to_string()),
+                    packet_name: None,
+                    packet_name_is_wildcard: false,
+                    is_exclusion: true,
+                    ..Default::default()
+                },
+            ],
+        };
+        let numbers: Vec<i64> = db.get_packets(session_id, Some(&filter)).await.unwrap()
+            .iter()
+            .map(|p| p.packet_number)
+            .collect();
+        assert_eq!(numbers, vec![2, 3]);
+        let stats = db.get_packet_stats(session_id, Some(&filter)).await.unwrap();
+        let mut names: Vec<_> = stats.iter().map(|s| (s.name.as_str(), s.direction.as_str(), s.count)).collect();
+        names.sort_unstable();
+        assert_eq!(names, [("start_game", "clientbound", 1), ("text", "clientbound", 1)]);
+        assert_eq!(db.get_packet_stats(session_id, None).await.unwrap().len(), 5);
+        // Regexes run through the registered regexp() function
+        let regex = DbPacketFilterSet {
+            filters: vec![DbPacketFilter {
+                packet_name: Some("^(start|dis)".to_string()),
+                packet_name_is_regex: true,
+                ..Default::default()
+            }],
+        };
+        assert_eq!(db.count_packets(session_id, Some(&regex), None).await.unwrap(), 2);
+        let rate = db.get_packet_rate(session_id, Some(&filter)).await.unwrap();
+        assert_eq!(rate, [PacketRateBucket { second: 0, clientbound: 2, serverbound: 0 }]);
+
+        // Time and packet number ranges are inclusive
+        let range = DbPacketFilterSet {
+            filters: vec![DbPacketFilter {
+                ts_from: DateTime::from_timestamp_millis(TEST_START_MS + 20),
+                ts_to: DateTime::from_timestamp_millis(TEST_START_MS + 40),
+                packet_number_to: Some(3),
+                ..Default::default()
+            }],
+        };
+        assert_eq!(db.count_packets(session_id, Some(&range), None).await.unwrap(), 2);
+        // The first packet passing the filter from a time
+        let at = |ms: i64| DateTime::from_timestamp_millis(TEST_START_MS + ms).unwrap();
+        assert_eq!(db.packet_at_time(session_id, None, at(0)).await.unwrap(), Some(1));
+        assert_eq!(db.packet_at_time(session_id, None, at(25)).await.unwrap(), Some(3));
+        assert_eq!(db.packet_at_time(session_id, Some(&range), at(11)).await.unwrap(), Some(2));
+        assert_eq!(db.packet_at_time(session_id, Some(&range), at(31)).await.unwrap(), None);
+
+        // Field conditions on the packet JSON, in a session of their own
+        let fields_session = db.create_session().await.unwrap();
+        for (number, params) in [
+            (1, json!({ "runtime_id": 42, "position": { "y": 70.5 }, "on_ground": true })),
+            (2, json!({ "runtime_id": 7, "position": { "y": 12 }, "message": "Hello world" })),
+        ] {
+            let mut new_packet = packet(number, "clientbound", "move_player");
+            new_packet.packet["params"] = params;
+            db.insert_packet(fields_session, &new_packet).await.unwrap();
+        }
+        let field_matches = |path: &str, op: DbFieldOp, value: &str| {
+            let filter = DbPacketFilterSet {
+                filters: vec![DbPacketFilter {
+                    fields: vec![DbFieldCondition {
+                        path: path.split('.').map(|key| key.to_string()).collect(),
+                        op,
+                        value: value.to_string(),
+                    }],
+                    ..Default::default()
+                }],
+            };
+            let db = &db;
+            async move {
+                let page = db.get_packet_page(fields_session, Some(&filter), PageCursor::After(None), 10).await.unwrap();
+                page.iter().map(|p| p.packet_number).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(field_matches("params.runtime_id", DbFieldOp::Eq, "42").await, vec![1]);
+        assert_eq!(field_matches("params.runtime_id", DbFieldOp::Ne, "42").await, vec![2]);
+        assert_eq!(field_matches("params.position.y", DbFieldOp::Gt, "64").await, vec![1]);
+        assert_eq!(field_matches("params.on_ground", DbFieldOp::Eq, "true").await, vec![1]);
+        assert_eq!(field_matches("params.message", DbFieldOp::Like, "*hello*").await, vec![2]);
+        assert_eq!(field_matches("params.message", DbFieldOp::Ne, "x").await, vec![1, 2]);
+
+        // Entity references, as numbers or (64-bit ids) strings, at any depth
+        let mut remove = packet(3, "clientbound", "remove_entity");
+        remove.packet["params"] = json!({ "entity_id_self": "42" });
+        db.insert_packet(fields_session, &remove).await.unwrap();
+        let mut link = packet(4, "clientbound", "set_entity_link");
+        link.packet["params"] = json!({ "link": { "ridden_runtime_id": "7", "rider_unique_id": "42" } });
+        db.insert_packet(fields_session, &link).await.unwrap();
+        let entity_matches = |entity: i64| {
+            let filter = DbPacketFilterSet { filters: vec![DbPacketFilter { entity: Some(entity), ..Default::default() }] };
+            let db = &db;
+            async move {
+                let page = db.get_packet_page(fields_session, Some(&filter), PageCursor::After(None), 10).await.unwrap();
+                page.iter().map(|p| p.packet_number).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(entity_matches(42).await, vec![1, 3]);
+        assert_eq!(entity_matches(7).await, vec![2, 4]);
+        assert!(entity_matches(70).await.is_empty());
+
+        // Searches: containment compares leaf values, chat text needs a text packet with every word
+        let mut chat = packet(3, "clientbound", "text");
+        chat.packet["params"] = json!({ "message": "Hello there, World" });
+        db.insert_packet(fields_session, &chat).await.unwrap();
+        let search = |search: PacketSearch, cursor: PageCursor| {
+            let db = &db;
+            async move {
+                let found = db.search_packets(fields_session, None, &search, cursor, 10).await.unwrap();
+                found.iter().map(|p| p.packet_number).collect::<Vec<_>>()
+            }
+        };
+        let contains = PacketSearch::parse(r#"{"name": "move_player", "params": {"position": {"y": 12}}}"#).unwrap();
+        assert_eq!(search(contains, PageCursor::After(None)).await, vec![2]);
+        assert_eq!(search(PacketSearch::parse("chat: world hello").unwrap(), PageCursor::After(None)).await, vec![3]);
+        assert_eq!(search(PacketSearch::parse("chat:hello").unwrap(), PageCursor::After(Some(3))).await, Vec::<i64>::new());
+        // Plain words match names and contents anywhere, ignoring case
+        let text = PacketSearch::parse("HELLO").unwrap();
+        assert_eq!(search(text.clone(), PageCursor::After(None)).await, vec![2, 3]);
+        assert_eq!(search(text.clone(), PageCursor::Before(Some(3))).await, vec![2]);
+        assert_eq!(search(PacketSearch::parse("move_player 42").unwrap(), PageCursor::Before(None)).await, vec![1]);
+        assert_eq!(db.count_search_matches(fields_session, None, &text, None).await.unwrap(), 2);
+        assert_eq!(db.count_search_matches(fields_session, None, &text, Some(3)).await.unwrap(), 1);
+
+        // Keyset pages: Before pages come back in packet_number order too
+        let page_numbers = |page: Vec<DbPacket>| page.iter().map(|p| p.packet_number).collect::<Vec<_>>();
+        let page = db.get_packet_page(session_id, None, PageCursor::After(None), 2).await.unwrap();
+        assert_eq!(page_numbers(page), vec![1, 2]);
+        let page = db.get_packet_page(session_id, None, PageCursor::After(Some(2)), 2).await.unwrap();
+        assert_eq!(page_numbers(page), vec![3, 4]);
+        let page = db.get_packet_page(session_id, None, PageCursor::Before(None), 2).await.unwrap();
+        assert_eq!(page_numbers(page), vec![4, 5]);
+        let page = db.get_packet_page(session_id, Some(&filter), PageCursor::Before(Some(3)), 2).await.unwrap();
+        assert_eq!(page_numbers(page), vec![2]);
+        assert_eq!(db.count_packets(session_id, None, None).await.unwrap(), 5);
+        assert_eq!(db.count_packets(session_id, Some(&filter), Some(3)).await.unwrap(), 1);
+
+        let streamed: Vec<DbPacket> = db.stream_packets(session_id, Some(&filter)).try_collect().await.unwrap();
+        assert_eq!(page_numbers(streamed), vec![2, 3]);
+
+        let preview = db.get_session_preview(session_id, 2, 1).await.unwrap();
+        assert_eq!(preview.first_packets.iter().map(|p| p.packet_number).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(preview.last_packets.iter().map(|p| p.packet_number).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(preview.top_packet_types, vec![("text".to_string(), 2)]);
+        assert_eq!(preview.milestones.len(), 1);
+        assert_eq!(preview.milestones[0].milestone, "kick");
+
+        db.add_session_tag(session_id, "repro").await.unwrap();
+        db.add_session_tag(session_id, "repro").await.unwrap();
+        assert_eq!(db.get_session_tags(session_id).await.unwrap(), vec!["repro"]);
+        db.remove_session_tag(session_id, "repro").await.unwrap();
+        assert!(db.get_session_tags(session_id).await.unwrap().is_empty());
+        assert_eq!(db.get_all_tags().await.unwrap(), vec!["repro"]);
+
+        assert_eq!(db.get_session_note(session_id).await.unwrap(), None);
+        db.set_session_note(session_id, "kicked after the transfer").await.unwrap();
+        db.set_session_note(session_id, "kicked right after the transfer").await.unwrap();
+        assert_eq!(db.get_session_note(session_id).await.unwrap().as_deref(), Some("kicked right after the transfer"));
+        db.set_packet_note(session_id, 2, "first chat").await.unwrap();
+        db.set_packet_note(session_id, 4, "kick reason").await.unwrap();
+        db.set_packet_note(session_id, 2, " ").await.unwrap();
+        let notes = db.get_packet_notes(session_id).await.unwrap();
+        assert_eq!(notes.into_iter().collect::<Vec<_>>(), vec![(4, "kick reason".to_string())]);
+
+        let bookmark = |packet_number, label: Option<&str>, color| PacketBookmark { packet_number, label: label.map(str::to_string), color };
+        db.set_packet_bookmark(session_id, &bookmark(5, None, BookmarkColor::default())).await.unwrap();
+        db.set_packet_bookmark(session_id, &bookmark(3, Some("before kick"), BookmarkColor::Red)).await.unwrap();
+        db.set_packet_bookmark(session_id, &bookmark(3, Some("just before kick"), BookmarkColor::Red.next())).await.unwrap();
+        db.set_packet_bookmark(session_id, &bookmark(1, None, BookmarkColor::Blue)).await.unwrap();
+        db.delete_packet_bookmark(session_id, 1).await.unwrap();
+        assert_eq!(
+            db.get_packet_bookmarks(session_id).await.unwrap(),
+            vec![bookmark(3, Some("just before kick"), BookmarkColor::Green), bookmark(5, None, BookmarkColor::Yellow)]
+        );
+
+        db.save_filter_preset("movement only", "c.move_player").await.unwrap();
+        db.save_filter_preset("movement only", "c.move_player,c.move_entity").await.unwrap();
+        db.save_filter_preset("chat", "a.text").await.unwrap();
+        let presets = db.get_filter_presets().await.unwrap();
+        assert_eq!(presets.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["chat", "movement only"]);
+        assert_eq!(presets[1].filter, "c.move_player,c.move_entity");
+        db.delete_filter_preset("chat").await.unwrap();
+        assert_eq!(db.get_filter_presets().await.unwrap().len(), 1);
+
+        db.add_ignored_field("move_player", "params.tick").await.unwrap();
+        db.add_ignored_field("move_player", "params.tick").await.unwrap();
+        db.add_ignored_field("move_player", "params.position").await.unwrap();
+        db.add_ignored_field("player_auth_input", "params.tick").await.unwrap();
+        db.remove_ignored_field("move_player", "params.position").await.unwrap();
+        let ignored = db.get_ignored_fields().await.unwrap();
+        assert_eq!(ignored.len(), 2);
+        assert_eq!(ignored["move_player"].iter().collect::<Vec<_>>(), ["params.tick"]);
+
+        for filter in ["c.text", "s.move_player", "c.text"] {
+            db.add_filter_history(filter).await.unwrap();
+        }
+        assert_eq!(db.get_filter_history().await.unwrap(), ["s.move_player", "c.text"]);
+        for n in 0..FILTER_HISTORY_LIMIT {
+            db.add_filter_history(&format!("c.#{}", n)).await.unwrap();
+        }
+        let history = db.get_filter_history().await.unwrap();
+        assert_eq!(history.len() as i64, FILTER_HISTORY_LIMIT);
+        assert_eq!(history[0], "c.#0");
+
+        db.add_session_tag(session_id, "repro").await.unwrap();
+        // Summaries are kept by triggers; an empty session has no row and lists with 0 packets
+        let empty_session = db.create_session().await.unwrap();
+        let summaries = db.search_session_summaries(&SessionSearch::parse("sort:packets").unwrap()).await.unwrap();
+        let summary = summaries.iter().find(|s| s.session.id == session_id).unwrap();
+        assert_eq!((summary.packet_count, summary.tags.as_slice()), (5, ["repro".to_string()].as_slice()));
+        assert_eq!(summary.first_ts, DateTime::from_timestamp_millis(TEST_START_MS + 10));
+        assert_eq!(summary.last_ts, DateTime::from_timestamp_millis(TEST_START_MS + 50));
+        let empty = summaries.last().unwrap();
+        assert_eq!((empty.session.id, empty.packet_count, empty.first_ts), (empty_session, 0, None));
+
+        let usage = db.get_session_usage().await.unwrap();
+        let session_usage = usage.iter().find(|u| u.session_id == session_id).unwrap();
+        assert!(session_usage.tagged && session_usage.bytes > 0);
+        let deleted = db.delete_session(session_id).await.unwrap();
+        assert_eq!(deleted, DeletedSession { packets: 5, tag_maps: 1, notes: 2, bookmarks: 2 });
+        assert_eq!(db.get_session_note(session_id).await.unwrap(), None);
+        assert_eq!(db.get_session_packet_count(session_id).await.unwrap(), 0);
+        assert!(db.get_session_tags(session_id).await.unwrap().is_empty());
+        assert!(db.delete_session(session_id).await.is_err());
+
+        // Gaps between consecutive packets, largest first
+        let gaps_session = db.create_session().await.unwrap();
+        let gap_packets: Vec<NewDbPacket> = [1, 2, 7, 8].iter().map(|&number| packet(number, "clientbound", "text")).collect();
+        db.insert_packets(gaps_session, &gap_packets).await.unwrap();
+        let gaps = db.get_packet_gaps(gaps_session, None, 2).await.unwrap();
+        assert_eq!(
+            gaps.iter().map(|gap| (gap.previous_packet_number, gap.packet_number, gap.gap_ms)).collect::<Vec<_>>(),
+            [(2, 7, 50), (1, 2, 10)]
+        );
+        assert_eq!((gaps[0].name.as_deref(), gaps[0].session_time_ms), (Some("text"), 70));
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
@@ -0,0 +1,1000 @@
+// Storage backends for captured sessions
+// The TUI, DbSink and export-parquet work against the Storage trait; DB_BACKEND picks the implementation:
+//   postgres - Database (db.rs): the PostgreSQL database configured by DB_HOST, DB_PORT, ... (default)
+//   sqlite   - SqliteDatabase (sqlite_db.rs): a single file at DB_SQLITE_PATH, created on first use
+
+use crate::db::{
+    ArchivedSessions, Database, DbFieldOp, DbHealth, DeletedSession, FilterPreset, DbPacket, DbPacketFilterSet, NewDbPacket, PacketBookmark, PacketGap, PacketRateBucket, PacketSearch, PacketTypeStats, Session, SessionPreview,
+    SessionSearch, SessionSort, SessionSummary, SessionUsage,
+};
+use crate::sqlite_db::SqliteDatabase;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Utc};
+use futures::stream::BoxStream;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+const DEFAULT_SQLITE_PATH: &str = "bedrockrelay.sqlite";
+/// Applied filters kept in the filter_history table
+pub const FILTER_HISTORY_LIMIT: i64 = 100;
+pub(crate) const STREAM_BATCH_SIZE: i64 = 1000; // Packets fetched at a time by stream_packets
+
+/// Packets read a batch at a time (see Storage::stream_packets)
+pub type PacketStream<'a> = BoxStream<'a, Result<DbPacket>>;
+
+/// Health of the connection behind a Storage, for display (queries report their own errors)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connected,
+    Reconnecting { attempt: u32, last_error: String }, // Waiting to retry after `attempt` failed attempts
+    Unavailable { last_error: String }, // Gave up on the last request; the next one tries again
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Local backends are always connected
+    fn connection_status(&self) -> ConnectionStatus {
+        ConnectionStatus::Connected
+    }
+    async fn get_sessions(&self) -> Result<Vec<Session>>;
+    async fn get_session(&self, session_id: i32) -> Result<Option<Session>>;
+    /// Sessions matching a search, in its sort order
+    async fn search_sessions(&self, search: &SessionSearch) -> Result<Vec<Session>> {
+        Ok(self.search_session_summaries(search).await?.into_iter().map(|summary| summary.session).collect())
+    }
+    /// Sessions matching a search with their packet counts and tags, in one query (the session list)
+    async fn search_session_summaries(&self, search: &SessionSearch) -> Result<Vec<SessionSummary>>;
+    /// Age, packet bytes and tagged state of every session (for retention)
+    async fn get_session_usage(&self) -> Result<Vec<SessionUsage>>;
+    async fn get_session_packet_count(&self, session_id: i32) -> Result<usize>;
+    async fn get_packets(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<DbPacket>>;
+    /// Up to `limit` packets passing the filter set on one side of a cursor, in packet_number order
+    async fn get_packet_page(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        cursor: PageCursor,
+        limit: i64,
+    ) -> Result<Vec<DbPacket>>;
+    /// Packets passing the filter set, optionally only those before a packet number
+    async fn count_packets(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, before: Option<i64>) -> Result<usize>;
+    /// Packets passing the filter set in packet_number order, fetched in batches as the stream is read,
+    /// so whole sessions can be processed in constant memory
+    /// The default walks keyset pages; Database reads from a server-side cursor instead
+    fn stream_packets<'a>(&'a self, session_id: i32, filter_set: Option<&'a DbPacketFilterSet>) -> PacketStream<'a> {
+        Box::pin(async_stream::try_stream! {
+            let mut after = None;
+            loop {
+                let page = self
+                    .get_packet_page(session_id, filter_set, PageCursor::After(after), STREAM_BATCH_SIZE)
+                    .await?;
+                let Some(last) = page.last() else {
+                    break;
+                };
+                after = Some(last.packet_number);
+                let done = (page.len() as i64) < STREAM_BATCH_SIZE;
+                for packet in page {
+                    yield packet;
+                }
+                if done {
+                    break;
+                }
+            }
+        })
+    }
+    /// Packet number of the first packet passing the filter set at or after a time, if any
+    async fn packet_at_time(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, ts: DateTime<Utc>) -> Result<Option<i64>>;
+    /// Up to `limit` packets on the cursor's side of a packet number that match a search and pass the filter set,
+    /// in packet_number order (a Before page ends with the match closest to the cursor)
+    async fn search_packets(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        search: &PacketSearch,
+        cursor: PageCursor,
+        limit: i64,
+    ) -> Result<Vec<DbPacket>>;
+    /// Packets that match a search and pass the filter set, optionally only those before a packet number
+    async fn count_search_matches(
+        &self,
+        session_id: i32,
+        filter_set: Option<&DbPacketFilterSet>,
+        search: &PacketSearch,
+        before: Option<i64>,
+    ) -> Result<usize>;
+    /// Count, bytes and rate per packet name and direction of the packets passing the filter set, most frequent first
+    async fn get_packet_stats(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<PacketTypeStats>>;
+    /// Packets per second of the session passing the filter set, by direction; seconds without any are left out
+    async fn get_packet_rate(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>) -> Result<Vec<PacketRateBucket>>;
+    /// The `limit` largest time gaps between consecutive packets passing the filter set, largest first
+    async fn get_packet_gaps(&self, session_id: i32, filter_set: Option<&DbPacketFilterSet>, limit: i64) -> Result<Vec<PacketGap>>;
+    async fn get_session_preview(&self, session_id: i32, packet_limit: i64, type_limit: i64) -> Result<SessionPreview>;
+    async fn get_session_tags(&self, session_id: i32) -> Result<Vec<String>>;
+    async fn add_session_tag(&self, session_id: i32, tag: &str) -> Result<()>;
+    async fn remove_session_tag(&self, session_id: i32, tag: &str) -> Result<()>;
+    async fn get_all_tags(&self) -> Result<Vec<String>>;
+    async fn get_session_note(&self, session_id: i32) -> Result<Option<String>>;
+    /// Create or replace the session's note; a blank note removes it
+    async fn set_session_note(&self, session_id: i32, note: &str) -> Result<()>;
+    async fn get_session_name(&self, session_id: i32) -> Result<Option<String>>;
+    /// Name a session, or rename it; a blank name removes it
+    async fn set_session_name(&self, session_id: i32, name: &str) -> Result<()>;
+    /// Archive a session, hiding it from searches that don't ask for archived sessions, or restore it
+    async fn set_session_archived(&self, session_id: i32, archived: bool) -> Result<()>;
+    /// Notes by packet_number
+    async fn get_packet_notes(&self, session_id: i32) -> Result<BTreeMap<i64, String>>;
+    /// Create or replace a packet's note; a blank note removes it
+    async fn set_packet_note(&self, session_id: i32, packet_number: i64, note: &str) -> Result<()>;
+    /// Bookmarks in packet order
+    async fn get_packet_bookmarks(&self, session_id: i32) -> Result<Vec<PacketBookmark>>;
+    /// Create a packet's bookmark, or replace its label and color
+    async fn set_packet_bookmark(&self, session_id: i32, bookmark: &PacketBookmark) -> Result<()>;
+    async fn delete_packet_bookmark(&self, session_id: i32, packet_number: i64) -> Result<()>;
+    /// Saved filters, by name
+    async fn get_filter_presets(&self) -> Result<Vec<FilterPreset>>;
+    /// Create a preset, or replace the filter of an existing one with this name
+    async fn save_filter_preset(&self, name: &str, filter: &str) -> Result<()>;
+    async fn delete_filter_preset(&self, name: &str) -> Result<()>;
+    /// Fields compare mode leaves out of diffs: dotted paths from the packet root, by packet name
+    async fn get_ignored_fields(&self) -> Result<BTreeMap<String, BTreeSet<String>>>;
+    async fn add_ignored_field(&self, packet_name: &str, path: &str) -> Result<()>;
+    async fn remove_ignored_field(&self, packet_name: &str, path: &str) -> Result<()>;
+    /// Filters applied in the packet view, oldest first
+    async fn get_filter_history(&self) -> Result<Vec<String>>;
+    /// Record a filter as the most recently applied, keeping the last FILTER_HISTORY_LIMIT
+    async fn add_filter_history(&self, filter: &str) -> Result<()>;
+    /// Delete a session with its packets, tags, notes and bookmarks in one transaction; errors if there is no such session
+    async fn delete_session(&self, session_id: i32) -> Result<DeletedSession>;
+    async fn create_session(&self) -> Result<i32>;
+    /// Set the end time of a session made with create_session to now, once its capture stops
+    async fn end_session(&self, session_id: i32) -> Result<()>;
+    /// Create a session that was captured earlier (imports), with its original times
+    async fn insert_session(&self, started_at: DateTime<Utc>, ended_at: Option<DateTime<Utc>>) -> Result<i32>;
+    async fn insert_packet(&self, session_id: i32, packet: &NewDbPacket) -> Result<()>;
+    /// Insert packets all or nothing, in one round trip where the backend allows (binary COPY on PostgreSQL)
+    async fn insert_packets(&self, session_id: i32, packets: &[NewDbPacket]) -> Result<()>;
+    /// Connection status, database and table sizes, row counts and the oldest/newest session
+    async fn health(&self) -> Result<DbHealth>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageBackend {
+    Postgres,
+    Sqlite(PathBuf),
+}
+
+impl StorageBackend {
+    /// DB_BACKEND (postgres or sqlite, default postgres) and DB_SQLITE_PATH
+    /// A relative DB_SQLITE_PATH is resolved against the project root (the directory of the loaded .env),
+    /// so the relay and lazypacket open the same file wherever they're started from
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("DB_BACKEND").as_deref() {
+            Err(_) | Ok("postgres") => Ok(Self::Postgres),
+            Ok("sqlite") => {
+                let path = PathBuf::from(
+                    std::env::var("DB_SQLITE_PATH").unwrap_or_else(|_| DEFAULT_SQLITE_PATH.to_string()),
+                );
+                if path.is_absolute() {
+                    return Ok(Self::Sqlite(path));
+                }
+                Ok(Self::Sqlite(project_root().join(path)))
+            }
+            Ok(other) => Err(anyhow!("Invalid DB_BACKEND: {} (expected postgres or sqlite)", other)),
+        }
+    }
+}
+
+/// The directory of the loaded .env, or the working directory without one
+pub fn project_root() -> PathBuf {
+    dotenvy::dotenv()
+        .ok()
+        .and_then(|env_path| env_path.parent().map(|dir| dir.to_path_buf()))
+        .unwrap_or_default()
+}
+
+/// Open the backend selected by DB_BACKEND
+pub async fn connect() -> Result<Box<dyn Storage>> {
+    match StorageBackend::from_env()? {
+        StorageBackend::Postgres => Ok(Box::new(Database::connect().await?)),
+        StorageBackend::Sqlite(path) => Ok(Box::new(SqliteDatabase::open(path)?)),
+    }
+}
+
+/// `migrate`: bring the configured database's schema up to date and report what was applied
+/// Database::connect already does this unless DB_AUTO_MIGRATE=false; SQLite files get their schema on open
+pub async fn migrate(args: &[String]) -> Result<()> {
+    if let Some(arg) = args.first() {
+        return Err(anyhow!("Unexpected argument: {}", arg));
+    }
+    match StorageBackend::from_env()? {
+        StorageBackend::Postgres => {
+            // Open without the automatic run so the applied migrations can be reported here
+            let db = Database::open().await?;
+            let applied = db.migrate().await?;
+            if applied.is_empty() {
+                eprintln!("Database schema is up to date");
+            }
+            for migration in applied {
+                eprintln!("Applied {}", migration);
+            }
+        }
+        StorageBackend::Sqlite(path) => {
+            SqliteDatabase::open(&path)?;
+            eprintln!("SQLite schema applied to {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// `delete-session [--yes] SESSION_ID...`: delete sessions with their packets and tags, asking first unless --yes
+pub async fn delete_sessions(args: &[String]) -> Result<()> {
+    let mut confirmed = false;
+    let mut session_ids = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-y" | "--yes" => confirmed = true,
+            _ => session_ids.push(arg.parse::<i32>().with_context(|| format!("Invalid session id: {}", arg))?),
+        }
+    }
+    if session_ids.is_empty() {
+        return Err(anyhow!("Usage: lazypacket delete-session [--yes] SESSION_ID..."));
+    }
+
+    let db = connect().await?;
+    for session_id in session_ids {
+        if !confirmed {
+            let packet_count = db.get_session_packet_count(session_id).await?;
+            eprint!("Delete session #{} and its {} packets? [y/N] ", session_id, packet_count);
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer).context("Failed to read confirmation")?;
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                eprintln!("Skipped session #{}", session_id);
+                continue;
+            }
+        }
+        let deleted = db.delete_session(session_id).await?;
+        eprintln!(
+            "Deleted session #{} ({} packets, {} tags, {} notes)",
+            session_id, deleted.packets, deleted.tag_maps, deleted.notes
+        );
+    }
+    Ok(())
+}
+
+/// Packet statistics from (name, direction, count, bytes, first session_time_ms, last session_time_ms) rows,
+/// with rates over the session's span (at least a second) and the most frequent packets first
+pub(crate) fn packet_stats_from_rows(rows: Vec<(String, String, i64, i64, i64, i64)>) -> Vec<PacketTypeStats> {
+    let first_ms = rows.iter().map(|row| row.4).min().unwrap_or(0);
+    let last_ms = rows.iter().map(|row| row.5).max().unwrap_or(0);
+    let duration_secs = ((last_ms - first_ms) as f64 / 1000.0).max(1.0);
+
+    let mut stats: Vec<PacketTypeStats> = rows
+        .into_iter()
+        .map(|(name, direction, count, total_bytes, _, _)| PacketTypeStats {
+            name,
+            direction,
+            count,
+            total_bytes,
+            rate: count as f64 / duration_secs,
+        })
+        .collect();
+    stats.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)).then_with(|| a.direction.cmp(&b.direction)));
+    stats
+}
+
+// SQL differences between backends that packet filters depend on
+pub(crate) struct SqlDialect {
+    pub placeholder: char,           // '$' for $1 (PostgreSQL), '?' for ?1 (SQLite)
+    pub packet_name: &'static str,   // Expression for the packet's name
+    pub packet_size: &'static str,   // Expression for the size in bytes of the stored packet JSON
+    pub regex_match: &'static str,   // Regular expression match operator (text on the left, pattern on the right)
+    pub like: &'static str,          // Case-insensitive pattern match operator
+    pub json_path: fn(&[String]) -> SqlParam, // Bound form of a path into the packet JSON
+    pub json_text: fn(&str) -> String,   // Value at a bound path (placeholder) as text
+    pub json_number: fn(&str) -> String, // Value at a bound path as a number, NULL unless it's a JSON number
+    pub json_contains: Option<fn(&str) -> String>, // Containment test against a bound JSON value, if supported
+    pub chat_word: fn(&str) -> String, // Chat message contains a bound word
+    pub text_contains: fn(&str) -> String, // Packet name or JSON text contains a bound string, ignoring case
+    pub json_entity: fn(&str) -> String, // Packet references the bound entity id (see ENTITY_ID_KEYS)
+    pub session_duration: &'static str, // Length of session s, ordered like a number (NULL until it ends)
+    pub session_tags: &'static str, // Tags of session s in name order (an array, or JSON array text on SQLite)
+}
+
+// Bound query parameter, converted to each backend's parameter type
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SqlParam {
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Timestamp(NaiveDateTime), // UTC, like the ts column
+    TextArray(Vec<String>),
+    Json(serde_json::Value),
+}
+
+/// Where a page of packets starts, by packet number (keyset pagination)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageCursor {
+    After(Option<i64>),  // Packets after this packet number (from the first packet if None)
+    Before(Option<i64>), // Packets before this packet number (up to the last packet if None)
+}
+
+// Tables reported by Storage::health, in display order
+pub(crate) const HEALTH_TABLES: &[&str] =
+    &["sessions", "packets", "session_summaries", "tags", "tag_maps", "session_notes", "packet_notes", "packet_bookmarks", "filters"];
+
+/// Row count of a health table; packets are summed from session_summaries rather than counted
+pub(crate) fn table_rows_query(table: &str) -> String {
+    match table {
+        "packets" => "SELECT COALESCE(SUM(packet_count), 0) FROM session_summaries".to_string(),
+        _ => format!("SELECT COUNT(*) FROM {}", table),
+    }
+}
+
+pub(crate) const SESSION_RANGE_QUERY: &str = "SELECT MIN(started_at), MAX(started_at) FROM sessions";
+
+pub(crate) const PACKET_COLUMNS: &str =
+    "id, session_id, ts, session_time_ms, packet_number, server_version, direction, packet";
+
+/// SELECT for up to `limit` packets of a session that pass a filter set, starting at a cursor
+/// Rows come back nearest to the cursor first (descending for Before), so callers reverse Before pages
+/// Parameter 1 is the session id; the returned parameters are 2.. in order
+pub(crate) fn packet_page_query(
+    filter_set: Option<&DbPacketFilterSet>,
+    cursor: PageCursor,
+    limit: i64,
+    dialect: &SqlDialect,
+) -> (String, Vec<SqlParam>) {
+    let (mut where_clause, mut params) = packet_filter_clause(filter_set, dialect);
+    let order = match cursor {
+        PageCursor::After(Some(number)) => {
+            params.push(SqlParam::Int(number));
+            where_clause.push_str(&format!(" AND packet_number > {}{}", dialect.placeholder, params.len() + 1));
+            "ASC"
+        }
+        PageCursor::Before(Some(number)) => {
+            params.push(SqlParam::Int(number));
+            where_clause.push_str(&format!(" AND packet_number < {}{}", dialect.placeholder, params.len() + 1));
+            "DESC"
+        }
+        PageCursor::After(None) => "ASC",
+        PageCursor::Before(None) => "DESC",
+    };
+    params.push(SqlParam::Int(limit));
+    let query = format!(
+        "SELECT {} FROM packets WHERE {} ORDER BY packet_number {} LIMIT {}{}",
+        PACKET_COLUMNS, where_clause, order, dialect.placeholder, params.len() + 1
+    );
+    (query, params)
+}
+
+/// SELECT for up to `limit` packets on the cursor's side of a packet number that match a search and pass a
+/// filter set, nearest first (so Before pages come in descending order)
+/// Parameter 1 is the session id; the returned parameters are 2.. in order
+pub(crate) fn packet_search_query(
+    filter_set: Option<&DbPacketFilterSet>,
+    search: &PacketSearch,
+    cursor: PageCursor,
+    limit: i64,
+    dialect: &SqlDialect,
+) -> (String, Vec<SqlParam>) {
+    let p = dialect.placeholder;
+    let (mut where_clause, mut params) = packet_search_clause(filter_set, search, dialect);
+    let order = match cursor {
+        PageCursor::After(Some(number)) => {
+            params.push(SqlParam::Int(number));
+            where_clause.push_str(&format!(" AND packet_number > {}{}", p, params.len() + 1));
+            "ASC"
+        }
+        PageCursor::Before(Some(number)) => {
+            params.push(SqlParam::Int(number));
+            where_clause.push_str(&format!(" AND packet_number < {}{}", p, params.len() + 1));
+            "DESC"
+        }
+        PageCursor::After(None) => "ASC",
+        PageCursor::Before(None) => "DESC",
+    };
+    params.push(SqlParam::Int(limit));
+    let query = format!(
+        "SELECT {} FROM packets WHERE {} ORDER BY packet_number {} LIMIT {}{}",
+        PACKET_COLUMNS, where_clause, order, p, params.len() + 1
+    );
+    (query, params)
+}
+
+/// SELECT COUNT(*) of the packets that match a search and pass a filter set, optionally only those before a
+/// packet number
+pub(crate) fn packet_search_count_query(
+    filter_set: Option<&DbPacketFilterSet>,
+    search: &PacketSearch,
+    before: Option<i64>,
+    dialect: &SqlDialect,
+) -> (String, Vec<SqlParam>) {
+    let (mut where_clause, mut params) = packet_search_clause(filter_set, search, dialect);
+    if let Some(number) = before {
+        params.push(SqlParam::Int(number));
+        where_clause.push_str(&format!(" AND packet_number < {}{}", dialect.placeholder, params.len() + 1));
+    }
+    (format!("SELECT COUNT(*) FROM packets WHERE {}", where_clause), params)
+}
+
+/// WHERE clause selecting a session's packets that match a search and pass a filter set
+fn packet_search_clause(
+    filter_set: Option<&DbPacketFilterSet>,
+    search: &PacketSearch,
+    dialect: &SqlDialect,
+) -> (String, Vec<SqlParam>) {
+    let p = dialect.placeholder;
+    let (mut where_clause, mut params) = packet_filter_clause(filter_set, dialect);
+    match search {
+        PacketSearch::Contains(value) => match dialect.json_contains {
+            Some(json_contains) => {
+                params.push(SqlParam::Json(value.clone()));
+                where_clause.push_str(&format!(" AND {}", json_contains(&format!("{}{}", p, params.len() + 1))));
+            }
+            None => {
+                // Without a containment operator, compare each leaf value (arrays element by element)
+                let mut conditions = Vec::new();
+                json_leaf_conditions(value, &mut Vec::new(), dialect, &mut conditions, &mut params);
+                for condition in conditions {
+                    where_clause.push_str(&format!(" AND {}", condition));
+                }
+            }
+        },
+        PacketSearch::ChatText(text) => {
+            // Literal name so PostgreSQL can use the partial index on chat messages
+            where_clause.push_str(&format!(" AND {} = 'text'", dialect.packet_name));
+            for word in text.split_whitespace() {
+                params.push(SqlParam::Text(word.to_string()));
+                where_clause.push_str(&format!(" AND {}", (dialect.chat_word)(&format!("{}{}", p, params.len() + 1))));
+            }
+        }
+        PacketSearch::Text(text) => {
+            for word in text.split_whitespace() {
+                params.push(SqlParam::Text(word.to_string()));
+                where_clause.push_str(&format!(" AND {}", (dialect.text_contains)(&format!("{}{}", p, params.len() + 1))));
+            }
+        }
+    }
+    (where_clause, params)
+}
+
+/// SELECT id, started_at, ended_at, packet_count, first_ts, last_ts, tags of the sessions matching a search,
+/// in its order; the packet columns come from session_summaries (maintained by triggers on packets)
+/// The returned parameters are 1.. in order
+pub(crate) fn session_search_query(search: &SessionSearch, dialect: &SqlDialect) -> (String, Vec<SqlParam>) {
+    let p = dialect.placeholder;
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    let bind = |param: SqlParam, params: &mut Vec<SqlParam>| {
+        params.push(param);
+        format!("{}{}", p, params.len())
+    };
+
+    if let Some(day) = search.started_from {
+        let from = bind(SqlParam::Timestamp(day.and_time(NaiveTime::MIN)), &mut params);
+        conditions.push(format!("s.started_at >= {}", from));
+    }
+    if let Some(day) = search.started_to {
+        // Whole days: anything before the start of the next one
+        let next_day = day.succ_opt().unwrap_or(day).and_time(NaiveTime::MIN);
+        let to = bind(SqlParam::Timestamp(next_day), &mut params);
+        conditions.push(format!("s.started_at < {}", to));
+    }
+    for word in &search.words {
+        // `12` or `#12` is also the session's id
+        let id = bind(SqlParam::Text(word.trim_start_matches('#').to_string()), &mut params);
+        let pattern = bind(SqlParam::Text(format!("%{}%", word)), &mut params);
+        conditions.push(format!(
+            "(CAST(s.id AS TEXT) = {id} \
+             OR EXISTS (SELECT 1 FROM session_names sn WHERE sn.session_id = s.id AND sn.name {like} {pattern}) \
+             OR EXISTS (SELECT 1 FROM tag_maps m WHERE m.session_id = s.id AND m.tag {like} {pattern}) \
+             OR EXISTS (SELECT 1 FROM session_notes n WHERE n.session_id = s.id AND n.note {like} {pattern}))",
+            id = id,
+            like = dialect.like,
+            pattern = pattern
+        ));
+    }
+    for tag in &search.tags {
+        let tag = bind(SqlParam::Text(tag.clone()), &mut params);
+        conditions.push(format!("EXISTS (SELECT 1 FROM tag_maps m WHERE m.session_id = s.id AND m.tag = {})", tag));
+    }
+    if let Some(version) = &search.server_version {
+        let version = bind(SqlParam::Text(version.clone()), &mut params);
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM packets WHERE packets.session_id = s.id AND packets.server_version = {})",
+            version
+        ));
+    }
+    for name in &search.packet_types {
+        let name = bind(SqlParam::Text(name.clone()), &mut params);
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM packets WHERE packets.session_id = s.id AND {} = {})",
+            dialect.packet_name, name
+        ));
+    }
+
+    let archived = "EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id)";
+    match search.archived {
+        ArchivedSessions::Hidden => conditions.push(format!("NOT {}", archived)),
+        ArchivedSessions::Only => conditions.push(archived.to_string()),
+        ArchivedSessions::Included => {}
+    }
+
+    let where_clause = if conditions.is_empty() { String::new() } else { format!(" WHERE {}", conditions.join(" AND ")) };
+    let order = match search.sort {
+        SessionSort::Newest => "s.started_at DESC".to_string(),
+        SessionSort::Oldest => "s.started_at ASC".to_string(),
+        SessionSort::Packets => "COALESCE(ss.packet_count, 0) DESC, s.started_at DESC".to_string(),
+        SessionSort::Duration => format!(
+            "s.ended_at IS NULL, {} DESC, s.started_at DESC",
+            dialect.session_duration
+        ),
+        SessionSort::Tag => {
+            let first_tag = "(SELECT MIN(m.tag) FROM tag_maps m WHERE m.session_id = s.id)";
+            format!("{first_tag} IS NULL, {first_tag}, s.started_at DESC", first_tag = first_tag)
+        }
+    };
+    let query = format!(
+        "SELECT s.id, s.started_at, s.ended_at, COALESCE(ss.packet_count, 0), ss.first_ts, ss.last_ts, {}, {}, \
+         (SELECT sn.name FROM session_names sn WHERE sn.session_id = s.id) \
+         FROM sessions s LEFT JOIN session_summaries ss ON ss.session_id = s.id{} ORDER BY {}",
+        dialect.session_tags, archived, where_clause, order
+    );
+    (query, params)
+}
+
+// Conditions matching every leaf value of `value`, found under `path`
+fn json_leaf_conditions(
+    value: &serde_json::Value,
+    path: &mut Vec<String>,
+    dialect: &SqlDialect,
+    conditions: &mut Vec<String>,
+    params: &mut Vec<SqlParam>,
+) {
+    let p = dialect.placeholder;
+    let bind_path = |params: &mut Vec<SqlParam>, path: &[String]| {
+        params.push((dialect.json_path)(path));
+        format!("{}{}", p, params.len() + 1)
+    };
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map {
+                path.push(key.clone());
+                json_leaf_conditions(child, path, dialect, conditions, params);
+                path.pop();
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                path.push(index.to_string());
+                json_leaf_conditions(child, path, dialect, conditions, params);
+                path.pop();
+            }
+        }
+        serde_json::Value::Number(number) => {
+            let bound_path = bind_path(params, path);
+            params.push(SqlParam::Float(number.as_f64().unwrap_or_default()));
+            conditions.push(format!("{} = {}{}", (dialect.json_number)(&bound_path), p, params.len() + 1));
+        }
+        serde_json::Value::Null => {
+            let bound_path = bind_path(params, path);
+            conditions.push(format!("{} IS NULL", (dialect.json_text)(&bound_path)));
+        }
+        // Strings, and booleans as 'true'/'false'
+        other => {
+            let bound_path = bind_path(params, path);
+            let text = other.as_str().map(|s| s.to_string()).unwrap_or_else(|| other.to_string());
+            params.push(SqlParam::Text(text));
+            conditions.push(format!("{} = {}{}", (dialect.json_text)(&bound_path), p, params.len() + 1));
+        }
+    }
+}
+
+/// SELECT packet_number of the first packet passing a filter set at or after a time
+pub(crate) fn packet_at_time_query(
+    filter_set: Option<&DbPacketFilterSet>,
+    ts: DateTime<Utc>,
+    dialect: &SqlDialect,
+) -> (String, Vec<SqlParam>) {
+    let (mut where_clause, mut params) = packet_filter_clause(filter_set, dialect);
+    params.push(SqlParam::Timestamp(ts.naive_utc()));
+    where_clause.push_str(&format!(" AND ts >= {}{}", dialect.placeholder, params.len() + 1));
+    (format!("SELECT packet_number FROM packets WHERE {} ORDER BY packet_number ASC LIMIT 1", where_clause), params)
+}
+
+/// SELECT COUNT(*) of a session's packets that pass a filter set, optionally only those before a packet number
+pub(crate) fn packet_count_query(
+    filter_set: Option<&DbPacketFilterSet>,
+    before: Option<i64>,
+    dialect: &SqlDialect,
+) -> (String, Vec<SqlParam>) {
+    let (mut where_clause, mut params) = packet_filter_clause(filter_set, dialect);
+    if let Some(number) = before {
+        params.push(SqlParam::Int(number));
+        where_clause.push_str(&format!(" AND packet_number < {}{}", dialect.placeholder, params.len() + 1));
+    }
+    (format!("SELECT COUNT(*) FROM packets WHERE {}", where_clause), params)
+}
+
+/// SELECT name, direction, count, bytes and first / last session time per packet type of the packets passing a
+/// filter set, for packet_stats_from_rows
+pub(crate) fn packet_stats_query(filter_set: Option<&DbPacketFilterSet>, dialect: &SqlDialect) -> (String, Vec<SqlParam>) {
+    let (where_clause, params) = packet_filter_clause(filter_set, dialect);
+    let query = format!(
+        "SELECT COALESCE({}, 'unknown'), direction, COUNT(*), CAST(SUM({}) AS BIGINT), \
+         MIN(session_time_ms), MAX(session_time_ms) FROM packets WHERE {} GROUP BY 1, 2",
+        dialect.packet_name, dialect.packet_size, where_clause
+    );
+    (query, params)
+}
+
+/// SELECT second since the session's first packet, direction and count of the packets passing a filter set, in
+/// second order, for packet_rate_from_rows
+pub(crate) fn packet_rate_query(filter_set: Option<&DbPacketFilterSet>, dialect: &SqlDialect) -> (String, Vec<SqlParam>) {
+    let (where_clause, params) = packet_filter_clause(filter_set, dialect);
+    let query = format!(
+        "SELECT (session_time_ms - (SELECT MIN(session_time_ms) FROM packets WHERE session_id = {}1)) / 1000, \
+         direction, COUNT(*) FROM packets WHERE {} GROUP BY 1, 2 ORDER BY 1",
+        dialect.placeholder, where_clause
+    );
+    (query, params)
+}
+
+/// SELECT previous packet number and name, packet number, name and session time, and gap (ms) of the `limit`
+/// largest gaps between consecutive packets passing a filter set, largest (then earliest) first
+pub(crate) fn packet_gaps_query(filter_set: Option<&DbPacketFilterSet>, limit: i64, dialect: &SqlDialect) -> (String, Vec<SqlParam>) {
+    let (where_clause, mut params) = packet_filter_clause(filter_set, dialect);
+    params.push(SqlParam::Int(limit));
+    let query = format!(
+        "SELECT previous_number, previous_name, packet_number, name, session_time_ms, session_time_ms - previous_time \
+         FROM (SELECT packet_number, {0} AS name, session_time_ms, \
+         LAG(packet_number) OVER (ORDER BY packet_number) AS previous_number, \
+         LAG({0}) OVER (ORDER BY packet_number) AS previous_name, \
+         LAG(session_time_ms) OVER (ORDER BY packet_number) AS previous_time \
+         FROM packets WHERE {1}) consecutive \
+         WHERE previous_number IS NOT NULL ORDER BY 6 DESC, 3 LIMIT {2}{3}",
+        dialect.packet_name,
+        where_clause,
+        dialect.placeholder,
+        params.len() + 1
+    );
+    (query, params)
+}
+
+/// Per second packet counts from (second, direction, count) rows in second order
+pub(crate) fn packet_rate_from_rows(rows: Vec<(i64, String, i64)>) -> Vec<PacketRateBucket> {
+    let mut buckets: Vec<PacketRateBucket> = Vec::new();
+    for (second, direction, count) in rows {
+        let bucket = match buckets.last_mut() {
+            Some(bucket) if bucket.second == second => bucket,
+            _ => {
+                buckets.push(PacketRateBucket { second, clientbound: 0, serverbound: 0 });
+                buckets.last_mut().expect("pushed above")
+            }
+        };
+        match direction.as_str() {
+            "clientbound" => bucket.clientbound += count,
+            _ => bucket.serverbound += count,
+        }
+    }
+    buckets
+}
+
+/// WHERE clause selecting a session's packets that pass a filter set
+/// Every filter value is a bound parameter, so filters can't inject SQL and the query text only depends on
+/// the shape of the filter set (which keeps the backends' prepared statement caches effective)
+/// Parameter 1 is the session id; the returned parameters are 2.. in order
+pub(crate) fn packet_filter_clause(filter_set: Option<&DbPacketFilterSet>, dialect: &SqlDialect) -> (String, Vec<SqlParam>) {
+    let p = dialect.placeholder;
+    let mut params = Vec::new();
+    let filters = match filter_set {
+        Some(filter_set) if !filter_set.filters.is_empty() => &filter_set.filters,
+        // No filters - show all packets
+        _ => return (format!("session_id = {}1", p), params),
+    };
+
+    // Conditions of one filter, combined with AND
+    let mut filter_conditions = |filter: &crate::db::DbPacketFilter| {
+        let mut conditions = Vec::new();
+        if let Some(ref direction) = filter.direction {
+            params.push(SqlParam::Text(direction.clone()));
+            conditions.push(format!("direction = {}{}", p, params.len() + 1));
+        }
+        if let Some(ref packet_name) = filter.packet_name {
+            if filter.packet_name_is_regex {
+                params.push(SqlParam::Text(packet_name.clone()));
+                conditions.push(format!("{} {} {}{}", dialect.packet_name, dialect.regex_match, p, params.len() + 1));
+            } else if filter.packet_name_is_wildcard {
+                // Convert * to % for SQL pattern matching
+                params.push(SqlParam::Text(packet_name.replace('*', "%")));
+                conditions.push(format!("{} {} {}{}", dialect.packet_name, dialect.like, p, params.len() + 1));
+            } else {
+                params.push(SqlParam::Text(packet_name.clone()));
+                conditions.push(format!("{} = {}{}", dialect.packet_name, p, params.len() + 1));
+            }
+        }
+        // Ranges are inclusive at both ends
+        if let Some(ts_from) = filter.ts_from {
+            params.push(SqlParam::Timestamp(ts_from.naive_utc()));
+            conditions.push(format!("ts >= {}{}", p, params.len() + 1));
+        }
+        if let Some(ts_to) = filter.ts_to {
+            params.push(SqlParam::Timestamp(ts_to.naive_utc()));
+            conditions.push(format!("ts <= {}{}", p, params.len() + 1));
+        }
+        if let Some(number_from) = filter.packet_number_from {
+            params.push(SqlParam::Int(number_from));
+            conditions.push(format!("packet_number >= {}{}", p, params.len() + 1));
+        }
+        if let Some(number_to) = filter.packet_number_to {
+            params.push(SqlParam::Int(number_to));
+            conditions.push(format!("packet_number <= {}{}", p, params.len() + 1));
+        }
+        for field in &filter.fields {
+            params.push((dialect.json_path)(&field.path));
+            let path = format!("{}{}", p, params.len() + 1);
+            match field.op {
+                DbFieldOp::Eq | DbFieldOp::Ne | DbFieldOp::Like => {
+                    let (op, value) = match field.op {
+                        DbFieldOp::Eq => ("=", field.value.clone()),
+                        DbFieldOp::Ne => ("<>", field.value.clone()),
+                        _ => (dialect.like, field.value.replace('*', "%")),
+                    };
+                    params.push(SqlParam::Text(value));
+                    let value = format!("{}{}", p, params.len() + 1);
+                    let condition = format!("{} {} {}", (dialect.json_text)(&path), op, value);
+                    if field.op == DbFieldOp::Ne {
+                        // A missing field is also "not equal"
+                        conditions.push(format!("({} IS NULL OR {})", (dialect.json_text)(&path), condition));
+                    } else {
+                        conditions.push(condition);
+                    }
+                }
+                DbFieldOp::Lt | DbFieldOp::Le | DbFieldOp::Gt | DbFieldOp::Ge => match field.value.parse::<f64>() {
+                    Ok(number) => {
+                        params.push(SqlParam::Float(number));
+                        conditions.push(format!(
+                            "{} {} {}{}",
+                            (dialect.json_number)(&path),
+                            field.op.symbol(),
+                            p,
+                            params.len() + 1
+                        ));
+                    }
+                    // A non-numeric bound matches nothing
+                    Err(_) => conditions.push("1=0".to_string()),
+                },
+            }
+        }
+        if let Some(entity) = filter.entity {
+            params.push(SqlParam::Int(entity));
+            conditions.push((dialect.json_entity)(&format!("{}{}", p, params.len() + 1)));
+        }
+        conditions
+    };
+    // Inclusion filters: OR logic, match any inclusion filter (none means match all before exclusions)
+    let mut inclusion_conditions = Vec::new();
+    let mut exclusion_conditions = Vec::new();
+    for filter in filters.iter().filter(|f| !f.is_exclusion) {
+        let conditions = filter_conditions(filter);
+        if conditions.is_empty() {
+            // No conditions means match all
+            inclusion_conditions.push("1=1".to_string());
+        } else {
+            inclusion_conditions.push(format!("({})", conditions.join(" AND ")));
+        }
+    }
+    // Exclusion filters: AND NOT logic, exclude packets matching any of them
+    for filter in filters.iter().filter(|f| f.is_exclusion) {
+        let conditions = filter_conditions(filter);
+        // An exclusion without conditions would exclude everything; skip it as invalid
+        if !conditions.is_empty() {
+            exclusion_conditions.push(format!("NOT ({})", conditions.join(" AND ")));
+        }
+    }
+
+    let mut where_parts = vec![format!("session_id = {}1", p)];
+    if inclusion_conditions.is_empty() {
+        where_parts.push("1=1".to_string());
+    } else {
+        where_parts.push(format!("({})", inclusion_conditions.join(" OR ")));
+    }
+    if !exclusion_conditions.is_empty() {
+        where_parts.push(format!("({})", exclusion_conditions.join(" AND ")));
+    }
+    (where_parts.join(" AND "), params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DbPacketFilter;
+
+    const TEST_DIALECT: SqlDialect = SqlDialect {
+        placeholder: '$',
+        packet_name: "name",
+        packet_size: "size(packet)",
+        regex_match: "~",
+        like: "ILIKE",
+        json_path: |path| SqlParam::TextArray(path.to_vec()),
+        json_text: |path| format!("text({})", path),
+        json_number: |path| format!("number({})", path),
+        json_contains: None,
+        chat_word: |word| format!("has_word({})", word),
+        text_contains: |text| format!("contains({})", text),
+        json_entity: |id| format!("entity({})", id),
+        session_duration: "s.ended_at - s.started_at",
+        session_tags: "tags(s.id)",
+    };
+
+    #[test]
+    fn test_filter_values_are_bound() {
+        let filter_set = DbPacketFilterSet {
+            filters: vec![
+                DbPacketFilter {
+                    direction: Some("clientbound' OR 1=1 --".to_string()),
+                    packet_name: Some("move*".to_string()),
+                    packet_name_is_wildcard: true,
+                    is_exclusion: false,
+                    ..Default::default()
+                },
+                DbPacketFilter {
+                    direction: Some("serverbound".to_string()),
+                    packet_name: None,
+                    packet_name_is_wildcard: false,
+                    is_exclusion: true,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let (where_clause, params) = packet_filter_clause(Some(&filter_set), &TEST_DIALECT);
+        assert_eq!(
+            where_clause,
+            "session_id = $1 AND ((direction = $2 AND name ILIKE $3)) AND (NOT (direction = $4))"
+        );
+        assert_eq!(
+            params,
+            vec![
+                SqlParam::Text("clientbound' OR 1=1 --".to_string()),
+                SqlParam::Text("move%".to_string()),
+                SqlParam::Text("serverbound".to_string()),
+            ]
+        );
+
+        let (query, params) = packet_page_query(Some(&filter_set), PageCursor::After(Some(10)), 50, &TEST_DIALECT);
+        assert!(query.ends_with("AND packet_number > $5 ORDER BY packet_number ASC LIMIT $6"));
+        assert_eq!(params[3..], [SqlParam::Int(10), SqlParam::Int(50)]);
+
+        let entity = DbPacketFilterSet {
+            filters: vec![DbPacketFilter { packet_name: Some("move_entity".to_string()), entity: Some(42), ..Default::default() }],
+        };
+        let (where_clause, params) = packet_filter_clause(Some(&entity), &TEST_DIALECT);
+        assert_eq!(where_clause, "session_id = $1 AND ((name = $2 AND entity($3)))");
+        assert_eq!(params, vec![SqlParam::Text("move_entity".to_string()), SqlParam::Int(42)]);
+    }
+
+    #[test]
+    fn test_packet_search_queries() {
+        let search = PacketSearch::parse("start_game Creative").unwrap();
+        let (query, params) = packet_search_query(None, &search, PageCursor::Before(Some(7)), 1, &TEST_DIALECT);
+        assert_eq!(
+            query,
+            format!(
+                "SELECT {} FROM packets WHERE session_id = $1 AND contains($2) AND contains($3) \
+                 AND packet_number < $4 ORDER BY packet_number DESC LIMIT $5",
+                PACKET_COLUMNS
+            )
+        );
+        assert_eq!(
+            params,
+            [
+                SqlParam::Text("start_game".to_string()),
+                SqlParam::Text("Creative".to_string()),
+                SqlParam::Int(7),
+                SqlParam::Int(1),
+            ]
+        );
+
+        let chat = PacketSearch::parse("chat: hi").unwrap();
+        assert_eq!(chat.to_input(), "chat:hi");
+        let (query, params) = packet_search_count_query(None, &chat, None, &TEST_DIALECT);
+        assert_eq!(query, "SELECT COUNT(*) FROM packets WHERE session_id = $1 AND name = 'text' AND has_word($2)");
+        assert_eq!(params, [SqlParam::Text("hi".to_string())]);
+        assert!(PacketSearch::parse("chat:").is_err());
+    }
+
+    #[test]
+    fn test_packet_stats_query() {
+        let filter_set = DbPacketFilterSet {
+            filters: vec![DbPacketFilter { direction: Some("clientbound".to_string()), ..Default::default() }],
+        };
+        let (query, params) = packet_stats_query(Some(&filter_set), &TEST_DIALECT);
+        assert_eq!(
+            query,
+            "SELECT COALESCE(name, 'unknown'), direction, COUNT(*), CAST(SUM(size(packet)) AS BIGINT), \
+             MIN(session_time_ms), MAX(session_time_ms) FROM packets WHERE session_id = $1 AND ((direction = $2)) \
+             GROUP BY 1, 2"
+        );
+        assert_eq!(params, [SqlParam::Text("clientbound".to_string())]);
+    }
+
+    #[test]
+    fn test_packet_gaps_query() {
+        let (query, params) = packet_gaps_query(None, 20, &TEST_DIALECT);
+        assert_eq!(
+            query,
+            "SELECT previous_number, previous_name, packet_number, name, session_time_ms, session_time_ms - previous_time \
+             FROM (SELECT packet_number, name AS name, session_time_ms, \
+             LAG(packet_number) OVER (ORDER BY packet_number) AS previous_number, \
+             LAG(name) OVER (ORDER BY packet_number) AS previous_name, \
+             LAG(session_time_ms) OVER (ORDER BY packet_number) AS previous_time \
+             FROM packets WHERE session_id = $1) consecutive \
+             WHERE previous_number IS NOT NULL ORDER BY 6 DESC, 3 LIMIT $2"
+        );
+        assert_eq!(params, [SqlParam::Int(20)]);
+    }
+
+    #[test]
+    fn test_session_search_query() {
+        let (query, params) = session_search_query(&SessionSearch::default(), &TEST_DIALECT);
+        assert_eq!(
+            query,
+            "SELECT s.id, s.started_at, s.ended_at, COALESCE(ss.packet_count, 0), ss.first_ts, ss.last_ts, tags(s.id), \
+             EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id), \
+             (SELECT sn.name FROM session_names sn WHERE sn.session_id = s.id) \
+             FROM sessions s LEFT JOIN session_summaries ss ON ss.session_id = s.id \
+             WHERE NOT EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id) ORDER BY s.started_at DESC"
+        );
+        assert!(params.is_empty());
+
+        let search = SessionSearch::parse("tag:repro to:2024-06-30 has:text sort:duration").unwrap();
+        assert_eq!(search.to_input(), "tag:repro to:2024-06-30 has:text sort:duration");
+        let (query, params) = session_search_query(&search, &TEST_DIALECT);
+        assert_eq!(
+            query,
+            "SELECT s.id, s.started_at, s.ended_at, COALESCE(ss.packet_count, 0), ss.first_ts, ss.last_ts, tags(s.id), \
+             EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id), \
+             (SELECT sn.name FROM session_names sn WHERE sn.session_id = s.id) \
+             FROM sessions s LEFT JOIN session_summaries ss ON ss.session_id = s.id WHERE s.started_at < $1 \
+             AND EXISTS (SELECT 1 FROM tag_maps m WHERE m.session_id = s.id AND m.tag = $2) \
+             AND EXISTS (SELECT 1 FROM packets WHERE packets.session_id = s.id AND name = $3) \
+             AND NOT EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id) \
+             ORDER BY s.ended_at IS NULL, s.ended_at - s.started_at DESC, s.started_at DESC"
+        );
+        assert_eq!(params[0], SqlParam::Timestamp("2024-07-01T00:00:00".parse().unwrap()));
+
+        let search = SessionSearch::parse("#12 repro sort:tag").unwrap();
+        assert_eq!(search.to_input(), "#12 repro sort:tag");
+        let (query, params) = session_search_query(&search, &TEST_DIALECT);
+        assert_eq!(
+            query,
+            "SELECT s.id, s.started_at, s.ended_at, COALESCE(ss.packet_count, 0), ss.first_ts, ss.last_ts, tags(s.id), \
+             EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id), \
+             (SELECT sn.name FROM session_names sn WHERE sn.session_id = s.id) \
+             FROM sessions s LEFT JOIN session_summaries ss ON ss.session_id = s.id WHERE (CAST(s.id AS TEXT) = $1 \
+             OR EXISTS (SELECT 1 FROM session_names sn WHERE sn.session_id = s.id AND sn.name ILIKE $2) \
+             OR EXISTS (SELECT 1 FROM tag_maps m WHERE m.session_id = s.id AND m.tag ILIKE $2) \
+             OR EXISTS (SELECT 1 FROM session_notes n WHERE n.session_id = s.id AND n.note ILIKE $2)) \
+             AND (CAST(s.id AS TEXT) = $3 \
+             OR EXISTS (SELECT 1 FROM session_names sn WHERE sn.session_id = s.id AND sn.name ILIKE $4) \
+             OR EXISTS (SELECT 1 FROM tag_maps m WHERE m.session_id = s.id AND m.tag ILIKE $4) \
+             OR EXISTS (SELECT 1 FROM session_notes n WHERE n.session_id = s.id AND n.note ILIKE $4)) \
+             AND NOT EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id) \
+             ORDER BY (SELECT MIN(m.tag) FROM tag_maps m WHERE m.session_id = s.id) IS NULL, \
+             (SELECT MIN(m.tag) FROM tag_maps m WHERE m.session_id = s.id), s.started_at DESC"
+        );
+        assert_eq!(params[..2], [SqlParam::Text("12".to_string()), SqlParam::Text("%#12%".to_string())]);
+
+        let search = SessionSearch::parse("archived:only").unwrap();
+        assert_eq!(search.to_input(), "archived:only");
+        let (query, _) = session_search_query(&search, &TEST_DIALECT);
+        assert!(query.ends_with("WHERE EXISTS (SELECT 1 FROM archived_sessions a WHERE a.session_id = s.id) ORDER BY s.started_at DESC"));
+        let (query, _) = session_search_query(&SessionSearch::parse("archived:include").unwrap(), &TEST_DIALECT);
+        assert!(query.ends_with("ON ss.session_id = s.id ORDER BY s.started_at DESC"));
+        assert!(SessionSearch::parse("archived:maybe").is_err());
+        assert!(SessionSearch::parse("color:red").is_err());
+        assert!(SessionSearch::parse("from:yesterday").is_err());
+    }
+}
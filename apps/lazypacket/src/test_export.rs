@@ -0,0 +1,148 @@
+// `export-test` subcommand: turn a curated packet sequence from a log into a Rust test
+// The generated test asserts the same packets (and selected field values) appear, in order,
+// in a capture of a replay against a server build (see packet_expect.rs)
+
+use crate::log_reader::LogReader;
+use crate::packet_dump::DumpContext;
+use crate::packet_logger::PacketDirection;
+use crate::packet_expect::json_path;
+use anyhow::{anyhow, Context, Result};
+use std::fmt::Write as _;
+
+#[derive(Default)]
+struct ExportOptions {
+    path: Option<String>,
+    name: Option<String>,
+    packets: Vec<String>,
+    direction: Option<PacketDirection>,
+    fields: Vec<String>,
+    from: usize,
+    count: Option<usize>,
+}
+
+impl ExportOptions {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut options = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = |flag: &str| {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{} requires a value", flag))
+            };
+            match arg.as_str() {
+                "--name" => options.name = Some(value("--name")?),
+                // Comma-separated or repeated
+                "--packet" => options.packets.extend(value("--packet")?.split(',').map(|s| s.trim().to_string())),
+                "--field" => options.fields.extend(value("--field")?.split(',').map(|s| s.trim().to_string())),
+                "--direction" => {
+                    options.direction = Some(match value("--direction")?.to_lowercase().as_str() {
+                        "clientbound" | "c" => PacketDirection::Clientbound,
+                        "serverbound" | "s" => PacketDirection::Serverbound,
+                        other => return Err(anyhow!("Invalid --direction: {}", other)),
+                    })
+                }
+                "--from" => options.from = value("--from")?.parse().context("Invalid --from")?,
+                "--count" => options.count = Some(value("--count")?.parse().context("Invalid --count")?),
+                _ if options.path.is_none() => options.path = Some(arg.clone()),
+                _ => return Err(anyhow!("Unexpected argument: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// `export-test [--name NAME] [--packet NAMES] [--direction DIR] [--field PATHS] [--from N] [--count M] [FILE|-]`
+/// Writes the generated test to stdout
+pub fn export_test(args: &[String]) -> Result<()> {
+    let options = ExportOptions::parse(args)?;
+    let path = options.path.as_deref().unwrap_or("-");
+    let context = DumpContext::new();
+    let reader = LogReader::open(path)?;
+
+    let mut expectations = String::new();
+    let mut exported = 0;
+    for entry in reader.skip(options.from) {
+        if options.count.is_some_and(|count| exported >= count) {
+            break;
+        }
+        let entry = entry?;
+        if options.direction.is_some_and(|d| d != entry.direction) {
+            continue;
+        }
+        // Undecodable packets can't be matched by name, so they can't be expected
+        let Some(packet) = context.packet_json(&entry) else {
+            continue;
+        };
+        let Some(name) = packet.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        if !options.packets.is_empty() && !options.packets.iter().any(|p| p == name) {
+            continue;
+        }
+
+        let direction = match entry.direction {
+            PacketDirection::Clientbound => "Clientbound",
+            PacketDirection::Serverbound => "Serverbound",
+        };
+        write!(expectations, "        ExpectedPacket::new(PacketDirection::{}, {:?})", direction, name)?;
+        for field in &options.fields {
+            if let Some(value) = json_path(&packet, field) {
+                write!(expectations, "\n            .field({:?}, {})", field, raw_string(&value.to_string()))?;
+            }
+        }
+        expectations.push_str(",\n");
+        exported += 1;
+    }
+
+    if exported == 0 {
+        return Err(anyhow!("No packets matched; nothing to export"));
+    }
+
+    let test_name = test_ident(options.name.as_deref().unwrap_or("capture"));
+    print!(
+        "\
+// Generated by `lazypacket export-test` from {source} ({exported} packets)
+// Run with REPLAY_LOG pointing at a capture of the replay against the server build under test:
+//   REPLAY_LOG=logs/<session>.bin cargo test replay_{test_name}
+
+use lazypacket::packet_expect::{{assert_log_contains, ExpectedPacket}};
+use lazypacket::packet_logger::PacketDirection;
+
+#[test]
+fn replay_{test_name}() {{
+    let log = std::env::var(\"REPLAY_LOG\").expect(\"set REPLAY_LOG to a capture of the replay\");
+    let expected = [
+{expectations}    ];
+    assert_log_contains(&log, &expected).unwrap();
+}}
+",
+        source = if path == "-" { "stdin" } else { path },
+    );
+
+    Ok(())
+}
+
+/// Rust raw string literal for `s`, with enough `#`s that `s` can't terminate it early
+fn raw_string(s: &str) -> String {
+    let mut hashes = 1;
+    while s.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let hashes = "#".repeat(hashes);
+    format!("r{}\"{}\"{}", hashes, s, hashes)
+}
+
+/// Lowercase identifier for the test function name
+fn test_ident(name: &str) -> String {
+    let ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let ident = ident.trim_matches('_');
+    if ident.is_empty() {
+        "capture".to_string()
+    } else {
+        ident.to_string()
+    }
+}
@@ -0,0 +1,221 @@
+// Viewer colors and borders, from the theme section of the viewer config file (viewer_config.rs):
+//   theme:
+//     base: light             # dark (default), light or high-contrast
+//     serverbound: "#5f87ff"  # any role below, as a color name, a 256-color index or #rrggbb
+//     border_type: rounded    # plain, rounded, double or thick
+
+use anyhow::{anyhow, Context, Result};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, BorderType, Borders};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub clientbound: Color,
+    pub serverbound: Color,
+    pub added: Color, // Values added in a compare mode diff
+    pub removed: Color, // Values removed in a compare mode diff
+    pub highlight: Color, // Selected rows, inputs, the current packet
+    pub highlight_text: Color, // Text on a highlight background
+    pub text: Color, // List entries that aren't selected
+    pub secondary: Color, // Labels and entries that are switched off
+    pub muted: Color, // Help text, timestamps, offsets
+    pub accent: Color, // Headings and informational messages
+    pub error: Color,
+    pub status: Color, // Outcome of the last action
+    pub json_key: Color,
+    pub json_string: Color,
+    pub json_number: Color,
+    pub json_bool: Color,
+    pub json_null: Color,
+    pub border: Color,
+    pub border_type: BorderType,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            clientbound: Color::Green,
+            serverbound: Color::Blue,
+            added: Color::Green,
+            removed: Color::Red,
+            highlight: Color::Yellow,
+            highlight_text: Color::Black,
+            text: Color::White,
+            secondary: Color::Gray,
+            muted: Color::DarkGray,
+            accent: Color::Cyan,
+            error: Color::Red,
+            status: Color::Green,
+            json_key: Color::Cyan,
+            json_string: Color::Green,
+            json_number: Color::Magenta,
+            json_bool: Color::Yellow,
+            json_null: Color::DarkGray,
+            border: Color::Reset,
+            border_type: BorderType::Plain,
+        }
+    }
+
+    /// For light terminal backgrounds, where yellow and gray text fade out
+    pub fn light() -> Self {
+        Theme {
+            clientbound: Color::Green,
+            serverbound: Color::Blue,
+            added: Color::Green,
+            removed: Color::Red,
+            highlight: Color::Blue,
+            highlight_text: Color::White,
+            text: Color::Black,
+            secondary: Color::DarkGray,
+            muted: Color::DarkGray,
+            accent: Color::Magenta,
+            error: Color::Red,
+            status: Color::Green,
+            json_key: Color::Blue,
+            json_string: Color::Green,
+            json_number: Color::Magenta,
+            json_bool: Color::Red,
+            json_null: Color::DarkGray,
+            border: Color::Black,
+            border_type: BorderType::Plain,
+        }
+    }
+
+    /// Bright colors only, and thick borders
+    pub fn high_contrast() -> Self {
+        Theme {
+            clientbound: Color::LightGreen,
+            serverbound: Color::LightCyan,
+            added: Color::LightGreen,
+            removed: Color::LightRed,
+            highlight: Color::LightYellow,
+            highlight_text: Color::Black,
+            text: Color::White,
+            secondary: Color::White,
+            muted: Color::Gray,
+            accent: Color::LightCyan,
+            error: Color::LightRed,
+            status: Color::LightGreen,
+            json_key: Color::LightCyan,
+            json_string: Color::LightGreen,
+            json_number: Color::LightMagenta,
+            json_bool: Color::LightYellow,
+            json_null: Color::Gray,
+            border: Color::White,
+            border_type: BorderType::Thick,
+        }
+    }
+
+    pub fn named(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            other => Err(anyhow!("Unknown theme: {} (expected dark, light or high-contrast)", other)),
+        }
+    }
+
+    /// The base theme with the section's other entries overriding it
+    pub fn from_config(config: &BTreeMap<String, String>) -> Result<Self> {
+        let mut theme = match config.get("base") {
+            Some(base) => Self::named(base)?,
+            None => Self::default(),
+        };
+        for (name, value) in config {
+            match name.as_str() {
+                "base" => {}
+                "border_type" => theme.border_type = parse_border_type(value)?,
+                role => {
+                    let color = theme.color_mut(role).ok_or_else(|| anyhow!("Unknown theme entry: {}", role))?;
+                    *color = value.parse().ok().with_context(|| format!("Invalid color for {}: {}", role, value))?;
+                }
+            }
+        }
+        Ok(theme)
+    }
+
+    fn color_mut(&mut self, role: &str) -> Option<&mut Color> {
+        Some(match role {
+            "clientbound" => &mut self.clientbound,
+            "serverbound" => &mut self.serverbound,
+            "added" => &mut self.added,
+            "removed" => &mut self.removed,
+            "highlight" => &mut self.highlight,
+            "highlight_text" => &mut self.highlight_text,
+            "text" => &mut self.text,
+            "secondary" => &mut self.secondary,
+            "muted" => &mut self.muted,
+            "accent" => &mut self.accent,
+            "error" => &mut self.error,
+            "status" => &mut self.status,
+            "json_key" => &mut self.json_key,
+            "json_string" => &mut self.json_string,
+            "json_number" => &mut self.json_number,
+            "json_bool" => &mut self.json_bool,
+            "json_null" => &mut self.json_null,
+            "border" => &mut self.border,
+            _ => return None,
+        })
+    }
+
+    /// A bordered block in the theme's border style
+    pub fn block(&self) -> Block<'static> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(self.border_type)
+            .border_style(Style::default().fg(self.border))
+    }
+
+    pub fn diff(&self, added: bool) -> Color {
+        if added { self.added } else { self.removed }
+    }
+}
+
+fn parse_border_type(value: &str) -> Result<BorderType> {
+    match value {
+        "plain" => Ok(BorderType::Plain),
+        "rounded" => Ok(BorderType::Rounded),
+        "double" => Ok(BorderType::Double),
+        "thick" => Ok(BorderType::Thick),
+        other => Err(anyhow!("Invalid border_type: {} (expected plain, rounded, double or thick)", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_from_config() {
+        let config = |entries: &[(&str, &str)]| -> BTreeMap<String, String> {
+            entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        assert_eq!(Theme::from_config(&config(&[])).unwrap(), Theme::dark());
+
+        let theme = Theme::from_config(&config(&[
+            ("base", "high-contrast"),
+            ("serverbound", "#5f87ff"),
+            ("added", "light-blue"),
+            ("muted", "244"),
+            ("border_type", "rounded"),
+        ]))
+        .unwrap();
+        assert_eq!(theme.serverbound, Color::Rgb(0x5f, 0x87, 0xff));
+        assert_eq!(theme.added, Color::LightBlue);
+        assert_eq!(theme.muted, Color::Indexed(244));
+        assert_eq!(theme.border_type, BorderType::Rounded);
+        assert_eq!(theme.clientbound, Theme::high_contrast().clientbound);
+
+        assert!(Theme::from_config(&config(&[("base", "solarized")])).is_err());
+        assert!(Theme::from_config(&config(&[("clientbound", "greenish")])).is_err());
+        assert!(Theme::from_config(&config(&[("background", "black")])).is_err());
+        assert!(Theme::from_config(&config(&[("border_type", "dashed")])).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+// Protocol version switcher (`V` in the packet view): decodes a packet's raw payload with another protocol version
+// from the registry, to compare with what it was stored as. Packets stored without a name keep their bytes in
+// params.data; for the others the payload is rebuilt by encoding the stored JSON with the definitions of the
+// version it was logged with, which gives back the bytes that were captured for any packet they decode fully
+
+use crate::packet_logger::{decode_hex, PacketDirection};
+use crate::protocol::ProtocolParser;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// The bytes of a stored packet, with `logged` the parser of the version it was logged with
+pub fn raw_payload(packet: &Value, logged: Option<&ProtocolParser>) -> Result<Vec<u8>> {
+    let name = packet["name"].as_str().unwrap_or("unknown");
+    if name == "unknown" {
+        let hex = packet["params"]["data"].as_str().ok_or_else(|| anyhow!("The packet's bytes weren't stored"))?;
+        return decode_hex(hex);
+    }
+    let logged = logged.ok_or_else(|| anyhow!("No protocol definitions for the version the packet was logged with"))?;
+    logged.encode_packet(name, &packet["params"])
+}
+
+/// A payload decoded with another version, as stored packet JSON ({name, params}), and where decoding broke
+pub fn decode_with(data: &[u8], direction: PacketDirection, parser: &ProtocolParser) -> (Value, Option<String>) {
+    let decoded = parser.decode_packet(data, direction);
+    let error = match (&decoded.error, decoded.packet_id) {
+        (Some(error), _) => Some(error.to_string()),
+        (None, Some(id)) if decoded.packet_name.is_none() => Some(format!("No {:?} packet with id {} is defined", direction, id)),
+        (None, None) => Some("No packet id in the payload".to_string()),
+        (None, _) => None,
+    };
+    let packet = decoded.to_packet_json().unwrap_or_else(|| json!({ "name": "unknown", "params": {} }));
+    (packet, error)
+}
+
+/// Fields (leaf values) of decoded packet JSON, to compare how much each version decodes
+pub fn count_fields(value: &Value) -> usize {
+    match value {
+        Value::Object(fields) => fields.values().map(count_fields).sum(),
+        Value::Array(items) => items.iter().map(count_fields).sum(),
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_payload() {
+        let unknown = json!({ "name": "unknown", "params": { "data": "fe0102" } });
+        assert_eq!(raw_payload(&unknown, None).unwrap(), [0xfe, 0x01, 0x02]);
+        let undecoded = json!({ "name": "unknown", "params": {} });
+        assert!(raw_payload(&undecoded, None).is_err());
+        // Rebuilding a named packet's bytes takes its logged version's definitions
+        let text = json!({ "name": "text", "params": { "message": "hi" } });
+        assert!(raw_payload(&text, None).is_err());
+
+        assert_eq!(count_fields(&json!({ "name": "text", "params": { "message": "hi", "xuid": ["a", "b"] } })), 4);
+    }
+}
@@ -0,0 +1,401 @@
+// Viewer settings from a YAML file: LAZYPACKET_CONFIG, or lazypacket.yml in the project root (the directory of the
+// loaded .env) when it exists. Its keymap section remaps packet view keys by action name, e.g.
+//   keymap:
+//     previous_packet: [Left, a]
+//     next_packet: [Right, d]
+//     compare: m
+// A key is a character or one of Left, Right, Up, Down, PageUp, PageDown, Home, End, Tab, Enter, Backspace, Delete,
+// Insert, Space and F1-F12. An action's default keys keep working unless they're mapped to another action.
+// Its theme section sets the viewer's colors (theme.rs).
+
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CONFIG_FILE: &str = "lazypacket.yml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewerConfig {
+    pub keymap: BTreeMap<String, KeyList>,
+    pub theme: BTreeMap<String, String>, // Read by Theme::from_config (theme.rs)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn keys(&self) -> &[String] {
+        match self {
+            KeyList::One(key) => std::slice::from_ref(key),
+            KeyList::Many(keys) => keys,
+        }
+    }
+}
+
+impl ViewerConfig {
+    /// LAZYPACKET_CONFIG, which must exist when set, or the project's lazypacket.yml if there is one
+    pub fn load() -> Result<Self> {
+        match std::env::var("LAZYPACKET_CONFIG") {
+            Ok(path) => Self::from_path(Path::new(&path)),
+            Err(_) => {
+                let path = crate::storage::project_root().join(DEFAULT_CONFIG_FILE);
+                if path.exists() {
+                    Self::from_path(&path)
+                } else {
+                    Ok(Self::default())
+                }
+            }
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid viewer config {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        // An empty file is a config without settings
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    /// Path the config is read from, for messages
+    pub fn path() -> PathBuf {
+        std::env::var("LAZYPACKET_CONFIG")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(DEFAULT_CONFIG_FILE))
+    }
+}
+
+/// Something a packet view key does
+pub struct Action {
+    pub name: &'static str, // Name in the keymap section
+    pub keys: &'static [KeyCode], // Default keys; the packet view handles the first
+    pub description: &'static str,
+}
+
+/// Packet view actions, in the order the header and cheat sheet list them
+pub const ACTIONS: &[Action] = &[
+    Action { name: "help", keys: &[KeyCode::Char('?')], description: "keys" },
+    Action { name: "previous_packet", keys: &[KeyCode::Left, KeyCode::Char('h')], description: "previous packet" },
+    Action { name: "next_packet", keys: &[KeyCode::Right, KeyCode::Char('l')], description: "next packet" },
+    Action { name: "scroll_up", keys: &[KeyCode::Up, KeyCode::Char('k')], description: "scroll up" },
+    Action { name: "scroll_down", keys: &[KeyCode::Down, KeyCode::Char('j')], description: "scroll down" },
+    Action { name: "page_up", keys: &[KeyCode::PageUp], description: "back 10 packets" },
+    Action { name: "page_down", keys: &[KeyCode::PageDown], description: "forward 10 packets" },
+    Action { name: "first_packet", keys: &[KeyCode::Home], description: "first packet" },
+    Action { name: "last_packet", keys: &[KeyCode::End], description: "last packet" },
+    Action { name: "packet_list", keys: &[KeyCode::Char('t')], description: "packet list" },
+    Action { name: "rate_histogram", keys: &[KeyCode::Char('H')], description: "rate histogram" },
+    Action { name: "toggle_hex", keys: &[KeyCode::Char('x'), KeyCode::Char('X')], description: "JSON / hex view" },
+    Action { name: "hex_link", keys: &[KeyCode::Char('v')], description: "linked hex view" },
+    Action { name: "follow", keys: &[KeyCode::Char('L')], description: "follow a live session" },
+    Action { name: "playback", keys: &[KeyCode::Char(' ')], description: "play / pause" },
+    Action { name: "playback_slower", keys: &[KeyCode::Char('<')], description: "slower playback" },
+    Action { name: "playback_faster", keys: &[KeyCode::Char('>')], description: "faster playback" },
+    Action { name: "filter", keys: &[KeyCode::Char('f'), KeyCode::Char('F')], description: "filter" },
+    Action { name: "clientbound", keys: &[KeyCode::Char('1')], description: "clientbound only" },
+    Action { name: "serverbound", keys: &[KeyCode::Char('2')], description: "serverbound only" },
+    Action { name: "both_directions", keys: &[KeyCode::Char('0')], description: "both directions" },
+    Action { name: "only_type", keys: &[KeyCode::Char('=')], description: "only this packet type" },
+    Action { name: "hide_type", keys: &[KeyCode::Char('-')], description: "hide this packet type" },
+    Action { name: "goto", keys: &[KeyCode::Char('g')], description: "go to" },
+    Action { name: "watch", keys: &[KeyCode::Char('w')], description: "watch a field" },
+    Action { name: "search", keys: &[KeyCode::Char('/')], description: "search" },
+    Action { name: "next_match", keys: &[KeyCode::Char('n')], description: "next match" },
+    Action { name: "previous_match", keys: &[KeyCode::Char('N')], description: "previous match" },
+    Action { name: "export_packet", keys: &[KeyCode::Char('e')], description: "export packet" },
+    Action { name: "export_filtered", keys: &[KeyCode::Char('E')], description: "export filtered packets" },
+    Action { name: "copy", keys: &[KeyCode::Char('y')], description: "copy" },
+    Action { name: "stats", keys: &[KeyCode::Char('s')], description: "session stats" },
+    Action { name: "filtered_stats", keys: &[KeyCode::Char('S')], description: "filtered stats" },
+    Action { name: "note", keys: &[KeyCode::Char('a')], description: "note" },
+    Action { name: "bookmark", keys: &[KeyCode::Char('b')], description: "bookmark" },
+    Action { name: "bookmarks", keys: &[KeyCode::Char('B')], description: "bookmarks" },
+    Action { name: "previous_bookmark", keys: &[KeyCode::Char('[')], description: "previous bookmark" },
+    Action { name: "next_bookmark", keys: &[KeyCode::Char(']')], description: "next bookmark" },
+    Action { name: "compare", keys: &[KeyCode::Char('c')], description: "compare" },
+    Action { name: "pin_baseline", keys: &[KeyCode::Char('C')], description: "pin baseline across sessions" },
+    Action { name: "compare_previous", keys: &[KeyCode::Char('p')], description: "compare with previous of type" },
+    Action { name: "ignored_fields", keys: &[KeyCode::Char('i')], description: "ignored fields" },
+    Action { name: "follow_entity", keys: &[KeyCode::Char('m')], description: "follow the packet's entity" },
+    Action { name: "movement_map", keys: &[KeyCode::Char('M')], description: "player movement map" },
+    Action { name: "gaps", keys: &[KeyCode::Char('G')], description: "gaps and round trips" },
+    Action { name: "exchanges", keys: &[KeyCode::Char('R')], description: "requests and responses" },
+    Action { name: "sequence_diagram", keys: &[KeyCode::Char('T')], description: "sequence diagram" },
+    Action { name: "decode_errors", keys: &[KeyCode::Char('D')], description: "decode errors" },
+    Action { name: "protocol_version", keys: &[KeyCode::Char('V')], description: "decode with another protocol version" },
+    Action { name: "back", keys: &[KeyCode::Char('q')], description: "back" },
+];
+
+/// Keys of the packet view that the keymap doesn't cover
+pub const PACKET_VIEW_FIXED_KEYS: &[(&str, &str)] = &[
+    ("Esc", "leave compare mode, or back"),
+    ("mouse wheel", "scroll"),
+    ("click +/-", "expand / collapse JSON"),
+    ("click a row", "select the packet"),
+];
+
+/// Keys of the other views and panels, which can't be remapped
+pub const VIEW_KEYS: &[(&str, &[(&str, &str)])] = &[
+    ("Session list", &[
+        ("↑/↓", "select a session"),
+        ("Enter", "open it"),
+        ("/", "search and sort sessions"),
+        ("o", "cycle the sort order"),
+        ("s", "statistics"),
+        ("t", "tags"),
+        ("a", "note"),
+        ("r", "rename"),
+        ("e", "export to .lpsession"),
+        ("A", "archive, or restore an archived session"),
+        ("d/D", "delete"),
+        ("i", "database health"),
+        ("?", "keys"),
+        ("Esc", "clear the search, or quit"),
+        ("q", "quit"),
+    ]),
+    ("Filter input", &[
+        ("Enter", "apply"),
+        ("Tab", "complete the packet name, or saved presets"),
+        ("↑/↓", "earlier filters"),
+        ("Esc", "cancel"),
+    ]),
+    ("Search, go to, watch, export, name and note inputs", &[
+        ("Enter", "search / go / watch / export / save"),
+        ("Alt+Enter/Ctrl+J", "new line in a note"),
+        ("Esc", "cancel"),
+    ]),
+    ("Statistics", &[
+        ("↑/↓/j/k", "scroll"),
+        ("o", "sort by the next column"),
+        ("Esc/q/s/S", "close"),
+    ]),
+    ("Bookmarks", &[
+        ("↑/↓/j/k", "select"),
+        ("Enter", "go to the packet"),
+        ("l", "label"),
+        ("c", "color"),
+        ("d", "delete"),
+        ("Esc/q", "close"),
+    ]),
+    ("Filter presets", &[
+        ("↑/↓/j/k", "select"),
+        ("Enter", "load into the filter"),
+        ("s", "save the current filter"),
+        ("d", "delete"),
+        ("Esc/q", "back"),
+    ]),
+    ("Ignored fields", &[
+        ("↑/↓/j/k", "select"),
+        ("Enter/Space", "ignore, or compare again"),
+        ("a", "add a path"),
+        ("Esc/q", "close"),
+    ]),
+    ("Linked hex view", &[
+        ("↑/↓/j/k", "select a field"),
+        ("Tab", "switch between fields and bytes"),
+        ("←→↑↓/h/l/k/j", "move the byte cursor"),
+        ("Esc/q/v", "close"),
+    ]),
+    ("Movement map", &[
+        ("←/→/h/l", "previous / next position"),
+        ("Home/End", "first / last position"),
+        ("Esc/q/M", "close"),
+    ]),
+    ("Gaps and round trips", &[
+        ("↑/↓/j/k", "select"),
+        ("Enter", "go to the packet after the gap, or the slowest request"),
+        ("Esc/q/G", "close"),
+    ]),
+    ("Requests and responses", &[
+        ("↑/↓/j/k", "select an exchange, going to its request"),
+        ("←/h/Enter", "go to the request"),
+        ("→/l", "go to the response"),
+        ("Esc/q/R", "close"),
+    ]),
+    ("Sequence diagram", &[
+        ("↑/↓/j/k/PgUp/PgDn", "select a packet"),
+        ("Enter", "go to the packet"),
+        ("Tab", "handshake / from the current packet"),
+        ("y", "copy as text"),
+        ("Esc/q/T", "close"),
+    ]),
+    ("Decode errors", &[
+        ("↑/↓/j/k", "select"),
+        ("Enter", "go to the packet"),
+        ("Esc/q/D", "close"),
+    ]),
+    ("Protocol versions", &[
+        ("↑/↓/j/k", "select a version"),
+        ("PgUp/PgDn", "scroll the comparison"),
+        ("Enter", "decode the session with it"),
+        ("Esc/q/V", "close"),
+    ]),
+    ("Tags", &[
+        ("↑/↓", "select"),
+        ("a", "add a tag"),
+        ("d", "delete a tag"),
+        ("Esc/q", "close"),
+    ]),
+];
+
+/// Packet view keys, with the keymap section's remappings
+#[derive(Debug, Default)]
+pub struct Keymap {
+    remapped: HashMap<KeyCode, KeyCode>, // Configured key -> the default key of its action
+    configured: HashMap<&'static str, Vec<KeyCode>>, // Keys configured per action name
+}
+
+impl Keymap {
+    pub fn new(keymap: &BTreeMap<String, KeyList>) -> Result<Self> {
+        let mut map = Keymap::default();
+        let mut mapped_by: HashMap<KeyCode, &'static str> = HashMap::new();
+        for (name, keys) in keymap {
+            let action = ACTIONS
+                .iter()
+                .find(|action| action.name == name)
+                .ok_or_else(|| anyhow!("Unknown action in keymap: {} (expected one of {})", name, action_names()))?;
+            let mut codes = Vec::new();
+            for key in keys.keys() {
+                let code = parse_key(key).with_context(|| format!("Invalid key for {}", name))?;
+                if let Some(other) = mapped_by.insert(code, action.name).filter(|other| *other != action.name) {
+                    return Err(anyhow!("{} is mapped to both {} and {}", format_key(code), other, action.name));
+                }
+                if code != action.keys[0] {
+                    map.remapped.insert(code, action.keys[0]);
+                }
+                codes.push(code);
+            }
+            map.configured.insert(action.name, codes);
+        }
+        Ok(map)
+    }
+
+    /// The key the packet view handles for a pressed key
+    pub fn translate(&self, code: KeyCode) -> KeyCode {
+        self.remapped.get(&code).copied().unwrap_or(code)
+    }
+
+    /// Keys that do an action: those configured for it, then its defaults that no other action took
+    pub fn keys(&self, action: &Action) -> Vec<KeyCode> {
+        let mut keys = self.configured.get(action.name).cloned().unwrap_or_default();
+        for &key in action.keys {
+            let taken = self.translate(key) != key;
+            if !taken && !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// "keys: description" for some actions, e.g. "Left/h: previous packet, Right/l: next packet"
+    pub fn hints(&self, names: &[&str]) -> String {
+        self.cheat_sheet()
+            .into_iter()
+            .zip(ACTIONS)
+            .filter(|(_, action)| names.contains(&action.name))
+            .map(|((keys, description), _)| format!("{}: {}", keys, description))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// `keys: description` for every action, e.g. "Left/h: previous packet"
+    pub fn cheat_sheet(&self) -> Vec<(String, &'static str)> {
+        ACTIONS
+            .iter()
+            .map(|action| {
+                let keys: Vec<String> = self.keys(action).into_iter().map(format_key).collect();
+                let keys = if keys.is_empty() { "(none)".to_string() } else { keys.join("/") };
+                (keys, action.description)
+            })
+            .collect()
+    }
+}
+
+fn action_names() -> String {
+    ACTIONS.iter().map(|action| action.name).collect::<Vec<_>>().join(", ")
+}
+
+pub fn parse_key(key: &str) -> Result<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let code = match key.to_ascii_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "space" => KeyCode::Char(' '),
+        // Esc always closes or cancels, so it can't be remapped
+        "esc" => return Err(anyhow!("Esc can't be remapped")),
+        name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return Err(anyhow!("Unknown key: {}", key)),
+        },
+    };
+    Ok(code)
+}
+
+pub fn format_key(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap() {
+        let config = ViewerConfig::parse("keymap:\n  previous_packet: [a, Left]\n  next_packet: d\n  compare: F2\n").unwrap();
+        let keymap = Keymap::new(&config.keymap).unwrap();
+        assert_eq!(keymap.translate(KeyCode::Char('a')), KeyCode::Left);
+        assert_eq!(keymap.translate(KeyCode::Char('d')), KeyCode::Right);
+        assert_eq!(keymap.translate(KeyCode::F(2)), KeyCode::Char('c'));
+        // Unmapped and default keys pass through
+        assert_eq!(keymap.translate(KeyCode::Char('l')), KeyCode::Char('l'));
+        assert_eq!(keymap.translate(KeyCode::Left), KeyCode::Left);
+
+        let cheat_sheet = keymap.cheat_sheet();
+        assert!(cheat_sheet.contains(&("a/Left/h".to_string(), "previous packet")));
+        assert!(cheat_sheet.contains(&("d/Right/l".to_string(), "next packet")));
+        // a was taken from the note action
+        assert!(cheat_sheet.contains(&("(none)".to_string(), "note")));
+        assert!(cheat_sheet.contains(&("PgUp".to_string(), "back 10 packets")));
+        assert_eq!(keymap.hints(&["next_packet", "compare", "help"]), "?: keys, d/Right/l: next packet, F2/c: compare");
+
+        let config = ViewerConfig::parse("keymap:\n  compare: x\n  toggle_hex: x\n").unwrap();
+        assert!(Keymap::new(&config.keymap).is_err());
+        let config = ViewerConfig::parse("keymap:\n  jump: x\n").unwrap();
+        assert!(Keymap::new(&config.keymap).is_err());
+        assert!(ViewerConfig::parse("keymap:\n  compare: Esc\n").map(|c| Keymap::new(&c.keymap)).unwrap().is_err());
+        assert!(ViewerConfig::parse("keys: {}\n").is_err());
+        assert!(ViewerConfig::parse("").unwrap().keymap.is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+// Generated by `lazypacket codegen` from protocol test; regenerate instead of editing
+
+/// packet_rewrite (0x44)
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PacketRewrite {
+    pub name: String,
+    pub mode: GameMode,
+    pub flags: InputFlag,
+    pub position: crate::protocol_codegen::Vec3f,
+    pub entries: Vec<Entry>,
+    pub tag: Option<crate::protocol_codegen::Long>,
+    pub r#type: u8,
+    #[serde(rename = "runtimeEntityId")]
+    pub runtime_entity_id: crate::protocol_codegen::Long,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub tree: Node,
+}
+
+impl crate::protocol_codegen::TypedPacket for PacketRewrite {
+    const NAME: &'static str = "packet_rewrite";
+    const ID: u32 = 0x44;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum GameMode {
+    #[serde(rename = "survival")]
+    Survival, // 0
+    #[serde(rename = "creative")]
+    Creative, // 1
+    #[serde(untagged)]
+    Unknown(crate::protocol_codegen::Long),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct InputFlag {
+    pub ascend: bool, // 0x1
+    pub descend: bool, // 0x2
+    pub jump: bool, // 0x4
+    #[serde(rename = "_value")]
+    pub value: crate::protocol_codegen::Long,
+}
+
+/// Entry
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    pub key: String,
+    pub value: crate::protocol_codegen::Long,
+}
+
+/// Node
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Node {
+    pub name: String,
+    pub children: Vec<Node>,
+    pub parent: Option<Box<Node>>,
+}
+
+/// packet_input (0x90)
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PacketInput {
+    pub mode: GameMode,
+    pub held: crate::protocol_codegen::Item,
+}
+
+impl crate::protocol_codegen::TypedPacket for PacketInput {
+    const NAME: &'static str = "packet_input";
+    const ID: u32 = 0x90;
+}
@@ -0,0 +1,8 @@
+-- Human-readable session names set in lazypacket's session list (Storage::set_session_name), shown instead of
+-- the session's number; a session without a row has no name
+
+CREATE TABLE IF NOT EXISTS session_names (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
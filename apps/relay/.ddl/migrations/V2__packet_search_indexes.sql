@@ -0,0 +1,10 @@
+-- GIN indexes for packet content search (lazypacket's Storage::search_packets)
+
+-- Containment search, e.g. packet @> '{"name": "text"}'
+-- jsonb_path_ops only supports @>, but is smaller and faster for it than the default operator class
+CREATE INDEX IF NOT EXISTS idx_packets_packet_gin ON packets USING GIN (packet jsonb_path_ops);
+
+-- Full-text search over chat messages
+CREATE INDEX IF NOT EXISTS idx_packets_chat_message_fts ON packets
+    USING GIN (to_tsvector('simple', packet->'params'->>'message'))
+    WHERE packet->>'name' = 'text';
@@ -0,0 +1,17 @@
+-- Free-form notes recorded during analysis (lazypacket's Storage::set_session_note / set_packet_note)
+
+-- One note per session
+CREATE TABLE IF NOT EXISTS session_notes (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    note TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One note per packet, identified by its packet_number within the session
+CREATE TABLE IF NOT EXISTS packet_notes (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_number BIGINT NOT NULL,
+    note TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (session_id, packet_number)
+);
@@ -0,0 +1,9 @@
+-- Named packet filters saved from lazypacket's filter panel (Storage::save_filter_preset)
+-- filter holds the filter text as typed in the panel, e.g. 'c.move_player,c.move_entity'
+
+CREATE TABLE IF NOT EXISTS filters (
+    name VARCHAR(255) PRIMARY KEY,
+    filter TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,51 @@
+-- Per-session packet count and first/last packet time, kept up to date by triggers on packets,
+-- so the session list is one query instead of a COUNT(*) per session
+-- Sessions without packets have no row (read with LEFT JOIN and COALESCE(packet_count, 0))
+-- Packets are only deleted together with their session, so deletes just lower the count
+
+CREATE TABLE IF NOT EXISTS session_summaries (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_count BIGINT NOT NULL DEFAULT 0,
+    first_ts TIMESTAMP,
+    last_ts TIMESTAMP
+);
+
+-- Statement-level with transition tables, so a multi-row INSERT or COPY updates each session once
+CREATE OR REPLACE FUNCTION session_summaries_packets_inserted() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO session_summaries (session_id, packet_count, first_ts, last_ts)
+    SELECT session_id, COUNT(*), MIN(ts), MAX(ts) FROM inserted_packets GROUP BY session_id
+    ON CONFLICT (session_id) DO UPDATE SET
+        packet_count = session_summaries.packet_count + EXCLUDED.packet_count,
+        first_ts = LEAST(session_summaries.first_ts, EXCLUDED.first_ts),
+        last_ts = GREATEST(session_summaries.last_ts, EXCLUDED.last_ts);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION session_summaries_packets_deleted() RETURNS trigger AS $$
+BEGIN
+    UPDATE session_summaries s
+    SET packet_count = GREATEST(s.packet_count - d.packet_count, 0)
+    FROM (SELECT session_id, COUNT(*) AS packet_count FROM deleted_packets GROUP BY session_id) d
+    WHERE s.session_id = d.session_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS packets_summary_insert ON packets;
+CREATE TRIGGER packets_summary_insert
+    AFTER INSERT ON packets
+    REFERENCING NEW TABLE AS inserted_packets
+    FOR EACH STATEMENT EXECUTE FUNCTION session_summaries_packets_inserted();
+
+DROP TRIGGER IF EXISTS packets_summary_delete ON packets;
+CREATE TRIGGER packets_summary_delete
+    AFTER DELETE ON packets
+    REFERENCING OLD TABLE AS deleted_packets
+    FOR EACH STATEMENT EXECUTE FUNCTION session_summaries_packets_deleted();
+
+-- Existing sessions
+INSERT INTO session_summaries (session_id, packet_count, first_ts, last_ts)
+SELECT session_id, COUNT(*), MIN(ts), MAX(ts) FROM packets GROUP BY session_id
+ON CONFLICT (session_id) DO NOTHING;
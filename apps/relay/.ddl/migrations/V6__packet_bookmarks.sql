@@ -0,0 +1,11 @@
+-- Bookmarks on individual packets (lazypacket's Storage::set_packet_bookmark), identified like packet notes
+-- by their packet_number within the session; label is optional, color one of lazypacket's bookmark colors
+
+CREATE TABLE IF NOT EXISTS packet_bookmarks (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_number BIGINT NOT NULL,
+    label TEXT,
+    color VARCHAR(16) NOT NULL DEFAULT 'yellow',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (session_id, packet_number)
+);
@@ -0,0 +1,9 @@
+-- Fields lazypacket's compare mode leaves out of diffs (Storage::add_ignored_field), per packet type
+-- path is dotted keys from the packet root, e.g. 'params.tick'; it applies to every element of arrays on the way
+
+CREATE TABLE IF NOT EXISTS compare_ignored_fields (
+    packet_name VARCHAR(255) NOT NULL,
+    path TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (packet_name, path)
+);
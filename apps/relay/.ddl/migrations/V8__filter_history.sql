@@ -0,0 +1,8 @@
+-- Filters applied in lazypacket's filter panel (Storage::add_filter_history), recalled with Up/Down
+-- Reapplying a filter moves it to the end (a new id); only the most recent storage::FILTER_HISTORY_LIMIT are kept
+
+CREATE TABLE IF NOT EXISTS filter_history (
+    id BIGSERIAL PRIMARY KEY,
+    filter TEXT NOT NULL UNIQUE,
+    used_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+-- Sessions archived from lazypacket's session list (Storage::set_session_archived): hidden from the list unless a
+-- search asks for them (archived:only / archived:include), and restored by removing their row
+
+CREATE TABLE IF NOT EXISTS archived_sessions (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    archived_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
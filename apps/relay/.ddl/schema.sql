@@ -1,3 +1,6 @@
+-- Current schema, used by docker-compose to initialize a fresh database
+-- Schema changes go in migrations/ as a new V<n>__<name>.sql (lazypacket applies them); keep this file in sync
+
 -- Sessions table
 CREATE TABLE IF NOT EXISTS sessions (
     id SERIAL PRIMARY KEY,
@@ -45,3 +48,112 @@ CREATE INDEX IF NOT EXISTS idx_sessions_started_at ON sessions(started_at);
 CREATE INDEX IF NOT EXISTS idx_tag_maps_packet_id ON tag_maps(packet_id);
 CREATE INDEX IF NOT EXISTS idx_tag_maps_session_id ON tag_maps(session_id);
 CREATE INDEX IF NOT EXISTS idx_tag_maps_tag ON tag_maps(tag);
+
+-- Packet content search (see migrations/V2__packet_search_indexes.sql)
+CREATE INDEX IF NOT EXISTS idx_packets_packet_gin ON packets USING GIN (packet jsonb_path_ops);
+CREATE INDEX IF NOT EXISTS idx_packets_chat_message_fts ON packets
+    USING GIN (to_tsvector('simple', packet->'params'->>'message'))
+    WHERE packet->>'name' = 'text';
+
+-- Analysis notes (see migrations/V3__notes.sql)
+CREATE TABLE IF NOT EXISTS session_notes (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    note TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS packet_notes (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_number BIGINT NOT NULL,
+    note TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (session_id, packet_number)
+);
+
+-- Packet bookmarks (see migrations/V6__packet_bookmarks.sql)
+CREATE TABLE IF NOT EXISTS packet_bookmarks (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_number BIGINT NOT NULL,
+    label TEXT,
+    color VARCHAR(16) NOT NULL DEFAULT 'yellow',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (session_id, packet_number)
+);
+
+-- Saved filter presets (see migrations/V4__filter_presets.sql)
+CREATE TABLE IF NOT EXISTS filters (
+    name VARCHAR(255) PRIMARY KEY,
+    filter TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Fields compare mode ignores, per packet type (see migrations/V7__compare_ignored_fields.sql)
+CREATE TABLE IF NOT EXISTS compare_ignored_fields (
+    packet_name VARCHAR(255) NOT NULL,
+    path TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (packet_name, path)
+);
+
+-- Recently applied filters, oldest first by id (see migrations/V8__filter_history.sql)
+CREATE TABLE IF NOT EXISTS filter_history (
+    id BIGSERIAL PRIMARY KEY,
+    filter TEXT NOT NULL UNIQUE,
+    used_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Sessions hidden from the session list until restored (see migrations/V9__archived_sessions.sql)
+CREATE TABLE IF NOT EXISTS archived_sessions (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    archived_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Session names (see migrations/V10__session_names.sql)
+CREATE TABLE IF NOT EXISTS session_names (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Session list summaries, maintained by triggers on packets (see migrations/V5__session_summaries.sql)
+CREATE TABLE IF NOT EXISTS session_summaries (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_count BIGINT NOT NULL DEFAULT 0,
+    first_ts TIMESTAMP,
+    last_ts TIMESTAMP
+);
+
+CREATE OR REPLACE FUNCTION session_summaries_packets_inserted() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO session_summaries (session_id, packet_count, first_ts, last_ts)
+    SELECT session_id, COUNT(*), MIN(ts), MAX(ts) FROM inserted_packets GROUP BY session_id
+    ON CONFLICT (session_id) DO UPDATE SET
+        packet_count = session_summaries.packet_count + EXCLUDED.packet_count,
+        first_ts = LEAST(session_summaries.first_ts, EXCLUDED.first_ts),
+        last_ts = GREATEST(session_summaries.last_ts, EXCLUDED.last_ts);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION session_summaries_packets_deleted() RETURNS trigger AS $$
+BEGIN
+    UPDATE session_summaries s
+    SET packet_count = GREATEST(s.packet_count - d.packet_count, 0)
+    FROM (SELECT session_id, COUNT(*) AS packet_count FROM deleted_packets GROUP BY session_id) d
+    WHERE s.session_id = d.session_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS packets_summary_insert ON packets;
+CREATE TRIGGER packets_summary_insert
+    AFTER INSERT ON packets
+    REFERENCING NEW TABLE AS inserted_packets
+    FOR EACH STATEMENT EXECUTE FUNCTION session_summaries_packets_inserted();
+
+DROP TRIGGER IF EXISTS packets_summary_delete ON packets;
+CREATE TRIGGER packets_summary_delete
+    AFTER DELETE ON packets
+    REFERENCING OLD TABLE AS deleted_packets
+    FOR EACH STATEMENT EXECUTE FUNCTION session_summaries_packets_deleted();
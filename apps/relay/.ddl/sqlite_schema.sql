@@ -0,0 +1,129 @@
+-- SQLite schema (DB_BACKEND=sqlite): the tables of schema.sql in a single local file
+-- Applied automatically by the relay and lazypacket whenever they open the database
+-- Timestamps are UTC text ('YYYY-MM-DD HH:MM:SS.SSS'); packet holds the packet JSON as text
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    ended_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS packets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    ts TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    session_time_ms INTEGER NOT NULL,
+    packet_number INTEGER NOT NULL,
+    server_version TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    packet TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tags (
+    tag TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS tag_maps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tag TEXT NOT NULL REFERENCES tags(tag) ON DELETE CASCADE,
+    packet_id INTEGER REFERENCES packets(id) ON DELETE CASCADE,
+    session_id INTEGER REFERENCES sessions(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    CHECK (
+        (packet_id IS NOT NULL AND session_id IS NULL) OR
+        (packet_id IS NULL AND session_id IS NOT NULL)
+    )
+);
+
+CREATE TABLE IF NOT EXISTS session_notes (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    note TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS packet_notes (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_number INTEGER NOT NULL,
+    note TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    PRIMARY KEY (session_id, packet_number)
+);
+
+CREATE TABLE IF NOT EXISTS packet_bookmarks (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_number INTEGER NOT NULL,
+    label TEXT,
+    color TEXT NOT NULL DEFAULT 'yellow',
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    PRIMARY KEY (session_id, packet_number)
+);
+
+CREATE TABLE IF NOT EXISTS filters (
+    name TEXT PRIMARY KEY,
+    filter TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS compare_ignored_fields (
+    packet_name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    PRIMARY KEY (packet_name, path)
+);
+
+CREATE TABLE IF NOT EXISTS filter_history (
+    id INTEGER PRIMARY KEY,
+    filter TEXT NOT NULL UNIQUE,
+    used_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS archived_sessions (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    archived_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS session_names (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
+
+-- Session list summaries (see migrations/V5__session_summaries.sql), maintained by the triggers below
+CREATE TABLE IF NOT EXISTS session_summaries (
+    session_id INTEGER PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    packet_count INTEGER NOT NULL DEFAULT 0,
+    first_ts TEXT,
+    last_ts TEXT
+);
+
+CREATE TRIGGER IF NOT EXISTS packets_summary_insert AFTER INSERT ON packets
+BEGIN
+    INSERT INTO session_summaries (session_id, packet_count, first_ts, last_ts)
+    VALUES (NEW.session_id, 1, NEW.ts, NEW.ts)
+    ON CONFLICT (session_id) DO UPDATE SET
+        packet_count = packet_count + 1,
+        first_ts = MIN(COALESCE(first_ts, excluded.first_ts), excluded.first_ts),
+        last_ts = MAX(COALESCE(last_ts, excluded.last_ts), excluded.last_ts);
+END;
+
+CREATE TRIGGER IF NOT EXISTS packets_summary_delete AFTER DELETE ON packets
+BEGIN
+    UPDATE session_summaries SET packet_count = MAX(packet_count - 1, 0) WHERE session_id = OLD.session_id;
+END;
+
+CREATE INDEX IF NOT EXISTS idx_packets_session_id ON packets(session_id);
+CREATE INDEX IF NOT EXISTS idx_packets_ts ON packets(ts);
+CREATE INDEX IF NOT EXISTS idx_packets_direction ON packets(direction);
+CREATE INDEX IF NOT EXISTS idx_sessions_started_at ON sessions(started_at);
+CREATE INDEX IF NOT EXISTS idx_tag_maps_packet_id ON tag_maps(packet_id);
+CREATE INDEX IF NOT EXISTS idx_tag_maps_session_id ON tag_maps(session_id);
+CREATE INDEX IF NOT EXISTS idx_tag_maps_tag ON tag_maps(tag);
+
+-- Existing sessions, as in V5: databases from before the summaries have packets but no summary rows
+-- Runs on every open, so only sessions without a row are counted (through idx_packets_session_id)
+INSERT INTO session_summaries (session_id, packet_count, first_ts, last_ts)
+SELECT session_id, COUNT(*), MIN(ts), MAX(ts) FROM packets
+WHERE session_id IN (SELECT id FROM sessions WHERE id NOT IN (SELECT session_id FROM session_summaries))
+GROUP BY session_id
+ON CONFLICT (session_id) DO NOTHING;
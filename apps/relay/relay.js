@@ -12,14 +12,92 @@ dotenv.config({ path: rootEnvPath });
 
 import bedrockProtocol from 'bedrock-protocol';
 const { Relay } = bedrockProtocol;
-import { initPool, setupGracefulShutdown, registerShutdownHandler, createSession, endSession, writePacket, getConnectionString } from './src/lib/db/index.js';
+import { initPool, initSqlitePool, setupGracefulShutdown, registerShutdownHandler, createSession, endSession, writePacket, addSessionTag, getConnectionString, getDatabaseBackend, getSqlitePath, getSslOptions, startHealthMonitor, healthIntervalFromEnv } from './src/lib/db/index.js';
+import { ByteAccounting, packetSize, formatBytes, renderPrometheus, renderDatabaseHealth, startMetricsServer } from './src/lib/metrics/index.js';
+import { loadTagRules, SessionTagger } from './src/lib/tagging/index.js';
+import { PacketPreviewLogger, previewOptionsFromEnv } from './src/lib/debug/index.js';
+import { PacketRingBuffer, captureOptionsFromEnv, truncatePayload } from './src/lib/capture/index.js';
+import { PacketRedactor, redactionOptionsFromEnv } from './src/lib/redaction/index.js';
+import { retentionOptionsFromEnv, startRetention } from './src/lib/retention/index.js';
+import { createInterface } from 'readline';
 
-// Initialize database connection
-initPool(getConnectionString());
+// Initialize database connection: PostgreSQL, or a local SQLite file (path relative to the project root)
+if (getDatabaseBackend() === 'sqlite') {
+  const sqlitePath = resolve(__dirname, '../..', getSqlitePath());
+  initSqlitePool(sqlitePath);
+  console.log(`Writing sessions to SQLite database ${sqlitePath}`);
+} else {
+  initPool(getConnectionString(), { ssl: getSslOptions() });
+}
+
+// Capture mode: 'full' writes every packet, 'ring' keeps each session's last packets until a dump is triggered
+const captureOptions = captureOptionsFromEnv();
+const ringCapture = captureOptions.mode === 'ring';
+
+// Sensitive-data redaction before packets are persisted (opt-in via REDACT)
+const packetRedactor = new PacketRedactor(redactionOptionsFromEnv());
+if (packetRedactor.enabled) {
+  console.log(`Redacting ${packetRedactor.categories.map(c => c.name).join(', ')} (${packetRedactor.mode}) in captured packets`);
+}
+
+// Bytes per packet name and direction across all sessions, and database size and row counts (exported via /metrics)
+const globalAccounting = new ByteAccounting();
+const healthMonitor = process.env.METRICS_PORT ? startHealthMonitor(healthIntervalFromEnv()) : null;
+const metricsServer = process.env.METRICS_PORT
+  ? startMetricsServer(
+      Number(process.env.METRICS_PORT),
+      () => renderPrometheus(globalAccounting) + renderDatabaseHealth(healthMonitor.current()),
+      ringCapture ? { '/dump': () => dumpAllRingBuffers('API request') } : {}
+    )
+  : null;
+
+// Automatic session tagging rules (optional), path relative to the project root
+const tagRules = process.env.TAG_RULES_FILE
+  ? loadTagRules(resolve(__dirname, '../..', process.env.TAG_RULES_FILE))
+  : [];
+if (tagRules.length > 0) {
+  console.log(`Loaded ${tagRules.length} tag rule(s) from ${process.env.TAG_RULES_FILE}`);
+}
+// Sampled per-packet debug lines (opt-in via PACKET_DEBUG_SAMPLE_RATE)
+const packetPreview = new PacketPreviewLogger(previewOptionsFromEnv());
+if (packetPreview.enabled) {
+  console.log(`Packet debug previews enabled (sample rate ${packetPreview.sampleRate})`);
+}
+
+const upstreamAddress = `${process.env.PROXY_DESTINATION_ADDRESS}:${process.env.PROXY_DESTINATION_PORT}`;
 
 // Track active player sessions for graceful shutdown
 const activePlayers = new Map(); // Map<sessionId, { player, sessionId }>
 
+// Retention (optional): prune old sessions, or the oldest ones beyond a total size, in the background
+const retentionOptions = retentionOptionsFromEnv();
+if (retentionOptions.enabled) {
+  const limits = [
+    retentionOptions.maxAgeDays !== null && `older than ${retentionOptions.maxAgeDays} days`,
+    retentionOptions.maxTotalBytes !== null && `beyond ${formatBytes(retentionOptions.maxTotalBytes)} in total`
+  ].filter(Boolean);
+  console.log(`Retention enabled: deleting ${retentionOptions.untaggedOnly ? 'untagged ' : ''}sessions ${limits.join(' or ')}`);
+  const retention = startRetention(retentionOptions, () => activePlayers.keys(), ({ deleted, bytes }) => {
+    console.log(`Retention: deleted ${deleted.length} session(s) (${formatBytes(bytes)} of packets): ${deleted.join(', ')}`);
+  });
+  registerShutdownHandler(async () => retention.stop());
+}
+
+if (captureOptions.truncatePayloadBytes !== null) {
+  console.log(`Keeping the first ${formatBytes(captureOptions.truncatePayloadBytes)} of each captured payload`);
+}
+
+if (ringCapture) {
+  console.log(`Ring capture enabled: keeping the last ${captureOptions.ringSize} packets per session`);
+  // Hotkey: Enter on the relay's terminal dumps every active session
+  if (process.stdin.isTTY) {
+    createInterface({ input: process.stdin }).on('line', () => dumpAllRingBuffers('hotkey'));
+    console.log('Press Enter to dump ring buffers' + (metricsServer ? `, or POST http://localhost:${process.env.METRICS_PORT}/dump` : ''));
+  } else if (metricsServer) {
+    console.log(`POST http://localhost:${process.env.METRICS_PORT}/dump to dump ring buffers`);
+  }
+}
+
 // Start your server first on port 19131.
 
 // Start the proxy server
@@ -41,6 +119,11 @@ relay.listen() // Tell the server to start listening.
 registerShutdownHandler(async () => {
   console.log('Shutting down relay server...');
   
+  if (metricsServer) {
+    metricsServer.close();
+    healthMonitor.stop();
+  }
+  
   // Stop accepting new connections by removing the listener
   // This prevents new clients from connecting during shutdown
   relay.removeAllListeners('connect');
@@ -81,6 +164,88 @@ registerShutdownHandler(async () => {
 // Setup graceful shutdown (this will call our registered handler)
 setupGracefulShutdown();
 
+/**
+ * Log per-direction byte totals and the packet types using the most bandwidth
+ * @param {number} sessionId - The session ID
+ * @param {ByteAccounting} accounting - The session's byte accounting
+ */
+function logBandwidth(sessionId, accounting) {
+  const totals = accounting.totalsByDirection();
+  const directionSummary = Object.entries(totals)
+    .map(([direction, { packets, bytes }]) => `${direction} ${formatBytes(bytes)} (${packets} packets)`)
+    .join(', ');
+  console.log(`Session ${sessionId} bandwidth: ${directionSummary || 'none'}`);
+
+  for (const { direction, name, packets, bytes } of accounting.topByBytes(5)) {
+    console.log(`  ${formatBytes(bytes).padStart(10)}  ${direction.padEnd(11)}  ${name} (${packets} packets)`);
+  }
+}
+
+/**
+ * Persist a captured packet, or hold it in the session's ring buffer in ring capture mode
+ * Sensitive data is redacted and payloads truncated (CAPTURE_TRUNCATE_PAYLOAD_BYTES) first, so neither is buffered
+ * @param {object} player - The relay player (session state lives on it)
+ * @param {object} packetData - Arguments for writePacket
+ * @param {number} size - The packet's size on the wire, recorded when its payload is truncated
+ */
+function capturePacket(player, packetData, size) {
+  let packet = packetRedactor.redact(packetData.packet);
+  if (captureOptions.truncatePayloadBytes !== null) {
+    packet = truncatePayload(packet, captureOptions.truncatePayloadBytes, size);
+  }
+  packetData = { ...packetData, packet };
+  if (player.ringBuffer) {
+    player.ringBuffer.push(packetData);
+  } else {
+    writePacket(packetData);
+  }
+}
+
+/**
+ * Write a session's buffered packets to the database and empty its ring buffer
+ * @param {object} player - The relay player
+ * @param {string} reason - What triggered the dump (logged)
+ * @returns {number} Number of packets written
+ */
+function dumpRingBuffer(player, reason) {
+  if (!player.ringBuffer) {
+    return 0;
+  }
+  const dropped = player.ringBuffer.dropped;
+  const packets = player.ringBuffer.drain();
+  for (const packetData of packets) {
+    writePacket(packetData);
+  }
+  const droppedNote = dropped > 0 ? ` (${dropped} older packets were not kept)` : '';
+  console.log(`Session ${player.sessionId}: dumped ${packets.length} buffered packets on ${reason}${droppedNote}`);
+  return packets.length;
+}
+
+/**
+ * Dump the ring buffers of all active sessions
+ * @param {string} reason - What triggered the dump (logged)
+ * @returns {string} Summary of what was written
+ */
+function dumpAllRingBuffers(reason) {
+  let total = 0;
+  for (const { player } of activePlayers.values()) {
+    total += dumpRingBuffer(player, reason);
+  }
+  return `Dumped ${total} packets from ${activePlayers.size} session(s)`;
+}
+
+/**
+ * Save tags matched by a session's tag rules (fire-and-forget)
+ * @param {number} sessionId - The session ID
+ * @param {string[]} tags - Newly matched tags
+ */
+function applyTags(sessionId, tags) {
+  for (const tag of tags) {
+    console.log(`Session ${sessionId}: auto-tagged "${tag}"`);
+    addSessionTag(sessionId, tag).catch(() => {}); // Errors are logged by addSessionTag
+  }
+}
+
 relay.on('connect', async (player) => {
   console.log('New connection', player.connection.address)
 
@@ -93,10 +258,25 @@ relay.on('connect', async (player) => {
   player.sessionId = sessionId;
   player.sessionStartTime = sessionStartTime;
   player.packetNumber = packetNumber;
+  player.byteAccounting = new ByteAccounting();
+  player.tagger = new SessionTagger(tagRules);
+  player.ringBuffer = ringCapture ? new PacketRingBuffer(captureOptions.ringSize) : null;
+  applyTags(sessionId, player.tagger.update({ upstream: upstreamAddress, client: player.connection.address }));
+
+  // Player name is known once the client has logged in
+  player.on('login', () => {
+    applyTags(sessionId, player.tagger.update({ player: player.profile?.name }));
+  });
 
   // Track this active session
   activePlayers.set(sessionId, { player, sessionId });
 
+  // Keep the packets leading up to a connection error
+  player.on('error', (error) => {
+    console.error(`Session ${sessionId}: connection error:`, error);
+    dumpRingBuffer(player, 'error');
+  });
+
   // Set up periodic logging (every minute)
   const statsInterval = setInterval(() => {
     const sessionTimeMs = Date.now() - player.sessionStartTime;
@@ -105,27 +285,36 @@ relay.on('connect', async (player) => {
     const remainingSeconds = sessionTimeSeconds % 60;
     
     console.log(`Session ${sessionId}: ${Number(player.packetNumber)} packets, ${sessionTimeMinutes}m ${remainingSeconds}s`)
+    logBandwidth(sessionId, player.byteAccounting);
   }, 60000); // Every minute
 
   // Store interval so we can clear it on disconnect
   player.statsInterval = statsInterval;
 
   // Server is sending a message to the client.
-  player.on('clientbound', (packet) => {
+  player.on('clientbound', (packet, des) => {
     const { name, params } = packet;
     
+    // Byte accounting per packet name and direction
+    const bytes = packetSize(des);
+    player.byteAccounting.record('clientbound', name, bytes);
+    globalAccounting.record('clientbound', name, bytes);
+    packetPreview.record(player.sessionId, 'clientbound', name, des);
+    
     // Increment packet number for this session
     player.packetNumber++;
     
-    // Save packet to database (fire-and-forget)
-    writePacket({
+    // Save packet to database (fire-and-forget), or buffer it in ring capture mode
+    capturePacket(player, {
       sessionId: player.sessionId,
       sessionTimeMs: Date.now() - player.sessionStartTime,
       packetNumber: player.packetNumber,
       serverVersion: relay.options.version || process.env.BEDROCK_VERSION || 'unknown',
       direction: 'clientbound',
-      packet: { name, params }
-    });
+      packet: { name, params },
+      ts: new Date()
+    }, bytes);
+    applyTags(player.sessionId, player.tagger.packet('clientbound', packet));
 
     if (name === 'disconnect') { // Intercept kick
       params.message = 'Intercepted' // Change kick message to "Intercepted"
@@ -133,21 +322,29 @@ relay.on('connect', async (player) => {
   })
   
   // Client is sending a message to the server
-  player.on('serverbound', (packet) => {
+  player.on('serverbound', (packet, des) => {
     const { name, params } = packet;
     
+    // Byte accounting per packet name and direction
+    const bytes = packetSize(des);
+    player.byteAccounting.record('serverbound', name, bytes);
+    globalAccounting.record('serverbound', name, bytes);
+    packetPreview.record(player.sessionId, 'serverbound', name, des);
+    
     // Increment packet number for this session
     player.packetNumber++;
     
-    // Save packet to database (fire-and-forget)
-    writePacket({
+    // Save packet to database (fire-and-forget), or buffer it in ring capture mode
+    capturePacket(player, {
       sessionId: player.sessionId,
       sessionTimeMs: Date.now() - player.sessionStartTime,
       packetNumber: player.packetNumber,
       serverVersion: relay.options.version || process.env.BEDROCK_VERSION || 'unknown',
       direction: 'serverbound',
-      packet: { name, params }
-    });
+      packet: { name, params },
+      ts: new Date()
+    }, bytes);
+    applyTags(player.sessionId, player.tagger.packet('serverbound', packet));
 
     if (name === 'text') { // Intercept chat message to server and append time.
       params.message += `, on ${new Date().toLocaleString()}`
@@ -171,6 +368,10 @@ relay.on('connect', async (player) => {
     const finalRemainingSeconds = finalSessionTimeSeconds % 60;
     
     console.log(`Connection closed ${player.connection.address} - Session ${sessionId}: ${Number(player.packetNumber)} packets total, ${finalSessionTimeMinutes}m ${finalRemainingSeconds}s`)
+    logBandwidth(sessionId, player.byteAccounting);
+    if (player.ringBuffer && player.ringBuffer.size > 0) {
+      console.log(`Session ${sessionId}: discarding ${player.ringBuffer.size} buffered packets (no dump was triggered)`);
+    }
 
     try {
       await endSession(player.sessionId);
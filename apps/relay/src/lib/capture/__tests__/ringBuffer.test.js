@@ -0,0 +1,39 @@
+import { describe, it, expect } from 'vitest';
+import { PacketRingBuffer, captureOptionsFromEnv } from '../index.js';
+
+describe('Packet Ring Buffer', () => {
+  it('should keep the most recent items in order', () => {
+    const buffer = new PacketRingBuffer(3);
+    for (let i = 1; i <= 5; i++) {
+      buffer.push(i);
+    }
+
+    expect(buffer.size).toBe(3);
+    expect(buffer.dropped).toBe(2);
+    expect(buffer.drain()).toEqual([3, 4, 5]);
+  });
+
+  it('should be empty after draining', () => {
+    const buffer = new PacketRingBuffer(2);
+    buffer.push('a');
+    expect(buffer.drain()).toEqual(['a']);
+
+    expect(buffer.size).toBe(0);
+    expect(buffer.drain()).toEqual([]);
+    buffer.push('b');
+    expect(buffer.drain()).toEqual(['b']);
+  });
+
+  it('should reject invalid capacities', () => {
+    expect(() => new PacketRingBuffer(0)).toThrow();
+    expect(() => new PacketRingBuffer(1.5)).toThrow();
+  });
+
+  it('should read capture options from the environment', () => {
+    expect(captureOptionsFromEnv({})).toEqual({ mode: 'full', ringSize: 10000, truncatePayloadBytes: null });
+    expect(captureOptionsFromEnv({ CAPTURE_MODE: 'Ring', CAPTURE_RING_SIZE: '500' })).toEqual({ mode: 'ring', ringSize: 500, truncatePayloadBytes: null });
+    expect(captureOptionsFromEnv({ CAPTURE_TRUNCATE_PAYLOAD_BYTES: '4096' }).truncatePayloadBytes).toBe(4096);
+    expect(() => captureOptionsFromEnv({ CAPTURE_MODE: 'sometimes' })).toThrow();
+    expect(() => captureOptionsFromEnv({ CAPTURE_TRUNCATE_PAYLOAD_BYTES: '4k' })).toThrow();
+  });
+});
@@ -0,0 +1,38 @@
+import { describe, it, expect } from 'vitest';
+import { truncatePayload } from '../index.js';
+
+describe('Payload Truncation', () => {
+  it('should cut binary fields and record the original size', () => {
+    const payload = Buffer.alloc(1000, 7);
+    const packet = {
+      name: 'level_chunk',
+      params: { x: 1, z: 2, payload, blobs: { hashes: [5n], blobs: [new Uint8Array(10)] } }
+    };
+
+    const truncated = truncatePayload(packet, 4, 1012);
+    expect(truncated.original_size).toBe(1012);
+    expect([...truncated.params.payload]).toEqual([7, 7, 7, 7]);
+    expect(truncated.params.blobs.blobs[0].length).toBe(4);
+    expect(truncated.params.blobs.hashes).toEqual([5n]);
+    expect(truncated.params.x).toBe(1);
+
+    // The relay forwards the original, and the stored copy doesn't keep the whole chunk in memory
+    expect(packet.params.payload.length).toBe(1000);
+    expect(packet.original_size).toBeUndefined();
+    expect(truncated.params.payload.buffer).not.toBe(payload.buffer);
+  });
+
+  it('should store the truncated payload in the packet JSON', () => {
+    const packet = { name: 'level_chunk', params: { payload: Buffer.from([1, 2, 3, 4, 5, 6]) } };
+
+    const stored = JSON.parse(JSON.stringify(truncatePayload(packet, 2, 9)));
+    expect(stored).toEqual({ name: 'level_chunk', params: { payload: { type: 'Buffer', data: [1, 2] } }, original_size: 9 });
+  });
+
+  it('should leave packets without long binary fields as they are', () => {
+    const packet = { name: 'text', params: { message: 'a'.repeat(100), data: Buffer.from([1, 2]) } };
+
+    expect(truncatePayload(packet, 2, 120)).toBe(packet);
+    expect(truncatePayload(null, 2, 0)).toBe(null);
+  });
+});
@@ -0,0 +1,2 @@
+export { PacketRingBuffer, captureOptionsFromEnv } from './ringBuffer.js';
+export { truncatePayload } from './truncate.js';
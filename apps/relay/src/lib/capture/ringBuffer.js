@@ -0,0 +1,82 @@
+/**
+ * Fixed-size buffer keeping the most recent items, oldest overwritten first
+ * Used in ring capture mode to hold a session's last N packets until a dump is triggered
+ */
+export class PacketRingBuffer {
+  /**
+   * @param {number} capacity - Maximum number of items kept
+   */
+  constructor(capacity) {
+    if (!Number.isInteger(capacity) || capacity < 1) {
+      throw new Error(`Ring buffer capacity must be a positive integer, got ${capacity}`);
+    }
+    this.capacity = capacity;
+    this.items = new Array(capacity);
+    this.start = 0;
+    this.length = 0;
+    this.dropped = 0; // Items overwritten since the last drain
+  }
+
+  get size() {
+    return this.length;
+  }
+
+  /**
+   * Add an item, overwriting the oldest one when full
+   * @param {any} item
+   */
+  push(item) {
+    if (this.length < this.capacity) {
+      this.items[(this.start + this.length) % this.capacity] = item;
+      this.length++;
+      return;
+    }
+    this.items[this.start] = item;
+    this.start = (this.start + 1) % this.capacity;
+    this.dropped++;
+  }
+
+  /**
+   * Remove and return all buffered items, oldest first
+   * @returns {Array<any>}
+   */
+  drain() {
+    const drained = [];
+    for (let i = 0; i < this.length; i++) {
+      const index = (this.start + i) % this.capacity;
+      drained.push(this.items[index]);
+      this.items[index] = undefined;
+    }
+    this.start = 0;
+    this.length = 0;
+    this.dropped = 0;
+    return drained;
+  }
+}
+
+/**
+ * Capture options from environment variables
+ * CAPTURE_MODE - 'full' (default) writes every packet; 'ring' keeps the last packets in memory until dumped
+ * CAPTURE_RING_SIZE - packets kept per session in ring mode (default 10000)
+ * CAPTURE_TRUNCATE_PAYLOAD_BYTES - bytes kept of each binary field of a packet (unset keeps everything)
+ * @param {object} env - Environment (defaults to process.env)
+ * @returns {{mode: 'full'|'ring', ringSize: number, truncatePayloadBytes: number|null}}
+ */
+export function captureOptionsFromEnv(env = process.env) {
+  const mode = (env.CAPTURE_MODE || 'full').toLowerCase();
+  if (mode !== 'full' && mode !== 'ring') {
+    throw new Error(`Invalid CAPTURE_MODE "${env.CAPTURE_MODE}" (expected full or ring)`);
+  }
+  let truncatePayloadBytes = null;
+  if (env.CAPTURE_TRUNCATE_PAYLOAD_BYTES) {
+    truncatePayloadBytes = Number(env.CAPTURE_TRUNCATE_PAYLOAD_BYTES);
+    if (!Number.isInteger(truncatePayloadBytes) || truncatePayloadBytes < 0) {
+      throw new Error(`Invalid CAPTURE_TRUNCATE_PAYLOAD_BYTES "${env.CAPTURE_TRUNCATE_PAYLOAD_BYTES}" (expected a number of bytes)`);
+    }
+  }
+  return {
+    mode,
+    ringSize: Number(env.CAPTURE_RING_SIZE) || 10000,
+    truncatePayloadBytes
+  };
+}
@@ -0,0 +1,44 @@
+/**
+ * Payload truncation for captured packets (CAPTURE_TRUNCATE_PAYLOAD_BYTES), so chunk-heavy sessions stay small
+ * Packets are stored decoded, so the payload kept is that of their binary fields (chunk and sub-chunk data, NBT
+ * blobs): each is cut to the limit, and the packet's full size on the wire is recorded as original_size, the key
+ * lazypacket's database sink uses for the same setting
+ */
+
+/**
+ * Copy of a packet with every binary field longer than `limit` bytes cut to `limit` (the packet itself if none is)
+ * The copies don't share memory with the originals, so a ring buffer holding them doesn't keep whole chunks alive
+ * @param {object} packet - Packet ({ name, params })
+ * @param {number} limit - Bytes kept per binary field
+ * @param {number} originalSize - The packet's size on the wire, recorded when anything was cut
+ * @returns {object}
+ */
+export function truncatePayload(packet, limit, originalSize) {
+  let truncated = false;
+  const walk = (value) => {
+    if (value instanceof Uint8Array) {
+      if (value.length <= limit) {
+        return value;
+      }
+      truncated = true;
+      return Buffer.isBuffer(value) ? Buffer.from(value.subarray(0, limit)) : value.slice(0, limit);
+    }
+    if (Array.isArray(value)) {
+      return value.map(walk);
+    }
+    if (value === null || typeof value !== 'object' || value.constructor !== Object) {
+      return value;
+    }
+    const result = {};
+    for (const [key, child] of Object.entries(value)) {
+      result[key] = walk(child);
+    }
+    return result;
+  };
+
+  if (!packet) {
+    return packet;
+  }
+  const params = walk(packet.params);
+  return truncated ? { ...packet, params, original_size: originalSize } : packet;
+}
@@ -0,0 +1,67 @@
+import { describe, it, expect, vi, afterEach } from 'vitest';
+import { PacketBatchWriter, packetBatchOptionsFromEnv, buildPacketInsert } from '../batch.js';
+
+const row = (packetNumber) => [1, new Date(0), packetNumber * 10, packetNumber, '1.21.0', 'clientbound', { n: packetNumber }];
+
+describe('buildPacketInsert', () => {
+  it('should number placeholders across rows and cast the packet to jsonb', () => {
+    const { sql, params } = buildPacketInsert([row(1), row(2)]);
+    expect(sql).toBe(
+      'INSERT INTO packets (session_id, ts, session_time_ms, packet_number, server_version, direction, packet) ' +
+      'VALUES ($1, $2, $3, $4, $5, $6, $7::jsonb), ($8, $9, $10, $11, $12, $13, $14::jsonb)'
+    );
+    expect(params).toHaveLength(14);
+    expect(params[10]).toBe(2);
+  });
+});
+
+describe('PacketBatchWriter', () => {
+  afterEach(() => {
+    vi.useRealTimers();
+  });
+
+  it('should write a batch once it is full', async () => {
+    const query = vi.fn().mockResolvedValue({ rows: [] });
+    const writer = new PacketBatchWriter({ batchSize: 2, flushIntervalMs: 1000 }, query);
+    writer.add(row(1));
+    expect(query).not.toHaveBeenCalled();
+    writer.add(row(2));
+    writer.add(row(3));
+    expect(query).toHaveBeenCalledTimes(1);
+    expect(query.mock.calls[0][1]).toHaveLength(14);
+
+    await writer.drain();
+    expect(query).toHaveBeenCalledTimes(2);
+    expect(query.mock.calls[1][1][3]).toBe(3);
+  });
+
+  it('should write a partial batch after the flush interval', () => {
+    vi.useFakeTimers();
+    const query = vi.fn().mockResolvedValue({ rows: [] });
+    const writer = new PacketBatchWriter({ batchSize: 100, flushIntervalMs: 50 }, query);
+    writer.add(row(1));
+    vi.advanceTimersByTime(49);
+    expect(query).not.toHaveBeenCalled();
+    vi.advanceTimersByTime(1);
+    expect(query).toHaveBeenCalledTimes(1);
+  });
+
+  it('should log failed writes without rejecting', async () => {
+    const error = vi.spyOn(console, 'error').mockImplementation(() => {});
+    const writer = new PacketBatchWriter({ batchSize: 1, flushIntervalMs: 0 }, () => Promise.reject(new Error('down')));
+    writer.add(row(1));
+    await writer.drain();
+    expect(error).toHaveBeenCalledOnce();
+    error.mockRestore();
+  });
+});
+
+describe('packetBatchOptionsFromEnv', () => {
+  it('should use defaults and validate values', () => {
+    expect(packetBatchOptionsFromEnv({})).toEqual({ batchSize: 500, flushIntervalMs: 50 });
+    expect(packetBatchOptionsFromEnv({ DB_BATCH_SIZE: '1', DB_FLUSH_INTERVAL_MS: '0' }))
+      .toEqual({ batchSize: 1, flushIntervalMs: 0 });
+    expect(() => packetBatchOptionsFromEnv({ DB_BATCH_SIZE: '0' })).toThrow('DB_BATCH_SIZE');
+    expect(() => packetBatchOptionsFromEnv({ DB_FLUSH_INTERVAL_MS: 'soon' })).toThrow('DB_FLUSH_INTERVAL_MS');
+  });
+});
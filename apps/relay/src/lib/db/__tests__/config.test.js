@@ -0,0 +1,25 @@
+import { describe, it, expect } from 'vitest';
+import { writeFileSync, rmSync } from 'fs';
+import { tmpdir } from 'os';
+import { join } from 'path';
+import { getSslOptions } from '../config.js';
+
+describe('getSslOptions', () => {
+  it('should map DB_SSL_MODE to pg ssl options', () => {
+    expect(getSslOptions({})).toBe(false);
+    expect(getSslOptions({ DB_SSL_MODE: 'require' })).toEqual({ rejectUnauthorized: false });
+    expect(getSslOptions({ DB_SSL_MODE: 'verify-full' })).toEqual({ rejectUnauthorized: true });
+    expect(() => getSslOptions({ DB_SSL_MODE: 'prefer' })).toThrow('DB_SSL_MODE');
+    expect(() => getSslOptions({ DB_SSL_MODE: 'require', DB_SSL_CERT: 'client.crt' })).toThrow('DB_SSL_KEY');
+  });
+
+  it('should read the CA bundle for verify-full', () => {
+    const path = join(tmpdir(), `relay-ca-${process.pid}.crt`);
+    writeFileSync(path, 'PEM');
+    try {
+      expect(getSslOptions({ DB_SSL_MODE: 'verify-full', DB_SSL_ROOT_CERT: path }).ca).toBe('PEM');
+    } finally {
+      rmSync(path);
+    }
+  });
+});
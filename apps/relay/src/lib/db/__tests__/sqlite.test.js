@@ -0,0 +1,82 @@
+import { describe, it, expect, beforeAll, afterAll } from 'vitest';
+import { rmSync } from 'fs';
+import { tmpdir } from 'os';
+import { join } from 'path';
+import {
+  initSqlitePool, closePool, getPool, createSession, endSession, getSession, addSessionTag, getSessionTags,
+  toSqliteSql, toSqliteParam, formatTimestamp, parseTimestamp, getDatabaseBackend
+} from '../index.js';
+
+describe('SQLite query translation', () => {
+  it('should rewrite pg placeholders and drop jsonb casts', () => {
+    expect(toSqliteSql('INSERT INTO packets (session_id, packet) VALUES ($1, $12::jsonb)'))
+      .toBe('INSERT INTO packets (session_id, packet) VALUES (?1, ?12)');
+  });
+
+  it('should convert parameters to bindable values', () => {
+    const date = new Date('2024-01-01T12:00:00.123Z');
+    expect(toSqliteParam(date)).toBe('2024-01-01 12:00:00.123');
+    expect(toSqliteParam({ name: 'text' })).toBe('{"name":"text"}');
+    expect(toSqliteParam(undefined)).toBeNull();
+    expect(toSqliteParam(true)).toBe(1);
+    expect(toSqliteParam(42)).toBe(42);
+  });
+
+  it('should round-trip timestamps', () => {
+    const date = new Date('2024-06-30T23:59:59.999Z');
+    expect(parseTimestamp(formatTimestamp(date)).getTime()).toBe(date.getTime());
+    expect(parseTimestamp('2024-06-30 23:59:59').getTime()).toBe(new Date('2024-06-30T23:59:59Z').getTime());
+  });
+
+  it('should validate DB_BACKEND', () => {
+    expect(getDatabaseBackend({})).toBe('postgres');
+    expect(getDatabaseBackend({ DB_BACKEND: 'sqlite' })).toBe('sqlite');
+    expect(() => getDatabaseBackend({ DB_BACKEND: 'mysql' })).toThrow('Invalid DB_BACKEND');
+  });
+});
+
+const hasSqlite = Boolean(process.getBuiltinModule?.('node:sqlite'));
+
+describe.skipIf(!hasSqlite)('SQLite backend', () => {
+  const dir = join(tmpdir(), `bedrockrelay-test-${process.pid}`);
+
+  beforeAll(() => {
+    initSqlitePool(join(dir, 'test.sqlite'));
+  });
+
+  afterAll(async () => {
+    await closePool();
+    rmSync(dir, { recursive: true, force: true });
+  });
+
+  it('should create and end sessions', async () => {
+    const startedAt = new Date('2024-01-01T12:00:00Z');
+    const sessionId = await createSession(startedAt);
+    expect(sessionId).toBeGreaterThan(0);
+
+    const endedAt = new Date('2024-01-01T12:30:00Z');
+    await endSession(sessionId, endedAt);
+    const session = await getSession(sessionId);
+    expect(session.started_at.getTime()).toBe(startedAt.getTime());
+    expect(session.ended_at.getTime()).toBe(endedAt.getTime());
+  });
+
+  it('should store packets as JSON', async () => {
+    const sessionId = await createSession();
+    await getPool().query(
+      `INSERT INTO packets (session_id, ts, session_time_ms, packet_number, server_version, direction, packet)
+       VALUES ($1, $2, $3, $4, $5, $6, $7::jsonb)`,
+      [sessionId, new Date(), 0, 1, '1.21.111', 'clientbound', { name: 'text', params: { message: 'hi' } }]
+    );
+
+    const result = await getPool().query('SELECT packet FROM packets WHERE session_id = $1', [sessionId]);
+    expect(result.rows[0].packet).toEqual({ name: 'text', params: { message: 'hi' } });
+  });
+
+  it('should tag sessions once', async () => {
+    const sessionId = await createSession();
+    await addSessionTag(sessionId, 'death');
+    await addSessionTag(sessionId, 'death');
+    expect(await getSessionTags(sessionId)).toEqual(['death']);
+  });
+});
@@ -0,0 +1,113 @@
+const PACKET_COLUMNS = ['session_id', 'ts', 'session_time_ms', 'packet_number', 'server_version', 'direction', 'packet'];
+
+// Rows per INSERT statement: 7000 parameters, within both PostgreSQL's (65535) and SQLite's (32766) limits
+const MAX_ROWS_PER_INSERT = 1000;
+
+/**
+ * Packet batching options from environment variables (shared with lazypacket's db sink)
+ * DB_BATCH_SIZE - packets written per INSERT; 1 writes every packet on its own (default 500)
+ * DB_FLUSH_INTERVAL_MS - longest a packet waits for its batch to fill (default 50)
+ * @param {object} env - Environment (defaults to process.env)
+ * @returns {{batchSize: number, flushIntervalMs: number}}
+ */
+export function packetBatchOptionsFromEnv(env = process.env) {
+  const integer = (name, fallback, min) => {
+    if (env[name] === undefined || env[name] === '') {
+      return fallback;
+    }
+    const value = Number(env[name]);
+    if (!Number.isInteger(value) || value < min) {
+      throw new Error(`Invalid ${name} "${env[name]}" (expected an integer of at least ${min})`);
+    }
+    return value;
+  };
+
+  return {
+    batchSize: integer('DB_BATCH_SIZE', 500, 1),
+    flushIntervalMs: integer('DB_FLUSH_INTERVAL_MS', 50, 0)
+  };
+}
+
+/**
+ * Build a multi-row INSERT into the packets table
+ * @param {any[][]} rows - Values in PACKET_COLUMNS order (the packet as a JSON-serializable object)
+ * @returns {{sql: string, params: any[]}} Query with $n placeholders
+ */
+export function buildPacketInsert(rows) {
+  const values = rows.map((_, row) => {
+    const placeholders = PACKET_COLUMNS.map((_, column) => `$${row * PACKET_COLUMNS.length + column + 1}`);
+    placeholders[placeholders.length - 1] += '::jsonb';
+    return `(${placeholders.join(', ')})`;
+  });
+  return {
+    sql: `INSERT INTO packets (${PACKET_COLUMNS.join(', ')}) VALUES ${values.join(', ')}`,
+    params: rows.flat()
+  };
+}
+
+/**
+ * Accumulates packet rows and writes them with multi-row INSERTs
+ * A batch is written once it holds batchSize rows, or flushIntervalMs after its first row arrived.
+ * Writes are fire-and-forget: failures are logged and the rows dropped, like single-row writes.
+ */
+export class PacketBatchWriter {
+  /**
+   * @param {{batchSize: number, flushIntervalMs: number}} options
+   * @param {(sql: string, params: any[]) => Promise<any>} query - Runs a query (e.g. pool.query)
+   */
+  constructor({ batchSize, flushIntervalMs }, query) {
+    this.batchSize = batchSize;
+    this.flushIntervalMs = flushIntervalMs;
+    this.query = query;
+    this.pending = [];
+    this.timer = null;
+    this.writes = new Set(); // In-flight writes, awaited by drain()
+  }
+
+  /**
+   * Queue a row, writing the batch if it is full
+   * @param {any[]} row - Values in PACKET_COLUMNS order
+   */
+  add(row) {
+    this.pending.push(row);
+    if (this.pending.length >= this.batchSize) {
+      this.flush();
+    } else if (this.timer === null) {
+      this.timer = setTimeout(() => this.flush(), this.flushIntervalMs);
+    }
+  }
+
+  /**
+   * Write the queued rows now
+   * @returns {Promise<void>} Resolves when they are written (or failed and were logged)
+   */
+  flush() {
+    clearTimeout(this.timer);
+    this.timer = null;
+    const rows = this.pending;
+    this.pending = [];
+
+    const writes = [];
+    for (let start = 0; start < rows.length; start += MAX_ROWS_PER_INSERT) {
+      const chunk = rows.slice(start, start + MAX_ROWS_PER_INSERT);
+      const { sql, params } = buildPacketInsert(chunk);
+      const write = this.query(sql, params)
+        .catch((error) => {
+          console.error(`Error writing ${chunk.length} packet(s) (fire-and-forget):`, error);
+        })
+        .finally(() => this.writes.delete(write));
+      this.writes.add(write);
+      writes.push(write);
+    }
+    return Promise.all(writes).then(() => {});
+  }
+
+  /**
+   * Write the queued rows and wait for every write still in flight (before closing the pool)
+   * @returns {Promise<void>}
+   */
+  async drain() {
+    this.flush();
+    await Promise.all([...this.writes]);
+  }
+}
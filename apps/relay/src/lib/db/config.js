@@ -1,3 +1,5 @@
+import { readFileSync } from 'fs';
+
 /**
  * Build a PostgreSQL connection string from environment variables
  * @returns {string} PostgreSQL connection string
@@ -11,3 +13,55 @@ export function getConnectionString() {
 
   return `postgresql://${user}:${password}@${host}:${port}/${database}`;
 }
+
+/**
+ * Database backend from DB_BACKEND: 'postgres' (default) or 'sqlite'
+ * @param {object} env - Environment variables
+ * @returns {'postgres'|'sqlite'} The backend
+ */
+export function getDatabaseBackend(env = process.env) {
+  const backend = env.DB_BACKEND || 'postgres';
+  if (backend !== 'postgres' && backend !== 'sqlite') {
+    throw new Error(`Invalid DB_BACKEND: ${backend} (expected postgres or sqlite)`);
+  }
+  return backend;
+}
+
+/**
+ * SQLite database path from DB_SQLITE_PATH (relative paths are relative to the project root)
+ * @param {object} env - Environment variables
+ * @returns {string} The configured path
+ */
+export function getSqlitePath(env = process.env) {
+  return env.DB_SQLITE_PATH || 'bedrockrelay.sqlite';
+}
+
+/**
+ * TLS options for pg from the settings lazypacket uses (DB_SSL_MODE, DB_SSL_ROOT_CERT, DB_SSL_CERT, DB_SSL_KEY)
+ * 'require' encrypts without checking the server certificate; 'verify-full' checks it against DB_SSL_ROOT_CERT
+ * (default: Node's CA store) and the host name
+ * @param {object} env - Environment variables
+ * @returns {false|{rejectUnauthorized: boolean, ca?: string, cert?: string, key?: string}} The pg `ssl` option
+ */
+export function getSslOptions(env = process.env) {
+  const mode = env.DB_SSL_MODE || 'disable';
+  if (mode !== 'disable' && mode !== 'require' && mode !== 'verify-full') {
+    throw new Error(`Invalid DB_SSL_MODE: ${mode} (expected disable, require or verify-full)`);
+  }
+  if (mode === 'disable') {
+    return false;
+  }
+  if (Boolean(env.DB_SSL_CERT) !== Boolean(env.DB_SSL_KEY)) {
+    throw new Error('DB_SSL_CERT and DB_SSL_KEY must be set together');
+  }
+
+  const ssl = { rejectUnauthorized: mode === 'verify-full' };
+  if (mode === 'verify-full' && env.DB_SSL_ROOT_CERT) {
+    ssl.ca = readFileSync(env.DB_SSL_ROOT_CERT, 'utf8');
+  }
+  if (env.DB_SSL_CERT) {
+    ssl.cert = readFileSync(env.DB_SSL_CERT, 'utf8');
+    ssl.key = readFileSync(env.DB_SSL_KEY, 'utf8');
+  }
+  return ssl;
+}
@@ -0,0 +1,129 @@
+import { getPool } from './pool.js';
+import { getDatabaseBackend } from './config.js';
+import { parseTimestamp } from './sqlite.js';
+
+// Tables reported on (the same list as `lazypacket health`)
+export const HEALTH_TABLES = ['sessions', 'packets', 'session_summaries', 'tags', 'tag_maps', 'session_notes', 'packet_notes', 'packet_bookmarks', 'filters'];
+
+// Packets are summed from session_summaries rather than counted, which would scan the whole table
+function tableRowsQuery(table) {
+  return table === 'packets'
+    ? 'SELECT COALESCE(SUM(packet_count), 0) AS count FROM session_summaries'
+    : `SELECT COUNT(*) AS count FROM ${table}`;
+}
+
+function toDate(value) {
+  if (value === null || value === undefined) {
+    return null;
+  }
+  return value instanceof Date ? value : parseTimestamp(value);
+}
+
+/**
+ * Table sizes in bytes (including their indexes), or null when the backend can't tell
+ * SQLite needs the dbstat virtual table, which not every build has
+ * @param {object} pool - pg Pool or SqlitePool
+ * @param {'postgres'|'sqlite'} backend - Database backend
+ * @returns {Promise<Map<string, number>|null>} Bytes by table
+ */
+async function tableSizes(pool, backend) {
+  const sizes = new Map();
+  if (backend === 'postgres') {
+    for (const table of HEALTH_TABLES) {
+      const result = await pool.query('SELECT pg_total_relation_size(to_regclass($1)) AS bytes', [table]);
+      sizes.set(table, Number(result.rows[0].bytes ?? 0));
+    }
+    return sizes;
+  }
+
+  try {
+    const result = await pool.query(
+      `SELECT COALESCE(i.tbl_name, d.name) AS name, SUM(d.pgsize) AS bytes
+       FROM dbstat d LEFT JOIN sqlite_master i ON i.type = 'index' AND i.name = d.name
+       GROUP BY 1`
+    );
+    for (const row of result.rows) {
+      sizes.set(row.name, Number(row.bytes));
+    }
+    return sizes;
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * Database size, row counts and table sizes, and the oldest/newest session (as `lazypacket health` shows them)
+ * @returns {Promise<{databaseBytes: number, tables: Array<{name: string, rows: number, bytes: number|null}>, oldestSession: Date|null, newestSession: Date|null}>}
+ */
+export async function getDatabaseHealth() {
+  const pool = getPool();
+  const backend = getDatabaseBackend();
+
+  const sizes = await tableSizes(pool, backend);
+  const tables = [];
+  for (const table of HEALTH_TABLES) {
+    const result = await pool.query(tableRowsQuery(table));
+    tables.push({ name: table, rows: Number(result.rows[0].count), bytes: sizes ? (sizes.get(table) ?? 0) : null });
+  }
+
+  const size = await pool.query(
+    backend === 'postgres'
+      ? 'SELECT pg_database_size(current_database()) AS bytes'
+      : 'SELECT page_count * page_size AS bytes FROM pragma_page_count(), pragma_page_size()'
+  );
+  const range = await pool.query('SELECT MIN(started_at) AS oldest, MAX(started_at) AS newest FROM sessions');
+
+  return {
+    databaseBytes: Number(size.rows[0].bytes),
+    tables,
+    oldestSession: toDate(range.rows[0].oldest),
+    newestSession: toDate(range.rows[0].newest)
+  };
+}
+
+/**
+ * Refresh the database health now and then every intervalMs in the background, keeping the latest snapshot
+ * for synchronous readers such as /metrics
+ * @param {number} intervalMs - Time between refreshes
+ * @returns {{current: () => ({up: boolean, health: object|null}), stop: () => void}} Latest snapshot and a handle to stop the timer
+ */
+export function startHealthMonitor(intervalMs) {
+  let snapshot = { up: false, health: null };
+  let running = false;
+  const run = async () => {
+    if (running) {
+      return; // The previous refresh is still querying
+    }
+    running = true;
+    try {
+      snapshot = { up: true, health: await getDatabaseHealth() };
+    } catch (error) {
+      // Keep the last figures but report the database as down
+      snapshot = { up: false, health: snapshot.health };
+      console.error('Error checking database health:', error.message);
+    } finally {
+      running = false;
+    }
+  };
+
+  run();
+  const timer = setInterval(run, intervalMs);
+  timer.unref();
+  return { current: () => snapshot, stop: () => clearInterval(timer) };
+}
+
+/**
+ * Health refresh interval from DB_HEALTH_INTERVAL_SECONDS (default 60)
+ * @param {object} env - Environment (defaults to process.env)
+ * @returns {number} Interval in milliseconds
+ */
+export function healthIntervalFromEnv(env = process.env) {
+  if (env.DB_HEALTH_INTERVAL_SECONDS === undefined || env.DB_HEALTH_INTERVAL_SECONDS === '') {
+    return 60 * 1000;
+  }
+  const seconds = Number(env.DB_HEALTH_INTERVAL_SECONDS);
+  if (!Number.isFinite(seconds) || seconds <= 0) {
+    throw new Error(`Invalid DB_HEALTH_INTERVAL_SECONDS "${env.DB_HEALTH_INTERVAL_SECONDS}" (expected a positive number)`);
+  }
+  return seconds * 1000;
+}
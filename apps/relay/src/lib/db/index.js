@@ -1,5 +1,9 @@
-export { initPool, getPool, closePool } from './pool.js';
+export { initPool, initSqlitePool, getPool, closePool } from './pool.js';
 export { createSession, endSession, getSession } from './sessions.js';
-export { writePacket, getPacket } from './packets.js';
+export { writePacket, flushPackets, getPacket } from './packets.js';
+export { PacketBatchWriter, packetBatchOptionsFromEnv, buildPacketInsert } from './batch.js';
+export { addSessionTag, getSessionTags } from './tags.js';
 export { setupGracefulShutdown, registerShutdownHandler } from './shutdown.js';
-export { getConnectionString } from './config.js';
+export { getConnectionString, getDatabaseBackend, getSqlitePath, getSslOptions } from './config.js';
+export { SqlitePool, toSqliteSql, toSqliteParam, formatTimestamp, parseTimestamp } from './sqlite.js';
+export { getDatabaseHealth, startHealthMonitor, healthIntervalFromEnv, HEALTH_TABLES } from './health.js';
@@ -1,18 +1,16 @@
 import { getPool } from './pool.js';
+import { PacketBatchWriter, packetBatchOptionsFromEnv } from './batch.js';
+
+// Created on the first write, so DB_BATCH_SIZE / DB_FLUSH_INTERVAL_MS are read after .env is loaded
+let batchWriter = null;
+
+function getBatchWriter() {
+  if (batchWriter === null) {
+    batchWriter = new PacketBatchWriter(packetBatchOptionsFromEnv(), (sql, params) => getPool().query(sql, params));
+  }
+  return batchWriter;
+}
 
-/**
- * Write a packet to the database (fire-and-forget)
- * This function does not block and errors are logged but not thrown
- * @param {object} packetData - The packet data
- * @param {number} packetData.sessionId - The session ID
- * @param {number} packetData.sessionTimeMs - Session time in milliseconds
- * @param {bigint|number} packetData.packetNumber - The packet number
- * @param {string} packetData.serverVersion - The server version (semver)
- * @param {string} packetData.direction - Packet direction ('clientbound' or 'serverbound')
- * @param {object} packetData.packet - The packet JSON object
- * @param {Date} packetData.ts - When the packet was captured (defaults to now)
- * @returns {Promise<void>}
- */
 /**
  * Recursively convert BigInt values to strings for JSON serialization
  * and remove null bytes from strings (PostgreSQL JSONB doesn't support \u0000)
@@ -48,6 +46,20 @@ function serializeBigInts(obj) {
   return obj;
 }
 
+/**
+ * Write a packet to the database (fire-and-forget)
+ * This function does not block and errors are logged but not thrown.
+ * Packets are queued and written in batches (see PacketBatchWriter); flushPackets() writes the queue now.
+ * @param {object} packetData - The packet data
+ * @param {number} packetData.sessionId - The session ID
+ * @param {number} packetData.sessionTimeMs - Session time in milliseconds
+ * @param {bigint|number} packetData.packetNumber - The packet number
+ * @param {string} packetData.serverVersion - The server version (semver)
+ * @param {string} packetData.direction - Packet direction ('clientbound' or 'serverbound')
+ * @param {object} packetData.packet - The packet JSON object
+ * @param {Date} packetData.ts - When the packet was captured (defaults to now)
+ * @returns {Promise<void>}
+ */
 export async function writePacket({
   sessionId,
   sessionTimeMs,
@@ -57,8 +69,6 @@ export async function writePacket({
   packet,
   ts = new Date()
 }) {
-  const pool = getPool();
-  
   // Serialize BigInts in the packet object for JSON
   const serializedPacket = serializeBigInts(packet);
   
@@ -69,15 +79,17 @@ export async function writePacket({
     ? (packetNumber <= Number.MAX_SAFE_INTEGER ? Number(packetNumber) : packetNumber.toString())
     : Number(packetNumber);
   
-  // Fire-and-forget: don't await, just log errors
-  pool.query(
-    `INSERT INTO packets (session_id, ts, session_time_ms, packet_number, server_version, direction, packet)
-     VALUES ($1, $2, $3, $4, $5, $6, $7::jsonb)`,
-    [sessionId, ts, sessionTimeMs, packetNumberValue, serverVersion, direction, serializedPacket]
-  ).catch((error) => {
-    console.error('Error writing packet (fire-and-forget):', error);
-    // Don't rethrow - this is fire-and-forget
-  });
+  getBatchWriter().add([sessionId, ts, sessionTimeMs, packetNumberValue, serverVersion, direction, serializedPacket]);
+}
+
+/**
+ * Write queued packets and wait for all pending packet writes (e.g. before closing the pool)
+ * @returns {Promise<void>}
+ */
+export async function flushPackets() {
+  if (batchWriter !== null) {
+    await batchWriter.drain();
+  }
 }
 
 /**
@@ -1,4 +1,5 @@
 import pkg from 'pg';
+import { SqlitePool } from './sqlite.js';
 const { Pool } = pkg;
 
 let pool = null;
@@ -29,6 +30,20 @@ export function initPool(connectionString, options = {}) {
   return pool;
 }
 
+/**
+ * Initialize a SQLite database file as the pool (DB_BACKEND=sqlite)
+ * @param {string} path - Database file path, created with its schema if missing
+ * @returns {SqlitePool} The database pool instance
+ */
+export function initSqlitePool(path) {
+  if (pool) {
+    return pool;
+  }
+
+  pool = new SqlitePool(path);
+  return pool;
+}
+
 /**
  * Get the current database pool instance
  * @returns {Pool} The database pool instance
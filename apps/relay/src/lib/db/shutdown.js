@@ -1,4 +1,5 @@
 import { closePool } from './pool.js';
+import { flushPackets } from './packets.js';
 
 let shutdownHandlers = [];
 
@@ -26,6 +27,9 @@ export function setupGracefulShutdown() {
       }
     }
     
+    // Write packets still queued for a batch (failures are logged, not thrown)
+    await flushPackets();
+
     // Close database pool
     try {
       await closePool();
@@ -0,0 +1,115 @@
+import { readFileSync, mkdirSync } from 'fs';
+import { dirname } from 'path';
+
+// Shared with lazypacket, which applies the same schema when it opens the file
+const SCHEMA_PATH = new URL('../../../.ddl/sqlite_schema.sql', import.meta.url);
+
+// Columns converted back to the types pg returns
+const TIMESTAMP_COLUMNS = new Set(['started_at', 'ended_at', 'ts', 'created_at']);
+const JSON_COLUMNS = new Set(['packet']);
+
+/**
+ * Format a Date the way the SQLite schema stores timestamps (UTC, 'YYYY-MM-DD HH:MM:SS.SSS')
+ * @param {Date} date - The date
+ * @returns {string} The stored text
+ */
+export function formatTimestamp(date) {
+  return date.toISOString().replace('T', ' ').replace('Z', '');
+}
+
+/**
+ * Parse a stored SQLite timestamp
+ * @param {string} text - Stored text, as written by formatTimestamp or the schema defaults
+ * @returns {Date} The date
+ */
+export function parseTimestamp(text) {
+  return new Date(`${text.replace(' ', 'T')}Z`);
+}
+
+/**
+ * Rewrite a PostgreSQL query for SQLite: $n placeholders become ?n and ::jsonb casts are dropped
+ * @param {string} sql - Query written for pg
+ * @returns {string} Equivalent SQLite query
+ */
+export function toSqliteSql(sql) {
+  return sql.replace(/::jsonb\b/g, '').replace(/\$(\d+)/g, '?$1');
+}
+
+/**
+ * Convert a query parameter to a value SQLite can bind
+ * @param {any} value - Parameter as passed to pg
+ * @returns {any} Bindable value (Dates as timestamp text, objects as JSON text)
+ */
+export function toSqliteParam(value) {
+  if (value === undefined) {
+    return null;
+  }
+  if (value instanceof Date) {
+    return formatTimestamp(value);
+  }
+  if (typeof value === 'boolean') {
+    return value ? 1 : 0;
+  }
+  if (value !== null && typeof value === 'object' && !Buffer.isBuffer(value)) {
+    return JSON.stringify(value);
+  }
+  return value;
+}
+
+function fromSqliteRow(row) {
+  const result = {};
+  for (const [column, value] of Object.entries(row)) {
+    if (value !== null && TIMESTAMP_COLUMNS.has(column)) {
+      result[column] = parseTimestamp(value);
+    } else if (value !== null && JSON_COLUMNS.has(column)) {
+      result[column] = JSON.parse(value);
+    } else {
+      result[column] = value;
+    }
+  }
+  return result;
+}
+
+/**
+ * A single SQLite file behind the subset of the pg Pool interface the db module uses (query, end),
+ * so sessions, packets and tags work unchanged with DB_BACKEND=sqlite
+ * Uses the built-in node:sqlite module
+ */
+export class SqlitePool {
+  /**
+   * Open (or create) the database file and apply the schema
+   * @param {string} path - Database file path
+   */
+  constructor(path) {
+    const sqlite = process.getBuiltinModule?.('node:sqlite');
+    if (!sqlite) {
+      throw new Error(`DB_BACKEND=sqlite requires a Node.js version with the built-in node:sqlite module (running ${process.version})`);
+    }
+
+    mkdirSync(dirname(path), { recursive: true });
+    this.path = path;
+    this.db = new sqlite.DatabaseSync(path);
+    // WAL lets lazypacket read while the relay is writing; the busy timeout covers writer contention
+    this.db.exec('PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL; PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;');
+    this.db.exec(readFileSync(SCHEMA_PATH, 'utf8'));
+  }
+
+  /**
+   * Run a query written for pg
+   * @param {string} sql - Query with $n placeholders
+   * @param {any[]} params - Query parameters
+   * @returns {Promise<{rows: object[]}>} Result rows, like pg
+   */
+  async query(sql, params = []) {
+    const rows = this.db.prepare(toSqliteSql(sql)).all(...params.map(toSqliteParam));
+    return { rows: rows.map(fromSqliteRow) };
+  }
+
+  /**
+   * Close the database
+   * @returns {Promise<void>}
+   */
+  async end() {
+    this.db.close();
+  }
+}
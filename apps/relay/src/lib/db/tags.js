@@ -0,0 +1,47 @@
+import { getPool } from './pool.js';
+
+/**
+ * Add a tag to a session (no-op if the session already has it)
+ * @param {number} sessionId - The session ID
+ * @param {string} tag - The tag
+ * @returns {Promise<void>}
+ */
+export async function addSessionTag(sessionId, tag) {
+  const pool = getPool();
+
+  try {
+    await pool.query(
+      'INSERT INTO tags (tag) VALUES ($1) ON CONFLICT (tag) DO NOTHING',
+      [tag]
+    );
+    await pool.query(
+      `INSERT INTO tag_maps (tag, session_id)
+       SELECT $1, $2
+       WHERE NOT EXISTS (SELECT 1 FROM tag_maps WHERE tag = $1 AND session_id = $2)`,
+      [tag, sessionId]
+    );
+  } catch (error) {
+    console.error(`Error tagging session ${sessionId} with "${tag}":`, error);
+    throw error;
+  }
+}
+
+/**
+ * Get the tags of a session
+ * @param {number} sessionId - The session ID
+ * @returns {Promise<string[]>} Tags, sorted alphabetically
+ */
+export async function getSessionTags(sessionId) {
+  const pool = getPool();
+
+  try {
+    const result = await pool.query(
+      'SELECT tag FROM tag_maps WHERE session_id = $1 ORDER BY tag',
+      [sessionId]
+    );
+    return result.rows.map(row => row.tag);
+  } catch (error) {
+    console.error(`Error getting tags for session ${sessionId}:`, error);
+    throw error;
+  }
+}
@@ -0,0 +1,38 @@
+import { describe, it, expect } from 'vitest';
+import { PacketPreviewLogger, hexPreview, previewOptionsFromEnv } from '../index.js';
+
+describe('Packet Preview Logging', () => {
+  it('should log nothing when disabled', () => {
+    const lines = [];
+    const logger = new PacketPreviewLogger({ log: line => lines.push(line) });
+    logger.record(1, 'clientbound', 'text', { fullBuffer: Buffer.from([1, 2]) });
+
+    expect(logger.enabled).toBe(false);
+    expect(lines).toEqual([]);
+  });
+
+  it('should log a deterministic fraction of packets', () => {
+    const lines = [];
+    const logger = new PacketPreviewLogger({ sampleRate: 0.25, log: line => lines.push(line) });
+    for (let i = 0; i < 100; i++) {
+      logger.record(1, 'serverbound', 'player_auth_input', { fullBuffer: Buffer.alloc(40) });
+    }
+
+    expect(lines).toHaveLength(25);
+    expect(lines[1]).toContain('(+3 unsampled)');
+  });
+
+  it('should include direction, size, name and a hex preview', () => {
+    const lines = [];
+    const logger = new PacketPreviewLogger({ sampleRate: 1, previewBytes: 3, log: line => lines.push(line) });
+    logger.record(7, 'clientbound', 'text', { fullBuffer: Buffer.from([0x09, 0x00, 0xff, 0x10]) });
+
+    expect(lines[0]).toMatch(/session 7 clientbound\s+4 B\s+text\s+09 00 ff …$/);
+  });
+
+  it('should format previews and read options from the environment', () => {
+    expect(hexPreview(Buffer.from([0xab, 0xcd]), 8)).toBe('ab cd');
+    expect(hexPreview(undefined, 8)).toBe('-');
+    expect(previewOptionsFromEnv({ PACKET_DEBUG_SAMPLE_RATE: '0.01' })).toEqual({ sampleRate: 0.01, previewBytes: 16 });
+  });
+});
@@ -0,0 +1 @@
+export { PacketPreviewLogger, hexPreview, formatPreview, previewOptionsFromEnv } from './packetPreview.js';
@@ -0,0 +1,94 @@
+/**
+ * Sampled per-packet debug lines (direction, size, name, hex preview) for live troubleshooting
+ * Disabled unless a sample rate is set; when enabled, only sampled packets are formatted, so the
+ * cost of unsampled packets is a counter update even at full movement-packet rates
+ */
+export class PacketPreviewLogger {
+  /**
+   * @param {object} options
+   * @param {number} options.sampleRate - Fraction of packets to log (0 disables, 1 logs every packet)
+   * @param {number} options.previewBytes - Number of leading payload bytes shown as hex
+   * @param {function(string): void} options.log - Output function
+   */
+  constructor({ sampleRate = 0, previewBytes = 16, log = console.debug } = {}) {
+    this.sampleRate = Math.min(Math.max(Number(sampleRate) || 0, 0), 1);
+    this.previewBytes = previewBytes;
+    this.log = log;
+    // Deterministic sampling: log whenever the accumulated rate crosses 1 (no Math.random per packet)
+    this.accumulator = 0;
+    this.skipped = 0;
+  }
+
+  get enabled() {
+    return this.sampleRate > 0;
+  }
+
+  /**
+   * Consider one packet for logging
+   * @param {number} sessionId - The session ID
+   * @param {string} direction - Packet direction ('clientbound' or 'serverbound')
+   * @param {string} name - Decoded packet name
+   * @param {object} des - Deserializer output passed alongside relay packet events
+   */
+  record(sessionId, direction, name, des) {
+    if (!this.enabled) {
+      return;
+    }
+
+    this.accumulator += this.sampleRate;
+    if (this.accumulator < 1) {
+      this.skipped++;
+      return;
+    }
+    this.accumulator -= 1;
+
+    const buffer = des?.fullBuffer;
+    this.log(formatPreview({
+      sessionId,
+      direction,
+      name,
+      size: buffer?.length ?? des?.metadata?.size ?? 0,
+      preview: hexPreview(buffer, this.previewBytes),
+      skipped: this.skipped
+    }));
+    this.skipped = 0;
+  }
+}
+
+/**
+ * Hex preview of the first bytes of a buffer
+ * @param {Buffer|undefined} buffer - Packet bytes
+ * @param {number} maxBytes - Maximum number of bytes to show
+ * @returns {string} Space-separated hex bytes, with '…' if truncated
+ */
+export function hexPreview(buffer, maxBytes) {
+  if (!buffer || buffer.length === 0) {
+    return '-';
+  }
+  const shown = buffer.subarray(0, maxBytes).toString('hex').replace(/(..)(?!$)/g, '$1 ');
+  return buffer.length > maxBytes ? `${shown} …` : shown;
+}
+
+/**
+ * Format one debug line
+ * @param {object} line
+ * @returns {string}
+ */
+export function formatPreview({ sessionId, direction, name, size, preview, skipped }) {
+  const sampled = skipped > 0 ? ` (+${skipped} unsampled)` : '';
+  return `[packet] session ${sessionId} ${direction.padEnd(11)} ${String(size).padStart(7)} B  ${name.padEnd(28)} ${preview}${sampled}`;
+}
+
+/**
+ * Packet preview options from environment variables
+ * PACKET_DEBUG_SAMPLE_RATE - fraction of packets to log (unset or 0 disables)
+ * PACKET_DEBUG_PREVIEW_BYTES - leading payload bytes to show (default 16)
+ * @param {object} env - Environment (defaults to process.env)
+ * @returns {{sampleRate: number, previewBytes: number}}
+ */
+export function previewOptionsFromEnv(env = process.env) {
+  return {
+    sampleRate: Number(env.PACKET_DEBUG_SAMPLE_RATE) || 0,
+    previewBytes: Number(env.PACKET_DEBUG_PREVIEW_BYTES) || 16
+  };
+}
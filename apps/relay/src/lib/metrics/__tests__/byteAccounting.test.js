@@ -0,0 +1,34 @@
+import { describe, it, expect } from 'vitest';
+import { ByteAccounting, packetSize, renderPrometheus } from '../index.js';
+
+describe('Byte Accounting', () => {
+  it('should accumulate packets and bytes per direction and packet name', () => {
+    const accounting = new ByteAccounting();
+    accounting.record('clientbound', 'level_chunk', 4000);
+    accounting.record('clientbound', 'level_chunk', 6000);
+    accounting.record('serverbound', 'player_auth_input', 100);
+
+    expect(accounting.topByBytes(1)).toEqual([
+      { direction: 'clientbound', name: 'level_chunk', packets: 2, bytes: 10000 }
+    ]);
+    expect(accounting.totalsByDirection()).toEqual({
+      clientbound: { packets: 2, bytes: 10000 },
+      serverbound: { packets: 1, bytes: 100 }
+    });
+  });
+
+  it('should read packet size from deserializer output', () => {
+    expect(packetSize({ fullBuffer: Buffer.alloc(12) })).toBe(12);
+    expect(packetSize({ metadata: { size: 7 } })).toBe(7);
+    expect(packetSize(undefined)).toBe(0);
+  });
+
+  it('should render Prometheus counters', () => {
+    const accounting = new ByteAccounting();
+    accounting.record('clientbound', 'text', 42);
+
+    const body = renderPrometheus(accounting);
+    expect(body).toContain('bedrockrelay_packets_total{direction="clientbound",packet="text"} 1');
+    expect(body).toContain('bedrockrelay_packet_bytes_total{direction="clientbound",packet="text"} 42');
+  });
+});
@@ -0,0 +1,34 @@
+import { describe, it, expect } from 'vitest';
+import { renderDatabaseHealth } from '../index.js';
+
+describe('Database health metrics', () => {
+  it('should render gauges for a health snapshot', () => {
+    const body = renderDatabaseHealth({
+      up: true,
+      health: {
+        databaseBytes: 8192,
+        tables: [
+          { name: 'sessions', rows: 2, bytes: 4096 },
+          { name: 'packets', rows: 150, bytes: 4096 }
+        ],
+        oldestSession: new Date('2024-01-01T00:00:00Z'),
+        newestSession: null
+      }
+    });
+
+    expect(body).toContain('bedrockrelay_db_up 1');
+    expect(body).toContain('bedrockrelay_db_size_bytes 8192');
+    expect(body).toContain('bedrockrelay_db_table_rows{table="packets"} 150');
+    expect(body).toContain('bedrockrelay_db_table_bytes{table="sessions"} 4096');
+    expect(body).toContain('bedrockrelay_db_oldest_session_timestamp_seconds 1704067200');
+    expect(body).not.toContain('bedrockrelay_db_newest_session_timestamp_seconds');
+  });
+
+  it('should report the database as down before the first successful check', () => {
+    expect(renderDatabaseHealth({ up: false, health: null })).toBe(
+      '# HELP bedrockrelay_db_up Whether the last database health check succeeded\n' +
+      '# TYPE bedrockrelay_db_up gauge\n' +
+      'bedrockrelay_db_up 0\n'
+    );
+  });
+});
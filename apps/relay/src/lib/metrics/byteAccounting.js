@@ -0,0 +1,90 @@
+/**
+ * Per-direction and per-packet-type byte accounting
+ * Keeps running packet and byte totals keyed by direction and packet name
+ */
+export class ByteAccounting {
+  constructor() {
+    // Map<`${direction}:${name}`, { direction, name, packets, bytes }>
+    this.counters = new Map();
+  }
+
+  /**
+   * Record one packet
+   * @param {string} direction - Packet direction ('clientbound' or 'serverbound')
+   * @param {string} name - Packet name
+   * @param {number} bytes - Packet size in bytes
+   */
+  record(direction, name, bytes) {
+    const key = `${direction}:${name}`;
+    let counter = this.counters.get(key);
+    if (!counter) {
+      counter = { direction, name, packets: 0, bytes: 0 };
+      this.counters.set(key, counter);
+    }
+    counter.packets += 1;
+    counter.bytes += bytes;
+  }
+
+  /**
+   * Snapshot of all counters
+   * @returns {Array<{direction: string, name: string, packets: number, bytes: number}>}
+   */
+  snapshot() {
+    return Array.from(this.counters.values(), counter => ({ ...counter }));
+  }
+
+  /**
+   * Total packets and bytes per direction
+   * @returns {Object<string, {packets: number, bytes: number}>}
+   */
+  totalsByDirection() {
+    const totals = {};
+    for (const { direction, packets, bytes } of this.counters.values()) {
+      totals[direction] ??= { packets: 0, bytes: 0 };
+      totals[direction].packets += packets;
+      totals[direction].bytes += bytes;
+    }
+    return totals;
+  }
+
+  /**
+   * Packet types using the most bandwidth
+   * @param {number} limit - Maximum number of entries to return
+   * @returns {Array<{direction: string, name: string, packets: number, bytes: number}>}
+   */
+  topByBytes(limit = 5) {
+    return this.snapshot()
+      .sort((a, b) => b.bytes - a.bytes)
+      .slice(0, limit);
+  }
+}
+
+/**
+ * Size of a packet on the wire as reported by the bedrock-protocol deserializer
+ * @param {object} des - Deserializer output passed alongside relay packet events
+ * @returns {number} Packet size in bytes (0 if unknown)
+ */
+export function packetSize(des) {
+  if (des?.fullBuffer) {
+    return des.fullBuffer.length;
+  }
+  if (des?.metadata?.size !== undefined) {
+    return des.metadata.size;
+  }
+  return 0;
+}
+
+/**
+ * Format a byte count for log output
+ * @param {number} bytes
+ * @returns {string}
+ */
+export function formatBytes(bytes) {
+  if (bytes < 1024) {
+    return `${bytes} B`;
+  }
+  if (bytes < 1024 * 1024) {
+    return `${(bytes / 1024).toFixed(1)} KiB`;
+  }
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MiB`;
+}
@@ -0,0 +1,2 @@
+export { ByteAccounting, packetSize, formatBytes } from './byteAccounting.js';
+export { renderPrometheus, renderDatabaseHealth, startMetricsServer } from './prometheus.js';
@@ -0,0 +1,119 @@
+import http from 'http';
+
+/**
+ * Escape a Prometheus label value
+ * @param {string} value
+ * @returns {string}
+ */
+function escapeLabel(value) {
+  return String(value).replace(/\\/g, '\\\\').replace(/"/g, '\\"').replace(/\n/g, '\\n');
+}
+
+/**
+ * Render byte accounting counters in the Prometheus text exposition format
+ * @param {import('./byteAccounting.js').ByteAccounting} accounting
+ * @returns {string}
+ */
+export function renderPrometheus(accounting) {
+  const counters = accounting.snapshot();
+  const lines = [];
+
+  lines.push('# HELP bedrockrelay_packets_total Packets relayed, by direction and packet name');
+  lines.push('# TYPE bedrockrelay_packets_total counter');
+  for (const { direction, name, packets } of counters) {
+    lines.push(`bedrockrelay_packets_total{direction="${escapeLabel(direction)}",packet="${escapeLabel(name)}"} ${packets}`);
+  }
+
+  lines.push('# HELP bedrockrelay_packet_bytes_total Bytes relayed, by direction and packet name');
+  lines.push('# TYPE bedrockrelay_packet_bytes_total counter');
+  for (const { direction, name, bytes } of counters) {
+    lines.push(`bedrockrelay_packet_bytes_total{direction="${escapeLabel(direction)}",packet="${escapeLabel(name)}"} ${bytes}`);
+  }
+
+  return lines.join('\n') + '\n';
+}
+
+/**
+ * Render a database health snapshot as Prometheus gauges
+ * @param {{up: boolean, health: object|null}} snapshot - From startHealthMonitor's current()
+ * @returns {string}
+ */
+export function renderDatabaseHealth({ up, health }) {
+  const lines = [];
+
+  lines.push('# HELP bedrockrelay_db_up Whether the last database health check succeeded');
+  lines.push('# TYPE bedrockrelay_db_up gauge');
+  lines.push(`bedrockrelay_db_up ${up ? 1 : 0}`);
+  if (!health) {
+    return lines.join('\n') + '\n';
+  }
+
+  lines.push('# HELP bedrockrelay_db_size_bytes Size of the capture database');
+  lines.push('# TYPE bedrockrelay_db_size_bytes gauge');
+  lines.push(`bedrockrelay_db_size_bytes ${health.databaseBytes}`);
+
+  lines.push('# HELP bedrockrelay_db_table_rows Rows per table');
+  lines.push('# TYPE bedrockrelay_db_table_rows gauge');
+  for (const { name, rows } of health.tables) {
+    lines.push(`bedrockrelay_db_table_rows{table="${escapeLabel(name)}"} ${rows}`);
+  }
+
+  const sized = health.tables.filter(table => table.bytes !== null);
+  if (sized.length > 0) {
+    lines.push('# HELP bedrockrelay_db_table_bytes Size of each table including its indexes');
+    lines.push('# TYPE bedrockrelay_db_table_bytes gauge');
+    for (const { name, bytes } of sized) {
+      lines.push(`bedrockrelay_db_table_bytes{table="${escapeLabel(name)}"} ${bytes}`);
+    }
+  }
+
+  for (const [key, metric, help] of [
+    ['oldestSession', 'bedrockrelay_db_oldest_session_timestamp_seconds', 'Start time of the oldest stored session'],
+    ['newestSession', 'bedrockrelay_db_newest_session_timestamp_seconds', 'Start time of the newest stored session']
+  ]) {
+    if (health[key]) {
+      lines.push(`# HELP ${metric} ${help}`);
+      lines.push(`# TYPE ${metric} gauge`);
+      lines.push(`${metric} ${Math.floor(health[key].getTime() / 1000)}`);
+    }
+  }
+
+  return lines.join('\n') + '\n';
+}
+
+/**
+ * Start an HTTP server exposing /metrics, plus optional POST actions (e.g. /dump)
+ * @param {number} port - Port to listen on
+ * @param {() => string} render - Returns the metrics body
+ * @param {Object<string, () => string>} actions - POST handlers by path, each returning a plain-text response
+ * @returns {http.Server}
+ */
+export function startMetricsServer(port, render, actions = {}) {
+  const server = http.createServer((req, res) => {
+    const action = req.method === 'POST' && Object.hasOwn(actions, req.url) ? actions[req.url] : null;
+    if (action) {
+      res.writeHead(200, { 'Content-Type': 'text/plain' });
+      res.end(action() + '\n');
+      return;
+    }
+
+    if (req.url !== '/metrics') {
+      res.writeHead(404);
+      res.end();
+      return;
+    }
+
+    res.writeHead(200, { 'Content-Type': 'text/plain; version=0.0.4' });
+    res.end(render());
+  });
+
+  server.on('error', (error) => {
+    console.error('Metrics server error:', error);
+  });
+
+  server.listen(port, () => {
+    console.log(`Metrics available at http://localhost:${port}/metrics`);
+  });
+
+  return server;
+}
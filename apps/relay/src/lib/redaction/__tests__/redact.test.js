@@ -0,0 +1,64 @@
+import { describe, it, expect } from 'vitest';
+import { PacketRedactor, redactionOptionsFromEnv } from '../index.js';
+
+describe('Packet Redaction', () => {
+  it('should hash login tokens without modifying the original packet', () => {
+    const redactor = new PacketRedactor({ categories: ['tokens'] });
+    const packet = { name: 'login', params: { protocol_version: 800, tokens: { identity: '{"chain":[]}', client: 'eyJ...' } } };
+
+    const redacted = redactor.redact(packet);
+    expect(redacted.params.protocol_version).toBe(800);
+    expect(redacted.params.tokens).toMatch(/^sha256:[0-9a-f]{16}$/);
+    expect(packet.params.tokens.client).toBe('eyJ...');
+  });
+
+  it('should only redact token keys in login packets', () => {
+    const redactor = new PacketRedactor({ categories: ['tokens'] });
+    const packet = { name: 'text', params: { client: 'kept' } };
+
+    expect(redactor.redact(packet).params.client).toBe('kept');
+  });
+
+  it('should redact XUIDs and skins in nested records', () => {
+    const redactor = new PacketRedactor({ categories: ['xuid', 'skin'], mode: 'strip' });
+    const packet = {
+      name: 'player_list',
+      params: { records: { records_count: 1, records: [{ username: 'Steve', xbox_user_id: '2535...', skin: { skin_data: {} } }] } }
+    };
+
+    const record = redactor.redact(packet).params.records.records[0];
+    expect(record).toEqual({ username: 'Steve', xbox_user_id: '[redacted]', skin: '[redacted]' });
+  });
+
+  it('should redact IP addresses in keys and string values', () => {
+    const redactor = new PacketRedactor({ categories: ['ip'], mode: 'strip' });
+    const packet = { name: 'text', params: { message: 'join 10.0.0.5:19132 now', server_address: 'play.example.com' } };
+
+    const { params } = redactor.redact(packet);
+    expect(params.message).toBe('join [redacted] now');
+    expect(params.server_address).toBe('[redacted]');
+  });
+
+  it('should hash consistently for the same salt', () => {
+    const a = new PacketRedactor({ categories: ['xuid'], salt: 's1' });
+    const b = new PacketRedactor({ categories: ['xuid'], salt: 's2' });
+    const packet = { name: 'add_player', params: { xuid: 42n } };
+
+    expect(a.redact(packet).params.xuid).toBe(a.redact(packet).params.xuid);
+    expect(a.redact(packet).params.xuid).not.toBe(b.redact(packet).params.xuid);
+  });
+
+  it('should keep buffers by reference and pass packets through when disabled', () => {
+    const buffer = Buffer.alloc(4);
+    const packet = { name: 'level_chunk', params: { payload: buffer } };
+
+    expect(new PacketRedactor({ categories: ['ip'] }).redact(packet).params.payload).toBe(buffer);
+    expect(new PacketRedactor().redact(packet)).toBe(packet);
+  });
+
+  it('should read options from the environment', () => {
+    expect(redactionOptionsFromEnv({})).toEqual({ categories: [], mode: 'hash', salt: '' });
+    expect(redactionOptionsFromEnv({ REDACT: 'all', REDACT_MODE: 'strip' }).categories).toEqual(['tokens', 'xuid', 'skin', 'ip']);
+    expect(() => new PacketRedactor({ categories: ['passwords'] })).toThrow();
+  });
+});
@@ -0,0 +1 @@
+export { REDACTION_CATEGORIES, PacketRedactor, redactionOptionsFromEnv } from './redact.js';
@@ -0,0 +1,116 @@
+import { createHash } from 'crypto';
+
+/**
+ * Redaction categories and how each one is recognized in packet params
+ * Keys are matched case-insensitively anywhere in the params tree; the whole value under a matching key is redacted
+ */
+export const REDACTION_CATEGORIES = {
+  tokens: { keys: /^(tokens|identity|client|chain|identity_token|auth_token)$/i, packets: ['login'] },
+  xuid: { keys: /xuid|xbox_user_id/i },
+  skin: { keys: /^skin(_.*)?$/i },
+  ip: { keys: /(^|_)(address|ip)$/i, values: true }
+};
+
+// Dotted-quad IPv4 addresses (with an optional port) inside any string value
+const IPV4_PATTERN = /\b(?:\d{1,3}\.){3}\d{1,3}(?::\d{1,5})?\b/g;
+
+/**
+ * Strips or hashes sensitive values (login tokens, XUIDs, skins, IP addresses) from packets before they are persisted
+ * Packets are never modified in place: the relay forwards the originals, so redacted copies are returned
+ */
+export class PacketRedactor {
+  /**
+   * @param {object} options
+   * @param {string[]} options.categories - Categories to redact (see REDACTION_CATEGORIES)
+   * @param {'hash'|'strip'} options.mode - 'hash' replaces values with a salted SHA-256 prefix (stable within a salt), 'strip' with '[redacted]'
+   * @param {string} options.salt - Salt for hashed values
+   */
+  constructor({ categories = [], mode = 'hash', salt = '' } = {}) {
+    for (const category of categories) {
+      if (!REDACTION_CATEGORIES[category]) {
+        throw new Error(`Unknown redaction category "${category}" (expected ${Object.keys(REDACTION_CATEGORIES).join(', ')} or all)`);
+      }
+    }
+    if (mode !== 'hash' && mode !== 'strip') {
+      throw new Error(`Invalid redaction mode "${mode}" (expected hash or strip)`);
+    }
+    this.categories = categories.map(name => ({ name, ...REDACTION_CATEGORIES[name] }));
+    this.mode = mode;
+    this.salt = salt;
+    this.redactValues = this.categories.some(category => category.values);
+  }
+
+  get enabled() {
+    return this.categories.length > 0;
+  }
+
+  /**
+   * Redacted copy of a packet (the packet itself when redaction is disabled)
+   * @param {object} packet - Packet ({ name, params })
+   * @returns {object}
+   */
+  redact(packet) {
+    if (!this.enabled || !packet) {
+      return packet;
+    }
+    return { ...packet, params: this.walk(packet.params, packet.name) };
+  }
+
+  walk(value, packetName) {
+    if (typeof value === 'string') {
+      return this.redactValues ? value.replace(IPV4_PATTERN, match => this.replace(match)) : value;
+    }
+    if (Array.isArray(value)) {
+      return value.map(item => this.walk(item, packetName));
+    }
+    // Plain objects only; Buffers and typed arrays (chunk data etc.) are kept by reference
+    if (value === null || typeof value !== 'object' || value.constructor !== Object) {
+      return value;
+    }
+
+    const result = {};
+    for (const [key, child] of Object.entries(value)) {
+      result[key] = this.matchesKey(key, packetName) ? this.replace(child) : this.walk(child, packetName);
+    }
+    return result;
+  }
+
+  matchesKey(key, packetName) {
+    return this.categories.some(category =>
+      category.keys.test(key) && (!category.packets || category.packets.includes(packetName))
+    );
+  }
+
+  replace(value) {
+    if (value === null || value === undefined) {
+      return value;
+    }
+    if (this.mode === 'strip') {
+      return '[redacted]';
+    }
+    const text = typeof value === 'string'
+      ? value
+      : JSON.stringify(value, (_, v) => (typeof v === 'bigint' ? v.toString() : v));
+    return 'sha256:' + createHash('sha256').update(this.salt).update(text).digest('hex').slice(0, 16);
+  }
+}
+
+/**
+ * Redaction options from environment variables
+ * REDACT - comma-separated categories (tokens, xuid, skin, ip) or 'all'; unset disables redaction
+ * REDACT_MODE - 'hash' (default) or 'strip'
+ * REDACT_SALT - salt for hashed values, so hashes can't be matched against other captures
+ * @param {object} env - Environment (defaults to process.env)
+ * @returns {{categories: string[], mode: string, salt: string}}
+ */
+export function redactionOptionsFromEnv(env = process.env) {
+  const requested = (env.REDACT || '')
+    .split(',')
+    .map(part => part.trim().toLowerCase())
+    .filter(Boolean);
+  return {
+    categories: requested.includes('all') ? Object.keys(REDACTION_CATEGORIES) : requested,
+    mode: (env.REDACT_MODE || 'hash').toLowerCase(),
+    salt: env.REDACT_SALT || ''
+  };
+}
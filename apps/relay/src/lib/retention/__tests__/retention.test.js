@@ -0,0 +1,44 @@
+import { describe, it, expect } from 'vitest';
+import { retentionOptionsFromEnv, selectSessionsToPrune } from '../index.js';
+
+const now = new Date('2024-06-30T12:00:00Z');
+const daysAgo = (days) => new Date(now.getTime() - days * 24 * 60 * 60 * 1000);
+const session = (id, ageDays, bytes, extra = {}) => ({
+  id, startedAt: daysAgo(ageDays), endedAt: daysAgo(ageDays), bytes, tagged: false, ...extra
+});
+
+describe('Retention', () => {
+  it('should read options from the environment', () => {
+    expect(retentionOptionsFromEnv({}).enabled).toBe(false);
+
+    const options = retentionOptionsFromEnv({
+      RETENTION_MAX_AGE_DAYS: '30', RETENTION_MAX_TOTAL_GB: '0.5', RETENTION_UNTAGGED_ONLY: 'true'
+    });
+    expect(options).toEqual({
+      enabled: true, maxAgeDays: 30, maxTotalBytes: 512 * 1024 * 1024, untaggedOnly: true, intervalMs: 60 * 60 * 1000
+    });
+    expect(() => retentionOptionsFromEnv({ RETENTION_MAX_AGE_DAYS: 'soon' })).toThrow('Invalid RETENTION_MAX_AGE_DAYS');
+  });
+
+  it('should delete sessions older than the maximum age', () => {
+    const sessions = [session(1, 40, 10), session(2, 5, 10), session(3, 31, 10)];
+    const options = { maxAgeDays: 30, maxTotalBytes: null, untaggedOnly: false };
+
+    expect(selectSessionsToPrune(sessions, options, now)).toEqual([1, 3]);
+  });
+
+  it('should delete the oldest finished sessions until under the size limit', () => {
+    const sessions = [session(1, 3, 50), session(2, 2, 50, { endedAt: null }), session(3, 1, 50), session(4, 0, 50)];
+    const options = { maxAgeDays: null, maxTotalBytes: 100, untaggedOnly: false };
+
+    // Session 2 hasn't ended, so session 3 goes instead
+    expect(selectSessionsToPrune(sessions, options, now)).toEqual([1, 3]);
+  });
+
+  it('should keep tagged and protected sessions', () => {
+    const sessions = [session(1, 40, 10, { tagged: true }), session(2, 40, 10), session(3, 40, 10)];
+    const options = { maxAgeDays: 30, maxTotalBytes: null, untaggedOnly: true };
+
+    expect(selectSessionsToPrune(sessions, options, now, new Set([3]))).toEqual([2]);
+  });
+});
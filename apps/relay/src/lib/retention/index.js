@@ -0,0 +1 @@
+export { retentionOptionsFromEnv, selectSessionsToPrune, getSessionUsage, pruneSessions, startRetention } from './retention.js';
@@ -0,0 +1,161 @@
+import { getPool } from '../db/pool.js';
+import { getDatabaseBackend } from '../db/config.js';
+
+const DAY_MS = 24 * 60 * 60 * 1000;
+const GB = 1024 ** 3;
+
+// Stored size of a packet row's JSON: the (possibly compressed) jsonb value, or the SQLite text
+const PACKET_SIZE_SQL = {
+  postgres: 'pg_column_size(packet)',
+  sqlite: 'length(CAST(packet AS BLOB))'
+};
+
+/**
+ * Retention options from environment variables (shared with `lazypacket prune`)
+ * RETENTION_MAX_AGE_DAYS - delete sessions that started more than this many days ago
+ * RETENTION_MAX_TOTAL_GB - then delete the oldest sessions until packets take at most this much space
+ * RETENTION_UNTAGGED_ONLY - 'true' to never delete sessions that have tags
+ * RETENTION_INTERVAL_MINUTES - how often the relay prunes (default 60)
+ * @param {object} env - Environment (defaults to process.env)
+ * @returns {{enabled: boolean, maxAgeDays: number|null, maxTotalBytes: number|null, untaggedOnly: boolean, intervalMs: number}}
+ */
+export function retentionOptionsFromEnv(env = process.env) {
+  const positive = (name) => {
+    if (env[name] === undefined || env[name] === '') {
+      return null;
+    }
+    const value = Number(env[name]);
+    if (!Number.isFinite(value) || value <= 0) {
+      throw new Error(`Invalid ${name} "${env[name]}" (expected a positive number)`);
+    }
+    return value;
+  };
+
+  const maxAgeDays = positive('RETENTION_MAX_AGE_DAYS');
+  const maxTotalGb = positive('RETENTION_MAX_TOTAL_GB');
+  return {
+    enabled: maxAgeDays !== null || maxTotalGb !== null,
+    maxAgeDays,
+    maxTotalBytes: maxTotalGb === null ? null : Math.floor(maxTotalGb * GB),
+    untaggedOnly: String(env.RETENTION_UNTAGGED_ONLY).toLowerCase() === 'true',
+    intervalMs: (positive('RETENTION_INTERVAL_MINUTES') ?? 60) * 60 * 1000
+  };
+}
+
+/**
+ * Pick the sessions a retention policy deletes
+ * Sessions older than maxAgeDays go first; then, while the remaining sessions exceed maxTotalBytes,
+ * the oldest finished ones. Protected sessions (and tagged ones with untaggedOnly) are never picked,
+ * and sessions that haven't ended are only removed by age.
+ * @param {Array<{id: number, startedAt: Date, endedAt: Date|null, bytes: number, tagged: boolean}>} sessions
+ * @param {{maxAgeDays: number|null, maxTotalBytes: number|null, untaggedOnly: boolean}} options
+ * @param {Date} now - Current time
+ * @param {Set<number>} protectedIds - Sessions to keep regardless (e.g. ones being captured)
+ * @returns {number[]} Session ids to delete, oldest first
+ */
+export function selectSessionsToPrune(sessions, options, now = new Date(), protectedIds = new Set()) {
+  const oldestFirst = [...sessions].sort((a, b) => a.startedAt - b.startedAt);
+  const eligible = oldestFirst.filter(s => !protectedIds.has(s.id) && !(options.untaggedOnly && s.tagged));
+  const selected = new Set();
+
+  if (options.maxAgeDays !== null) {
+    const cutoff = now.getTime() - options.maxAgeDays * DAY_MS;
+    for (const session of eligible) {
+      if (session.startedAt.getTime() < cutoff) {
+        selected.add(session.id);
+      }
+    }
+  }
+
+  if (options.maxTotalBytes !== null) {
+    let total = oldestFirst.filter(s => !selected.has(s.id)).reduce((sum, s) => sum + s.bytes, 0);
+    for (const session of eligible) {
+      if (total <= options.maxTotalBytes) {
+        break;
+      }
+      if (!selected.has(session.id) && session.endedAt !== null) {
+        selected.add(session.id);
+        total -= session.bytes;
+      }
+    }
+  }
+
+  return oldestFirst.filter(s => selected.has(s.id)).map(s => s.id);
+}
+
+/**
+ * Start time, end time, packet bytes and whether it has tags, for every session
+ * @returns {Promise<Array<{id: number, startedAt: Date, endedAt: Date|null, bytes: number, tagged: boolean}>>}
+ */
+export async function getSessionUsage() {
+  const pool = getPool();
+  const result = await pool.query(
+    `SELECT s.id, s.started_at, s.ended_at, COALESCE(p.bytes, 0) AS bytes,
+            EXISTS (SELECT 1 FROM tag_maps t WHERE t.session_id = s.id) AS tagged
+     FROM sessions s
+     LEFT JOIN (
+       SELECT session_id, SUM(${PACKET_SIZE_SQL[getDatabaseBackend()]}) AS bytes FROM packets GROUP BY session_id
+     ) p ON p.session_id = s.id`
+  );
+  return result.rows.map(row => ({
+    id: row.id,
+    startedAt: new Date(row.started_at),
+    endedAt: row.ended_at === null ? null : new Date(row.ended_at),
+    bytes: Number(row.bytes), // pg returns SUM of bigint as a string
+    tagged: Boolean(row.tagged)
+  }));
+}
+
+/**
+ * Delete the sessions a retention policy selects; packets, tags and notes go with them (ON DELETE CASCADE)
+ * @param {{maxAgeDays: number|null, maxTotalBytes: number|null, untaggedOnly: boolean}} options
+ * @param {Set<number>} protectedIds - Sessions to keep regardless
+ * @returns {Promise<{deleted: number[], bytes: number}>} Deleted session ids and their packet bytes
+ */
+export async function pruneSessions(options, protectedIds = new Set()) {
+  const sessions = await getSessionUsage();
+  const ids = selectSessionsToPrune(sessions, options, new Date(), protectedIds);
+  const bytesById = new Map(sessions.map(s => [s.id, s.bytes]));
+  const pool = getPool();
+
+  const deleted = [];
+  let bytes = 0;
+  for (const id of ids) {
+    await pool.query('DELETE FROM sessions WHERE id = $1', [id]);
+    deleted.push(id);
+    bytes += bytesById.get(id);
+  }
+  return { deleted, bytes };
+}
+
+/**
+ * Prune now and then every options.intervalMs in the background
+ * @param {object} options - From retentionOptionsFromEnv
+ * @param {() => Iterable<number>} activeSessionIds - Sessions being captured, never pruned
+ * @param {(result: {deleted: number[], bytes: number}) => void} onPrune - Called after each run that deleted something
+ * @returns {{stop: () => void}} Handle to stop the timer
+ */
+export function startRetention(options, activeSessionIds, onPrune = () => {}) {
+  let running = false;
+  const run = async () => {
+    if (running) {
+      return; // The previous run is still deleting
+    }
+    running = true;
+    try {
+      const result = await pruneSessions(options, new Set(activeSessionIds()));
+      if (result.deleted.length > 0) {
+        onPrune(result);
+      }
+    } catch (error) {
+      console.error('Error pruning sessions:', error);
+    } finally {
+      running = false;
+    }
+  };
+
+  run();
+  const timer = setInterval(run, options.intervalMs);
+  timer.unref();
+  return { stop: () => clearInterval(timer) };
+}
@@ -0,0 +1,45 @@
+import { describe, it, expect } from 'vitest';
+import { parseTagRules, SessionTagger, globMatch } from '../index.js';
+
+describe('Tag Rules', () => {
+  it('should match globs case-insensitively', () => {
+    expect(globMatch('play.*.net:*', 'play.example.net:19132')).toBe(true);
+    expect(globMatch('steve', 'Steve')).toBe(true);
+    expect(globMatch('steve', 'Steve2')).toBe(false);
+    expect(globMatch('*', undefined)).toBe(false);
+  });
+
+  it('should apply connection rules once details are known', () => {
+    const rules = parseTagRules([
+      { tag: 'local', when: { upstream: '127.0.0.1:*' } },
+      { tag: 'steve', when: { player: 'Steve' } }
+    ]);
+    const tagger = new SessionTagger(rules);
+
+    expect(tagger.update({ upstream: '127.0.0.1:19132' })).toEqual(['local']);
+    expect(tagger.update({ player: 'Steve' })).toEqual(['steve']);
+    expect(tagger.update({ player: 'Steve' })).toEqual([]);
+  });
+
+  it('should apply milestone and packet rules at most once per session', () => {
+    const rules = parseTagRules([
+      { tag: 'death', when: { milestone: 'death' } },
+      { tag: 'kicked', when: { milestone: 'kick' } },
+      { tag: 'said-hello', when: { packet: 'text', direction: 'serverbound', params: { message: '*hello*' } } }
+    ]);
+    const tagger = new SessionTagger(rules);
+
+    expect(tagger.packet('clientbound', { name: 'death_info', params: {} })).toEqual(['death']);
+    expect(tagger.packet('clientbound', { name: 'death_info', params: {} })).toEqual([]);
+    expect(tagger.packet('serverbound', { name: 'text', params: { message: 'Hello there' } })).toEqual(['said-hello']);
+    expect(tagger.packet('serverbound', { name: 'disconnect', params: {} })).toEqual([]);
+    expect(tagger.packet('clientbound', { name: 'disconnect', params: {} })).toEqual(['kicked']);
+  });
+
+  it('should reject invalid rules', () => {
+    expect(() => parseTagRules({})).toThrow();
+    expect(() => parseTagRules([{ when: {} }])).toThrow(/tag/);
+    expect(() => parseTagRules([{ tag: 'x', when: { milestone: 'nope' } }])).toThrow(/milestone/);
+    expect(() => parseTagRules([{ tag: 'x', when: { server: 'a' } }])).toThrow(/unknown condition/);
+  });
+});
@@ -0,0 +1 @@
+export { MILESTONES, globMatch, parseTagRules, loadTagRules, SessionTagger } from './rules.js';
@@ -0,0 +1,148 @@
+import { readFileSync } from 'fs';
+
+/**
+ * Built-in milestone names usable as `{ "milestone": "death" }` in a rule
+ * Each maps to the packet conditions that signal it
+ */
+export const MILESTONES = {
+  death: { packet: 'death_info', direction: 'clientbound' },
+  kick: { packet: 'disconnect', direction: 'clientbound' },
+  transfer: { packet: 'transfer', direction: 'clientbound' }
+};
+
+const CONNECTION_KEYS = ['upstream', 'client', 'player'];
+const PACKET_KEYS = ['packet', 'direction', 'params', 'milestone'];
+
+/**
+ * Case-insensitive glob match where `*` matches any run of characters
+ * @param {string} pattern - Glob pattern
+ * @param {any} value - Value to match (converted to a string)
+ * @returns {boolean}
+ */
+export function globMatch(pattern, value) {
+  if (value === undefined || value === null) {
+    return false;
+  }
+  const escaped = String(pattern)
+    .split('*')
+    .map(part => part.replace(/[.+?^${}()|[\]\\]/g, '\\$&'))
+    .join('.*');
+  return new RegExp(`^${escaped}$`, 'is').test(String(value));
+}
+
+/**
+ * Validate and normalize a list of tag rules
+ * Rule shape: { tag, when: { upstream?, client?, player?, packet?, direction?, params?, milestone? } }
+ * @param {any} rules - Parsed rules (array)
+ * @returns {Array<{tag: string, when: object, onPacket: boolean}>}
+ */
+export function parseTagRules(rules) {
+  if (!Array.isArray(rules)) {
+    throw new Error('Tag rules must be a JSON array');
+  }
+
+  return rules.map((rule, index) => {
+    if (!rule || typeof rule.tag !== 'string' || rule.tag.trim() === '') {
+      throw new Error(`Tag rule ${index} is missing a "tag" string`);
+    }
+    let when = { ...(rule.when || {}) };
+    for (const key of Object.keys(when)) {
+      if (!CONNECTION_KEYS.includes(key) && !PACKET_KEYS.includes(key)) {
+        throw new Error(`Tag rule ${index} (${rule.tag}) has unknown condition "${key}"`);
+      }
+    }
+    if (when.milestone !== undefined) {
+      const milestone = MILESTONES[when.milestone];
+      if (!milestone) {
+        throw new Error(`Tag rule ${index} (${rule.tag}) has unknown milestone "${when.milestone}"`);
+      }
+      // Explicit conditions in the rule narrow or override the milestone's
+      const { milestone: _, ...conditions } = when;
+      when = { ...milestone, ...conditions };
+    }
+
+    return {
+      tag: rule.tag.trim(),
+      when,
+      onPacket: PACKET_KEYS.some(key => when[key] !== undefined)
+    };
+  });
+}
+
+/**
+ * Load tag rules from a JSON file
+ * @param {string} path - Path to the rules file
+ * @returns {Array<{tag: string, when: object, onPacket: boolean}>}
+ */
+export function loadTagRules(path) {
+  const contents = readFileSync(path, 'utf8');
+  return parseTagRules(JSON.parse(contents));
+}
+
+/**
+ * Applies tag rules to one session
+ * Connection rules (upstream, client, player) are checked as session details become known;
+ * packet rules are checked on every packet. Each tag is reported at most once per session.
+ */
+export class SessionTagger {
+  /**
+   * @param {Array<{tag: string, when: object, onPacket: boolean}>} rules - Parsed rules
+   * @param {object} context - Known session details ({ upstream, client, player })
+   */
+  constructor(rules, context = {}) {
+    this.rules = rules;
+    this.context = { ...context };
+    this.applied = new Set();
+  }
+
+  /**
+   * Add session details (e.g. the player name once the client logs in)
+   * @param {object} details - Details to merge into the context
+   * @returns {string[]} Newly matched tags
+   */
+  update(details) {
+    Object.assign(this.context, details);
+    return this.collect(rule => !rule.onPacket && this.matchesConnection(rule.when));
+  }
+
+  /**
+   * Check packet rules against one packet
+   * @param {string} direction - Packet direction ('clientbound' or 'serverbound')
+   * @param {object} packet - Packet ({ name, params })
+   * @returns {string[]} Newly matched tags
+   */
+  packet(direction, packet) {
+    return this.collect(rule =>
+      rule.onPacket && this.matchesConnection(rule.when) && matchesPacket(rule.when, direction, packet)
+    );
+  }
+
+  matchesConnection(when) {
+    return CONNECTION_KEYS.every(key => when[key] === undefined || globMatch(when[key], this.context[key]));
+  }
+
+  collect(predicate) {
+    const tags = [];
+    for (const rule of this.rules) {
+      if (!this.applied.has(rule.tag) && predicate(rule)) {
+        this.applied.add(rule.tag);
+        tags.push(rule.tag);
+      }
+    }
+    return tags;
+  }
+}
+
+function matchesPacket(when, direction, packet) {
+  if (when.direction !== undefined && when.direction !== direction) {
+    return false;
+  }
+  if (when.packet !== undefined && !globMatch(when.packet, packet?.name)) {
+    return false;
+  }
+  if (when.params !== undefined) {
+    const params = packet?.params || {};
+    return Object.entries(when.params).every(([key, pattern]) => globMatch(pattern, params[key]));
+  }
+  return true;
+}